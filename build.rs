@@ -0,0 +1,45 @@
+// build.rs
+//! Stamps the server with the commit it was built from and when it was built, for
+//! `GET /version`: `XTRADE_GIT_HASH` is empty outside a git checkout, and
+//! `XTRADE_BUILT_AT` is in seconds since the epoch, `SOURCE_DATE_EPOCH` if set, for
+//! reproducible builds.
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    let git_hash = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|hash| hash.trim().to_string())
+        .unwrap_or_default();
+    println!("cargo:rustc-env=XTRADE_GIT_HASH={}", git_hash);
+
+    let built_at = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            (SystemTime::now().duration_since(UNIX_EPOCH))
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default()
+        });
+    println!("cargo:rustc-env=XTRADE_BUILT_AT={}", built_at);
+
+    // Stamp again when the sources or the checked out commit change; paths that do not
+    // exist would stamp on every build
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    for path in [
+        "build.rs",
+        "src",
+        ".git/HEAD",
+        ".git/refs",
+        ".git/packed-refs",
+    ] {
+        if Path::new(path).exists() {
+            println!("cargo:rerun-if-changed={}", path);
+        }
+    }
+}
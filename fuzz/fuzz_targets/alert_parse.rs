@@ -0,0 +1,20 @@
+// fuzz/fuzz_targets/alert_parse.rs
+//
+// Feeds raw webhook bodies to `Alert::parse` for every supported source.
+// Parsing and validation must reject malformed input, never panic.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use xtrade::alert::{Alert, SOURCES};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(payload) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    for source in SOURCES.iter().copied().chain(["", "unknown"]) {
+        if let Ok(Alert::TradingView(alert)) = Alert::parse(source, payload) {
+            let _ = alert.validate();
+        }
+    }
+});
@@ -0,0 +1,22 @@
+// fuzz/fuzz_targets/alert_parse_json.rs
+//
+// Round-trips arbitrary input through `serde_json::Value` first, so the
+// parsers see well-formed JSON of the wrong shape (missing fields, wrong
+// types, deep nesting) far more often than with raw bytes.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use xtrade::alert::{Alert, SOURCES};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(value) = serde_json::from_slice::<serde_json::Value>(data) else {
+        return;
+    };
+    let payload = value.to_string();
+
+    for source in SOURCES {
+        if let Ok(Alert::TradingView(alert)) = Alert::parse(source, &payload) {
+            let _ = alert.validate();
+        }
+    }
+});
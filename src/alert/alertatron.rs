@@ -0,0 +1,111 @@
+// src/alert/alertatron.rs
+
+use crate::alert::tradingview::TradingViewAlert;
+
+/// Alert in Alertatron's text format, e.g. `keys(BTCUSDT) { market(side=buy, amount=1); } #bot`.
+/// The API key name is Alertatron's own, so the order goes to the bot whose webhook received
+/// it, with that bot's keys. Only a single `market` order, or a `limit` order at an absolute
+/// `offset=@price`, is understood.
+#[derive(Debug, PartialEq)]
+pub struct AlertatronAlert {
+    pub keys: String,
+    pub symbol: String,
+    pub command: String,
+    pub side: String,
+    pub amount: String,
+    /// Limit price of a `limit` order
+    pub price: Option<String>,
+}
+
+impl AlertatronAlert {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text: String = text
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let (keys, rest) = text
+            .split_once('(')
+            .ok_or("expected keys(SYMBOL) { command(...); }")?;
+        let (symbol, rest) = rest.split_once(')').ok_or("unclosed symbol")?;
+        let body = rest
+            .trim()
+            .strip_prefix('{')
+            .and_then(|rest| rest.trim_end().strip_suffix('}'))
+            .ok_or("expected the commands in { }")?;
+
+        let mut commands = body
+            .split(';')
+            .map(str::trim)
+            .filter(|command| !command.is_empty());
+        let command = commands.next().ok_or("no command given")?;
+        if commands.next().is_some() {
+            return Err("only one command per alert is supported".to_string());
+        }
+        let (name, args) = command
+            .strip_suffix(')')
+            .and_then(|command| command.split_once('('))
+            .ok_or_else(|| format!("malformed command '{}'", command))?;
+        let name = name.trim().to_lowercase();
+        if name != "market" && name != "limit" {
+            return Err(format!(
+                "command '{}' is not supported; use market or limit",
+                name
+            ));
+        }
+
+        let (mut side, mut amount, mut price) = (None, None, None);
+        for arg in args.split(',').map(str::trim).filter(|arg| !arg.is_empty()) {
+            let (key, value) = arg
+                .split_once('=')
+                .ok_or_else(|| format!("malformed argument '{}'", arg))?;
+            let value = value.trim().to_string();
+            match (key.trim(), name.as_str()) {
+                ("side", _) => side = Some(value),
+                ("amount", _) => amount = Some(value),
+                ("offset", "limit") => {
+                    let absolute = value.strip_prefix('@').ok_or_else(|| {
+                        format!(
+                            "only absolute offsets (@price) are supported, not '{}'",
+                            value
+                        )
+                    })?;
+                    price = Some(absolute.trim().to_string());
+                }
+                (key, _) => return Err(format!("argument '{}' of {} is not supported", key, name)),
+            }
+        }
+        if name == "limit" && price.is_none() {
+            return Err("limit orders need an offset=@price".to_string());
+        }
+        Ok(Self {
+            keys: keys.trim().to_string(),
+            symbol: symbol.trim().to_string(),
+            command: name,
+            side: side.ok_or("side is missing")?,
+            amount: amount.ok_or("amount is missing")?,
+            price,
+        })
+    }
+
+    /// The alert as a TradingView alert for `bot_id`.
+    pub fn into_tradingview(self, bot_id: &str) -> TradingViewAlert {
+        TradingViewAlert {
+            bot_id: bot_id.to_string(),
+            ticker: self.symbol,
+            action: self.side,
+            order_size: self.amount,
+            position_size: String::new(),
+            schema: "alertatron".to_string(),
+            timestamp: String::new(),
+            order_type: Some(self.command),
+            price: self.price,
+            stop_price: None,
+            take_profit_price: None,
+            trailing_delta_bps: None,
+            time_in_force: None,
+            alert_price: None,
+            slippage_bps: None,
+        }
+    }
+}
@@ -1,11 +1,19 @@
 // src/alert/mod.rs
 
+pub mod alertatron;
+pub mod threecommas;
 pub mod tradingview;
 // Future modules like telegram can be added here
 
+use crate::alert::alertatron::AlertatronAlert;
+use crate::alert::threecommas::ThreeCommasAlert;
 use crate::alert::tradingview::TradingViewAlert;
+use chrono::{DateTime, Utc};
 // use crate::alert::telegram::TelegramAlert; // Uncomment when added
 
+/// Alert sources understood by [`Alert::parse`].
+pub const SOURCES: [&str; 3] = ["tradingview", "3commas", "alertatron"];
+
 /// Enum representing different alert sources.
 pub enum Alert {
     TradingView(TradingViewAlert),
@@ -27,4 +35,136 @@ impl Alert {
             _ => Err("Unsupported alert source".into()),
         }
     }
+
+    /// Parses `payload` from `source` as an alert for `bot_id`. A TradingView alert names
+    /// its bot itself. 3Commas and Alertatron alerts name a bot or API keys on those
+    /// platforms, so they are taken as alerts for `bot_id` and become TradingView alerts.
+    pub fn parse_for(
+        source: &str,
+        payload: &str,
+        bot_id: &str,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let alert = match source {
+            "3commas" => {
+                serde_json::from_str::<ThreeCommasAlert>(payload)?.into_tradingview(bot_id)?
+            }
+            "alertatron" => AlertatronAlert::parse(payload)?.into_tradingview(bot_id),
+            _ => return Self::parse(source, payload),
+        };
+        Ok(Alert::TradingView(alert))
+    }
+
+    /// ID of the bot the alert is meant for.
+    pub fn bot_id(&self) -> &str {
+        match self {
+            Alert::TradingView(alert) => &alert.bot_id,
+        }
+    }
+
+    /// When the alert fired, if its source says so in a form we understand.
+    pub fn fired_at(&self) -> Option<DateTime<Utc>> {
+        match self {
+            Alert::TradingView(alert) => alert.fired_at(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rejects_malformed_payloads() {
+        let payloads = [
+            "",
+            "{",
+            "null",
+            "[]",
+            r#"{"bot_id": 1}"#,
+            r#"{"bot_id": "a", "ticker": "BTCUSDT"}"#,
+            &"[".repeat(10_000),
+        ];
+
+        for payload in payloads {
+            assert!(Alert::parse("tradingview", payload).is_err(), "{payload}");
+        }
+        assert!(Alert::parse("unknown", "{}").is_err());
+    }
+
+    #[test]
+    fn test_parse_tradingview() {
+        let payload = r#"{
+            "bot_id": "bot",
+            "ticker": "BTCUSDT",
+            "action": "BUY",
+            "order_size": "100%",
+            "position_size": "1",
+            "schema": "2",
+            "timestamp": "2025-01-10T12:00:00Z"
+        }"#;
+
+        let Alert::TradingView(alert) = Alert::parse("tradingview", payload).unwrap();
+        assert!(alert.validate().is_ok());
+    }
+
+    #[test]
+    fn test_parse_third_party_formats() {
+        let three_commas = r#"{
+            "secret": "s3cret",
+            "max_lag": "300",
+            "timestamp": "2025-01-10T12:00:00Z",
+            "trigger_price": "50000",
+            "tv_exchange": "BINANCE",
+            "tv_instrument": "BTCUSDT",
+            "action": "exit_long",
+            "bot_uuid": "0f3c7e2a",
+            "order": {"amount": "1000", "currency_type": "quote", "order_type": "market"}
+        }"#;
+        let Alert::TradingView(alert) = Alert::parse_for("3commas", three_commas, "bot").unwrap();
+        assert_eq!(
+            (alert.bot_id.as_str(), alert.action.as_str()),
+            ("bot", "sell")
+        );
+        assert_eq!(alert.order_size, "0.02");
+        assert!(alert.fired_at().is_some());
+        let entry = three_commas.replace("exit_long", "enter_sideways");
+        assert!(Alert::parse_for("3commas", &entry, "bot").is_err());
+
+        let text = "binance-keys(ETHUSDT) {\n  limit(side=buy, amount=0.5, offset=@2000);\n}\n#bot";
+        let Alert::TradingView(alert) = Alert::parse_for("alertatron", text, "bot").unwrap();
+        assert_eq!(alert.ticker, "ETHUSDT");
+        assert_eq!(alert.order_type.as_deref(), Some("limit"));
+        assert_eq!(alert.price.as_deref(), Some("2000"));
+        assert!(alert.validate().is_ok());
+        for unsupported in [
+            "keys(ETHUSDT) { market(side=buy, amount=1); wait(10s); } #bot",
+            "keys(ETHUSDT) { limit(side=buy, amount=1, offset=1%); } #bot",
+            "keys(ETHUSDT) { stopOrder(side=sell, amount=1, offset=@1900); } #bot",
+            "keys(ETHUSDT) { market(side=buy, amount=1, reduceOnly=true); } #bot",
+            "market(side=buy, amount=1)",
+        ] {
+            assert!(
+                Alert::parse_for("alertatron", unsupported, "bot").is_err(),
+                "{unsupported}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_fired_at_understands_timenow_and_unix_time() {
+        let fired_at = |timestamp: &str| {
+            let payload = format!(
+                r#"{{"bot_id": "bot", "ticker": "BTCUSDT", "action": "buy", "order_size": "1",
+                    "position_size": "1", "schema": "2", "timestamp": "{}"}}"#,
+                timestamp
+            );
+            Alert::parse("tradingview", &payload).unwrap().fired_at()
+        };
+        let expected = DateTime::parse_from_rfc3339("2025-01-10T12:00:00Z").unwrap();
+        assert_eq!(fired_at("2025-01-10T12:00:00Z"), Some(expected.into()));
+        assert_eq!(fired_at("2025-01-10T13:00:00+01:00"), Some(expected.into()));
+        assert_eq!(fired_at("1736510400"), Some(expected.into()));
+        assert_eq!(fired_at("1736510400000"), Some(expected.into()));
+        assert_eq!(fired_at("{{timenow}}"), None);
+    }
 }
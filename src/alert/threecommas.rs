@@ -0,0 +1,112 @@
+// src/alert/threecommas.rs
+
+use crate::alert::tradingview::TradingViewAlert;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+/// Alert of a 3Commas signal bot, as its custom-signal JSON template writes it. Its
+/// `bot_uuid` and `tv_exchange` are ignored: the alert goes to the bot whose webhook
+/// received it.
+#[derive(Debug, Deserialize)]
+pub struct ThreeCommasAlert {
+    /// `enter_long`, `exit_long`, `enter_short` or `exit_short`
+    pub action: String,
+    pub tv_instrument: String,
+    #[serde(default)]
+    pub timestamp: Option<String>,
+    /// Price when the alert fired, e.g. `{{close}}`
+    #[serde(default)]
+    pub trigger_price: Option<String>,
+    pub order: ThreeCommasOrder,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ThreeCommasOrder {
+    pub amount: String,
+    /// `base` (the default) or `quote`; a quote amount is converted at the trigger price
+    #[serde(default)]
+    pub currency_type: Option<String>,
+    /// `market` (the default) or `limit`
+    #[serde(default)]
+    pub order_type: Option<String>,
+    /// Limit price; the trigger price if unset
+    #[serde(default)]
+    pub price: Option<String>,
+}
+
+impl ThreeCommasAlert {
+    /// The alert as a TradingView alert for `bot_id`.
+    pub fn into_tradingview(self, bot_id: &str) -> Result<TradingViewAlert, String> {
+        let action = match self.action.to_lowercase().as_str() {
+            "enter_long" | "exit_short" => "buy",
+            "exit_long" | "enter_short" => "sell",
+            _ => {
+                return Err(format!(
+                    "action must be enter_long, exit_long, enter_short or exit_short, not '{}'",
+                    self.action
+                ))
+            }
+        };
+        let order = self.order;
+        let order_size = match order.currency_type.as_deref().unwrap_or("base") {
+            "base" => order.amount,
+            "quote" => {
+                let amount = number(&order.amount)
+                    .ok_or_else(|| format!("amount must be a number, not '{}'", order.amount))?;
+                let price = self
+                    .trigger_price
+                    .as_deref()
+                    .and_then(number)
+                    .ok_or("a quote amount needs a positive trigger_price to convert it at")?;
+                (amount / price).round_dp(8).normalize().to_string()
+            }
+            other => {
+                return Err(format!(
+                    "currency_type must be base or quote, not '{}'",
+                    other
+                ))
+            }
+        };
+        let order_type = order.order_type.unwrap_or_else(|| "market".to_string());
+        let price = match order_type.to_lowercase().as_str() {
+            "market" => None,
+            "limit" => Some(
+                order
+                    .price
+                    .or_else(|| self.trigger_price.clone())
+                    .ok_or("limit orders need a price or a trigger_price")?,
+            ),
+            _ => {
+                return Err(format!(
+                    "order_type must be market or limit, not '{}'",
+                    order_type
+                ))
+            }
+        };
+        Ok(TradingViewAlert {
+            bot_id: bot_id.to_string(),
+            ticker: self.tv_instrument,
+            action: action.to_string(),
+            order_size,
+            position_size: String::new(),
+            schema: "3commas".to_string(),
+            timestamp: self.timestamp.unwrap_or_default(),
+            order_type: Some(order_type),
+            price,
+            stop_price: None,
+            take_profit_price: None,
+            trailing_delta_bps: None,
+            time_in_force: None,
+            alert_price: self.trigger_price,
+            slippage_bps: None,
+        })
+    }
+}
+
+fn number(value: &str) -> Option<Decimal> {
+    value
+        .trim()
+        .parse::<Decimal>()
+        .ok()
+        .filter(|number| number.is_sign_positive() && !number.is_zero())
+}
@@ -1,5 +1,6 @@
 // src/alert/tradingview.rs
 
+use chrono::{DateTime, Utc};
 use serde::Deserialize;
 
 #[derive(Debug, Deserialize)]
@@ -11,7 +12,33 @@ pub struct TradingViewAlert {
     pub position_size: String,
     pub schema: String,
     pub timestamp: String,
-    // Future fields like order_type, slippage can be added if needed
+    /// `market` (the default), `limit`, `stop_loss`, `take_profit`, `trailing_stop` or `oco`
+    #[serde(default)]
+    pub order_type: Option<String>,
+    /// Limit price: of a limit order, of a stop-loss or take-profit once triggered, or of
+    /// the stop leg of an OCO order
+    #[serde(default)]
+    pub price: Option<String>,
+    /// Trigger price of a stop-loss, activation price of a trailing stop, or stop of an OCO
+    /// order
+    #[serde(default)]
+    pub stop_price: Option<String>,
+    /// Trigger price of a take-profit, or take-profit limit price of an OCO order
+    #[serde(default)]
+    pub take_profit_price: Option<String>,
+    /// Distance of a trailing stop from the price, in basis points
+    #[serde(default)]
+    pub trailing_delta_bps: Option<String>,
+    /// `GTC` (the default), `IOC` or `FOK`
+    #[serde(default)]
+    pub time_in_force: Option<String>,
+    /// Price when the alert fired, e.g. `{{close}}`, to measure the fill's slippage against
+    #[serde(default)]
+    pub alert_price: Option<String>,
+    /// Worst price a DEX swap or on-chain market order may fill at, in basis points from
+    /// the quote; unset, the venue's default applies
+    #[serde(default)]
+    pub slippage_bps: Option<String>,
 }
 
 impl TradingViewAlert {
@@ -29,4 +56,20 @@ impl TradingViewAlert {
         // Add more validations as needed
         Ok(())
     }
+
+    /// When the alert fired: `timestamp` as RFC 3339 (`{{timenow}}`) or in Unix seconds or
+    /// milliseconds. `None` if it is neither.
+    pub fn fired_at(&self) -> Option<DateTime<Utc>> {
+        let timestamp = self.timestamp.trim();
+        if let Ok(fired_at) = DateTime::parse_from_rfc3339(timestamp) {
+            return Some(fired_at.with_timezone(&Utc));
+        }
+        let epoch = timestamp.parse::<i64>().ok()?;
+        // Seconds would only reach 10^12 in the year 33658
+        if epoch.abs() >= 1_000_000_000_000 {
+            DateTime::from_timestamp_millis(epoch)
+        } else {
+            DateTime::from_timestamp(epoch, 0)
+        }
+    }
 }
@@ -1,6 +1,23 @@
-use config::{Config, File};
+use crate::bot::dispatch::ExecutionStatus;
+use crate::exchange::{Symbol, SymbolMap};
+use crate::utils::crypto::constant_time_eq;
+use chrono::{DateTime, Utc};
+use config::{Config, ConfigError, Environment, File, Map};
+use rust_decimal::{Decimal, RoundingStrategy};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+pub mod schema;
+
+/// Prefix for environment overrides, e.g. `XTRADE_API_SERVER__PORT=8000`.
+const ENV_PREFIX: &str = "XTRADE";
+/// Separator between a section and its key in environment overrides.
+const ENV_SEPARATOR: &str = "__";
+/// Suffix of variables holding the path of a file to read the value from.
+const ENV_FILE_SUFFIX: &str = "_FILE";
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub struct ApiServerConfig {
@@ -13,6 +30,53 @@ pub struct ApiServerConfig {
     /// File path for the application state
     #[serde(default)]
     pub state_file: PathBuf,
+    /// Reload the state file when it is modified by another process
+    #[serde(default)]
+    pub watch_state_file: bool,
+    /// How often to check the state file for external changes, in seconds
+    #[serde(default)]
+    pub watch_interval_secs: u64,
+    /// Indent the state file for humans; compact JSON is much faster to write for large states
+    #[serde(default)]
+    pub pretty_state_file: bool,
+    /// Earlier versions of the state file to keep, as `state.json.1` (the newest) and up;
+    /// 0 keeps none
+    #[serde(default = "default_state_backups")]
+    pub state_backups: usize,
+    /// In server mode, write changes to the state file in the background at most this often,
+    /// in milliseconds, instead of while the request waits; 0 writes every change at once
+    #[serde(default = "default_save_debounce_ms")]
+    pub save_debounce_ms: u64,
+    /// JSON Lines file recording every request the API and webhook servers answer
+    #[serde(default)]
+    pub access_log: Option<PathBuf>,
+    /// Also serve the API at its old unversioned paths, such as `/bots` for `/api/v1/bots`;
+    /// they are deprecated, and can be turned off once no integration uses them
+    #[serde(default = "legacy_routes_by_default")]
+    pub legacy_routes: bool,
+}
+
+impl ApiServerConfig {
+    /// URL reaching the API server from the host it runs on.
+    pub fn local_url(&self) -> String {
+        let host = match self.bind_address.as_str() {
+            "0.0.0.0" | "::" | "" => "127.0.0.1",
+            address => address,
+        };
+        format!("http://{}:{}", host, self.port)
+    }
+}
+
+fn legacy_routes_by_default() -> bool {
+    true
+}
+
+fn default_state_backups() -> usize {
+    3
+}
+
+fn default_save_debounce_ms() -> u64 {
+    200
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
@@ -23,6 +87,26 @@ pub struct WebhookServerConfig {
     /// Address to bind the Webhook Server
     #[serde(default)]
     pub bind_address: String,
+    /// Alerts whose timestamp is older than this, in seconds, are rejected; 0 accepts any age
+    #[serde(default = "default_max_alert_age_secs")]
+    pub max_alert_age_secs: u64,
+    /// Alerts identical to one a listener received this many seconds before are answered
+    /// with its outcome instead of trading again; 0 executes every alert
+    #[serde(default = "default_dedup_window_secs")]
+    pub dedup_window_secs: u64,
+    /// Base URL alert senders reach the webhook server at, e.g. `https://hooks.example.com`;
+    /// webhook URLs shown after a secret rotation start with it. Unset, they start with the
+    /// address the request for the rotation was sent to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_url: Option<String>,
+}
+
+fn default_max_alert_age_secs() -> u64 {
+    300
+}
+
+fn default_dedup_window_secs() -> u64 {
+    60
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
@@ -46,13 +130,1061 @@ pub struct RemoteCliConfig {
     /// URL of the remote server for online mode
     #[serde(default)]
     pub url: String,
+    /// Bearer token sent to the remote server (required when tenancy is enabled)
+    #[serde(default)]
+    pub token: Option<String>,
+    /// Send commands over a WebSocket session when the server supports it
+    #[serde(default = "websocket_by_default")]
+    pub websocket: bool,
 }
 
-#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+fn websocket_by_default() -> bool {
+    true
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub struct LocalCliConfig {
     /// File path for the local state file (offline mode)
     #[serde(default)]
     pub state_file: PathBuf,
+    /// How long offline commands wait for other processes to release the state file
+    #[serde(default = "default_lock_timeout_secs")]
+    pub lock_timeout_secs: u64,
+}
+
+fn default_lock_timeout_secs() -> u64 {
+    10
+}
+
+impl Default for LocalCliConfig {
+    fn default() -> Self {
+        Self {
+            state_file: PathBuf::default(),
+            lock_timeout_secs: default_lock_timeout_secs(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct TenancyConfig {
+    /// Namespace bots by the tenant owning the API bearer token
+    #[serde(default)]
+    pub enabled: bool,
+    /// Tenant name to API token
+    #[serde(default)]
+    pub tenants: HashMap<String, String>,
+    /// Support operator name to API token; admins may view a tenant read-only via `X-Act-As`
+    #[serde(default)]
+    pub admins: HashMap<String, String>,
+    /// JSON Lines file recording every impersonated request (always logged as well)
+    #[serde(default)]
+    pub audit_log: Option<PathBuf>,
+}
+
+impl TenancyConfig {
+    /// Separator between the tenant name and the bot ID in storage keys.
+    pub const SEPARATOR: char = ':';
+
+    /// Resolve the tenant owning `token`.
+    pub fn tenant_for_token(&self, token: &str) -> Option<&str> {
+        self.tenants
+            .iter()
+            .find(|(_, expected)| constant_time_eq(expected.as_bytes(), token.as_bytes()))
+            .map(|(tenant, _)| tenant.as_str())
+    }
+
+    /// Resolve the admin owning `token`.
+    pub fn admin_for_token(&self, token: &str) -> Option<&str> {
+        self.admins
+            .iter()
+            .find(|(_, expected)| constant_time_eq(expected.as_bytes(), token.as_bytes()))
+            .map(|(admin, _)| admin.as_str())
+    }
+
+    /// Tenant names are used as key prefixes, so they must be non-empty and free of the
+    /// separator, and tokens (of tenants and admins alike) must be non-empty and unique.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut tokens = HashSet::new();
+        for (tenant, token) in &self.tenants {
+            if tenant.is_empty() || tenant.contains(Self::SEPARATOR) {
+                return Err(ConfigError::Message(format!(
+                    "Invalid tenant name '{}': must be non-empty and not contain '{}'",
+                    tenant,
+                    Self::SEPARATOR
+                )));
+            }
+            if token.is_empty() || !tokens.insert(token) {
+                return Err(ConfigError::Message(format!(
+                    "Tenant '{}' must have a non-empty, unique token",
+                    tenant
+                )));
+            }
+        }
+        for (admin, token) in &self.admins {
+            if token.is_empty() || !tokens.insert(token) {
+                return Err(ConfigError::Message(format!(
+                    "Admin '{}' must have a non-empty, unique token",
+                    admin
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Users signing in at `POST /auth/login` for a token.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct AuthConfig {
+    /// Require a user's token, or a tenancy token, on every API request but the webhook
+    #[serde(default)]
+    pub enabled: bool,
+    /// Key signing the tokens (HS256), shared by servers accepting each other's tokens
+    #[serde(default)]
+    pub jwt_secret: Option<String>,
+    /// How long a token stays valid
+    #[serde(default = "default_token_ttl_secs")]
+    pub token_ttl_secs: u64,
+}
+
+fn default_token_ttl_secs() -> u64 {
+    3600
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            jwt_secret: None,
+            token_ttl_secs: default_token_ttl_secs(),
+        }
+    }
+}
+
+impl AuthConfig {
+    /// Shortest signing key accepted, in bytes.
+    pub const MIN_SECRET_LEN: usize = 32;
+
+    /// Tokens can only be issued with a signing key long enough not to be guessed.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let length = self.jwt_secret.as_deref().map_or(0, str::len);
+        if self.enabled && length < Self::MIN_SECRET_LEN {
+            return Err(ConfigError::Message(format!(
+                "auth.jwt_secret must be at least {} characters long",
+                Self::MIN_SECRET_LEN
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Resource limits; `None` means unlimited.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct QuotaLimits {
+    /// Maximum number of bots
+    #[serde(default)]
+    pub max_bots: Option<usize>,
+    /// Maximum number of listeners on a single bot
+    #[serde(default)]
+    pub max_listeners_per_bot: Option<usize>,
+    /// Maximum number of trades executed per UTC day
+    #[serde(default)]
+    pub max_trades_per_day: Option<u32>,
+}
+
+impl QuotaLimits {
+    /// Fill the limits left unset here from `fallback`.
+    pub fn or(&self, fallback: &QuotaLimits) -> QuotaLimits {
+        QuotaLimits {
+            max_bots: self.max_bots.or(fallback.max_bots),
+            max_listeners_per_bot: self
+                .max_listeners_per_bot
+                .or(fallback.max_listeners_per_bot),
+            max_trades_per_day: self.max_trades_per_day.or(fallback.max_trades_per_day),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct QuotaConfig {
+    /// Limits for every tenant (or the whole server when tenancy is disabled)
+    #[serde(default)]
+    pub default: QuotaLimits,
+    /// Per-tenant overrides of the default limits
+    #[serde(default)]
+    pub tenants: HashMap<String, QuotaLimits>,
+}
+
+impl QuotaConfig {
+    /// Effective limits for `tenant`.
+    pub fn limits_for(&self, tenant: Option<&str>) -> QuotaLimits {
+        tenant
+            .and_then(|tenant| self.tenants.get(tenant))
+            .map_or_else(|| self.default.clone(), |limits| limits.or(&self.default))
+    }
+}
+
+/// Comparing local order records with the exchanges after a restart.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ReconciliationConfig {
+    /// Reconcile every bot with its exchange when the server starts
+    #[serde(default = "reconcile_by_default")]
+    pub on_startup: bool,
+    /// How far back to fetch fills, in hours (at least back to the oldest unconfirmed order)
+    #[serde(default = "default_lookback_hours")]
+    pub lookback_hours: u64,
+}
+
+fn reconcile_by_default() -> bool {
+    true
+}
+
+fn default_lookback_hours() -> u64 {
+    24
+}
+
+impl Default for ReconciliationConfig {
+    fn default() -> Self {
+        Self {
+            on_startup: reconcile_by_default(),
+            lookback_hours: default_lookback_hours(),
+        }
+    }
+}
+
+/// Asking the exchange what the API key of a new bot may do.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct KeyCheckConfig {
+    /// Check the key of every bot created with credentials
+    #[serde(default = "check_keys_by_default")]
+    pub on_create: bool,
+    /// Refuse to create bots whose key may withdraw, or may not trade, instead of warning
+    #[serde(default)]
+    pub refuse: bool,
+}
+
+fn check_keys_by_default() -> bool {
+    true
+}
+
+impl Default for KeyCheckConfig {
+    fn default() -> Self {
+        Self {
+            on_create: check_keys_by_default(),
+            refuse: false,
+        }
+    }
+}
+
+/// Watching the exchange accounts of bots for withdrawals no one expected, e.g. with stolen
+/// API keys.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct WithdrawalMonitorConfig {
+    /// Check the withdrawals of every bot with credentials periodically
+    #[serde(default)]
+    pub enabled: bool,
+    /// Seconds between checks
+    #[serde(default = "default_withdrawal_interval_secs")]
+    pub interval_secs: u64,
+    /// How far back to fetch withdrawals and deposits, in hours
+    #[serde(default = "default_lookback_hours")]
+    pub lookback_hours: u64,
+    /// Addresses withdrawals are expected to, which raise no alarm
+    #[serde(default)]
+    pub allowed_addresses: Vec<String>,
+}
+
+fn default_withdrawal_interval_secs() -> u64 {
+    300
+}
+
+impl Default for WithdrawalMonitorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: default_withdrawal_interval_secs(),
+            lookback_hours: default_lookback_hours(),
+            allowed_addresses: Vec::new(),
+        }
+    }
+}
+
+/// How long a kind of record is kept: `forever`, or a number of hours (`12h`), days
+/// (`30d`), weeks (`2w`) or years of 365 days (`1y`).
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+pub enum RetentionPeriod {
+    #[default]
+    Forever,
+    Hours(u32),
+}
+
+impl RetentionPeriod {
+    /// Records from before this time are no longer kept at `now`; `None` keeps them all.
+    pub fn cutoff(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            RetentionPeriod::Forever => None,
+            RetentionPeriod::Hours(hours) => Some(now - chrono::Duration::hours((*hours).into())),
+        }
+    }
+}
+
+impl FromStr for RetentionPeriod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("forever") {
+            return Ok(RetentionPeriod::Forever);
+        }
+        let invalid = || {
+            format!(
+                "retention must be 'forever' or a positive number of hours, days, weeks or \
+                 years such as '30d', not '{}'",
+                s
+            )
+        };
+        let unit = s.chars().last().ok_or_else(invalid)?;
+        let hours_per_unit = match unit.to_ascii_lowercase() {
+            'h' => 1,
+            'd' => 24,
+            'w' => 7 * 24,
+            'y' => 365 * 24,
+            _ => return Err(invalid()),
+        };
+        s[..s.len() - 1]
+            .parse::<u32>()
+            .ok()
+            .filter(|count| *count > 0)
+            .and_then(|count| count.checked_mul(hours_per_unit))
+            .map(RetentionPeriod::Hours)
+            .ok_or_else(invalid)
+    }
+}
+
+impl fmt::Display for RetentionPeriod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RetentionPeriod::Forever => write!(f, "forever"),
+            RetentionPeriod::Hours(hours) => {
+                let (per_unit, unit) = [(365 * 24, 'y'), (7 * 24, 'w'), (24, 'd')]
+                    .into_iter()
+                    .find(|(per_unit, _)| hours % per_unit == 0)
+                    .unwrap_or((1, 'h'));
+                write!(f, "{}{}", hours / per_unit, unit)
+            }
+        }
+    }
+}
+
+impl TryFrom<String> for RetentionPeriod {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<RetentionPeriod> for String {
+    fn from(period: RetentionPeriod) -> Self {
+        period.to_string()
+    }
+}
+
+/// How long each kind of record is kept before compaction prunes it.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct RetentionConfig {
+    /// Alerts listeners accepted, kept in the state
+    #[serde(default = "default_alert_retention")]
+    pub alerts: RetentionPeriod,
+    /// Events of the bots' timelines, kept in the state
+    #[serde(default)]
+    pub events: RetentionPeriod,
+    /// Trade history; whole months are pruned once all of them are older
+    #[serde(default)]
+    pub trades: RetentionPeriod,
+    /// Lines of `tenancy.audit_log`
+    #[serde(default = "default_audit_retention")]
+    pub audit: RetentionPeriod,
+    /// Lines of `api_server.access_log`
+    #[serde(default = "default_access_log_retention")]
+    pub access_log: RetentionPeriod,
+    /// Append what is pruned to a JSON Lines file next to the state file
+    #[serde(default)]
+    pub archive: bool,
+}
+
+fn default_alert_retention() -> RetentionPeriod {
+    RetentionPeriod::Hours(30 * 24)
+}
+
+fn default_audit_retention() -> RetentionPeriod {
+    RetentionPeriod::Hours(365 * 24)
+}
+
+fn default_access_log_retention() -> RetentionPeriod {
+    RetentionPeriod::Hours(14 * 24)
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            alerts: default_alert_retention(),
+            events: RetentionPeriod::Forever,
+            trades: RetentionPeriod::Forever,
+            audit: default_audit_retention(),
+            access_log: default_access_log_retention(),
+            archive: false,
+        }
+    }
+}
+
+/// Compacting the state in server mode.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct CompactionConfig {
+    /// Prune what the retention policy no longer keeps periodically
+    #[serde(default)]
+    pub enabled: bool,
+    /// Hours between compactions
+    #[serde(default = "default_compaction_interval_hours")]
+    pub interval_hours: u64,
+}
+
+fn default_compaction_interval_hours() -> u64 {
+    24
+}
+
+impl Default for CompactionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_hours: default_compaction_interval_hours(),
+        }
+    }
+}
+
+/// What to do when the cached price of a symbol is too old.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StalePricePolicy {
+    /// Ask the exchange for a fresh price, rejecting if it has none
+    #[default]
+    Refresh,
+    /// Reject right away
+    Reject,
+}
+
+/// In-process cache of the last price of each symbol.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct PriceCacheConfig {
+    /// Prices older than this, in seconds, are stale
+    #[serde(default = "default_max_staleness_secs")]
+    pub max_staleness_secs: u64,
+    #[serde(default)]
+    pub on_stale: StalePricePolicy,
+    /// Exchange ticker streams keeping cached prices current
+    #[serde(default)]
+    pub streams: Vec<PriceStreamConfig>,
+    /// Wait before reconnecting a stream that failed or closed, in seconds
+    #[serde(default = "default_stream_reconnect_secs")]
+    pub stream_reconnect_secs: u64,
+    /// A stream without a price update for this long, in seconds, is reconnected
+    #[serde(default = "default_stream_max_gap_secs")]
+    pub stream_max_gap_secs: u64,
+}
+
+fn default_max_staleness_secs() -> u64 {
+    30
+}
+
+fn default_stream_reconnect_secs() -> u64 {
+    5
+}
+
+fn default_stream_max_gap_secs() -> u64 {
+    30
+}
+
+impl Default for PriceCacheConfig {
+    fn default() -> Self {
+        Self {
+            max_staleness_secs: default_max_staleness_secs(),
+            on_stale: StalePricePolicy::default(),
+            streams: Vec::new(),
+            stream_reconnect_secs: default_stream_reconnect_secs(),
+            stream_max_gap_secs: default_stream_max_gap_secs(),
+        }
+    }
+}
+
+/// WebSocket ticker stream of the prices of `symbols` on `exchange` (Binance or Bybit).
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct PriceStreamConfig {
+    pub exchange: String,
+    /// Symbols as alerts name them, like `BTCUSDT` or `BTCUSDT.P`
+    pub symbols: Vec<String>,
+    /// WebSocket base URL replacing the exchange's production endpoint
+    #[serde(default)]
+    pub endpoint: Option<String>,
+}
+
+/// Holding on to alerts while their exchange cannot be reached.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct OutageConfig {
+    /// Consecutive connection failures after which an exchange counts as down
+    #[serde(default = "default_failure_threshold")]
+    pub failure_threshold: u32,
+    /// How long to leave a down exchange alone before trying it again, in seconds
+    #[serde(default = "default_retry_after_secs")]
+    pub retry_after_secs: u64,
+    /// How long an alert waits for its exchange, in seconds; 0 fails alerts right away
+    #[serde(default = "default_buffer_ttl_secs")]
+    pub buffer_ttl_secs: u64,
+    /// Waiting alerts are dropped once the price moved by more than this fraction
+    #[serde(default = "default_price_tolerance")]
+    pub price_tolerance: f64,
+    /// Most alerts waiting at once; further alerts fail right away
+    #[serde(default = "default_max_buffered")]
+    pub max_buffered: usize,
+}
+
+fn default_failure_threshold() -> u32 {
+    3
+}
+
+fn default_retry_after_secs() -> u64 {
+    30
+}
+
+fn default_buffer_ttl_secs() -> u64 {
+    300
+}
+
+fn default_price_tolerance() -> f64 {
+    0.01
+}
+
+fn default_max_buffered() -> usize {
+    100
+}
+
+impl Default for OutageConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: default_failure_threshold(),
+            retry_after_secs: default_retry_after_secs(),
+            buffer_ttl_secs: default_buffer_ttl_secs(),
+            price_tolerance: default_price_tolerance(),
+            max_buffered: default_max_buffered(),
+        }
+    }
+}
+
+/// A period during which an exchange is known to be down for maintenance.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct MaintenanceWindow {
+    pub exchange: String,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl MaintenanceWindow {
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.starts_at <= now && now < self.ends_at
+    }
+}
+
+/// Known exchange maintenance. The bots of an exchange ignore their alerts while it is in
+/// one of its windows, and trade again once the window is over.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct MaintenanceConfig {
+    #[serde(default)]
+    pub windows: Vec<MaintenanceWindow>,
+}
+
+impl MaintenanceConfig {
+    /// The window `exchange` is in at `now`, if any.
+    pub fn active(&self, exchange: &str, now: DateTime<Utc>) -> Option<&MaintenanceWindow> {
+        self.windows
+            .iter()
+            .find(|window| window.exchange.eq_ignore_ascii_case(exchange) && window.is_active(now))
+    }
+
+    /// Windows not over at `now`, soonest first.
+    pub fn upcoming(&self, now: DateTime<Utc>) -> Vec<MaintenanceWindow> {
+        let mut windows: Vec<_> = self
+            .windows
+            .iter()
+            .filter(|window| window.ends_at > now)
+            .cloned()
+            .collect();
+        windows.sort_by_key(|window| window.starts_at);
+        windows
+    }
+
+    /// Every window must end after it starts.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        match self
+            .windows
+            .iter()
+            .find(|window| window.ends_at <= window.starts_at)
+        {
+            Some(window) => Err(ConfigError::Message(format!(
+                "Maintenance window of {} starting {} must end after it starts",
+                window.exchange,
+                window.starts_at.to_rfc3339()
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Decimal places of one symbol, overriding the defaults of [`PrecisionConfig`].
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct SymbolPrecision {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price_decimals: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quantity_decimals: Option<u32>,
+}
+
+/// Decimal places orders are rounded to before they are placed. Quantities are rounded down,
+/// so an order never exceeds the size the alert asked for; prices are rounded to the nearest
+/// step, ties to even.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct PrecisionConfig {
+    #[serde(default = "default_decimals")]
+    pub price_decimals: u32,
+    #[serde(default = "default_decimals")]
+    pub quantity_decimals: u32,
+    /// Overrides by symbol, as alerts name it
+    #[serde(default)]
+    pub symbols: HashMap<String, SymbolPrecision>,
+}
+
+fn default_decimals() -> u32 {
+    8
+}
+
+impl Default for PrecisionConfig {
+    fn default() -> Self {
+        Self {
+            price_decimals: default_decimals(),
+            quantity_decimals: default_decimals(),
+            symbols: HashMap::new(),
+        }
+    }
+}
+
+impl PrecisionConfig {
+    fn symbol(&self, symbol: &str) -> Option<&SymbolPrecision> {
+        let symbol = Symbol::normalize(symbol);
+        self.symbols
+            .iter()
+            .find(|(name, _)| Symbol::normalize(name) == symbol)
+            .map(|(_, precision)| precision)
+    }
+
+    /// `price` of `symbol` rounded to its decimal places.
+    pub fn round_price(&self, symbol: &str, price: Decimal) -> Decimal {
+        let decimals = self
+            .symbol(symbol)
+            .and_then(|precision| precision.price_decimals)
+            .unwrap_or(self.price_decimals);
+        price.round_dp_with_strategy(decimals, RoundingStrategy::MidpointNearestEven)
+    }
+
+    /// `quantity` of `symbol` rounded down to its decimal places.
+    pub fn round_quantity(&self, symbol: &str, quantity: Decimal) -> Decimal {
+        let decimals = self
+            .symbol(symbol)
+            .and_then(|precision| precision.quantity_decimals)
+            .unwrap_or(self.quantity_decimals);
+        quantity.round_dp_with_strategy(decimals, RoundingStrategy::ToZero)
+    }
+}
+
+/// Capital shared out among bots by their budgets.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct AllocationConfig {
+    /// Value of the portfolio, in the quote asset, that percentage budgets are shares of
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capital: Option<Decimal>,
+    /// Deviation of a bot's exposure from its budget, in percent of the budget, past which
+    /// the allocation report suggests rebalancing it
+    #[serde(default = "default_drift_threshold_pct")]
+    pub drift_threshold_pct: Decimal,
+}
+
+fn default_drift_threshold_pct() -> Decimal {
+    Decimal::TEN
+}
+
+impl Default for AllocationConfig {
+    fn default() -> Self {
+        Self {
+            capital: None,
+            drift_threshold_pct: default_drift_threshold_pct(),
+        }
+    }
+}
+
+/// Encryption of the credentials bots keep in the state file. The key is 32 bytes as 64 hex
+/// digits, e.g. from `openssl rand -hex 32`; at most one of `key` and `key_file` is set.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct EncryptionConfig {
+    /// The key, or `env:NAME` to read it from the environment variable `NAME`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// File holding the key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_file: Option<PathBuf>,
+}
+
+/// Where the state is kept.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageBackend {
+    /// The state file, `api_server.state_file`
+    #[default]
+    File,
+    /// A Redis key servers share, telling each other about changes over pub/sub
+    Redis,
+    /// An object in an S3-compatible bucket, e.g. for containers without persistent volumes
+    S3,
+}
+
+/// Storage of the state, shared by API server replicas unless it is kept in the state file.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct StorageConfig {
+    #[serde(default)]
+    pub backend: StorageBackend,
+    /// Server to keep the state in with the `redis` backend, e.g.
+    /// `redis://:password@host:6379/0`
+    #[serde(default = "default_redis_url")]
+    pub redis_url: String,
+    /// The state is kept under `<key_prefix>:state`; changes are announced on
+    /// `<key_prefix>:changes`
+    #[serde(default = "default_redis_key_prefix")]
+    pub key_prefix: String,
+    /// Bucket to keep the state in with the `s3` backend
+    #[serde(default)]
+    pub s3_bucket: String,
+    /// The state is kept as `<s3_prefix><file name of api_server.state_file>`, e.g.
+    /// `xtrade/state.json` for `xtrade/`
+    #[serde(default)]
+    pub s3_prefix: String,
+    #[serde(default = "default_s3_region")]
+    pub s3_region: String,
+    /// Endpoint of an S3-compatible service, e.g. `http://minio:9000`; AWS if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub s3_endpoint: Option<String>,
+    /// Name the bucket in the path (`<endpoint>/<bucket>/<key>`) rather than in the host
+    /// name, as most S3-compatible services expect
+    #[serde(default)]
+    pub s3_path_style: bool,
+    /// Access key ID; taken from `AWS_ACCESS_KEY_ID` if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub s3_access_key_id: Option<String>,
+    /// Secret access key; taken from `AWS_SECRET_ACCESS_KEY` if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub s3_secret_access_key: Option<String>,
+    /// Seconds between checks whether another server saved the state
+    #[serde(default = "default_s3_poll_interval_secs")]
+    pub s3_poll_interval_secs: u64,
+}
+
+fn default_redis_url() -> String {
+    "redis://127.0.0.1:6379".to_string()
+}
+
+fn default_redis_key_prefix() -> String {
+    "xtrade".to_string()
+}
+
+fn default_s3_region() -> String {
+    "us-east-1".to_string()
+}
+
+fn default_s3_poll_interval_secs() -> u64 {
+    5
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            backend: StorageBackend::File,
+            redis_url: default_redis_url(),
+            key_prefix: default_redis_key_prefix(),
+            s3_bucket: String::new(),
+            s3_prefix: String::new(),
+            s3_region: default_s3_region(),
+            s3_endpoint: None,
+            s3_path_style: false,
+            s3_access_key_id: None,
+            s3_secret_access_key: None,
+            s3_poll_interval_secs: default_s3_poll_interval_secs(),
+        }
+    }
+}
+
+/// Taking over the listening sockets of a running server when upgrading it.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct HandoffConfig {
+    /// Bind with `SO_REUSEPORT` so a new server can listen on the same ports before this one
+    /// stops (Unix only)
+    #[serde(default)]
+    pub reuse_port: bool,
+    /// How long a stopping server keeps serving the requests it already accepted, in seconds
+    #[serde(default = "default_shutdown_timeout_secs")]
+    pub shutdown_timeout_secs: u64,
+}
+
+fn default_shutdown_timeout_secs() -> u64 {
+    30
+}
+
+impl Default for HandoffConfig {
+    fn default() -> Self {
+        Self {
+            reuse_port: false,
+            shutdown_timeout_secs: default_shutdown_timeout_secs(),
+        }
+    }
+}
+
+/// Latency objective of alerts, from their receipt to the exchange's acknowledgment of their
+/// order. Operators are notified when it is first missed, and again only once it was met in
+/// between.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct SloConfig {
+    /// Latency the `percentile` of alerts must not exceed, in milliseconds; none if unset
+    #[serde(default)]
+    pub alert_to_fill_ms: Option<u64>,
+    /// Percentile of the latencies held to the target
+    #[serde(default = "default_slo_percentile")]
+    pub percentile: f64,
+    /// Latest executed orders the percentile is taken over, once there are that many
+    #[serde(default = "default_slo_window")]
+    pub window: usize,
+}
+
+fn default_slo_percentile() -> f64 {
+    95.0
+}
+
+fn default_slo_window() -> usize {
+    20
+}
+
+impl Default for SloConfig {
+    fn default() -> Self {
+        Self {
+            alert_to_fill_ms: None,
+            percentile: default_slo_percentile(),
+            window: default_slo_window(),
+        }
+    }
+}
+
+impl SloConfig {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if !(self.percentile > 0.0 && self.percentile <= 100.0) || self.window == 0 {
+            return Err(ConfigError::Message(
+                "slo.percentile must be above 0 and at most 100, and slo.window at least 1"
+                    .to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Compression of the responses of the API and Web UI servers, for clients that accept it.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct CompressionConfig {
+    #[serde(default = "compress_by_default")]
+    pub enabled: bool,
+    /// Smallest response compressed, in bytes; smaller ones are not worth it
+    #[serde(default = "default_compression_min_size")]
+    pub min_size: u64,
+}
+
+fn compress_by_default() -> bool {
+    true
+}
+
+fn default_compression_min_size() -> u64 {
+    1024
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: compress_by_default(),
+            min_size: default_compression_min_size(),
+        }
+    }
+}
+
+/// Where operator notifications are delivered.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct NotificationConfig {
+    /// URL receiving each notification as a JSON `POST`; notifications are only logged if unset
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+    /// More URLs notifications about alerts can be routed to, by channel name
+    #[serde(default)]
+    pub channels: HashMap<String, String>,
+    /// Alert outcomes that notify, and through which channels, unless the bot or the listener
+    /// of the alert chooses otherwise
+    #[serde(default)]
+    pub alerts: NotificationPrefs,
+}
+
+/// Which alert outcomes notify, and through which channels. A listener's preferences are
+/// merged with those of its bot and then `notifications.alerts`: each setting comes from the
+/// first that has it.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct NotificationPrefs {
+    /// Outcomes that notify, e.g. only `failed`; none if unset everywhere
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on: Option<Vec<ExecutionStatus>>,
+    /// `webhook` for `notifications.webhook_url`, `log` to only log, or a name from
+    /// `notifications.channels`; `webhook` if unset everywhere
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channels: Option<Vec<String>>,
+}
+
+impl NotificationPrefs {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// These preferences, with the settings they leave unset taken from `fallback`.
+    pub fn or(&self, fallback: &Self) -> Self {
+        Self {
+            on: self.on.clone().or_else(|| fallback.on.clone()),
+            channels: self.channels.clone().or_else(|| fallback.channels.clone()),
+        }
+    }
+}
+
+impl std::fmt::Display for NotificationPrefs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let join = |items: Vec<String>| {
+            if items.is_empty() {
+                "nothing".to_string()
+            } else {
+                items.join(", ")
+            }
+        };
+        let on = self
+            .on
+            .as_ref()
+            .map(|on| on.iter().map(|s| s.to_string()).collect());
+        match on {
+            Some(on) => write!(f, "on {}", join(on))?,
+            None => write!(f, "on inherited outcomes")?,
+        }
+        if let Some(channels) = &self.channels {
+            write!(f, " via {}", join(channels.clone()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Monthly statements of each bot's trading.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct StatementConfig {
+    /// Write each bot's statement for a month once it is over
+    #[serde(default)]
+    pub enabled: bool,
+    /// Attach every statement written to a notification
+    #[serde(default)]
+    pub notify: bool,
+    /// Command rendering a statement as PDF too, `{html}` and `{pdf}` replaced by the paths
+    /// of the statement and the PDF to write
+    #[serde(default)]
+    pub pdf_command: Option<String>,
+}
+
+/// A check run by `server --self-test`.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SelfTestCheck {
+    /// The tenant table and maintenance calendar can be enforced
+    Config,
+    /// The state file loads
+    State,
+    /// The state file can be written
+    Storage,
+    /// The exchange account of each bot with an enabled listener answers
+    Exchanges,
+    /// A test notification reaches the notification webhook
+    Notifications,
+}
+
+/// What `server --self-test` checks before reporting and exiting.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct SelfTestConfig {
+    /// Checks to run, by default all of them
+    #[serde(default = "default_self_test_checks")]
+    pub checks: Vec<SelfTestCheck>,
+    /// How long each exchange has to answer, in seconds
+    #[serde(default = "default_self_test_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_self_test_checks() -> Vec<SelfTestCheck> {
+    vec![
+        SelfTestCheck::Config,
+        SelfTestCheck::State,
+        SelfTestCheck::Storage,
+        SelfTestCheck::Exchanges,
+        SelfTestCheck::Notifications,
+    ]
+}
+
+fn default_self_test_timeout_secs() -> u64 {
+    10
+}
+
+impl Default for SelfTestConfig {
+    fn default() -> Self {
+        Self {
+            checks: default_self_test_checks(),
+            timeout_secs: default_self_test_timeout_secs(),
+        }
+    }
+}
+
+/// An exchange adapter run as a program of its own, speaking JSON-RPC over its stdin and
+/// stdout; see [`crate::exchange::plugin`].
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ExchangePluginConfig {
+    /// Program to run for each call of the adapter
+    pub command: PathBuf,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// The program's environment, besides `PATH`; nothing else of the server's is passed on
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// How long a call may take, in seconds, before the program is killed
+    #[serde(default = "default_plugin_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_plugin_timeout_secs() -> u64 {
+    10
+}
+
+/// Adapters of exchanges xtrade does not ship with.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct PluginConfig {
+    /// Adapters by the exchange name bots give
+    #[serde(default)]
+    pub exchanges: HashMap<String, ExchangePluginConfig>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
@@ -67,6 +1199,75 @@ pub struct AppConfig {
     pub remote_cli: RemoteCliConfig,
     /// Configuration for offline mode
     pub local_cli: LocalCliConfig,
+    /// Multi-tenant namespacing
+    #[serde(default)]
+    pub tenancy: TenancyConfig,
+    /// Users and their roles
+    #[serde(default)]
+    pub auth: AuthConfig,
+    /// Limits on bots, listeners and trades
+    #[serde(default)]
+    pub quotas: QuotaConfig,
+    /// Operator notifications
+    #[serde(default)]
+    pub notifications: NotificationConfig,
+    /// Latency objective of alerts
+    #[serde(default)]
+    pub slo: SloConfig,
+    /// Response compression
+    #[serde(default)]
+    pub compression: CompressionConfig,
+    /// Startup reconciliation with the exchanges
+    #[serde(default)]
+    pub reconciliation: ReconciliationConfig,
+    /// Checks of the API keys of new bots
+    #[serde(default)]
+    pub key_check: KeyCheckConfig,
+    /// Alarms on unexpected withdrawals from the bots' accounts
+    #[serde(default)]
+    pub withdrawals: WithdrawalMonitorConfig,
+    /// How long alerts, events, trades and log lines are kept
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    /// Periodic pruning of what the retention policy no longer keeps
+    #[serde(default)]
+    pub compaction: CompactionConfig,
+    /// Monthly statements per bot
+    #[serde(default)]
+    pub statements: StatementConfig,
+    /// Checks of `server --self-test`
+    #[serde(default)]
+    pub self_test: SelfTestConfig,
+    /// Price cache used by risk checks and sizing
+    #[serde(default)]
+    pub prices: PriceCacheConfig,
+    /// Zero-downtime restarts
+    #[serde(default)]
+    pub handoff: HandoffConfig,
+    /// Buffering alerts during exchange outages
+    #[serde(default)]
+    pub outages: OutageConfig,
+    /// Scheduled exchange maintenance
+    #[serde(default)]
+    pub maintenance: MaintenanceConfig,
+    /// Rounding of order quantities and prices
+    #[serde(default)]
+    pub precision: PrecisionConfig,
+    /// Budgets of bots
+    #[serde(default)]
+    pub allocation: AllocationConfig,
+    /// Encryption of bot credentials in the state file
+    #[serde(default)]
+    pub encryption: EncryptionConfig,
+    /// Where the state is kept
+    #[serde(default)]
+    pub storage: StorageConfig,
+    /// Exchange symbols to trade for strategy symbols, for bots that do not map them
+    #[serde(default)]
+    pub symbol_map: SymbolMap,
+    /// Exchange adapters of third parties
+    #[serde(default)]
+    pub plugins: PluginConfig,
 }
 
 impl Default for AppConfig {
@@ -76,10 +1277,20 @@ impl Default for AppConfig {
                 port: 7762,
                 bind_address: "127.0.0.1".to_string(),
                 state_file: PathBuf::from("state.json"),
+                watch_state_file: false,
+                watch_interval_secs: 2,
+                pretty_state_file: false,
+                state_backups: default_state_backups(),
+                save_debounce_ms: default_save_debounce_ms(),
+                access_log: None,
+                legacy_routes: legacy_routes_by_default(),
             },
             webhook_server: WebhookServerConfig {
                 port: 7763,
                 bind_address: "0.0.0.0".to_string(),
+                max_alert_age_secs: default_max_alert_age_secs(),
+                dedup_window_secs: default_dedup_window_secs(),
+                public_url: None,
             },
             web_client: WebClientConfig {
                 enable: true,
@@ -89,10 +1300,36 @@ impl Default for AppConfig {
             },
             remote_cli: RemoteCliConfig {
                 url: "http://localhost:7762".to_string(),
+                token: None,
+                websocket: true,
             },
             local_cli: LocalCliConfig {
                 state_file: PathBuf::from("state.json"),
+                ..LocalCliConfig::default()
             },
+            tenancy: TenancyConfig::default(),
+            auth: AuthConfig::default(),
+            quotas: QuotaConfig::default(),
+            notifications: NotificationConfig::default(),
+            slo: SloConfig::default(),
+            compression: CompressionConfig::default(),
+            reconciliation: ReconciliationConfig::default(),
+            key_check: KeyCheckConfig::default(),
+            withdrawals: WithdrawalMonitorConfig::default(),
+            retention: RetentionConfig::default(),
+            compaction: CompactionConfig::default(),
+            statements: StatementConfig::default(),
+            self_test: SelfTestConfig::default(),
+            prices: PriceCacheConfig::default(),
+            handoff: HandoffConfig::default(),
+            outages: OutageConfig::default(),
+            maintenance: MaintenanceConfig::default(),
+            precision: PrecisionConfig::default(),
+            allocation: AllocationConfig::default(),
+            encryption: EncryptionConfig::default(),
+            storage: StorageConfig::default(),
+            symbol_map: SymbolMap::default(),
+            plugins: PluginConfig::default(),
         }
     }
 }
@@ -118,36 +1355,123 @@ impl AppConfig {
     }
 
     /// Load configuration with optional environment-based overrides
-    pub fn load<P: AsRef<Path>>(file_path: Option<P>) -> Result<Self, config::ConfigError> {
+    ///
+    /// Sources are layered: built-in defaults, then the config file, then `XTRADE_*`
+    /// environment variables (see [`env_overrides`]).
+    pub fn load<P: AsRef<Path>>(file_path: Option<P>) -> Result<Self, ConfigError> {
         let config_path = file_path
             .map(|p| p.as_ref().to_path_buf())
             .or_else(|| std::env::var("CONFIG_FILE").ok().map(PathBuf::from))
             .unwrap_or_else(|| PathBuf::from("config.toml"));
 
+        let env_vars = std::env::vars_os()
+            .filter_map(|(key, value)| Some((key.into_string().ok()?, value.into_string().ok()?)));
+
+        Self::load_from(&config_path, env_overrides(env_vars)?)
+    }
+
+    fn load_from(config_path: &Path, overrides: Map<String, String>) -> Result<Self, ConfigError> {
         log::info!("Loading configuration from: {}", config_path.display());
 
-        // Try loading the configuration file
-        let result = Config::builder()
-            .add_source(File::from(config_path.clone()).required(false)) // Optional config file
+        // A value that does not parse fails the load: falling back to the defaults would drop
+        // the users, tokens and secrets of the file and start the server unprotected
+        let config = Config::builder()
+            .add_source(Config::try_from(&AppConfig::default())?)
+            .add_source(File::from(config_path.to_path_buf()).required(false)) // Optional config file
+            .add_source(
+                Environment::with_prefix(ENV_PREFIX)
+                    .prefix_separator("_")
+                    .separator(ENV_SEPARATOR)
+                    .try_parsing(true)
+                    .source(Some(overrides)),
+            )
             .build()
-            .and_then(|cfg| cfg.try_deserialize::<AppConfig>());
-
-        match result {
-            Ok(config) => Ok(config),
-            Err(_) => {
-                log::warn!(
-                    "Failed to load configuration from {}. Falling back to defaults.",
-                    config_path.display()
+            .and_then(|cfg| cfg.try_deserialize::<AppConfig>())
+            .map_err(|e| {
+                log::error!(
+                    "Failed to load configuration from {}: {}",
+                    config_path.display(),
+                    e
                 );
-                let default_config = AppConfig::default();
+                e
+            })?;
 
-                // Attempt to save defaults and let `save` handle errors/logging
-                let _ = default_config.save(Some(&config_path));
+        if !config_path.exists() {
+            // Write out the defaults (never the overrides, which may hold secrets)
+            let _ = AppConfig::default().save(Some(config_path));
+        }
+        Ok(config)
+    }
+}
 
-                Ok(default_config)
+/// Dotted keys of every configuration value, e.g. `api_server.state_file`.
+fn config_keys() -> HashSet<String> {
+    fn collect(prefix: &str, value: &serde_json::Value, keys: &mut HashSet<String>) {
+        if let serde_json::Value::Object(map) = value {
+            for (key, value) in map {
+                let key = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                collect(&key, value, keys);
+                keys.insert(key);
             }
         }
     }
+
+    let mut keys = HashSet::new();
+    if let Ok(value) = serde_json::to_value(AppConfig::default()) {
+        collect("", &value, &mut keys);
+    }
+    keys
+}
+
+/// Collects `XTRADE_*` environment overrides, resolving the `*_FILE` convention used for
+/// Docker and Kubernetes secrets: `XTRADE_FOO_FILE=/run/secrets/foo` sets `XTRADE_FOO` to
+/// the contents of that file (without the trailing newline).
+///
+/// A variable whose full name already maps to a config key, such as
+/// `XTRADE_API_SERVER__STATE_FILE`, is taken literally, and a directly set variable wins
+/// over its `_FILE` form. Unreadable secret files are an error rather than a silent fallback.
+fn env_overrides<I>(vars: I) -> Result<Map<String, String>, ConfigError>
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    let prefix = format!("{}_", ENV_PREFIX);
+    let known_keys = config_keys();
+    let vars: Map<String, String> = vars
+        .into_iter()
+        .filter(|(key, _)| key.starts_with(&prefix))
+        .collect();
+
+    let mut overrides = Map::new();
+    for (key, value) in &vars {
+        let config_key = key[prefix.len()..]
+            .to_lowercase()
+            .replace(ENV_SEPARATOR, ".");
+
+        match key.strip_suffix(ENV_FILE_SUFFIX) {
+            Some(target) if !known_keys.contains(&config_key) => {
+                if vars.contains_key(target) {
+                    log::warn!("Both {} and {} are set; using {}.", target, key, target);
+                    continue;
+                }
+                let contents = std::fs::read_to_string(value).map_err(|e| {
+                    ConfigError::Message(format!("Failed to read {} from {}: {}", key, value, e))
+                })?;
+                overrides.insert(
+                    target.to_string(),
+                    contents.trim_end_matches(['\r', '\n']).to_string(),
+                );
+            }
+            _ => {
+                overrides.insert(key.clone(), value.clone());
+            }
+        }
+    }
+
+    Ok(overrides)
 }
 
 #[cfg(test)]
@@ -167,10 +1491,10 @@ mod tests {
             PathBuf::from("state.json")
         );
 
-        assert_eq!(default_config.webhook_server.port, 7764);
+        assert_eq!(default_config.webhook_server.port, 7763);
 
         assert!(default_config.web_client.enable);
-        assert_eq!(default_config.web_client.port, 7763);
+        assert_eq!(default_config.web_client.port, 7764);
         assert_eq!(
             default_config.web_client.static_files,
             PathBuf::from("src/webui/dist")
@@ -183,6 +1507,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_retention_periods() {
+        let period = |s: &str| s.parse::<RetentionPeriod>();
+        assert_eq!(period("30d"), Ok(RetentionPeriod::Hours(720)));
+        assert_eq!(period("1y").unwrap().to_string(), "1y");
+        assert_eq!(period("14d").unwrap().to_string(), "2w");
+        assert_eq!(period("36h").unwrap().to_string(), "36h");
+        assert_eq!(period("Forever"), Ok(RetentionPeriod::Forever));
+        for invalid in ["", "0d", "30", "3m", "-1d"] {
+            assert!(period(invalid).is_err(), "{}", invalid);
+        }
+
+        let now = Utc::now();
+        assert_eq!(RetentionPeriod::Forever.cutoff(now), None);
+        assert_eq!(
+            period("1d").unwrap().cutoff(now),
+            Some(now - chrono::Duration::days(1))
+        );
+        let config: RetentionConfig = toml::from_str("trades = \"5y\"").unwrap();
+        assert_eq!(config.trades, RetentionPeriod::Hours(5 * 365 * 24));
+        assert_eq!(config.alerts, RetentionPeriod::Hours(30 * 24));
+        assert!(toml::from_str::<RetentionConfig>("audit = \"soon\"").is_err());
+    }
+
     #[test]
     fn test_save_and_load_config() {
         let temp_dir = tempdir().unwrap();
@@ -233,6 +1581,94 @@ mod tests {
         assert!(config_path.exists());
     }
 
+    #[test]
+    fn test_env_overrides_and_secret_files() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("config.toml");
+        let secret_path = temp_dir.path().join("url");
+        fs::write(&secret_path, "http://secret:7762\n").unwrap();
+
+        let vars = vec![
+            ("XTRADE_API_SERVER__PORT".to_string(), "8000".to_string()),
+            (
+                "XTRADE_API_SERVER__STATE_FILE".to_string(),
+                "/data/state.json".to_string(),
+            ),
+            (
+                "XTRADE_REMOTE_CLI__URL_FILE".to_string(),
+                secret_path.display().to_string(),
+            ),
+            ("UNRELATED_FILE".to_string(), "/nonexistent".to_string()),
+        ];
+        let config = AppConfig::load_from(&config_path, env_overrides(vars).unwrap()).unwrap();
+
+        assert_eq!(config.api_server.port, 8000);
+        assert_eq!(
+            config.api_server.state_file,
+            PathBuf::from("/data/state.json")
+        );
+        assert_eq!(config.remote_cli.url, "http://secret:7762");
+
+        // Overrides are never written back to disk
+        let saved = fs::read_to_string(&config_path).unwrap();
+        assert!(!saved.contains("secret"));
+    }
+
+    #[test]
+    fn test_env_overrides_missing_secret_file() {
+        let vars = vec![(
+            "XTRADE_REMOTE_CLI__URL_FILE".to_string(),
+            "/nonexistent/secret".to_string(),
+        )];
+        assert!(env_overrides(vars).is_err());
+    }
+
+    #[test]
+    fn test_tenancy_config() {
+        let mut tenancy = TenancyConfig::default();
+        tenancy
+            .tenants
+            .insert("acme".to_string(), "acme-token".to_string());
+        assert!(tenancy.validate().is_ok());
+        assert_eq!(tenancy.tenant_for_token("acme-token"), Some("acme"));
+        assert_eq!(tenancy.tenant_for_token("acme-token2"), None);
+
+        tenancy
+            .admins
+            .insert("support".to_string(), "acme-token".to_string());
+        assert!(tenancy.validate().is_err());
+        tenancy
+            .admins
+            .insert("support".to_string(), "support-token".to_string());
+        assert_eq!(tenancy.admin_for_token("support-token"), Some("support"));
+        assert_eq!(tenancy.tenant_for_token("support-token"), None);
+
+        tenancy
+            .tenants
+            .insert("bad:name".to_string(), "other".to_string());
+        assert!(tenancy.validate().is_err());
+    }
+
+    #[test]
+    fn test_quota_limits_for_tenant() {
+        let mut quotas = QuotaConfig::default();
+        quotas.default.max_bots = Some(2);
+        quotas.default.max_trades_per_day = Some(100);
+        quotas.tenants.insert(
+            "acme".to_string(),
+            QuotaLimits {
+                max_bots: Some(10),
+                ..Default::default()
+            },
+        );
+
+        let acme = quotas.limits_for(Some("acme"));
+        assert_eq!(acme.max_bots, Some(10));
+        assert_eq!(acme.max_trades_per_day, Some(100));
+        assert_eq!(quotas.limits_for(Some("globex")).max_bots, Some(2));
+        assert_eq!(quotas.limits_for(None), quotas.default);
+    }
+
     #[test]
     fn test_invalid_config_file() {
         let temp_dir = tempdir().unwrap();
@@ -241,11 +1677,125 @@ mod tests {
         // Write invalid content to the file
         fs::write(&config_path, "invalid_toml_content").unwrap();
 
-        // Load configuration (should fallback to defaults)
-        let config = AppConfig::load(Some(&config_path)).unwrap();
+        // Loading fails, and leaves the file as it was
+        assert!(AppConfig::load(Some(&config_path)).is_err());
+        assert_eq!(
+            fs::read_to_string(&config_path).unwrap(),
+            "invalid_toml_content"
+        );
+    }
 
-        // Verify the defaults are used
-        assert_eq!(config.api_server.port, 7762);
-        assert_eq!(config.api_server.bind_address, "127.0.0.1");
+    #[test]
+    fn test_bad_values_keep_the_secured_config() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("config.toml");
+        let secured = "[auth]\nenabled = true\njwt_secret = \"keep-me\"\n\n[api_server]\nport = \"notaport\"\n";
+        fs::write(&config_path, secured).unwrap();
+        assert!(AppConfig::load_from(&config_path, Map::new()).is_err());
+
+        // A bad override fails the load too, rather than dropping the file's settings
+        fs::write(
+            &config_path,
+            "[auth]\nenabled = true\njwt_secret = \"keep-me\"\n",
+        )
+        .unwrap();
+        let overrides = env_overrides(vec![(
+            "XTRADE_API_SERVER__PORT".to_string(),
+            "notaport".to_string(),
+        )])
+        .unwrap();
+        assert!(AppConfig::load_from(&config_path, overrides).is_err());
+
+        assert!(fs::read_to_string(&config_path)
+            .unwrap()
+            .contains("keep-me"));
+    }
+
+    #[test]
+    fn test_maintenance_windows() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("maintenance.toml");
+        fs::write(
+            &config_path,
+            r#"
+[[maintenance.windows]]
+exchange = "Binance"
+starts_at = "2025-03-01T02:00:00Z"
+ends_at = "2025-03-01T04:00:00Z"
+reason = "wallet upgrade"
+
+[[maintenance.windows]]
+exchange = "bybit"
+starts_at = 2025-02-01T00:00:00Z
+ends_at = 2025-02-01T01:00:00Z
+"#,
+        )
+        .unwrap();
+        let maintenance = AppConfig::load(Some(&config_path)).unwrap().maintenance;
+        assert_eq!(maintenance.windows.len(), 2);
+        assert!(maintenance.validate().is_ok());
+
+        let at = |time: &str| time.parse::<DateTime<Utc>>().unwrap();
+        let during = at("2025-03-01T03:00:00Z");
+        let window = maintenance.active("binance", during).unwrap();
+        assert_eq!(window.reason.as_deref(), Some("wallet upgrade"));
+        assert!(maintenance.active("bybit", during).is_none());
+        assert!(maintenance
+            .active("binance", at("2025-03-01T04:00:00Z"))
+            .is_none());
+
+        let upcoming = maintenance.upcoming(at("2025-01-01T00:00:00Z"));
+        assert_eq!(upcoming[0].exchange, "bybit");
+        assert_eq!(maintenance.upcoming(during).len(), 1);
+
+        let mut inverted = maintenance.clone();
+        inverted.windows[0].ends_at = inverted.windows[0].starts_at;
+        assert!(inverted.validate().is_err());
+    }
+
+    #[test]
+    fn test_symbol_precision() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("precision.toml");
+        fs::write(
+            &config_path,
+            r#"
+[precision]
+price_decimals = 4
+
+[precision.symbols.BTCUSDT]
+quantity_decimals = 5
+
+[symbol_map]
+"BINANCE:BTCUSDTPERP" = "BTCUSDT.P"
+"#,
+        )
+        .unwrap();
+
+        let config = AppConfig::load(Some(&config_path)).unwrap();
+        assert_eq!(
+            config.symbol_map.get("BINANCE:BTCUSDTPERP").as_deref(),
+            Some("BTCUSDT.P")
+        );
+        let precision = config.precision;
+        assert_eq!(precision.quantity_decimals, 8);
+        let price = "1.23456".parse().unwrap();
+        let quantity = "0.123456789".parse().unwrap();
+        assert_eq!(
+            precision.round_price("ETHUSDT", price).to_string(),
+            "1.2346"
+        );
+        assert_eq!(
+            precision.round_price("btcusdt", price).to_string(),
+            "1.2346"
+        );
+        assert_eq!(
+            precision.round_quantity("BTCUSDT", quantity).to_string(),
+            "0.12345"
+        );
+        assert_eq!(
+            precision.round_quantity("ETHUSDT", quantity).to_string(),
+            "0.12345678"
+        );
     }
 }
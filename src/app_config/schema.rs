@@ -0,0 +1,399 @@
+// src/app_config/schema.rs
+//! JSON Schema of `config.toml`, for editors to validate and complete the file and for the
+//! admin UI to build its settings form from.
+//!
+//! The schema is read off the source of the configuration types rather than written by
+//! hand, so it cannot drift from them: each struct becomes a definition whose properties
+//! are its fields, described by their doc comments, and each enum of plain variants a
+//! choice of its serialized names. Defaults are those of [`AppConfig::default`]. Every key
+//! may be left out of the file, the defaults filling it in; only the fields of entries of
+//! lists and tables, such as `prices.streams`, are required. Keys the types do not have are
+//! refused, though the server ignores them, as they are most likely misspelt.
+use super::AppConfig;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+
+/// Sources declaring the configuration types, and the types they use from elsewhere.
+const SOURCES: [&str; 2] = [
+    include_str!("../app_config.rs"),
+    include_str!("../bot/dispatch.rs"),
+];
+
+/// The configuration value for a struct field or enum variant.
+struct Field {
+    name: String,
+    ty: String,
+    doc: String,
+    defaulted: bool,
+}
+
+enum Definition {
+    Struct {
+        doc: String,
+        fields: Vec<Field>,
+    },
+    /// Enum of plain variants, by their serialized names
+    Choice {
+        doc: String,
+        variants: Vec<Field>,
+    },
+    /// Type serialized as a string of its own syntax, like `RetentionPeriod`
+    Text {
+        doc: String,
+    },
+}
+
+/// JSON Schema of the configuration file.
+pub fn config_schema() -> Value {
+    let types = SOURCES.iter().flat_map(|source| parse(source)).collect();
+    let defaults = serde_json::to_value(AppConfig::default()).unwrap_or(Value::Null);
+    let mut schema = Schema {
+        types: &types,
+        definitions: Map::new(),
+    };
+    let root = schema.definition("AppConfig", Some(&defaults), true);
+    let mut document = json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "xtrade configuration",
+    });
+    if let (Value::Object(document), Value::Object(root)) = (&mut document, root) {
+        document.extend(root);
+        document.insert("definitions".to_string(), Value::Object(schema.definitions));
+    }
+    document
+}
+
+struct Schema<'a> {
+    types: &'a BTreeMap<String, Definition>,
+    definitions: Map<String, Value>,
+}
+
+impl Schema<'_> {
+    /// The schema of the type `name`, given the defaults of its value where it is built in.
+    /// Structs `layered` on the built-in configuration require none of their fields.
+    fn definition(&mut self, name: &str, defaults: Option<&Value>, layered: bool) -> Value {
+        let mut schema = Map::new();
+        match &self.types[name] {
+            Definition::Struct { doc, fields } => {
+                describe(&mut schema, doc);
+                schema.insert("type".to_string(), json!("object"));
+                let mut properties = Map::new();
+                let mut required = Vec::new();
+                for field in fields {
+                    let default = defaults.and_then(|defaults| defaults.get(&field.name));
+                    properties.insert(field.name.clone(), self.property(field, default, layered));
+                    let optional = field.defaulted || field.ty.starts_with("Option<");
+                    if !layered && !optional {
+                        required.push(json!(field.name));
+                    }
+                }
+                schema.insert("properties".to_string(), Value::Object(properties));
+                if !required.is_empty() {
+                    schema.insert("required".to_string(), Value::Array(required));
+                }
+                schema.insert("additionalProperties".to_string(), json!(false));
+            }
+            Definition::Choice { doc, variants } => {
+                describe(&mut schema, doc);
+                let choices = variants.iter().map(|variant| {
+                    let mut choice = Map::new();
+                    choice.insert("const".to_string(), json!(variant.name));
+                    describe(&mut choice, &variant.doc);
+                    Value::Object(choice)
+                });
+                schema.insert("oneOf".to_string(), Value::Array(choices.collect()));
+            }
+            Definition::Text { doc } => {
+                describe(&mut schema, doc);
+                schema.insert("type".to_string(), json!("string"));
+            }
+        }
+        Value::Object(schema)
+    }
+
+    /// The schema of a field, with its built-in default unless it is a section of its own.
+    fn property(&mut self, field: &Field, default: Option<&Value>, layered: bool) -> Value {
+        let section = self.types.contains_key(&field.ty)
+            && matches!(self.types[&field.ty], Definition::Struct { .. });
+        let ty = self.type_schema(&field.ty, default.filter(|_| section), layered);
+        let mut schema = Map::new();
+        describe(&mut schema, &field.doc);
+        if let Some(default) = default.filter(|default| !section && !default.is_null()) {
+            schema.insert("default".to_string(), default.clone());
+        }
+        match ty {
+            Value::Object(ty) if !ty.contains_key("$ref") => schema.extend(ty),
+            ty if schema.is_empty() => return ty,
+            // Draft 7 ignores whatever stands next to a reference
+            ty => {
+                schema.insert("allOf".to_string(), json!([ty]));
+            }
+        }
+        Value::Object(schema)
+    }
+
+    /// The schema of the Rust type `ty`; types of unknown shape accept anything.
+    fn type_schema(&mut self, ty: &str, defaults: Option<&Value>, layered: bool) -> Value {
+        if let Some(inner) = generic(ty, "Option") {
+            return self.type_schema(inner, defaults, layered);
+        }
+        if let Some(item) = generic(ty, "Vec") {
+            return json!({ "type": "array", "items": self.type_schema(item, None, false) });
+        }
+        if let Some(item) = generic(ty, "HashSet") {
+            let items = self.type_schema(item, None, false);
+            return json!({ "type": "array", "items": items, "uniqueItems": true });
+        }
+        if let Some(entry) = generic(ty, "HashMap").or_else(|| generic(ty, "BTreeMap")) {
+            let value = entry
+                .split_once(',')
+                .map_or(entry, |(_, value)| value.trim());
+            let values = self.type_schema(value, None, false);
+            return json!({ "type": "object", "additionalProperties": values });
+        }
+        match ty {
+            "bool" => json!({ "type": "boolean" }),
+            "u8" | "u16" | "u32" | "u64" | "usize" => json!({ "type": "integer", "minimum": 0 }),
+            "i8" | "i16" | "i32" | "i64" | "isize" => json!({ "type": "integer" }),
+            "f32" | "f64" => json!({ "type": "number" }),
+            // Decimals are written as strings to keep their precision, and read from either
+            "Decimal" => json!({ "type": ["number", "string"] }),
+            "String" | "PathBuf" => json!({ "type": "string" }),
+            "DateTime<Utc>" => json!({ "type": "string", "format": "date-time" }),
+            "SymbolMap" => {
+                json!({ "type": "object", "additionalProperties": { "type": "string" } })
+            }
+            name if self.types.contains_key(name) => {
+                if !self.definitions.contains_key(name) {
+                    // Reserve the name first, for types that contain themselves
+                    self.definitions.insert(name.to_string(), Value::Null);
+                    let definition = self.definition(name, defaults, layered);
+                    self.definitions.insert(name.to_string(), definition);
+                }
+                json!({ "$ref": format!("#/definitions/{}", name) })
+            }
+            _ => json!({}),
+        }
+    }
+}
+
+/// What `ty` wraps if it is `outer<...>`.
+fn generic<'a>(ty: &'a str, outer: &str) -> Option<&'a str> {
+    ty.strip_prefix(outer)?
+        .strip_prefix('<')?
+        .strip_suffix('>')
+        .map(str::trim)
+}
+
+fn describe(schema: &mut Map<String, Value>, doc: &str) {
+    if !doc.is_empty() {
+        schema.insert("description".to_string(), json!(doc));
+    }
+}
+
+/// Serialized name of a variant under `#[serde(rename_all = ...)]`.
+fn rename(variant: &str, rename_all: Option<&str>) -> String {
+    match rename_all {
+        Some("snake_case") => {
+            let mut name = String::new();
+            for (i, c) in variant.chars().enumerate() {
+                if c.is_uppercase() && i > 0 {
+                    name.push('_');
+                }
+                name.push(c.to_ascii_lowercase());
+            }
+            name
+        }
+        Some("lowercase") => variant.to_lowercase(),
+        _ => variant.to_string(),
+    }
+}
+
+/// The public structs and enums declared in `source`, by name. Enums with data are left out,
+/// unless they are serialized through a string.
+fn parse(source: &str) -> BTreeMap<String, Definition> {
+    let mut types = BTreeMap::new();
+    let mut doc = Vec::new();
+    let mut attributes = Vec::new();
+    let mut lines = source.lines();
+    while let Some(line) = lines.next() {
+        if let Some(text) = line.strip_prefix("///") {
+            doc.push(text.trim().to_string());
+            continue;
+        }
+        if line.starts_with("#[") {
+            attributes.push(line.to_string());
+            continue;
+        }
+        let declaration = line
+            .strip_suffix(" {")
+            .and_then(|line| line.strip_prefix("pub "));
+        let Some((kind, name)) = declaration.and_then(|line| line.split_once(' ')) else {
+            doc.clear();
+            attributes.clear();
+            continue;
+        };
+        let type_doc = join(&std::mem::take(&mut doc));
+        let serde = std::mem::take(&mut attributes).join(" ");
+        let body = members(&mut lines);
+        let definition = match kind {
+            "struct" => Definition::Struct {
+                doc: type_doc,
+                fields: body
+                    .into_iter()
+                    .filter_map(|(doc, attributes, line)| {
+                        let line = line.strip_prefix("pub ").unwrap_or(&line).to_string();
+                        let (name, ty) = line.trim_end_matches(',').split_once(": ")?;
+                        Some(Field {
+                            name: name.to_string(),
+                            ty: ty.trim().to_string(),
+                            doc,
+                            defaulted: attributes.contains("#[serde(default"),
+                        })
+                    })
+                    .collect(),
+            },
+            "enum" if serde.contains("try_from = \"String\"") => Definition::Text { doc: type_doc },
+            "enum" => {
+                let rename_all = serde
+                    .split_once("rename_all = \"")
+                    .and_then(|(_, rest)| rest.split_once('"'))
+                    .map(|(rule, _)| rule);
+                let variants: Option<Vec<Field>> = body
+                    .into_iter()
+                    .map(|(doc, _, line)| {
+                        let variant = line.trim_end_matches(',');
+                        (variant.chars().all(char::is_alphanumeric)).then(|| Field {
+                            name: rename(variant, rename_all),
+                            ty: String::new(),
+                            doc,
+                            defaulted: false,
+                        })
+                    })
+                    .collect();
+                match variants {
+                    Some(variants) => Definition::Choice {
+                        doc: type_doc,
+                        variants,
+                    },
+                    None => continue,
+                }
+            }
+            _ => continue,
+        };
+        types.insert(name.to_string(), definition);
+    }
+    types
+}
+
+/// The members of a declaration up to its closing brace, with their doc comments and
+/// attributes; comments and blank lines are skipped.
+fn members<'a>(lines: &mut impl Iterator<Item = &'a str>) -> Vec<(String, String, String)> {
+    let mut members = Vec::new();
+    let mut doc = Vec::new();
+    let mut attributes = String::new();
+    for line in lines.by_ref() {
+        if line == "}" {
+            break;
+        }
+        let line = line.trim();
+        if let Some(text) = line.strip_prefix("///") {
+            doc.push(text.trim().to_string());
+        } else if line.starts_with("#[") {
+            attributes.push_str(line);
+        } else if !line.is_empty() && !line.starts_with("//") {
+            members.push((
+                join(&doc),
+                std::mem::take(&mut attributes),
+                line.to_string(),
+            ));
+            doc.clear();
+        }
+    }
+    members
+}
+
+/// Doc comment lines as one text, blank lines separating paragraphs.
+fn join(lines: &[String]) -> String {
+    lines
+        .split(|line| line.is_empty())
+        .filter(|paragraph| !paragraph.is_empty())
+        .map(|paragraph| paragraph.join(" "))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The schema of the property at a dotted key, following the references of its sections.
+    fn property<'a>(schema: &'a Value, key: &str) -> Option<&'a Value> {
+        let resolve = |mut node: &'a Value| {
+            while let Some(reference) = (node.get("$ref"))
+                .or_else(|| node.pointer("/allOf/0/$ref"))
+                .and_then(Value::as_str)
+            {
+                let name = reference.strip_prefix("#/definitions/")?;
+                node = schema.get("definitions")?.get(name)?;
+            }
+            Some(node)
+        };
+        key.split('.').try_fold(schema, |node, name| {
+            resolve(node)?.get("properties")?.get(name)
+        })
+    }
+
+    #[test]
+    fn every_config_key_is_in_the_schema() {
+        let schema = config_schema();
+        for key in super::super::config_keys() {
+            assert!(
+                property(&schema, &key).is_some(),
+                "{} is not in the schema",
+                key
+            );
+        }
+
+        let port = property(&schema, "api_server.port").unwrap();
+        assert_eq!(port["type"], "integer");
+        assert_eq!(port["default"], 7762);
+        assert_eq!(port["description"], "Port number for the API server");
+        let retention = property(&schema, "retention.events").unwrap();
+        assert_eq!(retention["default"], "forever");
+        assert_eq!(schema["definitions"]["RetentionPeriod"]["type"], "string");
+        assert!(schema["required"].is_null());
+    }
+
+    #[test]
+    fn enums_offer_their_serialized_names() {
+        let schema = config_schema();
+        let choices = |name: &str| -> Vec<Value> {
+            schema["definitions"][name]["oneOf"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|choice| choice["const"].clone())
+                .collect()
+        };
+        assert_eq!(
+            choices("StalePricePolicy"),
+            vec![json!("refresh"), json!("reject")]
+        );
+        assert_eq!(
+            choices("ExecutionStatus"),
+            vec![
+                json!("executed"),
+                json!("failed"),
+                json!("ignored"),
+                json!("buffered")
+            ]
+        );
+
+        let on = property(&schema, "notifications.alerts.on").unwrap();
+        assert_eq!(on["items"]["$ref"], "#/definitions/ExecutionStatus");
+        // Entries of lists need what has no default
+        let stream = &schema["definitions"]["PriceStreamConfig"];
+        assert_eq!(stream["required"], json!(["exchange", "symbols"]));
+    }
+}
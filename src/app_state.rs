@@ -8,7 +8,28 @@
 //! - **Loading State**: Loads the state from a specified file or creates a new file with default
 //!   values if the file is missing.
 //! - **Saving State**: Saves the current state to a file, ensuring the state is persisted across
-//!   restarts.
+//!   restarts. The state is streamed to the file as compact JSON (pretty-printed if
+//!   `api_server.pretty_state_file` is set) and zstd-compressed when the file name ends in
+//!   `.zst`. Loading detects compressed content by its magic number, whatever the file name.
+//!   Saves write a temporary file and rename it over the state file, so a crash mid-write
+//!   cannot corrupt it, and keep `api_server.state_backups` earlier versions as
+//!   `state.json.1` (the newest), `state.json.2`, ...
+//! - **Deferred Saves**: In server mode, [`persist_state`] writes changes in the background at
+//!   most every `api_server.save_debounce_ms`, so request handlers only mark the state dirty
+//!   instead of writing it while holding the lock. [`flush`] writes what is left at shutdown.
+//! - **File Locking**: Loads and saves hold an advisory [lock](FileLock) on the state file, so
+//!   a server and CLI commands sharing it never read a half-replaced file or save over each
+//!   other's changes; a process finding it held fails with [`AppError::StateLocked`], naming
+//!   the PID holding it. Offline commands [hold it](AppState::load_locked) from load to save,
+//!   waiting `local_cli.lock_timeout_secs` for it, so concurrent commands take turns.
+//! - **Encrypted Secrets**: With an `[encryption]` key configured, the secrets of bots are
+//!   encrypted in the file and decrypted on load; see [`StateKey`].
+//! - **Reloading State**: Optionally picks up external edits to the state file (manual edits,
+//!   restored backups) in server mode, validating them before replacing the in-memory state.
+//! - **Shared Storage**: With `[storage] backend = "redis"` or `"s3"`, the state is kept in a
+//!   [store](StateStore) instead of the file, so several API servers, or containers without
+//!   a persistent volume, can share it. Each server [reloads](watch_store) what another one
+//!   saved.
 //! - **Configuration Integration**: The state integrates with [`AppConfig`] to manage runtime
 //!   settings.
 //!
@@ -37,17 +58,48 @@
 //!
 //! ## Future Improvements
 //! - Add database support for storing and querying bots efficiently.
-//! - Implement an event-based state synchronization mechanism for distributed systems.
-use crate::app_config::AppConfig;
+//! - Keep trade histories and logs in the store as well, and count quotas across servers.
+use crate::app_config::{
+    AllocationConfig, AppConfig, KeyCheckConfig, MaintenanceConfig, NotificationConfig,
+    PrecisionConfig, QuotaConfig, RetentionConfig, SloConfig, StorageBackend,
+};
+use crate::bot::auth::Users;
+use crate::bot::compaction::LogFiles;
+use crate::bot::latency::ExchangeStats;
 use crate::bot::model::Bot;
+use crate::bot::portfolio::Portfolios;
+use crate::bot::secrets::{self, StateKey};
+use crate::bot::state::alerts::AlertLog;
+use crate::bot::state::dedup::RecentAlerts;
+use crate::bot::state::events::EventLog;
+use crate::bot::state::history::TradeHistory;
+use crate::bot::state::journal::Journal;
+use crate::bot::state::ledger::Ledger;
+use crate::bot::state::paper::PaperBook;
+use crate::bot::state::quota::TradeCounter;
+use crate::bot::state::ratelimit::RateLimiter;
+use crate::bot::state::store::StateStore;
+use crate::bot::state::templates::Templates;
+use crate::bot::state::Providers;
+use crate::bot::ws::{ServerEvent, ServerEvents};
 use crate::errors::AppError;
+use crate::exchange::prices::PriceCache;
+use crate::exchange::SymbolMap;
+use crate::utils::filelock::{self, FileLock};
+use chrono::{DateTime, Utc};
 use log::info;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::fs::{self, OpenOptions};
-use std::io::ErrorKind;
+use std::fs::{self, File, OpenOptions};
+use std::hash::{DefaultHasher, Hasher};
+use std::io::{self, BufWriter, ErrorKind, Write};
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// First bytes of every zstd frame.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub struct AppState {
@@ -55,6 +107,100 @@ pub struct AppState {
     pub state_file: PathBuf,
     #[serde(default)]
     pub bots: HashMap<String, Bot>,
+    /// What happened to each bot, for timelines.
+    #[serde(default, skip_serializing_if = "EventLog::is_empty")]
+    pub events: EventLog,
+    /// Work accepted but not yet confirmed, kept across restarts.
+    #[serde(default, skip_serializing_if = "Journal::is_empty")]
+    pub journal: Journal,
+    /// Simulated positions of `dry_run` bots.
+    #[serde(default, skip_serializing_if = "PaperBook::is_empty")]
+    pub paper: PaperBook,
+    /// Positions of every bot, moved by each trade recorded in its history.
+    #[serde(default, skip_serializing_if = "Portfolios::is_empty")]
+    pub portfolios: Portfolios,
+    /// Deposits and withdrawals seen on the bots' exchange accounts.
+    #[serde(default, skip_serializing_if = "Ledger::is_empty")]
+    pub ledger: Ledger,
+    /// Alerts listeners accepted, with their outcome, for replays.
+    #[serde(default, skip_serializing_if = "AlertLog::is_empty")]
+    pub alerts: AlertLog,
+    /// Webhook alerts received lately, to recognise repeats.
+    #[serde(default, skip_serializing_if = "RecentAlerts::is_empty")]
+    pub recent_alerts: RecentAlerts,
+    /// Users signing in to the API, with their password hashes.
+    #[serde(default, skip_serializing_if = "Users::is_empty")]
+    pub users: Users,
+    /// Message templates of listeners, by their hash.
+    #[serde(default, skip_serializing_if = "Templates::is_empty")]
+    pub templates: Templates,
+    /// When this state was last written to disk.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub saved_at: Option<DateTime<Utc>>,
+    /// ID and clock sources used by the registry (not persisted).
+    #[serde(skip)]
+    pub providers: Providers,
+    /// Hash of the state file content this process last loaded or saved.
+    #[serde(skip)]
+    pub content_hash: Option<u64>,
+    /// Limits enforced by tenant-scoped registries (from the configuration).
+    #[serde(skip)]
+    pub quotas: QuotaConfig,
+    /// Exchange maintenance windows pausing bots (from the configuration).
+    #[serde(skip)]
+    pub maintenance: MaintenanceConfig,
+    /// Decimal places orders are rounded to (from the configuration).
+    #[serde(skip)]
+    pub precision: PrecisionConfig,
+    /// Capital that percentage budgets of bots are shares of (from the configuration).
+    #[serde(skip)]
+    pub allocation: AllocationConfig,
+    /// Whether the API keys of new bots are checked with their exchange (from the
+    /// configuration).
+    #[serde(skip)]
+    pub key_check: KeyCheckConfig,
+    /// Where alert outcomes are announced (from the configuration).
+    #[serde(skip)]
+    pub notifications: NotificationConfig,
+    /// Latency objective of alerts (from the configuration).
+    #[serde(skip)]
+    pub slo: SloConfig,
+    /// Symbol mappings of bots without their own (from the configuration).
+    #[serde(skip)]
+    pub symbol_map: SymbolMap,
+    /// How long alerts, events, trades and log lines are kept (from the configuration).
+    #[serde(skip)]
+    pub retention: RetentionConfig,
+    /// Logs pruned along with the state (from the configuration).
+    #[serde(skip)]
+    pub log_files: LogFiles,
+    /// Trades counted against `max_trades_per_day`.
+    #[serde(skip)]
+    pub trades: TradeCounter,
+    /// Alerts each listener passed on in the last minute, for its rate limit.
+    #[serde(skip)]
+    pub rate_limits: RateLimiter,
+    /// Latency and errors of the orders sent to each exchange, per bot, since startup.
+    #[serde(skip)]
+    pub exchange_stats: ExchangeStats,
+    /// Write the state file indented (from the configuration).
+    #[serde(skip)]
+    pub pretty: bool,
+    /// Earlier versions of the state file to keep (from the configuration).
+    #[serde(skip)]
+    pub backups: usize,
+    /// Warnings of the last registry change, until its response takes them.
+    #[serde(skip)]
+    pub warnings: Vec<String>,
+    /// Whether saves are left to [`persist_state`], and what is waiting for it.
+    #[serde(skip)]
+    pub persistence: Persistence,
+    /// Key the secrets of bots are encrypted with in the file (from the configuration).
+    #[serde(skip)]
+    pub state_key: Option<Arc<StateKey>>,
+    /// Store keeping the state in place of `state_file` (from the configuration).
+    #[serde(skip)]
+    pub store: Option<StateStore>,
     // #[serde(default)]
     // pub config: AppConfig, // Running configuration
 }
@@ -63,6 +209,37 @@ impl Default for AppState {
     fn default() -> Self {
         AppState {
             bots: HashMap::new(),
+            events: EventLog::default(),
+            journal: Journal::default(),
+            paper: PaperBook::default(),
+            portfolios: Portfolios::default(),
+            ledger: Ledger::default(),
+            alerts: AlertLog::default(),
+            recent_alerts: RecentAlerts::default(),
+            users: Users::default(),
+            templates: Templates::default(),
+            saved_at: None,
+            providers: Providers::default(),
+            content_hash: None,
+            quotas: QuotaConfig::default(),
+            maintenance: MaintenanceConfig::default(),
+            precision: PrecisionConfig::default(),
+            allocation: AllocationConfig::default(),
+            key_check: KeyCheckConfig::default(),
+            notifications: NotificationConfig::default(),
+            slo: SloConfig::default(),
+            symbol_map: SymbolMap::default(),
+            retention: RetentionConfig::default(),
+            log_files: LogFiles::default(),
+            trades: TradeCounter::default(),
+            exchange_stats: ExchangeStats::default(),
+            rate_limits: RateLimiter::default(),
+            pretty: false,
+            backups: 0,
+            warnings: Vec::new(),
+            persistence: Persistence::default(),
+            state_key: None,
+            store: None,
             state_file: PathBuf::from("state.json"), // Provide a default file path            // config: AppConfig::default(),
         }
     }
@@ -70,12 +247,91 @@ impl Default for AppState {
 
 impl AppState {
     /// Loads the application state from a file or creates a new blank file if it doesn't exist.
+    /// With a `[storage]` backend other than the file, the state is loaded from its store
+    /// instead, starting blank if nothing was saved there yet.
     pub fn load(app_config: AppConfig) -> Result<AppState, AppError> {
+        Self::load_held(app_config, None)
+    }
+
+    /// Loads the state like [`load`](Self::load), keeping the state file locked until the
+    /// state is dropped so no other process changes it in between. Waits up to `timeout` for
+    /// other processes to release the lock.
+    pub fn load_locked(app_config: AppConfig, timeout: Duration) -> Result<AppState, AppError> {
+        if app_config.storage.backend != StorageBackend::File {
+            // Stores refuse saves over changes made since the state was read instead
+            return Self::load(app_config);
+        }
+        let state_file = &app_config.api_server.state_file;
+        let lock = (FileLock::wait_exclusive(state_file, timeout))
+            .map_err(|source| AppError::FileWriteError {
+                source,
+                path: filelock::lock_path(state_file),
+            })?
+            .ok_or_else(|| state_locked(state_file))?;
+        Self::load_held(app_config, Some(Arc::new(lock)))
+    }
+
+    fn load_held(app_config: AppConfig, held: Option<Arc<FileLock>>) -> Result<AppState, AppError> {
         // Determine the file path
         let state_file = app_config.clone().api_server.state_file;
+        let store = StateStore::open(&app_config.storage, &state_file)?;
+        let _lock = match (&store, &held) {
+            (None, None) => Some(lock_state_file(&state_file)?),
+            _ => None,
+        };
+
+        let raw_content = match &store {
+            Some(store) => (store.read())
+                .map_err(|e| store_error(store, e))?
+                .unwrap_or_else(|| b"{}".to_vec()),
+            None => Self::read_state_file(&state_file)?,
+        };
+
+        // Deserialize the JSON content into `AppState`
+        let mut state: AppState = serde_json::from_slice(&decode(&state_file, &raw_content)?)
+            .map_err(AppError::JsonParseError)?;
+        let state_key = StateKey::from_config(&app_config.encryption)
+            .map_err(AppError::ConfigError)?
+            .map(Arc::new);
+        unseal_bots(&mut state.bots, state_key.as_deref(), &state_file)?;
+        state.state_key = state_key;
+        state.templates.sync(&mut state.bots);
+        let history = TradeHistory::for_state_file(&state_file);
+        (state.portfolios.load_missing(&history, state.bots.keys())).map_err(|e| {
+            AppError::InternalServerError(format!("Failed to read trade history: {}", e))
+        })?;
+
+        state.state_file = state_file.clone();
+        state.content_hash = Some(content_hash(&raw_content));
+        state.quotas = app_config.quotas.clone();
+        state.maintenance = app_config.maintenance.clone();
+        state.precision = app_config.precision.clone();
+        state.allocation = app_config.allocation.clone();
+        state.key_check = app_config.key_check.clone();
+        state.notifications = app_config.notifications.clone();
+        state.slo = app_config.slo.clone();
+        state.symbol_map = app_config.symbol_map.clone();
+        state.retention = app_config.retention.clone();
+        state.log_files = LogFiles::from_config(&app_config);
+        state.pretty = app_config.api_server.pretty_state_file;
+        state.backups = app_config.api_server.state_backups;
+
+        // // Update the loaded state with `AppConfig`
+        // state.config = app_config;
+
+        match &store {
+            Some(store) => info!("State loaded successfully from: {}", store.location()),
+            None => info!("State loaded successfully from: {:?}", state_file),
+        }
+        state.store = store;
+        state.persistence.held = held;
+        Ok(state)
+    }
 
+    /// The content of the state file, created blank if missing and checked to be writable.
+    fn read_state_file(state_file: &Path) -> Result<Vec<u8>, AppError> {
         // Attempt to read the state file, or create it if it doesn't exist
-        let state_content = match fs::read_to_string(&state_file) {
+        let raw_content = match fs::read(state_file) {
             Ok(content) => content,
             Err(e) if e.kind() == ErrorKind::NotFound => {
                 info!(
@@ -83,45 +339,181 @@ impl AppState {
                     state_file
                 );
                 // Create a new blank file and write "{}" to it
-                fs::write(&state_file, b"{}").map_err(|e| AppError::FileWriteError {
+                fs::write(state_file, b"{}").map_err(|e| AppError::FileWriteError {
                     source: e,
-                    path: state_file.clone(),
+                    path: state_file.to_path_buf(),
                 })?;
-                "{}".to_string() // Return an empty JSON object as content
+                b"{}".to_vec() // Return an empty JSON object as content
             }
             Err(e) => {
                 return Err(AppError::FileReadError {
                     source: e,
-                    path: state_file.clone(),
+                    path: state_file.to_path_buf(),
                 });
             }
         };
 
         // Test writeability of the file
-        if OpenOptions::new().write(true).open(&state_file).is_err() {
-            return Err(AppError::FileWriteError {
-                source: std::io::Error::new(ErrorKind::PermissionDenied, "File not writable"),
-                path: state_file.clone(),
-            });
+        Self::ensure_writable(state_file)?;
+        Ok(raw_content)
+    }
+
+    /// Where the state is kept, for messages: the state file, or its store.
+    pub fn location(&self) -> String {
+        match &self.store {
+            Some(store) => store.location().to_string(),
+            None => self.state_file.display().to_string(),
         }
+    }
 
-        // println!("{}", &state_content);
+    /// Checks that the state file can be opened for writing.
+    pub fn ensure_writable(state_file: &Path) -> Result<(), AppError> {
+        OpenOptions::new()
+            .write(true)
+            .open(state_file)
+            .map(|_| ())
+            .map_err(|_| AppError::FileWriteError {
+                source: std::io::Error::new(ErrorKind::PermissionDenied, "File not writable"),
+                path: state_file.to_path_buf(),
+            })
+    }
 
-        // Deserialize the JSON content into `AppState`
-        let mut state: AppState =
-            serde_json::from_str(&state_content).map_err(AppError::JsonParseError)?;
+    /// Replaces the ID and clock providers, e.g. with deterministic ones for tests.
+    #[allow(dead_code)]
+    pub fn with_providers(mut self, providers: Providers) -> Self {
+        self.providers = providers;
+        self
+    }
 
-        state.state_file = state_file.clone();
+    /// Reloads the state file if it was changed by someone other than this process,
+    /// e.g. a manual edit or a restored backup, or the state in its store if another server
+    /// saved it.
+    ///
+    /// Returns `Ok(true)` when the in-memory bots were replaced. An unreadable or invalid
+    /// file is reported as an error and the in-memory state is left untouched.
+    pub fn reload_if_changed(&mut self) -> Result<bool, AppError> {
+        let content = match &self.store {
+            Some(store) => match store.read().map_err(|e| store_error(store, e))? {
+                Some(content) => content,
+                None => return Ok(false),
+            },
+            None => {
+                let read_error = |source| AppError::FileReadError {
+                    source,
+                    path: filelock::lock_path(&self.state_file),
+                };
+                let Some(_lock) = FileLock::shared(&self.state_file).map_err(read_error)? else {
+                    // Being saved; look again next time
+                    return Ok(false);
+                };
+                fs::read(&self.state_file).map_err(|e| AppError::FileReadError {
+                    source: e,
+                    path: self.state_file.clone(),
+                })?
+            }
+        };
+        let hash = content_hash(&content);
+        if self.content_hash == Some(hash) || self.persistence.pending == Some(hash) {
+            return Ok(false);
+        }
 
-        // // Update the loaded state with `AppConfig`
-        // state.config = app_config;
+        let mut reloaded: AppState = serde_json::from_slice(&decode(&self.state_file, &content)?)
+            .map_err(AppError::JsonParseError)?;
+        reloaded.validate()?;
+        unseal_bots(
+            &mut reloaded.bots,
+            self.state_key.as_deref(),
+            &self.state_file,
+        )?;
+        if self.persistence.dirty {
+            log::warn!(
+                "Discarding changes not yet saved to {:?} in favour of the modified file",
+                self.state_file
+            );
+            self.persistence.dirty = false;
+        }
 
-        info!("State loaded successfully from: {:?}", state_file);
-        Ok(state)
+        self.bots = reloaded.bots;
+        // The listeners reloaded refer to the templates written with them, and hand edits may
+        // hold theirs inline
+        self.templates = reloaded.templates;
+        self.templates.sync(&mut self.bots);
+        self.events = reloaded.events;
+        self.journal = reloaded.journal;
+        self.paper = reloaded.paper;
+        // Building portfolios from the trade histories would read them under the lock
+        reloaded
+            .portfolios
+            .adopt(&self.portfolios, self.bots.keys());
+        self.portfolios = reloaded.portfolios;
+        self.ledger = reloaded.ledger;
+        self.alerts = reloaded.alerts;
+        self.recent_alerts = reloaded.recent_alerts;
+        self.users = reloaded.users;
+        self.content_hash = Some(hash);
+        match &self.store {
+            Some(store) => info!("State reloaded from {}", store.location()),
+            None => info!(
+                "State reloaded from externally modified file: {:?}",
+                self.state_file
+            ),
+        }
+        Ok(true)
+    }
+
+    /// Refuses to save over a state file that changed since this process last loaded or
+    /// saved it, so manual edits are never silently lost.
+    fn check_for_conflict(&self) -> Result<(), AppError> {
+        let Some(expected) = self.content_hash else {
+            return Ok(());
+        };
+        if self.store.is_some() {
+            // Saves to the store check as they write
+            return Ok(());
+        }
+        match fs::read(&self.state_file) {
+            Ok(content)
+                if ![Some(expected), self.persistence.pending]
+                    .contains(&Some(content_hash(&content))) =>
+            {
+                Err(AppError::StateConflict(self.state_file.clone()))
+            }
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(AppError::FileReadError {
+                source: e,
+                path: self.state_file.clone(),
+            }),
+        }
+    }
+
+    /// Checks structural invariants that a hand-edited file could break.
+    pub fn validate(&self) -> Result<(), AppError> {
+        for (key, bot) in &self.bots {
+            if key.trim().is_empty() {
+                return Err(AppError::InvalidState("Bot with an empty ID.".to_string()));
+            }
+            if key != &bot.bot_id {
+                return Err(AppError::InvalidState(format!(
+                    "Bot stored under '{}' has bot_id '{}'.",
+                    key, bot.bot_id
+                )));
+            }
+            if bot.listeners.keys().any(|id| id.trim().is_empty()) {
+                return Err(AppError::InvalidState(format!(
+                    "Bot '{}' has a listener with an empty ID.",
+                    key
+                )));
+            }
+        }
+        Ok(())
     }
 
     /// Saves the current state to a JSON file.
-    pub fn save<P: AsRef<Path>>(&self, file_path: Option<P>) -> Result<(), AppError> {
+    ///
+    /// While saves are [deferred](Persistence::deferred), saving to the state file only marks
+    /// the state dirty for [`persist_state`] to write.
+    pub fn save<P: AsRef<Path>>(&mut self, file_path: Option<P>) -> Result<(), AppError> {
         let state_file = file_path
             .map(|p| p.as_ref().to_path_buf())
             .or_else(|| Some(self.state_file.clone()))
@@ -129,132 +521,1098 @@ impl AppState {
                 source: std::io::Error::new(ErrorKind::InvalidInput, "No file path provided"),
                 path: PathBuf::from("unknown"),
             })?;
+        self.templates.sync(&mut self.bots);
+        if self.persistence.deferred && state_file == self.state_file {
+            self.persistence.dirty = true;
+            return Ok(());
+        }
+        if let Some(store) = self.store.clone().filter(|_| state_file == self.state_file) {
+            return self.save_to_store(&store);
+        }
 
-        // Serialize the `AppState` to JSON
-        let state_json = serde_json::to_string_pretty(self).map_err(AppError::JsonParseError)?;
+        // Never clobber changes someone else made to our own state file
+        let held = self.persistence.held.is_some() && state_file == self.state_file;
+        let _lock = if held {
+            None
+        } else {
+            Some(lock_state_file(&state_file)?)
+        };
+        if state_file == self.state_file {
+            self.check_for_conflict()?;
+        }
 
-        // Write the serialized state to the file
-        fs::write(&state_file, state_json).map_err(|e| AppError::FileWriteError {
-            source: e,
-            path: state_file.clone(),
-        })?;
+        // Stream the `AppState` to the file as JSON
+        let previous_save = self.saved_at.replace(self.providers.clock.now());
+        let written = self.write_to(&state_file);
+        let hash = match written {
+            Ok(hash) => hash,
+            Err(e) => {
+                self.saved_at = previous_save;
+                return Err(e);
+            }
+        };
+
+        // Remember what we wrote so our own saves are not mistaken for external edits
+        if state_file == self.state_file {
+            self.content_hash = Some(hash);
+            self.persistence.dirty = false;
+            self.persistence.pending = None;
+        }
 
         info!("State saved successfully to file: {:?}", state_file);
         Ok(())
     }
 }
 
-//#[cfg(test)]
-//mod tests {
-//    //use super::*;
-//    use crate::app_config::{ApiServerConfig, AppConfig};
-//    use crate::app_state::AppState;
-//    use crate::bot::model::Bot;
-//    use crate::errors::ServerError;
-//    use std::fs;
-//    use std::io::ErrorKind;
-//    use std::path::Path;
-//    use tempfile::tempdir;
-//
-//    fn create_test_config(state_file: &Path) -> AppConfig {
-//        AppConfig {
-//            api_server: ApiServerConfig {
-//                port: 7762,
-//                bind_address: "127.0.0.1".to_string(),
-//                state_file_path: state_file.to_path_buf(),
-//            },
-//            ..AppConfig::default()
-//        }
-//    }
-//
-//    #[test]
-//    fn test_load_creates_blank_state_file_if_missing() {
-//        let temp_dir = tempdir().unwrap();
-//        let state_file = temp_dir.path().join("missing_state.json");
-//        let config = create_test_config(&state_file);
-//
-//        // Ensure the state file does not exist
-//        assert!(!state_file.exists());
-//
-//        // Load AppState, which should create the state file
-//        let app_state = AppState::load(config.clone()).unwrap();
-//
-//        // Check if the file was created
-//        assert!(state_file.exists());
-//
-//        // Check if the file contains an empty JSON object
-//        let content = fs::read_to_string(&state_file).unwrap();
-//        assert_eq!(content, "{}");
-//
-//        // Check if the loaded state is the default state
-//        assert_eq!(app_state, AppState::default());
-//    }
-//
-//    #[test]
-//    fn test_load_existing_state_file() {
-//        let temp_dir = tempdir().unwrap();
-//        let state_file = temp_dir.path().join("existing_state.json");
-//        let config = create_test_config(&state_file);
-//
-//        // Create a sample state file
-//        let sample_state = r#"{ "bots": { "bot1": { "name": "Bot 1", "exchange": "Test" } } }"#;
-//        fs::write(&state_file, sample_state).unwrap();
-//
-//        // Load AppState
-//        let app_state = AppState::load(config.clone()).unwrap();
-//
-//        // Check if the loaded state matches the file content
-//        assert!(app_state.bots.contains_key("bot1"));
-//    }
-//
-//    #[test]
-//    fn test_save_state_to_file() {
-//        let temp_dir = tempdir().unwrap();
-//        let state_file = temp_dir.path().join("saved_state.json");
-//        //let config = create_test_config(&state_file);
-//
-//        // Create an AppState
-//        let mut app_state = AppState::default();
-//        app_state.bots.insert(
-//            "bot1".to_string(),
-//            Bot {
-//                name: "Test Bot".to_string(),
-//                exchange: "Test Exchange".to_string(),
-//                ..Default::default()
-//            },
-//        );
-//
-//        // Save the state
-//        app_state.save::<&Path>(None).unwrap();
-//
-//        // Check if the state file exists
-//        assert!(state_file.exists());
-//
-//        // Check if the content matches the saved state
-//        let content = fs::read_to_string(&state_file).unwrap();
-//        assert!(content.contains("Test Bot"));
-//    }
-//
-//    #[test]
-//    fn test_load_fails_if_file_not_writable() {
-//        let temp_dir = tempdir().unwrap();
-//        let state_file = temp_dir.path().join("unwritable_state.json");
-//        let config = create_test_config(&state_file);
-//
-//        // Create the file and make it read-only
-//        fs::write(&state_file, "{}").unwrap();
-//        let mut permissions = fs::metadata(&state_file).unwrap().permissions();
-//        permissions.set_readonly(true);
-//        fs::set_permissions(&state_file, permissions).unwrap();
-//
-//        // Attempt to load the state, which should fail due to write permissions
-//        let result = AppState::load(config);
-//        assert!(result.is_err());
-//        if let Err(ServerError::FileWriteError { source, path }) = result {
-//            assert_eq!(path, state_file);
-//            assert_eq!(source.kind(), ErrorKind::PermissionDenied);
-//        } else {
-//            panic!("Expected FileWriteError");
-//        }
-//    }
-//}
+impl AppState {
+    /// Stops deferring saves and writes the changes they left in a single save, if any.
+    pub fn save_deferred(&mut self) -> Result<(), AppError> {
+        self.persistence.deferred = false;
+        if self.persistence.dirty {
+            self.save(None::<&Path>)?;
+        }
+        Ok(())
+    }
+
+    /// Save the state to `store`, unless another server saved it since this one last loaded
+    /// or saved it.
+    fn save_to_store(&mut self, store: &StateStore) -> Result<(), AppError> {
+        let previous_save = self.saved_at.replace(self.providers.clock.now());
+        let written = self.snapshot().and_then(|(content, hash)| {
+            let expected = [self.content_hash, self.persistence.pending];
+            match store.write(&content, hash, |current| {
+                expected.contains(&Some(content_hash(current)))
+            }) {
+                Ok(true) => Ok(hash),
+                Ok(false) => Err(AppError::StoreConflict(store.location().to_string())),
+                Err(e) => Err(store_error(store, e)),
+            }
+        });
+        let hash = match written {
+            Ok(hash) => hash,
+            Err(e) => {
+                self.saved_at = previous_save;
+                return Err(e);
+            }
+        };
+        self.content_hash = Some(hash);
+        self.persistence.dirty = false;
+        self.persistence.pending = None;
+        info!("State saved successfully to {}", store.location());
+        Ok(())
+    }
+
+    /// Serialize the state into `path`, returning the content hash of what was written.
+    ///
+    /// The state is written to `path.tmp` and then renamed over `path`, so a crash mid-write
+    /// leaves the previous state intact. The replaced file becomes the newest backup.
+    fn write_to(&self, path: &Path) -> Result<u64, AppError> {
+        let write_error = |source: io::Error| AppError::FileWriteError {
+            source,
+            path: path.to_path_buf(),
+        };
+        let temp_path = with_suffix(path, "tmp");
+        let hash = match self.write_temp(&temp_path, is_compressed(path)) {
+            Ok(hash) => hash,
+            Err(e) => {
+                let _ = fs::remove_file(&temp_path);
+                return Err(e);
+            }
+        };
+        replace_file(path, &temp_path, self.backups).map_err(write_error)?;
+        Ok(hash)
+    }
+
+    /// The state serialized as [`write_to`](Self::write_to) writes it to the state file,
+    /// with its content hash.
+    fn snapshot(&self) -> Result<(Vec<u8>, u64), AppError> {
+        let mut out = HashingWriter::new(Vec::new());
+        if is_compressed(&self.state_file) {
+            self.write_compressed(&mut out)?;
+        } else {
+            self.write_json(&mut out)?;
+        }
+        let hash = out.finish();
+        Ok((out.inner, hash))
+    }
+
+    /// The unsaved changes, serialized for [`persist_state`] to write without holding the
+    /// lock; `None` if there are none.
+    fn take_snapshot(&mut self) -> Result<Option<Snapshot>, AppError> {
+        if !self.persistence.dirty {
+            return Ok(None);
+        }
+        // Reading the file here would hold the lock; the write checks it under the file lock
+        let previous_save = self.saved_at.replace(self.providers.clock.now());
+        let (content, hash) = match self.snapshot() {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                self.saved_at = previous_save;
+                return Err(e);
+            }
+        };
+        self.persistence.dirty = false;
+        Ok(Some(Snapshot {
+            path: self.state_file.clone(),
+            content,
+            hash,
+            expected: self.content_hash,
+            pending: self.persistence.pending.replace(hash),
+            backups: self.backups,
+            previous_save,
+            store: self.store.clone(),
+        }))
+    }
+
+    /// Record the outcome of writing `snapshot`; a failed write leaves the changes dirty for
+    /// the next attempt.
+    fn finish_snapshot(&mut self, snapshot: Snapshot, written: io::Result<()>) {
+        if self.persistence.pending != Some(snapshot.hash) {
+            // Saved again, or reloaded, in the meantime
+            return;
+        }
+        self.persistence.pending = None;
+        match written {
+            Ok(()) => {
+                self.content_hash = Some(snapshot.hash);
+                log::debug!("State saved in the background to: {:?}", snapshot.path);
+            }
+            Err(e) => {
+                self.saved_at = snapshot.previous_save;
+                self.persistence.dirty = true;
+                log::error!("Failed to save state to {:?}: {}", snapshot.path, e);
+            }
+        }
+    }
+
+    /// Write the state to the new file `path` and flush it to disk.
+    fn write_temp(&self, path: &Path, compressed: bool) -> Result<u64, AppError> {
+        let write_error = |source: io::Error| AppError::FileWriteError {
+            source,
+            path: path.to_path_buf(),
+        };
+        let file = File::create(path).map_err(write_error)?;
+        let mut out = HashingWriter::new(file);
+
+        if compressed {
+            self.write_compressed(&mut out)?;
+        } else {
+            let mut buffered = BufWriter::new(&mut out);
+            self.write_json(&mut buffered)?;
+            buffered.flush().map_err(write_error)?;
+        }
+        out.inner.sync_all().map_err(write_error)?;
+        Ok(out.finish())
+    }
+
+    fn write_json<W: Write>(&self, out: W) -> Result<(), AppError> {
+        let sealed;
+        let state = match &self.state_key {
+            Some(key) => {
+                sealed = AppState {
+                    bots: (self.bots.iter())
+                        .map(|(id, bot)| (id.clone(), key.seal_bot(bot)))
+                        .collect(),
+                    ..self.clone()
+                };
+                &sealed
+            }
+            None => self,
+        };
+        let result = if self.pretty {
+            serde_json::to_writer_pretty(out, state)
+        } else {
+            serde_json::to_writer(out, state)
+        };
+        result.map_err(|e| match e.io_error_kind() {
+            Some(kind) => AppError::FileWriteError {
+                source: io::Error::new(kind, e.to_string()),
+                path: self.state_file.clone(),
+            },
+            None => AppError::JsonParseError(e),
+        })
+    }
+
+    #[cfg(feature = "state-zstd")]
+    fn write_compressed<W: Write>(&self, out: W) -> Result<(), AppError> {
+        let write_error = |source: io::Error| AppError::FileWriteError {
+            source,
+            path: self.state_file.clone(),
+        };
+        let mut encoder =
+            zstd::Encoder::new(out, zstd::DEFAULT_COMPRESSION_LEVEL).map_err(write_error)?;
+        self.write_json(&mut encoder)?;
+        encoder.finish().map_err(write_error)?;
+        Ok(())
+    }
+
+    #[cfg(not(feature = "state-zstd"))]
+    fn write_compressed<W: Write>(&self, _out: W) -> Result<(), AppError> {
+        Err(AppError::ConfigError(
+            "Compressed state files need xtrade built with the `state-zstd` feature.".to_string(),
+        ))
+    }
+}
+
+/// Whether state written to `path` is zstd-compressed.
+fn is_compressed(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "zst")
+}
+
+/// The JSON in state file `content`, decompressing it if it is a zstd frame.
+fn decode<'a>(path: &Path, content: &'a [u8]) -> Result<std::borrow::Cow<'a, [u8]>, AppError> {
+    if !content.starts_with(&ZSTD_MAGIC) {
+        return Ok(content.into());
+    }
+    #[cfg(feature = "state-zstd")]
+    {
+        zstd::decode_all(content)
+            .map(Into::into)
+            .map_err(|e| AppError::FileReadError {
+                source: e,
+                path: path.to_path_buf(),
+            })
+    }
+    #[cfg(not(feature = "state-zstd"))]
+    {
+        Err(AppError::ConfigError(format!(
+            "{:?} is compressed; xtrade was built without the `state-zstd` feature.",
+            path
+        )))
+    }
+}
+
+/// Decrypt the secrets of `bots`, read from `path`, with `key`.
+fn unseal_bots(
+    bots: &mut HashMap<String, Bot>,
+    key: Option<&StateKey>,
+    path: &Path,
+) -> Result<(), AppError> {
+    for (id, bot) in bots {
+        let outcome = match key {
+            Some(key) => key.unseal_bot(bot),
+            None if secrets::count_sealed(bot).0 > 0 => {
+                Err("its secrets are encrypted; configure encryption.key".to_string())
+            }
+            None => Ok(()),
+        };
+        outcome.map_err(|e| AppError::ConfigError(format!("Bot '{}' in {:?}: {}", id, path, e)))?;
+    }
+    Ok(())
+}
+
+/// [`AppError::StorageError`] for `e`, failing to access `store`.
+fn store_error(store: &StateStore, e: io::Error) -> AppError {
+    AppError::StorageError(store.location().to_string(), e.to_string())
+}
+
+/// Stable hash of state file content, used to tell our own writes from external edits.
+fn content_hash(content: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(content);
+    hasher.finish()
+}
+
+/// Bytes `value` takes up as compact JSON, counted without buffering it.
+pub fn serialized_len<T: Serialize + ?Sized>(value: &T) -> u64 {
+    let mut counter = ByteCounter(0);
+    match serde_json::to_writer(&mut counter, value) {
+        Ok(()) => counter.0,
+        Err(_) => 0,
+    }
+}
+
+struct ByteCounter(u64);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Hashes everything written through it, so streamed saves know their [`content_hash`].
+struct HashingWriter<W> {
+    inner: W,
+    hasher: DefaultHasher,
+}
+
+impl<W: Write> HashingWriter<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            hasher: DefaultHasher::new(),
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.hasher.finish()
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.write(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Lock `path` for a load or save; fails with [`AppError::StateLocked`] if another process
+/// holds it.
+fn lock_state_file(path: &Path) -> Result<FileLock, AppError> {
+    FileLock::exclusive(path)
+        .map_err(|source| AppError::FileWriteError {
+            source,
+            path: filelock::lock_path(path),
+        })?
+        .ok_or_else(|| state_locked(path))
+}
+
+/// The error for finding `path` locked by another process.
+fn state_locked(path: &Path) -> AppError {
+    AppError::StateLocked {
+        path: path.to_path_buf(),
+        holder: match filelock::holder(path) {
+            Some(pid) => format!("PID {}", pid),
+            None => "another xtrade process".to_string(),
+        },
+    }
+}
+
+/// Write `snapshot` under the state file's lock, unless another process holds it or changed
+/// the file since the snapshot was taken; to the store if the state is kept in one.
+fn write_snapshot(snapshot: &Snapshot) -> io::Result<()> {
+    if let Some(store) = &snapshot.store {
+        let unchanged = |current: &[u8]| {
+            snapshot.expected.is_none() || snapshot.replaces(content_hash(current))
+        };
+        if !store.write(&snapshot.content, snapshot.hash, unchanged)? {
+            return Err(io::Error::other(
+                "the state was saved by another server in the meantime",
+            ));
+        }
+        return Ok(());
+    }
+    let Some(_lock) = FileLock::exclusive(&snapshot.path)? else {
+        return Err(io::Error::new(
+            ErrorKind::WouldBlock,
+            "the state file is in use by another process",
+        ));
+    };
+    let current = match fs::read(&snapshot.path) {
+        Ok(content) => Some(content_hash(&content)),
+        Err(e) if e.kind() == ErrorKind::NotFound => None,
+        Err(e) => return Err(e),
+    };
+    if snapshot.expected.is_some() && current.is_some_and(|hash| !snapshot.replaces(hash)) {
+        return Err(io::Error::other(
+            "the state file was modified outside xtrade",
+        ));
+    }
+    write_file(&snapshot.path, &snapshot.content, snapshot.backups)
+}
+
+/// Write `content` to `path` the way [`AppState::save`] does: through a temporary file,
+/// keeping `backups` earlier versions.
+fn write_file(path: &Path, content: &[u8], backups: usize) -> io::Result<()> {
+    let temp_path = with_suffix(path, "tmp");
+    let written = File::create(&temp_path).and_then(|mut file| {
+        file.write_all(content)?;
+        file.sync_all()
+    });
+    if let Err(e) = written {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
+    replace_file(path, &temp_path, backups)
+}
+
+/// Rename `temp_path` over `path`, which becomes the newest of `backups` backups.
+fn replace_file(path: &Path, temp_path: &Path, backups: usize) -> io::Result<()> {
+    if let Ok(metadata) = fs::metadata(path) {
+        // Keep the permissions of the file being replaced, e.g. owner-only
+        let _ = fs::set_permissions(temp_path, metadata.permissions());
+    }
+    if let Err(e) = rotate_backups(path, backups) {
+        log::warn!("Failed to back up {:?}: {}", path, e);
+    }
+    fs::rename(temp_path, path)
+}
+
+/// `path` with `.suffix` appended to its file name.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// The `n`th newest backup of the state file `path`, as `state.json.1` for the newest.
+pub fn backup_path(path: &Path, n: usize) -> PathBuf {
+    with_suffix(path, &n.to_string())
+}
+
+/// Make the current `path` its newest backup, shifting the older ones and dropping those
+/// beyond `keep`.
+fn rotate_backups(path: &Path, keep: usize) -> io::Result<()> {
+    if keep == 0 || !path.exists() {
+        return Ok(());
+    }
+    for n in (1..keep).rev() {
+        let older = backup_path(path, n);
+        if older.exists() {
+            fs::rename(&older, backup_path(path, n + 1))?;
+        }
+    }
+    let newest = backup_path(path, 1);
+    match fs::remove_file(&newest) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    // A hard link leaves the file in place until the rename replaces it
+    fs::hard_link(path, &newest).or_else(|_| fs::copy(path, &newest).map(|_| ()))
+}
+
+/// Whether saves of the state file are deferred to [`persist_state`], and what they left to do.
+#[derive(Clone, Debug, Default)]
+pub struct Persistence {
+    /// Saves to the state file only mark the state dirty; set in server mode, and while
+    /// offline commands run so they are saved at once, or not at all.
+    pub deferred: bool,
+    /// Changes not yet handed to the writer.
+    dirty: bool,
+    /// Hash of a snapshot being written, so its file is not taken for an external edit.
+    pending: Option<u64>,
+    /// Held while a snapshot is written, so [`flush`] can wait for it.
+    writer: Arc<Mutex<()>>,
+    /// Lock on the state file held since it was [loaded](AppState::load_locked).
+    held: Option<Arc<FileLock>>,
+}
+
+impl Persistence {
+    /// Whether changes are waiting to be written.
+    #[allow(dead_code)]
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+}
+
+impl PartialEq for Persistence {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+/// State serialized under the lock, to be written to `path` outside it.
+struct Snapshot {
+    path: PathBuf,
+    content: Vec<u8>,
+    hash: u64,
+    /// Hash of the file the snapshot replaces
+    expected: Option<u64>,
+    /// Hash of a snapshot that may still be being written, which it replaces as well
+    pending: Option<u64>,
+    backups: usize,
+    previous_save: Option<DateTime<Utc>>,
+    /// Store to write to instead of `path`
+    store: Option<StateStore>,
+}
+
+impl Snapshot {
+    /// Whether the snapshot may replace content hashed `hash`.
+    fn replaces(&self, hash: u64) -> bool {
+        [self.expected, self.pending].contains(&Some(hash))
+    }
+}
+
+/// Writes the changes saves left dirty at most once per `interval`.
+pub async fn persist_state(app_state: Arc<Mutex<AppState>>, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        let (snapshot, writer) = {
+            let Ok(mut state) = app_state.lock() else {
+                log::error!("Failed to acquire lock on AppState; stopping state persistence.");
+                return;
+            };
+            match state.take_snapshot() {
+                Ok(Some(snapshot)) => (snapshot, state.persistence.writer.clone()),
+                Ok(None) => continue,
+                Err(e) => {
+                    log::error!("Changes not saved to {:?}: {}", state.state_file, e);
+                    continue;
+                }
+            }
+        };
+        let written = tokio::task::spawn_blocking(move || {
+            let _writing = writer.lock();
+            let written = write_snapshot(&snapshot);
+            (snapshot, written)
+        })
+        .await;
+        let Ok((snapshot, written)) = written else {
+            log::error!("State writer failed; stopping state persistence.");
+            return;
+        };
+        if let Ok(mut state) = app_state.lock() {
+            state.finish_snapshot(snapshot, written);
+        }
+    }
+}
+
+/// Stops deferring saves and writes the changes still waiting, e.g. at shutdown.
+pub fn flush(app_state: &Mutex<AppState>) -> Result<(), AppError> {
+    let writer = match app_state.lock() {
+        Ok(state) => state.persistence.writer.clone(),
+        Err(_) => return Err(AppError::LockError),
+    };
+    // Let a write in progress finish first
+    let _writing = writer.lock();
+    let mut state = app_state.lock().map_err(|_| AppError::LockError)?;
+    state.save_deferred()
+}
+
+/// Periodically reloads the state file when it was modified outside this process, telling
+/// subscribed WebSocket sessions through `events` and forgetting the cached `prices`.
+pub async fn watch_state_file(
+    app_state: Arc<Mutex<AppState>>,
+    interval: Duration,
+    events: ServerEvents,
+    prices: Arc<PriceCache>,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        if !reload_and_announce(&app_state, &events, &prices) {
+            log::error!("Failed to acquire lock on AppState; stopping state file watcher.");
+            return;
+        }
+    }
+}
+
+/// Reloads the state whenever another server saves it to `store`, telling subscribed
+/// WebSocket sessions through `events` and forgetting the cached `prices`. Runs on a thread
+/// of its own, subscribing again whenever watching it fails.
+pub fn watch_store(
+    app_state: Arc<Mutex<AppState>>,
+    store: StateStore,
+    events: ServerEvents,
+    prices: Arc<PriceCache>,
+) {
+    std::thread::spawn(move || loop {
+        match store.watch(|| reload_and_announce(&app_state, &events, &prices)) {
+            Ok(()) => {
+                log::error!("Failed to acquire lock on AppState; stopping state watcher.");
+                return;
+            }
+            Err(e) => log::warn!(
+                "Not notified of changes to {} until subscribed again: {}",
+                store.location(),
+                e
+            ),
+        }
+        std::thread::sleep(Duration::from_secs(1));
+    });
+}
+
+/// Reload the state if someone else changed it, publishing [`ServerEvent::StateReloaded`]
+/// and forgetting the cached `prices` if so; `false` if the state cannot be locked.
+///
+/// Prices were recorded from the alerts and fills of bots as this process knew them, which
+/// whoever changed the state may have pointed at other symbols or exchanges.
+fn reload_and_announce(
+    app_state: &Mutex<AppState>,
+    events: &ServerEvents,
+    prices: &PriceCache,
+) -> bool {
+    let Ok(mut state) = app_state.lock() else {
+        return false;
+    };
+    match state.reload_if_changed() {
+        Ok(true) => {
+            prices.clear();
+            events.publish(ServerEvent::StateReloaded {
+                reloaded_at: Utc::now(),
+            })
+        }
+        Ok(false) => {}
+        Err(e) => log::warn!(
+            "State in {} changed but was not reloaded; in-memory state diverges: {}",
+            state.location(),
+            e
+        ),
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn create_test_config(state_file: &Path) -> AppConfig {
+        let mut config = AppConfig::default();
+        config.api_server.state_file = state_file.to_path_buf();
+        config
+    }
+
+    fn sample_bot(bot_id: &str) -> Bot {
+        Bot {
+            bot_id: bot_id.to_string(),
+            name: "Test Bot".to_string(),
+            exchange: "Test Exchange".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_load_creates_blank_state_file_if_missing() {
+        let temp_dir = tempdir().unwrap();
+        let state_file = temp_dir.path().join("missing_state.json");
+        let config = create_test_config(&state_file);
+
+        // Ensure the state file does not exist
+        assert!(!state_file.exists());
+
+        // Load AppState, which should create the state file
+        let app_state = AppState::load(config).unwrap();
+
+        // Check if the file was created and contains an empty JSON object
+        assert!(state_file.exists());
+        assert_eq!(fs::read_to_string(&state_file).unwrap(), "{}");
+        assert!(app_state.bots.is_empty());
+    }
+
+    #[test]
+    fn test_save_and_load_state_file() {
+        let temp_dir = tempdir().unwrap();
+        let state_file = temp_dir.path().join("saved_state.json");
+        let config = create_test_config(&state_file);
+
+        let mut app_state = AppState::load(config.clone()).unwrap();
+        app_state
+            .bots
+            .insert("bot1".to_string(), sample_bot("bot1"));
+        app_state.save::<&Path>(None).unwrap();
+
+        let content = fs::read_to_string(&state_file).unwrap();
+        assert!(content.contains("Test Bot"));
+        assert_eq!(AppState::load(config).unwrap(), app_state);
+    }
+
+    #[test]
+    fn test_reload_ignores_own_writes() {
+        let temp_dir = tempdir().unwrap();
+        let state_file = temp_dir.path().join("state.json");
+        let mut app_state = AppState::load(create_test_config(&state_file)).unwrap();
+
+        app_state
+            .bots
+            .insert("bot1".to_string(), sample_bot("bot1"));
+        app_state.save::<&Path>(None).unwrap();
+
+        assert!(!app_state.reload_if_changed().unwrap());
+        assert!(app_state.bots.contains_key("bot1"));
+    }
+
+    #[test]
+    fn test_reload_picks_up_external_edit() {
+        let temp_dir = tempdir().unwrap();
+        let state_file = temp_dir.path().join("state.json");
+        let mut app_state = AppState::load(create_test_config(&state_file)).unwrap();
+
+        // Another process writes a bot into the file
+        let mut external = AppState::default();
+        external.bots.insert("bot2".to_string(), sample_bot("bot2"));
+        fs::write(&state_file, serde_json::to_string(&external).unwrap()).unwrap();
+
+        assert!(app_state.reload_if_changed().unwrap());
+        assert!(app_state.bots.contains_key("bot2"));
+        assert_eq!(app_state.state_file, state_file);
+    }
+
+    #[test]
+    fn test_reload_keeps_the_templates_of_the_file() {
+        let temp_dir = tempdir().unwrap();
+        let state_file = temp_dir.path().join("state.json");
+        let mut app_state = AppState::load(create_test_config(&state_file)).unwrap();
+
+        // Another server adds a listener whose template only the file holds, and a hand edit
+        // adds one with its template inline
+        let (kept, inline) = ("{\"action\":\"buy\"}", "{\"action\":\"sell\"}");
+        let mut external = AppState::default();
+        let mut bot = sample_bot("bot2");
+        let listener = |msg: &str, msg_sha256| crate::bot::model::Listener {
+            msg: msg.to_string(),
+            msg_sha256,
+            ..Default::default()
+        };
+        let address = external.templates.insert(kept);
+        bot.listeners
+            .insert("kept".to_string(), listener("", address));
+        bot.listeners
+            .insert("inline".to_string(), listener(inline, None));
+        external.bots.insert("bot2".to_string(), bot);
+        fs::write(&state_file, serde_json::to_string(&external).unwrap()).unwrap();
+
+        assert!(app_state.reload_if_changed().unwrap());
+        app_state.save::<&Path>(None).unwrap();
+        let reloaded = AppState::load(create_test_config(&state_file)).unwrap();
+        let body = |id: &str| {
+            let listener = &reloaded.bots["bot2"].listeners[id];
+            reloaded.templates.body(listener).map(str::to_string)
+        };
+        assert_eq!(body("kept").as_deref(), Some(kept));
+        assert_eq!(body("inline").as_deref(), Some(inline));
+    }
+
+    #[test]
+    fn test_reloads_are_announced_and_forget_prices() {
+        let temp_dir = tempdir().unwrap();
+        let state_file = temp_dir.path().join("state.json");
+        let app_state = Mutex::new(AppState::load(create_test_config(&state_file)).unwrap());
+        let events = ServerEvents::default();
+        let mut announced = events.subscribe();
+        let prices = PriceCache::default();
+        prices.record("BTCUSDT", 50000.0, Utc::now());
+
+        assert!(reload_and_announce(&app_state, &events, &prices));
+        assert!(prices.quote("BTCUSDT").is_some());
+
+        let mut external = AppState::default();
+        external.bots.insert("bot2".to_string(), sample_bot("bot2"));
+        fs::write(&state_file, serde_json::to_string(&external).unwrap()).unwrap();
+        assert!(reload_and_announce(&app_state, &events, &prices));
+        assert!(prices.quote("BTCUSDT").is_none());
+        assert!(matches!(
+            announced.try_recv(),
+            Ok(ServerEvent::StateReloaded { .. })
+        ));
+    }
+
+    #[test]
+    fn test_state_file_held_by_another_process_is_left_alone() {
+        let temp_dir = tempdir().unwrap();
+        let state_file = temp_dir.path().join("state.json");
+        let config = create_test_config(&state_file);
+        let mut app_state = AppState::load(config.clone()).unwrap();
+
+        // Another process is saving the file
+        let held = FileLock::exclusive(&state_file).unwrap().unwrap();
+        assert!(matches!(
+            AppState::load(config.clone()),
+            Err(AppError::StateLocked { .. })
+        ));
+        app_state
+            .bots
+            .insert("bot1".to_string(), sample_bot("bot1"));
+        assert!(matches!(
+            app_state.save::<&Path>(None),
+            Err(AppError::StateLocked { .. })
+        ));
+        assert!(!app_state.reload_if_changed().unwrap());
+        let mut snapshot = app_state.snapshot().unwrap();
+        let pending = Snapshot {
+            path: state_file.clone(),
+            content: std::mem::take(&mut snapshot.0),
+            hash: snapshot.1,
+            expected: app_state.content_hash,
+            pending: None,
+            backups: 0,
+            previous_save: None,
+            store: None,
+        };
+        assert_eq!(
+            write_snapshot(&pending).unwrap_err().kind(),
+            ErrorKind::WouldBlock
+        );
+        drop(held);
+
+        app_state.save::<&Path>(None).unwrap();
+        assert!(AppState::load(config).unwrap().bots.contains_key("bot1"));
+        // A background save does not replace a file changed after its snapshot
+        assert!(write_snapshot(&pending).is_err());
+    }
+
+    #[test]
+    fn test_save_refuses_to_overwrite_external_edit() {
+        let temp_dir = tempdir().unwrap();
+        let state_file = temp_dir.path().join("state.json");
+        let mut app_state = AppState::load(create_test_config(&state_file)).unwrap();
+
+        let manual_edit = r#"{"bots": {}, "note": "edited by hand"}"#;
+        fs::write(&state_file, manual_edit).unwrap();
+
+        app_state
+            .bots
+            .insert("bot1".to_string(), sample_bot("bot1"));
+        assert!(matches!(
+            app_state.save::<&Path>(None),
+            Err(AppError::StateConflict(_))
+        ));
+        assert_eq!(fs::read_to_string(&state_file).unwrap(), manual_edit);
+
+        // Once the edit has been picked up, saving works again
+        app_state.reload_if_changed().unwrap();
+        app_state
+            .bots
+            .insert("bot1".to_string(), sample_bot("bot1"));
+        app_state.save::<&Path>(None).unwrap();
+    }
+
+    #[test]
+    fn test_save_is_compact_unless_pretty() {
+        let temp_dir = tempdir().unwrap();
+        let state_file = temp_dir.path().join("state.json");
+        let mut config = create_test_config(&state_file);
+
+        let mut app_state = AppState::load(config.clone()).unwrap();
+        app_state
+            .bots
+            .insert("bot1".to_string(), sample_bot("bot1"));
+        app_state.save::<&Path>(None).unwrap();
+        assert!(!fs::read_to_string(&state_file).unwrap().contains('\n'));
+        assert_eq!(
+            serialized_len(&app_state),
+            fs::metadata(&state_file).unwrap().len()
+        );
+
+        config.api_server.pretty_state_file = true;
+        let mut app_state = AppState::load(config).unwrap();
+        app_state.save::<&Path>(None).unwrap();
+        assert!(fs::read_to_string(&state_file).unwrap().contains("\n  "));
+        assert!(!app_state.reload_if_changed().unwrap());
+    }
+
+    #[test]
+    fn test_save_replaces_the_file_and_keeps_backups() {
+        let temp_dir = tempdir().unwrap();
+        let state_file = temp_dir.path().join("state.json");
+        let mut config = create_test_config(&state_file);
+        config.api_server.state_backups = 2;
+
+        let mut app_state = AppState::load(config.clone()).unwrap();
+        for bot_id in ["bot1", "bot2", "bot3"] {
+            app_state
+                .bots
+                .insert(bot_id.to_string(), sample_bot(bot_id));
+            app_state.save::<&Path>(None).unwrap();
+        }
+        let bots_in = |path: PathBuf| {
+            let mut config = config.clone();
+            config.api_server.state_file = path;
+            AppState::load(config).unwrap().bots.len()
+        };
+        assert_eq!(bots_in(state_file.clone()), 3);
+        assert_eq!(bots_in(backup_path(&state_file, 1)), 2);
+        assert_eq!(bots_in(backup_path(&state_file, 2)), 1);
+        assert!(!backup_path(&state_file, 3).exists());
+        assert!(!with_suffix(&state_file, "tmp").exists());
+        // The backups are not mistaken for external edits of the state file
+        assert!(!app_state.reload_if_changed().unwrap());
+    }
+
+    #[test]
+    fn test_secrets_are_encrypted_in_the_file() {
+        let temp_dir = tempdir().unwrap();
+        let state_file = temp_dir.path().join("state.json");
+        let mut config = create_test_config(&state_file);
+        let mut app_state = AppState::load(config.clone()).unwrap();
+        let bot = Bot {
+            api_secret: Some("s3cret".to_string()),
+            ..sample_bot("bot1")
+        };
+        app_state.bots.insert("bot1".to_string(), bot.clone());
+        app_state.save::<&Path>(None).unwrap();
+
+        // Plaintext files load with a key, and are encrypted when next saved
+        config.encryption.key = Some("2a".repeat(32));
+        let mut app_state = AppState::load(config.clone()).unwrap();
+        app_state.save::<&Path>(None).unwrap();
+        let content = fs::read_to_string(&state_file).unwrap();
+        assert!(!content.contains("s3cret"), "{}", content);
+        assert!(content.contains("\"api_secret\":\"enc:"), "{}", content);
+        assert_eq!(app_state.bots["bot1"], bot);
+        assert_eq!(AppState::load(config.clone()).unwrap().bots["bot1"], bot);
+
+        config.encryption.key = Some("2b".repeat(32));
+        assert!(matches!(
+            AppState::load(config.clone()),
+            Err(AppError::ConfigError(_))
+        ));
+        config.encryption.key = None;
+        assert!(matches!(
+            AppState::load(config),
+            Err(AppError::ConfigError(_))
+        ));
+    }
+
+    #[test]
+    fn test_locked_state_is_kept_from_other_processes_until_dropped() {
+        let temp_dir = tempdir().unwrap();
+        let state_file = temp_dir.path().join("state.json");
+        let config = create_test_config(&state_file);
+        let timeout = Duration::from_millis(100);
+
+        let mut app_state = AppState::load_locked(config.clone(), timeout).unwrap();
+        let pid = format!("PID {}", std::process::id());
+        assert!(matches!(
+            AppState::load_locked(config.clone(), timeout),
+            Err(AppError::StateLocked { holder, .. }) if holder == pid
+        ));
+        assert!(matches!(
+            AppState::load(config.clone()),
+            Err(AppError::StateLocked { .. })
+        ));
+        app_state
+            .bots
+            .insert("bot1".to_string(), sample_bot("bot1"));
+        app_state.save::<&Path>(None).unwrap();
+        drop(app_state);
+
+        let app_state = AppState::load_locked(config, timeout).unwrap();
+        assert!(app_state.bots.contains_key("bot1"));
+    }
+
+    #[tokio::test]
+    async fn test_deferred_saves_are_written_in_the_background() {
+        let temp_dir = tempdir().unwrap();
+        let state_file = temp_dir.path().join("state.json");
+        let config = create_test_config(&state_file);
+        let bots_on_disk = || AppState::load(config.clone()).unwrap().bots.len();
+
+        let mut app_state = AppState::load(config.clone()).unwrap();
+        app_state.persistence.deferred = true;
+        app_state
+            .bots
+            .insert("bot1".to_string(), sample_bot("bot1"));
+        app_state.save::<&Path>(None).unwrap();
+        assert!(app_state.persistence.is_dirty());
+        assert_eq!(bots_on_disk(), 0);
+
+        let app_state = Arc::new(Mutex::new(app_state));
+        let writer = tokio::spawn(persist_state(app_state.clone(), Duration::from_millis(10)));
+        for _ in 0..100 {
+            if bots_on_disk() == 1 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(bots_on_disk(), 1);
+        writer.abort();
+        let _ = writer.await;
+        {
+            let mut state = app_state.lock().unwrap();
+            assert!(!state.persistence.is_dirty());
+            // The background write is not mistaken for an external edit
+            assert!(!state.reload_if_changed().unwrap());
+            state.bots.insert("bot2".to_string(), sample_bot("bot2"));
+            state.save::<&Path>(None).unwrap();
+        }
+        assert_eq!(bots_on_disk(), 1);
+
+        flush(&app_state).unwrap();
+        assert_eq!(bots_on_disk(), 2);
+        let mut state = app_state.lock().unwrap();
+        assert!(!state.persistence.deferred);
+        state.bots.clear();
+        state.save::<&Path>(None).unwrap();
+        assert_eq!(bots_on_disk(), 0);
+    }
+
+    #[cfg(feature = "state-zstd")]
+    #[test]
+    fn test_compressed_state_file_round_trip() {
+        let temp_dir = tempdir().unwrap();
+        let state_file = temp_dir.path().join("state.json.zst");
+        let config = create_test_config(&state_file);
+
+        let mut app_state = AppState::load(config.clone()).unwrap();
+        app_state
+            .bots
+            .insert("bot1".to_string(), sample_bot("bot1"));
+        app_state.save::<&Path>(None).unwrap();
+
+        assert!(fs::read(&state_file).unwrap().starts_with(&ZSTD_MAGIC));
+        assert!(!app_state.reload_if_changed().unwrap());
+        assert_eq!(AppState::load(config).unwrap(), app_state);
+
+        // Compressed content is recognised whatever the file is called
+        let renamed = temp_dir.path().join("state.json");
+        fs::copy(&state_file, &renamed).unwrap();
+        let loaded = AppState::load(create_test_config(&renamed)).unwrap();
+        assert!(loaded.bots.contains_key("bot1"));
+    }
+
+    /// Save timings for a large state: `cargo test --release -- --ignored --nocapture bench`
+    #[test]
+    #[ignore]
+    fn bench_save_large_state() {
+        let temp_dir = tempdir().unwrap();
+        let mut app_state = AppState::default();
+        for i in 0..5_000 {
+            let bot_id = format!("bot{}", i);
+            app_state.bots.insert(bot_id.clone(), sample_bot(&bot_id));
+        }
+
+        for (name, pretty) in [
+            ("state.json", true),
+            ("state.json", false),
+            ("state.json.zst", false),
+        ] {
+            let path = temp_dir.path().join(name);
+            app_state.pretty = pretty;
+            let started = std::time::Instant::now();
+            for _ in 0..10 {
+                app_state.save(Some(&path)).unwrap();
+            }
+            println!(
+                "{} (pretty: {}): {:?} per save, {} bytes",
+                name,
+                pretty,
+                started.elapsed() / 10,
+                fs::metadata(&path).unwrap().len()
+            );
+        }
+    }
+
+    #[test]
+    fn test_reload_rejects_invalid_edit() {
+        let temp_dir = tempdir().unwrap();
+        let state_file = temp_dir.path().join("state.json");
+        let mut app_state = AppState::load(create_test_config(&state_file)).unwrap();
+        app_state
+            .bots
+            .insert("bot1".to_string(), sample_bot("bot1"));
+        app_state.save::<&Path>(None).unwrap();
+
+        // Truncated JSON
+        fs::write(&state_file, "{\"bots\": {").unwrap();
+        assert!(app_state.reload_if_changed().is_err());
+
+        // Bot stored under the wrong key
+        let mut external = AppState::default();
+        external
+            .bots
+            .insert("other".to_string(), sample_bot("bot1"));
+        fs::write(&state_file, serde_json::to_string(&external).unwrap()).unwrap();
+        assert!(matches!(
+            app_state.reload_if_changed(),
+            Err(AppError::InvalidState(_))
+        ));
+
+        // The in-memory state is untouched
+        assert!(app_state.bots.contains_key("bot1"));
+    }
+}
@@ -0,0 +1,71 @@
+// src/bot/access_log.rs
+//! # Access Log
+//!
+//! With `api_server.access_log` set, every request the API and webhook servers answer is
+//! appended to that file as one JSON object per line: when it came, from where, what it
+//! asked for and how it was answered. Query strings are left out, as webhook URLs carry
+//! listener secrets in them. [Compaction](crate::bot::compaction) prunes lines older than
+//! `retention.access_log`.
+use crate::utils::jsonl;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::web;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::Instant;
+
+/// Where requests are logged, if anywhere; app data of the servers.
+#[derive(Clone, Debug, Default)]
+pub struct AccessLog {
+    pub file: Option<PathBuf>,
+}
+
+/// A request answered.
+#[derive(Debug, Serialize)]
+pub struct AccessLogEntry<'a> {
+    pub timestamp: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub peer: Option<&'a str>,
+    pub method: &'a str,
+    pub path: &'a str,
+    pub status: u16,
+    pub duration_ms: u64,
+}
+
+/// Middleware appending each answered request to the [`AccessLog`], if there is one.
+/// Failures to write it are logged; the request is answered regardless.
+pub async fn middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let Some(file) = (req.app_data::<web::Data<AccessLog>>()).and_then(|log| log.file.clone())
+    else {
+        return next.call(req).await;
+    };
+    let timestamp = Utc::now();
+    let started = Instant::now();
+    let peer = req.connection_info().realip_remote_addr().map(String::from);
+    let method = req.method().to_string();
+    let path = req.path().to_string();
+
+    let response = next.call(req).await?;
+    let entry = AccessLogEntry {
+        timestamp,
+        peer: peer.as_deref(),
+        method: &method,
+        path: &path,
+        status: response.status().as_u16(),
+        duration_ms: started.elapsed().as_millis() as u64,
+    };
+    match serde_json::to_string(&entry) {
+        Ok(line) => {
+            if let Err(e) = jsonl::append(&file, [line]) {
+                log::warn!("Failed to write access log {:?}: {}", file, e);
+            }
+        }
+        Err(e) => log::warn!("Failed to serialize access log entry: {}", e),
+    }
+    Ok(response)
+}
@@ -0,0 +1,258 @@
+// src/bot/allocation.rs
+//! # Capital Allocation
+//!
+//! A bot may be given a [`Budget`]: an amount of the quote asset, or a share of the
+//! configured `allocation.capital`. Its exposure is the value of its open positions, built
+//! from its [trade history](crate::bot::state::history) and marked to the cached prices, or
+//! to their entry prices where there are none. Orders that would take the exposure past the
+//! budget are cut down to what still [fits](fit); orders that reduce a position always pass.
+//!
+//! Positions move with the market, so exposure drifts from the budget even without trades.
+//! The allocation report compares the two and suggests rebalancing the bots that drifted
+//! further than `allocation.drift_threshold_pct` from their budget.
+use crate::app_config::AllocationConfig;
+use crate::bot::model::Bot;
+use crate::bot::state::BotAllocation;
+use crate::exchange::Side;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Most a bot may have in open positions.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+pub enum Budget {
+    /// In the quote asset
+    Amount(Decimal),
+    /// Percent of the configured capital
+    Percent(Decimal),
+}
+
+impl Budget {
+    /// The budget in the quote asset; `None` for a percentage without a `capital`.
+    pub fn amount(&self, capital: Option<Decimal>) -> Option<Decimal> {
+        match self {
+            Budget::Amount(amount) => Some(*amount),
+            Budget::Percent(percent) => {
+                capital.map(|capital| capital * percent / Decimal::ONE_HUNDRED)
+            }
+        }
+    }
+}
+
+impl FromStr for Budget {
+    type Err = String;
+
+    /// `5000` for an amount, `25%` for a share of the capital.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (number, percent) = match s.strip_suffix('%') {
+            Some(number) => (number.trim(), true),
+            None => (s, false),
+        };
+        let value = number
+            .parse::<Decimal>()
+            .ok()
+            .filter(|value| *value > Decimal::ZERO)
+            .ok_or_else(|| {
+                format!(
+                    "budget must be a positive amount or percentage, not '{}'",
+                    s
+                )
+            })?;
+        if !percent {
+            Ok(Budget::Amount(value))
+        } else if value > Decimal::ONE_HUNDRED {
+            Err(format!(
+                "budget cannot exceed 100% of the capital, not '{}'",
+                s
+            ))
+        } else {
+            Ok(Budget::Percent(value))
+        }
+    }
+}
+
+impl fmt::Display for Budget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Budget::Amount(amount) => write!(f, "{}", amount),
+            Budget::Percent(percent) => write!(f, "{}%", percent),
+        }
+    }
+}
+
+impl TryFrom<String> for Budget {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Budget> for String {
+    fn from(budget: Budget) -> Self {
+        budget.to_string()
+    }
+}
+
+/// Quantity of an order for `quantity` on `side` at `price` that keeps `exposure` within
+/// `budget`, given the bot's signed `position` in the symbol. Quantity closing the position
+/// frees its share of the exposure and is never cut.
+pub fn fit(
+    position: Decimal,
+    side: Side,
+    quantity: Decimal,
+    price: Decimal,
+    exposure: Decimal,
+    budget: Decimal,
+) -> Decimal {
+    let closing = if position.is_zero() || position.is_sign_positive() == side.is_buy() {
+        Decimal::ZERO
+    } else {
+        position.abs().min(quantity)
+    };
+    let opening = quantity - closing;
+    if opening.is_zero() || price <= Decimal::ZERO {
+        return quantity;
+    }
+    let room = (budget - (exposure - closing * price).max(Decimal::ZERO)).max(Decimal::ZERO);
+    closing + opening.min(room / price)
+}
+
+/// What keeps the budget of `bot` from being enforced.
+pub fn warnings(bot: &Bot, config: &AllocationConfig) -> Vec<String> {
+    match bot.budget {
+        Some(budget @ Budget::Percent(_)) if config.capital.is_none() => vec![format!(
+            "budget of {} needs allocation.capital; it is not enforced",
+            budget
+        )],
+        _ => Vec::new(),
+    }
+}
+
+/// How the `exposure` of `bot_id` compares with its `budget`, with a suggestion once it
+/// drifted past the configured threshold.
+pub fn allocation(
+    bot_id: &str,
+    budget: Option<Budget>,
+    exposure: Decimal,
+    config: &AllocationConfig,
+) -> BotAllocation {
+    let budget_amount = budget.and_then(|budget| budget.amount(config.capital));
+    let drift = budget_amount.map(|amount| exposure - amount);
+    let drift_pct = budget_amount
+        .zip(drift)
+        .filter(|(amount, _)| !amount.is_zero())
+        .map(|(amount, drift)| (drift / amount * Decimal::ONE_HUNDRED).round_dp(2));
+    let suggestion = drift
+        .zip(drift_pct)
+        .filter(|(_, pct)| pct.abs() > config.drift_threshold_pct)
+        .map(|(drift, _)| {
+            let drift = drift.round_dp(2);
+            if drift.is_sign_positive() {
+                format!("reduce exposure by {}", drift)
+            } else {
+                format!("{} of the budget is unused", -drift)
+            }
+        });
+    BotAllocation {
+        bot_id: bot_id.to_string(),
+        budget,
+        budget_amount,
+        exposure: exposure.round_dp(2),
+        drift_pct,
+        suggestion,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::dec;
+
+    #[test]
+    fn orders_past_the_budget_are_cut_but_closing_ones_pass() {
+        assert_eq!("5000".parse(), Ok(Budget::Amount(dec!(5000))));
+        assert_eq!(" 25 %".parse(), Ok(Budget::Percent(dec!(25))));
+        assert!("150%".parse::<Budget>().is_err());
+        assert!("-1".parse::<Budget>().is_err());
+        assert_eq!(
+            Budget::Percent(dec!(25)).amount(Some(dec!(8000))),
+            Some(dec!(2000))
+        );
+        assert_eq!(Budget::Percent(dec!(25)).amount(None), None);
+
+        // 600 of 1000 used: a buy of 10 at 100 is cut to 4
+        let fit_buy = |position, quantity| {
+            fit(
+                position,
+                Side::Buy,
+                quantity,
+                dec!(100),
+                dec!(600),
+                dec!(1000),
+            )
+        };
+        assert_eq!(fit_buy(dec!(6), dec!(10)), dec!(4));
+        assert_eq!(fit_buy(dec!(6), dec!(2)), dec!(2));
+        // Covering a short of 6 frees its 600 before a long of 4 is opened
+        assert_eq!(fit_buy(dec!(-6), dec!(20)), dec!(16));
+        // Selling out of a long is never cut
+        assert_eq!(
+            fit(
+                dec!(6),
+                Side::Sell,
+                dec!(6),
+                dec!(100),
+                dec!(2000),
+                dec!(1000)
+            ),
+            dec!(6)
+        );
+        assert_eq!(
+            fit(
+                dec!(0),
+                Side::Buy,
+                dec!(1),
+                dec!(100),
+                dec!(2000),
+                dec!(1000)
+            ),
+            dec!(0)
+        );
+    }
+
+    #[test]
+    fn drifted_bots_get_a_suggestion() {
+        let config = AllocationConfig {
+            capital: Some(dec!(10000)),
+            drift_threshold_pct: dec!(10),
+        };
+        let over = allocation(
+            "alpha",
+            Some(Budget::Percent(dec!(20))),
+            dec!(2500),
+            &config,
+        );
+        assert_eq!(over.budget_amount, Some(dec!(2000)));
+        assert_eq!(over.drift_pct, Some(dec!(25)));
+        assert_eq!(over.suggestion.as_deref(), Some("reduce exposure by 500"));
+
+        let under = allocation("beta", Some(Budget::Amount(dec!(1000))), dec!(850), &config);
+        assert_eq!(
+            under.suggestion.as_deref(),
+            Some("150 of the budget is unused")
+        );
+        let close = allocation(
+            "gamma",
+            Some(Budget::Amount(dec!(1000))),
+            dec!(950),
+            &config,
+        );
+        assert_eq!(close.suggestion, None);
+        let unbudgeted = allocation("delta", None, dec!(950), &config);
+        assert_eq!((unbudgeted.drift_pct, unbudgeted.suggestion), (None, None));
+    }
+}
@@ -4,26 +4,96 @@
 //use crate::bot::api::{acquire_lock, apply_pagination, create_api_response, Pagination};
 pub use super::state::{
     AppState, BotDeleteArgs, BotGetArgs, BotInsertArgs, BotListArgs, BotListView, BotRegistry,
-    BotUpdateArgs, ListenerDeleteArgs, ListenerGetArgs, ListenerInsertArgs, ListenerListArgs,
-    ListenerUpdateArgs, ListenersDeleteArgs,
+    BotTimelineArgs, BotUpdateArgs, ListenerDeleteArgs, ListenerGetArgs, ListenerInsertArgs,
+    ListenerListArgs, ListenerListView, ListenerUpdateArgs, ListenersDeleteArgs, SymbolMapArgs,
+    SymbolUnmapArgs,
 };
+use crate::app_config::{AuthConfig, TenancyConfig, WebhookServerConfig};
+use crate::app_state::serialized_len;
+use crate::bot::allocation;
+use crate::bot::audit::{self, AuditEvent};
+use crate::bot::auth::{self, Role};
+use crate::bot::caching;
+use crate::bot::compaction;
+use crate::bot::dispatch::{self, Delivery, ExecutionResult, ExecutionStatus};
+use crate::bot::fieldsets;
+use crate::bot::import;
+use crate::bot::keycheck;
+use crate::bot::metrics::Metrics;
+use crate::bot::model::Bot;
+use crate::bot::montecarlo;
+use crate::bot::outage::Outages;
+use crate::bot::performance::StrategyReport;
+use crate::bot::portfolio::Portfolio;
+use crate::bot::quality::QualityReport;
+use crate::bot::reconcile::ReconciliationReport;
+use crate::bot::recovery::RecoveryReport;
+use crate::bot::replay;
+use crate::bot::secrets::Secrets;
+use crate::bot::state::alerts::AlertRecord;
+use crate::bot::state::export::StateExport;
+use crate::bot::state::history::TradeHistory;
+use crate::bot::state::input::bot::list::SECRET_FILTERS;
+use crate::bot::state::{
+    AdminInfoView, AlertListArgs, AlertListView, AllocationReportArgs, AllocationView,
+    ApiVersionsView, BalanceListView, BulkOperation, BulkRequest, CancelledOrderView, EntityCounts,
+    ExecutionQualityArgs, ExecutionQualityView, FillImportView, ListenerBulkRequest,
+    ListenerRotateSecretArgs, LoginArgs, LoginView, MemoryStatsView, MonteCarloArgs,
+    OpenOrderListView, PositionListView, PriceListView, PriceView, ReadinessView, ReplayArgs,
+    StateCompactArgs, StateExportArgs, StateImportArgs, StateSizes, StrategyReportArgs,
+    StrategyReportView, SymbolMapView, TradeListView, VersionView,
+};
+use crate::bot::statement::{self, Statement};
 pub use crate::errors::AppError;
-use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
+use crate::errors::ErrorCode;
+use crate::exchange::prices::PriceCache;
+use crate::exchange::{self, Exchange};
+use actix_web::dev::{Payload, Service};
+use actix_web::http::header::{HeaderName, HeaderValue, AUTHORIZATION, CACHE_CONTROL, LINK};
+use actix_web::http::Method;
+use actix_web::middleware::from_fn;
+use actix_web::{
+    delete, get, guard, post, put, web, FromRequest, HttpMessage, HttpRequest, HttpResponse,
+    Responder,
+};
+use chrono::{DateTime, Utc};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::future::{ready, Ready};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
+use validator::Validate;
 
 /// Unified API response structure.
-#[derive(Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct ApiResponse<T> {
     pub success: bool,
     pub data: Option<T>,
     pub error: Option<String>,
+    /// Stable code of the error, for clients that act on it or word it themselves
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<ErrorCode>,
+    /// Things the caller should know about, e.g. a setting that limits the result, that did
+    /// not stop the request
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+impl<T> ApiResponse<T> {
+    pub fn with_warnings(mut self, warnings: Vec<String>) -> Self {
+        self.warnings.extend(warnings);
+        self
+    }
 }
 
 /// Helper to acquire a lock on `AppState`.
 pub fn acquire_lock(
     data: &web::Data<Arc<Mutex<AppState>>>,
-) -> Result<std::sync::MutexGuard<AppState>, AppError> {
+) -> Result<std::sync::MutexGuard<'_, AppState>, AppError> {
     data.lock().map_err(|e| {
         log::error!("Failed to acquire lock on AppState: {}", e);
         AppError::InternalServerError("Failed to acquire lock on AppState".to_string())
@@ -54,6 +124,353 @@ pub fn create_api_response<T>(
         success,
         data,
         error,
+        error_code: None,
+        warnings: Vec::new(),
+    }
+}
+
+/// Facts about this server run, shared with the handlers.
+#[derive(Debug)]
+pub struct ServerInfo {
+    pub started_at: DateTime<Utc>,
+    /// Set if the previous run did not shut down cleanly
+    pub recovery: Option<RecoveryReport>,
+    /// Set once startup reconciliation has finished
+    pub reconciliation: Mutex<Option<ReconciliationReport>>,
+    /// Alerts refused for being too old
+    pub stale_alerts: AtomicU64,
+}
+
+/// Header naming the tenant a support operator wants to view.
+pub const ACT_AS_HEADER: &str = "X-Act-As";
+
+/// The bearer token of `req`, if it carries one.
+fn bearer_token(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+}
+
+/// User whose token the request carries, with the role they have now.
+#[derive(Clone, Debug)]
+pub struct SignedIn {
+    pub username: String,
+    pub role: Role,
+}
+
+impl SignedIn {
+    /// `None` unless `[auth]` is enabled and the request carries a user's token rather than
+    /// a tenancy token. Requests of read-only users are marked for their responses to be
+    /// [redacted](auth::redact_for_viewers).
+    fn resolve(req: &HttpRequest) -> Result<Option<Self>, AppError> {
+        let Some(key) =
+            (req.app_data::<web::Data<AuthConfig>>()).and_then(|config| auth::signing_key(config))
+        else {
+            return Ok(None);
+        };
+        let tenancy = (req.app_data::<web::Data<TenancyConfig>>()).is_some_and(|c| c.enabled);
+        let token = match bearer_token(req) {
+            Some(token) if auth::is_jwt(token) => token,
+            // Tenancy tokens, or their absence, are for tenancy to judge
+            _ if tenancy => return Ok(None),
+            Some(_) => return Err(AppError::Unauthorized("Unknown token.".to_string())),
+            None => {
+                return Err(AppError::Unauthorized(
+                    "Missing bearer token; sign in at /auth/login.".to_string(),
+                ))
+            }
+        };
+        let claims = auth::verify(key, token, Utc::now())?;
+        let data = (req.app_data::<web::Data<Arc<Mutex<AppState>>>>()).ok_or_else(|| {
+            AppError::InternalServerError("The state is not available".to_string())
+        })?;
+        let role = acquire_lock(data)?.users.role(&claims.sub)?;
+        if role == Role::ReadOnly {
+            req.extensions_mut().insert(auth::ReadOnly);
+        }
+        Ok(Some(SignedIn {
+            username: claims.sub,
+            role,
+        }))
+    }
+}
+
+/// Tenant owning the request's bearer token.
+///
+/// Resolves to `None` when tenancy is disabled (or not configured on the app), in which case
+/// handlers address the whole state. An admin token combined with [`ACT_AS_HEADER`] resolves
+/// to the named tenant for read-only requests; every such request is audited.
+#[derive(Clone, Debug, Default)]
+pub struct Tenant {
+    name: Option<String>,
+    /// Admin viewing the tenant through [`ACT_AS_HEADER`]
+    impersonated_by: Option<String>,
+    /// Signed in as a [`Role::ReadOnly`] user
+    read_only: bool,
+}
+
+impl Tenant {
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn impersonated_by(&self) -> Option<&str> {
+        self.impersonated_by.as_deref()
+    }
+
+    /// Whether the request may only read, and must not be shown secrets.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    fn resolve(req: &HttpRequest) -> Result<Self, AppError> {
+        // Users address the whole state; read-only ones may only read it
+        if let Some(user) = SignedIn::resolve(req)? {
+            let read_only = user.role == Role::ReadOnly;
+            if read_only && !matches!(*req.method(), Method::GET | Method::HEAD) {
+                return Err(AppError::Forbidden(format!(
+                    "User '{}' is read-only; {} is not allowed.",
+                    user.username,
+                    req.method()
+                )));
+            }
+            return Ok(Tenant {
+                read_only,
+                ..Tenant::default()
+            });
+        }
+        let config = match req.app_data::<web::Data<TenancyConfig>>() {
+            Some(config) if config.enabled => config,
+            _ => return Ok(Tenant::default()),
+        };
+        let token = bearer_token(req)
+            .ok_or_else(|| AppError::Unauthorized("Missing bearer token.".to_string()))?;
+
+        if let Some(tenant) = config.tenant_for_token(token) {
+            return Ok(Tenant {
+                name: Some(tenant.to_string()),
+                ..Tenant::default()
+            });
+        }
+        let admin = config
+            .admin_for_token(token)
+            .ok_or_else(|| AppError::Unauthorized("Unknown token.".to_string()))?;
+        Self::impersonate(config, admin, req)
+    }
+
+    /// Resolve an admin request to the tenant named in [`ACT_AS_HEADER`].
+    fn impersonate(
+        config: &TenancyConfig,
+        admin: &str,
+        req: &HttpRequest,
+    ) -> Result<Self, AppError> {
+        let tenant = req
+            .headers()
+            .get(ACT_AS_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|tenant| config.tenants.contains_key(*tenant))
+            .ok_or_else(|| {
+                AppError::Forbidden(format!(
+                    "Admin tokens must name an existing tenant with the {} header.",
+                    ACT_AS_HEADER
+                ))
+            })?;
+        let read_only = matches!(*req.method(), Method::GET | Method::HEAD);
+
+        audit::record(
+            &AuditEvent::new(admin, tenant, req.method().as_str(), req.path(), read_only),
+            config.audit_log.as_deref(),
+        )
+        .map_err(|e| AppError::InternalServerError(format!("Failed to write audit log: {}", e)))?;
+
+        if !read_only {
+            return Err(AppError::Forbidden(format!(
+                "Impersonated requests are read-only; {} is not allowed.",
+                req.method()
+            )));
+        }
+        Ok(Tenant {
+            name: Some(tenant.to_string()),
+            impersonated_by: Some(admin.to_string()),
+            read_only: false,
+        })
+    }
+}
+
+impl FromRequest for Tenant {
+    type Error = AppError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(Self::resolve(req))
+    }
+}
+
+/// Proof that the request may use admin endpoints.
+///
+/// Admin endpoints are open when neither users nor tenancy are enabled; otherwise they require
+/// an [`Role::Admin`] user or an admin token.
+#[derive(Debug)]
+pub struct Admin;
+
+impl Admin {
+    fn resolve(req: &HttpRequest) -> Result<Self, AppError> {
+        if let Some(user) = SignedIn::resolve(req)? {
+            return match user.role {
+                Role::Admin => Ok(Admin),
+                Role::ReadOnly => Err(AppError::Forbidden(
+                    "This endpoint requires the admin role.".to_string(),
+                )),
+            };
+        }
+        let config = match req.app_data::<web::Data<TenancyConfig>>() {
+            Some(config) if config.enabled => config,
+            _ => return Ok(Admin),
+        };
+        let token = bearer_token(req)
+            .ok_or_else(|| AppError::Unauthorized("Missing bearer token.".to_string()))?;
+
+        match config.admin_for_token(token) {
+            Some(_) => Ok(Admin),
+            None if config.tenant_for_token(token).is_some() => Err(AppError::Forbidden(
+                "This endpoint requires an admin token.".to_string(),
+            )),
+            None => Err(AppError::Unauthorized("Unknown token.".to_string())),
+        }
+    }
+}
+
+impl FromRequest for Admin {
+    type Error = AppError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(Self::resolve(req))
+    }
+}
+
+/// Filters of a list given in the query string; an invalid one is a bad request.
+fn query_filter<T>(query: Result<web::Query<T>, actix_web::Error>) -> Result<T, AppError> {
+    query
+        .map(web::Query::into_inner)
+        .map_err(|e| AppError::InvalidInput(format!("Invalid filter: {}", e)))
+}
+
+/// Fields of bot views lists can be sorted by.
+pub const BOT_SORTS: [&str; 9] = [
+    "bot_id",
+    "name",
+    "exchange",
+    "trading_fee",
+    "dry_run",
+    "observer",
+    "paper_slippage_bps",
+    "exclusion_group",
+    "rest_endpoint",
+];
+
+/// Fields of listener views lists can be sorted by.
+pub const LISTENER_SORTS: [&str; 8] = [
+    "bot_id",
+    "listener_id",
+    "service",
+    "enabled",
+    "verify_secret",
+    "last_fired",
+    "max_alerts_per_minute",
+    "cooldown_secs",
+];
+
+/// Order of a list, from `?sort=name,-trading_fee`: by each field in turn, descending if it
+/// starts with `-`. Which fields of the list are served, `?fields=`, is up to
+/// [`fieldsets`] as for any response.
+#[derive(Debug, Default, Deserialize)]
+pub struct ListQuery {
+    pub sort: Option<String>,
+}
+
+impl ListQuery {
+    /// The fields to sort by, and whether descending; each must be one of `sortable`.
+    fn keys(&self, sortable: &[&str]) -> Result<Vec<(String, bool)>, AppError> {
+        (self.sort.as_deref().unwrap_or_default().split(','))
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+            .map(|key| {
+                let (field, descending) = match key.strip_prefix('-') {
+                    Some(field) => (field, true),
+                    None => (key, false),
+                };
+                if sortable.contains(&field) {
+                    Ok((field.to_string(), descending))
+                } else {
+                    Err(AppError::InvalidInput(format!(
+                        "Cannot sort by '{}'; expected one of: {}",
+                        field,
+                        sortable.join(", ")
+                    )))
+                }
+            })
+            .collect()
+    }
+
+    /// Check the fields to sort by before looking for the items, so an unknown one is a bad
+    /// request even when nothing matches.
+    pub fn validate(&self, sortable: &[&str]) -> Result<(), AppError> {
+        self.keys(sortable).map(drop)
+    }
+
+    /// Sort `items` by the fields of their views asked for, keeping the order of those that
+    /// compare equal. Items missing a field, or with it null, come last either way.
+    pub fn sort<T: Serialize>(&self, items: Vec<T>, sortable: &[&str]) -> Result<Vec<T>, AppError> {
+        let keys = self.keys(sortable)?;
+        if keys.is_empty() {
+            return Ok(items);
+        }
+        let mut keyed: Vec<(Vec<Value>, T)> = (items.into_iter())
+            .map(|item| {
+                let view = serde_json::to_value(&item).unwrap_or_default();
+                let values = (keys.iter())
+                    .map(|(field, _)| view.get(field).cloned().unwrap_or_default())
+                    .collect();
+                (values, item)
+            })
+            .collect();
+        keyed.sort_by(|(a, _), (b, _)| {
+            (keys.iter().zip(a.iter().zip(b)))
+                .map(|((_, descending), (a, b))| compare_fields(a, b, *descending))
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        Ok(keyed.into_iter().map(|(_, item)| item).collect())
+    }
+}
+
+/// Compare two values of a field; decimals, serialized as strings, compare as numbers.
+fn compare_fields(a: &Value, b: &Value, descending: bool) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+    let ordering = match (a, b) {
+        (Value::Null, Value::Null) => return Ordering::Equal,
+        (Value::Null, _) => return Ordering::Greater,
+        (_, Value::Null) => return Ordering::Less,
+        (Value::Number(a), Value::Number(b)) => {
+            (a.as_f64().unwrap_or_default()).total_cmp(&b.as_f64().unwrap_or_default())
+        }
+        (Value::String(a), Value::String(b)) => {
+            match (a.parse::<Decimal>(), b.parse::<Decimal>()) {
+                (Ok(a), Ok(b)) => a.cmp(&b),
+                _ => a.cmp(b),
+            }
+        }
+        (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+        _ => Ordering::Equal,
+    };
+    if descending {
+        ordering.reverse()
+    } else {
+        ordering
     }
 }
 
@@ -90,73 +507,809 @@ impl Pagination {
     }
 }
 
-/// Configure bot-related API routes
+/// Version of the API served under [`API_PREFIX`].
+pub const API_VERSION: &str = "v1";
+
+/// Path prefix of the current API version.
+pub const API_PREFIX: &str = "/api/v1";
+
+/// Whether the API is also served at its old unversioned paths (`api_server.legacy_routes`);
+/// it is, unless the app says otherwise.
+#[derive(Clone, Copy, Debug)]
+pub struct LegacyRoutes(pub bool);
+
+/// Configure the API: its routes under [`API_PREFIX`], the list of versions, and the same
+/// routes at their old unversioned paths unless [`LegacyRoutes`] turn them off. The old paths
+/// are deprecated: their responses carry a `Deprecation` header and a `Link` to the
+/// versioned path.
 pub fn configure(cfg: &mut web::ServiceConfig) {
-    cfg.service(add_bot)
+    cfg.service(api_versions)
+        .service(
+            web::scope(API_PREFIX)
+                .wrap(from_fn(auth::redact_for_viewers))
+                .wrap(from_fn(fieldsets::select_fields))
+                .wrap(from_fn(caching::conditional))
+                .configure(routes),
+        )
+        .service(
+            web::scope("")
+                .guard(guard::fn_guard(|ctx| {
+                    (ctx.app_data::<web::Data<LegacyRoutes>>()).is_none_or(|legacy| legacy.0)
+                }))
+                .wrap(from_fn(auth::redact_for_viewers))
+                .wrap(from_fn(fieldsets::select_fields))
+                .wrap(from_fn(caching::conditional))
+                .wrap_fn(|req, srv| {
+                    let successor =
+                        format!("<{}{}>; rel=\"successor-version\"", API_PREFIX, req.path());
+                    let response = srv.call(req);
+                    async move {
+                        let mut response = response.await?;
+                        let headers = response.headers_mut();
+                        headers.insert(
+                            HeaderName::from_static("deprecation"),
+                            HeaderValue::from_static("true"),
+                        );
+                        if let Ok(link) = HeaderValue::from_str(&successor) {
+                            headers.insert(LINK, link);
+                        }
+                        Ok(response)
+                    }
+                })
+                .configure(routes),
+        );
+}
+
+/// API versions this server serves.
+#[get("/api/versions")]
+async fn api_versions() -> impl Responder {
+    let versions = ApiVersionsView {
+        versions: vec![API_VERSION.to_string()],
+        current: API_VERSION.to_string(),
+    };
+    // Versions only change with the server
+    HttpResponse::Ok()
+        .insert_header((CACHE_CONTROL, "public, max-age=3600"))
+        .json(create_api_response(true, Some(versions), None))
+}
+
+/// Bot-related API routes, relative to the API version's prefix.
+fn routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(healthz)
+        .service(readyz)
+        .service(server_version)
+        .service(login)
+        .service(admin_info)
+        .service(admin_prices)
+        .service(admin_config_schema)
+        .service(admin_memory_stats)
+        .service(compact_state)
+        .service(webhook)
+        .service(crate::bot::ws::ws_session)
+        .service(crate::bot::sse::event_stream)
+        .service(crate::bot::changes::changes_feed)
+        .service(add_bot)
+        .service(
+            web::resource("/bots/bulk")
+                .app_data(web::JsonConfig::default().limit(MAX_BULK_BYTES))
+                .route(web::post().to(bulk_bots)),
+        )
+        .service(
+            web::resource("/bots/{bot_id}/listeners/bulk")
+                .app_data(web::JsonConfig::default().limit(MAX_BULK_BYTES))
+                .route(web::post().to(bulk_listeners)),
+        )
         .service(get_bots)
         .service(get_bot)
         .service(update_bot)
         .service(delete_bot)
+        .service(bot_timeline)
+        .service(list_bot_alerts)
+        .service(replay_alert)
+        .service(bot_balances)
+        .service(bot_positions)
+        .service(bot_orders)
+        .service(cancel_bot_order)
+        .service(bot_trades)
+        .service(bot_pnl)
+        .service(bot_stats)
+        .service(execution_quality)
+        .service(strategy_report)
+        .service(allocation_report)
+        .service(montecarlo_report)
+        .service(replay_report)
+        .service(bot_statement)
+        .service(
+            web::resource("/bots/{bot_id}/fills")
+                .app_data(web::PayloadConfig::new(MAX_FILL_IMPORT_BYTES))
+                .route(web::post().to(import_bot_fills)),
+        )
+        .service(export_state)
+        .service(
+            web::resource("/state/import")
+                .app_data(web::PayloadConfig::new(MAX_STATE_IMPORT_BYTES))
+                .route(web::post().to(import_state)),
+        )
+        .service(bot_symbols)
+        .service(map_bot_symbol)
+        .service(unmap_bot_symbol)
         .service(add_listener)
         .service(list_listeners)
+        .service(list_all_listeners)
         .service(get_listener)
         .service(update_listener)
+        .service(rotate_listener_secret)
+        .service(test_fire_listener)
         //.service(delete_listeners)
         .service(delete_listener);
+    #[cfg(feature = "metrics")]
+    cfg.service(server_metrics);
 }
 
-#[post("/bots")]
-async fn add_bot(
+/// Liveness probe: the process is up and answering.
+#[get("/healthz")]
+async fn healthz() -> impl Responder {
+    HttpResponse::Ok().json(create_api_response(true, Some("ok".to_string()), None))
+}
+
+/// Which checks `GET /readyz` makes besides those on the state.
+#[derive(Debug, Default, Deserialize)]
+pub struct ReadinessQuery {
+    /// Also check that the exchanges of the bots can be reached, as far as their circuits
+    /// tell
+    #[serde(default)]
+    pub exchanges: bool,
+}
+
+/// Readiness probe: the state is lockable and its file is still writable, and, if asked,
+/// no exchange of a bot has its circuit open. Answers `503 Service Unavailable` when a check
+/// fails.
+#[get("/readyz")]
+async fn readyz(
     data: web::Data<Arc<Mutex<AppState>>>,
-    json_data: Result<web::Json<BotInsertArgs>, actix_web::Error>,
+    outages: Option<web::Data<Outages>>,
+    query: web::Query<ReadinessQuery>,
+) -> Result<impl Responder, AppError> {
+    let mut view = ReadinessView::default();
+    if let Ok(state) = acquire_lock(&data) {
+        view.state_loaded = true;
+        view.storage_writable = AppState::ensure_writable(&state.state_file).is_ok();
+        if query.exchanges {
+            let now = state.providers.clock.now();
+            view.exchanges = (state.bots.values())
+                .map(|bot| {
+                    let open = (outages.as_ref()).is_some_and(|o| o.is_open(&bot.exchange, now));
+                    (bot.exchange.clone(), !open)
+                })
+                .collect();
+        }
+    }
+    view.ready =
+        view.state_loaded && view.storage_writable && view.exchanges.values().all(|up| *up);
+
+    if !view.ready {
+        let api_response = create_api_response(false, Some(view), Some("Not ready".to_string()));
+        return Ok(HttpResponse::ServiceUnavailable().json(api_response));
+    }
+    Ok(HttpResponse::Ok().json(create_api_response(true, Some(view), None)))
+}
+
+/// The version of the server, and the commit and time it was built from.
+#[get("/version")]
+async fn server_version() -> impl Responder {
+    let view = VersionView {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_hash: Some(env!("XTRADE_GIT_HASH").to_string()).filter(|hash| !hash.is_empty()),
+        built_at: (env!("XTRADE_BUILT_AT").parse().ok())
+            .filter(|secs| *secs > 0)
+            .and_then(|secs| DateTime::from_timestamp(secs, 0)),
+        api_version: API_VERSION.to_string(),
+    };
+    // The build only changes with the server
+    HttpResponse::Ok()
+        .insert_header((CACHE_CONTROL, "public, max-age=3600"))
+        .json(create_api_response(true, Some(view), None))
+}
+
+/// Sign in with a username and password for a token to send as `Authorization: Bearer`.
+#[post("/auth/login")]
+async fn login(
+    data: web::Data<Arc<Mutex<AppState>>>,
+    config: web::Data<AuthConfig>,
+    body: web::Json<LoginArgs>,
 ) -> Result<impl Responder, AppError> {
+    let key = auth::signing_key(&config)
+        .ok_or_else(|| AppError::NotFound("Users are not enabled on this server.".to_string()))?;
+    let args = body.into_inner();
+    args.validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+    let (users, now) = {
+        let state = acquire_lock(&data)?;
+        (state.users.clone(), state.providers.clock.now())
+    };
+    // Hashing takes a while; other requests need not wait for it
+    let username = args.username.clone();
+    let role = web::block(move || users.authenticate(&args.username, &args.password))
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))??;
+    let ttl = chrono::Duration::seconds(config.token_ttl_secs as i64);
+    let (token, expires_at) = auth::issue(key, &username, now, ttl)?;
+    let view = LoginView {
+        token,
+        token_type: "Bearer".to_string(),
+        expires_at,
+        role,
+    };
+    Ok(HttpResponse::Ok().json(create_api_response(true, Some(view), None)))
+}
+
+/// Server facts for operators, including the recovery report after an unclean shutdown.
+#[get("/admin/info")]
+async fn admin_info(
+    data: web::Data<Arc<Mutex<AppState>>>,
+    info: web::Data<ServerInfo>,
+    _admin: Admin,
+) -> Result<impl Responder, AppError> {
+    let state = acquire_lock(&data)?;
+    let view = AdminInfoView {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        pid: std::process::id(),
+        started_at: info.started_at,
+        state_file: state.state_file.clone(),
+        bots: state.bots.len(),
+        last_successful_save: state.saved_at,
+        recovery: info.recovery.clone(),
+        reconciliation: info
+            .reconciliation
+            .lock()
+            .ok()
+            .and_then(|report| report.clone()),
+        stale_alerts: info.stale_alerts.load(Ordering::Relaxed),
+        maintenance: state.maintenance.upcoming(state.providers.clock.now()),
+    };
+    drop(state);
+
+    Ok(HttpResponse::Ok().json(create_api_response(true, Some(view), None)))
+}
+
+/// Prices currently held by the price cache.
+#[get("/admin/prices")]
+async fn admin_prices(
+    prices: web::Data<PriceCache>,
+    _admin: Admin,
+) -> Result<impl Responder, AppError> {
+    let now = Utc::now();
+    let view = PriceListView(
+        prices
+            .quotes()
+            .into_iter()
+            .map(|(symbol, quote)| PriceView {
+                symbol,
+                price: quote.price,
+                observed_at: quote.observed_at,
+                age_secs: (now - quote.observed_at).num_seconds(),
+                stale: prices.is_stale(&quote, now),
+            })
+            .collect(),
+    );
+    Ok(HttpResponse::Ok().json(create_api_response(true, Some(view), None)))
+}
+
+/// JSON Schema of the configuration file, which the admin UI builds its settings form from.
+#[get("/admin/config/schema")]
+async fn admin_config_schema(_admin: Admin) -> impl Responder {
+    // The schema only changes with the server
+    HttpResponse::Ok()
+        .insert_header((CACHE_CONTROL, "private, max-age=3600"))
+        .json(create_api_response(
+            true,
+            Some(crate::app_config::schema::config_schema()),
+            None,
+        ))
+}
+
+/// Header carrying the hex HMAC-SHA256 of a webhook body under the listener's secret.
+pub const SIGNATURE_HEADER: &str = "X-Xtrade-Signature";
+
+/// Credentials of a webhook request; TradingView cannot send headers, so they travel in the
+/// URL, or in the alert's `secret` field. Relays that can sign the body send
+/// [`SIGNATURE_HEADER`] instead.
+#[derive(Debug, Deserialize)]
+pub struct WebhookQuery {
+    pub secret: Option<String>,
+    /// Tenant owning the bot; required when tenancy is enabled
+    pub tenant: Option<String>,
+}
+
+/// Execute an alert received by a listener on the exchange of its bot.
+#[post("/webhook/{bot_id}/{listener_id}")]
+#[allow(clippy::too_many_arguments)]
+async fn webhook(
+    req: HttpRequest,
+    data: web::Data<Arc<Mutex<AppState>>>,
+    outages: web::Data<Outages>,
+    prices: web::Data<PriceCache>,
+    client: web::Data<reqwest::Client>,
+    path: web::Path<(String, String)>,
+    query: web::Query<WebhookQuery>,
+    body: String,
+) -> Result<impl Responder, AppError> {
+    let (bot_id, listener_id) = path.into_inner();
+    let tenant = match req.app_data::<web::Data<TenancyConfig>>() {
+        Some(config) if config.enabled => Some(
+            query
+                .tenant
+                .as_deref()
+                .filter(|tenant| config.tenants.contains_key(*tenant))
+                .ok_or_else(|| AppError::BotNotFound(bot_id.clone()))?,
+        ),
+        _ => None,
+    };
+
+    let credentials = dispatch::Credentials {
+        secret: query.secret.as_deref(),
+        signature: req
+            .headers()
+            .get(SIGNATURE_HEADER)
+            .and_then(|value| value.to_str().ok()),
+        body: &body,
+    };
+    let (source, alert_id) = {
+        let mut state = acquire_lock(&data)?;
+        let source =
+            dispatch::authenticate(&mut state, tenant, &bot_id, &listener_id, &credentials)?;
+        let alert_id = log_alert(&mut state, tenant, &bot_id, &listener_id, &body, None);
+        (source, alert_id)
+    };
+    let outcome = receive(
+        &req,
+        &data,
+        &outages,
+        &prices,
+        &client,
+        tenant,
+        (&bot_id, &listener_id),
+        &source,
+        &body,
+    )
+    .await;
+    settle_alert(&data, &alert_id, &outcome)?;
+    Ok(execution_response(outcome?))
+}
+
+/// Add an alert accepted by `listener_id` of `bot_id` to the alert log, returning its ID.
+fn log_alert(
+    state: &mut AppState,
+    tenant: Option<&str>,
+    bot_id: &str,
+    listener_id: &str,
+    payload: &str,
+    replay_of: Option<String>,
+) -> String {
+    let id = state.providers.ids.next_id();
+    let received_at = state.providers.clock.now();
+    state.alerts.record(AlertRecord {
+        id: id.clone(),
+        received_at,
+        bot_id: bot_id.to_string(),
+        listener_id: listener_id.to_string(),
+        tenant: tenant.map(str::to_string),
+        payload: payload.to_string(),
+        replay_of,
+        error: None,
+        result: None,
+    });
+    id
+}
+
+/// Record the outcome of the logged alert `alert_id` and persist it.
+fn settle_alert(
+    data: &web::Data<Arc<Mutex<AppState>>>,
+    alert_id: &str,
+    outcome: &Result<ExecutionResult, AppError>,
+) -> Result<(), AppError> {
+    let mut state = acquire_lock(data)?;
+    state
+        .alerts
+        .settle(alert_id, outcome.as_ref().map_err(ToString::to_string));
+    if let Err(e) = state.save::<PathBuf>(None) {
+        log::error!("Failed to save the outcome of alert {}: {}", alert_id, e);
+    }
+    Ok(())
+}
+
+/// Parse, check and dispatch a webhook alert whose listener authenticated it. Stale alerts
+/// are refused, and repeats answered with the first outcome.
+#[allow(clippy::too_many_arguments)]
+async fn receive(
+    req: &HttpRequest,
+    data: &web::Data<Arc<Mutex<AppState>>>,
+    outages: &Outages,
+    prices: &PriceCache,
+    client: &reqwest::Client,
+    tenant: Option<&str>,
+    (bot_id, listener_id): (&str, &str),
+    source: &str,
+    body: &str,
+) -> Result<ExecutionResult, AppError> {
+    let alert = dispatch::parse_alert(source, body, bot_id)?;
+    let config = req.app_data::<web::Data<WebhookServerConfig>>();
+    if let Some(config) = config {
+        let now = acquire_lock(data)?.providers.clock.now();
+        if let Err(e) = dispatch::check_fresh(&alert, now, config.max_alert_age_secs) {
+            if let Some(info) = req.app_data::<web::Data<ServerInfo>>() {
+                info.stale_alerts.fetch_add(1, Ordering::Relaxed);
+            }
+            log::warn!(
+                "Refusing stale alert for bot '{}' via listener '{}': {}",
+                bot_id,
+                listener_id,
+                e
+            );
+            return Err(e);
+        }
+    }
+
+    let window_secs = config.map_or(0, |config| config.dedup_window_secs);
+    let claim = if window_secs > 0 {
+        let mut state = acquire_lock(data)?;
+        match dispatch::deduplicate(&mut state, tenant, listener_id, &alert, body, window_secs)? {
+            Delivery::First(key) => Some(key),
+            Delivery::Repeat(result) => {
+                log::info!(
+                    "Duplicate alert answered with the first outcome: {}",
+                    result
+                );
+                return Ok(*result);
+            }
+        }
+    } else {
+        None
+    };
+
+    let result =
+        dispatch::dispatch(data, outages, prices, tenant, listener_id, alert, client).await;
+    if let Some(key) = claim {
+        // Saved with the outcome of the alert
+        let mut state = acquire_lock(data)?;
+        state.recent_alerts.settle(&key, result.as_ref().ok());
+    }
+    result
+}
+
+/// The reply to a webhook caller for the outcome of its alert.
+fn execution_response(result: ExecutionResult) -> HttpResponse {
+    let warnings = result.warnings.clone();
+    match result.status {
+        ExecutionStatus::Failed => {
+            let error = Some(result.message.clone());
+            let error_code = result.error_code;
+            HttpResponse::BadGateway().json(
+                ApiResponse {
+                    error_code,
+                    ..create_api_response(false, Some(result), error)
+                }
+                .with_warnings(warnings),
+            )
+        }
+        ExecutionStatus::Buffered => HttpResponse::Accepted()
+            .json(create_api_response(true, Some(result), None).with_warnings(warnings)),
+        _ => HttpResponse::Ok()
+            .json(create_api_response(true, Some(result), None).with_warnings(warnings)),
+    }
+}
+
+/// Alerts the listeners of the bot accepted, newest first, with their outcome.
+#[get("/bots/{bot_id}/alerts")]
+async fn list_bot_alerts(
+    data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
+    bot_id: web::Path<String>,
+    query: web::Query<AlertListArgs>,
+) -> Result<impl Responder, AppError> {
+    let mut args = query.into_inner();
+    args.bot_id = bot_id.into_inner();
+
     let mut state = acquire_lock(&data)?;
-    match json_data {
-        Ok(good_json_data) => {
-            let bot = state.add_bot(good_json_data.into_inner())?;
-            let location = format!("/bots/{}", bot.bot_id);
-            let api_response = create_api_response(true, Some(bot.clone()), None);
+    state.scoped(tenant.name()).get_bot_ref(&args.bot_id)?;
+    let alerts = state
+        .alerts
+        .list(tenant.name(), &args.bot_id, args.limit.unwrap_or(20));
+    drop(state);
 
-            Ok(HttpResponse::Created()
-                .insert_header(("Location", location))
-                .json(api_response))
+    let view = AlertListView {
+        bot_id: args.bot_id,
+        alerts,
+    };
+    Ok(HttpResponse::Ok().json(create_api_response(true, Some(view), None)))
+}
+
+/// Dispatch a failed alert again from its logged payload, e.g. after fixing the bot's
+/// credentials. The replay skips the freshness and duplicate checks and is logged as a new
+/// alert.
+#[post("/alerts/{alert_id}/replay")]
+async fn replay_alert(
+    data: web::Data<Arc<Mutex<AppState>>>,
+    outages: web::Data<Outages>,
+    prices: web::Data<PriceCache>,
+    client: web::Data<reqwest::Client>,
+    tenant: Tenant,
+    alert_id: web::Path<String>,
+) -> Result<impl Responder, AppError> {
+    let alert_id = alert_id.into_inner();
+    let tenant = tenant.name();
+    let (original, source, replay_id) = {
+        let mut state = acquire_lock(&data)?;
+        let original = state
+            .alerts
+            .get(tenant, &alert_id)
+            .cloned()
+            .ok_or_else(|| AppError::NotFound(format!("Alert '{}' not found.", alert_id)))?;
+        if !original.failed() {
+            return Err(AppError::InvalidInput(format!(
+                "Alert '{}' is {}; only failed alerts can be replayed",
+                alert_id,
+                original.status()
+            )));
         }
+        let source = state
+            .scoped(tenant)
+            .get_listener_ref(&original.bot_id, &original.listener_id)?
+            .service
+            .to_lowercase();
+        let replay_id = log_alert(
+            &mut state,
+            tenant,
+            &original.bot_id,
+            &original.listener_id,
+            &original.payload,
+            Some(alert_id.clone()),
+        );
+        (original, source, replay_id)
+    };
+    log::info!("Replaying alert {} as {}", alert_id, replay_id);
+
+    let outcome = match dispatch::parse_alert(&source, &original.payload, &original.bot_id) {
+        Ok(alert) => {
+            dispatch::dispatch(
+                &data,
+                &outages,
+                &prices,
+                tenant,
+                &original.listener_id,
+                alert,
+                &client,
+            )
+            .await
+        }
+        Err(e) => Err(e),
+    };
+    settle_alert(&data, &replay_id, &outcome)?;
+    Ok(execution_response(outcome?))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TestFireQuery {
+    /// Dispatch the alert rather than only simulate it
+    #[serde(default)]
+    pub execute: bool,
+}
+
+/// What the listener would make of the alert in the body, without its secret, e.g. to check
+/// a new alert template. With `execute`, the alert is then dispatched like a replay, and
+/// logged as received.
+#[post("/bots/{bot_id}/listeners/{listener_id}/test-fire")]
+#[allow(clippy::too_many_arguments)]
+async fn test_fire_listener(
+    data: web::Data<Arc<Mutex<AppState>>>,
+    outages: web::Data<Outages>,
+    prices: web::Data<PriceCache>,
+    client: web::Data<reqwest::Client>,
+    tenant: Tenant,
+    path: web::Path<(String, String)>,
+    query: web::Query<TestFireQuery>,
+    body: String,
+) -> Result<impl Responder, AppError> {
+    let (bot_id, listener_id) = path.into_inner();
+    let tenant = tenant.name();
+    let ids = (bot_id.as_str(), listener_id.as_str());
+    let (mut view, execution) = {
+        let mut state = acquire_lock(&data)?;
+        let view = dispatch::simulate(&mut state, &prices, tenant, ids, &body)?;
+        let execution = if query.execute {
+            let alert = dispatch::listener_alert(&mut state, tenant, ids, &body)?;
+            let alert_id = log_alert(&mut state, tenant, &bot_id, &listener_id, &body, None);
+            Some((alert, alert_id))
+        } else {
+            None
+        };
+        (view, execution)
+    };
+
+    if let Some((alert, alert_id)) = execution {
+        log::info!(
+            "Test-firing alert {} via listener '{}'",
+            alert_id,
+            listener_id
+        );
+        let outcome = dispatch::dispatch(
+            &data,
+            &outages,
+            &prices,
+            tenant,
+            &listener_id,
+            alert,
+            &client,
+        )
+        .await;
+        settle_alert(&data, &alert_id, &outcome)?;
+        let result = outcome?;
+        view.warnings.clone_from(&result.warnings);
+        view.result = Some(result);
+    }
+    let warnings = view.warnings.clone();
+    Ok(
+        HttpResponse::Ok()
+            .json(create_api_response(true, Some(view), None).with_warnings(warnings)),
+    )
+}
+
+/// The server's metrics, in the Prometheus text format.
+#[cfg(feature = "metrics")]
+#[get("/metrics")]
+async fn server_metrics(
+    data: web::Data<Arc<Mutex<AppState>>>,
+    metrics: web::Data<Metrics>,
+    _admin: Admin,
+) -> Result<impl Responder, AppError> {
+    let exchanges = acquire_lock(&data)?.exchange_stats.render();
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(metrics.render() + &exchanges))
+}
+
+/// Prune the alerts, events, trades and log lines the retention policy no longer keeps,
+/// with the overrides of the request body.
+#[post("/admin/compact")]
+async fn compact_state(
+    data: web::Data<Arc<Mutex<AppState>>>,
+    metrics: web::Data<Metrics>,
+    _admin: Admin,
+    json_data: Result<web::Json<StateCompactArgs>, actix_web::Error>,
+) -> Result<impl Responder, AppError> {
+    let args = json_data
+        .map_err(|e| {
+            log::error!("Failed to deserialize input: {}", e);
+            AppError::InvalidInput("Invalid input payload".to_string())
+        })?
+        .into_inner();
+
+    let mut state = acquire_lock(&data)?;
+    let policy = compaction::policy(&state.retention, &args);
+    let view = compaction::compact(&mut state, &policy)?;
+    compaction::count(&metrics, &view, state.providers.clock.now());
+    drop(state);
+
+    let api_response = create_api_response(true, Some(view), None);
+    Ok(HttpResponse::Ok().json(api_response))
+}
+
+/// Entity counts and sizes of the state, for capacity planning.
+#[get("/admin/stats/memory")]
+async fn admin_memory_stats(
+    data: web::Data<Arc<Mutex<AppState>>>,
+    prices: web::Data<PriceCache>,
+    _admin: Admin,
+) -> Result<impl Responder, AppError> {
+    let state = acquire_lock(&data)?;
+    let counts = EntityCounts {
+        bots: state.bots.len(),
+        listeners: state.bots.values().map(|bot| bot.listeners.len()).sum(),
+        events: state.events.len(),
+        journal_entries: state.journal.len(),
+        alerts: state.alerts.len(),
+        recent_alerts: state.recent_alerts.len(),
+        cached_prices: prices.quotes().len(),
+    };
+    let mut sizes = StateSizes {
+        bots_bytes: serialized_len(&state.bots),
+        events_bytes: serialized_len(&state.events),
+        journal_bytes: serialized_len(&state.journal),
+        ..Default::default()
+    };
+    let state_file = state.state_file.clone();
+    drop(state);
+
+    sizes.file_bytes = std::fs::metadata(&state_file).ok().map(|meta| meta.len());
+    (sizes.trade_history_shards, sizes.trade_history_bytes) =
+        TradeHistory::for_state_file(&state_file)
+            .disk_usage()
+            .unwrap_or_else(|e| {
+                log::warn!("Failed to measure the trade history: {}", e);
+                (0, 0)
+            });
+
+    let view = MemoryStatsView {
+        counts,
+        state: sizes,
+        allocator: crate::utils::alloc::stats(),
+    };
+    Ok(HttpResponse::Ok().json(create_api_response(true, Some(view), None)))
+}
+
+#[post("/bots")]
+async fn add_bot(
+    data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
+    client: web::Data<reqwest::Client>,
+    json_data: Result<web::Json<BotInsertArgs>, actix_web::Error>,
+) -> Result<impl Responder, AppError> {
+    let mut args = match json_data {
+        Ok(good_json_data) => good_json_data.into_inner(),
         Err(e) => {
             log::error!("Failed to deserialize input: {}", e);
-            Err(AppError::InvalidInput("Invalid input payload".to_string()))
+            return Err(AppError::InvalidInput("Invalid input payload".to_string()));
         }
-    }
+    };
+    // Ask the exchange about the key without holding the lock
+    let key_check = acquire_lock(&data)?.key_check.clone();
+    let check = keycheck::check(&args.clone().into(), &key_check, &client).await?;
+    args.key_permissions = check.permissions;
+
+    let mut state = acquire_lock(&data)?;
+    let bot = state.scoped(tenant.name()).add_bot(args)?;
+    let location = format!("/bots/{}", bot.bot_id);
+    let mut warnings = state.take_warnings();
+    warnings.extend(check.warnings);
+    let api_response = create_api_response(true, Some(bot.clone()), None).with_warnings(warnings);
+
+    Ok(HttpResponse::Created()
+        .insert_header(("Location", location))
+        .json(api_response))
 }
 
 #[get("/bots")]
 async fn get_bots(
     data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
     query: Option<web::Query<Pagination>>, // Pagination query is optional
-    filter: Option<web::Json<BotListArgs>>, // Optional filter in the request body
+    query_filter_args: Result<web::Query<BotListArgs>, actix_web::Error>,
+    list_query: web::Query<ListQuery>,
+    filter: Option<web::Json<BotListArgs>>, // Filter in the request body, from older clients
 ) -> Result<impl Responder, AppError> {
     // Use default pagination values if none are provided
-    let pagination = query.unwrap_or_else(|| {
-        web::Query(Pagination {
-            page: Some(1),
-            limit: Some(10),
-        })
-    });
+    let pagination = query.unwrap_or(web::Query(Pagination {
+        page: Some(1),
+        limit: Some(10),
+    }));
 
-    // Validate the pagination parameters
+    // Validate the pagination and sort parameters
     pagination.validate()?;
+    list_query.validate(&BOT_SORTS)?;
 
-    // Acquire the state lock
-    let state = acquire_lock(&data)?;
+    let query_filter_args = query_filter(query_filter_args)?;
+    if query_filter_args.has_secret_filters() {
+        return Err(AppError::InvalidInput(format!(
+            "Filter by {} in the request body; query strings are logged",
+            SECRET_FILTERS.join(" or ")
+        )));
+    }
+    // Filters of the body apply over those of the query
+    let filter_args = Some(match filter {
+        Some(filter) => filter.into_inner().or(query_filter_args),
+        None => query_filter_args,
+    });
 
-    // Extract optional filter arguments
-    let filter_args = filter.map(|f| f.into_inner());
+    // Acquire the state lock
+    let mut state = acquire_lock(&data)?;
 
     // Fetch the list of bots
-    let bots = state.list_bots(filter_args)?;
+    let bots = state.scoped(tenant.name()).list_bots(filter_args)?;
     drop(state); // Release the state lock early
+    let bots = list_query.sort(bots.0, &BOT_SORTS)?;
 
     // Apply pagination
-    let paginated_bots = apply_pagination(&bots.0, pagination.page(), pagination.limit());
+    let paginated_bots = apply_pagination(&bots, pagination.page(), pagination.limit());
 
     // If no bots exist, return an empty response
     if paginated_bots.is_empty() {
@@ -175,10 +1328,13 @@ async fn get_bots(
 #[get("/bots/{bot_id}")]
 async fn get_bot(
     data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
     bot_id: web::Path<String>,
 ) -> Result<impl Responder, AppError> {
-    let state = acquire_lock(&data)?;
-    let bot = state.get_bot(BotGetArgs::new(&bot_id))?;
+    let mut state = acquire_lock(&data)?;
+    let bot = state
+        .scoped(tenant.name())
+        .get_bot(BotGetArgs::new(&bot_id))?;
     let api_response = create_api_response(true, Some(bot), None);
     Ok(HttpResponse::Ok().json(api_response))
 }
@@ -186,6 +1342,7 @@ async fn get_bot(
 #[put("/bots/{bot_id}")]
 async fn update_bot(
     data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
     bot_id: web::Path<String>,
     json_data: Result<web::Json<BotUpdateArgs>, actix_web::Error>,
 ) -> Result<impl Responder, AppError> {
@@ -194,8 +1351,9 @@ async fn update_bot(
         Ok(good_json_data) => {
             let mut update_data = good_json_data.into_inner();
             update_data.bot_id = bot_id.to_string();
-            let bot = state.update_bot(update_data)?;
-            let api_response = create_api_response(true, Some(bot), None);
+            let bot = state.scoped(tenant.name()).update_bot(update_data)?;
+            let api_response =
+                create_api_response(true, Some(bot), None).with_warnings(state.take_warnings());
             Ok(HttpResponse::Ok().json(api_response))
         }
         Err(e) => {
@@ -208,18 +1366,708 @@ async fn update_bot(
 #[delete("/bots/{bot_id}")]
 async fn delete_bot(
     data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
     bot_id: web::Path<String>,
 ) -> Result<impl Responder, AppError> {
     let mut state = acquire_lock(&data)?;
-    state.delete_bot(BotDeleteArgs::new(&bot_id))?;
+    state
+        .scoped(tenant.name())
+        .delete_bot(BotDeleteArgs::new(&bot_id))?;
     let api_response =
         create_api_response(true, Some("Bot deleted successfully".to_string()), None);
     Ok(HttpResponse::Ok().json(api_response))
 }
 
+#[get("/bots/{bot_id}/timeline")]
+async fn bot_timeline(
+    data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
+    bot_id: web::Path<String>,
+    query: web::Query<BotTimelineArgs>, // Cursor, time and page size filters
+) -> Result<impl Responder, AppError> {
+    let mut args = query.into_inner();
+    args.bot_id = bot_id.into_inner();
+
+    let mut state = acquire_lock(&data)?;
+    let timeline = state.scoped(tenant.name()).bot_timeline(args)?;
+    drop(state);
+
+    let api_response = create_api_response(true, Some(timeline), None);
+    Ok(HttpResponse::Ok().json(api_response))
+}
+
+/// A copy of the bot `bot_id`, holding the secrets its credentials refer to, and the adapter
+/// of its exchange.
+fn bot_and_venue(
+    data: &web::Data<Arc<Mutex<AppState>>>,
+    tenant: &Tenant,
+    bot_id: &str,
+) -> Result<(Bot, Box<dyn Exchange + Send + Sync>), AppError> {
+    let mut state = acquire_lock(data)?;
+    let bot = state.scoped(tenant.name()).get_bot_ref(bot_id)?.clone();
+    let paper = state.paper.open_positions(&bot.bot_id);
+    drop(state);
+    let bot = Secrets::default()
+        .resolve_bot(&bot)
+        .map_err(AppError::ConfigError)?;
+    let venue = exchange::get_account(&bot, paper).ok_or_else(|| {
+        AppError::InvalidInput(format!("no adapter for exchange '{}'", bot.exchange))
+    })?;
+    Ok((bot, venue))
+}
+
+/// What answers the account queries of `bot`, for messages.
+fn venue_name(bot: &Bot) -> String {
+    if bot.dry_run {
+        format!("Paper trading on {}", bot.exchange)
+    } else {
+        bot.exchange.clone()
+    }
+}
+
+/// Error for a failed account query to the exchange of `bot`.
+fn account_error(bot: &Bot, error: &(dyn std::error::Error + 'static)) -> AppError {
+    let message = format!("{}: {}", venue_name(bot), error);
+    if exchange::is_unreachable(error) {
+        AppError::ConnectionError(message)
+    } else {
+        AppError::UnexpectedResponse(message)
+    }
+}
+
+/// Balances of the bot's exchange account, asked of the exchange.
+#[get("/bots/{bot_id}/balances")]
+async fn bot_balances(
+    data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
+    client: web::Data<reqwest::Client>,
+    bot_id: web::Path<String>,
+) -> Result<impl Responder, AppError> {
+    let (bot, venue) = bot_and_venue(&data, &tenant, &bot_id)?;
+    let balances = venue
+        .get_balances(&bot, &client)
+        .await
+        .map_err(|e| account_error(&bot, e.as_ref()))?
+        .ok_or_else(|| {
+            AppError::InvalidInput(format!("{} cannot list balances", venue_name(&bot)))
+        })?;
+
+    let api_response = create_api_response(true, Some(BalanceListView(balances)), None);
+    Ok(HttpResponse::Ok().json(api_response))
+}
+
+/// Open positions of the bot's exchange account, asked of the exchange; those of its paper
+/// positions for `dry_run` bots.
+#[get("/bots/{bot_id}/positions")]
+async fn bot_positions(
+    data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
+    client: web::Data<reqwest::Client>,
+    bot_id: web::Path<String>,
+) -> Result<impl Responder, AppError> {
+    let (bot, venue) = bot_and_venue(&data, &tenant, &bot_id)?;
+    let positions = venue
+        .get_positions(&bot, &client)
+        .await
+        .map_err(|e| account_error(&bot, e.as_ref()))?
+        .ok_or_else(|| {
+            AppError::InvalidInput(format!("{} cannot list positions", venue_name(&bot)))
+        })?;
+
+    let api_response = create_api_response(true, Some(PositionListView(positions)), None);
+    Ok(HttpResponse::Ok().json(api_response))
+}
+
+/// Orders of the bot still open on its exchange, asked of the exchange.
+#[get("/bots/{bot_id}/orders")]
+async fn bot_orders(
+    data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
+    client: web::Data<reqwest::Client>,
+    bot_id: web::Path<String>,
+) -> Result<impl Responder, AppError> {
+    let (bot, venue) = bot_and_venue(&data, &tenant, &bot_id)?;
+    let orders = venue
+        .open_orders(&bot, &client)
+        .await
+        .map_err(|e| account_error(&bot, e.as_ref()))?
+        .ok_or_else(|| {
+            AppError::InvalidInput(format!("{} cannot list open orders", venue_name(&bot)))
+        })?;
+
+    let api_response = create_api_response(true, Some(OpenOrderListView(orders)), None);
+    Ok(HttpResponse::Ok().json(api_response))
+}
+
+/// A page of the bot's trade history, newest first. Trades of deleted bots stay listed.
+#[get("/bots/{bot_id}/trades")]
+async fn bot_trades(
+    data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
+    bot_id: web::Path<String>,
+    query: Option<web::Query<Pagination>>, // Pagination query is optional
+) -> Result<impl Responder, AppError> {
+    let pagination = query.unwrap_or(web::Query(Pagination {
+        page: Some(1),
+        limit: Some(10),
+    }));
+    pagination.validate()?;
+
+    let (history, key, exists) = trade_history(&data, &tenant, &bot_id)?;
+    let trades = history
+        .page(&key, pagination.page(), pagination.limit())
+        .map_err(history_error)?;
+    // Only a bot that neither exists nor ever traded is unknown
+    if trades.is_empty() && pagination.page() == 1 && !exists {
+        return Err(bot_not_found(&bot_id));
+    }
+
+    let api_response = create_api_response(true, Some(TradeListView(trades)), None);
+    Ok(HttpResponse::Ok().json(api_response))
+}
+
+/// Realized and unrealized PnL of the bot's recorded trades, per symbol and in total.
+#[get("/bots/{bot_id}/pnl")]
+async fn bot_pnl(
+    data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
+    prices: web::Data<PriceCache>,
+    bot_id: web::Path<String>,
+) -> Result<impl Responder, AppError> {
+    let (history, key, exists) = trade_history(&data, &tenant, &bot_id)?;
+    let pnl = Portfolio::load(&history, &key)
+        .map_err(history_error)?
+        .pnl(&bot_id, &prices);
+    if pnl.symbols.is_empty() && !exists {
+        return Err(bot_not_found(&bot_id));
+    }
+
+    let api_response = create_api_response(true, Some(pnl), None);
+    Ok(HttpResponse::Ok().json(api_response))
+}
+
+/// Latency and error rate of the orders the bot sent to its exchanges since the server
+/// started.
+#[get("/bots/{bot_id}/stats")]
+async fn bot_stats(
+    data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
+    bot_id: web::Path<String>,
+) -> Result<impl Responder, AppError> {
+    let mut state = acquire_lock(&data)?;
+    let scope = state.scoped(tenant.name());
+    scope.get_bot_ref(&bot_id)?;
+    let key = scope.key(&bot_id);
+    let view = state.exchange_stats.view(&bot_id, &key);
+    drop(state);
+
+    Ok(HttpResponse::Ok().json(create_api_response(true, Some(view), None)))
+}
+
+/// The trade history, and the state key and ID of `bot_id` or else of every bot of `tenant`,
+/// for reports over the history of several bots.
+fn report_bots(
+    data: &web::Data<Arc<Mutex<AppState>>>,
+    tenant: &Tenant,
+    bot_id: Option<&str>,
+) -> Result<(TradeHistory, Vec<(String, String)>), AppError> {
+    let mut state = acquire_lock(data)?;
+    let history = TradeHistory::for_state_file(&state.state_file);
+    let scope = state.scoped(tenant.name());
+    let bot_ids = match bot_id {
+        Some(bot_id) => {
+            scope.get_bot_ref(bot_id)?;
+            vec![bot_id.to_string()]
+        }
+        None => scope.bot_ids(),
+    };
+    let bots = bot_ids
+        .into_iter()
+        .map(|bot_id| (scope.key(&bot_id), bot_id))
+        .collect();
+    Ok((history, bots))
+}
+
+/// Slippage of the fills of alerts from their alert prices, per bot and listener.
+#[get("/reports/execution-quality")]
+async fn execution_quality(
+    data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
+    query: web::Query<ExecutionQualityArgs>,
+) -> Result<impl Responder, AppError> {
+    let args = query.into_inner();
+    let (history, bots) = report_bots(&data, &tenant, args.bot_id.as_deref())?;
+    let mut listeners = Vec::new();
+    for (key, bot_id) in bots {
+        let report = QualityReport::load(&history, &key, args.since).map_err(history_error)?;
+        listeners.extend(report.listeners(&bot_id));
+    }
+    let view = ExecutionQualityView {
+        since: args.since,
+        listeners,
+    };
+    Ok(HttpResponse::Ok().json(create_api_response(true, Some(view), None)))
+}
+
+/// Win rate, profit factor, drawdown and Sharpe ratio of each listener's trades, per bot.
+#[get("/reports/strategies")]
+async fn strategy_report(
+    data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
+    query: web::Query<StrategyReportArgs>,
+) -> Result<impl Responder, AppError> {
+    let args = query.into_inner();
+    let (history, bots) = report_bots(&data, &tenant, args.bot_id.as_deref())?;
+    let mut strategies = Vec::new();
+    for (key, bot_id) in bots {
+        let report = StrategyReport::load(&history, &key, args.since).map_err(history_error)?;
+        strategies.extend(report.strategies(&bot_id));
+    }
+    let view = StrategyReportView {
+        since: args.since,
+        strategies,
+    };
+    Ok(HttpResponse::Ok().json(create_api_response(true, Some(view), None)))
+}
+
+/// Exposure of each bot against its budget, with rebalancing suggestions for bots that
+/// drifted from it.
+#[get("/reports/allocation")]
+async fn allocation_report(
+    data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
+    prices: web::Data<PriceCache>,
+    query: web::Query<AllocationReportArgs>,
+) -> Result<impl Responder, AppError> {
+    let args = query.into_inner();
+    let (history, bots) = report_bots(&data, &tenant, args.bot_id.as_deref())?;
+    let (config, budgets) = {
+        let mut state = acquire_lock(&data)?;
+        let config = state.allocation.clone();
+        let scope = state.scoped(tenant.name());
+        let budgets: Vec<_> = bots
+            .iter()
+            .map(|(_, bot_id)| scope.get_bot_ref(bot_id).ok().and_then(|bot| bot.budget))
+            .collect();
+        (config, budgets)
+    };
+    let mut allocations = Vec::new();
+    for ((key, bot_id), budget) in bots.into_iter().zip(budgets) {
+        let exposure = Portfolio::load(&history, &key)
+            .map_err(history_error)?
+            .exposure(&prices);
+        allocations.push(allocation::allocation(&bot_id, budget, exposure, &config));
+    }
+    allocations.sort_by(|a, b| a.bot_id.cmp(&b.bot_id));
+    let view = AllocationView {
+        capital: config.capital,
+        exposure: allocations.iter().map(|bot| bot.exposure).sum(),
+        bots: allocations,
+    };
+    Ok(HttpResponse::Ok().json(create_api_response(true, Some(view), None)))
+}
+
+/// Percentiles of the drawdowns and final PnLs of a bot's round trips resampled into random
+/// sequences, and the risk of ruin for a starting capital.
+#[get("/reports/montecarlo")]
+async fn montecarlo_report(
+    data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
+    query: web::Query<MonteCarloArgs>,
+) -> Result<impl Responder, AppError> {
+    let args = query.into_inner();
+    args.validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+    if args.capital.is_some_and(|capital| capital <= Decimal::ZERO) {
+        return Err(AppError::InvalidInput(
+            "capital must be positive".to_string(),
+        ));
+    }
+    if args
+        .ruin
+        .is_some_and(|ruin| ruin <= Decimal::ZERO || ruin > Decimal::ONE)
+    {
+        return Err(AppError::InvalidInput(
+            "ruin must be a share of the capital, above 0 and at most 1".to_string(),
+        ));
+    }
+
+    let (history, bots) = report_bots(&data, &tenant, Some(&args.bot_id))?;
+    let mut pnls = Vec::new();
+    for (key, _) in bots {
+        let report = StrategyReport::load(&history, &key, args.since).map_err(history_error)?;
+        pnls.extend(report.round_trips());
+    }
+    let mut rng = match args.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let view = montecarlo::simulate(&pnls, &args, &mut rng);
+    Ok(HttpResponse::Ok().json(create_api_response(true, Some(view), None)))
+}
+
+/// A bot's alerts of a day replayed by the paper engine, with its current risk limits and
+/// with those the query asks for instead.
+#[get("/reports/replay")]
+async fn replay_report(
+    data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
+    query: web::Query<ReplayArgs>,
+) -> Result<impl Responder, AppError> {
+    let args = query.into_inner();
+    args.validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+    let day = {
+        let mut state = acquire_lock(&data)?;
+        replay::day(&mut state, tenant.name(), &args)?
+    };
+    let view = day.replay(&args)?;
+    Ok(HttpResponse::Ok().json(create_api_response(true, Some(view), None)))
+}
+
+/// A bot's monthly statement as a standalone HTML page; `month` is `YYYY-MM`.
+#[get("/bots/{bot_id}/statements/{month}")]
+async fn bot_statement(
+    data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
+    path: web::Path<(String, String)>,
+) -> Result<impl Responder, AppError> {
+    let (bot_id, month) = path.into_inner();
+    if statement::parse_month(&month).is_none() {
+        return Err(AppError::InvalidInput(format!(
+            "'{}' is not a month (YYYY-MM)",
+            month
+        )));
+    }
+    let (history, key, exchange, now) = {
+        let mut state = acquire_lock(&data)?;
+        let history = TradeHistory::for_state_file(&state.state_file);
+        let now = state.providers.clock.now();
+        let scope = state.scoped(tenant.name());
+        let exchange = scope.get_bot_ref(&bot_id)?.exchange.clone();
+        (history, scope.key(&bot_id), exchange, now)
+    };
+    let statement =
+        Statement::load(&history, &key, &bot_id, &exchange, &month, now).map_err(history_error)?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(statement.to_html()))
+}
+
+/// Largest CSV `POST /bots/{bot_id}/fills` accepts.
+const MAX_FILL_IMPORT_BYTES: usize = 32 * 1024 * 1024;
+
+/// Backfill the bot's trade history from a CSV of fills exported by its exchange. Fills
+/// recorded already are skipped, so a file can be imported again.
+async fn import_bot_fills(
+    data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
+    bot_id: web::Path<String>,
+    body: String,
+) -> Result<impl Responder, AppError> {
+    let bot_id = bot_id.into_inner();
+    let (history, key, exchange) = {
+        let mut state = acquire_lock(&data)?;
+        let history = TradeHistory::for_state_file(&state.state_file);
+        let scope = state.scoped(tenant.name());
+        let exchange = scope.get_bot_ref(&bot_id)?.exchange.clone();
+        (history, scope.key(&bot_id), exchange)
+    };
+    let fills = import::parse_fills(&bot_id, &exchange, &body)
+        .map_err(|e| AppError::InvalidInput(format!("Invalid fills: {}", e)))?;
+    let imported = history.import(&key, &fills).map_err(|e| {
+        AppError::InternalServerError(format!("Failed to write trade history: {}", e))
+    })?;
+    if imported > 0 {
+        // Fills of the past move positions out of order; build the bot's portfolio again
+        let portfolio = Portfolio::load(&history, &key).map_err(|e| {
+            AppError::InternalServerError(format!("Failed to read trade history: {}", e))
+        })?;
+        let mut state = acquire_lock(&data)?;
+        state.portfolios.insert(&key, portfolio);
+        state.save::<PathBuf>(None)?;
+    }
+    log::info!(
+        "Imported {} of {} fill(s) into the trade history of bot '{}'",
+        imported,
+        fills.len(),
+        bot_id
+    );
+
+    let view = FillImportView {
+        bot_id,
+        fills: fills.len(),
+        imported,
+    };
+    Ok(HttpResponse::Ok().json(create_api_response(true, Some(view), None)))
+}
+
+/// The tenant's bots and their listeners as a JSON, YAML or TOML document. Admins acting as
+/// a tenant and read-only users never see its secrets.
+#[get("/state/export")]
+async fn export_state(
+    data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
+    query: web::Query<StateExportArgs>,
+) -> Result<impl Responder, AppError> {
+    let args = query.into_inner();
+    let redact = args.redact_secrets || tenant.impersonated_by().is_some() || tenant.is_read_only();
+    let export = acquire_lock(&data)?
+        .scoped(tenant.name())
+        .export_bots(redact);
+    Ok(HttpResponse::Ok()
+        .content_type(args.format.content_type())
+        .body(export.render(args.format)?))
+}
+
+/// Largest export `POST /state/import` accepts.
+const MAX_STATE_IMPORT_BYTES: usize = 8 * 1024 * 1024;
+
+/// Import the bots of an export, keeping the secrets it leaves out of bots that exist
+/// already; with `replace`, the tenant's bots the export does not hold are deleted.
+async fn import_state(
+    data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
+    query: web::Query<StateImportArgs>,
+    body: String,
+) -> Result<impl Responder, AppError> {
+    let args = query.into_inner();
+    let export = StateExport::parse(&body, args.format.unwrap_or_default())?;
+
+    let mut state = acquire_lock(&data)?;
+    let view = state
+        .scoped(tenant.name())
+        .import_bots(export, args.replace)?;
+    let warnings = state.take_warnings();
+    drop(state);
+
+    let api_response = create_api_response(true, Some(view), None).with_warnings(warnings);
+    Ok(HttpResponse::Ok().json(api_response))
+}
+
+/// Largest batch `POST /bots/bulk` and `POST /bots/{bot_id}/listeners/bulk` accept.
+const MAX_BULK_BYTES: usize = 8 * 1024 * 1024;
+
+/// Apply a batch of operations on bots and their listeners, all or none.
+async fn bulk_bots(
+    data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
+    client: web::Data<reqwest::Client>,
+    json_data: Result<web::Json<BulkRequest>, actix_web::Error>,
+) -> Result<impl Responder, AppError> {
+    let request = json_data
+        .map_err(|e| AppError::InvalidInput(format!("Invalid batch: {}", e)))?
+        .into_inner();
+    apply_bulk(&data, &tenant, &client, request.operations).await
+}
+
+/// Apply a batch of operations on the listeners of the bot `bot_id`, all or none.
+async fn bulk_listeners(
+    data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
+    client: web::Data<reqwest::Client>,
+    path: web::Path<String>,
+    json_data: Result<web::Json<ListenerBulkRequest>, actix_web::Error>,
+) -> Result<impl Responder, AppError> {
+    let bot_id = path.into_inner();
+    let request = json_data
+        .map_err(|e| AppError::InvalidInput(format!("Invalid batch: {}", e)))?
+        .into_inner();
+    let operations = (request.operations.into_iter())
+        .map(|operation| operation.of_bot(&bot_id))
+        .collect();
+    apply_bulk(&data, &tenant, &client, operations).await
+}
+
+/// Check the keys of the bots a batch inserts, as `POST /bots` does, then apply it.
+async fn apply_bulk(
+    data: &web::Data<Arc<Mutex<AppState>>>,
+    tenant: &Tenant,
+    client: &reqwest::Client,
+    mut operations: Vec<BulkOperation>,
+) -> Result<HttpResponse, AppError> {
+    // Ask the exchanges about the keys without holding the lock
+    let key_check = acquire_lock(data)?.key_check.clone();
+    let mut check_warnings = Vec::new();
+    for (index, operation) in operations.iter_mut().enumerate() {
+        if let BulkOperation::Insert(args) = operation {
+            let check = keycheck::check(&args.clone().into(), &key_check, client)
+                .await
+                .map_err(|e| AppError::BulkOperationFailed {
+                    index,
+                    source: Box::new(e),
+                })?;
+            args.key_permissions = check.permissions;
+            check_warnings.extend(
+                (check.warnings.into_iter()).map(|w| format!("operation {}: {}", index, w)),
+            );
+        }
+    }
+
+    let mut state = acquire_lock(data)?;
+    let view = state.scoped(tenant.name()).bulk(operations)?;
+    let mut warnings = state.take_warnings();
+    drop(state);
+    warnings.extend(check_warnings);
+
+    let api_response = create_api_response(true, Some(view), None).with_warnings(warnings);
+    Ok(HttpResponse::Ok().json(api_response))
+}
+
+/// The trade history, the storage key of the bot `bot_id` in it and whether the bot still
+/// exists. Shards are read without holding the lock.
+fn trade_history(
+    data: &web::Data<Arc<Mutex<AppState>>>,
+    tenant: &Tenant,
+    bot_id: &str,
+) -> Result<(TradeHistory, String, bool), AppError> {
+    let mut state = acquire_lock(data)?;
+    let history = TradeHistory::for_state_file(&state.state_file);
+    let scope = state.scoped(tenant.name());
+    Ok((
+        history,
+        scope.key(bot_id),
+        scope.get_bot_ref(bot_id).is_ok(),
+    ))
+}
+
+fn history_error(error: std::io::Error) -> AppError {
+    AppError::InternalServerError(format!("Failed to read trade history: {}", error))
+}
+
+fn bot_not_found(bot_id: &str) -> AppError {
+    AppError::BotNotFound(format!("Bot with ID '{}' not found.", bot_id))
+}
+
+/// Exchange symbols the bot trades for the symbols its alerts name.
+#[get("/bots/{bot_id}/symbols")]
+async fn bot_symbols(
+    data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
+    bot_id: web::Path<String>,
+) -> Result<impl Responder, AppError> {
+    let bot_id = bot_id.into_inner();
+    let mut state = acquire_lock(&data)?;
+    let symbols = state
+        .scoped(tenant.name())
+        .get_bot_ref(&bot_id)?
+        .symbol_map
+        .clone();
+    drop(state);
+
+    let api_response = create_api_response(true, Some(SymbolMapView { bot_id, symbols }), None);
+    Ok(HttpResponse::Ok().json(api_response))
+}
+
+/// Map a symbol of the bot's alerts to an exchange symbol, replacing any mapping of it.
+#[put("/bots/{bot_id}/symbols")]
+async fn map_bot_symbol(
+    data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
+    bot_id: web::Path<String>,
+    json_data: Result<web::Json<SymbolMapArgs>, actix_web::Error>,
+) -> Result<impl Responder, AppError> {
+    let mut args = json_data
+        .map_err(|e| {
+            log::error!("Failed to deserialize input: {}", e);
+            AppError::InvalidInput("Invalid input payload".to_string())
+        })?
+        .into_inner();
+    args.bot_id = bot_id.into_inner();
+
+    let mut state = acquire_lock(&data)?;
+    let view = state.scoped(tenant.name()).map_symbol(args)?;
+    let api_response = create_api_response(true, Some(view), None);
+    Ok(HttpResponse::Ok().json(api_response))
+}
+
+/// Drop the bot's mapping of the symbol in the `symbol` query parameter.
+#[delete("/bots/{bot_id}/symbols")]
+async fn unmap_bot_symbol(
+    data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
+    bot_id: web::Path<String>,
+    query: web::Query<SymbolUnmapArgs>,
+) -> Result<impl Responder, AppError> {
+    let mut args = query.into_inner();
+    args.bot_id = bot_id.into_inner();
+
+    let mut state = acquire_lock(&data)?;
+    let view = state.scoped(tenant.name()).unmap_symbol(args)?;
+    let api_response = create_api_response(true, Some(view), None);
+    Ok(HttpResponse::Ok().json(api_response))
+}
+
+/// Symbol of the order to cancel, for exchanges that cannot list open orders.
+#[derive(Debug, Deserialize)]
+pub struct CancelOrderQuery {
+    pub symbol: Option<String>,
+}
+
+/// Cancel an open order of the bot on its exchange.
+#[delete("/bots/{bot_id}/orders/{order_id}")]
+async fn cancel_bot_order(
+    data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
+    client: web::Data<reqwest::Client>,
+    path: web::Path<(String, String)>,
+    query: web::Query<CancelOrderQuery>,
+) -> Result<impl Responder, AppError> {
+    let (bot_id, order_id) = path.into_inner();
+    let (bot, venue) = bot_and_venue(&data, &tenant, &bot_id)?;
+    if bot.observer {
+        return Err(AppError::Forbidden(format!(
+            "Bot '{}' is an observer and cancels no orders",
+            bot_id
+        )));
+    }
+    let symbol = match query.into_inner().symbol {
+        Some(symbol) => symbol,
+        None => venue
+            .open_orders(&bot, &client)
+            .await
+            .map_err(|e| account_error(&bot, e.as_ref()))?
+            .ok_or_else(|| {
+                AppError::InvalidInput(format!(
+                    "{} cannot list open orders; give the order's symbol",
+                    venue_name(&bot)
+                ))
+            })?
+            .into_iter()
+            .find(|order| order.order_id == order_id)
+            .map(|order| order.symbol)
+            .ok_or_else(|| AppError::NotFound(format!("open order '{}'", order_id)))?,
+    };
+    let cancelled = venue
+        .cancel_order(&symbol, &order_id, &bot, &client)
+        .await
+        .map_err(|e| account_error(&bot, e.as_ref()))?;
+    if !cancelled {
+        return Err(AppError::InvalidInput(format!(
+            "{} cannot cancel orders",
+            venue_name(&bot)
+        )));
+    }
+    log::info!(
+        "Cancelled order {} of bot '{}' on {}",
+        order_id,
+        bot.bot_id,
+        symbol
+    );
+
+    let view = CancelledOrderView {
+        bot_id: bot.bot_id,
+        order_id,
+        symbol,
+    };
+    let api_response = create_api_response(true, Some(view), None);
+    Ok(HttpResponse::Ok().json(api_response))
+}
+
 #[post("/bots/{bot_id}/listeners")]
 async fn add_listener(
     data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
     bot_id: web::Path<String>,
     json_data: Result<web::Json<ListenerInsertArgs>, actix_web::Error>,
 ) -> Result<impl Responder, AppError> {
@@ -228,8 +2076,9 @@ async fn add_listener(
         Ok(good_json_data) => {
             let mut args = good_json_data.into_inner();
             args.bot_id = bot_id.into_inner();
-            let listener = state.add_listener(args)?;
-            let api_response = create_api_response(true, Some(listener), None);
+            let listener = state.scoped(tenant.name()).add_listener(args)?;
+            let api_response = create_api_response(true, Some(listener), None)
+                .with_warnings(state.take_warnings());
             Ok(HttpResponse::Ok().json(api_response))
         }
         Err(e) => {
@@ -242,18 +2091,27 @@ async fn add_listener(
 #[get("/bots/{bot_id}/listeners")]
 async fn list_listeners(
     data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
     path: web::Path<String>,
+    query: Result<web::Query<ListenerListArgs>, actix_web::Error>,
+    list_query: web::Query<ListQuery>,
     json_data: Option<web::Json<ListenerListArgs>>,
 ) -> Result<impl Responder, AppError> {
     let bot_id = path.into_inner();
-    let select_request = json_data
-        .map(|payload| payload.into_inner())
-        .unwrap_or_else(|| ListenerListArgs::new(&bot_id))
-        .bot_id(Some(&bot_id));
+    let query = query_filter(query)?;
+    // Filters of the body apply over those of the query
+    let select_request = match json_data {
+        Some(payload) => payload.into_inner().or(query),
+        None => query,
+    }
+    .bot_id(Some(&bot_id));
+    select_request.check_include()?;
+    list_query.validate(&LISTENER_SORTS)?;
 
-    let state = acquire_lock(&data)?;
-    let selected_list = state.list_listeners(select_request)?;
+    let mut state = acquire_lock(&data)?;
+    let selected_list = state.scoped(tenant.name()).list_listeners(select_request)?;
     drop(state);
+    let selected_list = ListenerListView(list_query.sort(selected_list.0, &LISTENER_SORTS)?);
 
     if selected_list.0.is_empty() {
         return Err(AppError::ListenerNotFound(
@@ -265,31 +2123,69 @@ async fn list_listeners(
     Ok(HttpResponse::Ok().json(api_response))
 }
 
+#[get("/listeners")]
+async fn list_all_listeners(
+    data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
+    query: Option<web::Query<Pagination>>, // Pagination query is optional
+    query_filter_args: Result<web::Query<ListenerListArgs>, actix_web::Error>,
+    list_query: web::Query<ListQuery>,
+    json_data: Option<web::Json<ListenerListArgs>>, // Filter in the request body, from older clients
+) -> Result<impl Responder, AppError> {
+    let pagination = query.unwrap_or(web::Query(Pagination {
+        page: Some(1),
+        limit: Some(10),
+    }));
+    pagination.validate()?;
+    list_query.validate(&LISTENER_SORTS)?;
+
+    let query_filter_args = query_filter(query_filter_args)?;
+    let select_request = match json_data {
+        Some(payload) => payload.into_inner().or(query_filter_args),
+        None => query_filter_args,
+    };
+    select_request.check_include()?;
+
+    let mut state = acquire_lock(&data)?;
+    let listeners = state
+        .scoped(tenant.name())
+        .list_all_listeners(select_request)?;
+    drop(state);
+    let listeners = list_query.sort(listeners.0, &LISTENER_SORTS)?;
+
+    let paginated = apply_pagination(&listeners, pagination.page(), pagination.limit());
+    let api_response = create_api_response(true, Some(ListenerListView(paginated)), None);
+    Ok(HttpResponse::Ok().json(api_response))
+}
+
 #[get("/bots/{bot_id}/listeners/{listener_id}")]
 async fn get_listener(
     data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
     path: web::Path<(String, String)>,
 ) -> Result<impl Responder, AppError> {
     let (bot_id, listener_id) = path.into_inner();
-    let state = acquire_lock(&data)?;
+    let mut state = acquire_lock(&data)?;
 
-    state.get_listener(ListenerGetArgs::new(&bot_id, &listener_id))?;
-    let api_response = create_api_response(
-        true,
-        Some("Listener deleted successfully".to_string()),
-        None,
-    );
+    let listener = state
+        .scoped(tenant.name())
+        .get_listener(ListenerGetArgs::new(&bot_id, &listener_id))?;
+    let api_response =
+        create_api_response(true, Some(listener), None).with_warnings(state.take_warnings());
     Ok(HttpResponse::Ok().json(api_response))
 }
 
 #[put("/bots/{bot_id}/listeners/{listener_id}")]
 async fn update_listener(
     data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
     path: web::Path<(String, String)>,
 ) -> Result<impl Responder, AppError> {
     let (bot_id, listener_id) = path.into_inner();
     let mut state = acquire_lock(&data)?;
-    state.update_listener(ListenerUpdateArgs::new(&bot_id, &listener_id))?;
+    state
+        .scoped(tenant.name())
+        .update_listener(ListenerUpdateArgs::new(&bot_id, &listener_id))?;
     let api_response = create_api_response(
         true,
         Some("Listener deleted successfully".to_string()),
@@ -298,16 +2194,53 @@ async fn update_listener(
     Ok(HttpResponse::Ok().json(api_response))
 }
 
+/// Give a listener a new secret, keeping the old one valid for the `grace_secs` query
+/// parameter. The reply is the only place the new secret is shown, with the full webhook URL.
+#[post("/bots/{bot_id}/listeners/{listener_id}/rotate-secret")]
+async fn rotate_listener_secret(
+    req: HttpRequest,
+    data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
+    path: web::Path<(String, String)>,
+    query: web::Query<ListenerRotateSecretArgs>,
+) -> Result<impl Responder, AppError> {
+    let (bot_id, listener_id) = path.into_inner();
+    let args = ListenerRotateSecretArgs {
+        grace_secs: query.grace_secs,
+        ..ListenerRotateSecretArgs::new(&bot_id, &listener_id)
+    };
+    let mut state = acquire_lock(&data)?;
+    let mut view = state.scoped(tenant.name()).rotate_listener_secret(args)?;
+    drop(state);
+
+    let base = match req
+        .app_data::<web::Data<WebhookServerConfig>>()
+        .and_then(|config| config.public_url.clone())
+    {
+        Some(url) => url.trim_end_matches('/').to_string(),
+        None => {
+            let connection = req.connection_info();
+            format!("{}://{}", connection.scheme(), connection.host())
+        }
+    };
+    view.webhook_url = format!("{}{}", base, view.webhook_url);
+    let api_response = create_api_response(true, Some(view), None);
+    Ok(HttpResponse::Ok().json(api_response))
+}
+
 #[delete("/bots/{bot_id}/listeners/{listener_id}")]
 async fn delete_listener(
     data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
     path: web::Path<(String, String)>,
 ) -> Result<impl Responder, AppError> {
     let (bot_id, listener_id) = path.into_inner();
     let mut state = acquire_lock(&data)?;
     //let args = ListenerDeleteArgs::new(bot_id, listener_id);
 
-    state.delete_listener(ListenerDeleteArgs::new(&bot_id, &listener_id))?;
+    state
+        .scoped(tenant.name())
+        .delete_listener(ListenerDeleteArgs::new(&bot_id, &listener_id))?;
     let api_response = create_api_response(
         true,
         Some("Listener deleted successfully".to_string()),
@@ -319,6 +2252,7 @@ async fn delete_listener(
 #[delete("/bots/{bot_id}/listeners")]
 async fn delete_listeners(
     data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
     path: web::Path<String>,
     json_data: Option<web::Json<ListenersDeleteArgs>>,
 ) -> Result<impl Responder, AppError> {
@@ -329,7 +2263,9 @@ async fn delete_listeners(
 
     delete_request.bot_id = bot_id;
     let mut state = acquire_lock(&data)?;
-    let deleted_list = state.delete_listeners(delete_request)?;
+    let deleted_list = state
+        .scoped(tenant.name())
+        .delete_listeners(delete_request)?;
     drop(state);
 
     if deleted_list.0.is_empty() {
@@ -341,3 +2277,569 @@ async fn delete_listeners(
     let api_response = create_api_response(true, Some(deleted_list), None);
     Ok(HttpResponse::Ok().json(api_response))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app_config::AppConfig;
+    use crate::bot::state::SecretRotationView;
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use tempfile::tempdir;
+
+    #[actix_web::test]
+    async fn old_paths_are_deprecated_aliases_of_the_versioned_ones() {
+        let dir = tempdir().unwrap();
+        let mut config = AppConfig::default();
+        config.api_server.state_file = dir.path().join("state.json");
+        let state = Arc::new(Mutex::new(AppState::load(config).unwrap()));
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state.clone()))
+                .configure(configure),
+        )
+        .await;
+
+        let get = |path: &str| test::TestRequest::get().uri(path).to_request();
+        let current = test::call_service(&app, get("/api/v1/readyz")).await;
+        assert!(current.status().is_success());
+        assert!(!current.headers().contains_key("deprecation"));
+
+        let old = test::call_service(&app, get("/readyz")).await;
+        assert!(old.status().is_success());
+        let header = |name: &str| old.headers().get(name).unwrap().to_str().unwrap();
+        assert_eq!(header("deprecation"), "true");
+        assert_eq!(
+            header("link"),
+            "</api/v1/readyz>; rel=\"successor-version\""
+        );
+
+        let versions: ApiResponse<ApiVersionsView> =
+            test::call_and_read_body_json(&app, get("/api/versions")).await;
+        assert_eq!(versions.data.unwrap().versions, [API_VERSION]);
+
+        // Without the old paths, only the versioned ones are served
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state))
+                .app_data(web::Data::new(LegacyRoutes(false)))
+                .configure(configure),
+        )
+        .await;
+        let old = test::call_service(&app, get("/readyz")).await;
+        assert_eq!(old.status(), StatusCode::NOT_FOUND);
+        let current = test::call_service(&app, get("/api/v1/readyz")).await;
+        assert!(current.status().is_success());
+    }
+
+    #[actix_web::test]
+    async fn legacy_routes_follow_the_config() {
+        for legacy_routes in [true, false] {
+            let dir = tempdir().unwrap();
+            let mut config = AppConfig::default();
+            config.api_server.state_file = dir.path().join("state.json");
+            config.api_server.legacy_routes = legacy_routes;
+            let mut state = AppState::load(config.clone()).unwrap();
+            state
+                .add_bot(
+                    BotInsertArgs::new("alpha".to_string(), "binance".to_string())
+                        .bot_id(Some("alpha".to_string())),
+                )
+                .unwrap();
+            let app = test::init_service(
+                App::new()
+                    .app_data(web::Data::new(Arc::new(Mutex::new(state))))
+                    .app_data(web::Data::new(LegacyRoutes(
+                        config.api_server.legacy_routes,
+                    )))
+                    .configure(configure),
+            )
+            .await;
+
+            let get = |path: &str| test::TestRequest::get().uri(path).to_request();
+            for path in ["/healthz", "/readyz", "/bots", "/bots/alpha"] {
+                let old = test::call_service(&app, get(path)).await;
+                if legacy_routes {
+                    assert!(old.status().is_success(), "{}", path);
+                    assert!(old.headers().contains_key("deprecation"));
+                } else {
+                    assert_eq!(old.status(), StatusCode::NOT_FOUND, "{}", path);
+                }
+                let current = test::call_service(&app, get(&format!("{}{}", API_PREFIX, path)));
+                assert!(current.await.status().is_success(), "{}", path);
+            }
+        }
+    }
+
+    #[actix_web::test]
+    async fn probes_report_liveness_readiness_and_the_build() {
+        let dir = tempdir().unwrap();
+        let mut config = AppConfig::default();
+        config.api_server.state_file = dir.path().join("state.json");
+        let mut state = AppState::load(config.clone()).unwrap();
+        for (name, exchange) in [("a", "binance"), ("b", "bybit")] {
+            state
+                .add_bot(BotInsertArgs::new(name.to_string(), exchange.to_string()))
+                .unwrap();
+        }
+        let outages = Outages::new(config.outages.clone());
+        while !outages.record_failure("bybit", Utc::now()) {}
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Arc::new(Mutex::new(state))))
+                .app_data(web::Data::new(outages))
+                .configure(configure),
+        )
+        .await;
+        let get = |path: &str| test::TestRequest::get().uri(path).to_request();
+
+        let health = test::call_service(&app, get("/api/v1/healthz")).await;
+        assert_eq!(health.status(), StatusCode::OK);
+        let ready: ApiResponse<ReadinessView> =
+            test::call_and_read_body_json(&app, get("/api/v1/readyz")).await;
+        let ready = ready.data.unwrap();
+        assert!(ready.ready && ready.state_loaded && ready.storage_writable);
+        assert!(ready.exchanges.is_empty());
+
+        // Bybit's circuit is open
+        let response = test::call_service(&app, get("/api/v1/readyz?exchanges=true")).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let not_ready: ApiResponse<ReadinessView> = test::read_body_json(response).await;
+        let not_ready = not_ready.data.unwrap();
+        assert!(!not_ready.ready);
+        assert_eq!(not_ready.exchanges.get("binance"), Some(&true));
+        assert_eq!(not_ready.exchanges.get("bybit"), Some(&false));
+
+        let version: ApiResponse<VersionView> =
+            test::call_and_read_body_json(&app, get("/api/v1/version")).await;
+        let version = version.data.unwrap();
+        assert_eq!(version.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(version.api_version, API_VERSION);
+        assert!(version.built_at.is_some());
+
+        let schema: ApiResponse<serde_json::Value> =
+            test::call_and_read_body_json(&app, get("/api/v1/admin/config/schema")).await;
+        let schema = schema.data.unwrap();
+        assert_eq!(schema["title"], "xtrade configuration");
+        assert!(schema["properties"]["api_server"].is_object());
+    }
+
+    #[actix_web::test]
+    async fn lists_are_filtered_by_the_query_and_the_body() {
+        let dir = tempdir().unwrap();
+        let mut config = AppConfig::default();
+        config.api_server.state_file = dir.path().join("state.json");
+        let mut state = AppState::load(config).unwrap();
+        for (bot_id, exchange, fee) in [("a", "binance", "0.001"), ("b", "binance", "0.01")] {
+            state
+                .add_bot(
+                    BotInsertArgs::new(bot_id.to_string(), exchange.to_string())
+                        .bot_id(Some(bot_id.to_string()))
+                        .trading_fee(fee.parse().ok()),
+                )
+                .unwrap();
+        }
+        state
+            .add_bot(BotInsertArgs::new("c".to_string(), "bybit".to_string()))
+            .unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Arc::new(Mutex::new(state))))
+                .configure(configure),
+        )
+        .await;
+        let bots = |request: test::TestRequest| {
+            let app = &app;
+            async move {
+                let list: ApiResponse<BotListView> =
+                    test::call_and_read_body_json(app, request.to_request()).await;
+                let mut ids: Vec<String> =
+                    list.data.unwrap().0.into_iter().map(|b| b.bot_id).collect();
+                ids.sort();
+                ids
+            }
+        };
+
+        let query =
+            test::TestRequest::get().uri("/api/v1/bots?exchange=binance&trading_fee_max=0.005");
+        assert_eq!(bots(query).await, ["a"]);
+        // Older clients send the filter in the body, which applies over the query
+        let body = test::TestRequest::get()
+            .uri("/api/v1/bots?exchange=bybit")
+            .set_json(serde_json::json!({"exchange": "binance", "name": "b"}));
+        assert_eq!(bots(body).await, ["b"]);
+
+        let get = |uri: &str| test::TestRequest::get().uri(uri).to_request();
+        let secret = test::call_service(&app, get("/api/v1/bots?api_key=plain-key")).await;
+        assert_eq!(secret.status(), StatusCode::BAD_REQUEST);
+        let invalid = test::call_service(&app, get("/api/v1/bots?trading_fee_min=cheap")).await;
+        assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+        let include = test::call_service(&app, get("/api/v1/listeners?include=secret")).await;
+        assert_eq!(include.status(), StatusCode::BAD_REQUEST);
+
+        let sparse: serde_json::Value =
+            test::call_and_read_body_json(&app, get("/api/v1/bots?fields=bot_id,exchange")).await;
+        let list = sparse["data"].as_array().unwrap();
+        assert_eq!(list.len(), 3);
+        assert!(list.iter().all(|bot| bot.as_object().unwrap().len() == 2));
+        let sparse: serde_json::Value =
+            test::call_and_read_body_json(&app, get("/api/v1/bots/a?fields=name")).await;
+        assert_eq!(sparse["data"], serde_json::json!({"name": "a"}));
+
+        let sorted: serde_json::Value = test::call_and_read_body_json(
+            &app,
+            get("/api/v1/bots?sort=-trading_fee,name&fields=name"),
+        )
+        .await;
+        // Bots without a fee come last, descending or not
+        let names: Vec<_> = (sorted["data"].as_array().unwrap().iter())
+            .map(|bot| bot["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["b", "a", "c"]);
+        let unsortable = test::call_service(&app, get("/api/v1/bots?sort=api_key")).await;
+        assert_eq!(unsortable.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn lists_sort_by_each_field_in_turn() {
+        let items = vec![
+            serde_json::json!({"id": "a", "exchange": "bybit", "fee": "9"}),
+            serde_json::json!({"id": "b", "exchange": "binance", "fee": null}),
+            serde_json::json!({"id": "c", "exchange": "binance", "fee": "10"}),
+            serde_json::json!({"id": "d", "exchange": "bybit"}),
+            serde_json::json!({"id": "e", "exchange": "binance", "fee": "0.5"}),
+        ];
+        let sortable = ["id", "exchange", "fee"];
+        let sorted = |sort: &str| {
+            let query = ListQuery {
+                sort: Some(sort.to_string()),
+            };
+            (query.sort(items.clone(), &sortable).unwrap().iter())
+                .map(|item| item["id"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        // Decimals compare as numbers, not as strings, and missing or null values come last
+        assert_eq!(sorted("fee"), ["e", "a", "c", "b", "d"]);
+        assert_eq!(sorted("-fee"), ["c", "a", "e", "b", "d"]);
+        // Later fields break the ties of earlier ones; equal items keep their order
+        assert_eq!(sorted("exchange,-fee"), ["c", "e", "b", "a", "d"]);
+        assert_eq!(sorted("-exchange, id"), ["a", "d", "b", "c", "e"]);
+        assert_eq!(sorted(""), ["a", "b", "c", "d", "e"]);
+
+        let unknown = ListQuery {
+            sort: Some("id,-bogus".to_string()),
+        };
+        assert!(matches!(
+            unknown.sort(items.clone(), &sortable),
+            Err(AppError::InvalidInput(_))
+        ));
+    }
+
+    #[actix_web::test]
+    async fn unknown_sort_fields_are_bad_requests() {
+        let dir = tempdir().unwrap();
+        let mut config = AppConfig::default();
+        config.api_server.state_file = dir.path().join("state.json");
+        let state = Arc::new(Mutex::new(AppState::load(config).unwrap()));
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state))
+                .configure(configure),
+        )
+        .await;
+        for uri in [
+            "/api/v1/bots?sort=bogus",
+            "/api/v1/bots?sort=name,-bogus",
+            "/api/v1/listeners?sort=-secret",
+            "/api/v1/bots/missing/listeners?sort=bogus",
+        ] {
+            let response =
+                test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", uri);
+        }
+    }
+
+    #[actix_web::test]
+    async fn batches_apply_all_or_none() {
+        let dir = tempdir().unwrap();
+        let mut config = AppConfig::default();
+        config.api_server.state_file = dir.path().join("state.json");
+        let mut state = AppState::load(config).unwrap();
+        state
+            .add_bot(
+                BotInsertArgs::new("a".to_string(), "binance".to_string())
+                    .bot_id(Some("a".to_string())),
+            )
+            .unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Arc::new(Mutex::new(state))))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .configure(configure),
+        )
+        .await;
+        let post = |uri: &str, body: serde_json::Value| {
+            test::TestRequest::post()
+                .uri(uri)
+                .set_json(body)
+                .to_request()
+        };
+        let bot_ids = || {
+            let app = &app;
+            async move {
+                let request = test::TestRequest::get().uri("/api/v1/bots").to_request();
+                let list: ApiResponse<BotListView> =
+                    test::call_and_read_body_json(app, request).await;
+                let mut ids: Vec<String> =
+                    list.data.unwrap().0.into_iter().map(|b| b.bot_id).collect();
+                ids.sort();
+                ids
+            }
+        };
+
+        // The last operation fails, so neither the new bot nor the deletion stays
+        let failing = serde_json::json!({"operations": [
+            {"op": "insert", "bot_id": "b", "name": "b", "exchange": "bybit"},
+            {"op": "insert_listener", "bot_id": "b", "service": "tradingview"},
+            {"op": "delete", "bot_id": "a"},
+            {"op": "delete", "bot_id": "missing"},
+        ]});
+        let response = test::call_service(&app, post("/api/v1/bots/bulk", failing)).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let error: serde_json::Value = test::read_body_json(response).await;
+        assert_eq!(error["details"]["index"], 3);
+        assert_eq!(bot_ids().await, ["a"]);
+
+        let batch = serde_json::json!({"operations": [
+            {"op": "insert", "bot_id": "b", "name": "b", "exchange": "bybit"},
+            {"op": "delete", "bot_id": "a"},
+        ]});
+        let response = test::call_service(&app, post("/api/v1/bots/bulk", batch)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let applied: serde_json::Value = test::read_body_json(response).await;
+        assert_eq!(applied["data"]["applied"].as_array().unwrap().len(), 2);
+        assert_eq!(bot_ids().await, ["b"]);
+
+        let listeners = serde_json::json!({"operations": [
+            {"op": "insert", "service": "tradingview", "listener_id": "tv"},
+            {"op": "insert", "service": "tradingview", "listener_id": "tv2"},
+            {"op": "delete", "listener_id": "tv2"},
+        ]});
+        let response =
+            test::call_service(&app, post("/api/v1/bots/b/listeners/bulk", listeners)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let request = test::TestRequest::get()
+            .uri("/api/v1/bots/b/listeners")
+            .to_request();
+        let list: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+        let ids: Vec<_> = (list["data"].as_array().unwrap().iter())
+            .map(|listener| listener["listener_id"].as_str().unwrap())
+            .collect();
+        assert_eq!(ids, ["tv"]);
+    }
+
+    #[actix_web::test]
+    async fn rotation_replies_with_the_public_webhook_url() {
+        let dir = tempdir().unwrap();
+        let mut config = AppConfig::default();
+        config.api_server.state_file = dir.path().join("state.json");
+        let mut state = AppState::load(config.clone()).unwrap();
+        let bot = state
+            .add_bot(BotInsertArgs::new(
+                "Alpha".to_string(),
+                "binance".to_string(),
+            ))
+            .unwrap();
+        state
+            .add_listener(ListenerInsertArgs {
+                listener_id: Some("tv".to_string()),
+                ..ListenerInsertArgs::new(&bot.bot_id, "tradingview")
+            })
+            .unwrap();
+        config.webhook_server.public_url = Some("https://hooks.example.com/".to_string());
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Arc::new(Mutex::new(state))))
+                .app_data(web::Data::new(config.webhook_server))
+                .configure(configure),
+        )
+        .await;
+
+        let uri = format!(
+            "/api/v1/bots/{}/listeners/tv/rotate-secret?grace_secs=60",
+            bot.bot_id
+        );
+        let rotation: ApiResponse<SecretRotationView> =
+            test::call_and_read_body_json(&app, test::TestRequest::post().uri(&uri).to_request())
+                .await;
+        let rotation = rotation.data.unwrap();
+        assert_eq!(
+            rotation.webhook_url,
+            format!(
+                "https://hooks.example.com/api/v1/webhook/{}/tv?secret={}",
+                bot.bot_id, rotation.secret
+            )
+        );
+    }
+    #[actix_web::test]
+    async fn read_only_users_see_no_secrets_and_change_nothing() {
+        use crate::bot::state::UserInsertArgs;
+
+        let dir = tempdir().unwrap();
+        let mut config = AppConfig::default();
+        config.api_server.state_file = dir.path().join("state.json");
+        config.auth.enabled = true;
+        config.auth.jwt_secret = Some("0123456789abcdef0123456789abcdef".to_string());
+        let mut state = AppState::load(config.clone()).unwrap();
+        let bot = state
+            .add_bot(
+                BotInsertArgs::new("Alpha".to_string(), "binance".to_string())
+                    .api_key(Some("plain-key".to_string())),
+            )
+            .unwrap();
+        for (username, role) in [("ops", Role::Admin), ("viewer", Role::ReadOnly)] {
+            let args = UserInsertArgs {
+                username: username.to_string(),
+                password: format!("{}-password", username),
+                role,
+            };
+            state.users.add(args, Utc::now()).unwrap();
+        }
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Arc::new(Mutex::new(state))))
+                .app_data(web::Data::new(config.auth))
+                .configure(configure),
+        )
+        .await;
+
+        let sign_in = |username: &str, password: &str| {
+            test::TestRequest::post()
+                .uri("/api/v1/auth/login")
+                .set_json(serde_json::json!({"username": username, "password": password}))
+                .to_request()
+        };
+        let wrong = test::call_service(&app, sign_in("viewer", "ops-password")).await;
+        assert_eq!(wrong.status(), StatusCode::UNAUTHORIZED);
+        let signed_in: ApiResponse<LoginView> =
+            test::call_and_read_body_json(&app, sign_in("viewer", "viewer-password")).await;
+        let viewer = format!("Bearer {}", signed_in.data.unwrap().token);
+        let signed_in: ApiResponse<LoginView> =
+            test::call_and_read_body_json(&app, sign_in("ops", "ops-password")).await;
+        let admin = format!("Bearer {}", signed_in.data.unwrap().token);
+
+        let anonymous = test::TestRequest::get().uri("/api/v1/bots").to_request();
+        let anonymous = test::call_service(&app, anonymous).await;
+        assert_eq!(anonymous.status(), StatusCode::UNAUTHORIZED);
+
+        let export = |token: &str| {
+            test::TestRequest::get()
+                .uri("/api/v1/state/export")
+                .insert_header((AUTHORIZATION, token.to_string()))
+                .to_request()
+        };
+        let body = test::call_and_read_body(&app, export(&viewer)).await;
+        assert!(!String::from_utf8_lossy(&body).contains("plain-key"));
+        let body = test::call_and_read_body(&app, export(&admin)).await;
+        assert!(String::from_utf8_lossy(&body).contains("plain-key"));
+
+        let delete = |token: &str| {
+            test::TestRequest::delete()
+                .uri(&format!("/api/v1/bots/{}", bot.bot_id))
+                .insert_header((AUTHORIZATION, token.to_string()))
+                .to_request()
+        };
+        let refused = test::call_service(&app, delete(&viewer)).await;
+        assert_eq!(refused.status(), StatusCode::FORBIDDEN);
+        let deleted = test::call_service(&app, delete(&admin)).await;
+        assert!(deleted.status().is_success());
+    }
+
+    #[actix_web::test]
+    async fn memory_stats_count_and_size_the_state_for_admins() {
+        use crate::bot::state::{ListenerInsertArgs, UserInsertArgs};
+
+        let dir = tempdir().unwrap();
+        let mut config = AppConfig::default();
+        config.api_server.state_file = dir.path().join("state.json");
+        config.auth.enabled = true;
+        config.auth.jwt_secret = Some("0123456789abcdef0123456789abcdef".to_string());
+        let mut state = AppState::load(config.clone()).unwrap();
+        for (bot_id, listeners) in [("alpha", 2), ("beta", 1)] {
+            state
+                .add_bot(
+                    BotInsertArgs::new(bot_id.to_string(), "binance".to_string())
+                        .bot_id(Some(bot_id.to_string())),
+                )
+                .unwrap();
+            for _ in 0..listeners {
+                let args = ListenerInsertArgs::new(bot_id, "tradingview");
+                state.add_listener(args).unwrap();
+            }
+        }
+        for (username, role) in [("ops", Role::Admin), ("viewer", Role::ReadOnly)] {
+            let args = UserInsertArgs {
+                username: username.to_string(),
+                password: format!("{}-password", username),
+                role,
+            };
+            state.users.add(args, Utc::now()).unwrap();
+        }
+        state.save::<&std::path::Path>(None).unwrap();
+        let expected = (
+            serialized_len(&state.bots),
+            serialized_len(&state.events),
+            state.events.len(),
+        );
+        let prices = PriceCache::new(Default::default());
+        prices.record("BTCUSDT", 60_000.0, Utc::now());
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Arc::new(Mutex::new(state))))
+                .app_data(web::Data::new(prices))
+                .app_data(web::Data::new(config.auth))
+                .configure(configure),
+        )
+        .await;
+
+        let sign_in = |username: &str| {
+            let app = &app;
+            let credentials = serde_json::json!({
+                "username": username,
+                "password": format!("{}-password", username),
+            });
+            async move {
+                let request = test::TestRequest::post()
+                    .uri("/api/v1/auth/login")
+                    .set_json(credentials)
+                    .to_request();
+                let signed_in: ApiResponse<LoginView> =
+                    test::call_and_read_body_json(app, request).await;
+                format!("Bearer {}", signed_in.data.unwrap().token)
+            }
+        };
+        let stats = |token: String| {
+            test::TestRequest::get()
+                .uri("/api/v1/admin/stats/memory")
+                .insert_header((AUTHORIZATION, token))
+                .to_request()
+        };
+
+        let refused = test::call_service(&app, stats(sign_in("viewer").await)).await;
+        assert_eq!(refused.status(), StatusCode::FORBIDDEN);
+
+        let view: ApiResponse<MemoryStatsView> =
+            test::call_and_read_body_json(&app, stats(sign_in("ops").await)).await;
+        let view = view.data.unwrap();
+        assert_eq!(view.counts.bots, 2);
+        assert_eq!(view.counts.listeners, 3);
+        assert_eq!(view.counts.events, expected.2);
+        assert_eq!(view.counts.cached_prices, 1);
+        assert_eq!(view.state.bots_bytes, expected.0);
+        assert_eq!(view.state.events_bytes, expected.1);
+        let file = std::fs::metadata(dir.path().join("state.json")).unwrap();
+        assert_eq!(view.state.file_bytes, Some(file.len()));
+        assert_eq!(view.state.trade_history_shards, 0);
+    }
+}
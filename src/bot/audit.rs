@@ -0,0 +1,85 @@
+// src/bot/audit.rs
+//! # Audit Trail
+//!
+//! Records requests made by support operators on behalf of a tenant. Every event is logged
+//! under the `audit` target and, when `tenancy.audit_log` is set, appended to that file as
+//! one JSON object per line, until [compaction](crate::bot::compaction) prunes lines older
+//! than `retention.audit`. Callers refuse the request if the event cannot be recorded.
+use crate::utils::jsonl;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::path::Path;
+
+/// A single impersonated request.
+#[derive(Debug, Serialize)]
+pub struct AuditEvent<'a> {
+    pub timestamp: DateTime<Utc>,
+    pub admin: &'a str,
+    pub tenant: &'a str,
+    pub method: &'a str,
+    pub path: &'a str,
+    /// Whether the request was let through (impersonation is read-only)
+    pub allowed: bool,
+}
+
+impl<'a> AuditEvent<'a> {
+    pub fn new(
+        admin: &'a str,
+        tenant: &'a str,
+        method: &'a str,
+        path: &'a str,
+        allowed: bool,
+    ) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            admin,
+            tenant,
+            method,
+            path,
+            allowed,
+        }
+    }
+}
+
+/// Log `event` and append it to `log_file`, if any.
+pub fn record(event: &AuditEvent, log_file: Option<&Path>) -> std::io::Result<()> {
+    let line = serde_json::to_string(event)?;
+    log::info!(target: "audit", "{}", line);
+
+    if let Some(path) = log_file {
+        jsonl::append(path, [line])?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_record_appends_json_lines() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+
+        record(
+            &AuditEvent::new("support", "acme", "GET", "/bots", true),
+            Some(&path),
+        )
+        .unwrap();
+        record(
+            &AuditEvent::new("support", "acme", "DELETE", "/bots/x", false),
+            Some(&path),
+        )
+        .unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let events: Vec<serde_json::Value> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["tenant"], "acme");
+        assert_eq!(events[1]["allowed"], false);
+    }
+}
@@ -0,0 +1,336 @@
+// src/bot/auth.rs
+//! # Users
+//!
+//! With `[auth] enabled`, API requests must carry a token: a user's, issued by
+//! `POST /auth/login` for their username and password, or one of the tenancy tokens. Users
+//! are kept in the state with an argon2 hash of their password, and are added with
+//! `xtrade offline add-user`.
+//!
+//! Tokens are JWTs signed with HS256 and `auth.jwt_secret`, naming the user and expiring
+//! after `auth.token_ttl_secs`. A token only counts while its user exists, with the role the
+//! user has now:
+//!
+//! - **admin** users may use every endpoint, on the whole state.
+//! - **read_only** users may only read, and are never shown credentials or listener secrets:
+//!   they are [masked](redact_for_viewers) in every response.
+use crate::app_config::AuthConfig;
+use crate::bot::secrets::{self, Secrets};
+use crate::bot::state::{UserInsertArgs, UserListView, UserView};
+use crate::errors::AppError;
+use crate::utils::crypto::{constant_time_eq, hmac_sha256};
+use actix_web::body::{self, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderValue, CONTENT_LENGTH, CONTENT_TYPE};
+use actix_web::middleware::Next;
+use actix_web::HttpMessage;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use validator::Validate;
+
+/// What a user may do.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Everything
+    Admin,
+    /// Read, without seeing secrets
+    #[default]
+    ReadOnly,
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Role::Admin => write!(f, "admin"),
+            Role::ReadOnly => write!(f, "read_only"),
+        }
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "admin" => Ok(Role::Admin),
+            "read_only" => Ok(Role::ReadOnly),
+            _ => Err(format!("Unknown role '{}': use admin or read_only", s)),
+        }
+    }
+}
+
+/// A user who may sign in.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct User {
+    /// Argon2 hash of the password, in the PHC string format
+    pub password_hash: String,
+    pub role: Role,
+    pub created_at: DateTime<Utc>,
+}
+
+/// The users, by username.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(transparent)]
+pub struct Users(BTreeMap<String, User>);
+
+impl Users {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Add the user `args` describe, or replace their password and role.
+    pub fn add(&mut self, args: UserInsertArgs, now: DateTime<Utc>) -> Result<UserView, AppError> {
+        args.validate()
+            .map_err(|e| AppError::ValidationError(e.to_string()))?;
+        let password = (Secrets::default().resolve(&args.password))
+            .map_err(|e| AppError::InvalidInput(format!("Invalid password: {}", e)))?;
+        if password.is_empty() {
+            return Err(AppError::InvalidInput(
+                "Password cannot be empty.".to_string(),
+            ));
+        }
+        let created_at = (self.0.get(&args.username)).map_or(now, |user| user.created_at);
+        let user = User {
+            password_hash: hash_password(&password)?,
+            role: args.role,
+            created_at,
+        };
+        let view = UserView::new(&args.username, &user);
+        self.0.insert(args.username, user);
+        Ok(view)
+    }
+
+    pub fn list(&self) -> UserListView {
+        UserListView(
+            (self.0.iter())
+                .map(|(username, user)| UserView::new(username, user))
+                .collect(),
+        )
+    }
+
+    pub fn delete(&mut self, username: &str) -> Result<UserView, AppError> {
+        let user = self
+            .0
+            .remove(username)
+            .ok_or_else(|| unknown_user(username))?;
+        Ok(UserView::new(username, &user))
+    }
+
+    /// The role of `username`, if `password` is theirs.
+    pub fn authenticate(&self, username: &str, password: &str) -> Result<Role, AppError> {
+        let invalid = || AppError::Unauthorized("Invalid username or password.".to_string());
+        let user = self.0.get(username).ok_or_else(invalid)?;
+        if verify_password(&user.password_hash, password) {
+            Ok(user.role)
+        } else {
+            Err(invalid())
+        }
+    }
+
+    /// The role `username` has now; tokens of deleted users no longer count.
+    pub fn role(&self, username: &str) -> Result<Role, AppError> {
+        (self.0.get(username))
+            .map(|user| user.role)
+            .ok_or_else(|| AppError::Unauthorized("The token's user no longer exists.".to_string()))
+    }
+}
+
+fn unknown_user(username: &str) -> AppError {
+    AppError::NotFound(format!("User '{}' not found.", username))
+}
+
+/// The argon2id hash of `password`, with a random salt.
+pub fn hash_password(password: &str) -> Result<String, AppError> {
+    let mut salt = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut salt);
+    let salt = SaltString::encode_b64(&salt)
+        .map_err(|e| AppError::InternalServerError(format!("Failed to salt password: {}", e)))?;
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| AppError::InternalServerError(format!("Failed to hash password: {}", e)))
+}
+
+/// Whether `password` is the one `hash` was made of.
+pub fn verify_password(hash: &str, password: &str) -> bool {
+    PasswordHash::new(hash)
+        .and_then(|hash| Argon2::default().verify_password(password.as_bytes(), &hash))
+        .is_ok()
+}
+
+/// What a token says of its user.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Claims {
+    /// Username
+    pub sub: String,
+    /// Issued at, in seconds since the epoch
+    pub iat: i64,
+    /// Expires at, in seconds since the epoch
+    pub exp: i64,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Header {
+    alg: String,
+    typ: String,
+}
+
+/// A token for `username` signed with `secret`, and when it expires.
+pub fn issue(
+    secret: &[u8],
+    username: &str,
+    now: DateTime<Utc>,
+    ttl: Duration,
+) -> Result<(String, DateTime<Utc>), AppError> {
+    let expires_at = now + ttl;
+    let header = Header {
+        alg: "HS256".to_string(),
+        typ: "JWT".to_string(),
+    };
+    let claims = Claims {
+        sub: username.to_string(),
+        iat: now.timestamp(),
+        exp: expires_at.timestamp(),
+    };
+    let signed = format!("{}.{}", encode(&header)?, encode(&claims)?);
+    let signature = URL_SAFE_NO_PAD.encode(hmac_sha256(secret, signed.as_bytes()));
+    Ok((format!("{}.{}", signed, signature), expires_at))
+}
+
+fn encode<T: Serialize>(value: &T) -> Result<String, AppError> {
+    serde_json::to_vec(value)
+        .map(|json| URL_SAFE_NO_PAD.encode(json))
+        .map_err(AppError::JsonParseError)
+}
+
+/// The claims of `token` if `secret` signed it and it has not expired at `now`.
+pub fn verify(secret: &[u8], token: &str, now: DateTime<Utc>) -> Result<Claims, AppError> {
+    let invalid = |reason: &str| AppError::Unauthorized(format!("Invalid token: {}.", reason));
+    let (signed, signature) = token.rsplit_once('.').ok_or_else(|| invalid("not a JWT"))?;
+    let (header, claims) = signed.split_once('.').ok_or_else(|| invalid("not a JWT"))?;
+    let decode = |part: &str| {
+        URL_SAFE_NO_PAD
+            .decode(part)
+            .map_err(|_| invalid("not a JWT"))
+    };
+
+    let header: Header =
+        serde_json::from_slice(&decode(header)?).map_err(|_| invalid("malformed header"))?;
+    if header.alg != "HS256" {
+        return Err(invalid("not signed with HS256"));
+    }
+    let expected = hmac_sha256(secret, signed.as_bytes());
+    if !constant_time_eq(&decode(signature)?, &expected) {
+        return Err(invalid("bad signature"));
+    }
+    let claims: Claims =
+        serde_json::from_slice(&decode(claims)?).map_err(|_| invalid("malformed claims"))?;
+    if claims.exp <= now.timestamp() {
+        return Err(AppError::Unauthorized(
+            "Token expired; sign in again.".to_string(),
+        ));
+    }
+    Ok(claims)
+}
+
+/// Whether `token` looks like a JWT rather than an opaque tenancy token.
+pub fn is_jwt(token: &str) -> bool {
+    token.split('.').count() == 3
+}
+
+/// The signing key of `config`; `None` unless users are required.
+pub fn signing_key(config: &AuthConfig) -> Option<&[u8]> {
+    config
+        .enabled
+        .then(|| config.jwt_secret.as_deref().map(str::as_bytes))
+        .flatten()
+}
+
+/// Marks a request made by a [`Role::ReadOnly`] user, whose response must not show secrets.
+#[derive(Clone, Copy, Debug)]
+pub struct ReadOnly;
+
+/// Middleware masking the secrets in JSON responses to requests marked [`ReadOnly`] while
+/// they were handled.
+pub async fn redact_for_viewers(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let response = next.call(req).await?;
+    let is_json = (response.headers().get(CONTENT_TYPE))
+        .is_some_and(|value| value.as_bytes().starts_with(b"application/json"));
+    if !is_json || response.request().extensions().get::<ReadOnly>().is_none() {
+        return Ok(response.map_into_boxed_body());
+    }
+    let (request, response) = response.into_parts();
+    let (mut response, content) = response.into_parts();
+    let content = body::to_bytes(content).await.map_err(|e| {
+        let e: Box<dyn std::error::Error> = e.into();
+        actix_web::error::ErrorInternalServerError(format!("Failed to read response: {}", e))
+    })?;
+    let content = match serde_json::from_slice(&content) {
+        Ok(mut value) => {
+            secrets::redact(&mut value);
+            serde_json::to_vec(&value).unwrap_or_default()
+        }
+        // Not JSON after all; nothing is shown rather than secrets
+        Err(_) => Vec::new(),
+    };
+    response.headers_mut().insert(
+        CONTENT_LENGTH,
+        HeaderValue::from_str(&content.len().to_string()).expect("a number is a header value"),
+    );
+    let response = response.set_body(content).map_into_boxed_body();
+    Ok(ServiceResponse::new(request, response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passwords_are_hashed_with_a_salt_and_verified() {
+        let hash = hash_password("correct horse").unwrap();
+        assert!(hash.starts_with("$argon2id$"));
+        assert_ne!(hash, hash_password("correct horse").unwrap());
+        assert!(verify_password(&hash, "correct horse"));
+        assert!(!verify_password(&hash, "battery staple"));
+        assert!(!verify_password("not a hash", "correct horse"));
+    }
+
+    #[test]
+    fn tokens_are_signed_and_expire() {
+        let secret = b"0123456789abcdef0123456789abcdef";
+        let now = DateTime::parse_from_rfc3339("2025-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let (token, expires_at) = issue(secret, "alice", now, Duration::hours(1)).unwrap();
+        assert!(is_jwt(&token));
+        assert_eq!(expires_at, now + Duration::hours(1));
+
+        let claims = verify(secret, &token, now).unwrap();
+        assert_eq!(claims.sub, "alice");
+        assert_eq!(claims.exp - claims.iat, 3600);
+        assert!(matches!(
+            verify(secret, &token, expires_at),
+            Err(AppError::Unauthorized(message)) if message.contains("expired")
+        ));
+        assert!(verify(b"another secret of thirty-two bytes", &token, now).is_err());
+
+        // Claims cannot be changed without the secret, nor the signature left out
+        let (header, rest) = token.split_once('.').unwrap();
+        let signature = rest.rsplit_once('.').unwrap().1;
+        let forged = URL_SAFE_NO_PAD.encode(br#"{"sub":"root","iat":0,"exp":9999999999}"#);
+        assert!(verify(secret, &format!("{}.{}.{}", header, forged, signature), now).is_err());
+        let none = URL_SAFE_NO_PAD.encode(br#"{"alg":"none","typ":"JWT"}"#);
+        assert!(verify(secret, &format!("{}.{}.", none, forged), now).is_err());
+    }
+}
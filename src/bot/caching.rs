@@ -0,0 +1,142 @@
+// src/bot/caching.rs
+//! # HTTP Caching
+//!
+//! Successful `GET` responses of the API carry a weak `ETag`, the hash of their body as sent
+//! to the client, fields selected and secrets redacted. Clients polling a list, prices or
+//! the server info send it back as `If-None-Match` and get an empty `304 Not Modified` while
+//! nothing changed, instead of the whole body again.
+//!
+//! Responses may be kept by the client, but not by shared caches as they depend on who asks,
+//! and must be revalidated before they are used again (`Cache-Control: private, no-cache`),
+//! unless the endpoint says otherwise. Streams, such as event streams, are not tagged.
+use crate::utils::crypto::sha256_hex;
+use actix_web::body::{self, BodySize, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderValue, CACHE_CONTROL, ETAG, IF_NONE_MATCH};
+use actix_web::http::{Method, StatusCode};
+use actix_web::middleware::Next;
+use actix_web::HttpResponse;
+
+/// How responses may be cached, unless their endpoint says otherwise.
+pub const DEFAULT_CACHE_CONTROL: &str = "private, no-cache";
+
+/// Weak entity tag of a response body.
+pub fn etag(body: &[u8]) -> String {
+    format!("W/\"{}\"", &sha256_hex(body)[..32])
+}
+
+/// Whether `If-None-Match` holds `etag`; tags compare weakly, as for `GET` they must.
+fn holds(if_none_match: &str, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    (if_none_match.split(',')).any(|tag| tag.trim() == "*" || opaque(tag) == opaque(etag))
+}
+
+/// Middleware tagging successful `GET` responses and answering `304 Not Modified` to
+/// clients that have them already.
+pub async fn conditional(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let cacheable = matches!(*req.method(), Method::GET | Method::HEAD);
+    let if_none_match = (req.headers().get(IF_NONE_MATCH))
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let response = next.call(req).await?;
+    let sized = matches!(response.response().body().size(), BodySize::Sized(_));
+    if !cacheable || !sized || response.status() != StatusCode::OK {
+        return Ok(response.map_into_boxed_body().map_into_left_body());
+    }
+    let (request, response) = response.into_parts();
+    let (mut response, content) = response.into_parts();
+    let content = body::to_bytes(content).await.map_err(|e| {
+        let e: Box<dyn std::error::Error> = e.into();
+        actix_web::error::ErrorInternalServerError(format!("Failed to read response: {}", e))
+    })?;
+    let etag = etag(&content);
+    let headers = response.headers_mut();
+    headers.insert(
+        ETAG,
+        HeaderValue::from_str(&etag).expect("a hex digest is a header value"),
+    );
+    if !headers.contains_key(CACHE_CONTROL) {
+        headers.insert(
+            CACHE_CONTROL,
+            HeaderValue::from_static(DEFAULT_CACHE_CONTROL),
+        );
+    }
+    if if_none_match.is_some_and(|tags| holds(&tags, &etag)) {
+        let mut not_modified = HttpResponse::NotModified().finish();
+        for name in [ETAG, CACHE_CONTROL] {
+            if let Some(value) = response.headers().get(&name) {
+                not_modified.headers_mut().insert(name, value.clone());
+            }
+        }
+        return Ok(ServiceResponse::new(
+            request,
+            not_modified.map_into_right_body(),
+        ));
+    }
+    let response = response.set_body(content).map_into_boxed_body();
+    Ok(ServiceResponse::new(request, response.map_into_left_body()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::middleware::from_fn;
+    use actix_web::{test, web, App};
+
+    #[actix_web::test]
+    async fn unchanged_responses_are_not_sent_again() {
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(conditional))
+                .route("/bots", web::get().to(|| async { "[]" }))
+                .route(
+                    "/versions",
+                    web::get().to(|| async {
+                        HttpResponse::Ok()
+                            .insert_header((CACHE_CONTROL, "public, max-age=3600"))
+                            .body("v1")
+                    }),
+                )
+                .route("/bots", web::post().to(|| async { "created" })),
+        )
+        .await;
+        let get = |uri| test::TestRequest::get().uri(uri);
+        let response = test::call_service(&app, get("/bots").to_request()).await;
+        let tag = response.headers().get(ETAG).unwrap().clone();
+        assert_eq!(tag.to_str().unwrap(), etag(b"[]"));
+        assert_eq!(
+            response.headers().get(CACHE_CONTROL).unwrap(),
+            DEFAULT_CACHE_CONTROL
+        );
+        assert_eq!(test::read_body(response).await, "[]");
+
+        // Strong or weak, the tag matches, also among others
+        for sent in [
+            tag.to_str().unwrap().to_string(),
+            format!("\"other\", {}", &etag(b"[]")[2..]),
+        ] {
+            let request = get("/bots")
+                .insert_header((IF_NONE_MATCH, sent))
+                .to_request();
+            let response = test::call_service(&app, request).await;
+            assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+            assert_eq!(response.headers().get(ETAG), Some(&tag));
+            assert!(test::read_body(response).await.is_empty());
+        }
+        let stale = get("/bots").insert_header((IF_NONE_MATCH, "W/\"stale\""));
+        let response = test::call_service(&app, stale.to_request()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = test::call_service(&app, get("/versions").to_request()).await;
+        assert_eq!(
+            response.headers().get(CACHE_CONTROL).unwrap(),
+            "public, max-age=3600"
+        );
+        let post = test::TestRequest::post().uri("/bots").to_request();
+        let response = test::call_service(&app, post).await;
+        assert!(response.headers().get(ETAG).is_none());
+    }
+}
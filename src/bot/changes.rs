@@ -0,0 +1,165 @@
+// src/bot/changes.rs
+//! # Changes Feed
+//!
+//! `GET /changes?since=<seq>` answers with the state changes of the tenant's bots and their
+//! listeners recorded in the event log after the sequence number `since`, oldest first, for
+//! integrations that keep a copy of the bots in sync but cannot hold a WebSocket or an event
+//! stream. Each change is the `state_change` event the timeline shows:
+//!
+//! ```json
+//! {"changes": [{"seq": 42, "timestamp": "…", "bot_id": "alpha", "kind": "state_change",
+//!   "summary": "Bot updated", "details": {"changes": […]}}],
+//!  "next": 42, "resync": false}
+//! ```
+//!
+//! The request is held until there is a change or `wait` seconds passed, so a client asking
+//! again with `since` set to `next` learns of changes as they happen without polling in a
+//! tight loop. The log is bounded: when changes after `since` were dropped from it already,
+//! `resync` says the client must fetch the bots again before following the feed from `next`.
+use crate::app_state::AppState;
+use crate::bot::api::{acquire_lock, create_api_response, Tenant};
+use crate::bot::state::events::Event;
+use crate::errors::AppError;
+use actix_web::{get, web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// How often the event log is checked while a request waits.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// Seconds a request waits for a change, unless it says otherwise.
+const DEFAULT_WAIT_SECS: u64 = 30;
+/// Longest a request may wait, in seconds.
+const MAX_WAIT_SECS: u64 = 60;
+/// Changes answered at a time, unless the request says otherwise.
+const DEFAULT_LIMIT: usize = 100;
+/// Most changes answered at a time.
+const MAX_LIMIT: usize = 1000;
+
+/// Which changes `GET /changes` answers with.
+#[derive(Debug, Default, Deserialize)]
+pub struct ChangesQuery {
+    /// Only the changes after this sequence number; all those still in the log if absent
+    #[serde(default)]
+    pub since: u64,
+    /// Seconds to wait for a change when there is none yet, up to 60; 0 answers at once
+    pub wait: Option<u64>,
+    /// Most changes to answer with, up to 1000
+    pub limit: Option<usize>,
+}
+
+impl ChangesQuery {
+    fn wait(&self) -> Duration {
+        Duration::from_secs(self.wait.unwrap_or(DEFAULT_WAIT_SECS).min(MAX_WAIT_SECS))
+    }
+
+    fn limit(&self) -> usize {
+        self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+    }
+}
+
+/// State changes after a sequence number.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct ChangeFeed {
+    /// State changes after `since`, oldest first
+    pub changes: Vec<Event>,
+    /// Sequence number to ask for the changes after these with
+    pub next: u64,
+    /// Changes after `since` are no longer in the log; the bots must be fetched again
+    pub resync: bool,
+}
+
+/// The state changes of the tenant after `query.since`, or none if there were none before
+/// `query.wait` passed.
+#[get("/changes")]
+pub async fn changes_feed(
+    data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
+    query: web::Query<ChangesQuery>,
+) -> Result<HttpResponse, AppError> {
+    let deadline = Instant::now() + query.wait();
+    loop {
+        let feed = {
+            let mut state = acquire_lock(&data)?;
+            let resync = state.events.lost_after(query.since);
+            let (changes, next) =
+                (state.scoped(tenant.name())).changes_after(query.since, query.limit());
+            ChangeFeed {
+                changes,
+                next,
+                resync,
+            }
+        };
+        let now = Instant::now();
+        if !feed.changes.is_empty() || feed.resync || now >= deadline {
+            return Ok(HttpResponse::Ok().json(create_api_response(true, Some(feed), None)));
+        }
+        tokio::time::sleep(POLL_INTERVAL.min(deadline - now)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app_config::AppConfig;
+    use crate::bot::api::{configure, ApiResponse};
+    use crate::bot::state::{BotInsertArgs, BotRegistry, BotUpdateArgs};
+    use actix_web::{test, App};
+    use tempfile::tempdir;
+
+    #[actix_web::test]
+    async fn the_feed_waits_for_the_next_change() {
+        let dir = tempdir().unwrap();
+        let mut config = AppConfig::default();
+        config.api_server.state_file = dir.path().join("state.json");
+        let mut state = AppState::load(config).unwrap();
+        state
+            .add_bot(
+                BotInsertArgs::new("a".to_string(), "binance".to_string())
+                    .bot_id(Some("a".to_string())),
+            )
+            .unwrap();
+        let state = Arc::new(Mutex::new(state));
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state.clone()))
+                .configure(configure),
+        )
+        .await;
+        let feed = |uri: String| {
+            let app = &app;
+            async move {
+                let request = test::TestRequest::get().uri(&uri).to_request();
+                let response: ApiResponse<ChangeFeed> =
+                    test::call_and_read_body_json(app, request).await;
+                response.data.unwrap()
+            }
+        };
+
+        let created = feed("/api/v1/changes?wait=0".to_string()).await;
+        assert_eq!(created.changes.len(), 1);
+        assert_eq!(created.changes[0].summary, "Bot created on binance");
+        assert!(!created.resync);
+        let idle = feed(format!("/api/v1/changes?since={}&wait=0", created.next)).await;
+        assert!(idle.changes.is_empty());
+        assert_eq!(idle.next, created.next);
+
+        let update = async {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            let mut args = BotUpdateArgs::new("a");
+            args.name = Some("b".to_string());
+            state.lock().unwrap().update_bot(args).unwrap();
+        };
+        let uri = format!("/api/v1/changes?since={}&wait=5", created.next);
+        let (updated, _) = futures_util::join!(feed(uri), update);
+        assert_eq!(updated.changes.len(), 1);
+        assert_eq!(updated.changes[0].summary, "Bot updated");
+        assert_eq!(updated.next, updated.changes[0].seq);
+
+        // A cursor from another state file cannot be followed
+        let ahead = feed("/api/v1/changes?since=1000&wait=5".to_string()).await;
+        assert!(ahead.resync && ahead.changes.is_empty());
+        assert_eq!(ahead.next, updated.next);
+    }
+}
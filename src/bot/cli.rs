@@ -4,10 +4,16 @@ use crate::app_config::AppConfig;
 use crate::app_state::AppState;
 use crate::bot::rest::RestClient;
 use crate::bot::state::{
-    BotDeleteArgs, BotGetArgs, BotInsertArgs, BotListArgs, BotUpdateArgs, ListenerDeleteArgs,
-    ListenerGetArgs, ListenerInsertArgs, ListenerListArgs, ListenerUpdateArgs, ListenersDeleteArgs,
-    ServerStartupArgs,
+    AlertListArgs, AlertReplayArgs, AlertSimulateArgs, AllocationReportArgs, BotDeleteArgs,
+    BotGetArgs, BotInsertArgs, BotListArgs, BotTimelineArgs, BotTradesArgs, BotUpdateArgs,
+    BotWatchArgs, BulkApplyArgs, ExecutionQualityArgs, FillImportArgs, ListenerDeleteArgs,
+    ListenerGetArgs, ListenerInsertArgs, ListenerListArgs, ListenerRotateSecretArgs,
+    ListenerUpdateArgs, ListenersDeleteArgs, MonteCarloArgs, OrderCancelArgs, ReplayArgs,
+    ServerStartupArgs, StateApplyArgs, StateCompactArgs, StateExportArgs, StateImportArgs,
+    StatementArgs, StrategyReportArgs, SymbolMapArgs, SymbolUnmapArgs, UserDeleteArgs,
+    UserInsertArgs,
 };
+use crate::utils::progress::OutputFormat;
 use clap::{Parser, Subcommand};
 // use log::LevelFilter;
 use std::io::{Error, ErrorKind, Result};
@@ -29,6 +35,11 @@ pub struct Cli {
     #[arg(long)]
     pub url: Option<String>,
 
+    /// Report the progress of long operations as bars on a terminal (text), or as JSON lines
+    /// on stderr (json)
+    #[arg(long, global = true, default_value_t)]
+    pub output: OutputFormat,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -39,6 +50,8 @@ impl Cli {
         match &self.command {
             Commands::Server { .. } => "server",
             Commands::Offline { .. } => "offline",
+            Commands::Migrate { .. } => "migrate",
+            Commands::Config { .. } => "config",
             _ => "online", // Default to "online" for all other commands
         }
     }
@@ -48,6 +61,8 @@ impl Cli {
         match self.mode() {
             "server" => run_server_mode(self.clone(), app_config, app_state).await,
             "offline" => run_offline_mode(self.clone()).await,
+            "migrate" => run_migrate_mode(self.clone()).await,
+            "config" => run_config_mode(self.clone()),
             "online" => run_online_mode(self.clone(), app_config).await,
             _ => Err(std::io::Error::new(
                 std::io::ErrorKind::InvalidInput,
@@ -65,23 +80,120 @@ pub enum Commands {
         #[arg(long)]
         state_file: Option<PathBuf>,
 
+        /// Change the state file even while a server uses it, which may then overwrite the
+        /// change or refuse to save
+        #[arg(long)]
+        force: bool,
+
         #[command(subcommand)]
         offline_command: OfflineCmds,
     },
+    /// Rewrite the local state file, e.g. to encrypt the secrets it holds
+    Migrate {
+        /// Use a local state file
+        #[arg(long)]
+        state_file: Option<PathBuf>,
+
+        #[command(subcommand)]
+        migrate_command: MigrateCmds,
+    },
+    /// Describe the configuration file
+    Config {
+        #[command(subcommand)]
+        config_command: ConfigCmds,
+    },
     // Server
     Server(ServerStartupArgs),
+    /// Check the server's readiness; exits non-zero if it is not ready (e.g. Docker HEALTHCHECK)
+    Healthcheck,
+    /// Show server facts, including the recovery report after an unclean shutdown
+    AdminInfo,
     // Online mode commands (mirrors offline commands but acts through REST)
     AddBot(BotInsertArgs),
     ListBots(BotListArgs),
     GetBot(BotGetArgs),
+    /// Show what happened to a bot, newest first
+    BotTimeline(BotTimelineArgs),
+    /// Follow a bot's timeline as new events happen
+    Watch(BotWatchArgs),
+    /// Show the balances of a bot's exchange account
+    Balances(BotGetArgs),
+    /// Show the open positions of a bot's exchange account
+    Positions(BotGetArgs),
+    /// List the orders a bot has open on its exchange
+    ListOrders(BotGetArgs),
+    /// Cancel an open order of a bot on its exchange
+    CancelOrder(OrderCancelArgs),
+    /// List the trades a bot has sent to its exchange, newest first
+    ListTrades(BotTradesArgs),
+    /// List the alerts a bot's listeners accepted, newest first, with their outcome
+    ListAlerts(AlertListArgs),
+    /// Dispatch a failed alert again, e.g. after fixing the bot's credentials
+    ReplayAlert(AlertReplayArgs),
+    /// Show what a listener would make of an alert payload, or dispatch it with --execute
+    SimulateAlert(AlertSimulateArgs),
+    /// Show a bot's realized and unrealized PnL per symbol
+    Pnl(BotGetArgs),
+    /// Show the latency and error rate of a bot's exchanges since the server started
+    BotStats(BotGetArgs),
+    /// Backfill a bot's trade history from a CSV of fills exported by its exchange
+    ImportFills(FillImportArgs),
+    /// Report the slippage of fills from alert prices, per bot and listener
+    ExecutionQuality(ExecutionQualityArgs),
+    /// Compare the win rate, profit factor, drawdown and Sharpe ratio of each listener's trades
+    StrategyReport(StrategyReportArgs),
+    /// Compare each bot's open positions with its budget and suggest rebalancing
+    AllocationReport(AllocationReportArgs),
+    /// Replay a bot's alerts of a day with the paper engine, to compare what they made with
+    /// what other risk limits would have made of them
+    Replay(ReplayArgs),
+    /// Reports that simulate a bot's trading
+    Report {
+        #[command(subcommand)]
+        report_command: ReportCmds,
+    },
+    /// Show the exchange symbols a bot trades for the symbols its alerts name
+    ListSymbols(BotGetArgs),
+    /// Trade an exchange symbol for alerts naming another symbol
+    MapSymbol(SymbolMapArgs),
+    /// Drop a bot's mapping of a symbol
+    UnmapSymbol(SymbolUnmapArgs),
     UpdateBot(BotUpdateArgs),
     DeleteBot(BotDeleteArgs),
     AddListener(ListenerInsertArgs),
     ListListeners(ListenerListArgs),
     GetListener(ListenerGetArgs),
     UpdateListener(ListenerUpdateArgs),
+    /// Give a listener a new secret, optionally keeping the old one valid for a grace period
+    RotateSecret(ListenerRotateSecretArgs),
     DeleteListener(ListenerDeleteArgs),
     DeleteListeners(ListenersDeleteArgs),
+    /// Export the bots and their listeners as JSON, YAML or TOML
+    Export(StateExportArgs),
+    /// Import the bots of an export, adding them or replacing all bots
+    Import(StateImportArgs),
+    /// Apply a manifest of operations on bots and listeners, all or none
+    Apply(BulkApplyArgs),
+    /// Maintenance of the server's state
+    State {
+        #[command(subcommand)]
+        state_command: StateCmds,
+    },
+}
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum StateCmds {
+    /// Prune alerts, events and trades older than the retention policy, optionally archiving
+    /// them
+    Compact(StateCompactArgs),
+}
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum ReportCmds {
+    /// Resample a bot's round trips to estimate the spread of its drawdowns and its risk of ruin
+    Montecarlo(MonteCarloArgs),
+    /// Render a bot's statement for a month as a standalone HTML page
+    Statement(StatementArgs),
 }
 
 #[derive(Subcommand, Clone, Debug)]
@@ -95,14 +207,99 @@ pub enum OfflineCmds {
     AddBot(BotInsertArgs),
     ListBots(BotListArgs),
     GetBot(BotGetArgs),
+    /// Show what happened to a bot, newest first
+    BotTimeline(BotTimelineArgs),
     UpdateBot(BotUpdateArgs),
     DeleteBot(BotDeleteArgs),
+    /// Trade an exchange symbol for alerts naming another symbol
+    MapSymbol(SymbolMapArgs),
+    /// Drop a bot's mapping of a symbol
+    UnmapSymbol(SymbolUnmapArgs),
     AddListener(ListenerInsertArgs),
     ListListeners(ListenerListArgs),
     GetListener(ListenerGetArgs),
     UpdateListener(ListenerUpdateArgs),
+    /// Give a listener a new secret, optionally keeping the old one valid for a grace period
+    RotateSecret(ListenerRotateSecretArgs),
     DeleteListener(ListenerDeleteArgs),
     DeleteListeners(ListenersDeleteArgs),
+    /// Export the bots and their listeners as JSON, YAML or TOML
+    Export(StateExportArgs),
+    /// Import the bots of an export, adding them or replacing all bots
+    Import(StateImportArgs),
+    /// Prune alerts, events and trades older than the retention policy, optionally archiving
+    /// them
+    Compact(StateCompactArgs),
+    /// Show what a listener would make of an alert payload, or dispatch it with --execute
+    SimulateAlert(AlertSimulateArgs),
+    /// Replay a bot's alerts of a day with the paper engine, to compare what they made with
+    /// what other risk limits would have made of them
+    Replay(ReplayArgs),
+    /// Run the commands of a manifest, saving the state once if they all succeed
+    Apply(StateApplyArgs),
+    /// Add a user who may sign in to the API, or replace their password and role
+    AddUser(UserInsertArgs),
+    /// List the users who may sign in to the API
+    ListUsers,
+    /// Delete a user; their tokens stop working
+    DeleteUser(UserDeleteArgs),
+}
+
+impl OfflineCmds {
+    /// Whether the command changes the state, rather than only reading it.
+    pub fn is_mutation(&self) -> bool {
+        match self {
+            OfflineCmds::ListBots(_)
+            | OfflineCmds::GetBot(_)
+            | OfflineCmds::BotTimeline(_)
+            | OfflineCmds::ListListeners(_)
+            | OfflineCmds::GetListener(_)
+            | OfflineCmds::Export(_)
+            | OfflineCmds::Replay(_)
+            | OfflineCmds::ListUsers => false,
+            OfflineCmds::SimulateAlert(args) => args.execute,
+            OfflineCmds::ClearAll { .. }
+            | OfflineCmds::AddBot(_)
+            | OfflineCmds::UpdateBot(_)
+            | OfflineCmds::DeleteBot(_)
+            | OfflineCmds::MapSymbol(_)
+            | OfflineCmds::UnmapSymbol(_)
+            | OfflineCmds::AddListener(_)
+            | OfflineCmds::UpdateListener(_)
+            | OfflineCmds::RotateSecret(_)
+            | OfflineCmds::DeleteListener(_)
+            | OfflineCmds::DeleteListeners(_)
+            | OfflineCmds::Import(_)
+            | OfflineCmds::Compact(_)
+            | OfflineCmds::Apply(_)
+            | OfflineCmds::AddUser(_)
+            | OfflineCmds::DeleteUser(_) => true,
+        }
+    }
+}
+
+/// A line of an `offline apply` manifest.
+#[derive(Parser, Clone, Debug)]
+#[command(name = "offline", no_binary_name = true)]
+pub struct ManifestLine {
+    #[command(subcommand)]
+    pub command: OfflineCmds,
+}
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum MigrateCmds {
+    /// Encrypt the bots' secrets with the configured encryption key
+    EncryptState,
+}
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum ConfigCmds {
+    /// Print the JSON Schema of config.toml, for editors to validate and complete it
+    Schema {
+        /// Write the schema to this file instead of printing it
+        #[arg(short, long)]
+        file: Option<PathBuf>,
+    },
 }
 
 /// Handle server mode
@@ -126,15 +323,16 @@ async fn run_server_mode(
 async fn run_offline_mode(cli: Cli) -> Result<()> {
     if let Commands::Offline {
         state_file,
+        force,
         offline_command,
     } = cli.command
     {
         // Convert state_file from Option<PathBuf> to Option<&Path>
         let state_file = state_file.as_deref();
 
-        super::local_client::run(state_file, offline_command)
+        super::local_client::run(state_file, force, offline_command)
             .await
-            .map_err(|err| Error::new(ErrorKind::Other, err))
+            .map_err(Error::other)
     } else {
         Err(Error::new(
             ErrorKind::InvalidInput,
@@ -143,10 +341,54 @@ async fn run_offline_mode(cli: Cli) -> Result<()> {
     }
 }
 
+/// Handle migrate mode
+async fn run_migrate_mode(cli: Cli) -> Result<()> {
+    if let Commands::Migrate {
+        state_file,
+        migrate_command,
+    } = cli.command
+    {
+        super::local_client::migrate(state_file.as_deref(), migrate_command)
+            .await
+            .map_err(Error::other)
+    } else {
+        Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Invalid command for migrate mode.",
+        ))
+    }
+}
+
+/// Handle config mode
+fn run_config_mode(cli: Cli) -> Result<()> {
+    let Commands::Config { config_command } = cli.command else {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Invalid command for config mode.",
+        ));
+    };
+    match config_command {
+        ConfigCmds::Schema { file } => {
+            let schema = serde_json::to_string_pretty(&crate::app_config::schema::config_schema())?;
+            match file {
+                Some(path) => std::fs::write(path, schema + "\n"),
+                None => {
+                    println!("{}", schema);
+                    Ok(())
+                }
+            }
+        }
+    }
+}
+
 /// Handle online mode
 async fn run_online_mode(cli: Cli, app_config: AppConfig) -> Result<()> {
-    let rest_client = RestClient::new(&cli.url.unwrap_or(app_config.remote_cli.url));
+    let rest_client = RestClient::new(&cli.url.unwrap_or(app_config.remote_cli.url))
+        .with_token(app_config.remote_cli.token)
+        .with_websocket(app_config.remote_cli.websocket)
+        .negotiate()
+        .await;
     super::remote_client::run(cli.command, rest_client)
         .await
-        .map_err(|err| Error::new(ErrorKind::Other, err))
+        .map_err(Error::other)
 }
@@ -0,0 +1,430 @@
+// src/bot/compaction.rs
+//! # State Compaction
+//!
+//! Alerts and timeline events are saved with the state, so every one kept makes each load
+//! and save of it slower, and the trade history and logs only grow. Compaction prunes what
+//! the `[retention]` policy no longer keeps, with a period per kind of data: `alerts`,
+//! `events`, `trades`, `audit` for the lines of `tenancy.audit_log` and `access_log` for
+//! those of `api_server.access_log`. Trades go a monthly shard at a time, once the whole
+//! month is older.
+//!
+//! With `retention.archive`, pruned records are first appended to a side file, one JSON
+//! object per line tagged with its `type`:
+//!
+//! ```text
+//! <state_file>.archive.jsonl
+//! ```
+//!
+//! The archived alerts are read back to [replay](crate::bot::replay) the days they were
+//! received.
+//!
+//! `xtrade state compact` compacts the state of a server, `xtrade offline compact` a state
+//! file; both may override the policy. With `compaction.enabled` the server compacts its
+//! state at startup and every `compaction.interval_hours`, and counts what it pruned in the
+//! server's [metrics](crate::bot::metrics).
+use crate::app_config::{AppConfig, CompactionConfig, RetentionConfig};
+use crate::app_state::AppState;
+use crate::bot::metrics::Metrics;
+use crate::bot::state::alerts::AlertRecord;
+use crate::bot::state::events::Event;
+use crate::bot::state::history::{TradeHistory, TradeRecord};
+use crate::bot::state::{StateCompactArgs, StateCompactView};
+use crate::errors::AppError;
+use crate::utils::jsonl;
+use crate::utils::progress::Progress;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// A pruned record, as appended to the archive.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Archived<'a> {
+    Alert {
+        alert: &'a AlertRecord,
+    },
+    Event {
+        event: &'a Event,
+    },
+    Trade {
+        /// Storage key of the bot
+        bot: &'a str,
+        trade: &'a TradeRecord,
+    },
+    Audit {
+        entry: &'a Value,
+    },
+    AccessLog {
+        entry: &'a Value,
+    },
+}
+
+#[derive(Clone, Copy, Debug)]
+enum LogKind {
+    Audit,
+    Access,
+}
+
+impl LogKind {
+    fn archived(self, entry: &Value) -> Archived<'_> {
+        match self {
+            LogKind::Audit => Archived::Audit { entry },
+            LogKind::Access => Archived::AccessLog { entry },
+        }
+    }
+
+    fn count(self, view: &mut StateCompactView) -> &mut usize {
+        match self {
+            LogKind::Audit => &mut view.audit,
+            LogKind::Access => &mut view.access_log,
+        }
+    }
+}
+
+/// Log files compaction prunes (from the configuration).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LogFiles {
+    pub audit: Option<PathBuf>,
+    pub access: Option<PathBuf>,
+}
+
+impl LogFiles {
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self {
+            audit: config.tenancy.audit_log.clone(),
+            access: config.api_server.access_log.clone(),
+        }
+    }
+}
+
+/// The archive of the state kept in `state_file`.
+pub fn archive_path(state_file: &Path) -> PathBuf {
+    let mut path = state_file.as_os_str().to_owned();
+    path.push(".archive.jsonl");
+    PathBuf::from(path)
+}
+
+/// The alerts of the archive at `path`, in the order they were archived; none if there is no
+/// archive. Lines that are no archived alert are passed over.
+pub fn archived_alerts(path: &Path) -> io::Result<Vec<AlertRecord>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut alerts = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let Ok(mut record) = serde_json::from_str::<Value>(&line?) else {
+            continue;
+        };
+        if record["type"] != "alert" {
+            continue;
+        }
+        if let Ok(alert) = serde_json::from_value(record["alert"].take()) {
+            alerts.push(alert);
+        }
+    }
+    Ok(alerts)
+}
+
+/// The retention policy `config`, with what `args` override.
+pub fn policy(config: &RetentionConfig, args: &StateCompactArgs) -> RetentionConfig {
+    RetentionConfig {
+        alerts: args.alerts.unwrap_or(config.alerts),
+        events: args.events.unwrap_or(config.events),
+        trades: args.trades.unwrap_or(config.trades),
+        audit: args.audit.unwrap_or(config.audit),
+        access_log: args.access_log.unwrap_or(config.access_log),
+        archive: args.archive || config.archive,
+    }
+}
+
+/// Compact the state every `config.interval_hours`, as its retention policy says.
+pub async fn run(app_state: Arc<Mutex<AppState>>, config: CompactionConfig, metrics: Arc<Metrics>) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+        config.interval_hours.max(1) * 3600,
+    ));
+    loop {
+        interval.tick().await;
+        let Ok(mut state) = app_state.lock() else {
+            log::error!("Failed to acquire lock on AppState; skipping the compaction.");
+            continue;
+        };
+        let policy = state.retention.clone();
+        match compact(&mut state, &policy) {
+            Ok(view) => {
+                if view.pruned() > 0 {
+                    log::info!("{}", view);
+                }
+                count(&metrics, &view, state.providers.clock.now());
+            }
+            Err(e) => log::error!("Failed to compact the state: {}", e),
+        }
+    }
+}
+
+/// Count the compaction that pruned `view` at `now` in `metrics`.
+pub fn count(metrics: &Metrics, view: &StateCompactView, now: DateTime<Utc>) {
+    metrics.add("xtrade_compactions_total", &[], 1.0);
+    metrics.set(
+        "xtrade_last_compaction_timestamp_seconds",
+        &[],
+        now.timestamp() as f64,
+    );
+    for (data, pruned) in view.counts() {
+        metrics.add(
+            "xtrade_pruned_records_total",
+            &[("data", data)],
+            pruned as f64,
+        );
+    }
+}
+
+/// Prune the alerts, events, trades and log lines `policy` no longer keeps, archiving them
+/// first if it says so, and save the state.
+pub fn compact(
+    state: &mut AppState,
+    policy: &RetentionConfig,
+) -> Result<StateCompactView, AppError> {
+    compact_with(state, policy, &mut Progress::hidden())
+}
+
+/// [`compact`], reporting on the trade shards read to `progress`.
+pub fn compact_with(
+    state: &mut AppState,
+    policy: &RetentionConfig,
+    progress: &mut Progress,
+) -> Result<StateCompactView, AppError> {
+    let now = state.providers.clock.now();
+    let history_error = |e: io::Error| {
+        AppError::InternalServerError(format!("Failed to prune trade history: {}", e))
+    };
+
+    // Pruned from copies, so nothing is dropped unless the archive took it
+    let mut alerts = state.alerts.clone();
+    let pruned_alerts =
+        (policy.alerts.cutoff(now)).map_or_else(Vec::new, |before| alerts.prune(before));
+    let mut events = state.events.clone();
+    let pruned_events =
+        (policy.events.cutoff(now)).map_or_else(Vec::new, |before| events.prune(before));
+
+    let history = TradeHistory::for_state_file(&state.state_file);
+    let shards = match policy.trades.cutoff(now) {
+        Some(before) => expired_shards(&history, before).map_err(history_error)?,
+        None => Vec::new(),
+    };
+    let mut pruned_trades = Vec::with_capacity(shards.len());
+    progress.set_total(shards.len() as u64);
+    for (bot, month) in &shards {
+        let trades = history.load_month(bot, month).map_err(history_error)?;
+        pruned_trades.push((bot.as_str(), trades));
+        progress.advance(1);
+    }
+
+    let mut view = StateCompactView {
+        alerts: pruned_alerts.len(),
+        events: pruned_events.len(),
+        trades: pruned_trades.iter().map(|(_, trades)| trades.len()).sum(),
+        shards: shards.len(),
+        ..Default::default()
+    };
+
+    if policy.archive && view.pruned() > 0 {
+        let path = archive_path(&state.state_file);
+        let records = (pruned_alerts.iter().map(|alert| Archived::Alert { alert }))
+            .chain(pruned_events.iter().map(|event| Archived::Event { event }))
+            .chain(pruned_trades.iter().flat_map(|(bot, trades)| {
+                trades.iter().map(|trade| Archived::Trade { bot, trade })
+            }));
+        append(&path, records).map_err(|e| {
+            AppError::InternalServerError(format!("Failed to write archive {:?}: {}", path, e))
+        })?;
+        view.archive = Some(path);
+    }
+    for (bot, month) in &shards {
+        history.remove_month(bot, month).map_err(history_error)?;
+    }
+    if view.alerts + view.events > 0 {
+        state.alerts = alerts;
+        state.events = events;
+        state.save::<PathBuf>(None)?;
+    }
+
+    for (kind, log, period) in [
+        (LogKind::Audit, &state.log_files.audit, policy.audit),
+        (LogKind::Access, &state.log_files.access, policy.access_log),
+    ] {
+        let (Some(log), Some(before)) = (log, period.cutoff(now)) else {
+            continue;
+        };
+        let archive = policy.archive.then(|| archive_path(&state.state_file));
+        let pruned = jsonl::prune(log, before, |entries| match &archive {
+            Some(path) => append(path, entries.iter().map(|entry| kind.archived(entry))),
+            None => Ok(()),
+        })
+        .map_err(|e| AppError::InternalServerError(format!("Failed to prune {:?}: {}", log, e)))?;
+        if pruned > 0 {
+            *kind.count(&mut view) = pruned;
+            view.archive = view.archive.take().or(archive);
+        }
+    }
+    Ok(view)
+}
+
+/// Bots and months of the trade shards whose whole month is before `before`.
+fn expired_shards(
+    history: &TradeHistory,
+    before: DateTime<Utc>,
+) -> io::Result<Vec<(String, String)>> {
+    let current = before.format("%Y-%m").to_string();
+    let mut shards = Vec::new();
+    for bot in history.bots()? {
+        for month in history.months(&bot)? {
+            if month < current {
+                shards.push((bot.clone(), month));
+            }
+        }
+    }
+    Ok(shards)
+}
+
+/// Append `records` to the archive at `path`, on disk before returning.
+fn append<'a>(path: &Path, records: impl Iterator<Item = Archived<'a>>) -> io::Result<()> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let mut writer = BufWriter::new(file);
+    for record in records {
+        serde_json::to_writer(&mut writer, &record)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.into_inner().map_err(|e| e.into_error())?.sync_all()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::dispatch::{ExecutionResult, ExecutionStatus};
+    use crate::bot::state::events::EventKind;
+    use chrono::{Duration, TimeZone};
+    use tempfile::tempdir;
+
+    fn alert(id: &str, received_at: DateTime<Utc>) -> AlertRecord {
+        AlertRecord {
+            id: id.to_string(),
+            received_at,
+            bot_id: "alpha".to_string(),
+            listener_id: "tv".to_string(),
+            tenant: None,
+            payload: "{}".to_string(),
+            replay_of: None,
+            error: None,
+            result: None,
+        }
+    }
+
+    fn trade(order_id: &str, finished_at: DateTime<Utc>) -> TradeRecord {
+        TradeRecord {
+            result: ExecutionResult {
+                order_id: Some(order_id.to_string()),
+                bot_id: "alpha".to_string(),
+                listener_id: "tv".to_string(),
+                exchange: "binance".to_string(),
+                account: None,
+                action: "buy".to_string(),
+                symbol: "BTCUSDT".to_string(),
+                order_size: "0.5".to_string(),
+                status: ExecutionStatus::Executed,
+                message: "order accepted by the exchange".to_string(),
+                report: None,
+                duplicate: false,
+                error_code: None,
+                warnings: Vec::new(),
+                latency_ms: None,
+                finished_at,
+            },
+            order: None,
+        }
+    }
+
+    #[test]
+    fn records_past_retention_are_archived_then_pruned() {
+        let dir = tempdir().unwrap();
+        let mut state = AppState {
+            state_file: dir.path().join("state.json"),
+            ..Default::default()
+        };
+        let now = Utc::now();
+        let day = Duration::days(1);
+        state.alerts.record(alert("old", now - day * 40));
+        state.alerts.record(alert("new", now - day));
+        for age in [100, 5] {
+            state.events.record(
+                now - day * age,
+                "alpha",
+                EventKind::StateChange,
+                format!("{} days ago", age),
+                None,
+            );
+        }
+        let history = TradeHistory::for_state_file(&state.state_file);
+        let old = Utc.with_ymd_and_hms(2020, 1, 15, 12, 0, 0).unwrap();
+        history.append("alpha", &trade("1", old)).unwrap();
+        history.append("alpha", &trade("2", now)).unwrap();
+        let audit_log = dir.path().join("audit.jsonl");
+        let line = |age: i32| format!(r#"{{"timestamp":"{}"}}"#, (now - day * age).to_rfc3339());
+        jsonl::append(&audit_log, [line(400), line(10)]).unwrap();
+        state.log_files.audit = Some(audit_log.clone());
+
+        // Trades are kept by default, audit lines for a year
+        let view = compact(&mut state, &RetentionConfig::default()).unwrap();
+        assert_eq!(
+            (view.alerts, view.events, view.trades, view.audit),
+            (1, 0, 0, 1)
+        );
+        assert_eq!(
+            std::fs::read_to_string(&audit_log).unwrap(),
+            line(10) + "\n"
+        );
+        assert!(!archive_path(&state.state_file).exists());
+
+        let args = StateCompactArgs {
+            events: Some("30d".parse().unwrap()),
+            trades: Some("1y".parse().unwrap()),
+            archive: true,
+            ..Default::default()
+        };
+        let view = compact(&mut state, &policy(&RetentionConfig::default(), &args)).unwrap();
+        assert_eq!(
+            (view.events, view.trades, view.shards, view.pruned()),
+            (1, 1, 1, 2)
+        );
+        assert_eq!((state.alerts.len(), state.events.len()), (1, 1));
+        assert_eq!(history.recent("alpha", 10).unwrap().len(), 1);
+
+        let archive = std::fs::read_to_string(view.archive.as_ref().unwrap()).unwrap();
+        let types: Vec<String> = archive
+            .lines()
+            .map(|line| {
+                serde_json::from_str::<serde_json::Value>(line).unwrap()["type"].to_string()
+            })
+            .collect();
+        assert_eq!(types, ["\"event\"", "\"trade\""]);
+
+        // Saved without what was pruned
+        let saved: AppState =
+            serde_json::from_slice(&std::fs::read(&state.state_file).unwrap()).unwrap();
+        assert_eq!((saved.alerts.len(), saved.events.len()), (1, 1));
+
+        #[cfg(feature = "metrics")]
+        {
+            let metrics = Metrics::default();
+            count(&metrics, &view, now);
+            assert!(metrics
+                .render()
+                .contains("xtrade_pruned_records_total{data=\"trades\"} 1\n"));
+        }
+    }
+}
@@ -0,0 +1,106 @@
+// src/bot/compression.rs
+//! # Response Compression
+//!
+//! The API and Web UI servers compress their responses with gzip, brotli or zstd for clients
+//! that accept it, as trade histories and bot lists can get large over slow links. Responses
+//! smaller than `compression.min_size` are sent as they are, as are event streams, which a
+//! compressor would hold back until it has enough of them.
+//!
+//! actix-web's [`Compress`](actix_web::middleware::Compress) compresses whatever is not
+//! already encoded, so [`mark`] tells it what to leave alone with `Content-Encoding:
+//! identity`, and [`unmark`], around it, takes that header off again before responses are
+//! sent.
+use crate::app_config::CompressionConfig;
+use actix_web::body::{BodySize, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderValue, CONTENT_ENCODING, CONTENT_TYPE};
+use actix_web::middleware::Next;
+use actix_web::web;
+
+const IDENTITY: HeaderValue = HeaderValue::from_static("identity");
+
+/// Middleware, inside `Compress`, marking the responses it should not compress.
+pub async fn mark(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let config = (req.app_data::<web::Data<CompressionConfig>>())
+        .map(|config| config.get_ref().clone())
+        .unwrap_or_default();
+    let mut response = next.call(req).await?;
+    let small = match response.response().body().size() {
+        BodySize::Sized(size) => size < config.min_size,
+        BodySize::None => true,
+        BodySize::Stream => false,
+    };
+    let events = (response.headers().get(CONTENT_TYPE))
+        .is_some_and(|value| value.as_bytes().starts_with(b"text/event-stream"));
+    if small || events {
+        let headers = response.headers_mut();
+        if !headers.contains_key(CONTENT_ENCODING) {
+            headers.insert(CONTENT_ENCODING, IDENTITY);
+        }
+    }
+    Ok(response)
+}
+
+/// Middleware, around `Compress`, dropping the marks of [`mark`].
+pub async fn unmark(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let mut response = next.call(req).await?;
+    if response.headers().get(CONTENT_ENCODING) == Some(&IDENTITY) {
+        response.headers_mut().remove(CONTENT_ENCODING);
+    }
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::header::ACCEPT_ENCODING;
+    use actix_web::middleware::{from_fn, Compress};
+    use actix_web::{test, App, HttpResponse};
+
+    #[actix_web::test]
+    async fn only_large_responses_are_compressed() {
+        let config = CompressionConfig {
+            enabled: true,
+            min_size: 100,
+        };
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(config))
+                .wrap(from_fn(mark))
+                .wrap(Compress::default())
+                .wrap(from_fn(unmark))
+                .route("/small", web::get().to(|| async { "small" }))
+                .route("/large", web::get().to(|| async { "large ".repeat(100) }))
+                .route(
+                    "/events",
+                    web::get().to(|| async {
+                        HttpResponse::Ok()
+                            .content_type("text/event-stream")
+                            .body("data: x\n\n".repeat(100))
+                    }),
+                ),
+        )
+        .await;
+        let encoding = |uri: &'static str| {
+            let app = &app;
+            async move {
+                let request = test::TestRequest::get()
+                    .uri(uri)
+                    .insert_header((ACCEPT_ENCODING, "gzip"))
+                    .to_request();
+                let response = test::call_service(app, request).await;
+                (response.headers().get(CONTENT_ENCODING))
+                    .map(|value| value.to_str().unwrap().to_string())
+            }
+        };
+        assert_eq!(encoding("/large").await.as_deref(), Some("gzip"));
+        assert_eq!(encoding("/small").await, None);
+        assert_eq!(encoding("/events").await, None);
+    }
+}
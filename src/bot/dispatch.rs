@@ -0,0 +1,2505 @@
+// src/bot/dispatch.rs
+//! # Alert Dispatch
+//!
+//! Connects incoming alerts to the exchanges. A webhook request names a bot and one of its
+//! listeners; once the listener's secret checks out, the listener's service decides how the
+//! body is parsed into an [`Alert`], and [`dispatch`] turns that alert into a trade:
+//!
+//! 1. resolve the bot the alert targets and the exchange adapter for it,
+//! 2. count the trade against the tenant's quota and journal it as submitted,
+//! 3. call [`Exchange::execute_trade`](crate::exchange::Exchange::execute_trade) without
+//!    holding the state lock,
+//! 4. record the outcome on the bot's timeline and in its
+//!    [trade history](crate::bot::state::history), and return it as an [`ExecutionResult`].
+//!
+//! Bots whose exchange is in a configured maintenance window ignore their alerts until it
+//! ends. A bot in an exclusion group refuses alerts for a symbol another bot of the group
+//! holds a position in. A bot with a [budget](crate::bot::allocation) has orders that would
+//! take its open positions past it cut down to what fits. An order whose exchange call never returned stays in the
+//! [journal](crate::bot::state::journal) and is listed by the next recovery report. Orders
+//! for an exchange that cannot be reached wait for it in
+//! [`Outages`](crate::bot::outage::Outages) and are sent by [`replay`] once it returns.
+//! Webhook alerts repeated within the [dedup window](crate::bot::state::dedup) are answered
+//! by [`deduplicate`] and never reach [`dispatch`].
+use crate::alert::tradingview::TradingViewAlert;
+use crate::alert::{self, Alert};
+use crate::app_config::PrecisionConfig;
+use crate::app_state::AppState;
+use crate::bot::allocation;
+use crate::bot::model::{Bot, Listener};
+use crate::bot::notify::{self, Notification};
+use crate::bot::outage::{Outages, PendingOrder};
+use crate::bot::portfolio::Portfolio;
+use crate::bot::secrets::Secrets;
+use crate::bot::state::alerts::AlertRecord;
+use crate::bot::state::dedup::AlertKey;
+use crate::bot::state::events::EventKind;
+use crate::bot::state::history::{TradeHistory, TradeRecord};
+use crate::bot::state::journal::JournalEntry;
+use crate::bot::state::output::AlertSimulationView;
+use crate::bot::state::paper::PaperFill;
+use crate::bot::state::BotRegistry;
+use crate::errors::{AppError, ErrorCode};
+use crate::exchange::prices::PriceCache;
+use crate::exchange::{
+    self, Exchange, ExecutionReport, OrderRequest, Side, Symbol, SymbolMap, TimeInForce,
+};
+use crate::utils::crypto::{constant_time_eq, hmac_sha256_hex, sha256_hex};
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::{dec, Decimal};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Instant;
+
+/// Slippage tolerances alerts may set stay below the whole price.
+const MAX_SLIPPAGE_BPS: Decimal = dec!(10_000);
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionStatus {
+    /// The exchange accepted the order
+    Executed,
+    /// The order could not be placed
+    Failed,
+    /// The alert was received but not acted upon
+    Ignored,
+    /// The exchange is unreachable; the order waits for it to return
+    Buffered,
+}
+
+impl fmt::Display for ExecutionStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            ExecutionStatus::Executed => "executed",
+            ExecutionStatus::Failed => "failed",
+            ExecutionStatus::Ignored => "ignored",
+            ExecutionStatus::Buffered => "buffered",
+        };
+        write!(f, "{}", status)
+    }
+}
+
+impl std::str::FromStr for ExecutionStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "executed" => Ok(ExecutionStatus::Executed),
+            "failed" => Ok(ExecutionStatus::Failed),
+            "ignored" => Ok(ExecutionStatus::Ignored),
+            "buffered" => Ok(ExecutionStatus::Buffered),
+            _ => Err(format!(
+                "expected executed, failed, ignored or buffered, not '{}'",
+                s
+            )),
+        }
+    }
+}
+
+/// What became of an alert; logged and returned to the webhook caller.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ExecutionResult {
+    /// Journal ID of the order, if one was placed
+    pub order_id: Option<String>,
+    pub bot_id: String,
+    pub listener_id: String,
+    pub exchange: String,
+    /// Sub-account the order was routed to; unset for the bot's main account
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account: Option<String>,
+    pub action: String,
+    pub symbol: String,
+    pub order_size: String,
+    pub status: ExecutionStatus,
+    pub message: String,
+    /// What the exchange reported, for executed orders
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub report: Option<ExecutionReport>,
+    /// The alert repeated one received shortly before; this is the outcome of that one
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub duplicate: bool,
+    /// Why a failed order failed, from the [catalogue](ErrorCode)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<ErrorCode>,
+    /// What limits the outcome without failing it, e.g. a fill whose fee is not known
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// Milliseconds from receiving the alert to the exchange acknowledging its order
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    pub finished_at: DateTime<Utc>,
+}
+
+impl fmt::Display for ExecutionResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Alert for bot '{}' via listener '{}' {}: {} {} {} on {}",
+            self.bot_id,
+            self.listener_id,
+            self.status,
+            self.action,
+            self.order_size,
+            self.symbol,
+            self.exchange,
+        )?;
+        if let Some(account) = &self.account {
+            write!(f, " ({})", account)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// The order an alert asks for.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub(crate) struct Order {
+    bot_id: String,
+    listener_id: String,
+    action: String,
+    /// What to place on the exchange
+    request: OrderRequest,
+    symbol: String,
+    /// Symbol the alert named, if the bot trades it under another one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mapped_from: Option<String>,
+    order_size: String,
+    /// `order_size` the alert asked for, if the bot's budget cut it down
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sized_from: Option<String>,
+    /// `order_size` in units of the base asset
+    quantity: Decimal,
+    position_size: String,
+    /// As sent by the alert source
+    schema: String,
+    timestamp: String,
+    /// Price when the alert fired: the alert's `alert_price`, or else the fresh cached price
+    /// of the symbol when the alert arrived
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    alert_price: Option<Decimal>,
+    /// Slippage tolerance the alert set, in basis points
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    slippage_bps: Option<Decimal>,
+}
+
+impl Order {
+    pub(crate) fn from_alert(alert: Alert, listener_id: &str) -> Result<Self, AppError> {
+        let Alert::TradingView(alert) = alert;
+        alert.validate().map_err(AppError::ValidationError)?;
+        let quantity = alert
+            .order_size
+            .trim()
+            .parse::<Decimal>()
+            .ok()
+            .filter(|quantity| *quantity > Decimal::ZERO)
+            .ok_or_else(|| {
+                AppError::ValidationError(format!(
+                    "order_size must be a positive quantity, not '{}'",
+                    alert.order_size
+                ))
+            })?;
+        let request = order_request(&alert)?;
+        let alert_price = match &alert.alert_price {
+            Some(price) => Some(
+                price
+                    .trim()
+                    .parse::<Decimal>()
+                    .ok()
+                    .filter(|price| *price > Decimal::ZERO)
+                    .ok_or_else(|| {
+                        AppError::ValidationError(format!(
+                            "alert_price must be a positive number, not '{}'",
+                            price
+                        ))
+                    })?,
+            ),
+            None => None,
+        };
+        let slippage_bps = match &alert.slippage_bps {
+            Some(bps) => Some(
+                bps.trim()
+                    .parse::<Decimal>()
+                    .ok()
+                    .filter(|bps| *bps > Decimal::ZERO && *bps < MAX_SLIPPAGE_BPS)
+                    .ok_or_else(|| {
+                        AppError::ValidationError(format!(
+                            "slippage_bps must be a number of basis points above 0 and below {}, not '{}'",
+                            MAX_SLIPPAGE_BPS, bps
+                        ))
+                    })?,
+            ),
+            None => None,
+        };
+        Ok(Self {
+            bot_id: alert.bot_id,
+            listener_id: listener_id.to_string(),
+            action: alert.action.to_lowercase(),
+            request,
+            symbol: Symbol::normalize(&alert.ticker),
+            mapped_from: None,
+            order_size: alert.order_size,
+            sized_from: None,
+            quantity,
+            position_size: alert.position_size,
+            schema: alert.schema,
+            timestamp: alert.timestamp,
+            alert_price,
+            slippage_bps,
+        })
+    }
+
+    /// Slippage tolerance of the order as a fraction, or 0 for the venue's default.
+    fn slippage(&self) -> f64 {
+        (self.slippage_bps.unwrap_or_default() / dec!(10_000))
+            .to_f64()
+            .unwrap_or_default()
+    }
+
+    /// The order with the fresh cached price of its symbol as the alert price, unless the
+    /// alert gave one.
+    fn quoted(self, prices: &PriceCache, now: DateTime<Utc>) -> Self {
+        if self.alert_price.is_some() {
+            return self;
+        }
+        let alert_price = prices
+            .fresh(&self.symbol, now)
+            .ok()
+            .and_then(|quote| Decimal::try_from(quote.price).ok());
+        Self {
+            alert_price,
+            ..self
+        }
+    }
+
+    pub(crate) fn alert_price(&self) -> Option<Decimal> {
+        self.alert_price
+    }
+
+    pub(crate) fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    pub(crate) fn quantity(&self) -> Decimal {
+        self.quantity
+    }
+
+    pub(crate) fn request(&self) -> &OrderRequest {
+        &self.request
+    }
+
+    /// The order on the exchange symbol the bot's `symbols`, or else the configured `global`
+    /// mappings, give for its symbol.
+    fn mapped(self, symbols: Option<&SymbolMap>, global: &SymbolMap) -> Self {
+        let mapped = symbols
+            .and_then(|symbols| symbols.get(&self.symbol))
+            .or_else(|| global.get(&self.symbol));
+        match mapped {
+            Some(symbol) if symbol != self.symbol => Self {
+                mapped_from: Some(self.symbol.clone()),
+                symbol,
+                ..self
+            },
+            _ => self,
+        }
+    }
+
+    /// The order with its quantity and prices rounded to the decimal places of its symbol.
+    /// Fails if any of them rounds to nothing.
+    fn rounded(self, precision: &PrecisionConfig) -> Result<Self, AppError> {
+        let quantity = precision.round_quantity(&self.symbol, self.quantity);
+        if quantity.is_zero() {
+            return Err(AppError::ValidationError(format!(
+                "order_size {} of {} rounds to nothing",
+                self.order_size, self.symbol
+            )));
+        }
+        let symbol = self.symbol.clone();
+        let request = self
+            .request
+            .map_prices(|price| precision.round_price(&symbol, price));
+        if request.prices().iter().any(|price| price.is_zero()) {
+            return Err(AppError::ValidationError(format!(
+                "{} prices of {} round to nothing",
+                request.kind(),
+                symbol
+            )));
+        }
+        Ok(Self {
+            quantity,
+            request,
+            ..self
+        })
+    }
+
+    /// What the bot's budget did to the order, if it cut it down.
+    fn budget_warning(&self) -> Option<String> {
+        let asked = self.sized_from.as_ref()?;
+        Some(format!(
+            "order_size cut from {} to {} to stay within the bot's budget",
+            asked, self.order_size
+        ))
+    }
+
+    pub(crate) fn side(&self) -> Side {
+        self.request.side()
+    }
+
+    pub(crate) fn summary(&self) -> String {
+        match self.request {
+            OrderRequest::Market { .. } => {
+                format!("{} {} {}", self.action, self.order_size, self.symbol)
+            }
+            _ => format!("{} {} ({})", self.order_size, self.symbol, self.request),
+        }
+    }
+
+    fn result(
+        &self,
+        exchange: &str,
+        order_id: Option<String>,
+        status: ExecutionStatus,
+        message: String,
+        finished_at: DateTime<Utc>,
+    ) -> ExecutionResult {
+        ExecutionResult {
+            order_id,
+            bot_id: self.bot_id.clone(),
+            listener_id: self.listener_id.clone(),
+            exchange: exchange.to_string(),
+            account: None,
+            action: self.action.clone(),
+            symbol: self.symbol.clone(),
+            order_size: self.order_size.clone(),
+            status,
+            message,
+            report: None,
+            duplicate: false,
+            error_code: None,
+            warnings: Vec::new(),
+            latency_ms: None,
+            finished_at,
+        }
+    }
+}
+
+/// The order `alert` asks to place, from its `action`, `order_type` and prices.
+fn order_request(alert: &TradingViewAlert) -> Result<OrderRequest, AppError> {
+    let invalid = |message: String| AppError::ValidationError(message);
+    let side = Side::parse(&alert.action)
+        .ok_or_else(|| invalid("action must be 'buy' or 'sell'".to_string()))?;
+    let number = |field: &str, value: &Option<String>| -> Result<Option<Decimal>, AppError> {
+        let Some(value) = value else {
+            return Ok(None);
+        };
+        value
+            .trim()
+            .parse::<Decimal>()
+            .ok()
+            .filter(|number| *number > Decimal::ZERO)
+            .map(Some)
+            .ok_or_else(|| {
+                invalid(format!(
+                    "{} must be a positive number, not '{}'",
+                    field, value
+                ))
+            })
+    };
+    let price = number("price", &alert.price)?;
+    let stop_price = number("stop_price", &alert.stop_price)?;
+    let take_profit_price = number("take_profit_price", &alert.take_profit_price)?;
+    let time_in_force = match &alert.time_in_force {
+        Some(value) => TimeInForce::parse(value).ok_or_else(|| {
+            invalid(format!(
+                "time_in_force must be GTC, IOC or FOK, not '{}'",
+                value
+            ))
+        })?,
+        None => TimeInForce::default(),
+    };
+    let order_type = alert.order_type.as_deref().unwrap_or("market");
+    let required = |field: &str, value: Option<Decimal>| {
+        value.ok_or_else(|| invalid(format!("{} orders need a {}", order_type, field)))
+    };
+    let request = match order_type.to_lowercase().as_str() {
+        "market" => OrderRequest::Market { side },
+        "limit" => OrderRequest::Limit {
+            side,
+            price: required("price", price)?,
+            time_in_force,
+        },
+        "stop_loss" => OrderRequest::StopLoss {
+            side,
+            stop_price: required("stop_price", stop_price)?,
+            limit_price: price,
+            time_in_force,
+        },
+        "take_profit" => OrderRequest::TakeProfit {
+            side,
+            trigger_price: required("take_profit_price", take_profit_price)?,
+            limit_price: price,
+            time_in_force,
+        },
+        "trailing_stop" => {
+            let delta = alert.trailing_delta_bps.as_deref().unwrap_or_default();
+            OrderRequest::TrailingStop {
+                side,
+                trailing_delta_bps: delta
+                    .trim()
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|bps| *bps > 0)
+                    .ok_or_else(|| {
+                        invalid(format!(
+                            "trailing_stop orders need a trailing_delta_bps, not '{}'",
+                            delta
+                        ))
+                    })?,
+                activation_price: stop_price,
+            }
+        }
+        "oco" => OrderRequest::Oco {
+            side,
+            take_profit: required("take_profit_price", take_profit_price)?,
+            stop_price: required("stop_price", stop_price)?,
+            stop_limit_price: price,
+        },
+        other => {
+            return Err(invalid(format!(
+                "order_type must be market, limit, stop_loss, take_profit, trailing_stop or oco, not '{}'",
+                other
+            )))
+        }
+    };
+    Ok(request)
+}
+
+/// What a webhook request presents as proof that it knows its listener's secret.
+#[derive(Debug, Default)]
+pub struct Credentials<'a> {
+    /// `secret` of the URL query
+    pub secret: Option<&'a str>,
+    /// Hex HMAC-SHA256 of the body under the secret, optionally prefixed with `sha256=`
+    pub signature: Option<&'a str>,
+    /// The alert, whose `secret` field may carry the secret too
+    pub body: &'a str,
+}
+
+impl Credentials<'_> {
+    /// Whether the query, the signature or the body's `secret` field proves `secret`.
+    fn prove(&self, secret: &str) -> bool {
+        let matches = |offered: &str| constant_time_eq(secret.as_bytes(), offered.as_bytes());
+        let signed = |signature: &str| {
+            let signature = signature.trim();
+            let signature = signature.strip_prefix("sha256=").unwrap_or(signature);
+            let expected = hmac_sha256_hex(secret.as_bytes(), self.body.as_bytes());
+            constant_time_eq(
+                expected.as_bytes(),
+                signature.to_ascii_lowercase().as_bytes(),
+            )
+        };
+        let in_body = || {
+            let body = serde_json::from_str::<serde_json::Value>(self.body).ok()?;
+            body.get("secret")?.as_str().map(str::to_string)
+        };
+        self.secret.is_some_and(matches)
+            || self.signature.is_some_and(signed)
+            || in_body().is_some_and(|offered| matches(&offered))
+    }
+}
+
+/// Check `credentials` against the listener `listener_id` of `bot_id`.
+///
+/// Listeners without a secret, or with `verify_secret` off, accept any request. A secret
+/// replaced by a rotation is accepted until its grace period ends. Returns the alert source
+/// the listener's service sends, for [`Alert::parse`].
+pub fn authenticate(
+    state: &mut AppState,
+    tenant: Option<&str>,
+    bot_id: &str,
+    listener_id: &str,
+    credentials: &Credentials<'_>,
+) -> Result<String, AppError> {
+    let now = state.providers.clock.now();
+    let scope = state.scoped(tenant);
+    let listener = scope
+        .get_bot_ref(bot_id)?
+        .listeners
+        .get(listener_id)
+        .ok_or_else(|| AppError::ListenerNotFound(listener_id.to_string()))?;
+    if listener.verify_secret && !listener.secret.is_empty() && !credentials.prove(&listener.secret)
+    {
+        let previous = listener
+            .previous_secret
+            .as_ref()
+            .and_then(|previous| previous.valid_at(now));
+        if !previous.is_some_and(|secret| credentials.prove(secret)) {
+            return Err(AppError::Unauthorized(
+                "Invalid webhook secret or signature.".to_string(),
+            ));
+        }
+        log::warn!(
+            "Listener '{}' of bot '{}' received an alert with its old secret; update the alert \
+             before the old secret expires",
+            listener_id,
+            bot_id
+        );
+    }
+    Ok(listener.service.to_lowercase())
+}
+
+/// Refuse `alert` if it fired more than `max_age_secs` before `now`, e.g. because TradingView
+/// delivered it late; acting on an old signal is worse than missing it. Alerts without a
+/// readable timestamp, and any alert if `max_age_secs` is 0, pass.
+pub fn check_fresh(alert: &Alert, now: DateTime<Utc>, max_age_secs: u64) -> Result<(), AppError> {
+    let Some(fired_at) = alert.fired_at().filter(|_| max_age_secs > 0) else {
+        return Ok(());
+    };
+    let age_secs = (now - fired_at).num_seconds();
+    if age_secs > max_age_secs as i64 {
+        return Err(AppError::StaleAlert {
+            age_secs,
+            max_age_secs,
+        });
+    }
+    Ok(())
+}
+
+/// What [`deduplicate`] made of a webhook alert.
+pub enum Delivery {
+    /// Not seen within the window; settle the key with the outcome of [`dispatch`]
+    First(AlertKey),
+    /// A repeat, answered with the outcome of the first delivery
+    Repeat(Box<ExecutionResult>),
+}
+
+/// Recognise `alert`, with request body `body`, as a repeat of one `listener_id` received
+/// less than `window_secs` ago, or claim it as the first delivery. Repeats of an alert still
+/// being executed are answered as ignored.
+pub fn deduplicate(
+    state: &mut AppState,
+    tenant: Option<&str>,
+    listener_id: &str,
+    alert: &Alert,
+    body: &str,
+    window_secs: u64,
+) -> Result<Delivery, AppError> {
+    let now = state.providers.clock.now();
+    let (key, exchange) = {
+        let scope = state.scoped(tenant);
+        let bot = scope.get_bot_ref(alert.bot_id())?;
+        let key = AlertKey {
+            bot_id: bot.bot_id.clone(),
+            listener_id: listener_id.to_string(),
+            hash: sha256_hex(body.as_bytes()),
+        };
+        (key, bot.exchange.clone())
+    };
+    let Some(first) = state.recent_alerts.claim(&key, now, window_secs) else {
+        return Ok(Delivery::First(key));
+    };
+    let result = first.result.unwrap_or_else(|| {
+        let Alert::TradingView(alert) = alert;
+        ExecutionResult {
+            order_id: None,
+            bot_id: alert.bot_id.clone(),
+            listener_id: listener_id.to_string(),
+            exchange,
+            account: None,
+            action: alert.action.to_lowercase(),
+            symbol: Symbol::normalize(&alert.ticker),
+            order_size: alert.order_size.clone(),
+            status: ExecutionStatus::Ignored,
+            message: format!(
+                "the alert received at {} is still being executed",
+                first.received_at.to_rfc3339()
+            ),
+            report: None,
+            duplicate: false,
+            error_code: None,
+            warnings: Vec::new(),
+            latency_ms: None,
+            finished_at: now,
+        }
+    });
+    Ok(Delivery::Repeat(Box::new(ExecutionResult {
+        duplicate: true,
+        ..result
+    })))
+}
+
+/// Outcome of the locked first half of [`dispatch`].
+enum Prepared {
+    /// Nothing to send to an exchange now
+    Done(ExecutionResult),
+    /// Journaled and ready to be sent
+    Ready {
+        bot: Bot,
+        venue: Box<dyn Exchange + Send + Sync>,
+        pending: Box<PendingOrder>,
+    },
+}
+
+/// What the checks of its bot and listener make of an order.
+enum Verdict {
+    /// The order never reaches an exchange
+    Skip {
+        status: ExecutionStatus,
+        error_code: Option<ErrorCode>,
+        message: String,
+    },
+    /// The order goes to `venue`, cut down to the bot's budget if need be
+    Pass {
+        order: Box<Order>,
+        listener: Box<Listener>,
+        venue: Box<dyn Exchange + Send + Sync>,
+    },
+}
+
+/// An order checked against its bot and listener by [`screen`].
+struct Screening {
+    /// Storage key of the bot
+    key: String,
+    exchange: String,
+    verdict: Verdict,
+}
+
+/// Check `order` against the listener, maintenance windows, exclusion group and budget of
+/// the bot it targets, without changing anything.
+fn screen(
+    state: &mut AppState,
+    prices: &PriceCache,
+    tenant: Option<&str>,
+    order: &Order,
+    now: DateTime<Utc>,
+) -> Result<Screening, AppError> {
+    let (key, bot_exchange, venue, listener, observer, group, budget) = {
+        let scope = state.scoped(tenant);
+        let bot = scope.get_bot_ref(&order.bot_id)?;
+        let listener = bot
+            .listeners
+            .get(&order.listener_id)
+            .ok_or_else(|| AppError::ListenerNotFound(order.listener_id.clone()))?;
+        (
+            bot.bot_id.clone(),
+            bot.exchange.clone(),
+            exchange::get_exchange(bot),
+            listener.clone(),
+            bot.observer,
+            bot.exclusion_group.clone(),
+            bot.budget,
+        )
+    };
+    let screening = |verdict| Screening {
+        key: key.clone(),
+        exchange: bot_exchange.clone(),
+        verdict,
+    };
+    let skip = |status, error_code, message| {
+        Ok(screening(Verdict::Skip {
+            status,
+            error_code,
+            message,
+        }))
+    };
+
+    if !listener.enabled {
+        let message = format!("listener '{}' is disabled", order.listener_id);
+        return skip(ExecutionStatus::Ignored, None, message);
+    }
+    let symbols = [
+        order.mapped_from.as_deref().unwrap_or(&order.symbol),
+        order.symbol.as_str(),
+    ];
+    if let Err(rule) = listener
+        .filter
+        .check(&symbols, &order.action, order.alert_price, now)
+    {
+        let message = format!("filtered out: {}", rule);
+        return skip(ExecutionStatus::Ignored, None, message);
+    }
+    if observer {
+        let message = format!("bot '{}' is an observer and places no orders", order.bot_id);
+        return skip(ExecutionStatus::Ignored, None, message);
+    }
+    if let Some(window) = state.maintenance.active(&bot_exchange, now) {
+        let mut message = format!(
+            "{} is down for maintenance until {}",
+            bot_exchange,
+            window.ends_at.to_rfc3339()
+        );
+        if let Some(reason) = &window.reason {
+            message.push_str(&format!(" ({})", reason));
+        }
+        return skip(ExecutionStatus::Ignored, None, message);
+    }
+    let Some(venue) = venue else {
+        let message = format!("no adapter for exchange '{}'", bot_exchange);
+        let code = Some(ErrorCode::ExchangeUnsupported);
+        return skip(ExecutionStatus::Failed, code, message);
+    };
+
+    if let Some(group) = group {
+        if let Some(holder) = position_holder(state, tenant, &order.bot_id, &group, &order.symbol)?
+        {
+            return Err(AppError::PositionHeld {
+                group,
+                holder,
+                symbol: order.symbol.clone(),
+            });
+        }
+    }
+    let order = match budget.and_then(|budget| budget.amount(state.allocation.capital)) {
+        Some(budget) => budgeted(state, &key, budget, order, prices, now)?,
+        None => order.clone(),
+    };
+    Ok(screening(Verdict::Pass {
+        order: Box::new(order),
+        listener: Box::new(listener),
+        venue,
+    }))
+}
+
+/// Resolve the bot and exchange adapter of `order`, then journal the order as submitted, or
+/// as queued if its exchange is down and the order can wait for it.
+fn prepare(
+    state: &mut AppState,
+    outages: &Outages,
+    prices: &PriceCache,
+    tenant: Option<&str>,
+    order: &Order,
+) -> Result<Prepared, AppError> {
+    let now = state.providers.clock.now();
+    let Screening {
+        key,
+        exchange: bot_exchange,
+        verdict,
+    } = screen(state, prices, tenant, order, now)?;
+    let (order, listener, venue) = match verdict {
+        // Alerts that never reach an exchange are only put on the timeline
+        Verdict::Skip {
+            status,
+            error_code,
+            message,
+        } => {
+            let kind = match status {
+                ExecutionStatus::Failed => EventKind::Error,
+                _ => EventKind::Alert,
+            };
+            state.events.record(
+                now,
+                &key,
+                kind,
+                format!("Alert {} ({}): {}", status, order.summary(), message),
+                serde_json::to_value(order).ok(),
+            );
+            state.save::<PathBuf>(None)?;
+            let result = ExecutionResult {
+                error_code,
+                warnings: Vec::new(),
+                ..order.result(&bot_exchange, None, status, message, now)
+            };
+            return Ok(Prepared::Done(result));
+        }
+        Verdict::Pass {
+            order,
+            listener,
+            venue,
+        } => (*order, *listener, venue),
+    };
+    state
+        .rate_limits
+        .check(&key, &order.listener_id, &listener, now)?;
+    let mut scope = state.scoped(tenant);
+    scope.record_trade()?;
+    let bot = scope.get_bot_mut(&order.bot_id)?;
+    if let Some(listener) = bot.listeners.get_mut(&order.listener_id) {
+        listener.last_fired = Some(now);
+    }
+    let bot = bot.clone();
+
+    let order_id = state.providers.ids.next_id();
+    state.journal.enqueue(JournalEntry {
+        id: order_id.clone(),
+        bot_id: key.clone(),
+        recorded_at: now,
+        summary: order.summary(),
+    });
+    state.events.record(
+        now,
+        &key,
+        EventKind::Alert,
+        format!(
+            "Alert from listener '{}': {}",
+            order.listener_id,
+            order.summary()
+        ),
+        serde_json::to_value(&order).ok(),
+    );
+    let pending = PendingOrder {
+        order: order.clone(),
+        tenant: tenant.map(str::to_string),
+        bot_key: key,
+        order_id: order_id.clone(),
+        exchange: bot_exchange.clone(),
+        received_at: now,
+        reference_price: prices.quote(&order.symbol).map(|quote| quote.price),
+    };
+
+    // An exchange known to be down is left alone; the order waits for it in the journal
+    if outages.is_open(&bot_exchange, now) && outages.buffer(pending.clone()) {
+        state.save::<PathBuf>(None)?;
+        let message = format!(
+            "{} is unreachable; the order waits up to {}s for it",
+            bot_exchange,
+            outages.config().buffer_ttl_secs
+        );
+        let result = order.result(
+            &bot_exchange,
+            Some(order_id),
+            ExecutionStatus::Buffered,
+            message,
+            now,
+        );
+        return Ok(Prepared::Done(result));
+    }
+    state.journal.submit(&order_id, now);
+    state.save::<PathBuf>(None)?;
+    Ok(Prepared::Ready {
+        bot,
+        venue,
+        pending: Box::new(pending),
+    })
+}
+
+/// Another bot of the exclusion group `group` than `bot_id` with an open position in
+/// `symbol`, by its [portfolio](crate::bot::portfolio).
+fn position_holder(
+    state: &mut AppState,
+    tenant: Option<&str>,
+    bot_id: &str,
+    group: &str,
+    symbol: &str,
+) -> Result<Option<String>, AppError> {
+    let scope = state.scoped(tenant);
+    for peer_id in scope.bot_ids() {
+        let peer = scope.get_bot_ref(&peer_id)?;
+        if peer_id == bot_id || peer.exclusion_group.as_deref() != Some(group) {
+            continue;
+        }
+        let portfolio = scope.portfolio(&peer_id);
+        if portfolio.is_some_and(|portfolio| portfolio.is_open(symbol)) {
+            return Ok(Some(peer_id));
+        }
+    }
+    Ok(None)
+}
+
+/// `order` cut down to what fits in `budget` beside the open positions of the bot stored as
+/// `key`, by its [portfolio](crate::bot::portfolio).
+fn budgeted(
+    state: &AppState,
+    key: &str,
+    budget: Decimal,
+    order: &Order,
+    prices: &PriceCache,
+    now: DateTime<Utc>,
+) -> Result<Order, AppError> {
+    let empty = Portfolio::default();
+    let portfolio = state.portfolios.get(key).unwrap_or(&empty);
+    // What the order adds to the exposure is only known at a price
+    let price = match order
+        .alert_price
+        .or_else(|| order.request.prices().first().copied())
+    {
+        Some(price) => price,
+        None => Decimal::try_from(prices.fresh(&order.symbol, now)?.price)
+            .map_err(|e| AppError::InternalServerError(format!("Invalid price: {}", e)))?,
+    };
+    let exposure = portfolio.exposure(prices);
+    let quantity = allocation::fit(
+        portfolio.position(&order.symbol),
+        order.side(),
+        order.quantity,
+        price,
+        exposure,
+        budget,
+    );
+    if quantity >= order.quantity {
+        return Ok(order.clone());
+    }
+    let quantity = state.precision.round_quantity(&order.symbol, quantity);
+    if quantity.is_zero() {
+        return Err(AppError::BudgetExhausted {
+            bot_id: order.bot_id.clone(),
+            budget,
+            exposure: exposure.round_dp(2),
+        });
+    }
+    Ok(Order {
+        order_size: quantity.normalize().to_string(),
+        sized_from: Some(order.order_size.clone()),
+        quantity,
+        ..order.clone()
+    })
+}
+
+/// Take the order out of the journal and put its outcome on the bot's timeline.
+fn finish(state: &mut AppState, bot_id: &str, result: &ExecutionResult) {
+    if let Some(order_id) = &result.order_id {
+        state.journal.confirm(order_id);
+    }
+    let kind = match result.status {
+        ExecutionStatus::Executed => EventKind::Trade,
+        _ => EventKind::Error,
+    };
+    state.events.record(
+        result.finished_at,
+        bot_id,
+        kind,
+        format!(
+            "Order {} ({} {} {}): {}",
+            result.status, result.action, result.order_size, result.symbol, result.message
+        ),
+        serde_json::to_value(result).ok(),
+    );
+    if let Err(e) = state.save::<PathBuf>(None) {
+        log::error!(
+            "Failed to save the outcome of order {:?}: {}",
+            result.order_id,
+            e
+        );
+    }
+}
+
+/// [`finish`] `order` and move the bot's portfolio by it, then add it to the bot's trade
+/// history without holding the lock.
+fn conclude(
+    mut state: MutexGuard<'_, AppState>,
+    bot_id: &str,
+    order: &Order,
+    result: ExecutionResult,
+) -> ExecutionResult {
+    let record = TradeRecord {
+        result,
+        order: Some(order.clone()),
+    };
+    state.portfolios.record(bot_id, &record);
+    finish(&mut state, bot_id, &record.result);
+    let history = TradeHistory::for_state_file(&state.state_file);
+    drop(state);
+
+    if let Err(e) = history.append(bot_id, &record) {
+        log::error!(
+            "Failed to record order {:?} in the trade history: {}",
+            record.result.order_id,
+            e
+        );
+    }
+    let result = record.result;
+    match result.status {
+        ExecutionStatus::Failed => log::warn!("{}", result),
+        _ => log::info!("{}", result),
+    }
+    result
+}
+
+/// Fail a buffered order that will not be sent after all.
+fn abandon(
+    app_state: &Mutex<AppState>,
+    pending: PendingOrder,
+    message: String,
+) -> Result<ExecutionResult, AppError> {
+    let mut state = app_state.lock().map_err(|_| AppError::LockError)?;
+    state.journal.discard(&pending.order_id);
+    let result = ExecutionResult {
+        error_code: Some(ErrorCode::ExchangeUnavailable),
+        warnings: Vec::new(),
+        ..pending.order.result(
+            &pending.exchange,
+            Some(pending.order_id.clone()),
+            ExecutionStatus::Failed,
+            message,
+            state.providers.clock.now(),
+        )
+    };
+    Ok(conclude(state, &pending.bot_key, &pending.order, result))
+}
+
+/// Send `pending`, journaled as submitted, to `venue` through the account of `bot` it is
+/// routed to and record the outcome. Orders that cannot reach the exchange are buffered while
+/// there is room for them.
+async fn execute(
+    app_state: &Mutex<AppState>,
+    outages: &Outages,
+    pending: PendingOrder,
+    bot: Bot,
+    venue: Box<dyn Exchange + Send + Sync>,
+    client: &Client,
+) -> Result<ExecutionResult, AppError> {
+    let order = &pending.order;
+    let (bot, account) = bot.routed(order.side(), &order.symbol);
+    let mut elapsed = None;
+    let outcome = match Secrets::default().resolve_bot(&bot) {
+        Ok(resolved) => {
+            let started = Instant::now();
+            let outcome = venue
+                .execute_trade(
+                    &order.request,
+                    &order.symbol,
+                    order.quantity,
+                    order.slippage(),
+                    &resolved,
+                    client,
+                )
+                .await
+                .map_err(|e| {
+                    let code = if exchange::is_unreachable(e.as_ref()) {
+                        ErrorCode::ExchangeUnavailable
+                    } else {
+                        ErrorCode::ExchangeRejected
+                    };
+                    (code, e.to_string())
+                });
+            // Dry runs never reach the exchange
+            elapsed = (!bot.dry_run).then(|| started.elapsed());
+            outcome
+        }
+        Err(e) => Err((ErrorCode::ConfigInvalid, e)),
+    };
+
+    let mut state = app_state.lock().map_err(|_| AppError::LockError)?;
+    let finished_at = state.providers.clock.now();
+    if let Some(elapsed) = elapsed {
+        let error = (outcome.as_ref().err()).map(|(code, e)| (*code, e.as_str()));
+        (state.exchange_stats).record(&pending.bot_key, &bot.exchange, elapsed, error, finished_at);
+    }
+    let (status, message, report, error_code) = match outcome {
+        Ok(report) if bot.dry_run => {
+            let buy = order.request.side().is_buy();
+            let mut message = format!("dry run, nothing was sent: {}", report);
+            if let Some(fill) = PaperFill::from_report(&order.symbol, buy, &report) {
+                let position = state.paper.apply(&bot.bot_id, &fill);
+                message.push_str(&format!(
+                    "; position {} with net PnL {}",
+                    position.quantity,
+                    position.net_pnl()
+                ));
+            }
+            (ExecutionStatus::Executed, message, Some(report), None)
+        }
+        Ok(report) => {
+            if outages.record_success(&bot.exchange) {
+                log::info!("{} is reachable again", bot.exchange);
+            }
+            (
+                ExecutionStatus::Executed,
+                format!("order accepted by the exchange: {}", report),
+                Some(report),
+                None,
+            )
+        }
+        Err((code, e)) => {
+            let unreachable = code == ErrorCode::ExchangeUnavailable;
+            if unreachable && outages.record_failure(&bot.exchange, finished_at) {
+                log::warn!(
+                    "{} is unreachable; holding its alerts for up to {}s",
+                    bot.exchange,
+                    outages.config().buffer_ttl_secs
+                );
+            }
+            if unreachable && outages.buffer(pending.clone()) {
+                state.journal.requeue(&pending.order_id, finished_at);
+                let result = order.result(
+                    &bot.exchange,
+                    Some(pending.order_id.clone()),
+                    ExecutionStatus::Buffered,
+                    format!("{}; waiting for {} to return", e, bot.exchange),
+                    finished_at,
+                );
+                state.events.record(
+                    finished_at,
+                    &bot.bot_id,
+                    EventKind::Error,
+                    format!("Order {} ({}): {}", result.status, order.summary(), e),
+                    serde_json::to_value(&result).ok(),
+                );
+                if let Err(e) = state.save::<PathBuf>(None) {
+                    log::error!("Failed to save buffered order {}: {}", pending.order_id, e);
+                }
+                log::warn!("{}", result);
+                return Ok(result);
+            }
+            (ExecutionStatus::Failed, e, None, Some(code))
+        }
+    };
+    let mut warnings = Vec::new();
+    if status == ExecutionStatus::Executed && bot.trading_fee.is_none() {
+        warnings.push("trading_fee missing; PnL will exclude fees".to_string());
+    }
+    warnings.extend(order.budget_warning());
+    let latency = (elapsed.is_some() && status == ExecutionStatus::Executed).then(|| {
+        (finished_at - pending.received_at)
+            .to_std()
+            .unwrap_or_default()
+    });
+    if let Some(latency) = latency {
+        let slo = state.slo.clone();
+        if let Some(breach) = (state.exchange_stats).record_fill(&pending.bot_key, latency, &slo) {
+            log::warn!("Alert latency objective missed: {}", breach);
+            let notifications = state.notifications.clone();
+            let notification = Notification::new("Alert latency objective missed", &breach);
+            tokio::spawn(async move { notify::send(&notifications, &notification).await });
+        }
+    }
+    let result = ExecutionResult {
+        account,
+        report,
+        error_code,
+        warnings,
+        latency_ms: latency.map(|latency| latency.as_millis() as u64),
+        ..order.result(
+            &bot.exchange,
+            Some(pending.order_id.clone()),
+            status,
+            message,
+            finished_at,
+        )
+    };
+    Ok(conclude(state, &bot.bot_id, order, result))
+}
+
+/// Execute `alert`, received through `listener_id`, on the exchange of the bot it targets.
+///
+/// Errors are returned for alerts that cannot be attributed to a bot or are not allowed to
+/// trade; everything after that, including exchange failures, is an [`ExecutionResult`].
+pub async fn dispatch(
+    app_state: &Mutex<AppState>,
+    outages: &Outages,
+    prices: &PriceCache,
+    tenant: Option<&str>,
+    listener_id: &str,
+    alert: Alert,
+    client: &Client,
+) -> Result<ExecutionResult, AppError> {
+    let order = Order::from_alert(alert, listener_id)?;
+    let prepared = {
+        let mut state = app_state.lock().map_err(|_| AppError::LockError)?;
+        let symbols = state
+            .scoped(tenant)
+            .get_bot_ref(&order.bot_id)
+            .ok()
+            .map(|bot| bot.symbol_map.clone());
+        let now = state.providers.clock.now();
+        let order = order
+            .mapped(symbols.as_ref(), &state.symbol_map)
+            .rounded(&state.precision)?
+            .quoted(prices, now);
+        prepare(&mut state, outages, prices, tenant, &order)?
+    };
+    let result = match prepared {
+        Prepared::Done(result) => {
+            log::info!("{}", result);
+            result
+        }
+        Prepared::Ready {
+            bot,
+            venue,
+            pending,
+        } => execute(app_state, outages, *pending, bot, venue, client).await?,
+    };
+    announce(app_state, tenant, &result);
+    Ok(result)
+}
+
+/// `payload` parsed as an alert of the listener service `source` for the bot `bot_id`.
+pub fn parse_alert(source: &str, payload: &str, bot_id: &str) -> Result<Alert, AppError> {
+    if !alert::SOURCES.contains(&source) {
+        return Err(AppError::InvalidInput(format!(
+            "Listener service '{}' cannot receive alerts; supported: {}",
+            source,
+            alert::SOURCES.join(", ")
+        )));
+    }
+    let alert = Alert::parse_for(source, payload, bot_id)
+        .map_err(|e| AppError::InvalidInput(format!("Invalid {} alert: {}", source, e)))?;
+    if alert.bot_id() != bot_id {
+        return Err(AppError::InvalidInput(format!(
+            "Alert is for bot '{}', not '{}'",
+            alert.bot_id(),
+            bot_id
+        )));
+    }
+    Ok(alert)
+}
+
+/// `payload` parsed as an alert received by `listener_id` of `bot_id`, by the listener's
+/// service.
+pub fn listener_alert(
+    state: &mut AppState,
+    tenant: Option<&str>,
+    (bot_id, listener_id): (&str, &str),
+    payload: &str,
+) -> Result<Alert, AppError> {
+    let source = state
+        .scoped(tenant)
+        .get_listener_ref(bot_id, listener_id)?
+        .service
+        .to_lowercase();
+    parse_alert(&source, payload, bot_id)
+}
+
+/// The order the logged alert `record` asked for, parsed again from its payload by the
+/// listener service `source`, then mapped by the bot's `symbols` or the `global` mappings
+/// and rounded, as when it was dispatched.
+pub(crate) fn recorded_order(
+    record: &AlertRecord,
+    source: &str,
+    symbols: &SymbolMap,
+    global: &SymbolMap,
+    precision: &PrecisionConfig,
+) -> Result<Order, AppError> {
+    let alert = parse_alert(source, &record.payload, &record.bot_id)?;
+    Order::from_alert(alert, &record.listener_id)?
+        .mapped(Some(symbols), global)
+        .rounded(precision)
+}
+
+/// What [`dispatch`] would make of `payload` received by `listener_id` of `bot_id`, without
+/// placing, journaling or counting anything.
+///
+/// Payloads that are no valid alert of the listener's service are errors. Everything after
+/// that, including what the bot's risk limits would refuse, is in the simulation. Like
+/// replays, simulations skip the freshness and duplicate checks.
+pub fn simulate(
+    state: &mut AppState,
+    prices: &PriceCache,
+    tenant: Option<&str>,
+    (bot_id, listener_id): (&str, &str),
+    payload: &str,
+) -> Result<AlertSimulationView, AppError> {
+    let alert = listener_alert(state, tenant, (bot_id, listener_id), payload)?;
+    let order = Order::from_alert(alert, listener_id)?;
+    let symbols = (state.scoped(tenant).get_bot_ref(bot_id)).map(|bot| bot.symbol_map.clone())?;
+    let now = state.providers.clock.now();
+    let order = order
+        .mapped(Some(&symbols), &state.symbol_map)
+        .rounded(&state.precision)?
+        .quoted(prices, now);
+
+    let mut view = AlertSimulationView {
+        bot_id: bot_id.to_string(),
+        listener_id: listener_id.to_string(),
+        exchange: String::new(),
+        order: order.summary(),
+        status: ExecutionStatus::Executed,
+        message: String::new(),
+        error_code: None,
+        warnings: Vec::new(),
+        result: None,
+    };
+    let refuse = |view: AlertSimulationView, e: AppError| AlertSimulationView {
+        status: ExecutionStatus::Failed,
+        message: e.to_string(),
+        error_code: Some(e.code()),
+        ..view
+    };
+    let screening = match screen(state, prices, tenant, &order, now) {
+        Ok(screening) => screening,
+        Err(e) => return Ok(refuse(view, e)),
+    };
+    view.exchange = screening.exchange;
+    let (order, listener) = match screening.verdict {
+        Verdict::Skip {
+            status,
+            error_code,
+            message,
+        } => {
+            return Ok(AlertSimulationView {
+                status,
+                message,
+                error_code,
+                ..view
+            })
+        }
+        Verdict::Pass {
+            order, listener, ..
+        } => (order, listener),
+    };
+
+    // Rate limits and quotas are checked on copies, which count the simulated alert
+    let limit = state.quotas.limits_for(tenant).max_trades_per_day;
+    let checked = (state.rate_limits.clone())
+        .check(&screening.key, listener_id, &listener, now)
+        .and_then(|()| state.trades.clone().record(tenant, now.date_naive(), limit));
+    if let Err(e) = checked {
+        return Ok(refuse(view, e));
+    }
+    Ok(AlertSimulationView {
+        order: order.summary(),
+        message: "nothing was sent".to_string(),
+        warnings: order.budget_warning().into_iter().collect(),
+        ..view
+    })
+}
+
+/// Notify about `result` if its listener, its bot or the configuration ask for its outcome.
+fn announce(app_state: &Mutex<AppState>, tenant: Option<&str>, result: &ExecutionResult) {
+    let Ok(mut state) = app_state.lock() else {
+        return;
+    };
+    let config = state.notifications.clone();
+    let prefs = match state.scoped(tenant).get_bot_ref(&result.bot_id) {
+        Ok(bot) => bot
+            .listeners
+            .get(&result.listener_id)
+            .map(|listener| listener.notifications.clone())
+            .unwrap_or_default()
+            .or(&bot.notifications),
+        Err(_) => Default::default(),
+    }
+    .or(&config.alerts);
+    drop(state);
+
+    let Some(urls) = notify::route(&config, &prefs, result.status) else {
+        return;
+    };
+    let title = format!("Alert {} for bot '{}'", result.status, result.bot_id);
+    let notification = Notification::new(&title, &result.to_string());
+    tokio::spawn(async move { notify::send_to(&urls, &notification).await });
+}
+
+/// Send the buffered orders whose exchange lets them through again, and fail those that
+/// waited longer than `outages.buffer_ttl_secs` or whose price moved too far meanwhile.
+///
+/// Orders whose reference or current price is unknown are only held to the TTL.
+pub async fn replay(
+    app_state: &Mutex<AppState>,
+    outages: &Outages,
+    prices: &PriceCache,
+    client: &Client,
+) -> Result<Vec<ExecutionResult>, AppError> {
+    let now = app_state
+        .lock()
+        .map_err(|_| AppError::LockError)?
+        .providers
+        .clock
+        .now();
+    let (expired, ready) = outages.take_due(now);
+    let mut results = Vec::with_capacity(expired.len() + ready.len());
+    for pending in expired {
+        let message = format!(
+            "{} stayed unreachable for {}s",
+            pending.exchange,
+            outages.config().buffer_ttl_secs
+        );
+        results.push((
+            pending.tenant.clone(),
+            abandon(app_state, pending, message)?,
+        ));
+    }
+
+    for pending in ready {
+        // An earlier order may just have found the exchange still down
+        if outages.is_open(&pending.exchange, now) {
+            outages.restore(pending);
+            continue;
+        }
+        let bot = {
+            let mut state = app_state.lock().map_err(|_| AppError::LockError)?;
+            let scope = state.scoped(pending.tenant.as_deref());
+            scope.get_bot_ref(&pending.order.bot_id).ok().cloned()
+        };
+        let Some(bot) = bot else {
+            let message = format!("bot '{}' no longer exists", pending.order.bot_id);
+            results.push((
+                pending.tenant.clone(),
+                abandon(app_state, pending, message)?,
+            ));
+            continue;
+        };
+        if bot.observer {
+            let message = format!("bot '{}' became an observer", bot.bot_id);
+            results.push((
+                pending.tenant.clone(),
+                abandon(app_state, pending, message)?,
+            ));
+            continue;
+        }
+        let Some(venue) = exchange::get_exchange(&bot) else {
+            let message = format!("no adapter for exchange '{}'", bot.exchange);
+            results.push((
+                pending.tenant.clone(),
+                abandon(app_state, pending, message)?,
+            ));
+            continue;
+        };
+
+        if let Some(reference) = pending.reference_price {
+            let symbol = &pending.order.symbol;
+            if let Ok(quote) = prices.price(symbol, now, venue.as_ref(), client).await {
+                let moved = (quote.price - reference).abs() / reference;
+                if moved > outages.config().price_tolerance {
+                    let message = format!(
+                        "price of {} moved from {} to {} while {} was unreachable",
+                        symbol, reference, quote.price, pending.exchange
+                    );
+                    results.push((
+                        pending.tenant.clone(),
+                        abandon(app_state, pending, message)?,
+                    ));
+                    continue;
+                }
+            }
+        }
+
+        {
+            let mut state = app_state.lock().map_err(|_| AppError::LockError)?;
+            state.journal.submit(&pending.order_id, now);
+            if let Err(e) = state.save::<PathBuf>(None) {
+                log::error!("Failed to save replayed order {}: {}", pending.order_id, e);
+            }
+        }
+        log::info!(
+            "Replaying order {} ({}) on {}",
+            pending.order_id,
+            pending.order.summary(),
+            pending.exchange
+        );
+        let tenant = pending.tenant.clone();
+        let result = execute(app_state, outages, pending, bot, venue, client).await?;
+        results.push((tenant, result));
+    }
+    for (tenant, result) in &results {
+        announce(app_state, tenant.as_deref(), result);
+    }
+    Ok(results.into_iter().map(|(_, result)| result).collect())
+}
+
+/// Fail every buffered order, when the server stops before their exchanges returned.
+pub fn abandon_all(app_state: &Mutex<AppState>, outages: &Outages) {
+    for pending in outages.drain() {
+        let message = format!("server stopped before {} returned", pending.exchange);
+        if let Err(e) = abandon(app_state, pending, message) {
+            log::error!("Failed to record an abandoned order: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app_config::{AppConfig, SymbolPrecision};
+    use crate::bot::state::{BotInsertArgs, BotUpdateArgs, ListenerInsertArgs, ListenerUpdateArgs};
+    use tempfile::tempdir;
+
+    const PAYLOAD: &str = r#"{
+        "bot_id": "alpha",
+        "ticker": "BTCUSDT",
+        "action": "BUY",
+        "order_size": "0.5",
+        "position_size": "1",
+        "schema": "2",
+        "timestamp": "2025-01-10T12:00:00Z"
+    }"#;
+
+    fn prices() -> PriceCache {
+        PriceCache::new(Default::default())
+    }
+
+    fn state_with_bot(dir: &std::path::Path, exchange: &str) -> Mutex<AppState> {
+        let mut config = AppConfig::default();
+        config.api_server.state_file = dir.join("state.json");
+        let mut state = AppState::load(config).unwrap();
+        state
+            .add_bot(
+                BotInsertArgs::new("Alpha".to_string(), exchange.to_string())
+                    .bot_id(Some("alpha".to_string())),
+            )
+            .unwrap();
+        state
+            .add_listener(
+                ListenerInsertArgs::new("alpha", "TradingView")
+                    .listener_id(Some("tv".to_string()))
+                    .secret(Some("s3cret".to_string())),
+            )
+            .unwrap();
+        Mutex::new(state)
+    }
+
+    #[test]
+    fn authenticate_checks_the_listener_secret() {
+        let dir = tempdir().unwrap();
+        let state = state_with_bot(dir.path(), "binance");
+        let mut state = state.lock().unwrap();
+
+        let query = |secret| Credentials {
+            secret: Some(secret),
+            body: PAYLOAD,
+            ..Default::default()
+        };
+        let source = authenticate(&mut state, None, "alpha", "tv", &query("s3cret")).unwrap();
+        assert_eq!(source, "tradingview");
+        assert!(matches!(
+            authenticate(&mut state, None, "alpha", "tv", &query("guess")),
+            Err(AppError::Unauthorized(_))
+        ));
+        assert!(matches!(
+            authenticate(&mut state, None, "alpha", "tv", &Credentials::default()),
+            Err(AppError::Unauthorized(_))
+        ));
+        assert!(matches!(
+            authenticate(&mut state, None, "alpha", "other", &query("s3cret")),
+            Err(AppError::ListenerNotFound(_))
+        ));
+
+        // A signature of the body, or the secret inside it, proves the secret too
+        let signature = hmac_sha256_hex(b"s3cret", PAYLOAD.as_bytes()).to_uppercase();
+        let prefixed = format!("sha256={}", signature);
+        for (body, signature, valid) in [
+            (PAYLOAD, signature.as_str(), true),
+            (PAYLOAD, prefixed.as_str(), true),
+            (&PAYLOAD.replace("0.5", "50"), signature.as_str(), false),
+        ] {
+            let signed = Credentials {
+                signature: Some(signature),
+                body,
+                ..Default::default()
+            };
+            let result = authenticate(&mut state, None, "alpha", "tv", &signed);
+            assert_eq!(result.is_ok(), valid, "{}", signature);
+        }
+        let with_secret = PAYLOAD.replacen('{', r#"{"secret": "s3cret", "#, 1);
+        let in_body = Credentials {
+            body: &with_secret,
+            ..Default::default()
+        };
+        assert!(authenticate(&mut state, None, "alpha", "tv", &in_body).is_ok());
+
+        state
+            .update_listener(ListenerUpdateArgs {
+                verify_secret: Some(false),
+                ..ListenerUpdateArgs::new("alpha", "tv")
+            })
+            .unwrap();
+        assert!(authenticate(&mut state, None, "alpha", "tv", &Credentials::default()).is_ok());
+    }
+
+    #[test]
+    fn rotated_secrets_stay_valid_for_their_grace_period() {
+        use crate::bot::state::provider::{Clock, ManualClock, Providers, SequentialIdGenerator};
+        use crate::bot::state::{ListenerRotateSecretArgs, MAX_SECRET_GRACE_SECS};
+        use std::sync::Arc;
+
+        let dir = tempdir().unwrap();
+        let state = state_with_bot(dir.path(), "binance");
+        let clock = Arc::new(ManualClock::new(Utc::now()));
+        let providers = Providers::new(Arc::new(SequentialIdGenerator::new("id")), clock.clone());
+        let mut state = state.into_inner().unwrap().with_providers(providers);
+        let query = |secret| Credentials {
+            secret: Some(secret),
+            body: PAYLOAD,
+            ..Default::default()
+        };
+
+        let rotation = state
+            .rotate_listener_secret(ListenerRotateSecretArgs {
+                grace_secs: Some(60),
+                ..ListenerRotateSecretArgs::new("alpha", "tv")
+            })
+            .unwrap();
+        assert_eq!(rotation.secret.len(), 64);
+        assert_eq!(
+            rotation.webhook_url,
+            format!("/api/v1/webhook/alpha/tv?secret={}", rotation.secret)
+        );
+        assert_eq!(
+            rotation.previous_valid_until,
+            Some(clock.now() + chrono::Duration::seconds(60))
+        );
+        let new = query(&rotation.secret);
+        assert!(authenticate(&mut state, None, "alpha", "tv", &new).is_ok());
+        assert!(authenticate(&mut state, None, "alpha", "tv", &query("s3cret")).is_ok());
+
+        clock.advance(chrono::Duration::seconds(60));
+        assert!(matches!(
+            authenticate(&mut state, None, "alpha", "tv", &query("s3cret")),
+            Err(AppError::Unauthorized(_))
+        ));
+        assert!(authenticate(&mut state, None, "alpha", "tv", &new).is_ok());
+
+        // Without a grace period the old secret stops working at once
+        let again = state
+            .rotate_listener_secret(ListenerRotateSecretArgs::new("alpha", "tv"))
+            .unwrap();
+        assert_eq!(again.previous_valid_until, None);
+        assert!(authenticate(&mut state, None, "alpha", "tv", &new).is_err());
+        assert!(state
+            .rotate_listener_secret(ListenerRotateSecretArgs {
+                grace_secs: Some(MAX_SECRET_GRACE_SECS + 1),
+                ..ListenerRotateSecretArgs::new("alpha", "tv")
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn old_alerts_are_stale() {
+        let alert = Alert::parse("tradingview", PAYLOAD).unwrap();
+        let fired_at = alert.fired_at().unwrap();
+        let later = |secs| fired_at + chrono::Duration::seconds(secs);
+        assert!(check_fresh(&alert, later(300), 300).is_ok());
+        assert!(matches!(
+            check_fresh(&alert, later(301), 300),
+            Err(AppError::StaleAlert {
+                age_secs: 301,
+                max_age_secs: 300
+            })
+        ));
+        assert!(check_fresh(&alert, later(3600), 0).is_ok());
+
+        let undated = PAYLOAD.replace("2025-01-10T12:00:00Z", "{{timenow}}");
+        let alert = Alert::parse("tradingview", &undated).unwrap();
+        assert!(check_fresh(&alert, later(3600), 300).is_ok());
+    }
+
+    #[test]
+    fn alerts_set_the_slippage_tolerance() {
+        let order = |fields: &str| {
+            let payload = PAYLOAD.replace(
+                r#""schema": "2","#,
+                &format!(r#""schema": "2", {}"#, fields),
+            );
+            Order::from_alert(Alert::parse("tradingview", &payload).unwrap(), "tv")
+        };
+        assert_eq!(order("").unwrap().slippage(), 0.0);
+        assert_eq!(order(r#""slippage_bps": "50","#).unwrap().slippage(), 0.005);
+        for invalid in ["0", "-5", "10000", "lots"] {
+            assert!(matches!(
+                order(&format!(r#""slippage_bps": "{}","#, invalid)),
+                Err(AppError::ValidationError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn alerts_choose_the_order_type() {
+        let request = |fields: &str| {
+            let payload = PAYLOAD.replace(
+                r#""schema": "2","#,
+                &format!(r#""schema": "2", {}"#, fields),
+            );
+            let alert = Alert::parse("tradingview", &payload).unwrap();
+            Order::from_alert(alert, "tv").map(|order| order.request)
+        };
+        assert_eq!(
+            request("").unwrap(),
+            OrderRequest::Market { side: Side::Buy }
+        );
+        assert_eq!(
+            request(r#""order_type": "limit", "price": "100", "time_in_force": "ioc","#).unwrap(),
+            OrderRequest::Limit {
+                side: Side::Buy,
+                price: dec!(100),
+                time_in_force: TimeInForce::Ioc
+            }
+        );
+        assert_eq!(
+            request(r#""order_type": "stop_loss", "stop_price": "95", "price": "94","#).unwrap(),
+            OrderRequest::StopLoss {
+                side: Side::Buy,
+                stop_price: dec!(95),
+                limit_price: Some(dec!(94)),
+                time_in_force: TimeInForce::Gtc
+            }
+        );
+        assert_eq!(
+            request(r#""order_type": "trailing_stop", "trailing_delta_bps": "150","#).unwrap(),
+            OrderRequest::TrailingStop {
+                side: Side::Buy,
+                trailing_delta_bps: 150,
+                activation_price: None
+            }
+        );
+        assert_eq!(
+            request(r#""order_type": "OCO", "take_profit_price": "90", "stop_price": "105","#)
+                .unwrap(),
+            OrderRequest::Oco {
+                side: Side::Buy,
+                take_profit: dec!(90),
+                stop_price: dec!(105),
+                stop_limit_price: None
+            }
+        );
+
+        for invalid in [
+            r#""order_type": "limit","#,
+            r#""order_type": "limit", "price": "-1","#,
+            r#""order_type": "take_profit", "price": "100","#,
+            r#""order_type": "trailing_stop","#,
+            r#""order_type": "iceberg","#,
+            r#""time_in_force": "day","#,
+        ] {
+            assert!(
+                matches!(request(invalid), Err(AppError::ValidationError(_))),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn orders_are_rounded_to_the_symbol_precision() {
+        let payload = PAYLOAD.replace(
+            r#""order_size": "0.5","#,
+            r#""order_size": "0.123456789", "order_type": "limit", "price": "100.125","#,
+        );
+        let order = || Order::from_alert(Alert::parse("tradingview", &payload).unwrap(), "tv");
+        let mut precision = PrecisionConfig::default();
+        let rounded = order().unwrap().rounded(&precision).unwrap();
+        assert_eq!(rounded.quantity, dec!(0.12345678));
+        assert_eq!(rounded.request.prices(), vec![dec!(100.125)]);
+
+        precision.symbols.insert(
+            "btcusdt".to_string(),
+            SymbolPrecision {
+                price_decimals: Some(2),
+                quantity_decimals: Some(3),
+            },
+        );
+        let rounded = order().unwrap().rounded(&precision).unwrap();
+        assert_eq!(rounded.quantity, dec!(0.123));
+        assert_eq!(rounded.request.prices(), vec![dec!(100.12)]);
+
+        precision
+            .symbols
+            .get_mut("btcusdt")
+            .unwrap()
+            .quantity_decimals = Some(0);
+        assert!(matches!(
+            order().unwrap().rounded(&precision),
+            Err(AppError::ValidationError(_))
+        ));
+    }
+
+    #[test]
+    fn bot_symbol_mappings_take_precedence() {
+        let payload = PAYLOAD.replace("BTCUSDT", "BINANCE:BTCUSDTPERP");
+        let order = || Order::from_alert(Alert::parse("tradingview", &payload).unwrap(), "tv");
+        let mut global = SymbolMap::default();
+        assert_eq!(
+            order().unwrap().mapped(None, &global).symbol,
+            "BINANCE:BTCUSDTPERP"
+        );
+
+        global.insert("binance:btcusdtperp", "BTCUSDT.P");
+        let mapped = order().unwrap().mapped(None, &global);
+        assert_eq!(mapped.symbol, "BTCUSDT.P");
+        assert_eq!(mapped.mapped_from.as_deref(), Some("BINANCE:BTCUSDTPERP"));
+
+        let mut symbols = SymbolMap::default();
+        symbols.insert("BINANCE:BTCUSDTPERP", "btc-usdt");
+        let mapped = order().unwrap().mapped(Some(&symbols), &global);
+        assert_eq!(mapped.symbol, "BTCUSDT");
+        assert_eq!(
+            order()
+                .unwrap()
+                .mapped(Some(&SymbolMap::default()), &global)
+                .symbol,
+            "BTCUSDT.P"
+        );
+    }
+
+    #[tokio::test]
+    async fn filtered_alerts_are_ignored() {
+        let dir = tempdir().unwrap();
+        let state = state_with_bot(dir.path(), "binance");
+        state
+            .lock()
+            .unwrap()
+            .update_listener(ListenerUpdateArgs {
+                symbols: Some(vec!["ETHUSDT".to_string()]),
+                ..ListenerUpdateArgs::new("alpha", "tv")
+            })
+            .unwrap();
+        let (outages, prices) = (Outages::default(), prices());
+        let client = Client::new();
+
+        let alert = Alert::parse("tradingview", PAYLOAD).unwrap();
+        let result = dispatch(&state, &outages, &prices, None, "tv", alert, &client)
+            .await
+            .unwrap();
+        assert_eq!(result.status, ExecutionStatus::Ignored);
+        assert_eq!(
+            result.message,
+            "filtered out: symbol BTCUSDT is not in the listener's symbols"
+        );
+        assert!(state.lock().unwrap().bots["alpha"].listeners["tv"]
+            .last_fired
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn listeners_cool_down_after_a_trade() {
+        if !cfg!(feature = "exchange-binance") {
+            return;
+        }
+        let dir = tempdir().unwrap();
+        let state = state_with_bot(dir.path(), "binance");
+        state
+            .lock()
+            .unwrap()
+            .update_listener(ListenerUpdateArgs {
+                cooldown_secs: Some(60),
+                ..ListenerUpdateArgs::new("alpha", "tv")
+            })
+            .unwrap();
+        let (outages, prices) = (Outages::default(), prices());
+        let client = Client::new();
+
+        // Without credentials the order fails at the exchange, but it was a trade
+        let alert = || Alert::parse("tradingview", PAYLOAD).unwrap();
+        let result = dispatch(&state, &outages, &prices, None, "tv", alert(), &client)
+            .await
+            .unwrap();
+        assert_eq!(result.status, ExecutionStatus::Failed);
+
+        let err = dispatch(&state, &outages, &prices, None, "tv", alert(), &client)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            AppError::RateLimited {
+                retry_after_secs: 60,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn alerts_become_journaled_trades() {
+        let dir = tempdir().unwrap();
+        let exchange = if cfg!(feature = "exchange-binance") {
+            "binance"
+        } else {
+            "nowhere"
+        };
+        let state = state_with_bot(dir.path(), exchange);
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/api/v3/order")
+            .match_query(mockito::Matcher::Any)
+            .with_body(r#"{"orderId":28,"orderListId":-1,"executedQty":"0.5"}"#)
+            .create_async()
+            .await;
+        {
+            let mut state = state.lock().unwrap();
+            let bot = state.bots.get_mut("alpha").unwrap();
+            bot.api_key = Some("key".to_string());
+            bot.api_secret = Some("secret".to_string());
+            bot.rest_endpoint = Some(server.url());
+        }
+        let (outages, prices) = (Outages::default(), prices());
+        let client = Client::new();
+
+        let alert = Alert::parse("tradingview", PAYLOAD).unwrap();
+        let result = dispatch(&state, &outages, &prices, None, "tv", alert, &client)
+            .await
+            .unwrap();
+        assert_eq!(result.action, "buy");
+        assert_eq!(result.symbol, "BTCUSDT");
+
+        let state = state.lock().unwrap();
+        assert!(state.journal.is_empty());
+        if cfg!(feature = "exchange-binance") {
+            assert_eq!(result.status, ExecutionStatus::Executed);
+            assert!(result.order_id.is_some());
+            let report = result.report.as_ref().unwrap();
+            assert_eq!(report.order_id.as_deref(), Some("28"));
+            assert!(state.bots["alpha"].listeners["tv"].last_fired.is_some());
+            let history = TradeHistory::for_state_file(&state.state_file);
+            let trades = history.recent("alpha", 10).unwrap();
+            assert_eq!(trades.len(), 1);
+            assert_eq!(trades[0].result, result);
+            let order = trades[0].order.as_ref().unwrap();
+            assert_eq!(
+                (order.listener_id.as_str(), order.quantity),
+                ("tv", dec!(0.5))
+            );
+            let stats = state.exchange_stats.view("alpha", "alpha");
+            assert_eq!(stats.exchanges[0].exchange, "binance");
+            assert_eq!(
+                (stats.exchanges[0].requests, stats.exchanges[0].errors),
+                (1, 0)
+            );
+            assert!(result.latency_ms.is_some());
+            assert_eq!(stats.alert_to_fill.map(|fills| fills.fills), Some(1));
+        } else {
+            assert_eq!(result.status, ExecutionStatus::Failed);
+            assert!(result.message.contains("no adapter"));
+            assert_eq!(result.error_code, Some(ErrorCode::ExchangeUnsupported));
+        }
+    }
+
+    #[tokio::test]
+    async fn observer_bots_place_no_orders() {
+        let dir = tempdir().unwrap();
+        let state = state_with_bot(dir.path(), "binance");
+        state
+            .lock()
+            .unwrap()
+            .update_bot(BotUpdateArgs {
+                observer: Some(true),
+                ..BotUpdateArgs::new("alpha")
+            })
+            .unwrap();
+        let (outages, prices) = (Outages::default(), prices());
+        let client = Client::new();
+
+        let alert = Alert::parse("tradingview", PAYLOAD).unwrap();
+        let result = dispatch(&state, &outages, &prices, None, "tv", alert, &client)
+            .await
+            .unwrap();
+        assert_eq!(result.status, ExecutionStatus::Ignored);
+        assert!(result.message.contains("observer"), "{}", result);
+        assert!(state.lock().unwrap().journal.submitted.is_empty());
+    }
+
+    #[tokio::test]
+    async fn repeated_alerts_get_the_first_outcome() {
+        let dir = tempdir().unwrap();
+        let state = state_with_bot(dir.path(), "binance");
+        state
+            .lock()
+            .unwrap()
+            .update_bot(BotUpdateArgs {
+                observer: Some(true),
+                ..BotUpdateArgs::new("alpha")
+            })
+            .unwrap();
+        let (outages, prices) = (Outages::default(), prices());
+        let client = Client::new();
+        let deliver = |state: &Mutex<AppState>| {
+            let alert = Alert::parse("tradingview", PAYLOAD).unwrap();
+            deduplicate(&mut state.lock().unwrap(), None, "tv", &alert, PAYLOAD, 60).unwrap()
+        };
+
+        let Delivery::First(key) = deliver(&state) else {
+            panic!("the first delivery is a repeat");
+        };
+        let Delivery::Repeat(pending) = deliver(&state) else {
+            panic!("a repeat was not recognised");
+        };
+        assert!(pending.duplicate);
+        assert!(pending.message.contains("still being executed"));
+
+        let alert = Alert::parse("tradingview", PAYLOAD).unwrap();
+        let result = dispatch(&state, &outages, &prices, None, "tv", alert, &client)
+            .await
+            .unwrap();
+        {
+            let mut state = state.lock().unwrap();
+            state.recent_alerts.settle(&key, Some(&result));
+            state.save::<PathBuf>(None).unwrap();
+        }
+
+        // Recent alerts survive a restart
+        let mut config = AppConfig::default();
+        config.api_server.state_file = dir.path().join("state.json");
+        let restarted = Mutex::new(AppState::load(config).unwrap());
+        let Delivery::Repeat(repeat) = deliver(&restarted) else {
+            panic!("a repeat was not recognised after a restart");
+        };
+        assert_eq!(
+            *repeat,
+            ExecutionResult {
+                duplicate: true,
+                ..result
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn disabled_listeners_ignore_alerts() {
+        let dir = tempdir().unwrap();
+        let state = state_with_bot(dir.path(), "binance");
+        state
+            .lock()
+            .unwrap()
+            .update_listener(ListenerUpdateArgs {
+                enabled: Some(false),
+                ..ListenerUpdateArgs::new("alpha", "tv")
+            })
+            .unwrap();
+        let (outages, prices) = (Outages::default(), prices());
+        let client = Client::new();
+
+        let alert = Alert::parse("tradingview", PAYLOAD).unwrap();
+        let result = dispatch(&state, &outages, &prices, None, "tv", alert, &client)
+            .await
+            .unwrap();
+        assert_eq!(result.status, ExecutionStatus::Ignored);
+        assert_eq!(result.order_id, None);
+
+        for invalid in [
+            PAYLOAD.replace("BUY", "HOLD"),
+            PAYLOAD.replace("0.5", "100%"),
+        ] {
+            let alert = Alert::parse("tradingview", &invalid).unwrap();
+            assert!(matches!(
+                dispatch(&state, &outages, &prices, None, "tv", alert, &client).await,
+                Err(AppError::ValidationError(_))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn bots_pause_during_exchange_maintenance() {
+        use crate::app_config::MaintenanceWindow;
+        use chrono::Duration;
+
+        let dir = tempdir().unwrap();
+        let state = state_with_bot(dir.path(), "binance");
+        let now = Utc::now();
+        state
+            .lock()
+            .unwrap()
+            .maintenance
+            .windows
+            .push(MaintenanceWindow {
+                exchange: "binance".to_string(),
+                starts_at: now - Duration::minutes(5),
+                ends_at: now + Duration::minutes(5),
+                reason: Some("wallet upgrade".to_string()),
+            });
+        let (outages, prices) = (Outages::default(), prices());
+        let client = Client::new();
+
+        let alert = Alert::parse("tradingview", PAYLOAD).unwrap();
+        let result = dispatch(&state, &outages, &prices, None, "tv", alert, &client)
+            .await
+            .unwrap();
+        assert_eq!(result.status, ExecutionStatus::Ignored);
+        assert!(result.message.contains("maintenance"), "{}", result.message);
+        assert!(result.message.contains("wallet upgrade"));
+        assert_eq!(result.order_id, None);
+
+        // Alerts go through again once the window is over
+        state.lock().unwrap().maintenance.windows[0].ends_at = now;
+        let alert = Alert::parse("tradingview", PAYLOAD).unwrap();
+        let result = dispatch(&state, &outages, &prices, None, "tv", alert, &client)
+            .await
+            .unwrap();
+        assert_ne!(result.status, ExecutionStatus::Ignored);
+    }
+
+    #[cfg(feature = "exchange-bybit")]
+    #[tokio::test]
+    async fn alerts_wait_for_unreachable_exchanges() {
+        use crate::app_config::OutageConfig;
+        use chrono::Duration;
+
+        let dir = tempdir().unwrap();
+        let mut config = AppConfig::default();
+        config.api_server.state_file = dir.path().join("state.json");
+        let mut state = AppState::load(config).unwrap();
+        state
+            .add_bot(
+                BotInsertArgs::new("Alpha".to_string(), "bybit".to_string())
+                    .bot_id(Some("alpha".to_string()))
+                    .api_key(Some("key".to_string()))
+                    .api_secret(Some("secret".to_string()))
+                    // Nothing listens on port 1
+                    .rest_endpoint(Some("http://127.0.0.1:1".to_string())),
+            )
+            .unwrap();
+        state
+            .add_listener(
+                ListenerInsertArgs::new("alpha", "TradingView").listener_id(Some("tv".to_string())),
+            )
+            .unwrap();
+        let state = Mutex::new(state);
+        let outages = Outages::new(OutageConfig {
+            failure_threshold: 1,
+            retry_after_secs: 0,
+            ..OutageConfig::default()
+        });
+        let prices = prices();
+        let client = Client::new();
+
+        let alert = Alert::parse("tradingview", PAYLOAD).unwrap();
+        let buffered = dispatch(&state, &outages, &prices, None, "tv", alert, &client)
+            .await
+            .unwrap();
+        assert_eq!(buffered.status, ExecutionStatus::Buffered);
+        assert_eq!(outages.len(), 1);
+        assert_eq!(state.lock().unwrap().journal.queued.len(), 1);
+
+        // The exchange is back
+        let mut server = mockito::Server::new_async().await;
+        let accepted = server
+            .mock("POST", "/v5/order/create")
+            .with_body(r#"{"retCode":0,"retMsg":"OK","result":{"orderId":"1"}}"#)
+            .create_async()
+            .await;
+        state
+            .lock()
+            .unwrap()
+            .bots
+            .get_mut("alpha")
+            .unwrap()
+            .rest_endpoint = Some(server.url());
+        let results = replay(&state, &outages, &prices, &client).await.unwrap();
+        accepted.assert_async().await;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].status, ExecutionStatus::Executed);
+        assert_eq!(results[0].order_id, buffered.order_id);
+        let report = results[0].report.as_ref().unwrap();
+        assert_eq!(report.order_id.as_deref(), Some("1"));
+        assert!(outages.is_empty());
+        assert!(state.lock().unwrap().journal.is_empty());
+
+        // Orders that waited too long, or whose price moved too far, are dropped
+        let ttl = Duration::seconds(OutageConfig::default().buffer_ttl_secs as i64);
+        let order = Order::from_alert(Alert::parse("tradingview", PAYLOAD).unwrap(), "tv").unwrap();
+        let now = Utc::now();
+        prices.record("BTCUSDT", 110.0, now);
+        for (id, received_at, reference_price) in
+            [("stale", now - ttl, None), ("moved", now, Some(100.0))]
+        {
+            state.lock().unwrap().journal.enqueue(JournalEntry {
+                id: id.to_string(),
+                bot_id: "alpha".to_string(),
+                recorded_at: received_at,
+                summary: "buy 0.5 BTCUSDT".to_string(),
+            });
+            assert!(outages.buffer(PendingOrder {
+                order: order.clone(),
+                tenant: None,
+                bot_key: "alpha".to_string(),
+                order_id: id.to_string(),
+                exchange: "bybit".to_string(),
+                received_at,
+                reference_price,
+            }));
+        }
+        let results = replay(&state, &outages, &prices, &client).await.unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results
+            .iter()
+            .all(|result| result.status == ExecutionStatus::Failed));
+        assert!(results[0].message.contains("unreachable for 300s"));
+        assert!(results[1].message.contains("moved from 100 to 110"));
+        let state = state.lock().unwrap();
+        assert!(state.journal.is_empty());
+        let history = TradeHistory::for_state_file(&state.state_file);
+        assert_eq!(history.recent("alpha", 10).unwrap().len(), 3);
+    }
+
+    #[cfg(feature = "exchange-binance")]
+    #[tokio::test]
+    async fn trades_are_routed_to_sub_accounts() {
+        use crate::bot::model::{AccountRoute, SubAccount};
+
+        let dir = tempdir().unwrap();
+        let state = state_with_bot(dir.path(), "binance");
+        let mut server = mockito::Server::new_async().await;
+        let mut orders = Vec::new();
+        for key in ["main-key", "shorts-key"] {
+            let mock = server
+                .mock("POST", "/api/v3/order")
+                .match_query(mockito::Matcher::Any)
+                .match_header("X-MBX-APIKEY", key)
+                .with_body(r#"{"orderId":1,"orderListId":-1,"executedQty":"0.5"}"#)
+                .expect(1)
+                .create_async()
+                .await;
+            orders.push(mock);
+        }
+        {
+            let mut state = state.lock().unwrap();
+            let bot = state.bots.get_mut("alpha").unwrap();
+            bot.api_key = Some("main-key".to_string());
+            bot.api_secret = Some("secret".to_string());
+            bot.rest_endpoint = Some(server.url());
+            bot.accounts = vec![SubAccount {
+                name: "shorts".to_string(),
+                api_key: Some("shorts-key".to_string()),
+                api_secret: Some("sub-secret".to_string()),
+                private_key: None,
+                route: AccountRoute {
+                    side: Some(Side::Sell),
+                    symbols: vec!["btc/usdt".to_string()],
+                },
+            }];
+        }
+        let (outages, prices) = (Outages::default(), prices());
+        let client = Client::new();
+
+        let mut accounts = Vec::new();
+        for action in ["SELL", "BUY"] {
+            let payload = PAYLOAD.replace("BUY", action);
+            let alert = Alert::parse("tradingview", &payload).unwrap();
+            let result = dispatch(&state, &outages, &prices, None, "tv", alert, &client)
+                .await
+                .unwrap();
+            assert_eq!(result.status, ExecutionStatus::Executed, "{}", result);
+            accounts.push(result.account);
+        }
+        assert_eq!(accounts, [Some("shorts".to_string()), None]);
+        for mock in orders {
+            mock.assert_async().await;
+        }
+    }
+
+    #[cfg(feature = "exchange-binance")]
+    #[tokio::test]
+    async fn exclusion_groups_take_turns_on_a_symbol() {
+        let dir = tempdir().unwrap();
+        let state = state_with_bot(dir.path(), "binance");
+        let fill = |action: &str| TradeRecord {
+            result: ExecutionResult {
+                order_id: None,
+                bot_id: "beta".to_string(),
+                listener_id: "tv".to_string(),
+                exchange: "binance".to_string(),
+                account: None,
+                action: action.to_string(),
+                symbol: Symbol::normalize("BTCUSDT"),
+                order_size: "1".to_string(),
+                status: ExecutionStatus::Executed,
+                message: "filled".to_string(),
+                report: Some(ExecutionReport {
+                    filled_quantity: Some(dec!(1)),
+                    average_price: Some(dec!(50000)),
+                    ..ExecutionReport::acknowledged(Utc::now())
+                }),
+                duplicate: false,
+                error_code: None,
+                warnings: Vec::new(),
+                latency_ms: None,
+                finished_at: Utc::now(),
+            },
+            order: None,
+        };
+        {
+            let mut state = state.lock().unwrap();
+            let mut update = BotUpdateArgs::new("alpha");
+            update.exclusion_group = Some("spot".to_string());
+            state.update_bot(update).unwrap();
+            let beta = BotInsertArgs {
+                exclusion_group: Some("spot".to_string()),
+                ..BotInsertArgs::new("Beta".to_string(), "binance".to_string())
+                    .bot_id(Some("beta".to_string()))
+            };
+            state.add_bot(beta).unwrap();
+            state.portfolios.record("beta", &fill("buy"));
+        }
+
+        let alert = Alert::parse("tradingview", PAYLOAD).unwrap();
+        let outages = Outages::default();
+        let error = dispatch(
+            &state,
+            &outages,
+            &prices(),
+            None,
+            "tv",
+            alert,
+            &Client::new(),
+        )
+        .await
+        .unwrap_err();
+        assert!(
+            matches!(&error, AppError::PositionHeld { holder, .. } if holder == "beta"),
+            "{}",
+            error
+        );
+        assert_eq!(error.code(), ErrorCode::RiskLimitExceeded);
+
+        let mut state = state.lock().unwrap();
+        let holder = |state: &mut AppState, bot_id| {
+            position_holder(state, None, bot_id, "spot", &Symbol::normalize("BTCUSDT")).unwrap()
+        };
+        // A bot is not kept from its own position
+        assert_eq!(holder(&mut state, "beta"), None);
+        state.portfolios.record("beta", &fill("sell"));
+        assert_eq!(holder(&mut state, "alpha"), None);
+    }
+
+    #[test]
+    fn budgets_cut_orders_down_to_what_fits() {
+        let dir = tempdir().unwrap();
+        let state = state_with_bot(dir.path(), "binance");
+        let mut state = state.lock().unwrap();
+        let buy = || TradeRecord {
+            result: ExecutionResult {
+                order_id: None,
+                bot_id: "alpha".to_string(),
+                listener_id: "tv".to_string(),
+                exchange: "binance".to_string(),
+                account: None,
+                action: "buy".to_string(),
+                symbol: Symbol::normalize("BTCUSDT"),
+                order_size: "0.5".to_string(),
+                status: ExecutionStatus::Executed,
+                message: "filled".to_string(),
+                report: Some(ExecutionReport {
+                    filled_quantity: Some(dec!(0.5)),
+                    average_price: Some(dec!(40000)),
+                    ..ExecutionReport::acknowledged(Utc::now())
+                }),
+                duplicate: false,
+                error_code: None,
+                warnings: Vec::new(),
+                latency_ms: None,
+                finished_at: Utc::now(),
+            },
+            order: None,
+        };
+        state.portfolios.record("alpha", &buy());
+        let order = Order::from_alert(Alert::parse("tradingview", PAYLOAD).unwrap(), "tv").unwrap();
+        let now = Utc::now();
+
+        // Without a price the order's share of the budget is unknown
+        let error = budgeted(&state, "alpha", dec!(30000), &order, &prices(), now).unwrap_err();
+        assert_eq!(error.code(), ErrorCode::StalePrice);
+
+        // 25000 of 30000 is held once the position is marked to 50000: 0.1 more fits
+        let prices = prices();
+        prices.record("BTCUSDT", 50000.0, now);
+        let sized = budgeted(&state, "alpha", dec!(30000), &order, &prices, now).unwrap();
+        assert_eq!(
+            (sized.quantity, sized.order_size.as_str()),
+            (dec!(0.1), "0.1")
+        );
+        assert_eq!(sized.sized_from.as_deref(), Some("0.5"));
+        let roomy = budgeted(&state, "alpha", dec!(60000), &order, &prices, now).unwrap();
+        assert_eq!(roomy, order);
+
+        state.portfolios.record("alpha", &buy());
+        let error = budgeted(&state, "alpha", dec!(30000), &order, &prices, now).unwrap_err();
+        assert!(
+            matches!(error, AppError::BudgetExhausted { .. }),
+            "{}",
+            error
+        );
+        assert_eq!(error.code(), ErrorCode::RiskLimitExceeded);
+
+        // Percentage budgets without a capital to take a share of are reported, not enforced
+        let mut update = BotUpdateArgs::new("alpha");
+        update.budget = Some("20%".parse().unwrap());
+        state.update_bot(update).unwrap();
+        assert!(state
+            .take_warnings()
+            .iter()
+            .any(|w| w.contains("allocation.capital")));
+    }
+
+    #[cfg(feature = "exchange-bybit")]
+    #[tokio::test]
+    async fn dry_run_bots_never_reach_the_exchange() {
+        let dir = tempdir().unwrap();
+        let mut server = mockito::Server::new_async().await;
+        let orders = server
+            .mock("POST", "/v5/order/create")
+            .expect(0)
+            .create_async()
+            .await;
+        let mut config = AppConfig::default();
+        config.api_server.state_file = dir.path().join("state.json");
+        let mut state = AppState::load(config).unwrap();
+        state
+            .add_bot(
+                BotInsertArgs::new("Alpha".to_string(), "bybit".to_string())
+                    .bot_id(Some("alpha".to_string()))
+                    .api_key(Some("key".to_string()))
+                    .api_secret(Some("secret".to_string()))
+                    .rest_endpoint(Some(server.url()))
+                    .dry_run(true),
+            )
+            .unwrap();
+        state
+            .add_listener(
+                ListenerInsertArgs::new("alpha", "TradingView").listener_id(Some("tv".to_string())),
+            )
+            .unwrap();
+        let state = Mutex::new(state);
+
+        // Bybit cannot quote prices, so there is nothing to simulate the fill at
+        let alert = Alert::parse("tradingview", PAYLOAD).unwrap();
+        let result = dispatch(
+            &state,
+            &Outages::default(),
+            &prices(),
+            None,
+            "tv",
+            alert,
+            &Client::new(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.status, ExecutionStatus::Failed);
+        assert!(result.message.contains("cannot quote"));
+        assert_eq!(result.error_code, Some(ErrorCode::ExchangeRejected));
+        orders.assert_async().await;
+        let state = state.lock().unwrap();
+        assert!(state.journal.is_empty());
+        assert!(state.paper.is_empty());
+        assert!(state
+            .exchange_stats
+            .view("alpha", "alpha")
+            .exchanges
+            .is_empty());
+    }
+
+    #[cfg(feature = "exchange-binance")]
+    #[test]
+    fn simulations_change_nothing() {
+        let dir = tempdir().unwrap();
+        let state = state_with_bot(dir.path(), "binance");
+        let mut state = state.lock().unwrap();
+        state
+            .update_listener(ListenerUpdateArgs {
+                max_alerts_per_minute: Some(1),
+                ..ListenerUpdateArgs::new("alpha", "tv")
+            })
+            .unwrap();
+        let events = state.events.clone();
+
+        // Simulated alerts count against no rate limit
+        for _ in 0..2 {
+            let view = simulate(&mut state, &prices(), None, ("alpha", "tv"), PAYLOAD).unwrap();
+            assert_eq!(view.status, ExecutionStatus::Executed, "{}", view);
+            assert_eq!(view.order, "buy 0.5 BTCUSDT");
+            assert!(view.to_string().contains("would be sent"), "{}", view);
+        }
+        assert!(state.journal.is_empty());
+        assert_eq!(state.events, events);
+        assert!(state
+            .get_listener_ref("alpha", "tv")
+            .unwrap()
+            .last_fired
+            .is_none());
+
+        state
+            .update_bot(BotUpdateArgs {
+                observer: Some(true),
+                ..BotUpdateArgs::new("alpha")
+            })
+            .unwrap();
+        let view = simulate(&mut state, &prices(), None, ("alpha", "tv"), PAYLOAD).unwrap();
+        assert_eq!(view.status, ExecutionStatus::Ignored);
+        assert!(view.message.contains("observer"), "{}", view);
+
+        let invalid = PAYLOAD.replace("0.5", "-1");
+        assert!(matches!(
+            simulate(&mut state, &prices(), None, ("alpha", "tv"), &invalid),
+            Err(AppError::ValidationError(_))
+        ));
+        assert!(matches!(
+            simulate(&mut state, &prices(), None, ("alpha", "other"), PAYLOAD),
+            Err(AppError::NotFound(_))
+        ));
+    }
+}
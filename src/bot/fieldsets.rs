@@ -0,0 +1,125 @@
+// src/bot/fieldsets.rs
+//! # Sparse Fieldsets
+//!
+//! Any JSON endpoint of the API answers with only some fields of its views when asked with
+//! `?fields=`, a comma-separated list of field names, so dashboards polling large lists don't
+//! download what they don't show:
+//!
+//! ```text
+//! GET /api/v1/bots?fields=bot_id,name,exchange
+//! {"success":true,"data":[{"bot_id":"alpha","exchange":"binance","name":"Alpha"}],…}
+//! ```
+//!
+//! Fields are picked from what the view serializes to, so every view supports it alike: from
+//! each element of the `data` of lists, and from the `data` itself of single views. Only
+//! top-level fields are picked, and names a view doesn't have are left out, since elements
+//! of a list may differ. The rest of the response, errors and warnings included, is kept.
+use actix_web::body::{self, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderValue, CONTENT_LENGTH, CONTENT_TYPE};
+use actix_web::middleware::Next;
+use actix_web::web;
+use serde::Deserialize;
+use serde_json::Value;
+
+#[derive(Debug, Default, Deserialize)]
+struct FieldsQuery {
+    fields: Option<String>,
+}
+
+/// The fields `?fields=` asks for, if any.
+fn requested(query: &str) -> Option<Vec<String>> {
+    let query = web::Query::<FieldsQuery>::from_query(query).ok()?;
+    let fields: Vec<String> = (query.fields.as_deref()?.split(','))
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+        .map(str::to_string)
+        .collect();
+    (!fields.is_empty()).then_some(fields)
+}
+
+fn pick(value: &mut Value, fields: &[String]) {
+    if let Value::Object(object) = value {
+        object.retain(|name, _| fields.contains(name));
+    }
+}
+
+/// Keep only `fields` of the `data` of `response`, or of each of its elements if a list.
+pub fn select(response: &mut Value, fields: &[String]) {
+    match response.get_mut("data") {
+        Some(Value::Array(items)) => items.iter_mut().for_each(|item| pick(item, fields)),
+        Some(data) => pick(data, fields),
+        None => {}
+    }
+}
+
+/// Middleware answering with the fields asked for with `?fields=` only.
+pub async fn select_fields(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let fields = requested(req.query_string());
+    let response = next.call(req).await?;
+    let is_json = (response.headers().get(CONTENT_TYPE))
+        .is_some_and(|value| value.as_bytes().starts_with(b"application/json"));
+    let fields = match fields {
+        Some(fields) if is_json && response.status().is_success() => fields,
+        _ => return Ok(response.map_into_boxed_body()),
+    };
+    let (request, response) = response.into_parts();
+    let (mut response, content) = response.into_parts();
+    let content = body::to_bytes(content).await.map_err(|e| {
+        let e: Box<dyn std::error::Error> = e.into();
+        actix_web::error::ErrorInternalServerError(format!("Failed to read response: {}", e))
+    })?;
+    let content = match serde_json::from_slice::<Value>(&content) {
+        Ok(mut value) => {
+            select(&mut value, &fields);
+            serde_json::to_vec(&value).unwrap_or_default().into()
+        }
+        Err(_) => content,
+    };
+    response.headers_mut().insert(
+        CONTENT_LENGTH,
+        HeaderValue::from_str(&content.len().to_string()).expect("a number is a header value"),
+    );
+    let response = response.set_body(content).map_into_boxed_body();
+    Ok(ServiceResponse::new(request, response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn only_the_requested_fields_of_views_are_kept() {
+        assert_eq!(requested("page=2"), None);
+        assert_eq!(requested("fields=%20,"), None);
+        let fields = requested("fields=bot_id,%20name,exchange&page=1").unwrap();
+        assert_eq!(fields, ["bot_id", "name", "exchange"]);
+
+        let bot =
+            json!({"bot_id": "alpha", "name": "Alpha", "exchange": "binance", "listeners": {}});
+        let mut list = json!({"success": true, "data": [bot, "not a view"], "error": null});
+        select(&mut list, &fields);
+        assert_eq!(
+            list,
+            json!({
+                "success": true,
+                "data": [{"bot_id": "alpha", "name": "Alpha", "exchange": "binance"}, "not a view"],
+                "error": null
+            })
+        );
+
+        let mut detail = json!({"success": true, "data": bot, "warnings": ["careful"]});
+        select(
+            &mut detail,
+            &["listeners".to_string(), "unknown".to_string()],
+        );
+        assert_eq!(
+            detail,
+            json!({"success": true, "data": {"listeners": {}}, "warnings": ["careful"]})
+        );
+    }
+}
@@ -0,0 +1,209 @@
+// src/bot/filter.rs
+//! # Alert Filters
+//!
+//! Rules a listener holds its alerts to before they reach the exchange: the symbols and
+//! actions it trades, the range of prices it trades at and the hours of the day (UTC) it
+//! trades in. An alert breaking a rule is ignored, with the rule it broke as the message;
+//! a listener without rules passes every alert on.
+use chrono::{DateTime, NaiveTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// A window of the day in UTC, `HH:MM-HH:MM`; it wraps past midnight if it ends before it
+/// starts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TradingHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl TradingHours {
+    pub fn contains(&self, at: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= at && at < self.end
+        } else {
+            at >= self.start || at < self.end
+        }
+    }
+}
+
+impl FromStr for TradingHours {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("trading hours must be HH:MM-HH:MM in UTC, not '{}'", s);
+        let (start, end) = s.trim().split_once('-').ok_or_else(invalid)?;
+        let time = |t: &str| NaiveTime::parse_from_str(t.trim(), "%H:%M").map_err(|_| invalid());
+        Ok(Self {
+            start: time(start)?,
+            end: time(end)?,
+        })
+    }
+}
+
+impl TryFrom<String> for TradingHours {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<TradingHours> for String {
+    fn from(hours: TradingHours) -> Self {
+        hours.to_string()
+    }
+}
+
+impl fmt::Display for TradingHours {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M")
+        )
+    }
+}
+
+/// The rules of a listener; unset rules let everything through.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct AlertFilter {
+    /// Symbols the listener trades, as the alerts or the exchange name them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub symbols: Vec<String>,
+    /// Actions the listener passes on, e.g. `buy`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub actions: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_price: Option<Decimal>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_price: Option<Decimal>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trading_hours: Option<TradingHours>,
+}
+
+impl AlertFilter {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Whether an alert to `action` `symbols` (the symbol the alert named and the one the
+    /// exchange trades) at `price`, arriving `now`, passes; otherwise the rule it breaks.
+    pub fn check(
+        &self,
+        symbols: &[&str],
+        action: &str,
+        price: Option<Decimal>,
+        now: DateTime<Utc>,
+    ) -> Result<(), String> {
+        if !self.symbols.is_empty()
+            && !symbols
+                .iter()
+                .any(|symbol| self.symbols.iter().any(|s| s.eq_ignore_ascii_case(symbol)))
+        {
+            return Err(format!(
+                "symbol {} is not in the listener's symbols",
+                symbols[0]
+            ));
+        }
+        if !self.actions.is_empty() && !self.actions.iter().any(|a| a.eq_ignore_ascii_case(action))
+        {
+            return Err(format!(
+                "action '{}' is not in the listener's actions",
+                action
+            ));
+        }
+        if self.min_price.is_some() || self.max_price.is_some() {
+            let Some(price) = price else {
+                return Err("the alert has no price to check against the price range".to_string());
+            };
+            if let Some(min) = self.min_price.filter(|min| price < *min) {
+                return Err(format!("price {} is below the minimum of {}", price, min));
+            }
+            if let Some(max) = self.max_price.filter(|max| price > *max) {
+                return Err(format!("price {} is above the maximum of {}", price, max));
+            }
+        }
+        if let Some(hours) = self
+            .trading_hours
+            .filter(|hours| !hours.contains(now.time()))
+        {
+            return Err(format!(
+                "{} UTC is outside trading hours {}",
+                now.format("%H:%M"),
+                hours
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for AlertFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut rules = Vec::new();
+        if !self.symbols.is_empty() {
+            rules.push(format!("symbols {}", self.symbols.join(", ")));
+        }
+        if !self.actions.is_empty() {
+            rules.push(format!("actions {}", self.actions.join(", ")));
+        }
+        match (self.min_price, self.max_price) {
+            (Some(min), Some(max)) => rules.push(format!("price {} to {}", min, max)),
+            (Some(min), None) => rules.push(format!("price from {}", min)),
+            (None, Some(max)) => rules.push(format!("price up to {}", max)),
+            (None, None) => {}
+        }
+        if let Some(hours) = self.trading_hours {
+            rules.push(format!("hours {} UTC", hours));
+        }
+        if rules.is_empty() {
+            write!(f, "none")
+        } else {
+            write!(f, "{}", rules.join("; "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use rust_decimal::dec;
+
+    #[test]
+    fn alerts_are_held_to_the_rules() {
+        let noon = Utc.with_ymd_and_hms(2026, 3, 2, 12, 0, 0).unwrap();
+        let filter = AlertFilter {
+            symbols: vec!["BTCUSDT".to_string()],
+            actions: vec!["buy".to_string()],
+            min_price: Some(dec!(20000)),
+            max_price: None,
+            trading_hours: Some("22:00-13:00".parse().unwrap()),
+        };
+        let price = Some(dec!(30000));
+        assert_eq!(filter.check(&["btcusdt"], "BUY", price, noon), Ok(()));
+        assert!(filter.check(&["ETHUSDT"], "buy", price, noon).is_err());
+        assert!(filter
+            .check(&["BTC", "BTCUSDT"], "buy", price, noon)
+            .is_ok());
+        assert!(filter.check(&["BTCUSDT"], "sell", price, noon).is_err());
+        assert!(filter
+            .check(&["BTCUSDT"], "buy", Some(dec!(100)), noon)
+            .is_err());
+        assert!(filter.check(&["BTCUSDT"], "buy", None, noon).is_err());
+        let evening = noon + chrono::Duration::hours(6);
+        assert_eq!(
+            filter.check(&["BTCUSDT"], "buy", price, evening),
+            Err("18:00 UTC is outside trading hours 22:00-13:00".to_string())
+        );
+
+        assert!(AlertFilter::default()
+            .check(&["X"], "sell", None, noon)
+            .is_ok());
+        assert!("9:00".parse::<TradingHours>().is_err());
+    }
+}
@@ -0,0 +1,163 @@
+// src/bot/handoff.rs
+//! # Socket Handoff
+//!
+//! TradingView does not retry webhooks, so an upgrade must never leave the webhook port
+//! closed. Two ways of restarting keep it open:
+//!
+//! - **`SO_REUSEPORT`** (`handoff.reuse_port` or `--reuse-port`): servers bind their ports
+//!   shared, so the new server starts listening next to the old one, which is then stopped
+//!   with `SIGTERM` and finishes the requests it already accepted
+//!   (`handoff.shutdown_timeout_secs`).
+//! - **Inherited sockets**: a supervisor holding the sockets (systemd socket activation,
+//!   `systemfd`) passes them to each new process following the `LISTEN_FDS` protocol, so the
+//!   ports never close at all. The sockets are used in the order API, webhook, Web UI, unless
+//!   `LISTEN_FDNAMES` names them `api`, `webhook` and `web_client`.
+use socket2::{Domain, Protocol, Socket, Type};
+use std::io;
+use std::net::{TcpListener, ToSocketAddrs};
+
+/// First file descriptor passed under the `LISTEN_FDS` protocol.
+const LISTEN_FDS_START: i32 = 3;
+
+/// Names of the servers, in the order they take unnamed inherited sockets.
+const SERVERS: [&str; 3] = ["api", "webhook", "web_client"];
+
+/// Listening sockets the servers bind to.
+#[derive(Debug, Default)]
+pub struct Listeners {
+    inherited: Vec<(String, TcpListener)>,
+    reuse_port: bool,
+}
+
+/// File descriptors passed to process `pid`, with their names, from the values of
+/// `LISTEN_PID`, `LISTEN_FDS` and `LISTEN_FDNAMES`.
+#[cfg_attr(not(unix), allow(dead_code))]
+fn passed_fds(
+    pid: u32,
+    listen_pid: Option<&str>,
+    listen_fds: Option<&str>,
+    names: Option<&str>,
+) -> Vec<(String, i32)> {
+    // Variables meant for another process (our parent, say) are not ours to use
+    if listen_pid.and_then(|listen_pid| listen_pid.parse::<u32>().ok()) != Some(pid) {
+        return Vec::new();
+    }
+    let count = listen_fds.and_then(|count| count.parse().ok()).unwrap_or(0);
+    let names: Vec<&str> = names
+        .map(|names| names.split(':').collect())
+        .unwrap_or_default();
+    (0..count)
+        .map(|i| {
+            let name = names
+                .get(i as usize)
+                .filter(|name| SERVERS.contains(name))
+                .or(SERVERS.get(i as usize))
+                .unwrap_or(&"unknown");
+            (name.to_string(), LISTEN_FDS_START + i)
+        })
+        .collect()
+}
+
+impl Listeners {
+    /// Take over the sockets passed to this process, if any.
+    pub fn from_env(reuse_port: bool) -> Self {
+        #[cfg(unix)]
+        let inherited = {
+            use std::os::fd::FromRawFd;
+
+            let var = |name| std::env::var(name).ok();
+            passed_fds(
+                std::process::id(),
+                var("LISTEN_PID").as_deref(),
+                var("LISTEN_FDS").as_deref(),
+                var("LISTEN_FDNAMES").as_deref(),
+            )
+            .into_iter()
+            // SAFETY: the LISTEN_FDS protocol hands these descriptors to this process
+            .map(|(name, fd)| (name, unsafe { TcpListener::from_raw_fd(fd) }))
+            .collect()
+        };
+        #[cfg(not(unix))]
+        let inherited = Vec::new();
+
+        #[cfg(not(unix))]
+        if reuse_port {
+            log::warn!("SO_REUSEPORT is only supported on Unix; binding the ports exclusively.");
+        }
+        Self {
+            inherited,
+            reuse_port,
+        }
+    }
+
+    /// Listener of `server`: its inherited socket if there is one, otherwise a new socket
+    /// bound to `address`:`port`.
+    pub fn take(&mut self, server: &str, address: &str, port: u16) -> io::Result<TcpListener> {
+        if let Some(index) = self.inherited.iter().position(|(name, _)| name == server) {
+            let (_, listener) = self.inherited.remove(index);
+            log::info!(
+                "Serving {} on inherited socket {}",
+                server,
+                listener.local_addr()?
+            );
+            listener.set_nonblocking(true)?;
+            return Ok(listener);
+        }
+        bind(address, port, self.reuse_port)
+    }
+}
+
+/// A listening socket bound to `address`:`port`, shared with other processes if
+/// `reuse_port` is set.
+pub fn bind(address: &str, port: u16, reuse_port: bool) -> io::Result<TcpListener> {
+    let address = (address, port).to_socket_addrs()?.next().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::AddrNotAvailable,
+            format!("{}:{} does not resolve", address, port),
+        )
+    })?;
+    let socket = Socket::new(
+        Domain::for_address(address),
+        Type::STREAM,
+        Some(Protocol::TCP),
+    )?;
+    socket.set_reuse_address(true)?;
+    #[cfg(unix)]
+    socket.set_reuse_port(reuse_port)?;
+    #[cfg(not(unix))]
+    let _ = reuse_port;
+    socket.bind(&address.into())?;
+    socket.listen(1024)?;
+    socket.set_nonblocking(true)?;
+    Ok(socket.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passed_sockets_are_matched_to_servers() {
+        let fds = |pid, names| passed_fds(42, Some(pid), Some("2"), names);
+        assert_eq!(
+            fds("42", None),
+            [("api".to_string(), 3), ("webhook".to_string(), 4)]
+        );
+        assert_eq!(
+            fds("42", Some("webhook:api")),
+            [("webhook".to_string(), 3), ("api".to_string(), 4)]
+        );
+        assert!(fds("7", None).is_empty());
+        assert!(passed_fds(42, None, Some("2"), None).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reused_ports_can_be_bound_twice() {
+        let first = bind("127.0.0.1", 0, true).unwrap();
+        let port = first.local_addr().unwrap().port();
+        let second = bind("127.0.0.1", port, true).unwrap();
+        assert_eq!(second.local_addr().unwrap().port(), port);
+        assert!(bind("127.0.0.1", port, false).is_err());
+    }
+}
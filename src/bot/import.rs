@@ -0,0 +1,239 @@
+// src/bot/import.rs
+//! # Fill Import
+//!
+//! Exchanges export the fills of an account as CSV. [`parse_fills`] turns such an export into
+//! [trade records](TradeRecord) for the bot's [trade history](crate::bot::state::history), so
+//! that PnL and reports also cover trades made before xtrade was installed.
+//!
+//! Columns are found by their name in the header row, in any case and ignoring anything but
+//! letters and digits, so `Date(UTC)` is `dateutc`:
+//!
+//! | Field    | Column names, first present wins               | Required |
+//! |----------|------------------------------------------------|----------|
+//! | time     | `time`, `timestamp`, `date`, `dateutc`, `datetime` | yes  |
+//! | symbol   | `symbol`, `pair`, `market`                     | yes      |
+//! | side     | `side`                                         | yes      |
+//! | quantity | `quantity`, `qty`, `executed`, `filled`, `size`, `amount` | yes |
+//! | price    | `price`, `avgprice`, `averageprice`            | yes      |
+//! | fee      | `fee`, `fees`, `commission`                    | no       |
+//! | trade ID | `tradeid`, `id`, `orderid`                     | no       |
+//!
+//! Times are RFC 3339, `YYYY-MM-DD HH:MM:SS` in UTC, or Unix seconds or milliseconds. Numbers
+//! may carry their asset, as in `0.5BTC`. Fees are taken to be in the quote asset.
+use crate::bot::dispatch::{ExecutionResult, ExecutionStatus};
+use crate::bot::state::history::TradeRecord;
+use crate::exchange::{ExecutionReport, Side, Symbol};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use rust_decimal::Decimal;
+use std::str::FromStr;
+
+/// Listener ID of imported trades, which no alert caused.
+pub const IMPORT_LISTENER: &str = "import";
+
+const TIME: [&str; 5] = ["time", "timestamp", "date", "dateutc", "datetime"];
+const SYMBOL: [&str; 3] = ["symbol", "pair", "market"];
+const SIDE: [&str; 1] = ["side"];
+const QUANTITY: [&str; 6] = ["quantity", "qty", "executed", "filled", "size", "amount"];
+const PRICE: [&str; 3] = ["price", "avgprice", "averageprice"];
+const FEE: [&str; 3] = ["fee", "fees", "commission"];
+const TRADE_ID: [&str; 3] = ["tradeid", "id", "orderid"];
+
+/// Positions of the columns of each field in a header row.
+struct Columns {
+    time: usize,
+    symbol: usize,
+    side: usize,
+    quantity: usize,
+    price: usize,
+    fee: Option<usize>,
+    trade_id: Option<usize>,
+}
+
+impl Columns {
+    fn find(header: &csv::StringRecord) -> Result<Self, String> {
+        let names: Vec<String> = header
+            .iter()
+            .map(|name| {
+                name.chars()
+                    .filter(char::is_ascii_alphanumeric)
+                    .collect::<String>()
+                    .to_ascii_lowercase()
+            })
+            .collect();
+        let optional = |aliases: &[&str]| {
+            aliases
+                .iter()
+                .find_map(|alias| names.iter().position(|name| name == alias))
+        };
+        let required = |aliases: &[&str]| {
+            optional(aliases).ok_or_else(|| format!("no {} column", aliases.join("/")))
+        };
+        Ok(Self {
+            time: required(&TIME)?,
+            symbol: required(&SYMBOL)?,
+            side: required(&SIDE)?,
+            quantity: required(&QUANTITY)?,
+            price: required(&PRICE)?,
+            fee: optional(&FEE),
+            trade_id: optional(&TRADE_ID),
+        })
+    }
+}
+
+/// The executed trades of `csv`, exported by `exchange`, as trades of `bot_id`.
+///
+/// Fails on the first row that cannot be read, naming its line.
+pub fn parse_fills(bot_id: &str, exchange: &str, csv: &str) -> Result<Vec<TradeRecord>, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(csv.as_bytes());
+    let columns = Columns::find(reader.headers().map_err(|e| e.to_string())?)?;
+
+    let mut trades = Vec::new();
+    for row in reader.records() {
+        let row = row.map_err(|e| e.to_string())?;
+        let line = row.position().map_or(0, |position| position.line());
+        let field = |column: usize| row.get(column).unwrap_or_default();
+        let trade =
+            fill(bot_id, exchange, &columns, field).map_err(|e| format!("line {}: {}", line, e))?;
+        trades.push(trade);
+    }
+    Ok(trades)
+}
+
+fn fill<'a>(
+    bot_id: &str,
+    exchange: &str,
+    columns: &Columns,
+    field: impl Fn(usize) -> &'a str,
+) -> Result<TradeRecord, String> {
+    let finished_at = timestamp(field(columns.time))?;
+    let side = Side::parse(field(columns.side))
+        .ok_or_else(|| format!("side '{}' is neither buy nor sell", field(columns.side)))?;
+    let symbol = field(columns.symbol);
+    if symbol.is_empty() {
+        return Err("symbol is missing".to_string());
+    }
+    let quantity = decimal(field(columns.quantity))?;
+    let fee = columns
+        .fee
+        .map(&field)
+        .filter(|fee| !fee.is_empty())
+        .map(decimal)
+        .transpose()?;
+    let trade_id = columns
+        .trade_id
+        .map(&field)
+        .filter(|id| !id.is_empty())
+        .map(str::to_string);
+
+    let result = ExecutionResult {
+        order_id: None,
+        bot_id: bot_id.to_string(),
+        listener_id: IMPORT_LISTENER.to_string(),
+        exchange: exchange.to_string(),
+        account: None,
+        action: side.to_string(),
+        symbol: Symbol::normalize(symbol),
+        order_size: quantity.to_string(),
+        status: ExecutionStatus::Executed,
+        message: "imported fill".to_string(),
+        report: Some(ExecutionReport {
+            order_id: trade_id,
+            filled_quantity: Some(quantity),
+            average_price: Some(decimal(field(columns.price))?),
+            fees: fee,
+            tx_hashes: Vec::new(),
+            submitted_at: finished_at,
+            acknowledged_at: finished_at,
+        }),
+        duplicate: false,
+        error_code: None,
+        warnings: Vec::new(),
+        latency_ms: None,
+        finished_at,
+    };
+    Ok(TradeRecord {
+        result,
+        order: None,
+    })
+}
+
+/// `value` without a trailing asset code, as in `0.5BTC`.
+fn decimal(value: &str) -> Result<Decimal, String> {
+    let number = value.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    Decimal::from_str(number)
+        .or_else(|_| Decimal::from_scientific(number))
+        .map(|number| number.abs())
+        .map_err(|_| format!("'{}' is not a number", value))
+}
+
+fn timestamp(value: &str) -> Result<DateTime<Utc>, String> {
+    let invalid = || format!("'{}' is not a time", value);
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    if let Ok(time) = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f") {
+        return Ok(time.and_utc());
+    }
+    let epoch = value.parse::<i64>().map_err(|_| invalid())?;
+    // Seconds would only reach 10^12 in the year 33658
+    let time = if epoch.abs() >= 1_000_000_000_000 {
+        DateTime::from_timestamp_millis(epoch)
+    } else {
+        DateTime::from_timestamp(epoch, 0)
+    };
+    time.ok_or_else(invalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::dec;
+
+    #[test]
+    fn exports_are_read_by_column_name() {
+        let binance = "Date(UTC),Pair,Side,Price,Executed,Amount,Fee\n\
+                       2023-05-01 10:00:00,BTCUSDT,BUY,29000,0.5BTC,14500USDT,14.5USDT\n\
+                       2023-05-02 11:30:00,BTCUSDT,SELL,30000,0.2BTC,6000USDT,6USDT\n";
+        let trades = parse_fills("alpha", "binance", binance).unwrap();
+        assert_eq!(trades.len(), 2);
+        let first = &trades[0].result;
+        let report = first.report.as_ref().unwrap();
+        assert_eq!(
+            (first.action.as_str(), first.symbol.as_str()),
+            ("buy", "BTCUSDT")
+        );
+        assert_eq!(first.listener_id, IMPORT_LISTENER);
+        assert_eq!(report.filled_quantity, Some(dec!(0.5)));
+        assert_eq!(report.average_price, Some(dec!(29000)));
+        assert_eq!(report.fees, Some(dec!(14.5)));
+        assert_eq!(first.finished_at.to_rfc3339(), "2023-05-01T10:00:00+00:00");
+
+        let generic = "symbol,side,qty,price,time,trade_id\n\
+                       eth/usdt,sell,1.25,1800.5,1682935200000,t-1\n";
+        let trade = &parse_fills("alpha", "bybit", generic).unwrap()[0].result;
+        assert_eq!(trade.symbol, "ETHUSDT");
+        assert_eq!(
+            trade.report.as_ref().unwrap().order_id.as_deref(),
+            Some("t-1")
+        );
+        assert_eq!(trade.report.as_ref().unwrap().fees, None);
+        assert_eq!(trade.finished_at.to_rfc3339(), "2023-05-01T10:00:00+00:00");
+    }
+
+    #[test]
+    fn bad_rows_name_their_line() {
+        let missing = "time,symbol,side,price\n2023-05-01T10:00:00Z,BTCUSDT,buy,1\n";
+        assert!(parse_fills("alpha", "binance", missing)
+            .unwrap_err()
+            .contains("quantity"));
+
+        let bad_side = "time,symbol,side,quantity,price\n\
+                        2023-05-01T10:00:00Z,BTCUSDT,buy,1,100\n\
+                        2023-05-01T11:00:00Z,BTCUSDT,hold,1,100\n";
+        let err = parse_fills("alpha", "binance", bad_side).unwrap_err();
+        assert!(err.starts_with("line 3:"), "{}", err);
+    }
+}
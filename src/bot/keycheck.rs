@@ -0,0 +1,131 @@
+// src/bot/keycheck.rs
+//! # API Key Checks
+//!
+//! A key allowed to withdraw turns a compromised server into lost funds, and one not allowed
+//! to trade leaves its bot failing every order. With `key_check.on_create`, the exchange of
+//! each bot created with credentials is asked what its key may do. The answer is kept as the
+//! bot's `key_permissions`; what is wrong with it comes back as warnings, or refuses the bot
+//! with `key_check.refuse`.
+//!
+//! Keys that cannot be checked, because the venue cannot tell or did not answer in time, are
+//! accepted, the latter with a warning.
+use crate::app_config::KeyCheckConfig;
+use crate::bot::model::Bot;
+use crate::bot::secrets::Secrets;
+use crate::errors::AppError;
+use crate::exchange::{self, KeyPermissions};
+use std::time::Duration;
+
+/// How long the exchange has to answer.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Outcome of checking the key of a new bot.
+#[derive(Debug, Default)]
+pub struct KeyCheck {
+    pub permissions: Option<KeyPermissions>,
+    pub warnings: Vec<String>,
+}
+
+/// Ask the exchange of `bot` what its API key may do.
+///
+/// Fails with [`AppError::UnsafeApiKey`] if the key has problems and `config.refuse` is set.
+pub async fn check(
+    bot: &Bot,
+    config: &KeyCheckConfig,
+    client: &reqwest::Client,
+) -> Result<KeyCheck, AppError> {
+    if !config.on_create || bot.dry_run || bot.api_key.is_none() {
+        return Ok(KeyCheck::default());
+    }
+    let permissions = match tokio::time::timeout(CHECK_TIMEOUT, fetch(bot, client)).await {
+        Ok(Ok(Some(permissions))) => permissions,
+        Ok(Ok(None)) => return Ok(KeyCheck::default()),
+        Ok(Err(e)) => return Ok(unchecked(e)),
+        Err(_) => return Ok(unchecked(format!("no answer within {:?}", CHECK_TIMEOUT))),
+    };
+    let problems = problems(bot, &permissions);
+    if config.refuse && !problems.is_empty() {
+        return Err(AppError::UnsafeApiKey { problems });
+    }
+    Ok(KeyCheck {
+        permissions: Some(permissions),
+        warnings: problems,
+    })
+}
+
+async fn fetch(bot: &Bot, client: &reqwest::Client) -> Result<Option<KeyPermissions>, String> {
+    let Some(venue) = exchange::get_exchange(bot) else {
+        return Ok(None);
+    };
+    let bot = Secrets::default().resolve_bot(bot)?;
+    venue
+        .key_permissions(&bot, client)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+fn unchecked(reason: String) -> KeyCheck {
+    KeyCheck {
+        permissions: None,
+        warnings: vec![format!(
+            "the API key's permissions could not be checked: {}",
+            reason
+        )],
+    }
+}
+
+/// What is wrong with a key allowed `permissions` for `bot`.
+pub fn problems(bot: &Bot, permissions: &KeyPermissions) -> Vec<String> {
+    let mut problems = Vec::new();
+    if permissions.can_withdraw {
+        problems.push("the API key may withdraw funds; disable withdrawals for it".to_string());
+    }
+    if !permissions.can_trade && !bot.observer {
+        problems.push("the API key may not trade; orders will fail".to_string());
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    #[tokio::test]
+    async fn keys_that_may_withdraw_or_not_trade_are_flagged() {
+        let permissions = |can_trade, can_withdraw| KeyPermissions {
+            can_read: true,
+            can_trade,
+            can_withdraw,
+            ip_restricted: None,
+            checked_at: Utc::now(),
+        };
+        let trader = Bot::default();
+        let observer = Bot {
+            observer: true,
+            ..Default::default()
+        };
+        assert!(problems(&trader, &permissions(true, false)).is_empty());
+        assert_eq!(problems(&trader, &permissions(false, true)).len(), 2);
+        assert!(problems(&observer, &permissions(false, false)).is_empty());
+
+        // Bots without a key, or on venues that cannot tell, are not checked
+        let config = KeyCheckConfig {
+            refuse: true,
+            ..Default::default()
+        };
+        let client = reqwest::Client::new();
+        let check = check(&trader, &config, &client).await.unwrap();
+        assert!(check.permissions.is_none() && check.warnings.is_empty());
+        let unknown = Bot {
+            exchange: "nowhere".to_string(),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        };
+        assert!(super::check(&unknown, &config, &client)
+            .await
+            .unwrap()
+            .warnings
+            .is_empty());
+    }
+}
@@ -0,0 +1,299 @@
+// src/bot/latency.rs
+//! # Exchange Latency
+//!
+//! Every order the execution pipeline sends to an exchange is timed, and counted as an error
+//! if the exchange could not be reached or rejected it. The [`ExchangeStats`] of the state
+//! keep both per bot and exchange, in memory since the server started: `GET /metrics` serves
+//! them as a latency histogram and error counters in builds with the `metrics` feature, and
+//! `GET /bots/{bot_id}/stats` sums them up for one bot, so exchanges slowing down or failing
+//! intermittently are visible before the bot's trades start failing. Orders of dry-run bots reach no exchange and are not
+//! counted.
+//!
+//! Executed orders are also timed from the receipt of their alert, waiting for the price, in
+//! an outage buffer or for the state lock included, to the exchange's acknowledgment. That
+//! latency is kept with the trade, counted per bot, and held to the [objective](SloConfig) of
+//! `[slo]`.
+use crate::app_config::SloConfig;
+#[cfg(feature = "metrics")]
+use crate::bot::metrics;
+use crate::bot::metrics::Histogram;
+use crate::bot::state::{BotStatsView, ExchangeRequestStats, FillLatencyStats};
+use crate::errors::ErrorCode;
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, VecDeque};
+use std::time::Duration;
+
+#[cfg(feature = "metrics")]
+const DURATION_METRIC: &str = "xtrade_exchange_request_duration_seconds";
+#[cfg(feature = "metrics")]
+const ERRORS_METRIC: &str = "xtrade_exchange_errors_total";
+#[cfg(feature = "metrics")]
+const FILL_METRIC: &str = "xtrade_alert_to_fill_seconds";
+#[cfg(feature = "metrics")]
+const BREACHES_METRIC: &str = "xtrade_slo_breaches_total";
+
+/// Requests of one bot to one exchange.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RequestStats {
+    pub latency: Histogram,
+    /// Requests that failed, by error code
+    pub errors: BTreeMap<&'static str, u64>,
+    pub last_error: Option<(DateTime<Utc>, String)>,
+}
+
+/// Requests to exchanges, by state key of the bot and exchange, and how long alerts took to be
+/// filled.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ExchangeStats {
+    requests: BTreeMap<(String, String), RequestStats>,
+    /// Alert-to-fill latencies, by state key of the bot
+    fills: BTreeMap<String, Histogram>,
+    /// Latest alert-to-fill latencies of all bots, in seconds, for the objective
+    recent: VecDeque<f64>,
+    /// Whether the objective is missed, since it was last met
+    breached: bool,
+    breaches: u64,
+}
+
+impl ExchangeStats {
+    /// Count a request of the bot `bot_key` to `exchange` that took `elapsed`, and failed
+    /// with `error` at `at` if it did.
+    pub fn record(
+        &mut self,
+        bot_key: &str,
+        exchange: &str,
+        elapsed: Duration,
+        error: Option<(ErrorCode, &str)>,
+        at: DateTime<Utc>,
+    ) {
+        let stats = (self.requests)
+            .entry((bot_key.to_string(), exchange.to_string()))
+            .or_default();
+        stats.latency.observe(elapsed.as_secs_f64());
+        if let Some((code, message)) = error {
+            *stats.errors.entry(code.as_str()).or_default() += 1;
+            stats.last_error = Some((at, message.to_string()));
+        }
+    }
+
+    /// Count an order of the bot `bot_key` filled `latency` after its alert was received.
+    ///
+    /// Returns what to tell operators if `slo` is missed now but was not before.
+    pub fn record_fill(
+        &mut self,
+        bot_key: &str,
+        latency: Duration,
+        slo: &SloConfig,
+    ) -> Option<String> {
+        let seconds = latency.as_secs_f64();
+        self.fills
+            .entry(bot_key.to_string())
+            .or_default()
+            .observe(seconds);
+        let target = slo.alert_to_fill_ms?;
+        self.recent.push_back(seconds);
+        while self.recent.len() > slo.window {
+            self.recent.pop_front();
+        }
+        if self.recent.len() < slo.window {
+            return None;
+        }
+        let mut sorted: Vec<f64> = self.recent.iter().copied().collect();
+        sorted.sort_by(f64::total_cmp);
+        let rank = (slo.percentile / 100.0 * sorted.len() as f64)
+            .ceil()
+            .max(1.0) as usize;
+        let observed_ms = sorted[rank.min(sorted.len()) - 1] * 1000.0;
+        let missed = observed_ms > target as f64;
+        if missed == self.breached {
+            return None;
+        }
+        self.breached = missed;
+        if !missed {
+            log::info!(
+                "Alert latency objective met again: p{} of the latest {} fills is {:.0}ms",
+                slo.percentile,
+                slo.window,
+                observed_ms
+            );
+            return None;
+        }
+        self.breaches += 1;
+        Some(format!(
+            "p{} of the latest {} alert-to-fill latencies is {:.0}ms, over the objective of \
+             {}ms; consider the outage and concurrency settings",
+            slo.percentile, slo.window, observed_ms, target
+        ))
+    }
+
+    /// Requests of the bot `bot_key`, known to its tenant as `bot_id`.
+    pub fn view(&self, bot_id: &str, bot_key: &str) -> BotStatsView {
+        let exchanges = (self.requests.iter())
+            .filter(|((key, _), _)| key == bot_key)
+            .map(|((_, exchange), stats)| summarize(exchange, stats))
+            .collect();
+        let millis = |seconds: Option<f64>| seconds.map(|seconds| seconds * 1000.0);
+        let alert_to_fill = self.fills.get(bot_key).map(|fills| FillLatencyStats {
+            fills: fills.count,
+            mean_ms: millis(fills.mean()),
+            p50_ms: millis(fills.quantile(0.5)),
+            p95_ms: millis(fills.quantile(0.95)),
+            p99_ms: millis(fills.quantile(0.99)),
+            max_ms: fills.max * 1000.0,
+        });
+        BotStatsView {
+            bot_id: bot_id.to_string(),
+            exchanges,
+            alert_to_fill,
+        }
+    }
+
+    /// The latency histograms and error counters in the Prometheus text format.
+    #[cfg(feature = "metrics")]
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        if !self.fills.is_empty() {
+            out.push_str(&metrics::describe(FILL_METRIC));
+        }
+        for (bot, fills) in &self.fills {
+            out.push_str(&fills.render(FILL_METRIC, &[("bot", bot.as_str())]));
+        }
+        if self.breaches > 0 {
+            out.push_str(&metrics::describe(BREACHES_METRIC));
+            out.push_str(&format!("{} {}\n", BREACHES_METRIC, self.breaches));
+        }
+        if self.requests.is_empty() {
+            return out;
+        }
+        out.push_str(&metrics::describe(DURATION_METRIC));
+        for ((bot, exchange), stats) in &self.requests {
+            let labels = [("bot", bot.as_str()), ("exchange", exchange.as_str())];
+            out.push_str(&stats.latency.render(DURATION_METRIC, &labels));
+        }
+        let mut errors = (self.requests.iter())
+            .flat_map(|((bot, exchange), stats)| {
+                (stats.errors.iter()).map(move |(code, count)| (bot, exchange, code, count))
+            })
+            .peekable();
+        if errors.peek().is_some() {
+            out.push_str(&metrics::describe(ERRORS_METRIC));
+        }
+        for (bot, exchange, code, count) in errors {
+            out.push_str(&format!(
+                "{}{{bot=\"{}\",exchange=\"{}\",error_code=\"{}\"}} {}\n",
+                ERRORS_METRIC,
+                metrics::escape(bot),
+                metrics::escape(exchange),
+                code,
+                count
+            ));
+        }
+        out
+    }
+}
+
+fn summarize(exchange: &str, stats: &RequestStats) -> ExchangeRequestStats {
+    let requests = stats.latency.count;
+    let errors = stats.errors.values().sum();
+    let millis = |seconds: Option<f64>| seconds.map(|seconds| seconds * 1000.0);
+    ExchangeRequestStats {
+        exchange: exchange.to_string(),
+        requests,
+        errors,
+        error_rate: match requests {
+            0 => 0.0,
+            requests => errors as f64 / requests as f64,
+        },
+        errors_by_code: (stats.errors.iter())
+            .map(|(code, count)| (code.to_string(), *count))
+            .collect(),
+        mean_ms: millis(stats.latency.mean()),
+        p50_ms: millis(stats.latency.quantile(0.5)),
+        p95_ms: millis(stats.latency.quantile(0.95)),
+        p99_ms: millis(stats.latency.quantile(0.99)),
+        max_ms: millis((requests > 0).then_some(stats.latency.max)),
+        last_error_at: stats.last_error.as_ref().map(|(at, _)| *at),
+        last_error: stats.last_error.as_ref().map(|(_, error)| error.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_are_summed_up_per_bot_and_exchange() {
+        let mut stats = ExchangeStats::default();
+        let now = Utc::now();
+        let ms = Duration::from_millis;
+        stats.record("alpha", "binance", ms(40), None, now);
+        stats.record("alpha", "binance", ms(300), None, now);
+        let timeout = Some((ErrorCode::ExchangeUnavailable, "timed out"));
+        stats.record("alpha", "binance", ms(4000), timeout, now);
+        stats.record("acme/alpha", "bybit", ms(20), None, now);
+
+        let view = stats.view("alpha", "alpha");
+        assert_eq!(view.exchanges.len(), 1);
+        let binance = &view.exchanges[0];
+        assert_eq!((binance.requests, binance.errors), (3, 1));
+        assert!((binance.error_rate - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(binance.errors_by_code["EXCHANGE_UNAVAILABLE"], 1);
+        assert_eq!(binance.p50_ms, Some(500.0));
+        assert_eq!(binance.max_ms, Some(4000.0));
+        assert_eq!(binance.last_error.as_deref(), Some("timed out"));
+        assert!(stats.view("beta", "beta").exchanges.is_empty());
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn requests_are_rendered_as_a_histogram_and_error_counters() {
+        let mut stats = ExchangeStats::default();
+        assert_eq!(stats.render(), "");
+        let now = Utc::now();
+        stats.record("alpha", "binance", Duration::from_millis(40), None, now);
+        let timeout = Some((ErrorCode::ExchangeUnavailable, "timed out"));
+        stats.record("alpha", "binance", Duration::from_secs(4), timeout, now);
+
+        let rendered = stats.render();
+        assert!(rendered.contains(
+            "xtrade_exchange_request_duration_seconds_count{bot=\"alpha\",exchange=\"binance\"} 2"
+        ));
+        assert!(rendered.contains(
+            "xtrade_exchange_errors_total{bot=\"alpha\",exchange=\"binance\",\
+             error_code=\"EXCHANGE_UNAVAILABLE\"} 1"
+        ));
+        assert_eq!(rendered.matches("# TYPE").count(), 2);
+    }
+
+    #[test]
+    fn missing_the_objective_notifies_once_until_it_is_met_again() {
+        let mut stats = ExchangeStats::default();
+        let slo = SloConfig {
+            alert_to_fill_ms: Some(500),
+            percentile: 50.0,
+            window: 3,
+        };
+        let mut fill = |ms| stats.record_fill("alpha", Duration::from_millis(ms), &slo);
+        // Not judged before the window is full
+        assert_eq!(fill(900), None);
+        assert_eq!(fill(900), None);
+        let breach = fill(100).unwrap();
+        assert!(breach.contains("p50 of the latest 3") && breach.contains("900ms"));
+        assert_eq!(fill(900), None);
+        // Met again with the median at 100ms, then missed again
+        assert_eq!(fill(100), None);
+        assert_eq!(fill(100), None);
+        assert!(fill(800).is_none() && fill(800).is_some());
+
+        let view = stats.view("alpha", "alpha");
+        let fills = view.alert_to_fill.unwrap();
+        assert_eq!((fills.fills, fills.max_ms), (8, 900.0));
+        #[cfg(feature = "metrics")]
+        {
+            assert!(stats.render().contains("xtrade_slo_breaches_total 2\n"));
+            assert!(stats
+                .render()
+                .contains("xtrade_alert_to_fill_seconds_count{bot=\"alpha\"} 8"));
+        }
+    }
+}
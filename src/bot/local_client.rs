@@ -1,50 +1,370 @@
-use super::cli::OfflineCmds;
-use crate::app_config::AppConfig;
-use crate::app_state::AppState;
+use super::cli::{ManifestLine, MigrateCmds, OfflineCmds};
+use crate::app_config::{AppConfig, OutageConfig, StorageBackend};
+use crate::app_state::{self, AppState};
+use crate::bot::compaction;
+use crate::bot::dispatch;
+use crate::bot::keycheck;
+use crate::bot::outage::Outages;
+use crate::bot::recovery;
+use crate::bot::replay;
+use crate::bot::secrets;
+use crate::bot::state::export::StateExport;
 use crate::bot::state::BotRegistry;
 use crate::errors::AppError;
+use crate::exchange::prices::PriceCache;
+use crate::utils::progress::Progress;
+use clap::Parser;
 use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
 
 /// Handle CLI commands in offline mode
-pub async fn run(state_file: Option<&Path>, args: OfflineCmds) -> Result<(), AppError> {
+///
+/// The changes of a command, or of every command of a manifest, are saved at once when it
+/// succeeds; nothing is saved if it fails. Changes are refused while a server uses the state
+/// file, unless `force`d.
+pub async fn run(
+    state_file: Option<&Path>,
+    force: bool,
+    args: OfflineCmds,
+) -> Result<(), AppError> {
     // Load AppConfig
     let mut app_config = AppConfig::load::<&Path>(None)?;
 
-    // Override the state file if provided via CLI
+    // Override the state file if provided via CLI; the state is loaded from the server's
     if let Some(v) = state_file {
         app_config.local_cli.state_file = v.to_path_buf();
+        app_config.api_server.state_file = v.to_path_buf();
     }
 
-    // Initialize the application state directly
-    let mut app_state = AppState::load(app_config.clone())?;
+    // A server would not see the change, and may overwrite it or refuse to save
+    if args.is_mutation() && !force && app_config.storage.backend == StorageBackend::File {
+        let path = &app_config.api_server.state_file;
+        if let Some(server) = recovery::running_server(path) {
+            return Err(AppError::ServerRunning {
+                path: path.clone(),
+                pid: server.pid,
+                url: app_config.api_server.local_url(),
+            });
+        }
+    }
 
-    match args {
+    // Initialize the application state directly, keeping other processes out until saved
+    let timeout = Duration::from_secs(app_config.local_cli.lock_timeout_secs);
+    let mut app_state = AppState::load_locked(app_config.clone(), timeout)?;
+    app_state.persistence.deferred = true;
+
+    let output = match args {
+        OfflineCmds::Apply(args) => {
+            let content = std::fs::read_to_string(&args.file).map_err(|e| {
+                AppError::InvalidInput(format!("Failed to read {}: {}", args.file.display(), e))
+            })?;
+            let commands = parse_manifest(&content)
+                .map_err(|e| AppError::InvalidInput(format!("{}: {}", args.file.display(), e)))?;
+            let mut outputs = Vec::new();
+            let mut warnings = Vec::new();
+            let mut progress = Progress::start("apply", Some(commands.len() as u64));
+            for (line, command) in commands {
+                match execute(&mut app_state, &app_config, command).await {
+                    Ok(output) => outputs.push(output),
+                    Err(e) => {
+                        drop(progress);
+                        eprintln!(
+                            "{} line {} failed; nothing was saved.",
+                            args.file.display(),
+                            line
+                        );
+                        return Err(e);
+                    }
+                }
+                warnings.extend(app_state.take_warnings());
+                progress.advance(1);
+            }
+            progress.finish();
+            app_state.warnings = warnings;
+            outputs.join("\n\n")
+        }
+        args => execute(&mut app_state, &app_config, args).await?,
+    };
+    app_state.save_deferred()?;
+
+    println!("{}", output);
+    for warning in app_state.take_warnings() {
+        eprintln!("Warning: {}", warning);
+    }
+    Ok(())
+}
+
+/// The commands of a manifest, by line number.
+fn parse_manifest(content: &str) -> Result<Vec<(usize, OfflineCmds)>, String> {
+    let mut commands = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let line_error = |e: String| format!("line {}: {}", index + 1, e);
+        let words = split_words(line).map_err(line_error)?;
+        if words.first().is_none_or(|word| word.starts_with('#')) {
+            continue;
+        }
+        let command = match ManifestLine::try_parse_from(&words) {
+            Ok(parsed) => parsed.command,
+            Err(e) => {
+                let message = e.to_string();
+                let first = message.lines().next().unwrap_or_default();
+                return Err(line_error(first.trim_start_matches("error: ").to_string()));
+            }
+        };
+        match &command {
+            OfflineCmds::Apply(_) => {
+                return Err(line_error("manifests cannot apply manifests".to_string()))
+            }
+            // Orders that reached an exchange are not taken back with the rest
+            OfflineCmds::SimulateAlert(args) if args.execute => {
+                return Err(line_error(
+                    "alerts cannot be executed by a manifest".to_string(),
+                ))
+            }
+            _ => {}
+        }
+        commands.push((index + 1, command));
+    }
+    Ok(commands)
+}
+
+/// Split `line` into words as a shell would: on whitespace, except within quotes or after
+/// a backslash.
+fn split_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') | (None, '\\') => {
+                let escaped = chars.next().ok_or("backslash at the end of the line")?;
+                word.get_or_insert_with(String::new).push(escaped);
+            }
+            (Some(_), c) => word.get_or_insert_with(String::new).push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, c) => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quote.is_some() {
+        return Err("unterminated quote".to_string());
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// Run an offline command against `app_state`, returning what to print.
+async fn execute(
+    app_state: &mut AppState,
+    app_config: &AppConfig,
+    args: OfflineCmds,
+) -> Result<String, AppError> {
+    let output = match args {
         OfflineCmds::ClearAll { target } => match target.as_str() {
             "bots" => {
                 app_state.clear_bots()?;
-                println!("All bots cleared.");
-                Ok(())
+                "All bots cleared.".to_string()
             }
             "listeners" => {
                 app_state.clear_listeners()?;
-                println!("All listeners cleared.");
-                Ok(())
+                "All listeners cleared.".to_string()
+            }
+            _ => {
+                return Err(AppError::InvalidInput(format!(
+                    "Invalid target: {}. Use 'bots' or 'listeners'.",
+                    target
+                )))
             }
-            _ => Err(AppError::InvalidInput(format!(
-                "Invalid target: {}. Use 'bots' or 'listeners'.",
-                target
-            ))),
         },
-        OfflineCmds::AddBot(args) => Ok(println!("{}", app_state.add_bot(args)?)),
-        OfflineCmds::ListBots(args) => Ok(println!("{}", app_state.list_bots(Some(args))?)),
-        OfflineCmds::GetBot(args) => Ok(println!("{}", app_state.get_bot(args)?)),
-        OfflineCmds::UpdateBot(args) => Ok(println!("{}", app_state.update_bot(args)?)),
-        OfflineCmds::DeleteBot(args) => Ok(println!("{}", app_state.delete_bot(args)?)),
-        OfflineCmds::AddListener(args) => Ok(println!("{}", app_state.add_listener(args)?)),
-        OfflineCmds::ListListeners(args) => Ok(println!("{}", app_state.list_listeners(args)?)),
-        OfflineCmds::GetListener(args) => Ok(println!("{}", app_state.get_listener(args)?)),
-        OfflineCmds::UpdateListener(args) => Ok(println!("{}", app_state.update_listener(args)?)),
-        OfflineCmds::DeleteListener(args) => Ok(println!("{}", app_state.delete_listener(args)?)),
-        OfflineCmds::DeleteListeners(args) => Ok(println!("{}", app_state.delete_listeners(args)?)),
+        OfflineCmds::AddBot(mut args) => {
+            let bot = args.clone().into();
+            let check =
+                keycheck::check(&bot, &app_config.key_check, &reqwest::Client::new()).await?;
+            args.key_permissions = check.permissions;
+            let view = app_state.add_bot(args)?;
+            app_state.warnings.extend(check.warnings);
+            view.to_string()
+        }
+        OfflineCmds::ListBots(args) => app_state.list_bots(Some(args))?.to_string(),
+        OfflineCmds::GetBot(args) => app_state.get_bot(args)?.to_string(),
+        OfflineCmds::BotTimeline(args) => app_state.bot_timeline(args)?.to_string(),
+        OfflineCmds::UpdateBot(args) => app_state.update_bot(args)?.to_string(),
+        OfflineCmds::DeleteBot(args) => app_state.delete_bot(args)?.to_string(),
+        OfflineCmds::MapSymbol(args) => app_state.map_symbol(args)?.to_string(),
+        OfflineCmds::UnmapSymbol(args) => app_state.unmap_symbol(args)?.to_string(),
+        OfflineCmds::AddListener(args) => app_state.add_listener(args)?.to_string(),
+        OfflineCmds::ListListeners(args) if args.all_bots => {
+            app_state.list_all_listeners(args)?.to_string()
+        }
+        OfflineCmds::ListListeners(args) => app_state.list_listeners(args)?.to_string(),
+        OfflineCmds::GetListener(args) => app_state.get_listener(args)?.to_string(),
+        OfflineCmds::UpdateListener(args) => app_state.update_listener(args)?.to_string(),
+        OfflineCmds::RotateSecret(args) => app_state.rotate_listener_secret(args)?.to_string(),
+        OfflineCmds::DeleteListener(args) => app_state.delete_listener(args)?.to_string(),
+        OfflineCmds::DeleteListeners(args) => app_state.delete_listeners(args)?.to_string(),
+        OfflineCmds::Export(args) => {
+            let export = app_state.scoped(None).export_bots(args.redact_secrets);
+            let content = export.render(args.format)?;
+            match &args.output {
+                Some(path) => {
+                    std::fs::write(path, content).map_err(|e| {
+                        AppError::GeneralError(format!("Failed to write {}: {}", path.display(), e))
+                    })?;
+                    format!("Export written to {}", path.display())
+                }
+                None => content.trim_end().to_string(),
+            }
+        }
+        OfflineCmds::Import(args) => {
+            let content = std::fs::read_to_string(&args.file).map_err(|e| {
+                AppError::InvalidInput(format!("Failed to read {}: {}", args.file.display(), e))
+            })?;
+            let export = StateExport::parse(&content, args.file_format())?;
+            (app_state.scoped(None))
+                .import_bots(export, args.replace)?
+                .to_string()
+        }
+        OfflineCmds::Compact(args) => {
+            let policy = compaction::policy(&app_config.retention, &args);
+            let mut progress = Progress::start("compact", None);
+            let view = compaction::compact_with(app_state, &policy, &mut progress)?;
+            progress.finish();
+            view.to_string()
+        }
+        OfflineCmds::SimulateAlert(args) => {
+            let payload = std::fs::read_to_string(&args.file).map_err(|e| {
+                AppError::InvalidInput(format!("Failed to read {}: {}", args.file.display(), e))
+            })?;
+            let prices = PriceCache::new(app_config.prices.clone());
+            let ids = (args.bot_id.as_str(), args.listener_id.as_str());
+            let mut view = dispatch::simulate(app_state, &prices, None, ids, &payload)?;
+            if args.execute {
+                let alert = dispatch::listener_alert(app_state, None, ids, &payload)?;
+                // Nothing is left to send alerts waiting for their exchange once we exit
+                let outages = Outages::new(OutageConfig {
+                    buffer_ttl_secs: 0,
+                    ..app_config.outages.clone()
+                });
+                let shared = Mutex::new(std::mem::take(app_state));
+                let client = reqwest::Client::new();
+                let outcome = dispatch::dispatch(
+                    &shared,
+                    &outages,
+                    &prices,
+                    None,
+                    &args.listener_id,
+                    alert,
+                    &client,
+                )
+                .await;
+                *app_state = shared.into_inner().map_err(|_| AppError::LockError)?;
+                if outcome.is_err() {
+                    // The exchange may have been reached; keep what was recorded of it
+                    app_state.save_deferred()?;
+                }
+                view.result = Some(outcome?);
+            }
+            if let Some(result) = &view.result {
+                view.warnings.clone_from(&result.warnings);
+            }
+            app_state.warnings.extend(view.warnings.clone());
+            view.to_string()
+        }
+        OfflineCmds::Replay(args) => replay::day(app_state, None, &args)?
+            .replay(&args)?
+            .to_string(),
+        OfflineCmds::AddUser(args) => {
+            let now = app_state.providers.clock.now();
+            let view = app_state.users.add(args, now)?;
+            app_state.save::<&Path>(None)?;
+            format!("{} saved.", view)
+        }
+        OfflineCmds::ListUsers => app_state.users.list().to_string(),
+        OfflineCmds::DeleteUser(args) => {
+            let view = app_state.users.delete(&args.username)?;
+            app_state.save::<&Path>(None)?;
+            format!("{} deleted.", view)
+        }
+        OfflineCmds::Apply(_) => {
+            return Err(AppError::InvalidInput(
+                "manifests cannot apply manifests".to_string(),
+            ))
+        }
+    };
+    Ok(output)
+}
+
+/// Rewrite the state file for a feature that changed how it is stored
+pub async fn migrate(state_file: Option<&Path>, args: MigrateCmds) -> Result<(), AppError> {
+    let mut app_config = AppConfig::load::<&Path>(None)?;
+    if let Some(v) = state_file {
+        app_config.api_server.state_file = v.to_path_buf();
+    }
+
+    match args {
+        MigrateCmds::EncryptState => {
+            let timeout = Duration::from_secs(app_config.local_cli.lock_timeout_secs);
+            let mut app_state = AppState::load_locked(app_config, timeout)?;
+            if app_state.state_key.is_none() {
+                return Err(AppError::ConfigError(
+                    "Set encryption.key or encryption.key_file to encrypt the state file."
+                        .to_string(),
+                ));
+            }
+            let encrypted: usize = (app_state.bots.values())
+                .map(|bot| secrets::count_sealed(bot).1)
+                .sum();
+            // Backups would keep the plaintext this save replaces
+            let backups = std::mem::take(&mut app_state.backups);
+            app_state.save::<&Path>(None)?;
+            println!(
+                "{} secret(s) of {} bot(s) are encrypted in {}",
+                encrypted,
+                app_state.bots.len(),
+                app_state.location()
+            );
+            if (1..=backups).any(|n| app_state::backup_path(&app_state.state_file, n).exists()) {
+                eprintln!(
+                    "Warning: earlier backups of {} still hold the secrets unencrypted",
+                    app_state.state_file.display()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifests_hold_one_offline_command_per_line() {
+        let manifest = "# Bots of the desk\n\
+             \n\
+             add-bot --bot-id b1 --name \"Main account\" --exchange binance\n\
+             delete-bot --bot-id 'b 2'\n";
+        let commands = parse_manifest(manifest).unwrap();
+        assert_eq!(commands.len(), 2);
+        assert!(
+            matches!(&commands[0], (3, OfflineCmds::AddBot(args)) if args.name == "Main account")
+        );
+        assert!(matches!(&commands[1], (4, OfflineCmds::DeleteBot(args)) if args.bot_id == "b 2"));
+
+        let error = parse_manifest("list-bots\nadd-bot --name x\n").unwrap_err();
+        assert!(error.starts_with("line 2: "), "{}", error);
+        let error = parse_manifest("apply --file other.txt").unwrap_err();
+        assert_eq!(error, "line 1: manifests cannot apply manifests");
+        assert_eq!(
+            split_words(r#"a "b \" c" d\ e"#).unwrap(),
+            ["a", "b \" c", "d e"]
+        );
+        assert!(split_words("add-bot --name 'x").is_err());
     }
 }
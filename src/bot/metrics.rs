@@ -0,0 +1,257 @@
+// src/bot/metrics.rs
+//! # Metrics
+//!
+//! Counters and gauges of the server, served by `GET /metrics` in the Prometheus text
+//! format. Every metric is [described](DESCRIPTIONS) once here; its series are told apart by
+//! their labels. Values live in memory and start over with the server. Builds without the
+//! `metrics` feature count them all the same, for the statistics of bots, but serve no
+//! `GET /metrics`.
+//!
+//! Latencies are counted in [`Histogram`]s, kept where they are measured, like the
+//! [exchange statistics](crate::bot::latency) of the state, and rendered along with the rest.
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// Name, type and help text of every metric.
+#[cfg(feature = "metrics")]
+pub const DESCRIPTIONS: &[(&str, &str, &str)] = &[
+    (
+        "xtrade_compactions_total",
+        "counter",
+        "Compactions of the state run",
+    ),
+    (
+        "xtrade_last_compaction_timestamp_seconds",
+        "gauge",
+        "When the state was last compacted",
+    ),
+    (
+        "xtrade_pruned_records_total",
+        "counter",
+        "Records pruned by compaction, by kind of data",
+    ),
+    (
+        "xtrade_exchange_request_duration_seconds",
+        "histogram",
+        "Time orders took their exchange to answer, by bot and exchange",
+    ),
+    (
+        "xtrade_exchange_errors_total",
+        "counter",
+        "Orders their exchange failed or rejected, by bot, exchange and error code",
+    ),
+    (
+        "xtrade_alert_to_fill_seconds",
+        "histogram",
+        "Time from receiving an alert to its exchange acknowledging the order, by bot",
+    ),
+    (
+        "xtrade_slo_breaches_total",
+        "counter",
+        "Times the alert-to-fill latency objective started being missed",
+    ),
+];
+
+/// Upper bounds of the buckets latencies are counted in, in seconds.
+pub const LATENCY_BUCKETS: [f64; 9] = [0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Values of the metrics, by name and labels.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    series: Mutex<BTreeMap<(&'static str, String), f64>>,
+}
+
+impl Metrics {
+    /// Add `value` to the counter `name` with `labels`.
+    pub fn add(&self, name: &'static str, labels: &[(&str, &str)], value: f64) {
+        self.update(name, labels, |total| *total += value);
+    }
+
+    /// Set the gauge `name` with `labels` to `value`.
+    pub fn set(&self, name: &'static str, labels: &[(&str, &str)], value: f64) {
+        self.update(name, labels, |gauge| *gauge = value);
+    }
+
+    fn update(&self, name: &'static str, labels: &[(&str, &str)], update: impl FnOnce(&mut f64)) {
+        let labels = format_labels(labels);
+        let mut series = self.series.lock().unwrap_or_else(|e| e.into_inner());
+        update(series.entry((name, labels)).or_default());
+    }
+
+    /// All series of the counters and gauges in the Prometheus text exposition format.
+    #[cfg(feature = "metrics")]
+    pub fn render(&self) -> String {
+        let series = self.series.lock().unwrap_or_else(|e| e.into_inner());
+        let mut out = String::new();
+        for (name, kind, help) in DESCRIPTIONS {
+            let mut values = series
+                .iter()
+                .filter(|((metric, _), _)| metric == name)
+                .peekable();
+            if values.peek().is_none() {
+                continue;
+            }
+            out.push_str(&header(name, kind, help));
+            for ((_, labels), value) in values {
+                if labels.is_empty() {
+                    out.push_str(&format!("{} {}\n", name, value));
+                } else {
+                    out.push_str(&format!("{}{{{}}} {}\n", name, labels, value));
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Latencies, in seconds, counted in [`LATENCY_BUCKETS`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Histogram {
+    /// Observations per bucket, and above the last one
+    buckets: [u64; LATENCY_BUCKETS.len() + 1],
+    pub sum: f64,
+    pub count: u64,
+    pub max: f64,
+}
+
+impl Histogram {
+    pub fn observe(&mut self, value: f64) {
+        let bucket = LATENCY_BUCKETS.partition_point(|bound| *bound < value);
+        self.buckets[bucket] += 1;
+        self.sum += value;
+        self.count += 1;
+        self.max = self.max.max(value);
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum / self.count as f64)
+    }
+
+    /// Upper bound of the bucket the `q` quantile falls in, at most the largest observation;
+    /// `None` without observations.
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        let rank = (q * self.count as f64).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (bucket, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                let bound = LATENCY_BUCKETS.get(bucket).copied().unwrap_or(self.max);
+                return Some(bound.min(self.max));
+            }
+        }
+        None
+    }
+
+    /// The series of the histogram `name` with `labels`, in the Prometheus text format.
+    #[cfg(feature = "metrics")]
+    pub fn render(&self, name: &str, labels: &[(&str, &str)]) -> String {
+        let labels = format_labels(labels);
+        let with_le = |le: &str| {
+            if labels.is_empty() {
+                format!("le=\"{}\"", le)
+            } else {
+                format!("{},le=\"{}\"", labels, le)
+            }
+        };
+        let mut out = String::new();
+        let mut cumulative = 0;
+        for (bound, count) in LATENCY_BUCKETS.iter().zip(&self.buckets) {
+            cumulative += count;
+            let le = with_le(&bound.to_string());
+            out.push_str(&format!("{}_bucket{{{}}} {}\n", name, le, cumulative));
+        }
+        out.push_str(&format!(
+            "{}_bucket{{{}}} {}\n",
+            name,
+            with_le("+Inf"),
+            self.count
+        ));
+        let braced = if labels.is_empty() {
+            String::new()
+        } else {
+            format!("{{{}}}", labels)
+        };
+        out.push_str(&format!("{}_sum{} {}\n", name, braced, self.sum));
+        out.push_str(&format!("{}_count{} {}\n", name, braced, self.count));
+        out
+    }
+}
+
+/// The `# HELP` and `# TYPE` lines of the metric `name`, as [described](DESCRIPTIONS).
+#[cfg(feature = "metrics")]
+pub fn describe(name: &str) -> String {
+    DESCRIPTIONS
+        .iter()
+        .find(|(metric, _, _)| *metric == name)
+        .map_or_else(String::new, |(name, kind, help)| header(name, kind, help))
+}
+
+#[cfg(feature = "metrics")]
+fn header(name: &str, kind: &str, help: &str) -> String {
+    format!("# HELP {} {}\n# TYPE {} {}\n", name, help, name, kind)
+}
+
+fn format_labels(labels: &[(&str, &str)]) -> String {
+    labels
+        .iter()
+        .map(|(label, value)| format!("{}=\"{}\"", label, escape(value)))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+pub fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn series_are_rendered_per_metric_with_their_labels() {
+        let metrics = Metrics::default();
+        assert_eq!(metrics.render(), "");
+
+        metrics.add("xtrade_pruned_records_total", &[("data", "alerts")], 3.0);
+        metrics.add("xtrade_pruned_records_total", &[("data", "alerts")], 2.0);
+        metrics.add("xtrade_pruned_records_total", &[("data", "a\"b")], 1.0);
+        metrics.set("xtrade_last_compaction_timestamp_seconds", &[], 1.5);
+        assert_eq!(
+            metrics.render(),
+            "# HELP xtrade_last_compaction_timestamp_seconds When the state was last compacted\n\
+             # TYPE xtrade_last_compaction_timestamp_seconds gauge\n\
+             xtrade_last_compaction_timestamp_seconds 1.5\n\
+             # HELP xtrade_pruned_records_total Records pruned by compaction, by kind of data\n\
+             # TYPE xtrade_pruned_records_total counter\n\
+             xtrade_pruned_records_total{data=\"a\\\"b\"} 1\n\
+             xtrade_pruned_records_total{data=\"alerts\"} 5\n"
+        );
+    }
+
+    #[test]
+    fn histograms_count_latencies_in_cumulative_buckets() {
+        let mut histogram = Histogram::default();
+        assert_eq!((histogram.mean(), histogram.quantile(0.5)), (None, None));
+        for latency in [0.02, 0.2, 0.3, 12.0] {
+            histogram.observe(latency);
+        }
+        assert_eq!(histogram.quantile(0.5), Some(0.25));
+        assert_eq!(histogram.quantile(0.75), Some(0.5));
+        // Beyond the last bucket all that is known is the largest latency
+        assert_eq!(histogram.quantile(0.99), Some(12.0));
+
+        #[cfg(feature = "metrics")]
+        {
+            let rendered = histogram.render("latency", &[("bot", "alpha")]);
+            let lines: Vec<&str> = rendered.lines().collect();
+            assert_eq!(lines[0], "latency_bucket{bot=\"alpha\",le=\"0.025\"} 1");
+            assert_eq!(lines[3], "latency_bucket{bot=\"alpha\",le=\"0.25\"} 2");
+            assert_eq!(lines[9], "latency_bucket{bot=\"alpha\",le=\"+Inf\"} 4");
+            assert_eq!(lines[11], "latency_count{bot=\"alpha\"} 4");
+        }
+    }
+}
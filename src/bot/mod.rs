@@ -1,9 +1,41 @@
 // src/bot/mod.rs
+pub mod access_log;
+pub mod allocation;
 pub mod api;
+pub mod audit;
+pub mod auth;
+pub mod caching;
+pub mod changes;
 pub mod cli;
+pub mod compaction;
+pub mod compression;
+pub mod dispatch;
+pub mod fieldsets;
+pub mod filter;
+pub mod handoff;
+pub mod import;
+pub mod keycheck;
+pub mod latency;
 pub mod local_client;
+pub mod metrics;
 pub mod model;
+pub mod montecarlo;
+pub mod notify;
+pub mod outage;
+pub mod performance;
+pub mod portfolio;
+pub mod quality;
+pub mod reconcile;
+pub mod recovery;
 pub mod remote_client;
+pub mod replay;
 pub mod rest;
+pub mod secrets;
+pub mod selftest;
 pub mod server;
+pub mod sse;
 pub mod state;
+pub mod statement;
+pub mod withdrawals;
+pub mod ws;
+pub mod ws_client;
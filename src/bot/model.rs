@@ -1,4 +1,11 @@
 // src/bot/model.rs
+use crate::app_config::NotificationPrefs;
+use crate::bot::allocation::Budget;
+use crate::bot::filter::AlertFilter;
+use crate::exchange::paper::PaperFills;
+use crate::exchange::{KeyPermissions, Side, Symbol, SymbolMap};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -13,15 +20,197 @@ pub struct Bot {
     pub rest_endpoint: Option<String>,
     pub rpc_endpoint: Option<String>,
     pub webhook_secret: Option<String>,
-    pub trading_fee: Option<f64>,
+    pub trading_fee: Option<Decimal>,
     pub private_key: Option<String>,
     pub contract_address: Option<String>,
+    /// Simulate trades instead of sending them to the exchange
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Track an account traded elsewhere: balances, positions and fills are read, but no
+    /// order is placed or cancelled
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub observer: bool,
+    /// Slippage of simulated market orders, in basis points
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paper_slippage_bps: Option<Decimal>,
+    /// How simulated orders fill: at once or at the next price, wholly or in part, late
+    #[serde(default, skip_serializing_if = "PaperFills::is_empty")]
+    pub paper_fills: PaperFills,
+    /// Exchange symbols to trade for the symbols the bot's alerts name
+    #[serde(default, skip_serializing_if = "SymbolMap::is_empty")]
+    pub symbol_map: SymbolMap,
+    /// Credential sets besides the main one, tried in order for every trade
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub accounts: Vec<SubAccount>,
+    /// Bots sharing a group take turns on a symbol: none trades it while another holds a
+    /// position in it, so strategies on the same account do not fight over it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exclusion_group: Option<String>,
+    /// Most the bot may have in open positions; orders past it are cut down
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget: Option<Budget>,
+    /// Notification preferences of the bot's listeners that have none of their own
+    #[serde(default, skip_serializing_if = "NotificationPrefs::is_empty")]
+    pub notifications: NotificationPrefs,
+    /// What the exchange said the bot's API key may do when the bot was created
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_permissions: Option<KeyPermissions>,
     pub listeners: HashMap<String, Listener>,
 }
 
+impl Bot {
+    /// The bot trading through the first sub-account whose route takes a `side` order on
+    /// `symbol`, and the name of that account; the bot itself if none does.
+    pub fn routed(self, side: Side, symbol: &str) -> (Self, Option<String>) {
+        let Some(account) = self
+            .accounts
+            .iter()
+            .find(|account| account.route.takes(side, symbol))
+            .cloned()
+        else {
+            return (self, None);
+        };
+        let bot = Self {
+            api_key: account.api_key,
+            api_secret: account.api_secret,
+            private_key: account.private_key,
+            ..self
+        };
+        (bot, Some(account.name))
+    }
+
+    /// Settings that leave the bot working, but not as well as it could.
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if self.trading_fee.is_none() {
+            warnings.push("trading_fee missing; PnL will exclude fees".to_string());
+        }
+        let places_orders = !self.dry_run && !self.observer;
+        if places_orders && self.api_key.is_none() && self.private_key.is_none() {
+            warnings.push("no credentials; orders will fail until they are set".to_string());
+        }
+        warnings
+    }
+}
+
+/// Credentials of an exchange sub-account or another wallet of a bot. They replace all of
+/// the bot's own credentials for the trades routed to it.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SubAccount {
+    pub name: String,
+    pub api_key: Option<String>,
+    pub api_secret: Option<String>,
+    pub private_key: Option<String>,
+    #[serde(default)]
+    pub route: AccountRoute,
+}
+
+/// Trades a sub-account takes; an empty route takes every trade.
 #[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AccountRoute {
+    /// Only buys (longs) or only sells (shorts)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub side: Option<Side>,
+    /// Only these symbols, in any form
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub symbols: Vec<String>,
+}
+
+impl AccountRoute {
+    pub fn takes(&self, side: Side, symbol: &str) -> bool {
+        let symbol = Symbol::normalize(symbol);
+        self.side.is_none_or(|only| only == side)
+            && (self.symbols.is_empty()
+                || self
+                    .symbols
+                    .iter()
+                    .any(|routed| Symbol::normalize(routed).eq_ignore_ascii_case(&symbol)))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Listener {
     pub service: String, // Service type (e.g., TradingView)
     pub secret: String,  // Security secret for the webhook
-    pub msg: String,     // Change msg to serde_json::Value
+    /// Message template held inline, until the state moves it to its
+    /// [templates](crate::bot::state::templates)
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub msg: String,
+    /// Hex SHA-256 of the message template, kept in the state's templates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub msg_sha256: Option<String>,
+    /// Disabled listeners are kept but ignore incoming alerts
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    /// Refuse alerts that do not prove knowledge of `secret`; turn off only for testing
+    #[serde(default = "enabled_by_default")]
+    pub verify_secret: bool,
+    /// When the listener last accepted an alert
+    #[serde(default)]
+    pub last_fired: Option<DateTime<Utc>>,
+    /// Alerts the listener passes on to the exchange per minute at most
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_alerts_per_minute: Option<u32>,
+    /// Seconds to wait after a trade before the listener passes on the next alert
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cooldown_secs: Option<u64>,
+    /// Rules alerts must pass to reach the exchange
+    #[serde(default, skip_serializing_if = "AlertFilter::is_empty")]
+    pub filter: AlertFilter,
+    /// Secret replaced by a rotation, still accepted until its grace period ends
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_secret: Option<RetiredSecret>,
+    /// Which of its alerts' outcomes notify, and where, over those of the bot
+    #[serde(default, skip_serializing_if = "NotificationPrefs::is_empty")]
+    pub notifications: NotificationPrefs,
+}
+
+/// A listener secret that was rotated out but stays valid until `valid_until`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RetiredSecret {
+    pub secret: String,
+    pub valid_until: DateTime<Utc>,
+}
+
+impl RetiredSecret {
+    /// The secret, if its grace period has not ended by `now`.
+    pub fn valid_at(&self, now: DateTime<Utc>) -> Option<&str> {
+        (now < self.valid_until).then_some(self.secret.as_str())
+    }
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+impl Listener {
+    /// Settings that let anyone reach the listener.
+    pub fn warnings(&self) -> Vec<String> {
+        if !self.verify_secret {
+            vec!["verify_secret is off; alerts are not authenticated".to_string()]
+        } else if self.secret.is_empty() {
+            vec!["no secret; anyone who knows the webhook URL can send alerts".to_string()]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+impl Default for Listener {
+    fn default() -> Self {
+        Self {
+            service: String::new(),
+            secret: String::new(),
+            msg: String::new(),
+            msg_sha256: None,
+            enabled: true,
+            verify_secret: true,
+            last_fired: None,
+            max_alerts_per_minute: None,
+            cooldown_secs: None,
+            filter: AlertFilter::default(),
+            previous_secret: None,
+            notifications: NotificationPrefs::default(),
+        }
+    }
 }
@@ -0,0 +1,246 @@
+// src/bot/montecarlo.rs
+//! # Monte Carlo Risk Simulation
+//!
+//! Estimates how deep a bot's drawdowns could get by resampling the PnLs of its historical
+//! round trips, as the [strategy report](crate::bot::performance) measures them: each
+//! simulated sequence draws its trades from them at random, with replacement, and adds them
+//! up. The spread of the sequences' largest drawdowns and final PnLs is reported by
+//! percentile; given a starting capital, so is the share of sequences that lost a given part
+//! of it, the risk of ruin. The spread of the cumulative PnL can be drawn as a PNG chart.
+use crate::bot::state::{EquityBand, MonteCarloArgs, MonteCarloPercentile, MonteCarloView};
+use crate::utils::png::{Canvas, Rgb};
+use rand::Rng;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::{dec, Decimal};
+use std::io;
+
+/// Sequences simulated unless asked otherwise.
+pub const DEFAULT_SIMULATIONS: usize = 1_000;
+/// Share of the capital whose loss counts as ruin unless asked otherwise.
+pub const DEFAULT_RUIN: Decimal = dec!(0.5);
+/// Percentiles reported for drawdowns and final PnLs.
+const PERCENTILES: [u8; 5] = [5, 25, 50, 75, 95];
+/// Points of the equity bands at most, however long the sequences.
+const MAX_BANDS: usize = 100;
+
+/// How one simulated sequence went.
+struct Outcome {
+    max_drawdown: f64,
+    relative_drawdown: f64,
+    final_pnl: f64,
+    ruined: bool,
+}
+
+/// Resample `pnls`, the round trip PnLs of the bot `args.bot_id`, as `args` ask.
+pub fn simulate(pnls: &[Decimal], args: &MonteCarloArgs, rng: &mut impl Rng) -> MonteCarloView {
+    let simulations = args.simulations.unwrap_or(DEFAULT_SIMULATIONS);
+    let trades = args.trades.unwrap_or(pnls.len());
+    let ruin = args.ruin.unwrap_or(DEFAULT_RUIN);
+    let mut view = MonteCarloView {
+        bot_id: args.bot_id.clone(),
+        since: args.since,
+        round_trips: pnls.len(),
+        simulations,
+        trades,
+        capital: args.capital,
+        ruin,
+        ..Default::default()
+    };
+    let pnls: Vec<f64> = pnls.iter().filter_map(Decimal::to_f64).collect();
+    if pnls.is_empty() || trades == 0 {
+        return view;
+    }
+    let capital = args.capital.and_then(|capital| capital.to_f64());
+    let ruin_loss = capital.map(|capital| capital * ruin.to_f64().unwrap_or(1.0));
+
+    let stride = trades.div_ceil(MAX_BANDS);
+    let mut bands: Vec<Vec<f64>> = vec![Vec::with_capacity(simulations); trades / stride];
+    let mut outcomes = Vec::with_capacity(simulations);
+    for _ in 0..simulations {
+        let mut outcome = Outcome {
+            max_drawdown: 0.0,
+            relative_drawdown: 0.0,
+            final_pnl: 0.0,
+            ruined: false,
+        };
+        let mut peak = 0.0_f64;
+        for trade in 1..=trades {
+            outcome.final_pnl += pnls[rng.gen_range(0..pnls.len())];
+            peak = peak.max(outcome.final_pnl);
+            let drawdown = peak - outcome.final_pnl;
+            outcome.max_drawdown = outcome.max_drawdown.max(drawdown);
+            if let Some(capital) = capital {
+                outcome.relative_drawdown =
+                    outcome.relative_drawdown.max(drawdown / (capital + peak));
+            }
+            if ruin_loss.is_some_and(|loss| -outcome.final_pnl >= loss) {
+                outcome.ruined = true;
+            }
+            if trade % stride == 0 {
+                bands[trade / stride - 1].push(outcome.final_pnl);
+            }
+        }
+        outcomes.push(outcome);
+    }
+
+    let mut max_drawdowns: Vec<f64> = outcomes.iter().map(|o| o.max_drawdown).collect();
+    let mut relative_drawdowns: Vec<f64> = outcomes.iter().map(|o| o.relative_drawdown).collect();
+    let mut final_pnls: Vec<f64> = outcomes.iter().map(|o| o.final_pnl).collect();
+    for values in [&mut max_drawdowns, &mut relative_drawdowns, &mut final_pnls] {
+        values.sort_by(f64::total_cmp);
+    }
+    view.percentiles = PERCENTILES
+        .iter()
+        .map(|&percentile| MonteCarloPercentile {
+            percentile,
+            max_drawdown: decimal(at(&max_drawdowns, percentile), 2),
+            relative_drawdown: capital.map(|_| decimal(at(&relative_drawdowns, percentile), 4)),
+            final_pnl: decimal(at(&final_pnls, percentile), 2),
+        })
+        .collect();
+    view.risk_of_ruin = capital.map(|_| {
+        let ruined = outcomes.iter().filter(|outcome| outcome.ruined).count();
+        decimal(ruined as f64 / simulations as f64, 4)
+    });
+    view.equity_bands = bands
+        .iter_mut()
+        .enumerate()
+        .map(|(i, pnls)| {
+            pnls.sort_by(f64::total_cmp);
+            EquityBand {
+                trade: (i + 1) * stride,
+                p5: decimal(at(pnls, 5), 2),
+                p50: decimal(at(pnls, 50), 2),
+                p95: decimal(at(pnls, 95), 2),
+            }
+        })
+        .collect();
+    view
+}
+
+/// Chart of the equity bands of `view` as a PNG image: the median cumulative PnL in dark
+/// blue within the 5th to 95th percentile shaded light blue, over the number of trades, with
+/// the zero line in grey.
+pub fn chart(view: &MonteCarloView) -> io::Result<Vec<u8>> {
+    const MARGIN: i64 = 20;
+    const BAND: Rgb = [198, 219, 239];
+    const EDGE: Rgb = [107, 174, 214];
+    const MEDIAN: Rgb = [8, 69, 148];
+    const AXIS: Rgb = [150, 150, 150];
+
+    let mut canvas = Canvas::new(800, 400, [255, 255, 255]);
+    let (width, height) = (canvas.width() as i64, canvas.height() as i64);
+    let origin = EquityBand::default();
+    let bands: Vec<&EquityBand> = std::iter::once(&origin).chain(&view.equity_bands).collect();
+    let value = |d: Decimal| d.to_f64().unwrap_or_default();
+    let low = bands.iter().map(|b| value(b.p5)).fold(0.0, f64::min);
+    let high = bands.iter().map(|b| value(b.p95)).fold(0.0, f64::max);
+    let last = bands.last().map_or(1, |b| b.trade.max(1)) as f64;
+    let range = (high - low).max(f64::EPSILON);
+    let x = |trade: usize| MARGIN + ((width - 2 * MARGIN) as f64 * trade as f64 / last) as i64;
+    let y = |pnl: f64| MARGIN + ((height - 2 * MARGIN) as f64 * (high - pnl) / range) as i64;
+
+    for pair in bands.windows(2) {
+        let (from, to) = (pair[0], pair[1]);
+        let (x0, x1) = (x(from.trade), x(to.trade));
+        for column in x0..=x1 {
+            let t = if x1 == x0 {
+                1.0
+            } else {
+                (column - x0) as f64 / (x1 - x0) as f64
+            };
+            let lerp = |a: Decimal, b: Decimal| value(a) + (value(b) - value(a)) * t;
+            canvas.line(
+                (column, y(lerp(from.p5, to.p5))),
+                (column, y(lerp(from.p95, to.p95))),
+                BAND,
+            );
+        }
+    }
+    canvas.line((MARGIN, y(0.0)), (width - MARGIN, y(0.0)), AXIS);
+    canvas.line((MARGIN, MARGIN), (MARGIN, height - MARGIN), AXIS);
+    for pair in bands.windows(2) {
+        let (from, to) = (pair[0], pair[1]);
+        for (a, b, color) in [
+            (from.p5, to.p5, EDGE),
+            (from.p95, to.p95, EDGE),
+            (from.p50, to.p50, MEDIAN),
+        ] {
+            canvas.line(
+                (x(from.trade), y(value(a))),
+                (x(to.trade), y(value(b))),
+                color,
+            );
+        }
+    }
+    canvas.encode()
+}
+
+/// The value at `percentile` of the sorted `values`, by nearest rank.
+fn at(values: &[f64], percentile: u8) -> f64 {
+    let rank = (values.len() - 1) as f64 * f64::from(percentile) / 100.0;
+    values[rank.round() as usize]
+}
+
+fn decimal(value: f64, dp: u32) -> Decimal {
+    Decimal::try_from(value)
+        .map(|value| value.round_dp(dp))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn args() -> MonteCarloArgs {
+        MonteCarloArgs {
+            bot_id: "alpha".to_string(),
+            simulations: Some(2_000),
+            trades: Some(250),
+            capital: Some(dec!(1000)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn resampled_sequences_spread_by_percentile() {
+        let pnls = [dec!(30), dec!(-20), dec!(10), dec!(-10)];
+        let mut rng = StdRng::seed_from_u64(7);
+        let view = simulate(&pnls, &args(), &mut rng);
+
+        assert_eq!(
+            (view.round_trips, view.simulations, view.trades),
+            (4, 2_000, 250)
+        );
+        let p5 = &view.percentiles[0];
+        let p95 = &view.percentiles[4];
+        assert!(p5.max_drawdown <= p95.max_drawdown);
+        assert!(p5.final_pnl < dec!(625) && dec!(625) < p95.final_pnl);
+        // Expected 2.5 per trade
+        let median = view.percentiles[2].final_pnl;
+        assert!(
+            (dec!(550)..dec!(700)).contains(&median),
+            "median {}",
+            median
+        );
+        assert!(p95.relative_drawdown.unwrap() < Decimal::ONE);
+
+        let risk = view.risk_of_ruin.unwrap();
+        assert!(risk < dec!(0.05), "risk of ruin {}", risk);
+        assert_eq!(view.equity_bands.len(), 83);
+        assert_eq!(view.equity_bands.last().unwrap().trade, 249);
+
+        // Losing trades only ruin every sequence
+        let view = simulate(&[dec!(-10)], &args(), &mut rng);
+        assert_eq!(view.risk_of_ruin, Some(Decimal::ONE));
+        assert_eq!(view.percentiles[0].max_drawdown, dec!(2500));
+
+        let png = chart(&view).unwrap();
+        assert!(png.starts_with(b"\x89PNG"));
+
+        let view = simulate(&[], &args(), &mut rng);
+        assert!(view.percentiles.is_empty() && view.equity_bands.is_empty());
+    }
+}
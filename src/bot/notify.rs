@@ -0,0 +1,184 @@
+// src/bot/notify.rs
+//! # Operator Notifications
+//!
+//! Tells whoever runs the server about things that need their attention. Every notification
+//! is logged under the `notify` target and, when `notifications.webhook_url` is set, `POST`ed
+//! there as JSON, along with any files attached to it. Delivery failures are logged, never
+//! propagated: a notification must not take the server down with it. Builds without the
+//! `notifications` feature only log them, and report every delivery as failed.
+//!
+//! Alert outcomes notify as their listener, its bot or `notifications.alerts` choose (see
+//! [`NotificationPrefs`]); [`route`] picks the channels a notification about one goes to.
+use crate::app_config::{NotificationConfig, NotificationPrefs};
+use crate::bot::dispatch::ExecutionStatus;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct Notification {
+    pub timestamp: DateTime<Utc>,
+    pub title: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<Attachment>,
+}
+
+/// A text file sent along with a notification, such as a monthly statement.
+#[derive(Debug, Serialize)]
+pub struct Attachment {
+    pub filename: String,
+    pub content_type: String,
+    pub content: String,
+}
+
+impl Notification {
+    pub fn new(title: &str, message: &str) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            title: title.to_string(),
+            message: message.to_string(),
+            attachments: Vec::new(),
+        }
+    }
+
+    pub fn with_attachment(mut self, attachment: Attachment) -> Self {
+        self.attachments.push(attachment);
+        self
+    }
+}
+
+/// Log `notification` and deliver it to the configured webhook, if any.
+pub async fn send(config: &NotificationConfig, notification: &Notification) {
+    log::warn!(target: "notify", "{}: {}", notification.title, notification.message);
+
+    if let Err(e) = deliver(config, notification).await {
+        log::error!(target: "notify", "Failed to deliver notification: {}", e);
+    }
+}
+
+/// Deliver `notification` to the configured webhook; `Ok(false)` if there is none.
+pub async fn deliver(
+    config: &NotificationConfig,
+    notification: &Notification,
+) -> Result<bool, String> {
+    let Some(url) = config.webhook_url.as_deref() else {
+        return Ok(false);
+    };
+    post(url, notification).await?;
+    Ok(true)
+}
+
+/// Log `notification` and deliver it to each of `urls`.
+pub async fn send_to(urls: &[String], notification: &Notification) {
+    log::warn!(target: "notify", "{}: {}", notification.title, notification.message);
+
+    for url in urls {
+        if let Err(e) = post(url, notification).await {
+            log::error!(target: "notify", "Failed to deliver notification: {}", e);
+        }
+    }
+}
+
+#[cfg(feature = "notifications")]
+async fn post(url: &str, notification: &Notification) -> Result<(), String> {
+    reqwest::Client::new()
+        .post(url)
+        .json(notification)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[cfg(not(feature = "notifications"))]
+async fn post(_url: &str, _notification: &Notification) -> Result<(), String> {
+    Err("xtrade was built without notifications".to_string())
+}
+
+/// URLs of the channels a notification about an alert with outcome `status` goes to under
+/// the merged `prefs`, or `None` if the outcome does not notify.
+pub fn route(
+    config: &NotificationConfig,
+    prefs: &NotificationPrefs,
+    status: ExecutionStatus,
+) -> Option<Vec<String>> {
+    if !prefs.on.as_ref().is_some_and(|on| on.contains(&status)) {
+        return None;
+    }
+    let default = ["webhook".to_string()];
+    let channels = prefs.channels.as_deref().unwrap_or(&default);
+    let mut urls = Vec::new();
+    for channel in channels {
+        let url = match channel.as_str() {
+            "log" => continue,
+            "webhook" => config.webhook_url.as_ref(),
+            name => {
+                let url = config.channels.get(name);
+                if url.is_none() {
+                    log::warn!(target: "notify", "Unknown notification channel '{}'", name);
+                }
+                url
+            }
+        };
+        if let Some(url) = url.filter(|url| !urls.contains(*url)) {
+            urls.push(url.clone());
+        }
+    }
+    Some(urls)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn listener_preferences_override_the_bot_and_the_defaults() {
+        let config = NotificationConfig {
+            webhook_url: Some("https://hooks.example.com/all".to_string()),
+            channels: HashMap::from([(
+                "ops".to_string(),
+                "https://hooks.example.com/ops".to_string(),
+            )]),
+            alerts: NotificationPrefs {
+                on: Some(vec![ExecutionStatus::Failed, ExecutionStatus::Buffered]),
+                channels: None,
+            },
+        };
+        let bot = NotificationPrefs {
+            on: None,
+            channels: Some(vec!["ops".to_string(), "webhook".to_string()]),
+        };
+        let listener = NotificationPrefs {
+            on: Some(vec![ExecutionStatus::Failed]),
+            channels: None,
+        };
+
+        let prefs = listener.or(&bot).or(&config.alerts);
+        assert_eq!(
+            route(&config, &prefs, ExecutionStatus::Failed),
+            Some(vec![
+                "https://hooks.example.com/ops".to_string(),
+                "https://hooks.example.com/all".to_string()
+            ])
+        );
+        assert_eq!(route(&config, &prefs, ExecutionStatus::Buffered), None);
+
+        // Without preferences of their own, listeners follow the configuration
+        let prefs = NotificationPrefs::default().or(&config.alerts);
+        assert_eq!(
+            route(&config, &prefs, ExecutionStatus::Buffered),
+            Some(vec!["https://hooks.example.com/all".to_string()])
+        );
+        assert_eq!(route(&config, &prefs, ExecutionStatus::Executed), None);
+        assert_eq!(
+            route(
+                &config,
+                &NotificationPrefs::default(),
+                ExecutionStatus::Failed
+            ),
+            None
+        );
+    }
+}
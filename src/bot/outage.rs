@@ -0,0 +1,235 @@
+// src/bot/outage.rs
+//! # Exchange Outages
+//!
+//! TradingView sends every alert once, so an alert that arrives while its exchange cannot be
+//! reached would otherwise be lost. Connection failures are counted per exchange; after
+//! `outages.failure_threshold` of them in a row the exchange's circuit opens and alerts for it
+//! are no longer sent, but held here until `outages.retry_after_secs` have passed and the
+//! exchange is tried again. Orders that found their exchange unreachable are held as well.
+//!
+//! [`dispatch::replay`](crate::bot::dispatch::replay) executes held orders once their
+//! exchange is back, as long as they are no older than `outages.buffer_ttl_secs` and the price
+//! moved by no more than `outages.price_tolerance` since the alert arrived.
+use crate::app_config::OutageConfig;
+use crate::bot::dispatch::Order;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// An accepted order waiting, queued in the journal, until its exchange can be reached.
+#[derive(Clone, Debug)]
+pub struct PendingOrder {
+    pub(crate) order: Order,
+    pub tenant: Option<String>,
+    /// Storage key of the bot
+    pub bot_key: String,
+    pub order_id: String,
+    pub exchange: String,
+    pub received_at: DateTime<Utc>,
+    /// Cached price of the symbol when the alert arrived
+    pub reference_price: Option<f64>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Circuit {
+    /// Connection failures in a row
+    failures: u32,
+    opened_at: Option<DateTime<Utc>>,
+}
+
+/// Circuits of the exchanges and the orders waiting for them.
+#[derive(Debug, Default)]
+pub struct Outages {
+    config: OutageConfig,
+    circuits: Mutex<HashMap<String, Circuit>>,
+    pending: Mutex<Vec<PendingOrder>>,
+}
+
+impl Outages {
+    pub fn new(config: OutageConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    pub fn config(&self) -> &OutageConfig {
+        &self.config
+    }
+
+    /// Whether alerts for `exchange` should wait instead of being sent. The circuit lets
+    /// orders through again once `retry_after_secs` passed since it opened.
+    pub fn is_open(&self, exchange: &str, now: DateTime<Utc>) -> bool {
+        let retry_after = Duration::seconds(self.config.retry_after_secs as i64);
+        self.circuits
+            .lock()
+            .ok()
+            .and_then(|circuits| circuits.get(exchange).and_then(|c| c.opened_at))
+            .is_some_and(|opened_at| now - opened_at < retry_after)
+    }
+
+    /// Close the circuit of `exchange`. Returns `true` if it was open.
+    pub fn record_success(&self, exchange: &str) -> bool {
+        let Ok(mut circuits) = self.circuits.lock() else {
+            return false;
+        };
+        circuits
+            .remove(exchange)
+            .is_some_and(|circuit| circuit.opened_at.is_some())
+    }
+
+    /// Count a connection failure of `exchange`. Returns `true` if its circuit opened, or
+    /// opened again after a failed retry.
+    pub fn record_failure(&self, exchange: &str, now: DateTime<Utc>) -> bool {
+        let Ok(mut circuits) = self.circuits.lock() else {
+            return false;
+        };
+        let circuit = circuits.entry(exchange.to_string()).or_default();
+        circuit.failures += 1;
+        if circuit.failures < self.config.failure_threshold.max(1) {
+            return false;
+        }
+        circuit.opened_at = Some(now);
+        true
+    }
+
+    /// Hold `order` until its exchange can be reached. Returns `false` if buffering is
+    /// disabled or `max_buffered` orders are already waiting.
+    pub fn buffer(&self, order: PendingOrder) -> bool {
+        if self.config.buffer_ttl_secs == 0 {
+            return false;
+        }
+        let Ok(mut pending) = self.pending.lock() else {
+            return false;
+        };
+        if pending.len() >= self.config.max_buffered {
+            return false;
+        }
+        pending.push(order);
+        true
+    }
+
+    /// Put back an order taken by [`Outages::take_due`], however many are waiting.
+    pub fn restore(&self, order: PendingOrder) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.push(order);
+        }
+    }
+
+    /// Take out the orders that waited longer than `buffer_ttl_secs`, and those whose
+    /// exchange's circuit lets them through.
+    pub fn take_due(&self, now: DateTime<Utc>) -> (Vec<PendingOrder>, Vec<PendingOrder>) {
+        let ttl = Duration::seconds(self.config.buffer_ttl_secs as i64);
+        let Ok(mut pending) = self.pending.lock() else {
+            return Default::default();
+        };
+        let (mut expired, mut ready) = (Vec::new(), Vec::new());
+        for order in std::mem::take(&mut *pending) {
+            if now - order.received_at >= ttl {
+                expired.push(order);
+            } else if !self.is_open(&order.exchange, now) {
+                ready.push(order);
+            } else {
+                pending.push(order);
+            }
+        }
+        (expired, ready)
+    }
+
+    /// Take out every waiting order.
+    pub fn drain(&self) -> Vec<PendingOrder> {
+        self.pending
+            .lock()
+            .map(|mut pending| std::mem::take(&mut *pending))
+            .unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending
+            .lock()
+            .map(|pending| pending.len())
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alert::Alert;
+
+    fn pending(exchange: &str, received_at: DateTime<Utc>) -> PendingOrder {
+        let alert = Alert::parse(
+            "tradingview",
+            r#"{"bot_id": "alpha", "ticker": "BTCUSDT", "action": "buy", "order_size": "1",
+                "position_size": "1", "schema": "2", "timestamp": "2025-01-10T12:00:00Z"}"#,
+        )
+        .unwrap();
+        PendingOrder {
+            order: Order::from_alert(alert, "tv").unwrap(),
+            tenant: None,
+            bot_key: "alpha".to_string(),
+            order_id: format!("{}-order", exchange),
+            exchange: exchange.to_string(),
+            received_at,
+            reference_price: None,
+        }
+    }
+
+    #[test]
+    fn circuits_open_after_repeated_failures_and_retry_later() {
+        let outages = Outages::new(OutageConfig::default());
+        let now = Utc::now();
+        assert!(!outages.record_failure("bybit", now));
+        assert!(!outages.record_failure("bybit", now));
+        assert!(!outages.is_open("bybit", now));
+        assert!(outages.record_failure("bybit", now));
+        assert!(outages.is_open("bybit", now));
+        assert!(!outages.is_open("binance", now));
+
+        // Half open once the retry delay passed; a failed retry opens it again
+        let later = now + Duration::seconds(30);
+        assert!(!outages.is_open("bybit", later));
+        assert!(outages.record_failure("bybit", later));
+        assert!(outages.is_open("bybit", later));
+        assert!(outages.record_success("bybit"));
+        assert!(!outages.is_open("bybit", later));
+        assert!(!outages.record_success("bybit"));
+    }
+
+    #[test]
+    fn buffered_orders_wait_for_their_exchange_until_they_expire() {
+        let outages = Outages::new(OutageConfig {
+            failure_threshold: 1,
+            max_buffered: 2,
+            ..OutageConfig::default()
+        });
+        let now = Utc::now();
+        outages.record_failure("bybit", now);
+        assert!(outages.buffer(pending("bybit", now)));
+        assert!(outages.buffer(pending("hyperliquid", now - Duration::seconds(300))));
+        assert!(!outages.buffer(pending("dydx", now)));
+
+        let (expired, ready) = outages.take_due(now);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].exchange, "hyperliquid");
+        assert!(ready.is_empty());
+        assert_eq!(outages.len(), 1);
+
+        let (expired, ready) = outages.take_due(now + Duration::seconds(30));
+        assert!(expired.is_empty());
+        assert_eq!(ready[0].order_id, "bybit-order");
+        outages.restore(ready[0].clone());
+        assert_eq!(outages.drain().len(), 1);
+        assert!(outages.is_empty());
+
+        let disabled = Outages::new(OutageConfig {
+            buffer_ttl_secs: 0,
+            ..OutageConfig::default()
+        });
+        assert!(!disabled.buffer(pending("bybit", now)));
+    }
+}
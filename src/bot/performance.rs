@@ -0,0 +1,227 @@
+// src/bot/performance.rs
+//! # Strategy Performance
+//!
+//! Compares the strategies feeding a bot. Each listener is a strategy: its executed trades,
+//! read from the [trade history](crate::bot::state::history) in the order they finished, move
+//! positions of its own by the average-cost rules of
+//! [paper positions](crate::bot::state::paper), so strategies trading the same symbol do not
+//! close each other's positions. [Imported](crate::bot::import) fills count as a strategy of
+//! their own listener.
+use crate::bot::dispatch::ExecutionStatus;
+use crate::bot::state::history::{TradeHistory, TradeRecord};
+use crate::bot::state::paper::{PaperFill, PaperPosition};
+use crate::bot::state::StrategyPerformance;
+use crate::exchange::Side;
+use chrono::{DateTime, Utc};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+use std::io;
+
+/// Trades of one listener.
+#[derive(Debug, Default)]
+struct Strategy {
+    positions: BTreeMap<String, PaperPosition>,
+    trades: u64,
+    /// Net PnL of each round trip
+    round_trips: Vec<Decimal>,
+    net_pnl: Decimal,
+    peak_pnl: Decimal,
+    max_drawdown: Decimal,
+}
+
+impl Strategy {
+    fn record(&mut self, fill: &PaperFill) {
+        let position = self.positions.entry(fill.symbol.clone()).or_default();
+        let reduces = !position.quantity.is_zero()
+            && position.quantity.is_sign_negative() != fill.quantity.is_sign_negative();
+        let realized_before = position.realized_pnl;
+        position.apply(fill);
+        let pnl = position.realized_pnl - realized_before - fill.fee;
+
+        self.trades += 1;
+        if reduces {
+            self.round_trips.push(pnl);
+        }
+        self.net_pnl += pnl;
+        self.peak_pnl = self.peak_pnl.max(self.net_pnl);
+        self.max_drawdown = self.max_drawdown.max(self.peak_pnl - self.net_pnl);
+    }
+
+    fn performance(&self, bot_id: &str, listener_id: &str) -> StrategyPerformance {
+        let count = self.round_trips.len();
+        let wins = self
+            .round_trips
+            .iter()
+            .filter(|pnl| pnl.is_sign_positive() && !pnl.is_zero());
+        let profit: Decimal = wins.clone().sum();
+        let loss: Decimal = -self
+            .round_trips
+            .iter()
+            .filter(|pnl| pnl.is_sign_negative())
+            .sum::<Decimal>();
+        StrategyPerformance {
+            bot_id: bot_id.to_string(),
+            listener_id: listener_id.to_string(),
+            trades: self.trades,
+            round_trips: count as u64,
+            win_rate: (count > 0)
+                .then(|| (Decimal::from(wins.count()) / Decimal::from(count)).round_dp(4)),
+            profit_factor: (!loss.is_zero()).then(|| (profit / loss).round_dp(2)),
+            net_pnl: self.net_pnl,
+            max_drawdown: self.max_drawdown,
+            sharpe: sharpe(&self.round_trips),
+        }
+    }
+}
+
+/// Mean over sample standard deviation of `pnls`, if there are two that differ.
+fn sharpe(pnls: &[Decimal]) -> Option<Decimal> {
+    let pnls: Vec<f64> = pnls.iter().filter_map(Decimal::to_f64).collect();
+    if pnls.len() < 2 {
+        return None;
+    }
+    let n = pnls.len() as f64;
+    let mean = pnls.iter().sum::<f64>() / n;
+    let variance = pnls.iter().map(|pnl| (pnl - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    let sharpe = mean / variance.sqrt();
+    Decimal::try_from(sharpe)
+        .ok()
+        .map(|sharpe| sharpe.round_dp(2))
+}
+
+/// Performance of a bot's listeners, built up trade by trade in the order they finished.
+#[derive(Debug, Default)]
+pub struct StrategyReport {
+    listeners: BTreeMap<String, Strategy>,
+}
+
+impl StrategyReport {
+    /// Report on the trades in `history` of the bot stored as `key`, from `since` on.
+    pub fn load(
+        history: &TradeHistory,
+        key: &str,
+        since: Option<DateTime<Utc>>,
+    ) -> io::Result<Self> {
+        let first_month = since.map(|since| since.format("%Y-%m").to_string());
+        let mut report = Self::default();
+        for month in history.months(key)? {
+            if first_month.as_ref().is_some_and(|first| month < *first) {
+                continue;
+            }
+            for trade in history.load_month(key, &month)? {
+                if since.is_none_or(|since| trade.result.finished_at >= since) {
+                    report.record(&trade);
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Count `trade` if it executed and reported its fill.
+    pub fn record(&mut self, trade: &TradeRecord) {
+        let result = &trade.result;
+        if result.status != ExecutionStatus::Executed {
+            return;
+        }
+        let side = match &trade.order {
+            Some(order) => Some(order.side()),
+            None => Side::parse(&result.action),
+        };
+        let (Some(side), Some(report)) = (side, &result.report) else {
+            return;
+        };
+        if let Some(fill) = PaperFill::from_report(&result.symbol, side.is_buy(), report) {
+            self.listeners
+                .entry(result.listener_id.clone())
+                .or_default()
+                .record(&fill);
+        }
+    }
+
+    /// PnL of every round trip, listener by listener.
+    pub fn round_trips(&self) -> Vec<Decimal> {
+        self.listeners
+            .values()
+            .flat_map(|strategy| strategy.round_trips.iter().copied())
+            .collect()
+    }
+
+    /// Performance per listener, as seen by callers naming the bot `bot_id`.
+    pub fn strategies(&self, bot_id: &str) -> Vec<StrategyPerformance> {
+        self.listeners
+            .iter()
+            .map(|(listener_id, strategy)| strategy.performance(bot_id, listener_id))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::dispatch::ExecutionResult;
+    use crate::exchange::ExecutionReport;
+    use rust_decimal::dec;
+
+    fn trade(listener_id: &str, action: &str, quantity: Decimal, price: Decimal) -> TradeRecord {
+        let result = ExecutionResult {
+            order_id: None,
+            bot_id: "alpha".to_string(),
+            listener_id: listener_id.to_string(),
+            exchange: "binance".to_string(),
+            account: None,
+            action: action.to_string(),
+            symbol: "BTCUSDT".to_string(),
+            order_size: quantity.to_string(),
+            status: ExecutionStatus::Executed,
+            message: "order accepted by the exchange".to_string(),
+            report: Some(ExecutionReport {
+                filled_quantity: Some(quantity),
+                average_price: Some(price),
+                fees: Some(dec!(1)),
+                ..Default::default()
+            }),
+            duplicate: false,
+            error_code: None,
+            warnings: Vec::new(),
+            latency_ms: None,
+            finished_at: Utc::now(),
+        };
+        TradeRecord {
+            result,
+            order: None,
+        }
+    }
+
+    #[test]
+    fn strategies_are_measured_by_their_round_trips() {
+        let mut report = StrategyReport::default();
+        for (listener_id, action, quantity, price) in [
+            ("trend", "buy", dec!(2), dec!(100)),
+            ("swing", "sell", dec!(1), dec!(150)),
+            // +20 and -10, less a fee of 1 each
+            ("trend", "sell", dec!(1), dec!(120)),
+            ("trend", "sell", dec!(1), dec!(90)),
+            ("trend", "buy", dec!(1), dec!(100)),
+            ("trend", "sell", dec!(1), dec!(130)),
+        ] {
+            report.record(&trade(listener_id, action, quantity, price));
+        }
+
+        let strategies = report.strategies("alpha");
+        let swing = &strategies[0];
+        assert_eq!(swing.listener_id, "swing");
+        assert_eq!((swing.trades, swing.round_trips), (1, 0));
+        assert_eq!((swing.win_rate, swing.sharpe), (None, None));
+
+        let trend = &strategies[1];
+        assert_eq!((trend.trades, trend.round_trips), (5, 3));
+        assert_eq!(trend.win_rate, Some(dec!(0.6667)));
+        // Round trips of 19, -11 and 29
+        assert_eq!(trend.profit_factor, Some(dec!(4.36)));
+        assert_eq!(trend.net_pnl, dec!(35));
+        // From 18 after the first sale down to 6 after the buy back
+        assert_eq!(trend.max_drawdown, dec!(12));
+        assert_eq!(trend.sharpe, Some(dec!(0.59)));
+    }
+}
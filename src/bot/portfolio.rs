@@ -0,0 +1,294 @@
+// src/bot/portfolio.rs
+//! # Portfolio
+//!
+//! Profit and loss of a bot, built from its [trade history](crate::bot::state::history).
+//! Every executed trade with a reported fill moves the bot's position in its symbol by the
+//! average-cost rules of [paper positions](crate::bot::state::paper): closing quantity
+//! realizes PnL, and what is still open is marked to the cached price of the symbol, when
+//! there is one.
+//!
+//! The state keeps the [`Portfolios`] of all bots, moved by each trade as it is recorded, so
+//! the checks of an alert need not replay trade histories.
+use crate::bot::dispatch::ExecutionStatus;
+use crate::bot::state::history::{TradeHistory, TradeRecord};
+use crate::bot::state::paper::{PaperFill, PaperPosition};
+use crate::bot::state::{PnlView, SymbolPnl};
+use crate::exchange::prices::PriceCache;
+use crate::exchange::Side;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io;
+
+/// Positions of a bot by symbol, built up trade by trade.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(transparent)]
+pub struct Portfolio {
+    positions: BTreeMap<String, PaperPosition>,
+}
+
+impl Portfolio {
+    /// Portfolio of every trade in `history` of the bot stored as `bot_id`.
+    pub fn load(history: &TradeHistory, bot_id: &str) -> io::Result<Self> {
+        let mut portfolio = Self::default();
+        for month in history.months(bot_id)? {
+            for trade in history.load_month(bot_id, &month)? {
+                portfolio.record(&trade);
+            }
+        }
+        Ok(portfolio)
+    }
+
+    /// Add `trade` to the position in its symbol. Trades that did not execute or report no
+    /// fill leave the portfolio alone.
+    pub fn record(&mut self, trade: &TradeRecord) {
+        let result = &trade.result;
+        if result.status != ExecutionStatus::Executed {
+            return;
+        }
+        let side = match &trade.order {
+            Some(order) => Some(order.side()),
+            None => Side::parse(&result.action),
+        };
+        let (Some(side), Some(report)) = (side, &result.report) else {
+            return;
+        };
+        if let Some(fill) = PaperFill::from_report(&result.symbol, side.is_buy(), report) {
+            self.positions
+                .entry(fill.symbol.clone())
+                .or_default()
+                .apply(&fill);
+        }
+    }
+
+    /// Whether the bot holds a position in `symbol`.
+    pub fn is_open(&self, symbol: &str) -> bool {
+        self.positions
+            .get(symbol)
+            .is_some_and(|position| !position.quantity.is_zero())
+    }
+
+    /// Signed quantity held in `symbol`: positive when long, negative when short.
+    pub fn position(&self, symbol: &str) -> Decimal {
+        self.positions
+            .get(symbol)
+            .map_or(Decimal::ZERO, |position| position.quantity)
+    }
+
+    /// Value of the open positions, marked to `prices` or else to their entry prices.
+    pub fn exposure(&self, prices: &PriceCache) -> Decimal {
+        self.positions
+            .iter()
+            .map(|(symbol, position)| {
+                let price = prices
+                    .quote(symbol)
+                    .and_then(|quote| Decimal::try_from(quote.price).ok())
+                    .unwrap_or(position.entry_price);
+                position.quantity.abs() * price
+            })
+            .sum()
+    }
+
+    /// PnL of `bot_id` per symbol and in total, marking open positions to `prices`.
+    pub fn pnl(&self, bot_id: &str, prices: &PriceCache) -> PnlView {
+        let symbols: Vec<SymbolPnl> = self
+            .positions
+            .iter()
+            .map(|(symbol, position)| {
+                let mark_price = prices
+                    .quote(symbol)
+                    .and_then(|quote| Decimal::try_from(quote.price).ok());
+                let unrealized_pnl = if position.quantity.is_zero() {
+                    Some(Decimal::ZERO)
+                } else {
+                    mark_price.map(|price| position.unrealized_pnl(price))
+                };
+                SymbolPnl {
+                    symbol: symbol.clone(),
+                    quantity: position.quantity,
+                    entry_price: position.entry_price,
+                    mark_price,
+                    realized_pnl: position.realized_pnl,
+                    unrealized_pnl,
+                    fees: position.fees,
+                    trades: position.trades,
+                }
+            })
+            .collect();
+
+        let realized_pnl = symbols.iter().map(|s| s.realized_pnl).sum();
+        let unrealized_pnl = symbols.iter().filter_map(|s| s.unrealized_pnl).sum();
+        let fees = symbols.iter().map(|s| s.fees).sum();
+        PnlView {
+            bot_id: bot_id.to_string(),
+            net_pnl: realized_pnl + unrealized_pnl - fees,
+            realized_pnl,
+            unrealized_pnl,
+            fees,
+            symbols,
+        }
+    }
+}
+
+/// Portfolios of every bot, by the key the bot is stored under.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(transparent)]
+pub struct Portfolios {
+    bots: BTreeMap<String, Portfolio>,
+}
+
+impl Portfolios {
+    pub fn is_empty(&self) -> bool {
+        self.bots.is_empty()
+    }
+
+    /// Portfolio of the bot stored as `key`, if it ever traded.
+    pub fn get(&self, key: &str) -> Option<&Portfolio> {
+        self.bots.get(key)
+    }
+
+    /// Add `trade` of the bot stored as `key` to its portfolio.
+    pub fn record(&mut self, key: &str, trade: &TradeRecord) {
+        self.bots.entry(key.to_string()).or_default().record(trade);
+    }
+
+    /// Replace the portfolio of the bot stored as `key`, e.g. once fills were imported.
+    pub fn insert(&mut self, key: &str, portfolio: Portfolio) {
+        self.bots.insert(key.to_string(), portfolio);
+    }
+
+    /// Build the portfolios of those of `keys` that have none from their trade histories,
+    /// for states saved before portfolios were kept.
+    pub fn load_missing<'a>(
+        &mut self,
+        history: &TradeHistory,
+        keys: impl IntoIterator<Item = &'a String>,
+    ) -> io::Result<()> {
+        for key in keys {
+            if self.bots.contains_key(key) {
+                continue;
+            }
+            let portfolio = Portfolio::load(history, key)?;
+            if !portfolio.positions.is_empty() {
+                self.bots.insert(key.clone(), portfolio);
+            }
+        }
+        Ok(())
+    }
+
+    /// Keep the portfolios of `previous` for the bots of `keys` that have none here.
+    pub fn adopt<'a>(&mut self, previous: &Portfolios, keys: impl IntoIterator<Item = &'a String>) {
+        for key in keys {
+            if let Some(portfolio) = previous.get(key) {
+                self.bots
+                    .entry(key.clone())
+                    .or_insert_with(|| portfolio.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::dispatch::ExecutionResult;
+    use crate::exchange::ExecutionReport;
+    use chrono::Utc;
+    use rust_decimal::dec;
+
+    fn trade(symbol: &str, action: &str, quantity: Decimal, price: Decimal) -> TradeRecord {
+        let result = ExecutionResult {
+            order_id: None,
+            bot_id: "alpha".to_string(),
+            listener_id: "tv".to_string(),
+            exchange: "binance".to_string(),
+            account: None,
+            action: action.to_string(),
+            symbol: symbol.to_string(),
+            order_size: quantity.to_string(),
+            status: ExecutionStatus::Executed,
+            message: "order accepted by the exchange".to_string(),
+            report: Some(ExecutionReport {
+                filled_quantity: Some(quantity),
+                average_price: Some(price),
+                fees: Some(dec!(0.5)),
+                ..ExecutionReport::acknowledged(Utc::now())
+            }),
+            duplicate: false,
+            error_code: None,
+            warnings: Vec::new(),
+            latency_ms: None,
+            finished_at: Utc::now(),
+        };
+        TradeRecord {
+            result,
+            order: None,
+        }
+    }
+
+    #[test]
+    fn trades_add_up_to_realized_and_unrealized_pnl() {
+        let mut portfolio = Portfolio::default();
+        portfolio.record(&trade("BTCUSDT", "buy", dec!(2), dec!(100)));
+        portfolio.record(&trade("BTCUSDT", "sell", dec!(1), dec!(120)));
+        portfolio.record(&trade("ETHUSDT", "sell", dec!(3), dec!(10)));
+        portfolio.record(&trade("SOLUSDT", "buy", dec!(1), dec!(5)));
+        portfolio.record(&trade("SOLUSDT", "sell", dec!(1), dec!(4)));
+        let mut failed = trade("BTCUSDT", "buy", dec!(5), dec!(1));
+        failed.result.status = ExecutionStatus::Failed;
+        portfolio.record(&failed);
+
+        let prices = PriceCache::new(Default::default());
+        prices.record("BTCUSDT", 130.0, Utc::now());
+        let pnl = portfolio.pnl("alpha", &prices);
+
+        let btc = &pnl.symbols[0];
+        assert_eq!((btc.symbol.as_str(), btc.quantity), ("BTCUSDT", dec!(1)));
+        assert_eq!(
+            (btc.realized_pnl, btc.unrealized_pnl),
+            (dec!(20), Some(dec!(30)))
+        );
+        assert_eq!(btc.fees, dec!(1.0));
+        // Open, but no price to mark it to
+        let eth = &pnl.symbols[1];
+        assert_eq!((eth.quantity, eth.unrealized_pnl), (dec!(-3), None));
+        let sol = &pnl.symbols[2];
+        assert_eq!(
+            (sol.realized_pnl, sol.unrealized_pnl),
+            (dec!(-1), Some(dec!(0)))
+        );
+
+        assert_eq!(pnl.realized_pnl, dec!(19));
+        assert_eq!(pnl.unrealized_pnl, dec!(30));
+        assert_eq!(pnl.fees, dec!(2.5));
+        assert_eq!(pnl.net_pnl, dec!(46.5));
+    }
+
+    #[test]
+    fn missing_portfolios_are_built_from_trade_histories() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = TradeHistory::for_state_file(&dir.path().join("state.json"));
+        history
+            .append("alpha", &trade("BTCUSDT", "buy", dec!(2), dec!(100)))
+            .unwrap();
+        let mut portfolios = Portfolios::default();
+        portfolios.record("beta", &trade("ETHUSDT", "sell", dec!(1), dec!(10)));
+        let keys = ["alpha".to_string(), "beta".to_string()];
+
+        portfolios.load_missing(&history, &keys).unwrap();
+        assert_eq!(
+            portfolios.get("alpha").unwrap().position("BTCUSDT"),
+            dec!(2)
+        );
+        // Kept ones are not replaced by their history
+        assert_eq!(
+            portfolios.get("beta").unwrap().position("ETHUSDT"),
+            dec!(-1)
+        );
+
+        let mut reloaded = Portfolios::default();
+        reloaded.adopt(&portfolios, &keys[..1]);
+        assert!(reloaded.get("alpha").unwrap().is_open("BTCUSDT"));
+        assert!(reloaded.get("beta").is_none());
+    }
+}
@@ -0,0 +1,192 @@
+// src/bot/quality.rs
+//! # Execution Quality
+//!
+//! How much the fills of alerts cost against the price the alert fired at: its
+//! `alert_price`, or the cached price of the symbol when it arrived. Trades are read from the
+//! [trade history](crate::bot::state::history) and grouped by bot and listener; only trades
+//! placed for alerts count, so [imported](crate::bot::import) fills are left out. A trade is
+//! measured once its exchange reported an average fill price.
+use crate::bot::dispatch::ExecutionStatus;
+use crate::bot::state::history::{TradeHistory, TradeRecord};
+use crate::bot::state::ExecutionQuality;
+use chrono::{DateTime, Utc};
+use rust_decimal::dec;
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+use std::io;
+
+/// Slippage of one trade.
+struct Slippage {
+    bps: Decimal,
+    /// In the quote asset; positive when the fill was worse than the alert price
+    cost: Decimal,
+    /// Quantity times alert price
+    notional: Decimal,
+}
+
+/// Slippage of `trade`, if it was placed for an alert with a price and reported its fill.
+fn slippage(trade: &TradeRecord) -> Option<Slippage> {
+    let order = trade.order.as_ref()?;
+    let alert_price = order.alert_price()?;
+    let report = trade.result.report.as_ref()?;
+    let (fill_price, quantity) = (report.average_price?, report.filled_quantity?);
+    if quantity.is_zero() {
+        return None;
+    }
+    let worse_by = if order.side().is_buy() {
+        fill_price - alert_price
+    } else {
+        alert_price - fill_price
+    };
+    Some(Slippage {
+        bps: (worse_by / alert_price * dec!(10_000)).round_dp(2),
+        cost: worse_by * quantity,
+        notional: alert_price * quantity,
+    })
+}
+
+/// Execution quality of a bot's listeners, built up trade by trade.
+#[derive(Debug, Default)]
+pub struct QualityReport {
+    /// By listener ID, with the notional of the measured trades
+    listeners: BTreeMap<String, (ExecutionQuality, Decimal)>,
+}
+
+impl QualityReport {
+    /// Report on the trades in `history` of the bot stored as `key`, from `since` on.
+    pub fn load(
+        history: &TradeHistory,
+        key: &str,
+        since: Option<DateTime<Utc>>,
+    ) -> io::Result<Self> {
+        let first_month = since.map(|since| since.format("%Y-%m").to_string());
+        let mut report = Self::default();
+        for month in history.months(key)? {
+            if first_month.as_ref().is_some_and(|first| month < *first) {
+                continue;
+            }
+            for trade in history.load_month(key, &month)? {
+                if since.is_none_or(|since| trade.result.finished_at >= since) {
+                    report.record(&trade);
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Count `trade` if it was placed for an alert and executed.
+    pub fn record(&mut self, trade: &TradeRecord) {
+        let result = &trade.result;
+        if trade.order.is_none() || result.status != ExecutionStatus::Executed {
+            return;
+        }
+        let (quality, notional) = self
+            .listeners
+            .entry(result.listener_id.clone())
+            .or_insert_with(|| {
+                let quality = ExecutionQuality {
+                    listener_id: result.listener_id.clone(),
+                    ..Default::default()
+                };
+                (quality, Decimal::ZERO)
+            });
+        quality.trades += 1;
+        let Some(slippage) = slippage(trade) else {
+            return;
+        };
+        quality.measured += 1;
+        quality.slippage_cost += slippage.cost;
+        *notional += slippage.notional;
+        if !notional.is_zero() {
+            quality.slippage_bps =
+                Some((quality.slippage_cost / *notional * dec!(10_000)).round_dp(2));
+        }
+        quality.worst_slippage_bps = Some(
+            quality
+                .worst_slippage_bps
+                .map_or(slippage.bps, |worst| worst.max(slippage.bps)),
+        );
+    }
+
+    /// Execution quality per listener, as seen by callers naming the bot `bot_id`.
+    pub fn listeners(self, bot_id: &str) -> Vec<ExecutionQuality> {
+        self.listeners
+            .into_values()
+            .map(|(quality, _)| ExecutionQuality {
+                bot_id: bot_id.to_string(),
+                ..quality
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alert::Alert;
+    use crate::bot::dispatch::{ExecutionResult, Order};
+    use crate::exchange::ExecutionReport;
+
+    fn trade(action: &str, alert_price: &str, fill_price: Option<Decimal>) -> TradeRecord {
+        let payload = format!(
+            r#"{{"bot_id": "alpha", "ticker": "BTCUSDT", "action": "{}", "order_size": "2",
+                "position_size": "1", "schema": "2", "timestamp": "1736510400",
+                "alert_price": "{}"}}"#,
+            action, alert_price
+        );
+        let alert = Alert::parse("tradingview", &payload).unwrap();
+        let order = Order::from_alert(alert, "tv").unwrap();
+        let result = ExecutionResult {
+            order_id: Some("1".to_string()),
+            bot_id: "alpha".to_string(),
+            listener_id: "tv".to_string(),
+            exchange: "binance".to_string(),
+            account: None,
+            action: action.to_string(),
+            symbol: "BTCUSDT".to_string(),
+            order_size: "2".to_string(),
+            status: ExecutionStatus::Executed,
+            message: "order accepted by the exchange".to_string(),
+            report: Some(ExecutionReport {
+                filled_quantity: Some(dec!(2)),
+                average_price: fill_price,
+                ..Default::default()
+            }),
+            duplicate: false,
+            error_code: None,
+            warnings: Vec::new(),
+            latency_ms: None,
+            finished_at: Utc::now(),
+        };
+        TradeRecord {
+            result,
+            order: Some(order),
+        }
+    }
+
+    #[test]
+    fn slippage_is_measured_against_the_alert_price() {
+        let mut report = QualityReport::default();
+        // A buy 10 above and a sell 5 above the alert price of 1000
+        report.record(&trade("buy", "1000", Some(dec!(1010))));
+        report.record(&trade("sell", "1000", Some(dec!(1005))));
+        report.record(&trade("buy", "1000", None));
+        let mut imported = trade("buy", "1000", Some(dec!(2000)));
+        imported.order = None;
+        report.record(&imported);
+
+        let listeners = report.listeners("alpha");
+        assert_eq!(
+            listeners,
+            vec![ExecutionQuality {
+                bot_id: "alpha".to_string(),
+                listener_id: "tv".to_string(),
+                trades: 3,
+                measured: 2,
+                slippage_bps: Some(dec!(25)),
+                worst_slippage_bps: Some(dec!(100)),
+                slippage_cost: dec!(10),
+            }]
+        );
+    }
+}
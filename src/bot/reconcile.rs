@@ -0,0 +1,416 @@
+// src/bot/reconcile.rs
+//! # Startup Reconciliation
+//!
+//! After a restart the local view of what was sent to exchanges may be stale. Once the server
+//! is up, every bot whose exchange adapter is available is asked for its open orders and
+//! recent fills, which are compared with the orders the [journal](crate::bot::state::journal)
+//! still lists as submitted:
+//!
+//! - a submitted order that was filled is confirmed and leaves the journal;
+//! - anything the two sides disagree on is flagged as a [`Discrepancy`] — on the bot's
+//!   timeline, in `GET /admin/info` and as an operator notification — and left for a human to
+//!   resolve rather than "fixed" from either side.
+//!
+//! Observer bots trade elsewhere, so nothing they do is a discrepancy: their fills are put on
+//! their timelines instead, once each.
+use crate::app_config::{NotificationConfig, ReconciliationConfig};
+use crate::app_state::AppState;
+use crate::bot::api::ServerInfo;
+use crate::bot::model::Bot;
+use crate::bot::notify::{self, Notification};
+use crate::bot::secrets::Secrets;
+use crate::bot::state::events::EventKind;
+use crate::bot::state::journal::JournalEntry;
+use crate::exchange::prices::PriceCache;
+use crate::exchange::{self, Fill, OpenOrder};
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::prelude::ToPrimitive;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Discrepancy {
+    /// Submitted by xtrade, but neither open nor filled on the exchange
+    MissingOnExchange { client_order_id: String },
+    /// Open on the exchange, but not submitted by xtrade (or already forgotten)
+    UnknownOpenOrder { order: OpenOrder },
+    /// Filled on the exchange, but not submitted by xtrade (or already confirmed)
+    UntrackedFill { fill: Fill },
+}
+
+impl fmt::Display for Discrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Discrepancy::MissingOnExchange { client_order_id } => write!(
+                f,
+                "order {} was submitted but the exchange does not know it",
+                client_order_id
+            ),
+            Discrepancy::UnknownOpenOrder { order } => write!(
+                f,
+                "open order {} ({} {} {} @ {}) is not tracked locally",
+                order.client_order_id, order.side, order.quantity, order.symbol, order.price
+            ),
+            Discrepancy::UntrackedFill { fill } => write!(
+                f,
+                "fill of {} ({} {} @ {}) is not tracked locally",
+                fill.client_order_id, fill.quantity, fill.symbol, fill.price
+            ),
+        }
+    }
+}
+
+/// Outcome for a single bot.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct BotReconciliation {
+    pub bot_id: String,
+    /// Why the bot could not be reconciled, if it could not
+    pub skipped: Option<String>,
+    /// Submitted orders the exchange reported as filled
+    pub confirmed: Vec<String>,
+    pub discrepancies: Vec<Discrepancy>,
+    /// Fills of an observer bot
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub observed: Vec<Fill>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ReconciliationReport {
+    pub finished_at: DateTime<Utc>,
+    pub bots: Vec<BotReconciliation>,
+}
+
+impl ReconciliationReport {
+    pub fn discrepancies(&self) -> usize {
+        self.bots.iter().map(|bot| bot.discrepancies.len()).sum()
+    }
+}
+
+impl fmt::Display for ReconciliationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Reconciliation: {} bot(s), {} discrepancies ({})",
+            self.bots.len(),
+            self.discrepancies(),
+            self.finished_at.to_rfc3339()
+        )?;
+        for bot in &self.bots {
+            if let Some(reason) = &bot.skipped {
+                write!(f, "\n  {}: skipped, {}", bot.bot_id, reason)?;
+            }
+            for discrepancy in &bot.discrepancies {
+                write!(f, "\n  {}: {}", bot.bot_id, discrepancy)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compare the orders xtrade submitted for `bot_id` with what the exchange reports.
+pub fn compare(
+    bot_id: &str,
+    submitted: &[JournalEntry],
+    open: &[OpenOrder],
+    fills: &[Fill],
+) -> BotReconciliation {
+    let submitted_ids: HashSet<&str> = submitted.iter().map(|entry| entry.id.as_str()).collect();
+    let open_ids: HashSet<&str> = open
+        .iter()
+        .map(|order| order.client_order_id.as_str())
+        .collect();
+    let filled_ids: HashSet<&str> = fills
+        .iter()
+        .map(|fill| fill.client_order_id.as_str())
+        .collect();
+
+    let mut result = BotReconciliation {
+        bot_id: bot_id.to_string(),
+        ..Default::default()
+    };
+    for entry in submitted {
+        if filled_ids.contains(entry.id.as_str()) && !open_ids.contains(entry.id.as_str()) {
+            result.confirmed.push(entry.id.clone());
+        } else if !open_ids.contains(entry.id.as_str()) {
+            result.discrepancies.push(Discrepancy::MissingOnExchange {
+                client_order_id: entry.id.clone(),
+            });
+        }
+    }
+    for order in open {
+        if !submitted_ids.contains(order.client_order_id.as_str()) {
+            result.discrepancies.push(Discrepancy::UnknownOpenOrder {
+                order: order.clone(),
+            });
+        }
+    }
+    for fill in fills {
+        if !submitted_ids.contains(fill.client_order_id.as_str()) {
+            result
+                .discrepancies
+                .push(Discrepancy::UntrackedFill { fill: fill.clone() });
+        }
+    }
+    result
+}
+
+/// Ask the exchange of `bot` about it and compare the answer with `submitted`.
+async fn reconcile_bot(
+    bot: &Bot,
+    submitted: &[JournalEntry],
+    since: DateTime<Utc>,
+    client: &reqwest::Client,
+    prices: &PriceCache,
+) -> BotReconciliation {
+    let skipped = |reason: String| BotReconciliation {
+        bot_id: bot.bot_id.clone(),
+        skipped: Some(reason),
+        ..Default::default()
+    };
+    let Some(venue) = exchange::get_exchange(bot) else {
+        return skipped(format!("no adapter for exchange '{}'", bot.exchange));
+    };
+    let bot = &match Secrets::default().resolve_bot(bot) {
+        Ok(bot) => bot,
+        Err(e) => return skipped(e),
+    };
+
+    let open = match venue.open_orders(bot, client).await {
+        Ok(Some(open)) => open,
+        Ok(None) => return skipped(format!("{} cannot list open orders", bot.exchange)),
+        Err(e) => return skipped(format!("failed to fetch open orders: {}", e)),
+    };
+    let fills = match venue.recent_fills(bot, client, since).await {
+        Ok(Some(fills)) => fills,
+        Ok(None) => return skipped(format!("{} cannot list fills", bot.exchange)),
+        Err(e) => return skipped(format!("failed to fetch fills: {}", e)),
+    };
+    for fill in &fills {
+        if let Some(price) = fill.price.to_f64() {
+            prices.record(&fill.symbol, price, fill.timestamp);
+        }
+    }
+    if bot.observer {
+        return BotReconciliation {
+            bot_id: bot.bot_id.clone(),
+            observed: fills,
+            ..Default::default()
+        };
+    }
+    compare(&bot.bot_id, submitted, &open, &fills)
+}
+
+/// Confirm filled orders and put discrepancies, and fills of observer bots not seen before,
+/// on the bots' timelines.
+///
+/// Returns when the results were recorded.
+fn apply(state: &mut AppState, results: &[BotReconciliation]) -> DateTime<Utc> {
+    let finished_at = state.providers.clock.now();
+    let mut changed = false;
+    for result in results {
+        let timeline = if result.observed.is_empty() {
+            Vec::new()
+        } else {
+            state.events.page(&result.bot_id, None, None, usize::MAX).0
+        };
+        for fill in &result.observed {
+            let details = serde_json::to_value(fill).ok();
+            if timeline
+                .iter()
+                .any(|event| event.kind == EventKind::Trade && event.details == details)
+            {
+                continue;
+            }
+            state.events.record(
+                finished_at,
+                &result.bot_id,
+                EventKind::Trade,
+                format!(
+                    "Observed fill of {} {} @ {}",
+                    fill.quantity, fill.symbol, fill.price
+                ),
+                details,
+            );
+            changed = true;
+        }
+        for id in &result.confirmed {
+            changed |= state.journal.confirm(id);
+        }
+        for discrepancy in &result.discrepancies {
+            log::warn!("Reconciliation of bot '{}': {}", result.bot_id, discrepancy);
+            state.events.record(
+                finished_at,
+                &result.bot_id,
+                EventKind::Error,
+                format!("Reconciliation: {}", discrepancy),
+                serde_json::to_value(discrepancy).ok(),
+            );
+            changed = true;
+        }
+    }
+    if changed {
+        if let Err(e) = state.save::<PathBuf>(None) {
+            log::error!("Failed to save reconciliation results: {}", e);
+        }
+    }
+    finished_at
+}
+
+/// Reconcile every bot, apply confirmations and flag discrepancies.
+pub async fn run(
+    app_state: Arc<Mutex<AppState>>,
+    server_info: Arc<ServerInfo>,
+    prices: Arc<PriceCache>,
+    config: ReconciliationConfig,
+    notifications: NotificationConfig,
+) {
+    let Ok((now, mut bots, submitted)) = app_state.lock().map(|state| {
+        let bots: Vec<Bot> = state.bots.values().cloned().collect();
+        (
+            state.providers.clock.now(),
+            bots,
+            state.journal.submitted.clone(),
+        )
+    }) else {
+        log::error!("Failed to acquire lock on AppState; skipping reconciliation.");
+        return;
+    };
+    bots.sort_by(|a, b| a.bot_id.cmp(&b.bot_id));
+
+    // Look back far enough to see every order still awaiting confirmation
+    let since = submitted
+        .iter()
+        .map(|entry| entry.recorded_at)
+        .chain([now - Duration::hours(config.lookback_hours as i64)])
+        .min()
+        .unwrap_or(now);
+
+    let client = reqwest::Client::new();
+    let mut results = Vec::with_capacity(bots.len());
+    for bot in &bots {
+        let orders: Vec<JournalEntry> = submitted
+            .iter()
+            .filter(|entry| entry.bot_id == bot.bot_id)
+            .cloned()
+            .collect();
+        results.push(reconcile_bot(bot, &orders, since, &client, &prices).await);
+    }
+
+    let Ok(finished_at) = app_state
+        .lock()
+        .map(|mut state| apply(&mut state, &results))
+    else {
+        log::error!("Failed to acquire lock on AppState; reconciliation results are lost.");
+        return;
+    };
+
+    let report = ReconciliationReport {
+        finished_at,
+        bots: results,
+    };
+    log::info!("{}", report);
+    if report.discrepancies() > 0 {
+        let notification = Notification::new(
+            "xtrade found discrepancies with an exchange",
+            &format!(
+                "Startup reconciliation found {} discrepancies; see GET /admin/info.",
+                report.discrepancies()
+            ),
+        );
+        notify::send(&notifications, &notification).await;
+    }
+    match server_info.reconciliation.lock() {
+        Ok(mut slot) => *slot = Some(report),
+        Err(_) => log::error!("Failed to publish the reconciliation report."),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app_config::AppConfig;
+    use rust_decimal::dec;
+    use tempfile::tempdir;
+
+    fn entry(id: &str) -> JournalEntry {
+        JournalEntry {
+            id: id.to_string(),
+            bot_id: "alpha".to_string(),
+            recorded_at: Utc::now(),
+            summary: "buy BTCUSDT".to_string(),
+        }
+    }
+
+    fn open(id: &str) -> OpenOrder {
+        OpenOrder {
+            order_id: id.to_string(),
+            client_order_id: id.to_string(),
+            symbol: "BTCUSDT".to_string(),
+            side: "buy".to_string(),
+            price: dec!(100),
+            quantity: dec!(1),
+        }
+    }
+
+    fn fill(id: &str) -> Fill {
+        Fill {
+            client_order_id: id.to_string(),
+            symbol: "BTCUSDT".to_string(),
+            price: dec!(100),
+            quantity: dec!(1),
+            timestamp: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn compare_confirms_fills_and_flags_the_rest() {
+        let submitted = [entry("filled"), entry("resting"), entry("lost")];
+        let fills = [fill("filled"), fill("manual")];
+        let result = compare(
+            "alpha",
+            &submitted,
+            &[open("resting"), open("stranger")],
+            &fills,
+        );
+
+        assert_eq!(result.confirmed, vec!["filled".to_string()]);
+        assert_eq!(
+            result.discrepancies,
+            vec![
+                Discrepancy::MissingOnExchange {
+                    client_order_id: "lost".to_string()
+                },
+                Discrepancy::UnknownOpenOrder {
+                    order: open("stranger")
+                },
+                Discrepancy::UntrackedFill {
+                    fill: fills[1].clone()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn observed_fills_reach_the_timeline_once() {
+        let dir = tempdir().unwrap();
+        let mut config = AppConfig::default();
+        config.api_server.state_file = dir.path().join("state.json");
+        let mut state = AppState::load(config).unwrap();
+        let results = [BotReconciliation {
+            bot_id: "watcher".to_string(),
+            observed: vec![fill("manual")],
+            ..Default::default()
+        }];
+
+        apply(&mut state, &results);
+        apply(&mut state, &results);
+        let (timeline, _) = state.events.page("watcher", None, None, 10);
+        assert_eq!(timeline.len(), 1);
+        assert_eq!(timeline[0].kind, EventKind::Trade);
+        assert!(timeline[0].summary.contains("1 BTCUSDT @ 100"));
+    }
+}
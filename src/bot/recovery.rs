@@ -0,0 +1,307 @@
+// src/bot/recovery.rs
+//! # Startup Recovery
+//!
+//! While the server runs it keeps a marker file next to the state file
+//! (`<state_file>.running`) and removes it when it stops without panicking. Finding the marker
+//! at startup means the previous run crashed or was killed; the server then builds a
+//! [`RecoveryReport`] from the persisted state — work left in the
+//! [journal](crate::bot::state::journal) and the time of the last successful save — and
+//! surfaces it on the console, through `GET /admin/info` and as an operator notification.
+//!
+//! During a [socket handoff](crate::bot::handoff) the old server is still running when the
+//! new one starts: a marker whose process is alive is taken over rather than reported, and a
+//! server only removes the marker while it is still its own.
+//!
+//! Offline commands look for the marker too: while a [server runs](running_server) against
+//! the state file, they refuse to edit it behind the server's back.
+use crate::app_state::AppState;
+use crate::bot::state::journal::JournalEntry;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// Left next to the state file for as long as a server uses it.
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct RunMarker {
+    pub pid: u32,
+    pub started_at: DateTime<Utc>,
+}
+
+/// What an unclean shutdown may have left unfinished.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct RecoveryReport {
+    pub detected_at: DateTime<Utc>,
+    /// Process of the run that did not shut down cleanly, if its marker was readable
+    pub previous_pid: Option<u32>,
+    pub previous_started_at: Option<DateTime<Utc>>,
+    /// Anything that happened after this was lost
+    pub last_successful_save: Option<DateTime<Utc>>,
+    /// Work accepted but never sent to an exchange
+    pub unflushed_queue_entries: Vec<JournalEntry>,
+    /// Orders sent to an exchange whose outcome is unknown; verify them on the exchange
+    pub unconfirmed_orders: Vec<JournalEntry>,
+}
+
+impl RecoveryReport {
+    fn new(state: &AppState, previous: Option<RunMarker>, now: DateTime<Utc>) -> Self {
+        Self {
+            detected_at: now,
+            previous_pid: previous.as_ref().map(|marker| marker.pid),
+            previous_started_at: previous.map(|marker| marker.started_at),
+            last_successful_save: state.saved_at,
+            unflushed_queue_entries: state.journal.queued.clone(),
+            unconfirmed_orders: state.journal.submitted.clone(),
+        }
+    }
+
+    /// One-line summary, used as the notification message.
+    pub fn summary(&self) -> String {
+        format!(
+            "Previous run did not shut down cleanly; last successful save: {}; {} unflushed \
+             queue entries; {} unconfirmed orders.",
+            format_time(self.last_successful_save),
+            self.unflushed_queue_entries.len(),
+            self.unconfirmed_orders.len()
+        )
+    }
+}
+
+impl fmt::Display for RecoveryReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rule = "=".repeat(72);
+        writeln!(f, "{}", rule)?;
+        writeln!(f, " RECOVERED FROM AN UNCLEAN SHUTDOWN")?;
+        writeln!(f, "{}", rule)?;
+        if let Some(pid) = self.previous_pid {
+            writeln!(
+                f,
+                " Previous run:            pid {} started {}",
+                pid,
+                format_time(self.previous_started_at)
+            )?;
+        }
+        writeln!(
+            f,
+            " Last successful save:    {}",
+            format_time(self.last_successful_save)
+        )?;
+        write_entries(f, "Unflushed queue entries", &self.unflushed_queue_entries)?;
+        write_entries(f, "Unconfirmed orders", &self.unconfirmed_orders)?;
+        if !self.unconfirmed_orders.is_empty() {
+            writeln!(f, " Check these orders on their exchange before resuming.")?;
+        }
+        write!(f, "{}", rule)
+    }
+}
+
+fn write_entries(f: &mut fmt::Formatter<'_>, label: &str, entries: &[JournalEntry]) -> fmt::Result {
+    writeln!(f, " {:<25}{}", format!("{}:", label), entries.len())?;
+    for entry in entries {
+        writeln!(
+            f,
+            "   - {} [{}] {} ({})",
+            entry.id,
+            entry.bot_id,
+            entry.summary,
+            entry.recorded_at.to_rfc3339()
+        )?;
+    }
+    Ok(())
+}
+
+fn format_time(time: Option<DateTime<Utc>>) -> String {
+    time.map_or_else(|| "never".to_string(), |time| time.to_rfc3339())
+}
+
+/// Path of the run marker belonging to `state_file`.
+pub fn marker_path(state_file: &Path) -> PathBuf {
+    let mut path = state_file.as_os_str().to_owned();
+    path.push(".running");
+    PathBuf::from(path)
+}
+
+/// Whether process `pid` is alive. Only known on Linux; elsewhere a marker is always
+/// assumed to be left behind by a crash.
+fn is_running(pid: u32) -> bool {
+    #[cfg(target_os = "linux")]
+    return pid != std::process::id() && Path::new("/proc").join(pid.to_string()).exists();
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = pid;
+        false
+    }
+}
+
+/// The marker of the server using `state_file`, if it is still running.
+pub fn running_server(state_file: &Path) -> Option<RunMarker> {
+    match read_marker(&marker_path(state_file)) {
+        Ok(Some(Some(marker))) if is_running(marker.pid) => Some(marker),
+        _ => None,
+    }
+}
+
+fn read_marker(path: &Path) -> std::io::Result<Option<Option<RunMarker>>> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(Some(serde_json::from_str(&content).ok())),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Removes the run marker when dropped, unless the thread is panicking or a newer server
+/// has taken the marker over.
+#[derive(Debug)]
+pub struct RunGuard {
+    state_file: PathBuf,
+}
+
+impl Drop for RunGuard {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            return;
+        }
+        let path = marker_path(&self.state_file);
+        if let Ok(Some(Some(marker))) = read_marker(&path) {
+            if marker.pid != std::process::id() {
+                return;
+            }
+        }
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => log::warn!(
+                "Failed to remove the run marker of {:?}: {}",
+                self.state_file,
+                e
+            ),
+            _ => {}
+        }
+    }
+}
+
+/// Mark `state`'s file as in use by this process until the returned guard is dropped.
+///
+/// Also returns a report if the previous run left its marker behind.
+pub fn begin_run(
+    state: &AppState,
+    now: DateTime<Utc>,
+) -> std::io::Result<(RunGuard, Option<RecoveryReport>)> {
+    let path = marker_path(&state.state_file);
+    let report = match read_marker(&path)? {
+        Some(Some(previous)) if is_running(previous.pid) => {
+            log::info!("Taking over from the running server (pid {})", previous.pid);
+            None
+        }
+        Some(previous) => Some(RecoveryReport::new(state, previous, now)),
+        None => None,
+    };
+
+    let marker = RunMarker {
+        pid: std::process::id(),
+        started_at: now,
+    };
+    fs::write(&path, serde_json::to_string(&marker)?)?;
+    let guard = RunGuard {
+        state_file: state.state_file.clone(),
+    };
+    Ok((guard, report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn only_unclean_shutdowns_are_reported() {
+        let temp_dir = tempdir().unwrap();
+        let mut state = AppState {
+            state_file: temp_dir.path().join("state.json"),
+            ..Default::default()
+        };
+        let now = Utc::now();
+
+        // First run, clean shutdown
+        let (guard, report) = begin_run(&state, now).unwrap();
+        assert_eq!(report, None);
+        drop(guard);
+        assert!(!marker_path(&state.state_file).exists());
+
+        // Second run crashes with an order in flight
+        let (guard, report) = begin_run(&state, now).unwrap();
+        assert_eq!(report, None);
+        std::mem::forget(guard);
+        state.saved_at = Some(now);
+        state.journal.submitted.push(JournalEntry {
+            id: "order-1".to_string(),
+            bot_id: "alpha".to_string(),
+            recorded_at: now,
+            summary: "buy BTCUSDT".to_string(),
+        });
+
+        let (_guard, report) = begin_run(&state, now).unwrap();
+        let report = report.expect("a recovery report");
+        assert_eq!(report.previous_pid, Some(std::process::id()));
+        assert_eq!(report.last_successful_save, Some(now));
+        assert!(report.unflushed_queue_entries.is_empty());
+        assert_eq!(report.unconfirmed_orders[0].id, "order-1");
+        assert!(report.to_string().contains("order-1"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn markers_of_running_servers_are_taken_over() {
+        let temp_dir = tempdir().unwrap();
+        let state = AppState {
+            state_file: temp_dir.path().join("state.json"),
+            ..Default::default()
+        };
+        let path = marker_path(&state.state_file);
+        let marker = |pid| {
+            serde_json::to_string(&RunMarker {
+                pid,
+                started_at: Utc::now(),
+            })
+            .unwrap()
+        };
+
+        // PID 1 is always alive: the old server is still draining
+        fs::write(&path, marker(1)).unwrap();
+        let (guard, report) = begin_run(&state, Utc::now()).unwrap();
+        assert_eq!(report, None);
+
+        // A newer server took over before this one stopped
+        fs::write(&path, marker(1)).unwrap();
+        drop(guard);
+        assert!(path.exists());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn only_live_servers_are_found_using_a_state_file() {
+        let temp_dir = tempdir().unwrap();
+        let state_file = temp_dir.path().join("state.json");
+        assert_eq!(running_server(&state_file), None);
+
+        let mut child = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        let marker = RunMarker {
+            pid: child.id(),
+            started_at: Utc::now(),
+        };
+        fs::write(
+            marker_path(&state_file),
+            serde_json::to_string(&marker).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(running_server(&state_file), Some(marker));
+
+        // Left behind by a crash
+        child.kill().unwrap();
+        child.wait().unwrap();
+        assert_eq!(running_server(&state_file), None);
+    }
+}
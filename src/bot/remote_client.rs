@@ -1,7 +1,20 @@
 use crate::bot::api::ApiResponse;
-use crate::bot::cli::Commands;
-use crate::bot::rest::{BotListView, BotView, ListenerListView, ListenerView, RestClient};
+use crate::bot::cli::{Commands, ReportCmds, StateCmds};
+use crate::bot::dispatch::ExecutionResult;
+use crate::bot::montecarlo;
+use crate::bot::rest::{
+    AdminInfoView, AlertListView, AlertSimulationView, AllocationView, BalanceListView,
+    BotListView, BotView, BulkView, CancelledOrderView, ExecutionQualityView, FillImportView,
+    ListenerListView, ListenerView, MonteCarloView, OpenOrderListView, PnlView, PositionListView,
+    ReadinessView, ReplayView, RestClient, SecretRotationView, StateCompactView, StateImportView,
+    StrategyReportView, SymbolMapView, TimelineView, TradeListView,
+};
+use crate::bot::state::export;
+use crate::bot::state::{BotStatsView, BotTimelineArgs, BotWatchArgs, BulkRequest};
+use crate::bot::ws::WsCommand;
+use crate::bot::ws_client::WsClient;
 use crate::errors::AppError;
+use crate::utils::progress;
 use reqwest::Response;
 use serde::de::DeserializeOwned;
 
@@ -11,6 +24,16 @@ pub async fn process_and_display_response<T>(
 ) -> Result<(), AppError>
 where
     T: DeserializeOwned + std::fmt::Display, // Ensure T can be deserialized and printed
+{
+    let data: T = parse_response(response).await?;
+    println!("{}", data); // Use the Display implementation of T
+    Ok(())
+}
+
+/// The `data` of a successful API response.
+pub async fn parse_response<T>(response: Response) -> Result<T, AppError>
+where
+    T: DeserializeOwned,
 {
     let status = response.status(); // Get the HTTP status code
     let body = response
@@ -24,11 +47,14 @@ where
         // Parse the body into ApiResponse<T>
         let api_response: ApiResponse<T> =
             serde_json::from_str(&body).map_err(AppError::JsonParseError)?;
+        // Warnings go to stderr, so they do not mix with output read by scripts
+        for warning in &api_response.warnings {
+            eprintln!("Warning: {}", warning);
+        }
 
         // Attempt to extract and display `data`
         if let Some(data) = api_response.data {
-            println!("{}", data); // Use the Display implementation of T
-            Ok(())
+            Ok(data)
         } else if let Some(error_message) = api_response.error {
             // Handle cases where `data` is missing but `error` is present
             Err(AppError::UnexpectedResponse(format!(
@@ -48,7 +74,23 @@ where
 
 /// Handle CLI commands in online mode
 pub async fn run(args: Commands, client: RestClient) -> Result<(), AppError> {
+    // Prefer a WebSocket session; servers without one are driven over REST
+    if let Some(command) = WsCommand::from_cli(&args).filter(|_| client.websocket()) {
+        match WsClient::connect(&client.api_url(), client.token()).await {
+            Ok(mut session) => return session.run(command).await,
+            Err(e) => log::debug!("WebSocket channel unavailable, using REST: {}", e),
+        }
+    }
+
     match args {
+        Commands::Healthcheck => {
+            process_and_display_response::<ReadinessView>(client.readyz().await?).await
+        }
+
+        Commands::AdminInfo => {
+            process_and_display_response::<AdminInfoView>(client.admin_info().await?).await
+        }
+
         Commands::AddBot(bot_insert_args) => {
             process_and_display_response::<BotView>(client.add_bot(bot_insert_args).await?).await
         }
@@ -68,6 +110,257 @@ pub async fn run(args: Commands, client: RestClient) -> Result<(), AppError> {
                 .await
         }
 
+        Commands::BotTimeline(timeline_args) => {
+            process_and_display_response::<TimelineView>(
+                client.get_bot_timeline(&timeline_args).await?,
+            )
+            .await
+        }
+
+        Commands::Watch(watch_args) => watch_over_rest(&client, watch_args).await,
+
+        Commands::Balances(bot_get_args) => {
+            process_and_display_response::<BalanceListView>(
+                client.get_bot_balances(&bot_get_args.bot_id).await?,
+            )
+            .await
+        }
+
+        Commands::Positions(bot_get_args) => {
+            process_and_display_response::<PositionListView>(
+                client.get_bot_positions(&bot_get_args.bot_id).await?,
+            )
+            .await
+        }
+
+        Commands::ListOrders(bot_get_args) => {
+            process_and_display_response::<OpenOrderListView>(
+                client.get_bot_orders(&bot_get_args.bot_id).await?,
+            )
+            .await
+        }
+
+        Commands::CancelOrder(order_cancel_args) => {
+            process_and_display_response::<CancelledOrderView>(
+                client.cancel_bot_order(&order_cancel_args).await?,
+            )
+            .await
+        }
+
+        Commands::ListTrades(bot_trades_args) => {
+            process_and_display_response::<TradeListView>(
+                client.get_bot_trades(&bot_trades_args).await?,
+            )
+            .await
+        }
+
+        Commands::Replay(replay_args) => {
+            let report = client.get_replay_report(&replay_args);
+            process_and_display_response::<ReplayView>(progress::track("replay", report).await?)
+                .await
+        }
+
+        Commands::Report {
+            report_command: ReportCmds::Montecarlo(montecarlo_args),
+        } => {
+            let report = client.get_montecarlo_report(&montecarlo_args);
+            let view: MonteCarloView =
+                parse_response(progress::track("montecarlo", report).await?).await?;
+            println!("{}", view);
+            if let Some(path) = &montecarlo_args.chart {
+                montecarlo::chart(&view)
+                    .and_then(|png| std::fs::write(path, png))
+                    .map_err(|e| {
+                        AppError::GeneralError(format!("Failed to write {}: {}", path.display(), e))
+                    })?;
+                println!("Chart written to {}", path.display());
+            }
+            Ok(())
+        }
+
+        Commands::Report {
+            report_command: ReportCmds::Statement(statement_args),
+        } => {
+            let response = client.get_bot_statement(&statement_args).await?;
+            let status = response.status();
+            let body = response.text().await.map_err(|e| {
+                AppError::ConnectionError(format!("Failed to read response body: {}", e))
+            })?;
+            if !status.is_success() {
+                return Err(AppError::HttpError(status.as_u16(), body));
+            }
+            match &statement_args.output {
+                Some(path) => {
+                    std::fs::write(path, body).map_err(|e| {
+                        AppError::GeneralError(format!("Failed to write {}: {}", path.display(), e))
+                    })?;
+                    println!("Statement written to {}", path.display());
+                }
+                None => print!("{}", body),
+            }
+            Ok(())
+        }
+
+        Commands::ListAlerts(alert_list_args) => {
+            process_and_display_response::<AlertListView>(
+                client.get_bot_alerts(&alert_list_args).await?,
+            )
+            .await
+        }
+
+        Commands::ReplayAlert(alert_replay_args) => {
+            process_and_display_response::<ExecutionResult>(
+                client.replay_alert(&alert_replay_args).await?,
+            )
+            .await
+        }
+
+        Commands::SimulateAlert(alert_simulate_args) => {
+            let payload = std::fs::read_to_string(&alert_simulate_args.file).map_err(|e| {
+                AppError::InvalidInput(format!(
+                    "Failed to read {}: {}",
+                    alert_simulate_args.file.display(),
+                    e
+                ))
+            })?;
+            process_and_display_response::<AlertSimulationView>(
+                client.test_fire(&alert_simulate_args, payload).await?,
+            )
+            .await
+        }
+
+        Commands::Pnl(bot_get_args) => {
+            process_and_display_response::<PnlView>(client.get_bot_pnl(&bot_get_args.bot_id).await?)
+                .await
+        }
+
+        Commands::BotStats(bot_get_args) => {
+            process_and_display_response::<BotStatsView>(
+                client.get_bot_stats(&bot_get_args.bot_id).await?,
+            )
+            .await
+        }
+
+        Commands::ImportFills(fill_import_args) => {
+            let csv = std::fs::read_to_string(&fill_import_args.file).map_err(|e| {
+                AppError::InvalidInput(format!(
+                    "Failed to read {}: {}",
+                    fill_import_args.file.display(),
+                    e
+                ))
+            })?;
+            let import = client.import_bot_fills(&fill_import_args.bot_id, csv);
+            process_and_display_response::<FillImportView>(
+                progress::track("import-fills", import).await?,
+            )
+            .await
+        }
+
+        Commands::Export(export_args) => {
+            let response = client.export_state(&export_args).await?;
+            let status = response.status();
+            let body = response.text().await.map_err(|e| {
+                AppError::ConnectionError(format!("Failed to read response body: {}", e))
+            })?;
+            if !status.is_success() {
+                return Err(AppError::HttpError(status.as_u16(), body));
+            }
+            match &export_args.output {
+                Some(path) => {
+                    std::fs::write(path, body).map_err(|e| {
+                        AppError::GeneralError(format!("Failed to write {}: {}", path.display(), e))
+                    })?;
+                    println!("Export written to {}", path.display());
+                }
+                None => print!("{}", body),
+            }
+            Ok(())
+        }
+
+        Commands::Import(import_args) => {
+            let content = std::fs::read_to_string(&import_args.file).map_err(|e| {
+                AppError::InvalidInput(format!(
+                    "Failed to read {}: {}",
+                    import_args.file.display(),
+                    e
+                ))
+            })?;
+            let import = client.import_state(&import_args, import_args.file_format(), content);
+            process_and_display_response::<StateImportView>(
+                progress::track("import", import).await?,
+            )
+            .await
+        }
+
+        Commands::Apply(apply_args) => {
+            let file = apply_args.file.display();
+            let content = std::fs::read_to_string(&apply_args.file)
+                .map_err(|e| AppError::InvalidInput(format!("Failed to read {}: {}", file, e)))?;
+            let format = apply_args.file_format();
+            let request: BulkRequest = export::parse_document(&content, format).map_err(|e| {
+                AppError::InvalidInput(format!("Invalid {} manifest {}: {}", format, file, e))
+            })?;
+            process_and_display_response::<BulkView>(
+                progress::track("apply", client.bulk(&request)).await?,
+            )
+            .await
+        }
+
+        Commands::State {
+            state_command: StateCmds::Compact(compact_args),
+        } => {
+            process_and_display_response::<StateCompactView>(
+                progress::track("compact", client.compact_state(&compact_args)).await?,
+            )
+            .await
+        }
+
+        Commands::ExecutionQuality(execution_quality_args) => {
+            process_and_display_response::<ExecutionQualityView>(
+                client
+                    .get_execution_quality(&execution_quality_args)
+                    .await?,
+            )
+            .await
+        }
+
+        Commands::StrategyReport(strategy_report_args) => {
+            process_and_display_response::<StrategyReportView>(
+                client.get_strategy_report(&strategy_report_args).await?,
+            )
+            .await
+        }
+
+        Commands::AllocationReport(allocation_report_args) => {
+            process_and_display_response::<AllocationView>(
+                client
+                    .get_allocation_report(&allocation_report_args)
+                    .await?,
+            )
+            .await
+        }
+
+        Commands::ListSymbols(bot_get_args) => {
+            process_and_display_response::<SymbolMapView>(
+                client.get_bot_symbols(&bot_get_args.bot_id).await?,
+            )
+            .await
+        }
+
+        Commands::MapSymbol(symbol_map_args) => {
+            process_and_display_response::<SymbolMapView>(
+                client.map_bot_symbol(&symbol_map_args).await?,
+            )
+            .await
+        }
+
+        Commands::UnmapSymbol(symbol_unmap_args) => {
+            process_and_display_response::<SymbolMapView>(
+                client.unmap_bot_symbol(&symbol_unmap_args).await?,
+            )
+            .await
+        }
+
         Commands::UpdateBot(bot_update_args) => {
             process_and_display_response::<BotView>(
                 client
@@ -96,6 +389,19 @@ pub async fn run(args: Commands, client: RestClient) -> Result<(), AppError> {
             .await
         }
 
+        Commands::ListListeners(listener_list_args) if listener_list_args.all_bots => {
+            process_and_display_response::<ListenerListView>(
+                client
+                    .get_all_listeners(
+                        listener_list_args.page,
+                        listener_list_args.limit,
+                        Some(listener_list_args),
+                    )
+                    .await?,
+            )
+            .await
+        }
+
         Commands::ListListeners(listener_list_args) => {
             process_and_display_response::<ListenerListView>(
                 client
@@ -132,6 +438,13 @@ pub async fn run(args: Commands, client: RestClient) -> Result<(), AppError> {
             .await
         }
 
+        Commands::RotateSecret(listener_rotate_args) => {
+            process_and_display_response::<SecretRotationView>(
+                client.rotate_listener_secret(&listener_rotate_args).await?,
+            )
+            .await
+        }
+
         Commands::DeleteListener(listener_delete_args) => {
             process_and_display_response::<ListenerView>(
                 client
@@ -161,3 +474,40 @@ pub async fn run(args: Commands, client: RestClient) -> Result<(), AppError> {
         )),
     }
 }
+
+/// Follow a bot's timeline by polling it, for servers without the WebSocket channel.
+async fn watch_over_rest(client: &RestClient, args: BotWatchArgs) -> Result<(), AppError> {
+    let mut last_seq = None;
+    loop {
+        let response = client
+            .get_bot_timeline(&BotTimelineArgs::new(&args.bot_id))
+            .await?;
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(AppError::HttpError(status.as_u16(), body));
+        }
+        let timeline = serde_json::from_str::<ApiResponse<TimelineView>>(&body)?
+            .data
+            .unwrap_or(TimelineView {
+                events: Vec::new(),
+                next_cursor: None,
+            });
+
+        let newest = timeline.events.first().map(|event| event.seq);
+        // Only events that happen after we started watching are shown
+        if let Some(last) = last_seq {
+            for event in timeline
+                .events
+                .iter()
+                .rev()
+                .filter(|event| event.seq > last)
+            {
+                println!("{}", event);
+            }
+        }
+        last_seq = newest.max(last_seq).or(Some(0));
+
+        tokio::time::sleep(args.interval()).await;
+    }
+}
@@ -0,0 +1,472 @@
+// src/bot/replay.rs
+//! # Alert Replay
+//!
+//! Runs the alerts a bot received on one day through the [paper engine](crate::exchange::paper)
+//! again, at the prices of the time, to show what they would have made had the bot's risk
+//! limits been different. The alerts come from the state's alert log and, for those
+//! [compaction](crate::bot::compaction) pruned since, its archive. Nothing is placed, logged
+//! or counted.
+//!
+//! Each order is parsed again from its alert's payload and fills at the price the alert
+//! gave, or else the price it filled at then: moved by the slippage for market orders, at
+//! their limit for limit orders, which are all the paper engine places. Alerts refused when
+//! they arrived are left out, and so are alerts replayed since, as their replay stands for
+//! them.
+//!
+//! The day is replayed twice from flat positions: with the bot's current limits, and with
+//! those the counterfactual asks for instead, such as another budget or fewer trades in the
+//! day. Budgets cut orders down as [allocation](crate::bot::allocation) does, against the
+//! positions of the replay valued at the last prices replayed.
+use crate::app_config::PrecisionConfig;
+use crate::app_state::AppState;
+use crate::bot::allocation::{self, Budget};
+use crate::bot::compaction;
+use crate::bot::dispatch::{self, Order};
+use crate::bot::model::Bot;
+use crate::bot::state::alerts::AlertRecord;
+use crate::bot::state::paper::{PaperFill, PaperPosition};
+use crate::bot::state::{
+    BotRegistry, ReplayArgs, ReplayFill, ReplayLimits, ReplayOutcome, ReplayView, ReplayedAlert,
+};
+use crate::errors::AppError;
+use crate::exchange::paper::{self, SimulatedFill, DEFAULT_SLIPPAGE_BPS};
+use crate::exchange::SymbolMap;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+
+/// The alerts of a bot's day the state still logs, and what replaying them takes from it.
+pub struct Day {
+    bot: Bot,
+    tenant: Option<String>,
+    alerts: Vec<AlertRecord>,
+    archive: PathBuf,
+    symbol_map: SymbolMap,
+    precision: PrecisionConfig,
+    capital: Option<Decimal>,
+    max_trades: Option<u32>,
+}
+
+/// The day `args` ask to replay of the bot `args.bot_id` of `tenant`, taken from `state` so
+/// that it can be replayed without it.
+pub fn day(state: &mut AppState, tenant: Option<&str>, args: &ReplayArgs) -> Result<Day, AppError> {
+    if args
+        .fill_pct
+        .is_some_and(|pct| pct <= Decimal::ZERO || pct > Decimal::ONE_HUNDRED)
+    {
+        return Err(AppError::InvalidInput(
+            "fill_pct must be above 0 and at most 100".to_string(),
+        ));
+    }
+    for (name, value) in [
+        ("slippage_bps", args.slippage_bps),
+        ("trading_fee", args.trading_fee),
+    ] {
+        if value.is_some_and(|value| value.is_sign_negative()) {
+            return Err(AppError::InvalidInput(format!(
+                "{} cannot be negative",
+                name
+            )));
+        }
+    }
+    let capital = state.allocation.capital;
+    if matches!(args.budget, Some(Budget::Percent(_))) && capital.is_none() {
+        return Err(AppError::InvalidInput(
+            "a budget in percent needs allocation.capital".to_string(),
+        ));
+    }
+    let bot = state.scoped(tenant).get_bot_ref(&args.bot_id)?.clone();
+    Ok(Day {
+        alerts: state.alerts.received_on(tenant, &args.bot_id, args.date),
+        tenant: tenant.map(str::to_string),
+        archive: compaction::archive_path(&state.state_file),
+        symbol_map: state.symbol_map.clone(),
+        precision: state.precision.clone(),
+        max_trades: state.quotas.limits_for(tenant).max_trades_per_day,
+        capital,
+        bot,
+    })
+}
+
+impl Day {
+    /// Replay the day, with what the archive kept of it, with the bot's current limits and
+    /// with those `args` ask for.
+    pub fn replay(self, args: &ReplayArgs) -> Result<ReplayView, AppError> {
+        let logged: HashSet<String> = self.alerts.iter().map(|alert| alert.id.clone()).collect();
+        let archived: Vec<AlertRecord> = compaction::archived_alerts(&self.archive)
+            .map_err(|e| {
+                AppError::InternalServerError(format!(
+                    "Failed to read archive {:?}: {}",
+                    self.archive, e
+                ))
+            })?
+            .into_iter()
+            .filter(|alert| alert.bot_id == args.bot_id && alert.tenant == self.tenant)
+            .filter(|alert| alert.received_at.date_naive() == args.date)
+            .filter(|alert| !logged.contains(&alert.id))
+            .collect();
+
+        let mut alerts = self.alerts.clone();
+        alerts.extend(archived);
+        alerts.sort_by_key(|alert| alert.received_at);
+        let replayed: HashSet<String> = (alerts.iter())
+            .filter_map(|alert| alert.replay_of.clone())
+            .collect();
+        alerts.retain(|alert| alert.error.is_none() && !replayed.contains(&alert.id));
+
+        let mut what_if = self.bot.clone();
+        what_if.paper_slippage_bps = args.slippage_bps.or(what_if.paper_slippage_bps);
+        what_if.paper_fills.fill_pct = args.fill_pct.or(what_if.paper_fills.fill_pct);
+        what_if.trading_fee = args.trading_fee.or(what_if.trading_fee);
+        let mut current = Run::new(&self.bot, self.bot.budget, self.capital, self.max_trades);
+        let mut counterfactual = Run::new(
+            &what_if,
+            args.budget.or(self.bot.budget),
+            self.capital,
+            args.max_trades.or(self.max_trades),
+        );
+
+        let mut view = ReplayView {
+            bot_id: args.bot_id.clone(),
+            date: args.date,
+            archived: (alerts.iter())
+                .filter(|alert| !logged.contains(&alert.id))
+                .count(),
+            alerts: Vec::with_capacity(alerts.len()),
+            current: ReplayOutcome::default(),
+            counterfactual: ReplayOutcome::default(),
+        };
+        for record in &alerts {
+            let mut alert = ReplayedAlert {
+                alert_id: record.id.clone(),
+                received_at: record.received_at,
+                order: None,
+                price: None,
+                logged: record.status(),
+                error: None,
+                current: None,
+                counterfactual: None,
+            };
+            match self.order(record) {
+                Ok((order, price)) => {
+                    let at = record.received_at;
+                    alert.order = Some(order.summary());
+                    alert.price = Some(price);
+                    alert.current = Some(current.fill(&order, price, &self.precision, at));
+                    alert.counterfactual =
+                        Some(counterfactual.fill(&order, price, &self.precision, at));
+                }
+                Err(e) => alert.error = Some(e),
+            }
+            view.alerts.push(alert);
+        }
+        view.current = current.outcome();
+        view.counterfactual = counterfactual.outcome();
+        Ok(view)
+    }
+
+    /// The order `record` asked for and the price it is replayed at, or why it cannot be.
+    fn order(&self, record: &AlertRecord) -> Result<(Order, Decimal), String> {
+        let listener = (self.bot.listeners.get(&record.listener_id))
+            .ok_or_else(|| format!("listener '{}' no longer exists", record.listener_id))?;
+        let order = dispatch::recorded_order(
+            record,
+            &listener.service.to_lowercase(),
+            &self.bot.symbol_map,
+            &self.symbol_map,
+            &self.precision,
+        )
+        .map_err(|e| e.to_string())?;
+        order.request().market_or_limit("Paper trading")?;
+        let filled_at = (record.result.as_ref())
+            .and_then(|result| result.report.as_ref())
+            .and_then(|report| report.average_price);
+        let price = (order.alert_price())
+            .or(filled_at)
+            .or_else(|| order.request().prices().first().copied())
+            .ok_or("no price was recorded for the alert")?;
+        Ok((order, price))
+    }
+}
+
+/// One replay of the day: positions from flat, under some limits.
+struct Run<'a> {
+    bot: &'a Bot,
+    limits: ReplayLimits,
+    positions: BTreeMap<String, PaperPosition>,
+    /// Last price replayed, by symbol
+    prices: BTreeMap<String, Decimal>,
+    trades: u64,
+    refused: u64,
+    cut: u64,
+}
+
+impl<'a> Run<'a> {
+    fn new(
+        bot: &'a Bot,
+        budget: Option<Budget>,
+        capital: Option<Decimal>,
+        max_trades: Option<u32>,
+    ) -> Self {
+        Self {
+            bot,
+            limits: ReplayLimits {
+                budget,
+                budget_amount: budget.and_then(|budget| budget.amount(capital)),
+                max_trades,
+                slippage_bps: bot.paper_slippage_bps.unwrap_or(DEFAULT_SLIPPAGE_BPS),
+                fill_pct: bot.paper_fills.fill_pct,
+                trading_fee: bot.trading_fee.unwrap_or_default(),
+            },
+            positions: BTreeMap::new(),
+            prices: BTreeMap::new(),
+            trades: 0,
+            refused: 0,
+            cut: 0,
+        }
+    }
+
+    /// Value of the open positions at the last prices replayed.
+    fn exposure(&self) -> Decimal {
+        (self.positions.iter())
+            .map(|(symbol, position)| position.quantity.abs() * self.prices[symbol])
+            .sum()
+    }
+
+    /// Fill `order`, sent at `at` when its symbol was at `price`, as far as the limits let it.
+    fn fill(
+        &mut self,
+        order: &Order,
+        price: Decimal,
+        precision: &PrecisionConfig,
+        at: DateTime<Utc>,
+    ) -> ReplayFill {
+        let symbol = order.symbol().to_string();
+        self.prices.insert(symbol.clone(), price);
+        let refuse = |run: &mut Self, note: &str| {
+            run.refused += 1;
+            ReplayFill {
+                note: Some(note.to_string()),
+                ..Default::default()
+            }
+        };
+        if (self.limits.max_trades).is_some_and(|max| self.trades >= u64::from(max)) {
+            return refuse(self, "trade limit reached");
+        }
+
+        let side = order.side();
+        let mut quantity = order.quantity();
+        let mut note = None;
+        if let Some(budget) = self.limits.budget_amount {
+            let position = (self.positions.get(&symbol)).map_or(Decimal::ZERO, |p| p.quantity);
+            let fit = allocation::fit(position, side, quantity, price, self.exposure(), budget);
+            if fit < quantity {
+                let fit = precision.round_quantity(&symbol, fit);
+                if fit.is_zero() {
+                    return refuse(self, "budget exhausted");
+                }
+                self.cut += 1;
+                note = Some(format!("cut from {} by the budget", quantity.normalize()));
+                quantity = fit;
+            }
+        }
+
+        let filled_at = match order.request().market_or_limit("Paper trading") {
+            Ok(Some((limit, _))) => limit,
+            _ => paper::slipped(price, side.is_buy(), self.bot),
+        };
+        let fill = SimulatedFill::at(filled_at, quantity, self.bot);
+        self.positions
+            .entry(symbol.clone())
+            .or_default()
+            .apply(&PaperFill {
+                symbol,
+                quantity: if side.is_buy() {
+                    fill.quantity
+                } else {
+                    -fill.quantity
+                },
+                price: fill.price,
+                fee: fill.fee,
+                timestamp: at,
+            });
+        self.trades += 1;
+        ReplayFill {
+            filled: fill.quantity,
+            price: Some(fill.price),
+            note,
+        }
+    }
+
+    fn outcome(self) -> ReplayOutcome {
+        let positions = self.positions.iter();
+        let realized_pnl = positions.clone().map(|(_, p)| p.realized_pnl).sum();
+        let fees = positions.clone().map(|(_, p)| p.fees).sum();
+        ReplayOutcome {
+            trades: self.trades,
+            refused: self.refused,
+            cut: self.cut,
+            realized_pnl,
+            fees,
+            net_pnl: realized_pnl - fees,
+            unrealized_pnl: positions
+                .map(|(symbol, p)| p.unrealized_pnl(self.prices[symbol]))
+                .sum(),
+            exposure: self.exposure(),
+            limits: self.limits,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app_config::AppConfig;
+    use crate::bot::dispatch::{ExecutionResult, ExecutionStatus};
+    use crate::bot::state::{BotInsertArgs, ListenerInsertArgs, StateCompactArgs};
+    use crate::exchange::ExecutionReport;
+    use chrono::{NaiveDate, TimeZone};
+    use rust_decimal::dec;
+    use tempfile::tempdir;
+
+    fn alert(id: &str, hour: u32, action: &str, size: &str, price: &str) -> AlertRecord {
+        let price = match price {
+            "" => String::new(),
+            price => format!(r#", "alert_price": "{}""#, price),
+        };
+        let payload = format!(
+            r#"{{"bot_id": "alpha", "ticker": "BTCUSDT", "action": "{}", "order_size": "{}",
+                "position_size": "1", "schema": "2", "timestamp": "2025-01-10T00:00:00Z"{}}}"#,
+            action, size, price
+        );
+        AlertRecord {
+            id: id.to_string(),
+            received_at: Utc.with_ymd_and_hms(2025, 1, 10, hour, 0, 0).unwrap(),
+            bot_id: "alpha".to_string(),
+            listener_id: "tv".to_string(),
+            tenant: None,
+            payload,
+            replay_of: None,
+            error: None,
+            result: None,
+        }
+    }
+
+    #[test]
+    fn alerts_of_a_day_are_replayed_with_other_limits() {
+        let dir = tempdir().unwrap();
+        let mut config = AppConfig::default();
+        config.api_server.state_file = dir.path().join("state.json");
+        let mut state = AppState::load(config).unwrap();
+        state
+            .add_bot(
+                BotInsertArgs::new("Alpha".to_string(), "binance".to_string())
+                    .bot_id(Some("alpha".to_string()))
+                    .paper_slippage_bps(Some(Decimal::ZERO)),
+            )
+            .unwrap();
+        state
+            .add_listener(
+                ListenerInsertArgs::new("alpha", "TradingView").listener_id(Some("tv".to_string())),
+            )
+            .unwrap();
+
+        // The morning went to the archive; its buy filled at 101 then
+        let mut buy = alert("buy", 9, "BUY", "2", "");
+        buy.result = Some(ExecutionResult {
+            order_id: None,
+            bot_id: "alpha".to_string(),
+            listener_id: "tv".to_string(),
+            exchange: "binance".to_string(),
+            account: None,
+            action: "buy".to_string(),
+            symbol: "BTCUSDT".to_string(),
+            order_size: "2".to_string(),
+            status: ExecutionStatus::Executed,
+            message: String::new(),
+            report: Some(ExecutionReport {
+                average_price: Some(dec!(101)),
+                ..ExecutionReport::acknowledged(buy.received_at)
+            }),
+            duplicate: false,
+            error_code: None,
+            warnings: Vec::new(),
+            latency_ms: None,
+            finished_at: buy.received_at,
+        });
+        state.alerts.record(buy);
+        let args = StateCompactArgs {
+            alerts: Some("1d".parse().unwrap()),
+            archive: true,
+            ..Default::default()
+        };
+        let policy = compaction::policy(&state.retention, &args);
+        compaction::compact(&mut state, &policy).unwrap();
+        assert!(state.alerts.is_empty());
+
+        let mut refused = alert("refused", 10, "BUY", "5", "100");
+        refused.error = Some("stale".to_string());
+        let failed = alert("failed", 11, "BUY", "1", "110");
+        let mut replay = alert("replay", 12, "BUY", "1", "110");
+        replay.replay_of = Some("failed".to_string());
+        let mut tomorrow = alert("tomorrow", 9, "SELL", "1", "90");
+        tomorrow.received_at += chrono::Duration::days(1);
+        for record in [
+            refused,
+            failed,
+            replay,
+            alert("sell", 14, "SELL", "3", "120"),
+            tomorrow,
+        ] {
+            state.alerts.record(record);
+        }
+
+        let args = ReplayArgs {
+            bot_id: "alpha".to_string(),
+            date: NaiveDate::from_ymd_opt(2025, 1, 10).unwrap(),
+            budget: Some("210".parse().unwrap()),
+            max_trades: None,
+            slippage_bps: None,
+            fill_pct: None,
+            trading_fee: Some(dec!(0.001)),
+        };
+        let view = day(&mut state, None, &args).unwrap().replay(&args).unwrap();
+        let ids: Vec<&str> = view.alerts.iter().map(|a| a.alert_id.as_str()).collect();
+        assert_eq!(ids, ["buy", "replay", "sell"]);
+        assert_eq!(view.archived, 1);
+        assert_eq!(view.alerts[0].price, Some(dec!(101)));
+        assert!(view
+            .to_string()
+            .ends_with("on 2025-01-10, 1 from the archive"));
+
+        // Unlimited, the bot bought 3 and sold them 120
+        let current = &view.current;
+        assert_eq!((current.trades, current.refused, current.cut), (3, 0, 0));
+        assert_eq!(current.realized_pnl, dec!(48));
+        assert_eq!(current.exposure, Decimal::ZERO);
+
+        // Within 210 it only bought the 2, as the second buy had no room left
+        let what_if = &view.counterfactual;
+        assert_eq!((what_if.trades, what_if.refused, what_if.cut), (2, 1, 0));
+        assert_eq!(
+            view.alerts[1].counterfactual.as_ref().unwrap().filled,
+            Decimal::ZERO
+        );
+        let sell = view.alerts[2].counterfactual.as_ref().unwrap();
+        assert_eq!((sell.filled, sell.price), (dec!(3), Some(dec!(120))));
+        assert_eq!(what_if.realized_pnl, dec!(38));
+        assert_eq!(what_if.fees, dec!(0.202) + dec!(0.36));
+        assert_eq!(what_if.exposure, dec!(120));
+        assert_eq!(what_if.limits.budget_amount, Some(dec!(210)));
+
+        let args = ReplayArgs {
+            budget: Some("25%".parse().unwrap()),
+            ..args
+        };
+        assert!(matches!(
+            day(&mut state, None, &args),
+            Err(AppError::InvalidInput(_))
+        ));
+    }
+}
@@ -1,15 +1,34 @@
+use crate::bot::api::{ApiResponse, API_PREFIX, API_VERSION};
+use crate::bot::state::export::ExportFormat;
+use crate::bot::state::input::bot::list::SECRET_FILTERS;
+use crate::bot::state::ApiVersionsView;
 pub use crate::bot::state::{
-    BotInsertArgs, BotListArgs, BotListView, BotUpdateArgs, BotView, ListenerInsertArgs,
-    ListenerListArgs, ListenerListView, ListenerUpdateArgs, ListenerView,
+    AdminInfoView, AlertListArgs, AlertListView, AlertReplayArgs, AlertSimulateArgs,
+    AlertSimulationView, AllocationReportArgs, AllocationView, BalanceListView, BotInsertArgs,
+    BotListArgs, BotListView, BotTimelineArgs, BotTradesArgs, BotUpdateArgs, BotView, BulkRequest,
+    BulkView, CancelledOrderView, ExecutionQualityArgs, ExecutionQualityView, FillImportView,
+    ListenerInsertArgs, ListenerListArgs, ListenerListView, ListenerRotateSecretArgs,
+    ListenerUpdateArgs, ListenerView, MonteCarloArgs, MonteCarloView, OpenOrderListView,
+    OrderCancelArgs, PnlView, PositionListView, ReadinessView, ReplayArgs, ReplayView,
+    SecretRotationView, StateCompactArgs, StateCompactView, StateExportArgs, StateImportArgs,
+    StateImportView, StatementArgs, StrategyReportArgs, StrategyReportView, SymbolMapArgs,
+    SymbolMapView, SymbolUnmapArgs, TimelineView, TradeListView,
 };
 use crate::errors::AppError;
 use log::{error, info};
-use reqwest::{Client, Method, Response};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
+use serde::Serialize;
+use serde_json::Value;
 
 #[derive(Debug, Clone)]
 pub struct RestClient {
     base_url: String,
+    /// Path prefix of the API version in use, see [`Self::negotiate`]
+    api_prefix: String,
     client: Client,
+    token: Option<String>,
+    websocket: bool,
 }
 
 impl RestClient {
@@ -17,10 +36,79 @@ impl RestClient {
     pub fn new(base_url: &str) -> Self {
         Self {
             base_url: base_url.to_string(),
+            api_prefix: API_PREFIX.to_string(),
             client: Client::new(),
+            token: None,
+            websocket: false,
         }
     }
 
+    /// Prefer the WebSocket command channel when the server offers it.
+    pub fn with_websocket(mut self, websocket: bool) -> Self {
+        self.websocket = websocket;
+        self
+    }
+
+    /// URL of the API version in use.
+    pub fn api_url(&self) -> String {
+        format!("{}{}", self.base_url, self.api_prefix)
+    }
+
+    /// Settle on the API version to use with the server: this client's, if the server lists
+    /// it in `GET /api/versions`; the unversioned paths of a server that predates versioning.
+    /// A server that cannot be asked keeps the client on its own version.
+    pub async fn negotiate(mut self) -> Self {
+        let url = format!("{}/api/versions", self.base_url);
+        let response = match self.request(Method::GET, &url).send().await {
+            Ok(response) => response,
+            Err(e) => {
+                log::debug!("Could not negotiate the API version: {}", e);
+                return self;
+            }
+        };
+        if response.status() == StatusCode::NOT_FOUND {
+            log::debug!("Server predates API versions; using unversioned paths");
+            self.api_prefix = String::new();
+            return self;
+        }
+        match response.json::<ApiResponse<ApiVersionsView>>().await {
+            Ok(ApiResponse {
+                data: Some(offered),
+                ..
+            }) if !offered
+                .versions
+                .iter()
+                .any(|version| version == API_VERSION) =>
+            {
+                log::warn!(
+                    "Server offers API versions {} but this client speaks {}; using the \
+                     unversioned paths of version {}",
+                    offered.versions.join(", "),
+                    API_VERSION,
+                    offered.current
+                );
+                self.api_prefix = String::new();
+            }
+            Ok(_) => {}
+            Err(e) => log::debug!("Unreadable API versions: {}", e),
+        }
+        self
+    }
+
+    pub fn token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
+
+    pub fn websocket(&self) -> bool {
+        self.websocket
+    }
+
+    /// Authenticate every request with the given bearer token.
+    pub fn with_token(mut self, token: Option<String>) -> Self {
+        self.token = token;
+        self
+    }
+
     /// Helper to send an HTTP request with an optional JSON body.
     async fn send_request<T: serde::Serialize + std::fmt::Debug>(
         &self,
@@ -30,8 +118,7 @@ impl RestClient {
     ) -> Result<Response, AppError> {
         info!("Sending {:?} request to URL: {}", method, url);
 
-        let request = self.client.request(method, url);
-
+        let request = self.request(method, url);
         let request = if let Some(body) = body {
             info!("Request body: {:?}", &body);
             request.json(&body)
@@ -39,10 +126,23 @@ impl RestClient {
             request
         };
 
-        request.send().await.map_err(|e| {
+        let response = request.send().await.map_err(|e| {
             error!("Request to {} failed: {}", url, e);
             AppError::ConnectionError(format!("Failed to send request: {}", e))
-        })
+        })?;
+        if response.headers().contains_key("deprecation") {
+            log::warn!("The server has deprecated {}", url);
+        }
+        Ok(response)
+    }
+
+    /// A request to `url`, with the bearer token if there is one.
+    fn request(&self, method: Method, url: &str) -> RequestBuilder {
+        let request = self.client.request(method, url);
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
     }
 
     // /// Helper to process the HTTP response into the desired type.
@@ -72,10 +172,47 @@ impl RestClient {
     //     Ok(result)
     // }
 
+    /// Query the server's readiness probe.
+    pub async fn readyz(&self) -> Result<reqwest::Response, AppError> {
+        self.send_request(
+            Method::GET,
+            &format!("{}/readyz", self.api_url()),
+            None::<()>,
+        )
+        .await
+    }
+
+    /// Fetch server facts for operators (requires an admin token when tenancy is enabled).
+    pub async fn admin_info(&self) -> Result<reqwest::Response, AppError> {
+        self.send_request(
+            Method::GET,
+            &format!("{}/admin/info", self.api_url()),
+            None::<()>,
+        )
+        .await
+    }
+
+    /// Prune what the retention policy, with the overrides of `args`, no longer keeps.
+    pub async fn compact_state(
+        &self,
+        args: &StateCompactArgs,
+    ) -> Result<reqwest::Response, AppError> {
+        self.send_request(
+            Method::POST,
+            &format!("{}/admin/compact", self.api_url()),
+            Some(args),
+        )
+        .await
+    }
+
     /// Add a new bot.
     pub async fn add_bot(&self, bot: BotInsertArgs) -> Result<reqwest::Response, AppError> {
-        self.send_request(Method::POST, &format!("{}/bots", self.base_url), Some(&bot))
-            .await
+        self.send_request(
+            Method::POST,
+            &format!("{}/bots", self.api_url()),
+            Some(&bot),
+        )
+        .await
     }
 
     /// List bots with optional pagination and filtering.
@@ -85,7 +222,7 @@ impl RestClient {
         limit: Option<u32>,
         filter: Option<BotListArgs>,
     ) -> Result<reqwest::Response, AppError> {
-        let mut url = reqwest::Url::parse(&format!("{}/bots", self.base_url))
+        let mut url = reqwest::Url::parse(&format!("{}/bots", self.api_url()))
             .map_err(|e| AppError::ConnectionError(format!("Invalid URL: {}", e)))?;
 
         // Add query parameters for pagination if they are provided
@@ -98,12 +235,11 @@ impl RestClient {
                 .append_pair("limit", &limit_val.to_string());
         }
 
-        // Serialize the filter into JSON (if provided)
+        // Filters go in the query, but for credentials, which are not to be logged
         let body = filter
-            .map(|f| {
-                serde_json::to_value(f).map_err(|e| AppError::SerializationError(e.to_string()))
-            })
-            .transpose()?;
+            .map(|f| filter_query(&mut url, &f, &SECRET_FILTERS))
+            .transpose()?
+            .flatten();
 
         self.send_request(Method::GET, url.as_str(), body.as_ref())
             .await
@@ -113,12 +249,437 @@ impl RestClient {
     pub async fn get_bot(&self, bot_id: &str) -> Result<reqwest::Response, AppError> {
         self.send_request(
             Method::GET,
-            &format!("{}/bots/{}", self.base_url, bot_id),
+            &format!("{}/bots/{}", self.api_url(), bot_id),
+            None::<()>,
+        )
+        .await
+    }
+
+    /// Balances of a bot's exchange account.
+    pub async fn get_bot_balances(&self, bot_id: &str) -> Result<reqwest::Response, AppError> {
+        self.send_request(
+            Method::GET,
+            &format!("{}/bots/{}/balances", self.api_url(), bot_id),
+            None::<()>,
+        )
+        .await
+    }
+
+    /// Open positions of a bot's exchange account.
+    pub async fn get_bot_positions(&self, bot_id: &str) -> Result<reqwest::Response, AppError> {
+        self.send_request(
+            Method::GET,
+            &format!("{}/bots/{}/positions", self.api_url(), bot_id),
+            None::<()>,
+        )
+        .await
+    }
+
+    /// Orders of a bot still open on its exchange.
+    pub async fn get_bot_orders(&self, bot_id: &str) -> Result<reqwest::Response, AppError> {
+        self.send_request(
+            Method::GET,
+            &format!("{}/bots/{}/orders", self.api_url(), bot_id),
+            None::<()>,
+        )
+        .await
+    }
+
+    /// Cancel an open order of a bot.
+    pub async fn cancel_bot_order(
+        &self,
+        args: &OrderCancelArgs,
+    ) -> Result<reqwest::Response, AppError> {
+        let mut url = reqwest::Url::parse(&format!(
+            "{}/bots/{}/orders/{}",
+            self.api_url(),
+            args.bot_id,
+            args.order_id
+        ))
+        .map_err(|e| AppError::ConnectionError(format!("Invalid URL: {}", e)))?;
+
+        if let Some(symbol) = &args.symbol {
+            url.query_pairs_mut().append_pair("symbol", symbol);
+        }
+
+        self.send_request(Method::DELETE, url.as_str(), None::<()>)
+            .await
+    }
+
+    /// Retrieve a page of a bot's event timeline.
+    pub async fn get_bot_timeline(
+        &self,
+        args: &BotTimelineArgs,
+    ) -> Result<reqwest::Response, AppError> {
+        let mut url =
+            reqwest::Url::parse(&format!("{}/bots/{}/timeline", self.api_url(), args.bot_id))
+                .map_err(|e| AppError::ConnectionError(format!("Invalid URL: {}", e)))?;
+
+        if let Some(before) = args.before {
+            url.query_pairs_mut()
+                .append_pair("before", &before.to_string());
+        }
+        if let Some(since) = args.since {
+            url.query_pairs_mut()
+                .append_pair("since", &since.to_rfc3339());
+        }
+        if let Some(limit) = args.limit {
+            url.query_pairs_mut()
+                .append_pair("limit", &limit.to_string());
+        }
+
+        self.send_request(Method::GET, url.as_str(), None::<()>)
+            .await
+    }
+
+    /// PnL of a bot's recorded trades.
+    pub async fn get_bot_pnl(&self, bot_id: &str) -> Result<reqwest::Response, AppError> {
+        self.send_request(
+            Method::GET,
+            &format!("{}/bots/{}/pnl", self.api_url(), bot_id),
+            None::<()>,
+        )
+        .await
+    }
+
+    /// Latency and error rate of a bot's exchanges.
+    pub async fn get_bot_stats(&self, bot_id: &str) -> Result<reqwest::Response, AppError> {
+        self.send_request(
+            Method::GET,
+            &format!("{}/bots/{}/stats", self.api_url(), bot_id),
             None::<()>,
         )
         .await
     }
 
+    /// Symbol mappings of a bot.
+    pub async fn get_bot_symbols(&self, bot_id: &str) -> Result<reqwest::Response, AppError> {
+        self.send_request(
+            Method::GET,
+            &format!("{}/bots/{}/symbols", self.api_url(), bot_id),
+            None::<()>,
+        )
+        .await
+    }
+
+    /// Map a symbol of a bot's alerts to an exchange symbol.
+    pub async fn map_bot_symbol(
+        &self,
+        args: &SymbolMapArgs,
+    ) -> Result<reqwest::Response, AppError> {
+        self.send_request(
+            Method::PUT,
+            &format!("{}/bots/{}/symbols", self.api_url(), args.bot_id),
+            Some(args),
+        )
+        .await
+    }
+
+    /// Drop a bot's mapping of a symbol.
+    pub async fn unmap_bot_symbol(
+        &self,
+        args: &SymbolUnmapArgs,
+    ) -> Result<reqwest::Response, AppError> {
+        let mut url =
+            reqwest::Url::parse(&format!("{}/bots/{}/symbols", self.api_url(), args.bot_id))
+                .map_err(|e| AppError::ConnectionError(format!("Invalid URL: {}", e)))?;
+        url.query_pairs_mut().append_pair("symbol", &args.symbol);
+
+        self.send_request(Method::DELETE, url.as_str(), None::<()>)
+            .await
+    }
+
+    /// Retrieve a page of a bot's trade history.
+    pub async fn get_bot_trades(
+        &self,
+        args: &BotTradesArgs,
+    ) -> Result<reqwest::Response, AppError> {
+        let mut url =
+            reqwest::Url::parse(&format!("{}/bots/{}/trades", self.api_url(), args.bot_id))
+                .map_err(|e| AppError::ConnectionError(format!("Invalid URL: {}", e)))?;
+
+        if let Some(page) = args.page {
+            url.query_pairs_mut().append_pair("page", &page.to_string());
+        }
+        if let Some(limit) = args.limit {
+            url.query_pairs_mut()
+                .append_pair("limit", &limit.to_string());
+        }
+
+        self.send_request(Method::GET, url.as_str(), None::<()>)
+            .await
+    }
+
+    /// Retrieve the alerts a bot's listeners accepted, newest first.
+    pub async fn get_bot_alerts(
+        &self,
+        args: &AlertListArgs,
+    ) -> Result<reqwest::Response, AppError> {
+        let mut url =
+            reqwest::Url::parse(&format!("{}/bots/{}/alerts", self.api_url(), args.bot_id))
+                .map_err(|e| AppError::ConnectionError(format!("Invalid URL: {}", e)))?;
+
+        if let Some(limit) = args.limit {
+            url.query_pairs_mut()
+                .append_pair("limit", &limit.to_string());
+        }
+
+        self.send_request(Method::GET, url.as_str(), None::<()>)
+            .await
+    }
+
+    /// Dispatch a failed alert again from its logged payload.
+    pub async fn replay_alert(
+        &self,
+        args: &AlertReplayArgs,
+    ) -> Result<reqwest::Response, AppError> {
+        let url = format!("{}/alerts/{}/replay", self.api_url(), args.alert_id);
+        self.send_request(Method::POST, &url, None::<()>).await
+    }
+
+    /// Simulate the alert `payload` on a listener, or dispatch it with `args.execute`.
+    pub async fn test_fire(
+        &self,
+        args: &AlertSimulateArgs,
+        payload: String,
+    ) -> Result<reqwest::Response, AppError> {
+        let mut url = reqwest::Url::parse(&format!(
+            "{}/bots/{}/listeners/{}/test-fire",
+            self.api_url(),
+            args.bot_id,
+            args.listener_id
+        ))
+        .map_err(|e| AppError::ConnectionError(format!("Invalid URL: {}", e)))?;
+        if args.execute {
+            url.query_pairs_mut().append_pair("execute", "true");
+        }
+        info!("Sending POST request to URL: {}", url);
+
+        self.request(Method::POST, url.as_str())
+            .header(CONTENT_TYPE, "application/json")
+            .body(payload)
+            .send()
+            .await
+            .map_err(|e| {
+                error!("Request to {} failed: {}", url, e);
+                AppError::ConnectionError(format!("Failed to send request: {}", e))
+            })
+    }
+
+    /// Slippage of fills from alert prices, per bot and listener.
+    pub async fn get_execution_quality(
+        &self,
+        args: &ExecutionQualityArgs,
+    ) -> Result<reqwest::Response, AppError> {
+        let mut url = reqwest::Url::parse(&format!("{}/reports/execution-quality", self.api_url()))
+            .map_err(|e| AppError::ConnectionError(format!("Invalid URL: {}", e)))?;
+
+        if let Some(bot_id) = &args.bot_id {
+            url.query_pairs_mut().append_pair("bot_id", bot_id);
+        }
+        if let Some(since) = args.since {
+            url.query_pairs_mut()
+                .append_pair("since", &since.to_rfc3339());
+        }
+
+        self.send_request(Method::GET, url.as_str(), None::<()>)
+            .await
+    }
+
+    /// Win rate, profit factor, drawdown and Sharpe ratio per bot and listener.
+    pub async fn get_strategy_report(
+        &self,
+        args: &StrategyReportArgs,
+    ) -> Result<reqwest::Response, AppError> {
+        let mut url = reqwest::Url::parse(&format!("{}/reports/strategies", self.api_url()))
+            .map_err(|e| AppError::ConnectionError(format!("Invalid URL: {}", e)))?;
+
+        if let Some(bot_id) = &args.bot_id {
+            url.query_pairs_mut().append_pair("bot_id", bot_id);
+        }
+        if let Some(since) = args.since {
+            url.query_pairs_mut()
+                .append_pair("since", &since.to_rfc3339());
+        }
+
+        self.send_request(Method::GET, url.as_str(), None::<()>)
+            .await
+    }
+
+    /// Exposure of each bot against its budget.
+    pub async fn get_allocation_report(
+        &self,
+        args: &AllocationReportArgs,
+    ) -> Result<reqwest::Response, AppError> {
+        let mut url = reqwest::Url::parse(&format!("{}/reports/allocation", self.api_url()))
+            .map_err(|e| AppError::ConnectionError(format!("Invalid URL: {}", e)))?;
+
+        if let Some(bot_id) = &args.bot_id {
+            url.query_pairs_mut().append_pair("bot_id", bot_id);
+        }
+
+        self.send_request(Method::GET, url.as_str(), None::<()>)
+            .await
+    }
+
+    /// Drawdown and risk-of-ruin percentiles of a bot's round trips, resampled.
+    pub async fn get_montecarlo_report(
+        &self,
+        args: &MonteCarloArgs,
+    ) -> Result<reqwest::Response, AppError> {
+        let mut url = reqwest::Url::parse(&format!("{}/reports/montecarlo", self.api_url()))
+            .map_err(|e| AppError::ConnectionError(format!("Invalid URL: {}", e)))?;
+
+        url.query_pairs_mut().append_pair("bot_id", &args.bot_id);
+        if let Some(since) = args.since {
+            url.query_pairs_mut()
+                .append_pair("since", &since.to_rfc3339());
+        }
+        for (name, value) in [
+            ("simulations", args.simulations.map(|n| n.to_string())),
+            ("trades", args.trades.map(|n| n.to_string())),
+            ("capital", args.capital.map(|d| d.to_string())),
+            ("ruin", args.ruin.map(|d| d.to_string())),
+            ("seed", args.seed.map(|n| n.to_string())),
+        ] {
+            if let Some(value) = value {
+                url.query_pairs_mut().append_pair(name, &value);
+            }
+        }
+
+        self.send_request(Method::GET, url.as_str(), None::<()>)
+            .await
+    }
+
+    /// A bot's alerts of a day replayed with its current risk limits and with those of
+    /// `args`.
+    pub async fn get_replay_report(
+        &self,
+        args: &ReplayArgs,
+    ) -> Result<reqwest::Response, AppError> {
+        let mut url = reqwest::Url::parse(&format!("{}/reports/replay", self.api_url()))
+            .map_err(|e| AppError::ConnectionError(format!("Invalid URL: {}", e)))?;
+
+        url.query_pairs_mut()
+            .append_pair("bot_id", &args.bot_id)
+            .append_pair("date", &args.date.to_string());
+        for (name, value) in [
+            ("budget", args.budget.map(|budget| budget.to_string())),
+            ("max_trades", args.max_trades.map(|n| n.to_string())),
+            ("slippage_bps", args.slippage_bps.map(|d| d.to_string())),
+            ("fill_pct", args.fill_pct.map(|d| d.to_string())),
+            ("trading_fee", args.trading_fee.map(|d| d.to_string())),
+        ] {
+            if let Some(value) = value {
+                url.query_pairs_mut().append_pair(name, &value);
+            }
+        }
+
+        self.send_request(Method::GET, url.as_str(), None::<()>)
+            .await
+    }
+
+    /// Retrieve a bot's monthly statement, an HTML page.
+    pub async fn get_bot_statement(
+        &self,
+        args: &StatementArgs,
+    ) -> Result<reqwest::Response, AppError> {
+        let url = format!(
+            "{}/bots/{}/statements/{}",
+            self.api_url(),
+            args.bot_id,
+            args.month
+        );
+        self.send_request(Method::GET, &url, None::<()>).await
+    }
+
+    /// Backfill a bot's trade history from `csv`, fills exported by its exchange.
+    pub async fn import_bot_fills(
+        &self,
+        bot_id: &str,
+        csv: String,
+    ) -> Result<reqwest::Response, AppError> {
+        let url = format!("{}/bots/{}/fills", self.api_url(), bot_id);
+        info!(
+            "Sending POST request to URL: {} ({} bytes of CSV)",
+            url,
+            csv.len()
+        );
+
+        let request = self.request(Method::POST, &url);
+        request
+            .header(CONTENT_TYPE, "text/csv")
+            .body(csv)
+            .send()
+            .await
+            .map_err(|e| {
+                error!("Request to {} failed: {}", url, e);
+                AppError::ConnectionError(format!("Failed to send request: {}", e))
+            })
+    }
+
+    /// Apply a batch of operations on bots and listeners, all or none.
+    pub async fn bulk(&self, request: &BulkRequest) -> Result<reqwest::Response, AppError> {
+        self.send_request(
+            Method::POST,
+            &format!("{}/bots/bulk", self.api_url()),
+            Some(request),
+        )
+        .await
+    }
+
+    /// Export the bots and their listeners, as a document in `args.format`.
+    pub async fn export_state(
+        &self,
+        args: &StateExportArgs,
+    ) -> Result<reqwest::Response, AppError> {
+        let mut url = reqwest::Url::parse(&format!("{}/state/export", self.api_url()))
+            .map_err(|e| AppError::ConnectionError(format!("Invalid URL: {}", e)))?;
+
+        url.query_pairs_mut()
+            .append_pair("format", &args.format.to_string());
+        if args.redact_secrets {
+            url.query_pairs_mut().append_pair("redact_secrets", "true");
+        }
+
+        self.send_request(Method::GET, url.as_str(), None::<()>)
+            .await
+    }
+
+    /// Import the bots of `content`, an export in `format`.
+    pub async fn import_state(
+        &self,
+        args: &StateImportArgs,
+        format: ExportFormat,
+        content: String,
+    ) -> Result<reqwest::Response, AppError> {
+        let mut url = reqwest::Url::parse(&format!("{}/state/import", self.api_url()))
+            .map_err(|e| AppError::ConnectionError(format!("Invalid URL: {}", e)))?;
+
+        url.query_pairs_mut()
+            .append_pair("format", &format.to_string());
+        if args.replace {
+            url.query_pairs_mut().append_pair("replace", "true");
+        }
+        info!(
+            "Sending POST request to URL: {} ({} bytes of {})",
+            url,
+            content.len(),
+            format
+        );
+
+        let request = self.request(Method::POST, url.as_str());
+        request
+            .header(CONTENT_TYPE, format.content_type())
+            .body(content)
+            .send()
+            .await
+            .map_err(|e| {
+                error!("Request to {} failed: {}", url, e);
+                AppError::ConnectionError(format!("Failed to send request: {}", e))
+            })
+    }
+
     /// Update a bot by ID.
     pub async fn update_bot(
         &self,
@@ -127,7 +688,7 @@ impl RestClient {
     ) -> Result<reqwest::Response, AppError> {
         self.send_request(
             Method::PUT,
-            &format!("{}/bots/{}", self.base_url, bot_id),
+            &format!("{}/bots/{}", self.api_url(), bot_id),
             Some(&update_data),
         )
         .await
@@ -137,7 +698,7 @@ impl RestClient {
     pub async fn delete_bot(&self, bot_id: &str) -> Result<reqwest::Response, AppError> {
         self.send_request(
             Method::DELETE,
-            &format!("{}/bots/{}", self.base_url, bot_id),
+            &format!("{}/bots/{}", self.api_url(), bot_id),
             None::<()>,
         )
         .await
@@ -151,7 +712,7 @@ impl RestClient {
     ) -> Result<reqwest::Response, AppError> {
         self.send_request(
             Method::POST,
-            &format!("{}/bots/{}/listeners", self.base_url, bot_id),
+            &format!("{}/bots/{}/listeners", self.api_url(), bot_id),
             Some(&args),
         )
         .await
@@ -165,7 +726,7 @@ impl RestClient {
         limit: Option<u32>,
         filter: Option<ListenerListArgs>,
     ) -> Result<reqwest::Response, AppError> {
-        let mut url = reqwest::Url::parse(&format!("{}/bots/{}/listeners", self.base_url, bot_id))
+        let mut url = reqwest::Url::parse(&format!("{}/bots/{}/listeners", self.api_url(), bot_id))
             .map_err(|e| AppError::ConnectionError(format!("Invalid URL: {}", e)))?;
 
         // Add query parameters for pagination if they are provided
@@ -178,18 +739,45 @@ impl RestClient {
                 .append_pair("limit", &limit_val.to_string());
         }
 
-        // Serialize the filter into JSON (if provided)
         let body = filter
-            .map(|f| {
-                serde_json::to_value(f).map_err(|e| AppError::SerializationError(e.to_string()))
-            })
-            .transpose()?;
+            .map(|f| filter_query(&mut url, &f, &[]))
+            .transpose()?
+            .flatten();
 
         // Send the GET request
         self.send_request(Method::GET, url.as_str(), body.as_ref())
             .await
     }
 
+    /// Retrieve listeners across all bots, optionally filtered.
+    pub async fn get_all_listeners(
+        &self,
+        page: Option<u32>,
+        limit: Option<u32>,
+        filter: Option<ListenerListArgs>,
+    ) -> Result<reqwest::Response, AppError> {
+        let mut url = reqwest::Url::parse(&format!("{}/listeners", self.api_url()))
+            .map_err(|e| AppError::ConnectionError(format!("Invalid URL: {}", e)))?;
+
+        // Add query parameters for pagination if they are provided
+        if let Some(page_val) = page {
+            url.query_pairs_mut()
+                .append_pair("page", &page_val.to_string());
+        }
+        if let Some(limit_val) = limit {
+            url.query_pairs_mut()
+                .append_pair("limit", &limit_val.to_string());
+        }
+
+        let body = filter
+            .map(|f| filter_query(&mut url, &f, &[]))
+            .transpose()?
+            .flatten();
+
+        self.send_request(Method::GET, url.as_str(), body.as_ref())
+            .await
+    }
+
     /// Retrieve a specific listener by bot ID and listener ID.
     pub async fn get_listener(
         &self,
@@ -200,7 +788,9 @@ impl RestClient {
             Method::GET,
             &format!(
                 "{}/bots/{}/listeners/{}",
-                self.base_url, bot_id, listener_id
+                self.api_url(),
+                bot_id,
+                listener_id
             ),
             None::<()>,
         )
@@ -218,7 +808,9 @@ impl RestClient {
             Method::PUT,
             &format!(
                 "{}/bots/{}/listeners/{}",
-                self.base_url, bot_id, listener_id
+                self.api_url(),
+                bot_id,
+                listener_id
             ),
             Some(&update),
         )
@@ -235,13 +827,37 @@ impl RestClient {
             Method::DELETE,
             &format!(
                 "{}/bots/{}/listeners/{}",
-                self.base_url, bot_id, listener_id
+                self.api_url(),
+                bot_id,
+                listener_id
             ),
             None::<()>,
         )
         .await
     }
 
+    /// Give a listener a new secret, keeping the old one valid for `args.grace_secs`.
+    pub async fn rotate_listener_secret(
+        &self,
+        args: &ListenerRotateSecretArgs,
+    ) -> Result<reqwest::Response, AppError> {
+        let mut url = reqwest::Url::parse(&format!(
+            "{}/bots/{}/listeners/{}/rotate-secret",
+            self.api_url(),
+            args.bot_id,
+            args.listener_id
+        ))
+        .map_err(|e| AppError::ConnectionError(format!("Invalid URL: {}", e)))?;
+
+        if let Some(grace_secs) = args.grace_secs {
+            url.query_pairs_mut()
+                .append_pair("grace_secs", &grace_secs.to_string());
+        }
+
+        self.send_request(Method::POST, url.as_str(), None::<()>)
+            .await
+    }
+
     /// Delete multiple listeners for a bot based on filters.
     pub async fn delete_listeners(
         &self,
@@ -250,9 +866,86 @@ impl RestClient {
     ) -> Result<reqwest::Response, AppError> {
         self.send_request(
             Method::DELETE,
-            &format!("{}/bots/{}/listeners", self.base_url, bot_id),
+            &format!("{}/bots/{}/listeners", self.api_url(), bot_id),
             filter.as_ref(), // Pass the filter payload if it exists, otherwise None
         )
         .await
     }
 }
+
+/// Add the filters set in `filter` to the query of `url`, with lists comma-separated, but for
+/// `in_body`, returned as the JSON body to send them in if any is set. Parameters the query
+/// has already are left as they are.
+fn filter_query<T: Serialize>(
+    url: &mut reqwest::Url,
+    filter: &T,
+    in_body: &[&str],
+) -> Result<Option<Value>, AppError> {
+    let fields = match serde_json::to_value(filter) {
+        Ok(Value::Object(fields)) => fields,
+        Ok(_) => return Ok(None),
+        Err(e) => return Err(AppError::SerializationError(e.to_string())),
+    };
+    let present: Vec<String> = url
+        .query_pairs()
+        .map(|(name, _)| name.into_owned())
+        .collect();
+    let mut body = serde_json::Map::new();
+    for (name, value) in fields {
+        if in_body.contains(&name.as_str()) {
+            if !value.is_null() {
+                body.insert(name, value);
+            }
+            continue;
+        }
+        let value = match value {
+            Value::Null | Value::Bool(false) => continue,
+            Value::String(value) => value,
+            Value::Array(items) => (items.iter())
+                .map(|item| {
+                    item.as_str()
+                        .map_or_else(|| item.to_string(), str::to_string)
+                })
+                .collect::<Vec<_>>()
+                .join(","),
+            value => value.to_string(),
+        };
+        if !value.is_empty() && !present.contains(&name) {
+            url.query_pairs_mut().append_pair(&name, &value);
+        }
+    }
+    Ok((!body.is_empty()).then_some(Value::Object(body)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::dec;
+
+    #[test]
+    fn filters_go_in_the_query_but_for_credentials() {
+        let mut url = reqwest::Url::parse("http://localhost/api/v1/bots?page=2").unwrap();
+        let filter = BotListArgs {
+            page: Some(3),
+            exchange: Some("binance".to_string()),
+            trading_fee_min: Some(dec!(0.001)),
+            api_key: Some("key".to_string()),
+            ..BotListArgs::default()
+        };
+        let body = filter_query(&mut url, &filter, &SECRET_FILTERS).unwrap();
+        assert_eq!(
+            url.query(),
+            Some("page=2&exchange=binance&trading_fee_min=0.001")
+        );
+        assert_eq!(body, Some(serde_json::json!({"api_key": "key"})));
+
+        let mut url = reqwest::Url::parse("http://localhost/api/v1/listeners").unwrap();
+        let mut filter = ListenerListArgs::all_bots();
+        filter.include = vec!["msg".to_string()];
+        assert_eq!(filter_query(&mut url, &filter, &[]).unwrap(), None);
+        assert_eq!(
+            url.query(),
+            Some("all_bots=true&include=msg&limit=10&page=1")
+        );
+    }
+}
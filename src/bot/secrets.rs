@@ -0,0 +1,357 @@
+// src/bot/secrets.rs
+//! # Secret References
+//!
+//! A bot's credentials may name an environment variable instead of holding the secret, as
+//! `env:BINANCE_API_KEY`. The reference is what the state file keeps; it is resolved just
+//! before each call to the exchange, so the secret never reaches the state, its backups or the
+//! API. Variables can also come from a `.env` file.
+//!
+//! Tests against exchange testnets use the same references. They take the variables from
+//! the environment, as CI sets them, or else from a `secrets.test.toml` next to `Cargo.toml`
+//! that is never checked in (see `secrets.test.toml.example`).
+//!
+//! Secrets kept in the state file itself can be encrypted there with a [`StateKey`] from the
+//! [configuration](EncryptionConfig). Each is stored as `enc:` and the hex of its nonce and
+//! AES-256-GCM ciphertext; the state in memory holds them decrypted.
+use crate::app_config::EncryptionConfig;
+use crate::bot::model::Bot;
+use crate::utils::crypto::{decode_hex, encode_hex, AeadKey};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+#[cfg(test)]
+use std::{io, path::Path};
+
+/// Prefix of a credential naming an environment variable.
+pub const ENV_PREFIX: &str = "env:";
+
+/// Prefix of a credential encrypted with the [`StateKey`].
+pub const ENCRYPTED_PREFIX: &str = "enc:";
+
+/// Resolves secret references, from the environment first and then from values loaded from
+/// a secrets file.
+#[derive(Debug, Default)]
+pub struct Secrets {
+    fallback: HashMap<String, String>,
+}
+
+impl Secrets {
+    /// Secrets from the environment, then from the TOML table of `NAME = "value"` at `path`;
+    /// a missing file adds none.
+    #[cfg(test)]
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e),
+        };
+        let fallback = toml::from_str(&content).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e),
+            )
+        })?;
+        Ok(Self { fallback })
+    }
+
+    /// Secrets of the testnet tests.
+    #[cfg(test)]
+    #[cfg_attr(not(feature = "exchange-binance"), allow(dead_code))]
+    pub fn for_tests() -> Self {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("secrets.test.toml");
+        Self::load(&path).unwrap()
+    }
+
+    /// `value`, or the secret it refers to.
+    pub fn resolve(&self, value: &str) -> Result<String, String> {
+        let Some(name) = value.strip_prefix(ENV_PREFIX) else {
+            return Ok(value.to_string());
+        };
+        std::env::var(name)
+            .ok()
+            .or_else(|| self.fallback.get(name).cloned())
+            .filter(|secret| !secret.is_empty())
+            .ok_or_else(|| format!("secret {} is not set", name))
+    }
+
+    fn resolve_option(&self, value: &Option<String>) -> Result<Option<String>, String> {
+        value
+            .as_deref()
+            .map(|value| self.resolve(value))
+            .transpose()
+    }
+
+    /// A copy of `bot` holding the secrets its credentials, and those of its sub-accounts,
+    /// refer to.
+    pub fn resolve_bot(&self, bot: &Bot) -> Result<Bot, String> {
+        let mut bot = bot.clone();
+        bot.api_key = self.resolve_option(&bot.api_key)?;
+        bot.api_secret = self.resolve_option(&bot.api_secret)?;
+        bot.private_key = self.resolve_option(&bot.private_key)?;
+        for account in &mut bot.accounts {
+            account.api_key = self.resolve_option(&account.api_key)?;
+            account.api_secret = self.resolve_option(&account.api_secret)?;
+            account.private_key = self.resolve_option(&account.private_key)?;
+        }
+        Ok(bot)
+    }
+}
+
+/// Key encrypting the secrets of bots in the state file.
+pub struct StateKey(AeadKey);
+
+impl StateKey {
+    /// The configured key, if one is configured.
+    pub fn from_config(config: &EncryptionConfig) -> Result<Option<Self>, String> {
+        let key = match (&config.key, &config.key_file) {
+            (Some(_), Some(_)) => {
+                return Err("set encryption.key or encryption.key_file, not both".to_string())
+            }
+            (Some(key), None) => Secrets::default().resolve(key)?,
+            (None, Some(path)) => fs::read_to_string(path).map_err(|e| {
+                format!("cannot read encryption key file {}: {}", path.display(), e)
+            })?,
+            (None, None) => return Ok(None),
+        };
+        Self::parse(key.trim()).map(Some)
+    }
+
+    /// A key of 64 hex digits.
+    pub fn parse(hex: &str) -> Result<Self, String> {
+        decode_hex(hex)
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+            .map(|key| Self(AeadKey::new(&key)))
+            .ok_or_else(|| "the encryption key must be 64 hex digits".to_string())
+    }
+
+    /// `value` encrypted; references to the environment are left as they are.
+    fn encrypt(&self, value: &str) -> String {
+        if value.starts_with(ENV_PREFIX) || value.starts_with(ENCRYPTED_PREFIX) {
+            return value.to_string();
+        }
+        let sealed = self.0.seal(value.as_bytes());
+        format!("{}{}", ENCRYPTED_PREFIX, encode_hex(&sealed))
+    }
+
+    /// `value`, decrypted if it was encrypted.
+    fn decrypt(&self, value: &str) -> Result<String, String> {
+        let Some(hex) = value.strip_prefix(ENCRYPTED_PREFIX) else {
+            return Ok(value.to_string());
+        };
+        decode_hex(hex)
+            .and_then(|sealed| self.0.open(&sealed))
+            .and_then(|plaintext| String::from_utf8(plaintext).ok())
+            .ok_or_else(|| {
+                "a secret does not decrypt with the configured encryption key".to_string()
+            })
+    }
+
+    /// A copy of `bot` with its secrets encrypted, as the state file keeps it.
+    pub fn seal_bot(&self, bot: &Bot) -> Bot {
+        let mut bot = bot.clone();
+        for value in stored_secrets(&mut bot).into_iter().flatten() {
+            *value = self.encrypt(value);
+        }
+        bot
+    }
+
+    /// Decrypt the secrets of `bot`, read from the state file.
+    pub fn unseal_bot(&self, bot: &mut Bot) -> Result<(), String> {
+        for value in stored_secrets(bot).into_iter().flatten() {
+            *value = self.decrypt(value)?;
+        }
+        Ok(())
+    }
+}
+
+// States are compared by what they hold, not by the key they are saved with
+impl PartialEq for StateKey {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl fmt::Debug for StateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "StateKey(..)")
+    }
+}
+
+/// The secrets of `bot` that the state file stores: its own and its sub-accounts'
+/// `api_secret` and `private_key`, and its `webhook_secret`.
+fn stored_secrets(bot: &mut Bot) -> Vec<Option<&mut String>> {
+    let mut secrets = vec![
+        bot.api_secret.as_mut(),
+        bot.private_key.as_mut(),
+        bot.webhook_secret.as_mut(),
+    ];
+    for account in &mut bot.accounts {
+        secrets.push(account.api_secret.as_mut());
+        secrets.push(account.private_key.as_mut());
+    }
+    secrets
+}
+
+/// Number of the secrets of `bot` stored encrypted, and of those stored as plaintext.
+pub fn count_sealed(bot: &Bot) -> (usize, usize) {
+    let mut bot = bot.clone();
+    let values: Vec<_> = stored_secrets(&mut bot)
+        .into_iter()
+        .flatten()
+        .filter(|value| !value.starts_with(ENV_PREFIX))
+        .map(|value| value.starts_with(ENCRYPTED_PREFIX))
+        .collect();
+    let sealed = values.iter().filter(|sealed| **sealed).count();
+    (sealed, values.len() - sealed)
+}
+
+/// Fields holding credentials or listener secrets, wherever they appear in JSON.
+pub const SECRET_FIELDS: &[&str] = &[
+    "api_key",
+    "api_secret",
+    "private_key",
+    "webhook_secret",
+    "secret",
+    "previous_secret",
+];
+
+/// What a masked credential shows instead of its value.
+pub const MASK: &str = "********";
+
+/// The credential `value` as shown to those who may not see it: masked, unless unset or a
+/// reference to the environment.
+pub fn mask(value: &Value) -> Value {
+    match value {
+        Value::String(reference) if reference.starts_with(ENV_PREFIX) => value.clone(),
+        Value::Null => Value::Null,
+        _ => Value::String(MASK.to_string()),
+    }
+}
+
+/// [Mask](mask) every [secret field](SECRET_FIELDS) in `value`, however deeply nested.
+pub fn redact(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            for (name, field) in object.iter_mut() {
+                if SECRET_FIELDS.contains(&name.as_str()) && !field.is_object() {
+                    *field = mask(field);
+                } else {
+                    redact(field);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::model::SubAccount;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    #[test]
+    fn redacted_json_masks_secrets_but_not_references() {
+        let mut value = json!({
+            "data": [{
+                "api_key": "env:BINANCE_API_KEY",
+                "api_secret": "plain",
+                "private_key": null,
+                "listeners": {"l1": {"secret": "s3cr3t", "msg": "buy"}},
+            }],
+        });
+        redact(&mut value);
+        assert_eq!(
+            value,
+            json!({
+                "data": [{
+                    "api_key": "env:BINANCE_API_KEY",
+                    "api_secret": MASK,
+                    "private_key": null,
+                    "listeners": {"l1": {"secret": MASK, "msg": "buy"}},
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn references_resolve_from_the_environment_then_the_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("secrets.test.toml");
+        assert!(Secrets::load(&path).unwrap().fallback.is_empty());
+        fs::write(
+            &path,
+            "XTRADE_TEST_KEY = \"file-key\"\nXTRADE_TEST_SECRET = \"file-secret\"\n",
+        )
+        .unwrap();
+        let secrets = Secrets::load(&path).unwrap();
+
+        let bot = Bot {
+            api_key: Some("env:XTRADE_TEST_KEY".to_string()),
+            api_secret: Some("literal".to_string()),
+            accounts: vec![SubAccount {
+                api_secret: Some("env:XTRADE_TEST_SECRET".to_string()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let resolved = secrets.resolve_bot(&bot).unwrap();
+        assert_eq!(resolved.api_key.as_deref(), Some("file-key"));
+        assert_eq!(resolved.api_secret.as_deref(), Some("literal"));
+        assert_eq!(resolved.private_key, None);
+        assert_eq!(
+            resolved.accounts[0].api_secret.as_deref(),
+            Some("file-secret")
+        );
+
+        assert_eq!(
+            secrets.resolve("env:PATH").unwrap(),
+            std::env::var("PATH").unwrap()
+        );
+        assert_eq!(
+            secrets.resolve("env:XTRADE_TEST_MISSING"),
+            Err("secret XTRADE_TEST_MISSING is not set".to_string())
+        );
+        fs::write(&path, "KEY = [").unwrap();
+        assert!(Secrets::load(&path).is_err());
+    }
+
+    #[test]
+    fn stored_secrets_are_encrypted_but_references_are_not() {
+        let key = StateKey::parse(&"2a".repeat(32)).unwrap();
+        let bot = Bot {
+            api_key: Some("public".to_string()),
+            api_secret: Some("s3cret".to_string()),
+            private_key: Some("env:XTRADE_TEST_PRIVATE_KEY".to_string()),
+            webhook_secret: Some("hook".to_string()),
+            accounts: vec![SubAccount {
+                api_secret: Some("sub-secret".to_string()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let mut sealed = key.seal_bot(&bot);
+        assert_eq!(sealed.api_key.as_deref(), Some("public"));
+        assert!(sealed.api_secret.as_deref().unwrap().starts_with("enc:"));
+        assert_eq!(sealed.private_key, bot.private_key);
+        assert_ne!(sealed.webhook_secret, key.seal_bot(&bot).webhook_secret);
+        assert_eq!(
+            (count_sealed(&bot), count_sealed(&sealed)),
+            ((0, 3), (3, 0))
+        );
+
+        let other = StateKey::parse(&"2b".repeat(32)).unwrap();
+        assert!(other.unseal_bot(&mut sealed.clone()).is_err());
+        key.unseal_bot(&mut sealed).unwrap();
+        assert_eq!(sealed, bot);
+        assert!(StateKey::parse("2a2a").is_err());
+        let config = EncryptionConfig {
+            key: Some("2a".repeat(32)),
+            key_file: Some("key".into()),
+        };
+        assert!(StateKey::from_config(&config).is_err());
+    }
+}
@@ -0,0 +1,307 @@
+// src/bot/selftest.rs
+//! # Self-Test
+//!
+//! `server --self-test` runs the [configured](SelfTestConfig) checks instead of serving, for
+//! a smoke test before a deployment: the config can be enforced, the state file loads and can
+//! be written, the exchange account of every bot with an enabled listener answers, and a test
+//! notification gets through. It prints a report and exits non-zero if any check failed.
+use crate::app_config::{AppConfig, SelfTestCheck, SelfTestConfig, StorageBackend};
+use crate::app_state::AppState;
+use crate::bot::model::Bot;
+use crate::bot::notify::{self, Notification};
+use crate::bot::secrets::Secrets;
+use crate::exchange;
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CheckStatus {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+impl fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckStatus::Passed => write!(f, "PASS"),
+            CheckStatus::Failed => write!(f, "FAIL"),
+            CheckStatus::Skipped => write!(f, "SKIP"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CheckResult {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+impl CheckResult {
+    fn new(name: &str, outcome: Result<String, String>) -> Self {
+        let (status, detail) = match outcome {
+            Ok(detail) => (CheckStatus::Passed, detail),
+            Err(detail) => (CheckStatus::Failed, detail),
+        };
+        Self {
+            name: name.to_string(),
+            status,
+            detail,
+        }
+    }
+
+    fn skipped(name: &str, reason: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            status: CheckStatus::Skipped,
+            detail: reason.to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SelfTestReport {
+    pub checks: Vec<CheckResult>,
+}
+
+impl SelfTestReport {
+    pub fn failures(&self) -> usize {
+        self.checks
+            .iter()
+            .filter(|check| check.status == CheckStatus::Failed)
+            .count()
+    }
+}
+
+impl fmt::Display for SelfTestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .checks
+            .iter()
+            .map(|check| check.name.len())
+            .max()
+            .unwrap_or(0);
+        for check in &self.checks {
+            writeln!(
+                f,
+                "{}  {:width$}  {}",
+                check.status, check.name, check.detail
+            )?;
+        }
+        match self.failures() {
+            0 => write!(f, "Self-test passed ({} checks)", self.checks.len()),
+            failed => write!(
+                f,
+                "Self-test failed: {} of {} checks",
+                failed,
+                self.checks.len()
+            ),
+        }
+    }
+}
+
+/// Run the checks of `app_config.self_test` against the state file `state_file`.
+pub async fn run(
+    app_config: &AppConfig,
+    state_file: PathBuf,
+    client: &reqwest::Client,
+) -> SelfTestReport {
+    let config = &app_config.self_test;
+    let enabled = |check| config.checks.contains(&check);
+    let mut report = SelfTestReport::default();
+
+    if enabled(SelfTestCheck::Config) {
+        let outcome = app_config
+            .tenancy
+            .validate()
+            .and_then(|_| app_config.maintenance.validate())
+            .map(|_| "tenancy and maintenance can be enforced".to_string())
+            .map_err(|e| e.to_string());
+        report.checks.push(CheckResult::new("config", outcome));
+    }
+
+    let mut loaded = app_config.clone();
+    loaded.api_server.state_file = state_file.clone();
+    let state = AppState::load(loaded);
+    if enabled(SelfTestCheck::State) {
+        let outcome = match &state {
+            Ok(state) => Ok(format!(
+                "{} bot(s) in {}",
+                state.bots.len(),
+                state.location()
+            )),
+            Err(e) => Err(e.to_string()),
+        };
+        report.checks.push(CheckResult::new("state", outcome));
+    }
+
+    if enabled(SelfTestCheck::Storage) {
+        let outcome = match state.as_ref().map(|state| state.store.as_ref()) {
+            Ok(Some(store)) => (store.read())
+                .map(|_| format!("{} answers", store.location()))
+                .map_err(|e| e.to_string()),
+            Err(e) if app_config.storage.backend == StorageBackend::Redis => Err(e.to_string()),
+            _ => AppState::ensure_writable(&state_file)
+                .map(|_| format!("{} is writable", state_file.display()))
+                .map_err(|e| e.to_string()),
+        };
+        report.checks.push(CheckResult::new("storage", outcome));
+    }
+
+    if enabled(SelfTestCheck::Exchanges) {
+        match &state {
+            Ok(state) => {
+                let mut bots: Vec<_> = state.bots.iter().collect();
+                bots.sort_by_key(|(key, _)| key.as_str());
+                for (key, bot) in bots {
+                    let name = format!("exchange {}", key);
+                    report
+                        .checks
+                        .push(check_bot(&name, bot, config, client).await);
+                }
+            }
+            Err(_) => report
+                .checks
+                .push(CheckResult::skipped("exchanges", "the state did not load")),
+        }
+    }
+
+    if enabled(SelfTestCheck::Notifications) {
+        let notification = Notification::new(
+            "xtrade self-test",
+            "This test notification was sent by `xtrade server --self-test`.",
+        );
+        let check = match notify::deliver(&app_config.notifications, &notification).await {
+            Ok(true) => CheckResult::new(
+                "notifications",
+                Ok("test notification delivered".to_string()),
+            ),
+            Ok(false) => CheckResult::skipped(
+                "notifications",
+                "no webhook_url; notifications are only logged",
+            ),
+            Err(e) => CheckResult::new("notifications", Err(e.to_string())),
+        };
+        report.checks.push(check);
+    }
+    report
+}
+
+/// Whether the exchange account of `bot` answers within the timeout.
+async fn check_bot(
+    name: &str,
+    bot: &Bot,
+    config: &SelfTestConfig,
+    client: &reqwest::Client,
+) -> CheckResult {
+    if !bot.listeners.values().any(|listener| listener.enabled) {
+        return CheckResult::skipped(name, "no enabled listener");
+    }
+    if bot.dry_run {
+        return CheckResult::skipped(name, "dry run; trades are simulated");
+    }
+    let bot = match Secrets::default().resolve_bot(bot) {
+        Ok(bot) => bot,
+        Err(e) => return CheckResult::new(name, Err(e)),
+    };
+    let Some(venue) = exchange::get_exchange(&bot) else {
+        return CheckResult::new(
+            name,
+            Err(format!("no adapter for exchange '{}'", bot.exchange)),
+        );
+    };
+
+    let timeout = Duration::from_secs(config.timeout_secs.max(1));
+    let timed_out = || format!("{} did not answer within {:?}", bot.exchange, timeout);
+    let outcome = match tokio::time::timeout(timeout, venue.get_balances(&bot, client)).await {
+        Ok(Ok(Some(balances))) => Ok(Some(format!(
+            "{}: {} balance(s)",
+            bot.exchange,
+            balances.len()
+        ))),
+        Ok(Ok(None)) => Ok(None),
+        Ok(Err(e)) => Err(format!("{}: {}", bot.exchange, e)),
+        Err(_) => Err(timed_out()),
+    };
+    let outcome = match outcome {
+        Ok(None) => match tokio::time::timeout(timeout, venue.open_orders(&bot, client)).await {
+            Ok(Ok(Some(orders))) => Ok(format!("{}: {} open order(s)", bot.exchange, orders.len())),
+            Ok(Ok(None)) => {
+                let reason = format!("{} cannot be asked about the account", bot.exchange);
+                return CheckResult::skipped(name, &reason);
+            }
+            Ok(Err(e)) => Err(format!("{}: {}", bot.exchange, e)),
+            Err(_) => Err(timed_out()),
+        },
+        Ok(Some(detail)) => Ok(detail),
+        Err(e) => Err(e),
+    };
+    CheckResult::new(name, outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::model::Listener;
+    use std::collections::HashMap;
+    use tempfile::tempdir;
+
+    fn bot(exchange: &str, dry_run: bool) -> Bot {
+        let listener = Listener {
+            enabled: true,
+            ..Default::default()
+        };
+        Bot {
+            exchange: exchange.to_string(),
+            api_key: Some("env:XTRADE_SELF_TEST_MISSING".to_string()),
+            dry_run,
+            listeners: HashMap::from([("tv".to_string(), listener)]),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn checks_are_reported_without_reaching_out() {
+        let dir = tempdir().unwrap();
+        let state_file = dir.path().join("state.json");
+        let mut state = AppState::default();
+        state.bots.insert("idle".to_string(), Bot::default());
+        state.bots.insert("paper".to_string(), bot("binance", true));
+        state.bots.insert("live".to_string(), bot("binance", false));
+        state.save(Some(&state_file)).unwrap();
+
+        let mut config = AppConfig::default();
+        config
+            .self_test
+            .checks
+            .retain(|check| *check != SelfTestCheck::Config);
+        let report = run(&config, state_file, &reqwest::Client::new()).await;
+        let statuses: Vec<_> = report
+            .checks
+            .iter()
+            .map(|check| (check.name.as_str(), check.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            [
+                ("state", CheckStatus::Passed),
+                ("storage", CheckStatus::Passed),
+                ("exchange idle", CheckStatus::Skipped),
+                ("exchange live", CheckStatus::Failed),
+                ("exchange paper", CheckStatus::Skipped),
+                ("notifications", CheckStatus::Skipped),
+            ]
+        );
+        assert_eq!(report.checks[0].detail.split(' ').next(), Some("3"));
+        assert_eq!(
+            report.checks[3].detail,
+            "secret XTRADE_SELF_TEST_MISSING is not set"
+        );
+        assert_eq!(report.failures(), 1);
+        assert!(report
+            .to_string()
+            .ends_with("Self-test failed: 1 of 6 checks"));
+    }
+}
@@ -1,12 +1,34 @@
-use crate::app_config::AppConfig;
+use crate::app_config::{AppConfig, CompressionConfig, PriceCacheConfig};
 use crate::app_state::AppState;
+use crate::bot::access_log::{self, AccessLog};
+use crate::bot::api::{LegacyRoutes, ServerInfo};
+use crate::bot::compression;
+use crate::bot::handoff::Listeners;
+use crate::bot::metrics::Metrics;
+use crate::bot::notify::{self, Notification};
+use crate::bot::outage::Outages;
+use crate::bot::recovery;
+use crate::bot::selftest;
+use crate::bot::ws::ServerEvents;
+use crate::exchange::prices::PriceCache;
 //use crate::bot::state::ServerStartupArgs;
+#[cfg(feature = "web-ui")]
 use actix_files as fs;
+use actix_web::dev::Server;
+use actix_web::middleware::{from_fn, Compress, Condition};
+#[cfg(feature = "web-ui")]
+use actix_web::{http::header, http::StatusCode, HttpRequest, HttpResponse};
 use actix_web::{web, App, HttpServer};
+use chrono::Utc;
 use clap::Args;
 use log::info;
 use std::path::PathBuf;
+use std::sync::atomic::AtomicU64;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How often buffered alerts are checked against their exchange's circuit.
+const REPLAY_INTERVAL: Duration = Duration::from_secs(5);
 
 #[derive(Args, Clone, Debug)]
 pub struct ServerStartupArgs {
@@ -16,6 +38,9 @@ pub struct ServerStartupArgs {
     pub api_bind_address: Option<String>,
     #[arg(long)]
     pub state_file: Option<PathBuf>,
+    /// Reload the state file when it is modified externally
+    #[arg(long)]
+    pub watch_state_file: bool,
     #[arg(long)]
     pub webhook_port: Option<u16>,
     #[arg(long)]
@@ -32,6 +57,13 @@ pub struct ServerStartupArgs {
     pub web_client_bind_address: Option<String>,
     #[arg(long)]
     pub web_client_static_files: Option<PathBuf>,
+    /// Share the ports with the server being replaced (SO_REUSEPORT)
+    #[arg(long)]
+    pub reuse_port: bool,
+    /// Run the `[self_test]` checks, print a report and exit instead of serving; exits
+    /// non-zero if a check failed
+    #[arg(long)]
+    pub self_test: bool,
 }
 
 /// Run the application in server mode
@@ -40,18 +72,49 @@ pub async fn run(
     app_config: AppConfig,
     app_state: Arc<Mutex<AppState>>,
 ) -> std::io::Result<()> {
-    // Acquire the lock on the AppState
-    let app_state_guard = app_state.lock().map_err(|_| {
-        log::error!("Failed to acquire lock on AppState.");
-        std::io::Error::new(
-            std::io::ErrorKind::Other,
-            "Failed to acquire lock on AppState.",
-        )
-    })?;
-
-    // Clone the configuration from the locked AppState
-    //let app_config = app_state_guard.config.clone();
-    drop(app_state_guard); // Explicitly drop the lock to avoid deadlocks later
+    if args.self_test {
+        let state_file = args
+            .state_file
+            .unwrap_or_else(|| app_config.api_server.state_file.clone());
+        let report = selftest::run(&app_config, state_file, &reqwest::Client::new()).await;
+        println!("{}", report);
+        return match report.failures() {
+            0 => Ok(()),
+            failed => Err(std::io::Error::other(format!(
+                "{} self-test check(s) failed",
+                failed
+            ))),
+        };
+    }
+
+    // Find out whether the previous run left unfinished work behind
+    let started_at = Utc::now();
+    let (_run_guard, recovery) = {
+        let app_state_guard = app_state.lock().map_err(|_| {
+            log::error!("Failed to acquire lock on AppState.");
+            std::io::Error::other("Failed to acquire lock on AppState.")
+        })?;
+        recovery::begin_run(&app_state_guard, started_at)?
+    };
+
+    if let Some(report) = &recovery {
+        eprintln!("{}", report);
+        let notifications = app_config.notifications.clone();
+        let notification = Notification::new(
+            "xtrade recovered from an unclean shutdown",
+            &report.summary(),
+        );
+        tokio::spawn(async move { notify::send(&notifications, &notification).await });
+    }
+    let prices = web::Data::new(PriceCache::new(app_config.prices.clone()));
+    let client = web::Data::new(reqwest::Client::new());
+    let outages = web::Data::new(Outages::new(app_config.outages.clone()));
+    let server_info = web::Data::new(ServerInfo {
+        started_at,
+        recovery,
+        reconciliation: Mutex::new(None),
+        stale_alerts: AtomicU64::new(0),
+    });
 
     // Extract server and web configuration with overrides
     let api_server_bind_address = args
@@ -75,6 +138,35 @@ pub async fn run(
         .web_client_static_files
         .unwrap_or_else(|| app_config.web_client.static_files.clone());
 
+    // Refuse to serve a tenant table, signing key, maintenance calendar or latency objective
+    // that cannot be enforced
+    app_config
+        .tenancy
+        .validate()
+        .and_then(|_| app_config.auth.validate())
+        .and_then(|_| app_config.maintenance.validate())
+        .and_then(|_| app_config.slo.validate())
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?;
+    if app_config.tenancy.enabled {
+        info!(
+            "Multi-tenancy enabled for {} tenant(s)",
+            app_config.tenancy.tenants.len()
+        );
+    }
+    let tenancy = web::Data::new(app_config.tenancy.clone());
+    if app_config.auth.enabled {
+        info!("Users must sign in at /auth/login");
+    }
+    let auth = web::Data::new(app_config.auth.clone());
+    let mut listeners = Listeners::from_env(args.reuse_port || app_config.handoff.reuse_port);
+    let shutdown_timeout = app_config.handoff.shutdown_timeout_secs;
+    let compress = app_config.compression.enabled;
+    let compression = web::Data::new(app_config.compression.clone());
+    let legacy_routes = web::Data::new(LegacyRoutes(app_config.api_server.legacy_routes));
+    if !app_config.api_server.legacy_routes {
+        info!("Serving the API under {} only", crate::bot::api::API_PREFIX);
+    }
+
     info!(
         "Starting API server on {}:{} with state file: {}",
         api_server_bind_address,
@@ -82,13 +174,153 @@ pub async fn run(
         api_server_state_file.display()
     );
 
+    // Optionally pick up external edits to the state file, or always what other servers
+    // sharing the state in a store saved
+    let events = web::Data::new(ServerEvents::default());
+    let store = app_state.lock().ok().and_then(|state| state.store.clone());
+    if let Some(store) = store {
+        info!(
+            "Reloading the state when other servers save it to {}",
+            store.location()
+        );
+        crate::app_state::watch_store(
+            app_state.clone(),
+            store,
+            events.get_ref().clone(),
+            prices.clone().into_inner(),
+        );
+    } else if args.watch_state_file || app_config.api_server.watch_state_file {
+        let interval = Duration::from_secs(app_config.api_server.watch_interval_secs.max(1));
+        info!(
+            "Watching state file for external changes every {:?}",
+            interval
+        );
+        tokio::spawn(crate::app_state::watch_state_file(
+            app_state.clone(),
+            interval,
+            events.get_ref().clone(),
+            prices.clone().into_inner(),
+        ));
+    }
+
+    // Write changes in the background rather than while requests wait
+    if app_config.api_server.save_debounce_ms > 0 {
+        let interval = Duration::from_millis(app_config.api_server.save_debounce_ms);
+        if let Ok(mut state) = app_state.lock() {
+            state.persistence.deferred = true;
+        }
+        info!("Saving state changes at most every {:?}", interval);
+        tokio::spawn(crate::app_state::persist_state(app_state.clone(), interval));
+    }
+
+    // Keep cached prices current from the exchanges' ticker streams
+    start_price_streams(&app_config.prices, prices.clone().into_inner())?;
+
+    // Check what the exchanges did while we were not looking
+    if app_config.reconciliation.on_startup {
+        tokio::spawn(crate::bot::reconcile::run(
+            app_state.clone(),
+            server_info.clone().into_inner(),
+            prices.clone().into_inner(),
+            app_config.reconciliation.clone(),
+            app_config.notifications.clone(),
+        ));
+    }
+
+    // Watch the bots' accounts for withdrawals made with stolen keys
+    if app_config.withdrawals.enabled {
+        tokio::spawn(crate::bot::withdrawals::run(
+            app_state.clone(),
+            app_config.withdrawals.clone(),
+            app_config.notifications.clone(),
+        ));
+    }
+
+    // Prune what the retention policy no longer keeps
+    let metrics = web::Data::new(Metrics::default());
+    if app_config.compaction.enabled {
+        tokio::spawn(crate::bot::compaction::run(
+            app_state.clone(),
+            app_config.compaction.clone(),
+            metrics.clone().into_inner(),
+        ));
+    }
+    let access_log = web::Data::new(AccessLog {
+        file: app_config.api_server.access_log.clone(),
+    });
+
+    // Write each bot's monthly statement once the month is over
+    if app_config.statements.enabled {
+        tokio::spawn(crate::bot::statement::run(
+            app_state.clone(),
+            app_config.statements.clone(),
+            app_config.notifications.clone(),
+        ));
+    }
+
+    // Send the alerts that waited for an exchange once it is back
+    let replay_state = app_state.clone();
+    let replay_outages = outages.clone();
+    let replay_prices = prices.clone();
+    let replay_client = client.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(REPLAY_INTERVAL);
+        loop {
+            interval.tick().await;
+            if replay_outages.is_empty() {
+                continue;
+            }
+            if let Err(e) = crate::bot::dispatch::replay(
+                &replay_state,
+                &replay_outages,
+                &replay_prices,
+                &replay_client,
+            )
+            .await
+            {
+                log::error!("Failed to replay buffered alerts: {}", e);
+            }
+        }
+    });
+
     // Start the API server
+    let api_state = app_state.clone();
+    let api_tenancy = tenancy.clone();
+    let api_auth = auth.clone();
+    let api_server_info = server_info.clone();
+    let api_prices = prices.clone();
+    let api_outages = outages.clone();
+    let api_events = events.clone();
+    let api_compression = compression.clone();
+    let webhook_config = web::Data::new(app_config.webhook_server.clone());
+    let api_webhook_config = webhook_config.clone();
+    let api_client = client.clone();
+    let api_metrics = metrics.clone();
+    let api_access_log = access_log.clone();
+    let api_legacy_routes = legacy_routes.clone();
     let api_server = HttpServer::new(move || {
         App::new()
-            .app_data(web::Data::new(app_state.clone())) // Share the same AppState
+            .wrap(from_fn(access_log::middleware))
+            .app_data(web::Data::new(api_state.clone())) // Share the same AppState
+            .app_data(api_tenancy.clone())
+            .app_data(api_auth.clone())
+            .app_data(api_server_info.clone())
+            .app_data(api_prices.clone())
+            .app_data(api_outages.clone())
+            .app_data(api_webhook_config.clone())
+            .app_data(api_client.clone())
+            .app_data(api_metrics.clone())
+            .app_data(api_access_log.clone())
+            .app_data(api_events.clone())
+            .app_data(api_compression.clone())
+            .app_data(api_legacy_routes.clone())
+            .wrap(from_fn(compression::mark))
+            .wrap(Condition::new(compress, Compress::default()))
+            .wrap(from_fn(compression::unmark))
             .configure(crate::bot::api::configure) // Add routes
     })
-    .bind((api_server_bind_address.as_str(), api_server_port))?
+    .shutdown_timeout(shutdown_timeout)
+    .listen(listeners.take("api", &api_server_bind_address, api_server_port)?)?
     .run();
 
     info!(
@@ -96,51 +328,204 @@ pub async fn run(
         webhook_server_bind_address, webhook_server_port
     );
 
-    Some(
-        HttpServer::new(move || {
-            App::new().configure(crate::bot::api::configure) // Configure webhook routes
-                                                             // App::new()
-                                                             //     .app_data(web::Data::new(app_state.clone())) // Share the same AppState
-                                                             //     .route(
-                                                             //         "/webhook",
-                                                             //         web::post().to(crate::webhook::handlers::handle_webhook),
-                                                             //     ) // Handle POST requests
-        })
-        .bind((webhook_server_bind_address.as_str(), webhook_server_port))?
-        .run(),
-    );
+    let webhook_state = app_state.clone();
+    let webhook_outages = outages.clone();
+    let web_client = client.clone();
+    let webhook_server = HttpServer::new(move || {
+        App::new()
+            .wrap(from_fn(access_log::middleware))
+            .app_data(web::Data::new(webhook_state.clone())) // Share the same AppState
+            .app_data(tenancy.clone())
+            .app_data(auth.clone())
+            .app_data(server_info.clone())
+            .app_data(prices.clone())
+            .app_data(webhook_outages.clone())
+            .app_data(webhook_config.clone())
+            .app_data(client.clone())
+            .app_data(metrics.clone())
+            .app_data(access_log.clone())
+            .app_data(legacy_routes.clone())
+            .configure(crate::bot::api::configure) // Configure webhook routes
+    })
+    .shutdown_timeout(shutdown_timeout)
+    .listen(listeners.take("webhook", &webhook_server_bind_address, webhook_server_port)?)?
+    .run();
 
-    // Conditionally start the Web UI server
-    if web_client_enable {
-        info!(
-            "Starting Web UI server on {}:{} serving files from: {}",
-            web_client_bind_address,
-            web_client_port,
-            web_client_static_files.display()
-        );
+    // Conditionally start the Web UI server, which reads reports through the API server
+    let api_host = match api_server_bind_address.as_str() {
+        "0.0.0.0" | "::" => "127.0.0.1",
+        address => address,
+    };
+    let web_server = start_web_client(
+        web_client_enable,
+        &web_client_bind_address,
+        web_client_port,
+        web_client_static_files,
+        format!("http://{}:{}", api_host, api_server_port),
+        web_client,
+        compression,
+        &mut listeners,
+    )?;
 
-        let web_server = HttpServer::new(move || {
-            App::new().service(
-                fs::Files::new("/", web_client_static_files.clone()).index_file("index.html"),
-            )
-            // Serve static files
-        })
-        .bind((web_client_bind_address.as_str(), web_client_port))?
-        .run();
-
-        // Run both servers concurrently
-        tokio::select! {
-            _ = api_server => {
-                info!("API server has stopped.");
-            }
-            _ = web_server => {
-                info!("Web UI server has stopped.");
+    // Stopping one server stops them all, each finishing the requests it accepted
+    let mut handles = vec![api_server.handle(), webhook_server.handle()];
+    handles.extend(web_server.as_ref().map(Server::handle));
+
+    // Run all servers concurrently
+    tokio::select! {
+        _ = api_server => {
+            info!("API server has stopped.");
+        }
+        _ = webhook_server => {
+            info!("Webhook server has stopped.");
+        }
+        _ = async {
+            match web_server {
+                Some(server) => server.await,
+                None => std::future::pending().await,
             }
+        } => {
+            info!("Web UI server has stopped.");
         }
-    } else {
-        // Run only the API server
-        api_server.await?;
     }
+    for handle in handles {
+        handle.stop(true).await;
+    }
+    if !outages.is_empty() {
+        log::warn!(
+            "Failing {} alert(s) still waiting for their exchange",
+            outages.len()
+        );
+        crate::bot::dispatch::abandon_all(&app_state, &outages);
+    }
+    if let Err(e) = crate::app_state::flush(&app_state) {
+        log::error!("Failed to save pending state changes: {}", e);
+    }
+
+    Ok(())
+}
 
+/// Start the configured price streams.
+#[cfg(any(feature = "exchange-binance", feature = "exchange-bybit"))]
+fn start_price_streams(config: &PriceCacheConfig, prices: Arc<PriceCache>) -> std::io::Result<()> {
+    let streams = crate::exchange::streams::spawn(config, prices)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    if streams > 0 {
+        info!("Streaming prices over {} connection(s)", streams);
+    }
     Ok(())
 }
+
+/// Builds without the Binance and Bybit adapters have no price streams to start.
+#[cfg(not(any(feature = "exchange-binance", feature = "exchange-bybit")))]
+fn start_price_streams(config: &PriceCacheConfig, _prices: Arc<PriceCache>) -> std::io::Result<()> {
+    if config.streams.is_empty() {
+        return Ok(());
+    }
+    Err(std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        "price streams are configured but xtrade was built without Binance and Bybit",
+    ))
+}
+
+/// Forward a read of the Web UI, with its credentials, to the current API version of the
+/// server at `api_url`, so the UI reads reports, bots and server facts from its own origin.
+#[cfg(feature = "web-ui")]
+async fn proxy_api(
+    req: HttpRequest,
+    api_url: web::Data<String>,
+    client: web::Data<reqwest::Client>,
+) -> HttpResponse {
+    let mut url = format!(
+        "{}{}{}",
+        api_url.get_ref(),
+        crate::bot::api::API_PREFIX,
+        req.path()
+    );
+    if !req.query_string().is_empty() {
+        url.push('?');
+        url.push_str(req.query_string());
+    }
+    let mut request = client.get(&url);
+    if let Some(authorization) = req.headers().get(header::AUTHORIZATION) {
+        request = request.header(reqwest::header::AUTHORIZATION, authorization.as_bytes());
+    }
+    let response = match request.send().await {
+        Ok(response) => response,
+        Err(e) => return HttpResponse::BadGateway().body(e.to_string()),
+    };
+    let status =
+        StatusCode::from_u16(response.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+    match response.bytes().await {
+        Ok(body) => HttpResponse::build(status)
+            .content_type("application/json")
+            .body(body),
+        Err(e) => HttpResponse::BadGateway().body(e.to_string()),
+    }
+}
+
+/// Start the static Web UI server if enabled.
+#[cfg(feature = "web-ui")]
+#[allow(clippy::too_many_arguments)]
+fn start_web_client(
+    enable: bool,
+    bind_address: &str,
+    port: u16,
+    static_files: PathBuf,
+    api_url: String,
+    client: web::Data<reqwest::Client>,
+    compression: web::Data<CompressionConfig>,
+    listeners: &mut Listeners,
+) -> std::io::Result<Option<Server>> {
+    if !enable {
+        return Ok(None);
+    }
+
+    info!(
+        "Starting Web UI server on {}:{} serving files from: {}",
+        bind_address,
+        port,
+        static_files.display()
+    );
+
+    let api_url = web::Data::new(api_url);
+    let compress = compression.enabled;
+    let server = HttpServer::new(move || {
+        // Serve static files
+        App::new()
+            .app_data(api_url.clone())
+            .app_data(client.clone())
+            .app_data(compression.clone())
+            .wrap(from_fn(compression::mark))
+            .wrap(Condition::new(compress, Compress::default()))
+            .wrap(from_fn(compression::unmark))
+            .route("/reports/{report:.*}", web::get().to(proxy_api))
+            .route("/bots", web::get().to(proxy_api))
+            .route("/bots/{bot_id}/timeline", web::get().to(proxy_api))
+            .route("/admin/info", web::get().to(proxy_api))
+            .service(fs::Files::new("/", static_files.clone()).index_file("index.html"))
+    })
+    .listen(listeners.take("web_client", bind_address, port)?)?
+    .run();
+
+    Ok(Some(server))
+}
+
+/// Builds without the `web-ui` feature have no Web UI server to start.
+#[cfg(not(feature = "web-ui"))]
+#[allow(clippy::too_many_arguments)]
+fn start_web_client(
+    enable: bool,
+    _bind_address: &str,
+    _port: u16,
+    _static_files: PathBuf,
+    _api_url: String,
+    _client: web::Data<reqwest::Client>,
+    _compression: web::Data<CompressionConfig>,
+    _listeners: &mut Listeners,
+) -> std::io::Result<Option<Server>> {
+    if enable {
+        log::warn!("Web UI is enabled but xtrade was built without the `web-ui` feature.");
+    }
+    Ok(None)
+}
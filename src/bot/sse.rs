@@ -0,0 +1,302 @@
+// src/bot/sse.rs
+//! # Server-Sent Events
+//!
+//! `GET /events` streams, as `text/event-stream`, what WebSocket sessions get by watching
+//! and subscribing, for clients that cannot hold a WebSocket: the timeline events of the
+//! tenant's bots (`state_change`, `alert`, `trade` and `error`) and the [`ServerEvent`]s.
+//! Each frame is named after its event type and carries the event as JSON:
+//!
+//! ```text
+//! id: 42
+//! event: trade
+//! data: {"seq":42,"timestamp":"…","bot_id":"alpha","kind":"trade","summary":"…"}
+//! ```
+//!
+//! Timeline events are identified by their sequence number. A client that reconnects with
+//! the `Last-Event-ID` header, as browsers do, or the `last_event_id` query, gets the events
+//! it missed that are still in the log first; other clients only get what happens after
+//! they connect. `bot_id` and `kinds` narrow the stream down. Comments keep idle streams
+//! open through proxies.
+use crate::app_state::AppState;
+use crate::bot::api::Tenant;
+use crate::bot::secrets;
+use crate::bot::state::events::Event;
+use crate::bot::ws::{ServerEvent, ServerEvents};
+use crate::errors::AppError;
+use actix_web::http::header::{CACHE_CONTROL, CONTENT_TYPE};
+use actix_web::web::Bytes;
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+
+/// How often the event log is checked for new events.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Longest an idle stream goes without a comment.
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+/// Milliseconds clients are asked to wait before reconnecting.
+const RETRY_MS: u64 = 3000;
+/// Timeline events read from the log at a time.
+const BATCH: usize = 100;
+/// Frames waiting for a slow client before the stream waits for it.
+const BUFFER: usize = 64;
+
+/// Which events `GET /events` streams.
+#[derive(Debug, Default, Deserialize)]
+pub struct EventStreamQuery {
+    /// Only the events of this bot
+    pub bot_id: Option<String>,
+    /// Only these event types, comma-separated, e.g. `alert,trade`
+    pub kinds: Option<String>,
+    /// Resume after this event, for clients that cannot send `Last-Event-ID`
+    pub last_event_id: Option<u64>,
+}
+
+/// The events a stream carries.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Selection {
+    bot_id: Option<String>,
+    /// Event types by name; all if empty
+    kinds: Vec<String>,
+}
+
+const KINDS: [&str; 5] = ["state_change", "alert", "trade", "error", "state_reloaded"];
+
+impl EventStreamQuery {
+    fn selection(&self) -> Result<Selection, AppError> {
+        let kinds = (self.kinds.as_deref().unwrap_or_default().split(','))
+            .map(str::trim)
+            .filter(|kind| !kind.is_empty())
+            .map(|kind| {
+                if KINDS.contains(&kind) {
+                    Ok(kind.to_string())
+                } else {
+                    Err(AppError::InvalidInput(format!(
+                        "Unknown event type '{}'; expected one of: {}",
+                        kind,
+                        KINDS.join(", ")
+                    )))
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Selection {
+            bot_id: self.bot_id.clone(),
+            kinds,
+        })
+    }
+}
+
+impl Selection {
+    fn takes_kind(&self, kind: &str) -> bool {
+        self.kinds.is_empty() || self.kinds.iter().any(|k| k == kind)
+    }
+
+    fn takes(&self, event: &Event) -> bool {
+        self.bot_id.as_ref().is_none_or(|id| *id == event.bot_id)
+            && self.takes_kind(&event.kind.to_string())
+    }
+}
+
+/// A frame of the stream; `id` is left out for events that cannot be resumed from.
+fn frame(id: Option<u64>, event: &str, data: &Value) -> Bytes {
+    let mut frame = String::new();
+    if let Some(id) = id {
+        frame.push_str(&format!("id: {}\n", id));
+    }
+    frame.push_str(&format!("event: {}\ndata: {}\n\n", event, data));
+    Bytes::from(frame)
+}
+
+fn timeline_frame(event: &Event, redact: bool) -> Option<Bytes> {
+    let mut data = serde_json::to_value(event).ok()?;
+    if redact {
+        secrets::redact(&mut data);
+    }
+    Some(frame(Some(event.seq), &event.kind.to_string(), &data))
+}
+
+fn server_frame(event: &ServerEvent) -> Option<Bytes> {
+    let data = serde_json::to_value(event).ok()?;
+    let name = data.get("event")?.as_str()?.to_string();
+    Some(frame(None, &name, &data))
+}
+
+/// The event to resume after, from `Last-Event-ID` or else the query.
+fn resume_after(req: &HttpRequest, query: &EventStreamQuery) -> Option<u64> {
+    (req.headers().get("Last-Event-ID"))
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+        .or(query.last_event_id)
+}
+
+/// What a stream needs to read the events of its tenant.
+struct Source {
+    state: Arc<Mutex<AppState>>,
+    tenant: Tenant,
+    selection: Selection,
+}
+
+impl Source {
+    /// Timeline events after `after` for the stream, and the sequence number read up to.
+    fn read(&self, after: u64) -> Result<(Vec<Event>, u64, bool), AppError> {
+        let mut state = self.state.lock().map_err(|_| AppError::LockError)?;
+        let events = state.scoped(self.tenant.name()).events_after(after, BATCH);
+        let full = events.len() == BATCH;
+        let last = events.last().map_or(after, |event| event.seq);
+        let events = events
+            .into_iter()
+            .filter(|event| self.selection.takes(event))
+            .collect();
+        Ok((events, last, full))
+    }
+}
+
+/// Send the events of `source` after `last_seq`, and the server events, to `frames` until
+/// the client goes away.
+async fn produce(
+    source: Source,
+    mut last_seq: u64,
+    server_events: ServerEvents,
+    frames: mpsc::Sender<Bytes>,
+) {
+    let mut server_events = server_events.subscribe();
+    let redact = source.tenant.is_read_only();
+    let states = source.selection.takes_kind("state_reloaded");
+    if frames
+        .send(Bytes::from(format!("retry: {}\n\n", RETRY_MS)))
+        .await
+        .is_err()
+    {
+        return;
+    }
+    let mut idle = Duration::ZERO;
+    loop {
+        let (events, last, full) = match source.read(last_seq) {
+            Ok(read) => read,
+            Err(e) => {
+                log::error!("Event stream stopped: {}", e);
+                return;
+            }
+        };
+        last_seq = last;
+        for frame in (events.iter()).filter_map(|event| timeline_frame(event, redact)) {
+            idle = Duration::ZERO;
+            if frames.send(frame).await.is_err() {
+                return; // Client went away
+            }
+        }
+        if full {
+            continue;
+        }
+        let frame = tokio::select! {
+            _ = tokio::time::sleep(POLL_INTERVAL) => {
+                idle += POLL_INTERVAL;
+                (idle >= KEEP_ALIVE).then(|| Bytes::from_static(b": keep-alive\n\n"))
+            }
+            event = server_events.recv() => match event {
+                Ok(event) if states => server_frame(&event),
+                Ok(_) => None,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    log::warn!("Event stream missed {} server event(s)", missed);
+                    None
+                }
+                // The server is shutting down
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+        };
+        if let Some(frame) = frame {
+            idle = Duration::ZERO;
+            if frames.send(frame).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+#[get("/events")]
+pub async fn event_stream(
+    req: HttpRequest,
+    data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
+    query: web::Query<EventStreamQuery>,
+) -> Result<HttpResponse, AppError> {
+    let selection = query.selection()?;
+    let after = match resume_after(&req, &query) {
+        Some(after) => after,
+        None => data
+            .lock()
+            .map_err(|_| AppError::LockError)?
+            .events
+            .last_seq(),
+    };
+    let server_events = (req.app_data::<web::Data<ServerEvents>>())
+        .map(|events| events.get_ref().clone())
+        .unwrap_or_default();
+    let source = Source {
+        state: data.get_ref().clone(),
+        tenant,
+        selection,
+    };
+    let (frames, received) = mpsc::channel(BUFFER);
+    actix_web::rt::spawn(produce(source, after, server_events, frames));
+    let body = futures_util::stream::unfold(received, |mut received| async move {
+        let frame = received.recv().await?;
+        Some((Ok::<_, actix_web::Error>(frame), received))
+    });
+    Ok(HttpResponse::Ok()
+        .insert_header((CONTENT_TYPE, "text/event-stream"))
+        .insert_header((CACHE_CONTROL, "no-cache"))
+        .streaming(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::state::events::{EventKind, EventLog};
+    use chrono::Utc;
+
+    #[test]
+    fn frames_carry_the_event_type_and_resumable_ids() {
+        let mut log = EventLog::default();
+        let now = Utc::now();
+        log.record(now, "alpha", EventKind::Alert, "alert".into(), None);
+        log.record(now, "beta", EventKind::Trade, "bought".into(), None);
+        log.record(now, "alpha", EventKind::Trade, "sold".into(), None);
+        let after: Vec<_> = log.after(1).map(|event| event.seq).collect();
+        assert_eq!(after, [2, 3]);
+        assert_eq!(log.last_seq(), 3);
+
+        let query = EventStreamQuery {
+            bot_id: Some("alpha".to_string()),
+            kinds: Some("trade, state_reloaded".to_string()),
+            last_event_id: None,
+        };
+        let selection = query.selection().unwrap();
+        let taken: Vec<_> = log
+            .after(0)
+            .filter(|event| selection.takes(event))
+            .collect();
+        assert_eq!(taken.len(), 1);
+        let frame = timeline_frame(taken[0], false).unwrap();
+        let text = std::str::from_utf8(&frame).unwrap();
+        assert!(text.starts_with("id: 3\nevent: trade\ndata: {"));
+        assert!(text.ends_with("}\n\n") && text.contains("\"summary\":\"sold\""));
+
+        let reloaded = ServerEvent::StateReloaded { reloaded_at: now };
+        let frame = server_frame(&reloaded).unwrap();
+        assert!(std::str::from_utf8(&frame)
+            .unwrap()
+            .starts_with("event: state_reloaded\ndata: "));
+
+        let unknown = EventStreamQuery {
+            kinds: Some("trades".to_string()),
+            ..EventStreamQuery::default()
+        };
+        assert!(matches!(
+            unknown.selection(),
+            Err(AppError::InvalidInput(_))
+        ));
+    }
+}
@@ -0,0 +1,182 @@
+// src/bot/state/alerts.rs
+//! # Alert Log
+//!
+//! Every alert a listener accepted, persisted with the state: the raw payload, why it was
+//! refused if it never reached the dispatcher, and otherwise the outcome of the dispatch. An
+//! alert that failed, e.g. because the bot's credentials were wrong, can be replayed from its
+//! payload once the cause is fixed; the replay is logged as an alert of its own.
+use crate::bot::dispatch::{ExecutionResult, ExecutionStatus};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Alerts kept before the oldest are dropped.
+pub const MAX_ALERTS: usize = 1_000;
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct AlertRecord {
+    pub id: String,
+    pub received_at: DateTime<Utc>,
+    /// Bot the alert was sent to, as its tenant names it
+    pub bot_id: String,
+    pub listener_id: String,
+    /// Tenant owning the bot, when tenancy is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    /// Request body, as received
+    pub payload: String,
+    /// ID of the alert this one replayed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replay_of: Option<String>,
+    /// Why the alert was refused, or could not be dispatched
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Outcome of the dispatch; unset until it finished
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<ExecutionResult>,
+}
+
+impl AlertRecord {
+    /// Whether the alert was refused, or its order failed.
+    pub fn failed(&self) -> bool {
+        self.error.is_some()
+            || self
+                .result
+                .as_ref()
+                .is_some_and(|result| result.status == ExecutionStatus::Failed)
+    }
+
+    /// `received`, `refused`, or the status of the dispatch outcome.
+    pub fn status(&self) -> String {
+        match (&self.error, &self.result) {
+            (Some(_), _) => "refused".to_string(),
+            (None, Some(result)) => result.status.to_string(),
+            (None, None) => "received".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(transparent)]
+pub struct AlertLog {
+    alerts: VecDeque<AlertRecord>,
+}
+
+impl AlertLog {
+    pub fn is_empty(&self) -> bool {
+        self.alerts.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.alerts.len()
+    }
+
+    /// Append `alert`, dropping the oldest once [`MAX_ALERTS`] is reached.
+    pub fn record(&mut self, alert: AlertRecord) {
+        if self.alerts.len() >= MAX_ALERTS {
+            self.alerts.pop_front();
+        }
+        self.alerts.push_back(alert);
+    }
+
+    /// Drop the alerts received before `before`, returning them oldest first.
+    pub fn prune(&mut self, before: DateTime<Utc>) -> Vec<AlertRecord> {
+        let (pruned, kept): (VecDeque<_>, _) =
+            (self.alerts.drain(..)).partition(|alert| alert.received_at < before);
+        self.alerts = kept;
+        pruned.into()
+    }
+
+    /// The alert `id` received for a bot of `tenant`.
+    pub fn get(&self, tenant: Option<&str>, id: &str) -> Option<&AlertRecord> {
+        self.alerts
+            .iter()
+            .find(|alert| alert.id == id && alert.tenant.as_deref() == tenant)
+    }
+
+    /// Record how the alert `id` ended: the outcome of its dispatch, or why it had none.
+    /// Returns `false` if the alert is unknown, e.g. because it was dropped meanwhile.
+    pub fn settle(&mut self, id: &str, outcome: Result<&ExecutionResult, String>) -> bool {
+        let Some(alert) = self.alerts.iter_mut().find(|alert| alert.id == id) else {
+            return false;
+        };
+        match outcome {
+            Ok(result) => alert.result = Some(result.clone()),
+            Err(error) => alert.error = Some(error),
+        }
+        true
+    }
+
+    /// Alerts of the bot `bot_id` of `tenant` received on `day`, oldest first.
+    pub fn received_on(
+        &self,
+        tenant: Option<&str>,
+        bot_id: &str,
+        day: NaiveDate,
+    ) -> Vec<AlertRecord> {
+        self.alerts
+            .iter()
+            .filter(|alert| alert.bot_id == bot_id && alert.tenant.as_deref() == tenant)
+            .filter(|alert| alert.received_at.date_naive() == day)
+            .cloned()
+            .collect()
+    }
+
+    /// Alerts of the bot `bot_id` of `tenant`, newest first, at most `limit`.
+    pub fn list(&self, tenant: Option<&str>, bot_id: &str, limit: usize) -> Vec<AlertRecord> {
+        self.alerts
+            .iter()
+            .rev()
+            .filter(|alert| alert.bot_id == bot_id && alert.tenant.as_deref() == tenant)
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alert(id: &str, bot_id: &str) -> AlertRecord {
+        AlertRecord {
+            id: id.to_string(),
+            received_at: Utc::now(),
+            bot_id: bot_id.to_string(),
+            listener_id: "tv".to_string(),
+            tenant: None,
+            payload: "{}".to_string(),
+            replay_of: None,
+            error: None,
+            result: None,
+        }
+    }
+
+    #[test]
+    fn alerts_are_listed_newest_first_and_bounded() {
+        let mut log = AlertLog::default();
+        for i in 0..MAX_ALERTS + 2 {
+            let bot_id = if i % 2 == 0 { "alpha" } else { "beta" };
+            log.record(alert(&format!("alert-{}", i), bot_id));
+        }
+        assert_eq!(log.len(), MAX_ALERTS);
+        assert!(log.get(None, "alert-0").is_none());
+
+        let latest = log.list(None, "alpha", 2);
+        assert_eq!(
+            latest.iter().map(|a| a.id.as_str()).collect::<Vec<_>>(),
+            [
+                format!("alert-{}", MAX_ALERTS),
+                format!("alert-{}", MAX_ALERTS - 2)
+            ]
+        );
+        assert_eq!(latest[0].status(), "received");
+        assert!(log.list(Some("acme"), "alpha", 2).is_empty());
+
+        let id = format!("alert-{}", MAX_ALERTS + 1);
+        assert!(log.settle(&id, Err("not a tradingview alert".to_string())));
+        assert!(log.get(None, &id).unwrap().failed());
+        assert_eq!(log.get(None, &id).unwrap().status(), "refused");
+        assert!(!log.settle("alert-0", Err("too late".to_string())));
+    }
+}
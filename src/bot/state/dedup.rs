@@ -0,0 +1,112 @@
+// src/bot/state/dedup.rs
+//! # Alert Deduplication
+//!
+//! TradingView re-sends an alert when it does not get an answer fast enough, so the same
+//! signal can arrive several times. Each webhook alert is remembered by its bot, listener and
+//! the hash of its body for `dedup_window_secs`; an identical alert within that window gets
+//! the outcome of the first one instead of trading again. Recent alerts are persisted with the
+//! state, so a restart in between does not let a repeat through.
+use crate::bot::dispatch::ExecutionResult;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Identity of a webhook alert.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct AlertKey {
+    /// State key of the bot the alert is for
+    pub bot_id: String,
+    pub listener_id: String,
+    /// Hex SHA-256 of the request body
+    pub hash: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct RecentAlert {
+    #[serde(flatten)]
+    pub key: AlertKey,
+    pub received_at: DateTime<Utc>,
+    /// Outcome of the alert; unset while it is being executed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<ExecutionResult>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(transparent)]
+pub struct RecentAlerts(Vec<RecentAlert>);
+
+impl RecentAlerts {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// The alert like `key` received within `window_secs` before `now`, if any. Otherwise
+    /// `key` is remembered as received at `now` and `None` returned; [`settle`](Self::settle)
+    /// it once it was executed.
+    pub fn claim(
+        &mut self,
+        key: &AlertKey,
+        now: DateTime<Utc>,
+        window_secs: u64,
+    ) -> Option<RecentAlert> {
+        let cutoff = now - Duration::seconds(window_secs as i64);
+        self.0.retain(|alert| alert.received_at > cutoff);
+        if let Some(alert) = self.0.iter().find(|alert| alert.key == *key) {
+            return Some(alert.clone());
+        }
+        self.0.push(RecentAlert {
+            key: key.clone(),
+            received_at: now,
+            result: None,
+        });
+        None
+    }
+
+    /// Record the outcome of the alert claimed as `key`, or forget it if it failed before it
+    /// had one, so that it can be sent again.
+    pub fn settle(&mut self, key: &AlertKey, result: Option<&ExecutionResult>) {
+        match result {
+            Some(result) => {
+                if let Some(alert) = self.0.iter_mut().find(|alert| alert.key == *key) {
+                    alert.result = Some(result.clone());
+                }
+            }
+            None => self.0.retain(|alert| alert.key != *key),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alerts_repeat_within_the_window() {
+        let mut alerts = RecentAlerts::default();
+        let now = Utc::now();
+        let key = AlertKey {
+            bot_id: "alpha".to_string(),
+            listener_id: "tv".to_string(),
+            hash: "ab12".to_string(),
+        };
+        let other = AlertKey {
+            listener_id: "manual".to_string(),
+            ..key.clone()
+        };
+
+        assert_eq!(alerts.claim(&key, now, 60), None);
+        assert_eq!(alerts.claim(&key, now, 60).unwrap().received_at, now);
+        assert_eq!(alerts.claim(&other, now, 60), None);
+
+        // An alert that failed to execute may be sent again
+        alerts.settle(&other, None);
+        assert_eq!(alerts.claim(&other, now, 60), None);
+
+        let later = now + Duration::seconds(61);
+        assert_eq!(alerts.claim(&key, later, 60), None);
+        assert_eq!(alerts.len(), 1);
+    }
+}
@@ -0,0 +1,307 @@
+// src/bot/state/events.rs
+//! # Event Log
+//!
+//! A bounded, persisted log of what happened to each bot: state changes made through the
+//! registry, received alerts, executed trades and errors. Every event carries a sequence
+//! number that increases monotonically for the lifetime of the state file, which makes it a
+//! stable cursor for paging through a bot's timeline.
+//!
+//! Updates of bots and listeners record the fields they changed as the `changes` of their
+//! event's details, one [`FieldChange`] each, with credentials masked.
+use crate::bot::secrets::{self, SECRET_FIELDS};
+use chrono::{DateTime, Utc};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeSet, VecDeque};
+use std::fmt;
+
+/// Events kept before the oldest are dropped.
+pub const MAX_EVENTS: usize = 10_000;
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    StateChange,
+    Alert,
+    Trade,
+    Error,
+}
+
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            EventKind::StateChange => "state_change",
+            EventKind::Alert => "alert",
+            EventKind::Trade => "trade",
+            EventKind::Error => "error",
+        };
+        write!(f, "{}", kind)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Event {
+    pub seq: u64,
+    pub timestamp: DateTime<Utc>,
+    pub bot_id: String,
+    pub kind: EventKind,
+    pub summary: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>,
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{}] {} {} {}: {}",
+            self.seq,
+            self.timestamp.to_rfc3339(),
+            self.bot_id,
+            self.kind,
+            self.summary
+        )
+    }
+}
+
+/// A field an update changed, from its value before to the one after.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct FieldChange {
+    /// Name of the field; fields of nested objects are joined by dots, as `filter.symbols`
+    pub field: String,
+    pub before: Value,
+    pub after: Value,
+}
+
+impl FieldChange {
+    /// The fields that differ between `before` and `after`, in field order. Credentials
+    /// show as masked, unless they name an environment variable.
+    pub fn between<T: Serialize>(before: &T, after: &T) -> Vec<Self> {
+        let json = |value| serde_json::to_value(value).unwrap_or_default();
+        let mut changes = Vec::new();
+        diff("", &json(before), &json(after), false, &mut changes);
+        changes
+    }
+}
+
+fn diff(field: &str, before: &Value, after: &Value, secret: bool, out: &mut Vec<FieldChange>) {
+    if before == after {
+        return;
+    }
+    if let (Value::Object(before), Value::Object(after)) = (before, after) {
+        let names: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+        for name in names {
+            let nested = if field.is_empty() {
+                name.clone()
+            } else {
+                format!("{}.{}", field, name)
+            };
+            let secret = secret || SECRET_FIELDS.contains(&name.as_str());
+            let value = |object: &serde_json::Map<String, Value>| {
+                object.get(name).cloned().unwrap_or_default()
+            };
+            diff(&nested, &value(before), &value(after), secret, out);
+        }
+        return;
+    }
+    let mask = |value: &Value| {
+        if secret {
+            secrets::mask(value)
+        } else {
+            value.clone()
+        }
+    };
+    out.push(FieldChange {
+        field: field.to_string(),
+        before: mask(before),
+        after: mask(after),
+    });
+}
+
+impl fmt::Display for FieldChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let removed = format!("- {}: {}", self.field, self.before);
+        let added = format!("+ {}: {}", self.field, self.after);
+        write!(f, "{}\n{}", removed.red(), added.green())
+    }
+}
+
+/// `changes` of `what`, one before/after pair per field, for update responses.
+pub fn write_changes(
+    f: &mut fmt::Formatter<'_>,
+    what: &str,
+    changes: &[FieldChange],
+) -> fmt::Result {
+    if changes.is_empty() {
+        return write!(f, "{} unchanged", what);
+    }
+    write!(f, "{} updated:", what)?;
+    for change in changes {
+        write!(f, "\n{}", change)?;
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct EventLog {
+    #[serde(default)]
+    next_seq: u64,
+    #[serde(default)]
+    events: VecDeque<Event>,
+}
+
+impl EventLog {
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Append an event, dropping the oldest once [`MAX_EVENTS`] is reached.
+    pub fn record(
+        &mut self,
+        timestamp: DateTime<Utc>,
+        bot_id: &str,
+        kind: EventKind,
+        summary: String,
+        details: Option<serde_json::Value>,
+    ) -> &Event {
+        self.next_seq += 1;
+        if self.events.len() >= MAX_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(Event {
+            seq: self.next_seq,
+            timestamp,
+            bot_id: bot_id.to_string(),
+            kind,
+            summary,
+            details,
+        });
+        self.events.back().expect("event was just pushed")
+    }
+
+    /// Sequence number of the newest event ever recorded (0 if none was).
+    pub fn last_seq(&self) -> u64 {
+        self.next_seq
+    }
+
+    /// Whether events newer than `seq` were recorded but are no longer kept, or `seq` is
+    /// ahead of the log, as when the state file was replaced.
+    pub fn lost_after(&self, seq: u64) -> bool {
+        let oldest = (self.events.front()).map_or(self.next_seq + 1, |event| event.seq);
+        seq > self.next_seq || oldest > seq + 1
+    }
+
+    /// Events still kept that are newer than `seq`, oldest first.
+    pub fn after(&self, seq: u64) -> impl Iterator<Item = &Event> {
+        let start = self.events.partition_point(|event| event.seq <= seq);
+        self.events.range(start..)
+    }
+
+    /// Drop the events that happened before `before`, returning them oldest first. Sequence
+    /// numbers are not reused.
+    pub fn prune(&mut self, before: DateTime<Utc>) -> Vec<Event> {
+        let (pruned, kept): (VecDeque<_>, _) =
+            (self.events.drain(..)).partition(|event| event.timestamp < before);
+        self.events = kept;
+        pruned.into()
+    }
+
+    /// Events of `bot_id`, newest first, starting below the `before` cursor and not older
+    /// than `since`. Returns at most `limit` events and the cursor of the next page, if any.
+    pub fn page(
+        &self,
+        bot_id: &str,
+        before: Option<u64>,
+        since: Option<DateTime<Utc>>,
+        limit: usize,
+    ) -> (Vec<Event>, Option<u64>) {
+        let mut matching = self
+            .events
+            .iter()
+            .rev()
+            .filter(|event| event.bot_id == bot_id)
+            .filter(|event| before.is_none_or(|before| event.seq < before))
+            .take_while(|event| since.is_none_or(|since| event.timestamp >= since));
+
+        let events: Vec<Event> = matching.by_ref().take(limit).cloned().collect();
+        let next_cursor = match (matching.next(), events.last()) {
+            (Some(_), Some(last)) => Some(last.seq),
+            _ => None,
+        };
+        (events, next_cursor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_page_with_cursor_and_since() {
+        let start = Utc::now();
+        let mut log = EventLog::default();
+        for i in 0..5 {
+            log.record(
+                start + Duration::minutes(i),
+                "a",
+                EventKind::StateChange,
+                format!("change {}", i),
+                None,
+            );
+            log.record(start, "b", EventKind::StateChange, "other".into(), None);
+        }
+
+        let (first, cursor) = log.page("a", None, None, 2);
+        assert_eq!(
+            first.iter().map(|e| e.summary.as_str()).collect::<Vec<_>>(),
+            ["change 4", "change 3"]
+        );
+        let (second, cursor) = log.page("a", cursor, None, 2);
+        assert_eq!(second[0].summary, "change 2");
+        let (last, cursor) = log.page("a", cursor, None, 2);
+        assert_eq!(last.len(), 1);
+        assert_eq!(cursor, None);
+
+        let (recent, _) = log.page("a", None, Some(start + Duration::minutes(3)), 10);
+        assert_eq!(recent.len(), 2);
+    }
+
+    #[test]
+    fn changes_name_nested_fields_and_mask_credentials() {
+        let before = serde_json::json!({
+            "name": "one",
+            "api_secret": "hunter2",
+            "api_key": "env:KEY",
+            "filter": {"symbols": ["BTC"], "sides": []},
+            "dry_run": false,
+        });
+        let after = serde_json::json!({
+            "name": "two",
+            "api_secret": "hunter3",
+            "api_key": "env:OTHER_KEY",
+            "filter": {"symbols": ["BTC", "ETH"], "sides": []},
+            "dry_run": false,
+            "budget": 100,
+        });
+        let changes = FieldChange::between(&before, &after);
+        let fields: Vec<_> = changes.iter().map(|c| c.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            ["api_key", "api_secret", "budget", "filter.symbols", "name"]
+        );
+        assert_eq!(changes[0].after, "env:OTHER_KEY");
+        assert_eq!(
+            (&changes[1].before, &changes[1].after),
+            (&"********".into(), &"********".into())
+        );
+        assert_eq!(changes[2].before, Value::Null);
+        assert!(!serde_json::to_string(&changes).unwrap().contains("hunter"));
+        assert!(FieldChange::between(&before, &before).is_empty());
+    }
+}
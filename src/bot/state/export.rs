@@ -0,0 +1,351 @@
+// src/bot/state/export.rs
+//! # State Export
+//!
+//! The bots of a state, with their listeners, as a JSON, YAML or TOML document that can be
+//! imported into another state: from an offline state file into a running server and back,
+//! or into a backup of the configuration. Histories, timelines and other records stay where
+//! they are.
+//!
+//! An export may leave out the secrets. Credentials naming an environment variable are kept,
+//! as they hold none; the others are dropped, and listener secrets emptied. Importing a bot
+//! that exists already keeps the secrets the import leaves out, so a redacted export can be
+//! edited and imported back. New listeners without a secret are given one.
+use crate::bot::model::{Bot, SubAccount};
+use crate::bot::secrets::ENV_PREFIX;
+use crate::errors::AppError;
+use crate::utils::crypto::random_secret;
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use yaml_rust2::{Yaml, YamlEmitter, YamlLoader};
+
+/// Document format of an export.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl ExportFormat {
+    /// The format the extension of `path` names, if it names one.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()?.to_str()?.parse().ok()
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "application/json",
+            ExportFormat::Yaml => "application/yaml",
+            ExportFormat::Toml => "application/toml",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(ExportFormat::Json),
+            "yaml" | "yml" => Ok(ExportFormat::Yaml),
+            "toml" => Ok(ExportFormat::Toml),
+            _ => Err(format!("format must be json, yaml or toml, not '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportFormat::Json => write!(f, "json"),
+            ExportFormat::Yaml => write!(f, "yaml"),
+            ExportFormat::Toml => write!(f, "toml"),
+        }
+    }
+}
+
+/// Bots of a state, by ID.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct StateExport {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exported_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub bots: BTreeMap<String, Bot>,
+}
+
+impl StateExport {
+    /// Drop the secrets of every bot, keeping references to the environment.
+    pub fn redact(&mut self) {
+        for bot in self.bots.values_mut() {
+            for value in [
+                &mut bot.api_key,
+                &mut bot.api_secret,
+                &mut bot.private_key,
+                &mut bot.webhook_secret,
+            ] {
+                redact(value);
+            }
+            for account in &mut bot.accounts {
+                for value in account_credentials(account) {
+                    redact(value);
+                }
+            }
+            for listener in bot.listeners.values_mut() {
+                listener.secret.clear();
+                listener.previous_secret = None;
+            }
+        }
+    }
+
+    /// The export as a `format` document.
+    pub fn render(&self, format: ExportFormat) -> Result<String, AppError> {
+        let failed = |e: String| AppError::InternalServerError(format!("Failed to export: {}", e));
+        match format {
+            ExportFormat::Json => {
+                serde_json::to_string_pretty(self).map_err(|e| failed(e.to_string()))
+            }
+            ExportFormat::Toml => toml::to_string(self).map_err(|e| failed(e.to_string())),
+            ExportFormat::Yaml => {
+                let value = serde_json::to_value(self).map_err(|e| failed(e.to_string()))?;
+                let mut out = String::new();
+                YamlEmitter::new(&mut out)
+                    .dump(&to_yaml(value))
+                    .map_err(|e| failed(e.to_string()))?;
+                out.push('\n');
+                Ok(out)
+            }
+        }
+    }
+
+    /// An export read from a `format` document.
+    pub fn parse(content: &str, format: ExportFormat) -> Result<Self, AppError> {
+        parse_document(content, format)
+            .map_err(|e| AppError::InvalidInput(format!("Invalid {} export: {}", format, e)))
+    }
+}
+
+/// A `format` document read as `T`, e.g. an export or a manifest.
+pub fn parse_document<T: DeserializeOwned>(
+    content: &str,
+    format: ExportFormat,
+) -> Result<T, String> {
+    match format {
+        ExportFormat::Json => serde_json::from_str(content).map_err(|e| e.to_string()),
+        ExportFormat::Toml => toml::from_str(content).map_err(|e| e.to_string()),
+        ExportFormat::Yaml => YamlLoader::load_from_str(content)
+            .map_err(|e| e.to_string())
+            .and_then(|docs| docs.into_iter().next().ok_or("empty document".to_string()))
+            .and_then(from_yaml)
+            .and_then(|value| serde_json::from_value(value).map_err(|e| e.to_string())),
+    }
+}
+
+fn redact(value: &mut Option<String>) {
+    if value.as_deref().is_some_and(|v| !v.starts_with(ENV_PREFIX)) {
+        *value = None;
+    }
+}
+
+fn account_credentials(account: &mut SubAccount) -> [&mut Option<String>; 3] {
+    [
+        &mut account.api_key,
+        &mut account.api_secret,
+        &mut account.private_key,
+    ]
+}
+
+/// Give the imported `bot` the secrets it leaves out from `existing`, the bot it replaces,
+/// and new secrets to its listeners that still have none.
+///
+/// Returns a warning for each listener given a new secret.
+pub fn restore_secrets(bot: &mut Bot, existing: Option<&Bot>) -> Vec<String> {
+    if let Some(existing) = existing {
+        for (value, kept) in [
+            (&mut bot.api_key, &existing.api_key),
+            (&mut bot.api_secret, &existing.api_secret),
+            (&mut bot.private_key, &existing.private_key),
+            (&mut bot.webhook_secret, &existing.webhook_secret),
+        ] {
+            if value.is_none() {
+                value.clone_from(kept);
+            }
+        }
+        for account in &mut bot.accounts {
+            let Some(kept) = existing.accounts.iter().find(|a| a.name == account.name) else {
+                continue;
+            };
+            let mut kept = kept.clone();
+            for (value, kept) in account_credentials(account)
+                .into_iter()
+                .zip(account_credentials(&mut kept))
+            {
+                if value.is_none() {
+                    *value = kept.take();
+                }
+            }
+        }
+        for (listener_id, listener) in &mut bot.listeners {
+            let Some(kept) = existing.listeners.get(listener_id) else {
+                continue;
+            };
+            if listener.secret.is_empty() {
+                listener.secret.clone_from(&kept.secret);
+                listener.previous_secret.clone_from(&kept.previous_secret);
+            }
+        }
+    }
+
+    let mut warnings = Vec::new();
+    let mut listener_ids: Vec<_> = (bot.listeners.keys())
+        .filter(|id| existing.is_none_or(|existing| !existing.listeners.contains_key(*id)))
+        .cloned()
+        .collect();
+    listener_ids.sort();
+    for listener_id in listener_ids {
+        let listener = bot.listeners.get_mut(&listener_id).expect("listed above");
+        if listener.secret.is_empty() && listener.verify_secret {
+            listener.secret = random_secret();
+            warnings.push(format!(
+                "listener '{}' was given a new secret; update the alerts sent to it",
+                listener_id
+            ));
+        }
+    }
+    warnings
+}
+
+fn to_yaml(value: Value) -> Yaml {
+    match value {
+        Value::Null => Yaml::Null,
+        Value::Bool(b) => Yaml::Boolean(b),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => Yaml::Integer(i),
+            None => Yaml::Real(n.to_string()),
+        },
+        Value::String(s) => Yaml::String(s),
+        Value::Array(items) => Yaml::Array(items.into_iter().map(to_yaml).collect()),
+        Value::Object(map) => Yaml::Hash(
+            map.into_iter()
+                .map(|(key, value)| (Yaml::String(key), to_yaml(value)))
+                .collect(),
+        ),
+    }
+}
+
+fn from_yaml(yaml: Yaml) -> Result<Value, String> {
+    Ok(match yaml {
+        Yaml::Null => Value::Null,
+        Yaml::Boolean(b) => Value::Bool(b),
+        Yaml::Integer(i) => Value::from(i),
+        Yaml::Real(s) => s
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map(Value::Number)
+            .ok_or_else(|| format!("'{}' is not a number", s))?,
+        Yaml::String(s) => Value::String(s),
+        Yaml::Array(items) => {
+            Value::Array(items.into_iter().map(from_yaml).collect::<Result<_, _>>()?)
+        }
+        Yaml::Hash(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| Ok((yaml_key(key)?, from_yaml(value)?)))
+                .collect::<Result<_, String>>()?,
+        ),
+        Yaml::Alias(_) | Yaml::BadValue => return Err("unsupported YAML value".to_string()),
+    })
+}
+
+fn yaml_key(key: Yaml) -> Result<String, String> {
+    match key {
+        Yaml::String(s) | Yaml::Real(s) => Ok(s),
+        Yaml::Integer(i) => Ok(i.to_string()),
+        Yaml::Boolean(b) => Ok(b.to_string()),
+        _ => Err("mapping keys must be scalars".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::model::Listener;
+    use rust_decimal::dec;
+
+    fn export() -> StateExport {
+        let bot = Bot {
+            bot_id: "alpha".to_string(),
+            name: "Alpha".to_string(),
+            exchange: "binance".to_string(),
+            api_key: Some("env:ALPHA_KEY".to_string()),
+            api_secret: Some("s3cret".to_string()),
+            trading_fee: Some(dec!(0.001)),
+            accounts: vec![SubAccount {
+                name: "hedge".to_string(),
+                api_secret: Some("sub-secret".to_string()),
+                ..Default::default()
+            }],
+            listeners: [(
+                "tv".to_string(),
+                Listener {
+                    service: "tradingview".to_string(),
+                    secret: "hook".to_string(),
+                    ..Default::default()
+                },
+            )]
+            .into(),
+            ..Default::default()
+        };
+        StateExport {
+            exported_at: Some(Utc::now()),
+            bots: [("alpha".to_string(), bot)].into(),
+        }
+    }
+
+    #[test]
+    fn exports_round_trip_and_redacted_ones_keep_the_secrets_on_import() {
+        let export = export();
+        for format in [ExportFormat::Json, ExportFormat::Yaml, ExportFormat::Toml] {
+            let content = export.render(format).unwrap();
+            assert_eq!(
+                StateExport::parse(&content, format).unwrap(),
+                export,
+                "{}",
+                format
+            );
+        }
+        assert!(StateExport::parse("bots = [", ExportFormat::Toml).is_err());
+        assert_eq!(
+            ExportFormat::from_path(Path::new("bots.YML")),
+            Some(ExportFormat::Yaml)
+        );
+
+        let mut redacted = export.clone();
+        redacted.redact();
+        let bot = &redacted.bots["alpha"];
+        assert_eq!(bot.api_key.as_deref(), Some("env:ALPHA_KEY"));
+        assert_eq!(
+            (&bot.api_secret, &bot.accounts[0].api_secret),
+            (&None, &None)
+        );
+        assert!(bot.listeners["tv"].secret.is_empty());
+
+        let mut imported = bot.clone();
+        let warnings = restore_secrets(&mut imported, export.bots.get("alpha"));
+        assert!(warnings.is_empty());
+        assert_eq!(imported, export.bots["alpha"]);
+
+        // A new bot's listeners get a secret of their own
+        let mut imported = bot.clone();
+        assert_eq!(restore_secrets(&mut imported, None).len(), 1);
+        assert_eq!(imported.listeners["tv"].secret.len(), 64);
+        assert_eq!(imported.api_secret, None);
+    }
+}
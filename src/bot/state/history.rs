@@ -0,0 +1,371 @@
+// src/bot/state/history.rs
+//! # Trade History
+//!
+//! Trade history grows with trading volume, so it is kept out of the state file: every
+//! order sent to an exchange is appended as a JSON line to a per-bot monthly shard,
+//!
+//! ```text
+//! <state_file>.trades/<bot_id>/<YYYY-MM>.jsonl
+//! ```
+//!
+//! and shards are only read when trades are asked for, newest first. Loading and saving the
+//! state — and therefore every bot CRUD request — costs the same no matter how much a bot
+//! traded. Shards of deleted bots are kept as an audit trail, until
+//! [compaction](crate::bot::compaction) prunes the months the retention policy no longer
+//! keeps.
+//!
+//! Each line is a [`TradeRecord`]: the [`ExecutionResult`] with the order the alert asked
+//! for alongside. Lines written before orders were recorded are read with no order.
+//!
+//! [Imported](crate::bot::import) fills are appended to the shards of the months they
+//! happened in, so a shard's lines need not be in order; they are sorted when read.
+use crate::bot::dispatch::{ExecutionResult, Order};
+use serde::{Deserialize, Serialize};
+use std::collections::{btree_map::Entry, BTreeMap};
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+/// Name of the directory of files kept per bot. Storage keys may contain `:` (tenants) or
+/// dots, so anything but ASCII alphanumerics, `-` and `_` is percent-encoded.
+pub(crate) fn dir_name(bot_id: &str) -> String {
+    bot_id
+        .bytes()
+        .map(|byte| match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// The storage key a directory is named after by [`dir_name`].
+fn key_of_dir(name: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(name.len());
+    let mut rest = name.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// An order sent to an exchange, as kept in the trade history.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct TradeRecord {
+    #[serde(flatten)]
+    pub result: ExecutionResult,
+    /// The order and the alert fields it was built from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) order: Option<Order>,
+}
+
+#[derive(Clone, Debug)]
+pub struct TradeHistory {
+    dir: PathBuf,
+}
+
+impl TradeHistory {
+    /// History kept next to `state_file`.
+    pub fn for_state_file(state_file: &Path) -> Self {
+        let mut dir = state_file.as_os_str().to_owned();
+        dir.push(".trades");
+        Self {
+            dir: PathBuf::from(dir),
+        }
+    }
+
+    /// Directory of `bot_id`'s shards.
+    fn bot_dir(&self, bot_id: &str) -> PathBuf {
+        self.dir.join(dir_name(bot_id))
+    }
+
+    /// Number of shards and their total size in bytes.
+    pub fn disk_usage(&self) -> io::Result<(usize, u64)> {
+        let bots = match fs::read_dir(&self.dir) {
+            Ok(bots) => bots,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok((0, 0)),
+            Err(e) => return Err(e),
+        };
+        let (mut shards, mut bytes) = (0, 0);
+        for bot in bots {
+            for shard in fs::read_dir(bot?.path())? {
+                shards += 1;
+                bytes += shard?.metadata()?.len();
+            }
+        }
+        Ok((shards, bytes))
+    }
+
+    /// Append `trade` to the shard of the month it finished in.
+    pub fn append(&self, bot_id: &str, trade: &TradeRecord) -> io::Result<()> {
+        let dir = self.bot_dir(bot_id);
+        fs::create_dir_all(&dir)?;
+        let month = Self::month(trade);
+        let mut line = serde_json::to_string(trade)?;
+        line.push('\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(format!("{}.jsonl", month)))?
+            .write_all(line.as_bytes())
+    }
+
+    /// Append those of `trades` that are not recorded yet. Returns how many were new.
+    pub fn import(&self, bot_id: &str, trades: &[TradeRecord]) -> io::Result<usize> {
+        let mut recorded: BTreeMap<String, Vec<TradeRecord>> = BTreeMap::new();
+        let mut imported = 0;
+        for trade in trades {
+            let month = match recorded.entry(Self::month(trade)) {
+                Entry::Occupied(month) => month.into_mut(),
+                Entry::Vacant(month) => {
+                    let trades = self.load_month(bot_id, month.key())?;
+                    month.insert(trades)
+                }
+            };
+            if month.contains(trade) {
+                continue;
+            }
+            self.append(bot_id, trade)?;
+            month.push(trade.clone());
+            imported += 1;
+        }
+        Ok(imported)
+    }
+
+    /// Month (`YYYY-MM`) of the shard `trade` belongs in.
+    fn month(trade: &TradeRecord) -> String {
+        trade.result.finished_at.format("%Y-%m").to_string()
+    }
+
+    /// Storage keys of the bots with trades, including deleted ones.
+    pub fn bots(&self) -> io::Result<Vec<String>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut bots = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+            if let Some(key) = entry.file_name().to_str().and_then(key_of_dir) {
+                bots.push(key);
+            }
+        }
+        bots.sort();
+        Ok(bots)
+    }
+
+    /// Delete the shard of `bot_id`'s trades in `month`, and the bot's directory once it is
+    /// empty.
+    pub fn remove_month(&self, bot_id: &str, month: &str) -> io::Result<()> {
+        let dir = self.bot_dir(bot_id);
+        match fs::remove_file(dir.join(format!("{}.jsonl", month))) {
+            Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        if fs::read_dir(&dir).is_ok_and(|mut entries| entries.next().is_none()) {
+            fs::remove_dir(&dir)?;
+        }
+        Ok(())
+    }
+
+    /// Months (`YYYY-MM`) with trades of `bot_id`, oldest first.
+    pub fn months(&self, bot_id: &str) -> io::Result<Vec<String>> {
+        let entries = match fs::read_dir(self.bot_dir(bot_id)) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut months = Vec::new();
+        for entry in entries {
+            let name = entry?.file_name();
+            if let Some(month) = name.to_str().and_then(|name| name.strip_suffix(".jsonl")) {
+                months.push(month.to_string());
+            }
+        }
+        months.sort();
+        Ok(months)
+    }
+
+    /// Trades of `bot_id` in `month`, in the order they finished.
+    ///
+    /// Lines that do not parse, such as one torn by a crash mid-write, are skipped.
+    pub fn load_month(&self, bot_id: &str, month: &str) -> io::Result<Vec<TradeRecord>> {
+        let path = self.bot_dir(bot_id).join(format!("{}.jsonl", month));
+        let file = match fs::File::open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut trades = Vec::new();
+        for (number, line) in BufReader::new(file).lines().enumerate() {
+            match serde_json::from_str(&line?) {
+                Ok(trade) => trades.push(trade),
+                Err(e) => log::warn!("Skipping line {} of {:?}: {}", number + 1, path, e),
+            }
+        }
+        trades.sort_by_key(|trade: &TradeRecord| trade.result.finished_at);
+        Ok(trades)
+    }
+
+    /// The latest `limit` trades of `bot_id`, newest first, reading only as many shards as
+    /// needed.
+    pub fn recent(&self, bot_id: &str, limit: usize) -> io::Result<Vec<TradeRecord>> {
+        let mut trades = Vec::new();
+        for month in self.months(bot_id)?.iter().rev() {
+            if trades.len() >= limit {
+                break;
+            }
+            trades.extend(self.load_month(bot_id, month)?.into_iter().rev());
+        }
+        trades.truncate(limit);
+        Ok(trades)
+    }
+
+    /// Page `page` (counting from 1) of `bot_id`'s trades, `limit` per page, newest first.
+    pub fn page(&self, bot_id: &str, page: usize, limit: usize) -> io::Result<Vec<TradeRecord>> {
+        let skip = page.saturating_sub(1) * limit;
+        let trades = self.recent(bot_id, skip + limit)?;
+        Ok(trades.into_iter().skip(skip).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::dispatch::ExecutionStatus;
+    use crate::exchange::ExecutionReport;
+    use chrono::{TimeZone, Utc};
+    use rust_decimal::dec;
+    use tempfile::tempdir;
+
+    fn trade(order_id: &str, month: u32) -> TradeRecord {
+        let result = ExecutionResult {
+            order_id: Some(order_id.to_string()),
+            bot_id: "alpha".to_string(),
+            listener_id: "tv".to_string(),
+            exchange: "binance".to_string(),
+            account: None,
+            action: "buy".to_string(),
+            symbol: "BTCUSDT".to_string(),
+            order_size: "0.5".to_string(),
+            status: ExecutionStatus::Executed,
+            message: "order accepted by the exchange".to_string(),
+            report: Some(ExecutionReport {
+                order_id: Some(format!("binance-{}", order_id)),
+                filled_quantity: Some(dec!(0.5)),
+                ..ExecutionReport::acknowledged(Utc::now())
+            }),
+            duplicate: false,
+            error_code: None,
+            warnings: Vec::new(),
+            latency_ms: None,
+            finished_at: Utc.with_ymd_and_hms(2025, month, 10, 12, 0, 0).unwrap(),
+        };
+        TradeRecord {
+            result,
+            order: None,
+        }
+    }
+
+    #[test]
+    fn trades_are_sharded_per_bot_and_month() {
+        let dir = tempdir().unwrap();
+        let history = TradeHistory::for_state_file(&dir.path().join("state.json"));
+
+        for (id, month) in [("1", 1), ("2", 1), ("3", 2), ("4", 3)] {
+            history.append("acme:alpha", &trade(id, month)).unwrap();
+        }
+        history.append("../beta", &trade("5", 3)).unwrap();
+
+        assert_eq!(
+            history.months("acme:alpha").unwrap(),
+            vec!["2025-01", "2025-02", "2025-03"]
+        );
+        assert!(dir
+            .path()
+            .join("state.json.trades/acme%3Aalpha/2025-01.jsonl")
+            .exists());
+        assert!(dir.path().join("state.json.trades/%2E%2E%2Fbeta").is_dir());
+
+        let ids = |trades: Vec<TradeRecord>| -> Vec<String> {
+            trades
+                .into_iter()
+                .filter_map(|t| t.result.order_id)
+                .collect()
+        };
+        assert_eq!(
+            ids(history.recent("acme:alpha", 3).unwrap()),
+            ["4", "3", "2"]
+        );
+        assert_eq!(
+            ids(history.load_month("acme:alpha", "2025-01").unwrap()),
+            ["1", "2"]
+        );
+        assert!(history.recent("gamma", 10).unwrap().is_empty());
+        assert_eq!(history.disk_usage().unwrap().0, 4);
+
+        assert_eq!(history.bots().unwrap(), ["../beta", "acme:alpha"]);
+        history.remove_month("../beta", "2025-03").unwrap();
+        assert_eq!(history.bots().unwrap(), ["acme:alpha"]);
+    }
+
+    #[test]
+    fn trades_are_paged_newest_first() {
+        let dir = tempdir().unwrap();
+        let history = TradeHistory::for_state_file(&dir.path().join("state.json"));
+        for (id, month) in [("1", 1), ("2", 2), ("3", 2), ("4", 3), ("5", 3)] {
+            history.append("alpha", &trade(id, month)).unwrap();
+        }
+
+        let ids = |page: usize, limit: usize| -> Vec<String> {
+            history
+                .page("alpha", page, limit)
+                .unwrap()
+                .into_iter()
+                .filter_map(|t| t.result.order_id)
+                .collect()
+        };
+        assert_eq!(ids(1, 2), ["5", "4"]);
+        assert_eq!(ids(2, 2), ["3", "2"]);
+        assert_eq!(ids(3, 2), ["1"]);
+        assert!(ids(4, 2).is_empty());
+
+        // Lines recorded before orders were kept still load
+        let legacy = trade("6", 4);
+        let line = serde_json::to_string(&legacy.result).unwrap();
+        fs::write(history.bot_dir("alpha").join("2025-04.jsonl"), line + "\n").unwrap();
+        assert_eq!(history.recent("alpha", 1).unwrap(), vec![legacy]);
+    }
+
+    #[test]
+    fn imports_skip_recorded_trades_and_read_in_order() {
+        let dir = tempdir().unwrap();
+        let history = TradeHistory::for_state_file(&dir.path().join("state.json"));
+        let live = trade("live", 1);
+        history.append("alpha", &live).unwrap();
+
+        let mut earlier = trade("imported", 1);
+        earlier.result.finished_at -= chrono::Duration::days(5);
+        let trades = [earlier, live, trade("later", 2)];
+        assert_eq!(history.import("alpha", &trades).unwrap(), 2);
+        assert_eq!(history.import("alpha", &trades).unwrap(), 0);
+
+        let january: Vec<_> = history
+            .load_month("alpha", "2025-01")
+            .unwrap()
+            .into_iter()
+            .filter_map(|t| t.result.order_id)
+            .collect();
+        assert_eq!(january, ["imported", "live"]);
+    }
+}
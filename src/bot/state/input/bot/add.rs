@@ -1,6 +1,13 @@
 //use crate::models::Listener;
-use crate::bot::model::Bot;
+use crate::app_config::NotificationPrefs;
+use crate::bot::allocation::Budget;
+use crate::bot::dispatch::ExecutionStatus;
+use crate::bot::model::{Bot, SubAccount};
+use crate::bot::state::provider::{IdGenerator, UuidGenerator};
+use crate::exchange::paper::{FillModel, PaperFills};
+use crate::exchange::{KeyPermissions, SymbolMap};
 use clap::Args;
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use validator::Validate;
@@ -16,8 +23,10 @@ pub struct BotInsertArgs {
     #[arg(long)]
     #[validate(length(min = 1, message = "Exchange cannot be empty"))]
     pub exchange: String,
+    /// The key, or `env:NAME` to read it from the environment variable `NAME` when trading
     #[arg(long)]
     pub api_key: Option<String>,
+    /// The secret, or `env:NAME` like `api_key`
     #[arg(long)]
     pub api_secret: Option<String>,
     #[arg(long)]
@@ -27,20 +36,70 @@ pub struct BotInsertArgs {
     #[arg(long)]
     pub webhook_secret: Option<String>,
     #[arg(long)]
-    pub trading_fee: Option<f64>,
+    pub trading_fee: Option<Decimal>,
     #[arg(long)]
     pub private_key: Option<String>,
     #[arg(long)]
     pub contract_address: Option<String>,
+    /// Simulate trades instead of sending them to the exchange
+    #[arg(long)]
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Only read the account, e.g. one traded by hand; needs read-only credentials at most
+    #[arg(long)]
+    #[serde(default)]
+    pub observer: bool,
+    /// Slippage of simulated market orders, in basis points
+    #[arg(long)]
+    #[serde(default)]
+    pub paper_slippage_bps: Option<Decimal>,
+    /// Price simulated market orders fill at: `instant`, or `next_tick` to wait for the
+    /// price to move
+    #[arg(long)]
+    #[serde(default)]
+    pub paper_fill: Option<FillModel>,
+    /// Share of each simulated order that fills, in percent
+    #[arg(long)]
+    #[serde(default)]
+    pub paper_fill_pct: Option<Decimal>,
+    /// Milliseconds simulated orders take to reach the exchange
+    #[arg(long)]
+    #[serde(default)]
+    pub paper_latency_ms: Option<u64>,
+    /// Do not trade a symbol while another bot of this group holds a position in it
+    #[arg(long)]
+    #[serde(default)]
+    pub exclusion_group: Option<String>,
+    /// Most the bot may have in open positions: an amount of the quote asset, or a share of
+    /// `allocation.capital` such as `25%`
+    #[arg(long)]
+    #[serde(default)]
+    pub budget: Option<Budget>,
+    /// Sub-accounts and the trades routed to them (JSON only)
+    #[arg(skip)]
+    #[serde(default)]
+    pub accounts: Vec<SubAccount>,
+    /// Alert outcomes that notify, e.g. `failed`; the configured default if unset
+    #[arg(long, value_delimiter = ',')]
+    #[serde(default)]
+    pub notify_on: Option<Vec<ExecutionStatus>>,
+    /// Channels notifications go through, e.g. `ops,webhook`; the configured default if unset
+    #[arg(long, value_delimiter = ',')]
+    #[serde(default)]
+    pub notify_channels: Option<Vec<String>>,
+    /// Permissions of the API key, as [checked](crate::bot::keycheck) with the exchange
+    #[arg(skip)]
+    #[serde(skip)]
+    pub key_permissions: Option<KeyPermissions>,
 }
 
 #[allow(dead_code)]
 impl BotInsertArgs {
-    /// Create a new `BotInsertArgs` instance with required fields
+    /// Create a new `BotInsertArgs` instance with required fields.
+    /// The bot ID is assigned by the registry unless set with [`Self::bot_id`].
     pub fn new(name: String, exchange: String) -> Self {
-        //let listeners: HashMap<String, Listener> = HashMap<String, Listener>::new();
         Self {
-            bot_id: Some(uuid::Uuid::new_v4().to_string()),
+            bot_id: None,
             name,
             exchange,
             api_key: None,
@@ -51,6 +110,18 @@ impl BotInsertArgs {
             trading_fee: None,
             private_key: None,
             contract_address: None,
+            dry_run: false,
+            observer: false,
+            paper_slippage_bps: None,
+            paper_fill: None,
+            paper_fill_pct: None,
+            paper_latency_ms: None,
+            exclusion_group: None,
+            budget: None,
+            accounts: Vec::new(),
+            notify_on: None,
+            notify_channels: None,
+            key_permissions: None,
         }
     }
 
@@ -84,7 +155,7 @@ impl BotInsertArgs {
         self
     }
 
-    pub fn trading_fee(mut self, trading_fee: Option<f64>) -> Self {
+    pub fn trading_fee(mut self, trading_fee: Option<Decimal>) -> Self {
         self.trading_fee = trading_fee;
         self
     }
@@ -98,14 +169,27 @@ impl BotInsertArgs {
         self.contract_address = contract_address;
         self
     }
+
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    pub fn observer(mut self, observer: bool) -> Self {
+        self.observer = observer;
+        self
+    }
+
+    pub fn paper_slippage_bps(mut self, paper_slippage_bps: Option<Decimal>) -> Self {
+        self.paper_slippage_bps = paper_slippage_bps;
+        self
+    }
 }
 
 impl From<BotInsertArgs> for Bot {
     fn from(args: BotInsertArgs) -> Self {
         Bot {
-            bot_id: args
-                .bot_id
-                .unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+            bot_id: args.bot_id.unwrap_or_else(|| UuidGenerator.next_id()),
             name: args.name,
             exchange: args.exchange,
             api_key: args.api_key,
@@ -116,6 +200,23 @@ impl From<BotInsertArgs> for Bot {
             trading_fee: args.trading_fee,
             private_key: args.private_key,
             contract_address: args.contract_address,
+            dry_run: args.dry_run,
+            observer: args.observer,
+            paper_slippage_bps: args.paper_slippage_bps,
+            paper_fills: PaperFills {
+                model: args.paper_fill.unwrap_or_default(),
+                fill_pct: args.paper_fill_pct,
+                latency_ms: args.paper_latency_ms,
+            },
+            symbol_map: SymbolMap::default(),
+            accounts: args.accounts,
+            exclusion_group: args.exclusion_group.filter(|group| !group.is_empty()),
+            budget: args.budget,
+            notifications: NotificationPrefs {
+                on: args.notify_on,
+                channels: args.notify_channels,
+            },
+            key_permissions: args.key_permissions,
             listeners: HashMap::new(), // Initialize with no listeners
         }
     }
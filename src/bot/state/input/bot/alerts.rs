@@ -0,0 +1,40 @@
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use validator::Validate;
+
+#[derive(Args, Clone, Debug, Deserialize, Serialize, Validate)]
+pub struct AlertListArgs {
+    #[arg(long)]
+    #[serde(default)]
+    #[validate(length(min = 1, message = "ID cannot be empty"))]
+    pub bot_id: String,
+    /// Alerts to list, newest first (defaults to 20)
+    #[arg(long)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Args, Clone, Debug, Deserialize, Serialize, Validate)]
+pub struct AlertReplayArgs {
+    /// ID of a failed alert, as listed by `list-alerts`
+    #[arg(long)]
+    #[validate(length(min = 1, message = "ID cannot be empty"))]
+    pub alert_id: String,
+}
+
+#[derive(Args, Clone, Debug, Deserialize, Serialize, Validate)]
+pub struct AlertSimulateArgs {
+    #[arg(long)]
+    #[validate(length(min = 1, message = "ID cannot be empty"))]
+    pub bot_id: String,
+    #[arg(long)]
+    #[validate(length(min = 1, message = "ID cannot be empty"))]
+    pub listener_id: String,
+    /// Alert payload, as the listener's service would send it
+    #[arg(long)]
+    pub file: PathBuf,
+    /// Dispatch the alert to the bot's exchange rather than only show what would happen
+    #[arg(long)]
+    #[serde(default)]
+    pub execute: bool,
+}
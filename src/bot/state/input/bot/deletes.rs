@@ -1,6 +1,7 @@
 //use crate::models::Listener;
 use crate::bot::model::Bot;
 use clap::Args;
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use validator::Validate;
 
@@ -19,7 +20,7 @@ pub struct BotsDeleteArgs {
     #[arg(long)]
     pub rpc_endpoint: Option<String>,
     #[arg(long)]
-    pub trading_fee: Option<f64>,
+    pub trading_fee: Option<Decimal>,
     #[arg(long)]
     pub private_key: Option<String>,
     #[arg(long)]
@@ -104,7 +105,7 @@ impl BotListArgs {
         self.rpc_endpoint = rpc_endpoint;
         self
     }
-    pub fn trading_fee(mut self, trading_fee: Option<f64>) -> Self {
+    pub fn trading_fee(mut self, trading_fee: Option<Decimal>) -> Self {
         self.trading_fee = trading_fee;
         self
     }
@@ -1,9 +1,10 @@
 use crate::bot::model::Bot;
 use clap::Args;
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use validator::Validate;
 
-#[derive(Args, Clone, Debug, Deserialize, Serialize, Validate)]
+#[derive(Args, Clone, Debug, Default, Deserialize, Serialize, Validate)]
 pub struct BotListArgs {
     // these 2 are here because of clap
     // ignored by other consumers
@@ -25,48 +26,90 @@ pub struct BotListArgs {
     #[arg(long)]
     pub rpc_endpoint: Option<String>,
     #[arg(long)]
-    pub trading_fee: Option<f64>,
+    pub trading_fee: Option<Decimal>,
+    /// Only bots with a trading fee of at least this much
+    #[arg(long)]
+    pub trading_fee_min: Option<Decimal>,
+    /// Only bots with a trading fee of at most this much
+    #[arg(long)]
+    pub trading_fee_max: Option<Decimal>,
     #[arg(long)]
     pub private_key: Option<String>,
     #[arg(long)]
     pub contract_address: Option<String>,
 }
 
+/// Filters that hold credentials, only accepted in a request body: query strings end up in
+/// access logs.
+pub const SECRET_FILTERS: [&str; 2] = ["api_key", "private_key"];
+
 impl BotListArgs {
     #[allow(dead_code)]
     pub fn bot_id(mut self, bot_id: Option<&str>) -> Self {
         self.bot_id = bot_id.map(|x| x.to_string());
         self
     }
+
+    /// The filters of `self`, and those of `other` where `self` has none.
+    pub fn or(self, other: Self) -> Self {
+        Self {
+            page: self.page.or(other.page),
+            limit: self.limit.or(other.limit),
+            bot_id: self.bot_id.or(other.bot_id),
+            name: self.name.or(other.name),
+            exchange: self.exchange.or(other.exchange),
+            api_key: self.api_key.or(other.api_key),
+            rest_endpoint: self.rest_endpoint.or(other.rest_endpoint),
+            rpc_endpoint: self.rpc_endpoint.or(other.rpc_endpoint),
+            trading_fee: self.trading_fee.or(other.trading_fee),
+            trading_fee_min: self.trading_fee_min.or(other.trading_fee_min),
+            trading_fee_max: self.trading_fee_max.or(other.trading_fee_max),
+            private_key: self.private_key.or(other.private_key),
+            contract_address: self.contract_address.or(other.contract_address),
+        }
+    }
+
+    /// Whether any filter holds a credential.
+    pub fn has_secret_filters(&self) -> bool {
+        self.api_key.is_some() || self.private_key.is_some()
+    }
     /// Checks whether a `Bot` matches the criteria in `BotListArgs`
     pub fn matches(&self, bot: &Bot) -> bool {
-        (self.bot_id.as_ref().map_or(true, |id| &bot.bot_id == id))
-            && (self.name.as_ref().map_or(true, |name| &bot.name == name))
+        (self.bot_id.as_ref().is_none_or(|id| &bot.bot_id == id))
+            && (self.name.as_ref().is_none_or(|name| &bot.name == name))
             && (self
                 .exchange
                 .as_ref()
-                .map_or(true, |exchange| &bot.exchange == exchange))
+                .is_none_or(|exchange| &bot.exchange == exchange))
             && (self
                 .api_key
                 .as_ref()
-                .map_or(true, |key| bot.api_key.as_ref() == Some(key)))
-            && (self.rest_endpoint.as_ref().map_or(true, |endpoint| {
-                bot.rest_endpoint.as_ref() == Some(endpoint)
-            }))
+                .is_none_or(|key| bot.api_key.as_ref() == Some(key)))
+            && (self
+                .rest_endpoint
+                .as_ref()
+                .is_none_or(|endpoint| bot.rest_endpoint.as_ref() == Some(endpoint)))
             && (self
                 .rpc_endpoint
                 .as_ref()
-                .map_or(true, |endpoint| bot.rpc_endpoint.as_ref() == Some(endpoint)))
+                .is_none_or(|endpoint| bot.rpc_endpoint.as_ref() == Some(endpoint)))
             && (self
                 .trading_fee
                 .as_ref()
-                .map_or(true, |fee| bot.trading_fee.as_ref() == Some(fee)))
+                .is_none_or(|fee| bot.trading_fee.as_ref() == Some(fee)))
+            && (self
+                .trading_fee_min
+                .is_none_or(|min| bot.trading_fee.is_some_and(|fee| fee >= min)))
+            && (self
+                .trading_fee_max
+                .is_none_or(|max| bot.trading_fee.is_some_and(|fee| fee <= max)))
             && (self
                 .private_key
                 .as_ref()
-                .map_or(true, |key| bot.private_key.as_ref() == Some(key)))
-            && (self.contract_address.as_ref().map_or(true, |address| {
-                bot.contract_address.as_ref() == Some(address)
-            }))
+                .is_none_or(|key| bot.private_key.as_ref() == Some(key)))
+            && (self
+                .contract_address
+                .as_ref()
+                .is_none_or(|address| bot.contract_address.as_ref() == Some(address)))
     }
 }
@@ -1,10 +1,22 @@
 pub mod add;
+pub mod alerts;
 pub mod get;
 pub mod list;
+pub mod orders;
+pub mod symbols;
+pub mod timeline;
+pub mod trades;
 pub mod update;
+pub mod watch;
 
 pub use add::BotInsertArgs;
+pub use alerts::{AlertListArgs, AlertReplayArgs, AlertSimulateArgs};
 pub use get::BotGetArgs;
 pub use get::BotGetArgs as BotDeleteArgs;
 pub use list::BotListArgs;
+pub use orders::OrderCancelArgs;
+pub use symbols::{SymbolMapArgs, SymbolUnmapArgs};
+pub use timeline::BotTimelineArgs;
+pub use trades::{BotTradesArgs, FillImportArgs};
 pub use update::BotUpdateArgs;
+pub use watch::BotWatchArgs;
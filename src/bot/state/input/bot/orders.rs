@@ -0,0 +1,19 @@
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Args, Clone, Debug, Deserialize, Serialize, Validate)]
+pub struct OrderCancelArgs {
+    #[arg(long)]
+    #[serde(default)]
+    #[validate(length(min = 1, message = "ID cannot be empty"))]
+    pub bot_id: String,
+    /// Exchange ID of the order, as listed by `list-orders`
+    #[arg(long)]
+    #[serde(default)]
+    #[validate(length(min = 1, message = "Order ID cannot be empty"))]
+    pub order_id: String,
+    /// Symbol of the order; looked up among the open orders if not given
+    #[arg(long)]
+    pub symbol: Option<String>,
+}
@@ -0,0 +1,31 @@
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Args, Clone, Debug, Deserialize, Serialize, Validate)]
+pub struct SymbolMapArgs {
+    #[arg(long)]
+    #[serde(default)]
+    #[validate(length(min = 1, message = "ID cannot be empty"))]
+    pub bot_id: String,
+    /// Symbol as the bot's alerts name it, e.g. BINANCE:BTCUSDTPERP
+    #[arg(long)]
+    #[validate(length(min = 1, message = "Symbol cannot be empty"))]
+    pub symbol: String,
+    /// Symbol to trade on the bot's exchange instead, e.g. BTCUSDT.P
+    #[arg(long)]
+    #[validate(length(min = 1, message = "Exchange symbol cannot be empty"))]
+    pub exchange_symbol: String,
+}
+
+#[derive(Args, Clone, Debug, Deserialize, Serialize, Validate)]
+pub struct SymbolUnmapArgs {
+    #[arg(long)]
+    #[serde(default)]
+    #[validate(length(min = 1, message = "ID cannot be empty"))]
+    pub bot_id: String,
+    /// Symbol whose mapping to drop
+    #[arg(long)]
+    #[validate(length(min = 1, message = "Symbol cannot be empty"))]
+    pub symbol: String,
+}
@@ -0,0 +1,37 @@
+use chrono::{DateTime, Utc};
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Args, Clone, Debug, Deserialize, Serialize, Validate)]
+pub struct BotTimelineArgs {
+    #[arg(long)]
+    #[serde(default)]
+    #[validate(length(min = 1, message = "ID cannot be empty"))]
+    pub bot_id: String,
+    /// Cursor from a previous page: only events older than this sequence number
+    #[arg(long)]
+    pub before: Option<u64>,
+    /// Only events at or after this time (RFC 3339)
+    #[arg(long)]
+    pub since: Option<DateTime<Utc>>,
+    #[arg(long)]
+    pub limit: Option<usize>,
+}
+
+impl BotTimelineArgs {
+    /// Create a new instance of `BotTimelineArgs`
+    pub fn new(bot_id: &str) -> Self {
+        Self {
+            bot_id: bot_id.to_string(),
+            before: None,
+            since: None,
+            limit: None,
+        }
+    }
+
+    /// Get the effective page size (defaults to 50)
+    pub fn limit(&self) -> usize {
+        self.limit.unwrap_or(50).max(1)
+    }
+}
@@ -0,0 +1,27 @@
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use validator::Validate;
+
+#[derive(Args, Clone, Debug, Deserialize, Serialize, Validate)]
+pub struct BotTradesArgs {
+    #[arg(long)]
+    #[validate(length(min = 1, message = "ID cannot be empty"))]
+    pub bot_id: String,
+    /// Page of the history, newest trades first (defaults to 1)
+    #[arg(long)]
+    pub page: Option<usize>,
+    /// Trades per page (defaults to 10)
+    #[arg(long)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Args, Clone, Debug, Deserialize, Serialize, Validate)]
+pub struct FillImportArgs {
+    #[arg(long)]
+    #[validate(length(min = 1, message = "ID cannot be empty"))]
+    pub bot_id: String,
+    /// CSV of fills exported by the bot's exchange
+    #[arg(long)]
+    pub file: PathBuf,
+}
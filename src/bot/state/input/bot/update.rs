@@ -1,6 +1,11 @@
 //use crate::models::Listener;
-use crate::bot::model::{Bot, Listener};
+use crate::app_config::NotificationPrefs;
+use crate::bot::allocation::Budget;
+use crate::bot::dispatch::ExecutionStatus;
+use crate::bot::model::{Bot, Listener, SubAccount};
+use crate::exchange::paper::FillModel;
 use clap::Args;
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use validator::Validate;
@@ -24,11 +29,53 @@ pub struct BotUpdateArgs {
     #[arg(long)]
     pub webhook_secret: Option<String>,
     #[arg(long)]
-    pub trading_fee: Option<f64>,
+    pub trading_fee: Option<Decimal>,
     #[arg(long)]
     pub private_key: Option<String>,
     #[arg(long)]
     pub contract_address: Option<String>,
+    /// Simulate trades instead of sending them to the exchange
+    #[arg(long)]
+    pub dry_run: Option<bool>,
+    /// Only read the account instead of trading it
+    #[arg(long)]
+    pub observer: Option<bool>,
+    /// Slippage of simulated market orders, in basis points
+    #[arg(long)]
+    pub paper_slippage_bps: Option<Decimal>,
+    /// Price simulated market orders fill at: `instant` or `next_tick`
+    #[arg(long)]
+    pub paper_fill: Option<FillModel>,
+    /// Share of each simulated order that fills, in percent
+    #[arg(long)]
+    pub paper_fill_pct: Option<Decimal>,
+    /// Milliseconds simulated orders take to reach the exchange
+    #[arg(long)]
+    pub paper_latency_ms: Option<u64>,
+    /// Group of bots that take turns on a symbol; an empty name leaves the group
+    #[arg(long)]
+    pub exclusion_group: Option<String>,
+    /// Most the bot may have in open positions, e.g. `5000` or `25%`
+    #[arg(long)]
+    pub budget: Option<Budget>,
+    /// Drop the bot's budget
+    #[arg(long)]
+    #[serde(default)]
+    pub clear_budget: bool,
+    /// Replacement for the bot's sub-accounts (JSON only)
+    #[arg(skip)]
+    #[serde(default)]
+    pub accounts: Option<Vec<SubAccount>>,
+    /// Drop the bot's notification preferences, so it follows the configured defaults again
+    #[arg(long)]
+    #[serde(default)]
+    pub clear_notifications: bool,
+    /// Alert outcomes that notify, e.g. `failed`
+    #[arg(long, value_delimiter = ',')]
+    pub notify_on: Option<Vec<ExecutionStatus>>,
+    /// Channels notifications go through, e.g. `ops,webhook`
+    #[arg(long, value_delimiter = ',')]
+    pub notify_channels: Option<Vec<String>>,
     #[arg(skip)]
     pub listeners: HashMap<String, Listener>,
 }
@@ -49,6 +96,19 @@ impl BotUpdateArgs {
             trading_fee: None,
             private_key: None,
             contract_address: None,
+            dry_run: None,
+            observer: None,
+            paper_slippage_bps: None,
+            paper_fill: None,
+            paper_fill_pct: None,
+            paper_latency_ms: None,
+            exclusion_group: None,
+            budget: None,
+            clear_budget: false,
+            accounts: None,
+            clear_notifications: false,
+            notify_on: None,
+            notify_channels: None,
             listeners: HashMap::new(),
         }
     }
@@ -98,6 +158,46 @@ impl BotUpdateArgs {
         if let Some(contract_address) = &self.contract_address {
             bot.contract_address = Some(contract_address.clone());
         }
+        if let Some(dry_run) = self.dry_run {
+            bot.dry_run = dry_run;
+        }
+        if let Some(observer) = self.observer {
+            bot.observer = observer;
+        }
+        if let Some(paper_slippage_bps) = self.paper_slippage_bps {
+            bot.paper_slippage_bps = Some(paper_slippage_bps);
+        }
+        if let Some(model) = self.paper_fill {
+            bot.paper_fills.model = model;
+        }
+        if let Some(fill_pct) = self.paper_fill_pct {
+            bot.paper_fills.fill_pct = Some(fill_pct);
+        }
+        if let Some(latency_ms) = self.paper_latency_ms {
+            bot.paper_fills.latency_ms = Some(latency_ms);
+        }
+        if let Some(group) = &self.exclusion_group {
+            bot.exclusion_group = Some(group.clone()).filter(|group| !group.is_empty());
+        }
+        if self.clear_budget {
+            bot.budget = None;
+        }
+        if let Some(budget) = self.budget {
+            bot.budget = Some(budget);
+        }
+        if let Some(accounts) = &self.accounts {
+            bot.accounts = accounts.clone();
+        }
+        let notifications = &mut bot.notifications;
+        if self.clear_notifications {
+            *notifications = NotificationPrefs::default();
+        }
+        if let Some(on) = &self.notify_on {
+            notifications.on = Some(on.clone());
+        }
+        if let Some(channels) = &self.notify_channels {
+            notifications.channels = Some(channels.clone());
+        }
         // Replace the entire listeners map if specified
         if !self.listeners.is_empty() {
             bot.listeners = self.listeners.clone();
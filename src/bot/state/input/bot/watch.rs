@@ -0,0 +1,21 @@
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use validator::Validate;
+
+#[derive(Args, Clone, Debug, Deserialize, Serialize, Validate)]
+pub struct BotWatchArgs {
+    #[arg(long)]
+    #[validate(length(min = 1, message = "ID cannot be empty"))]
+    pub bot_id: String,
+    /// How often to check for new events, in milliseconds (default 1000)
+    #[arg(long)]
+    pub interval_ms: Option<u64>,
+}
+
+impl BotWatchArgs {
+    /// Get the effective polling interval (at least 100ms)
+    pub fn interval(&self) -> Duration {
+        Duration::from_millis(self.interval_ms.unwrap_or(1000).max(100))
+    }
+}
@@ -0,0 +1,108 @@
+// src/bot/state/input/bulk.rs
+//! Batches of operations on bots and listeners, applied all or none by `POST /bots/bulk`
+//! and `POST /bots/{bot_id}/listeners/bulk`. Each operation names what it does with `op`
+//! and takes the arguments of the endpoint doing the same on its own:
+//!
+//! ```yaml
+//! operations:
+//!   - op: insert
+//!     bot_id: alpha
+//!     name: Alpha
+//!     exchange: binance
+//!   - op: insert_listener
+//!     bot_id: alpha
+//!     service: tradingview
+//!   - op: delete
+//!     bot_id: beta
+//! ```
+use super::bot::{BotDeleteArgs, BotInsertArgs, BotUpdateArgs};
+use super::listener::{ListenerDeleteArgs, ListenerInsertArgs, ListenerUpdateArgs};
+use crate::bot::state::export::ExportFormat;
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// An operation of a batch on bots and their listeners.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum BulkOperation {
+    Insert(BotInsertArgs),
+    Update(BotUpdateArgs),
+    Delete(BotDeleteArgs),
+    InsertListener(ListenerInsertArgs),
+    UpdateListener(ListenerUpdateArgs),
+    DeleteListener(ListenerDeleteArgs),
+}
+
+impl BulkOperation {
+    /// The `op` of the operation.
+    pub fn name(&self) -> &'static str {
+        match self {
+            BulkOperation::Insert(_) => "insert",
+            BulkOperation::Update(_) => "update",
+            BulkOperation::Delete(_) => "delete",
+            BulkOperation::InsertListener(_) => "insert_listener",
+            BulkOperation::UpdateListener(_) => "update_listener",
+            BulkOperation::DeleteListener(_) => "delete_listener",
+        }
+    }
+}
+
+/// Body of `POST /bots/bulk`, and the manifests of `apply`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct BulkRequest {
+    pub operations: Vec<BulkOperation>,
+}
+
+/// An operation of a batch on the listeners of one bot; the bot is that of the path.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum ListenerOperation {
+    Insert(ListenerInsertArgs),
+    Update(ListenerUpdateArgs),
+    Delete(ListenerDeleteArgs),
+}
+
+impl ListenerOperation {
+    /// The operation on the listeners of `bot_id`, as a batch on bots has it.
+    pub fn of_bot(self, bot_id: &str) -> BulkOperation {
+        match self {
+            ListenerOperation::Insert(args) => BulkOperation::InsertListener(ListenerInsertArgs {
+                bot_id: bot_id.to_string(),
+                ..args
+            }),
+            ListenerOperation::Update(args) => BulkOperation::UpdateListener(ListenerUpdateArgs {
+                bot_id: bot_id.to_string(),
+                ..args
+            }),
+            ListenerOperation::Delete(args) => {
+                BulkOperation::DeleteListener(ListenerDeleteArgs::new(bot_id, &args.listener_id))
+            }
+        }
+    }
+}
+
+/// Body of `POST /bots/{bot_id}/listeners/bulk`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ListenerBulkRequest {
+    pub operations: Vec<ListenerOperation>,
+}
+
+#[derive(Args, Clone, Debug, Default)]
+pub struct BulkApplyArgs {
+    /// Manifest of operations on bots and listeners, applied all or none
+    #[arg(short, long)]
+    pub file: PathBuf,
+    /// Document format; defaults to the file's extension, or json
+    #[arg(long)]
+    pub format: Option<ExportFormat>,
+}
+
+impl BulkApplyArgs {
+    /// The format of the file: as given, or as its extension names.
+    pub fn file_format(&self) -> ExportFormat {
+        (self.format)
+            .or_else(|| ExportFormat::from_path(&self.file))
+            .unwrap_or_default()
+    }
+}
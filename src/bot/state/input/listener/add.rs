@@ -1,15 +1,19 @@
 //use crate::models::Listener;
+use crate::bot::dispatch::ExecutionStatus;
+use crate::bot::filter::TradingHours;
 use clap::Args;
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
 use validator::Validate;
 
 #[derive(Args, Clone, Debug, Deserialize, Serialize, Validate)]
 pub struct ListenerInsertArgs {
     #[arg(long)]
+    #[serde(default)] // Set by the path of the API
     pub bot_id: String,
     #[arg(long)]
     pub listener_id: Option<String>,
+    /// Alert format the listener receives: `tradingview`, `3commas` or `alertatron`
     #[arg(long)]
     #[validate(length(min = 1, message = "Service cannot be empty"))]
     pub service: String,
@@ -17,19 +21,67 @@ pub struct ListenerInsertArgs {
     pub secret: Option<String>,
     #[arg(long)]
     pub msg: Option<String>,
+    /// Create the listener disabled with `--enabled false` (enabled by default)
+    #[arg(long)]
+    pub enabled: Option<bool>,
+    /// Accept alerts without checking the secret with `--verify-secret false`, for testing
+    #[arg(long)]
+    pub verify_secret: Option<bool>,
+    /// Pass on at most this many alerts per minute; more are refused with 429
+    #[arg(long)]
+    pub max_alerts_per_minute: Option<u32>,
+    /// Refuse alerts for this many seconds after each trade
+    #[arg(long)]
+    pub cooldown_secs: Option<u64>,
+    /// Only pass on alerts for these symbols (comma separated)
+    #[arg(long, value_delimiter = ',')]
+    #[serde(default)]
+    pub symbols: Vec<String>,
+    /// Only pass on these actions, e.g. `buy,sell`
+    #[arg(long, value_delimiter = ',')]
+    #[serde(default)]
+    pub actions: Vec<String>,
+    /// Ignore alerts priced below this
+    #[arg(long)]
+    pub min_price: Option<Decimal>,
+    /// Ignore alerts priced above this
+    #[arg(long)]
+    pub max_price: Option<Decimal>,
+    /// Only pass on alerts arriving within these hours, `HH:MM-HH:MM` in UTC
+    #[arg(long)]
+    pub trading_hours: Option<TradingHours>,
+    /// Alert outcomes that notify, e.g. `failed`; the bot's choice if unset
+    #[arg(long, value_delimiter = ',')]
+    #[serde(default)]
+    pub notify_on: Option<Vec<ExecutionStatus>>,
+    /// Channels notifications go through, e.g. `ops,webhook`; the bot's choice if unset
+    #[arg(long, value_delimiter = ',')]
+    #[serde(default)]
+    pub notify_channels: Option<Vec<String>>,
 }
 
 impl ListenerInsertArgs {
     /// Creates a new `ListenerInsertArgs` instance with required fields.
-    /// Generates a `listener_id` if none is provided.
+    /// The listener ID is assigned by the registry unless set with [`Self::listener_id`].
     #[allow(dead_code)]
     pub fn new(bot_id: &str, service: &str) -> Self {
         Self {
             bot_id: bot_id.to_string(),
-            listener_id: Some(Uuid::new_v4().to_string()),
+            listener_id: None,
             service: service.to_string(),
             secret: None,
             msg: None,
+            enabled: None,
+            verify_secret: None,
+            max_alerts_per_minute: None,
+            cooldown_secs: None,
+            symbols: Vec::new(),
+            actions: Vec::new(),
+            min_price: None,
+            max_price: None,
+            trading_hours: None,
+            notify_on: None,
+            notify_channels: None,
         }
     }
 
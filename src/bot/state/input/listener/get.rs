@@ -7,6 +7,7 @@ use validator::Validate;
 pub struct ListenerGetArgs {
     #[arg(long)]
     #[validate(length(min = 1, message = "ID cannot be empty"))]
+    #[serde(default)] // Set by the path of the API
     pub bot_id: String,
     #[arg(long)]
     pub listener_id: String,
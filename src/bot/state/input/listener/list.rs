@@ -1,7 +1,9 @@
 //use crate::models::Listener;
 use crate::bot::model::Listener;
+use crate::errors::AppError;
+use chrono::{DateTime, Utc};
 use clap::Args;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use validator::Validate;
 
 #[derive(Args, Debug, Clone, Serialize, Deserialize, Validate)]
@@ -10,12 +12,48 @@ pub struct ListenerListArgs {
     pub page: Option<u32>,
     #[arg(long)]
     pub limit: Option<u32>,
-    #[arg(long)]
+    #[arg(long, required_unless_present = "all_bots", default_value = "")]
+    #[serde(default)]
     pub bot_id: String,
+    /// List the listeners of every bot instead of a single one
+    #[arg(long, conflicts_with = "bot_id")]
+    #[serde(default)]
+    pub all_bots: bool,
     #[arg(long)]
     pub listener_id: Option<String>,
     #[arg(long)]
     pub service: Option<String>,
+    #[arg(long)]
+    pub enabled: Option<bool>,
+    /// Only listeners that have not fired since this time (RFC 3339), including those
+    /// that never fired
+    #[arg(long)]
+    pub last_fired_before: Option<DateTime<Utc>>,
+    /// Fields lists leave out to return as well: `msg` for the message templates
+    #[arg(long, value_delimiter = ',', value_parser = INCLUDES)]
+    #[serde(default, deserialize_with = "comma_separated")]
+    pub include: Vec<String>,
+}
+
+/// Fields lists of listeners leave out unless asked for.
+pub const INCLUDES: [&str; 1] = ["msg"];
+
+/// A list given either as such, in a JSON body, or comma-separated, in a query string.
+fn comma_separated<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum List {
+        Joined(String),
+        Items(Vec<String>),
+    }
+    Ok(match List::deserialize(deserializer)? {
+        List::Joined(joined) => (joined.split(','))
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+            .collect(),
+        List::Items(items) => items,
+    })
 }
 
 impl ListenerListArgs {
@@ -25,26 +63,82 @@ impl ListenerListArgs {
             page: Some(1),
             limit: Some(10),
             bot_id: bot_id.to_string(),
+            all_bots: false,
             listener_id: None,
             service: None,
+            enabled: None,
+            last_fired_before: None,
+            include: Vec::new(),
+        }
+    }
+
+    /// Create a new `ListenerListArgs` covering the listeners of every bot
+    #[allow(dead_code)]
+    pub fn all_bots() -> Self {
+        Self {
+            all_bots: true,
+            ..Self::new("")
         }
     }
 
     pub fn bot_id(mut self, bot_id: Option<&str>) -> Self {
-        self.bot_id = bot_id.map_or_else(|| String::new(), |x| x.to_string());
+        self.bot_id = bot_id.map_or_else(String::new, |x| x.to_string());
         self
     }
 
+    /// The filters of `self`, and those of `other` where `self` has none.
+    pub fn or(self, other: Self) -> Self {
+        Self {
+            page: self.page.or(other.page),
+            limit: self.limit.or(other.limit),
+            bot_id: if self.bot_id.is_empty() {
+                other.bot_id
+            } else {
+                self.bot_id
+            },
+            all_bots: self.all_bots || other.all_bots,
+            listener_id: self.listener_id.or(other.listener_id),
+            service: self.service.or(other.service),
+            enabled: self.enabled.or(other.enabled),
+            last_fired_before: self.last_fired_before.or(other.last_fired_before),
+            include: (self.include.into_iter()).chain(other.include).collect(),
+        }
+    }
+
+    /// Refuse to include fields lists cannot carry.
+    pub fn check_include(&self) -> Result<(), AppError> {
+        match (self.include.iter()).find(|field| !INCLUDES.contains(&field.as_str())) {
+            Some(field) => Err(AppError::InvalidInput(format!(
+                "Cannot include '{}'; expected one of: {}",
+                field,
+                INCLUDES.join(", ")
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether the listed views should carry the message templates.
+    pub fn includes_msg(&self) -> bool {
+        self.include.iter().any(|field| field == "msg")
+    }
+
     /// Determines whether a given listener matches the filter criteria
     pub fn matches(&self, listener_id: &str, listener: &Listener) -> bool {
         // Check if `listener_id` matches, if provided
         self.listener_id
             .as_ref()
-            .map_or(true, |id| id == listener_id)
+            .is_none_or(|id| id == listener_id)
             &&
         // Check if `service` matches, if provided
         self.service
             .as_ref()
-            .map_or(true, |service| service == &listener.service)
+            .is_none_or(|service| service == &listener.service)
+            &&
+        // Check if `enabled` matches, if provided
+        self.enabled.is_none_or(|enabled| enabled == listener.enabled)
+            &&
+        // Check the listener has not fired since `last_fired_before`, if provided
+        self.last_fired_before
+            .is_none_or(|before| listener.last_fired.is_none_or(|fired| fired < before))
     }
 }
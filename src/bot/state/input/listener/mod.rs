@@ -1,6 +1,7 @@
 pub mod add;
 pub mod get;
 pub mod list;
+pub mod rotate;
 pub mod update;
 
 pub use add::ListenerInsertArgs;
@@ -8,4 +9,5 @@ pub use get::ListenerGetArgs;
 pub use get::ListenerGetArgs as ListenerDeleteArgs;
 pub use list::ListenerListArgs;
 pub use list::ListenerListArgs as ListenersDeleteArgs;
+pub use rotate::{ListenerRotateSecretArgs, MAX_SECRET_GRACE_SECS};
 pub use update::ListenerUpdateArgs;
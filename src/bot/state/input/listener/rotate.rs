@@ -0,0 +1,33 @@
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+/// Longest grace period an old listener secret can be kept valid for: 30 days.
+pub const MAX_SECRET_GRACE_SECS: u64 = 30 * 24 * 60 * 60;
+
+#[derive(Args, Clone, Debug, Default, Deserialize, Serialize, Validate)]
+pub struct ListenerRotateSecretArgs {
+    #[arg(long)]
+    #[serde(default)]
+    #[validate(length(min = 1, message = "ID cannot be empty"))]
+    pub bot_id: String,
+    #[arg(long)]
+    #[serde(default)]
+    pub listener_id: String,
+    /// Seconds the old secret stays valid, so alerts configured with it keep working while
+    /// they are updated; by default it stops working at once. At most 30 days
+    #[arg(long)]
+    #[serde(default)]
+    pub grace_secs: Option<u64>,
+}
+
+impl ListenerRotateSecretArgs {
+    /// Create a new instance of `ListenerRotateSecretArgs`
+    pub fn new(bot_id: &str, listener_id: &str) -> Self {
+        Self {
+            bot_id: bot_id.to_string(),
+            listener_id: listener_id.to_string(),
+            grace_secs: None,
+        }
+    }
+}
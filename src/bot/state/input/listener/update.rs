@@ -1,12 +1,18 @@
 //use crate::models::Listener;
+use crate::app_config::NotificationPrefs;
+use crate::bot::dispatch::ExecutionStatus;
+use crate::bot::filter::{AlertFilter, TradingHours};
 pub use crate::bot::model::Listener;
+use crate::bot::state::templates;
 use clap::Args;
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use validator::Validate;
 
 #[derive(Args, Clone, Debug, Deserialize, Serialize, Validate)]
 pub struct ListenerUpdateArgs {
     #[arg(long)]
+    #[serde(default)] // Set by the path of the API
     pub bot_id: String,
     #[arg(long)]
     #[validate(length(min = 1, message = "Listener ID cannot be empty"))]
@@ -17,6 +23,45 @@ pub struct ListenerUpdateArgs {
     pub secret: Option<String>, // Optional
     #[arg(long)]
     pub msg: Option<String>, // Optional
+    #[arg(long)]
+    pub enabled: Option<bool>, // Optional
+    #[arg(long)]
+    pub verify_secret: Option<bool>, // Optional
+    /// Alerts passed on per minute at most; 0 lifts the limit
+    #[arg(long)]
+    pub max_alerts_per_minute: Option<u32>,
+    /// Seconds to refuse alerts after each trade; 0 lifts the cooldown
+    #[arg(long)]
+    pub cooldown_secs: Option<u64>,
+    /// Drop the listener's filter rules before applying any given here
+    #[arg(long)]
+    #[serde(default)]
+    pub clear_filter: bool,
+    /// Only pass on alerts for these symbols (comma separated)
+    #[arg(long, value_delimiter = ',')]
+    pub symbols: Option<Vec<String>>,
+    /// Only pass on these actions, e.g. `buy,sell`
+    #[arg(long, value_delimiter = ',')]
+    pub actions: Option<Vec<String>>,
+    /// Ignore alerts priced below this
+    #[arg(long)]
+    pub min_price: Option<Decimal>,
+    /// Ignore alerts priced above this
+    #[arg(long)]
+    pub max_price: Option<Decimal>,
+    /// Only pass on alerts arriving within these hours, `HH:MM-HH:MM` in UTC
+    #[arg(long)]
+    pub trading_hours: Option<TradingHours>,
+    /// Drop the listener's notification preferences, so it follows its bot's again
+    #[arg(long)]
+    #[serde(default)]
+    pub clear_notifications: bool,
+    /// Alert outcomes that notify, e.g. `failed`
+    #[arg(long, value_delimiter = ',')]
+    pub notify_on: Option<Vec<ExecutionStatus>>,
+    /// Channels notifications go through, e.g. `ops,webhook`
+    #[arg(long, value_delimiter = ',')]
+    pub notify_channels: Option<Vec<String>>,
 }
 impl ListenerUpdateArgs {
     /// Create a new `ListenerUpdateArgs` with mandatory `listener_id`
@@ -27,6 +72,19 @@ impl ListenerUpdateArgs {
             service: None,
             secret: None,
             msg: None,
+            enabled: None,
+            verify_secret: None,
+            max_alerts_per_minute: None,
+            cooldown_secs: None,
+            clear_filter: false,
+            symbols: None,
+            actions: None,
+            min_price: None,
+            max_price: None,
+            trading_hours: None,
+            clear_notifications: false,
+            notify_on: None,
+            notify_channels: None,
         }
     }
 
@@ -39,7 +97,49 @@ impl ListenerUpdateArgs {
             listener.secret = secret.clone();
         }
         if let Some(msg) = &self.msg {
-            listener.msg = msg.clone();
+            listener.msg.clear();
+            listener.msg_sha256 = templates::address(msg);
+        }
+        if let Some(enabled) = self.enabled {
+            listener.enabled = enabled;
+        }
+        if let Some(verify_secret) = self.verify_secret {
+            listener.verify_secret = verify_secret;
+        }
+        if let Some(max) = self.max_alerts_per_minute {
+            listener.max_alerts_per_minute = (max > 0).then_some(max);
+        }
+        if let Some(cooldown) = self.cooldown_secs {
+            listener.cooldown_secs = (cooldown > 0).then_some(cooldown);
+        }
+        let filter = &mut listener.filter;
+        if self.clear_filter {
+            *filter = AlertFilter::default();
+        }
+        if let Some(symbols) = &self.symbols {
+            filter.symbols = symbols.clone();
+        }
+        if let Some(actions) = &self.actions {
+            filter.actions = actions.clone();
+        }
+        if let Some(min_price) = self.min_price {
+            filter.min_price = Some(min_price);
+        }
+        if let Some(max_price) = self.max_price {
+            filter.max_price = Some(max_price);
+        }
+        if let Some(trading_hours) = self.trading_hours {
+            filter.trading_hours = Some(trading_hours);
+        }
+        let notifications = &mut listener.notifications;
+        if self.clear_notifications {
+            *notifications = NotificationPrefs::default();
+        }
+        if let Some(on) = &self.notify_on {
+            notifications.on = Some(on.clone());
+        }
+        if let Some(channels) = &self.notify_channels {
+            notifications.channels = Some(channels.clone());
         }
     }
 }
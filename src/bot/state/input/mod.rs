@@ -1,2 +1,6 @@
 pub mod bot;
+pub mod bulk;
 pub mod listener;
+pub mod report;
+pub mod state;
+pub mod user;
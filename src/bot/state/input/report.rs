@@ -0,0 +1,107 @@
+use crate::bot::allocation::Budget;
+use chrono::{DateTime, NaiveDate, Utc};
+use clap::Args;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use validator::Validate;
+
+#[derive(Args, Clone, Debug, Default, Deserialize, Serialize, Validate)]
+pub struct ExecutionQualityArgs {
+    /// Only report on this bot
+    #[arg(long)]
+    pub bot_id: Option<String>,
+    /// Only count trades finished at or after this time (RFC 3339)
+    #[arg(long)]
+    pub since: Option<DateTime<Utc>>,
+}
+
+#[derive(Args, Clone, Debug, Default, Deserialize, Serialize, Validate)]
+pub struct StrategyReportArgs {
+    /// Only report on this bot
+    #[arg(long)]
+    pub bot_id: Option<String>,
+    /// Only count trades finished at or after this time (RFC 3339)
+    #[arg(long)]
+    pub since: Option<DateTime<Utc>>,
+}
+
+#[derive(Args, Clone, Debug, Default, Deserialize, Serialize, Validate)]
+pub struct AllocationReportArgs {
+    /// Only report on this bot
+    #[arg(long)]
+    pub bot_id: Option<String>,
+}
+
+#[derive(Args, Clone, Debug, Default, Deserialize, Serialize, Validate)]
+pub struct MonteCarloArgs {
+    #[arg(long)]
+    #[serde(default)]
+    #[validate(length(min = 1, message = "ID cannot be empty"))]
+    pub bot_id: String,
+    /// Only resample trades finished at or after this time (RFC 3339)
+    #[arg(long)]
+    pub since: Option<DateTime<Utc>>,
+    /// Simulated trade sequences (defaults to 1000)
+    #[arg(long)]
+    #[validate(range(min = 1, max = 20_000, message = "Simulate 1 to 20000 sequences"))]
+    pub simulations: Option<usize>,
+    /// Trades per sequence (defaults to the number of historical round trips)
+    #[arg(long)]
+    #[validate(range(min = 1, max = 10_000, message = "Simulate 1 to 10000 trades"))]
+    pub trades: Option<usize>,
+    /// Starting equity in the quote asset, to express drawdowns in percent and estimate the
+    /// risk of ruin
+    #[arg(long)]
+    pub capital: Option<Decimal>,
+    /// Share of the capital whose loss counts as ruin (defaults to 0.5)
+    #[arg(long)]
+    pub ruin: Option<Decimal>,
+    /// Seed the resampling, for reproducible results
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// Also draw the spread of the simulated equity curves to this PNG file
+    #[arg(long)]
+    #[serde(skip)]
+    pub chart: Option<PathBuf>,
+}
+
+#[derive(Args, Clone, Debug, Default, Deserialize, Serialize, Validate)]
+pub struct StatementArgs {
+    #[arg(long)]
+    #[validate(length(min = 1, message = "ID cannot be empty"))]
+    pub bot_id: String,
+    /// Month of the statement (YYYY-MM)
+    #[arg(long)]
+    pub month: String,
+    /// Write the HTML statement to this file instead of printing it
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Clone, Debug, Deserialize, Serialize, Validate)]
+pub struct ReplayArgs {
+    #[arg(long)]
+    #[serde(default)]
+    #[validate(length(min = 1, message = "ID cannot be empty"))]
+    pub bot_id: String,
+    /// Day whose alerts to replay (YYYY-MM-DD, UTC)
+    #[arg(long)]
+    pub date: NaiveDate,
+    /// Replay with this budget instead of the bot's: an amount in the quote asset, or a
+    /// share of `allocation.capital` such as `25%`
+    #[arg(long)]
+    pub budget: Option<Budget>,
+    /// Replay with at most this many trades in the day instead of the quota's
+    #[arg(long)]
+    pub max_trades: Option<u32>,
+    /// Replay market orders with this slippage, in basis points, instead of the bot's
+    #[arg(long)]
+    pub slippage_bps: Option<Decimal>,
+    /// Replay with this share of each order filling, in percent, instead of the bot's
+    #[arg(long)]
+    pub fill_pct: Option<Decimal>,
+    /// Replay with this fee on the notional instead of the bot's `trading_fee`
+    #[arg(long)]
+    pub trading_fee: Option<Decimal>,
+}
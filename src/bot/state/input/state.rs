@@ -0,0 +1,89 @@
+use crate::app_config::RetentionPeriod;
+use crate::bot::state::export::ExportFormat;
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use validator::Validate;
+
+#[derive(Args, Clone, Debug, Default, Deserialize, Serialize, Validate)]
+pub struct StateExportArgs {
+    /// Document format: json, yaml or toml
+    #[arg(long, default_value_t)]
+    #[serde(default)]
+    pub format: ExportFormat,
+    /// Leave out the credentials and listener secrets, keeping references to the environment
+    #[arg(long)]
+    #[serde(default)]
+    pub redact_secrets: bool,
+    /// Write the export to this file instead of printing it
+    #[arg(long)]
+    #[serde(skip)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Clone, Debug, Default, Deserialize, Serialize, Validate)]
+pub struct StateImportArgs {
+    /// Export to import
+    #[arg(long)]
+    #[serde(skip)]
+    pub file: PathBuf,
+    /// Document format; defaults to the file's extension, or json
+    #[arg(long)]
+    #[serde(default)]
+    pub format: Option<ExportFormat>,
+    /// Add the exported bots and update those that exist already (the default)
+    #[arg(long, conflicts_with = "replace")]
+    #[serde(skip)]
+    pub merge: bool,
+    /// Also delete the bots the export does not hold
+    #[arg(long)]
+    #[serde(default)]
+    pub replace: bool,
+}
+
+impl StateImportArgs {
+    /// The format of the file: as given, or as its extension names.
+    pub fn file_format(&self) -> ExportFormat {
+        (self.format)
+            .or_else(|| ExportFormat::from_path(&self.file))
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Args, Clone, Debug, Default, Deserialize, Serialize, Validate)]
+pub struct StateApplyArgs {
+    /// Manifest of offline commands, one per line as typed after `xtrade offline`; blank
+    /// lines and lines starting with `#` are skipped
+    #[arg(long)]
+    #[serde(skip)]
+    pub file: PathBuf,
+}
+
+#[derive(Args, Clone, Debug, Default, Deserialize, Serialize, Validate)]
+pub struct StateCompactArgs {
+    /// Keep alerts this long instead of `retention.alerts`, e.g. 30d or forever
+    #[arg(long)]
+    #[serde(default)]
+    pub alerts: Option<RetentionPeriod>,
+    /// Keep timeline events this long instead of `retention.events`
+    #[arg(long)]
+    #[serde(default)]
+    pub events: Option<RetentionPeriod>,
+    /// Keep trades this long instead of `retention.trades`
+    #[arg(long)]
+    #[serde(default)]
+    pub trades: Option<RetentionPeriod>,
+    /// Keep audit log lines this long instead of `retention.audit`
+    #[arg(long)]
+    #[serde(default)]
+    pub audit: Option<RetentionPeriod>,
+    /// Keep access log lines this long instead of `retention.access_log`
+    #[arg(long)]
+    #[serde(default)]
+    pub access_log: Option<RetentionPeriod>,
+    /// Append what is pruned to the archive next to the state file, whatever
+    /// `retention.archive` says
+    #[arg(long)]
+    #[serde(default)]
+    pub archive: bool,
+}
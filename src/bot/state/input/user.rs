@@ -0,0 +1,34 @@
+use crate::bot::auth::Role;
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Args, Clone, Debug, Deserialize, Serialize, Validate)]
+pub struct UserInsertArgs {
+    #[arg(long)]
+    #[validate(length(min = 1, message = "Username cannot be empty"))]
+    pub username: String,
+    /// The password, or `env:NAME` to read it from the environment variable `NAME`
+    #[arg(long)]
+    #[serde(skip_serializing)]
+    pub password: String,
+    /// admin, or read_only to only read and never see secrets
+    #[arg(long, default_value_t)]
+    #[serde(default)]
+    pub role: Role,
+}
+
+#[derive(Args, Clone, Debug, Deserialize, Serialize, Validate)]
+pub struct UserDeleteArgs {
+    #[arg(long)]
+    #[validate(length(min = 1, message = "Username cannot be empty"))]
+    pub username: String,
+}
+
+/// Body of `POST /auth/login`.
+#[derive(Clone, Debug, Deserialize, Validate)]
+pub struct LoginArgs {
+    #[validate(length(min = 1, message = "Username cannot be empty"))]
+    pub username: String,
+    pub password: String,
+}
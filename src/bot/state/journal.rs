@@ -0,0 +1,119 @@
+// src/bot/state/journal.rs
+//! # Work Journal
+//!
+//! Work that was accepted but not finished is persisted with the state, so an unclean
+//! shutdown cannot lose track of it. Entries move from `queued` (accepted, not yet sent to an
+//! exchange) to `submitted` (sent, not yet confirmed) and are dropped once confirmed. Whatever
+//! is left after a crash ends up in the [recovery report](crate::bot::recovery).
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct JournalEntry {
+    pub id: String,
+    pub bot_id: String,
+    /// When the entry last changed stage
+    pub recorded_at: DateTime<Utc>,
+    pub summary: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct Journal {
+    /// Accepted but not yet submitted
+    #[serde(default)]
+    pub queued: Vec<JournalEntry>,
+    /// Submitted to an exchange but not yet confirmed
+    #[serde(default)]
+    pub submitted: Vec<JournalEntry>,
+}
+
+impl Journal {
+    pub fn is_empty(&self) -> bool {
+        self.queued.is_empty() && self.submitted.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.queued.len() + self.submitted.len()
+    }
+
+    pub fn enqueue(&mut self, entry: JournalEntry) {
+        self.queued.push(entry);
+    }
+
+    /// Move a queued entry to `submitted`. Returns `false` if it was not queued.
+    pub fn submit(&mut self, id: &str, now: DateTime<Utc>) -> bool {
+        let Some(index) = self.queued.iter().position(|entry| entry.id == id) else {
+            return false;
+        };
+        let mut entry = self.queued.remove(index);
+        entry.recorded_at = now;
+        self.submitted.push(entry);
+        true
+    }
+
+    /// Move a submitted entry back to `queued`, when its exchange could not be reached.
+    /// Returns `false` if it was not submitted.
+    pub fn requeue(&mut self, id: &str, now: DateTime<Utc>) -> bool {
+        let Some(index) = self.submitted.iter().position(|entry| entry.id == id) else {
+            return false;
+        };
+        let mut entry = self.submitted.remove(index);
+        entry.recorded_at = now;
+        self.queued.push(entry);
+        true
+    }
+
+    /// Forget a queued entry that will never be submitted. Returns `false` if unknown.
+    pub fn discard(&mut self, id: &str) -> bool {
+        let before = self.queued.len();
+        self.queued.retain(|entry| entry.id != id);
+        self.queued.len() != before
+    }
+
+    /// Forget a submitted entry once the exchange confirmed it. Returns `false` if unknown.
+    pub fn confirm(&mut self, id: &str) -> bool {
+        let before = self.submitted.len();
+        self.submitted.retain(|entry| entry.id != id);
+        self.submitted.len() != before
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_move_through_the_journal() {
+        let now = Utc::now();
+        let mut journal = Journal::default();
+        journal.enqueue(JournalEntry {
+            id: "order-1".to_string(),
+            bot_id: "alpha".to_string(),
+            recorded_at: now,
+            summary: "buy BTCUSDT".to_string(),
+        });
+        assert_eq!(journal.queued.len(), 1);
+
+        assert!(journal.submit("order-1", now));
+        assert!(!journal.submit("order-1", now));
+        assert!(journal.queued.is_empty());
+        assert_eq!(journal.submitted.len(), 1);
+
+        assert!(journal.requeue("order-1", now));
+        assert!(!journal.requeue("order-1", now));
+        assert!(journal.submit("order-1", now));
+
+        assert!(journal.confirm("order-1"));
+        assert!(journal.is_empty());
+
+        journal.enqueue(JournalEntry {
+            id: "order-2".to_string(),
+            bot_id: "alpha".to_string(),
+            recorded_at: now,
+            summary: "sell BTCUSDT".to_string(),
+        });
+        assert!(!journal.confirm("order-2"));
+        assert!(journal.discard("order-2"));
+        assert!(journal.is_empty());
+    }
+}
@@ -0,0 +1,123 @@
+// src/bot/state/ledger.rs
+//! # Transfer Ledger
+//!
+//! Deposits to and withdrawals from the exchange accounts of bots, as the
+//! [withdrawal monitor](crate::bot::withdrawals) last fetched them, persisted with the state
+//! so a transfer seen once is not reported again after a restart.
+use crate::exchange::Transfer;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Transfers of one bot's account.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct BotLedger {
+    /// When the exchange was last asked
+    pub checked_at: Option<DateTime<Utc>>,
+    /// Oldest first
+    #[serde(default)]
+    pub transfers: Vec<Transfer>,
+}
+
+/// Transfers of every bot whose account was checked, by bot.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(transparent)]
+pub struct Ledger {
+    bots: BTreeMap<String, BotLedger>,
+}
+
+impl Ledger {
+    pub fn is_empty(&self) -> bool {
+        self.bots.is_empty()
+    }
+
+    /// Record the `transfers` of `bot_id` the exchange listed at `now`, forgetting those
+    /// before `since`, and return the ones not seen before. Transfers seen before take the
+    /// status now listed.
+    pub fn record(
+        &mut self,
+        bot_id: &str,
+        transfers: Vec<Transfer>,
+        now: DateTime<Utc>,
+        since: DateTime<Utc>,
+    ) -> Vec<Transfer> {
+        let ledger = self.bots.entry(bot_id.to_string()).or_default();
+        ledger.checked_at = Some(now);
+        let mut new = Vec::new();
+        for transfer in transfers {
+            let known = ledger
+                .transfers
+                .iter_mut()
+                .find(|known| known.kind == transfer.kind && known.id == transfer.id);
+            match known {
+                Some(known) => *known = transfer,
+                None => {
+                    new.push(transfer.clone());
+                    ledger.transfers.push(transfer);
+                }
+            }
+        }
+        ledger
+            .transfers
+            .retain(|transfer| transfer.timestamp >= since);
+        ledger.transfers.sort_by_key(|transfer| transfer.timestamp);
+        new
+    }
+
+    /// Transfers of `bot_id` last seen, oldest first.
+    #[allow(dead_code)]
+    pub fn get(&self, bot_id: &str) -> Option<&BotLedger> {
+        self.bots.get(bot_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::TransferKind;
+    use chrono::Duration;
+    use rust_decimal::dec;
+
+    #[test]
+    fn transfers_are_new_once_and_forgotten_after_the_lookback() {
+        let now = Utc::now();
+        let transfer = |id: &str, status: &str, hours_ago| Transfer {
+            id: id.to_string(),
+            kind: TransferKind::Withdrawal,
+            asset: "USDT".to_string(),
+            amount: dec!(100),
+            address: Some("0xabc".to_string()),
+            status: status.to_string(),
+            timestamp: now - Duration::hours(hours_ago),
+        };
+        let mut ledger = Ledger::default();
+        let since = now - Duration::hours(24);
+        let new = ledger.record(
+            "alpha",
+            vec![
+                transfer("1", "processing", 2),
+                transfer("2", "completed", 30),
+            ],
+            now,
+            since,
+        );
+        assert_eq!(new.len(), 2);
+        assert_eq!(ledger.get("alpha").unwrap().transfers.len(), 1);
+
+        let new = ledger.record(
+            "alpha",
+            vec![
+                transfer("1", "completed", 2),
+                transfer("3", "processing", 1),
+            ],
+            now,
+            since,
+        );
+        let ids: Vec<_> = new.iter().map(|transfer| transfer.id.as_str()).collect();
+        assert_eq!(ids, ["3"]);
+        let alpha = ledger.get("alpha").unwrap();
+        assert_eq!(alpha.checked_at, Some(now));
+        assert_eq!(alpha.transfers[0].status, "completed");
+        assert!(ledger.get("beta").is_none());
+    }
+}
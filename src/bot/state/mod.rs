@@ -58,20 +58,59 @@
 //! state.clear_listeners()?;
 //! println!("All listeners cleared.");
 //! ```
+pub mod alerts;
+pub mod dedup;
+pub mod events;
+pub mod export;
+pub mod history;
 pub mod input;
+pub mod journal;
+pub mod ledger;
 pub mod output;
+pub mod paper;
+pub mod provider;
+pub mod quota;
+pub mod ratelimit;
 pub mod registry;
+pub mod store;
+pub mod templates;
+pub mod tenant;
 
 pub use super::server::ServerStartupArgs;
-pub use input::bot::{BotDeleteArgs, BotGetArgs, BotInsertArgs, BotListArgs, BotUpdateArgs};
+pub use input::bot::{
+    AlertListArgs, AlertReplayArgs, AlertSimulateArgs, BotDeleteArgs, BotGetArgs, BotInsertArgs,
+    BotListArgs, BotTimelineArgs, BotTradesArgs, BotUpdateArgs, BotWatchArgs, FillImportArgs,
+    OrderCancelArgs, SymbolMapArgs, SymbolUnmapArgs,
+};
+pub use input::bulk::{BulkApplyArgs, BulkOperation, BulkRequest, ListenerBulkRequest};
 pub use input::listener::{
-    ListenerDeleteArgs, ListenerGetArgs, ListenerInsertArgs, ListenerListArgs, ListenerUpdateArgs,
-    ListenersDeleteArgs,
+    ListenerDeleteArgs, ListenerGetArgs, ListenerInsertArgs, ListenerListArgs,
+    ListenerRotateSecretArgs, ListenerUpdateArgs, ListenersDeleteArgs, MAX_SECRET_GRACE_SECS,
+};
+pub use input::report::{
+    AllocationReportArgs, ExecutionQualityArgs, MonteCarloArgs, ReplayArgs, StatementArgs,
+    StrategyReportArgs,
+};
+pub use input::state::{StateApplyArgs, StateCompactArgs, StateExportArgs, StateImportArgs};
+pub use input::user::{LoginArgs, UserDeleteArgs, UserInsertArgs};
+pub use output::{
+    AdminInfoView, AlertListView, AlertSimulationView, ApiVersionsView, BalanceListView,
+    BotListView, BotView, CancelledOrderView, EntityCounts, FillImportView, MemoryStatsView,
+    OpenOrderListView, PnlView, PositionListView, PriceListView, PriceView, ReadinessView,
+    StateSizes, SymbolMapView, SymbolPnl, TimelineView, TradeListView, VersionView,
+};
+pub use output::{
+    AllocationView, BotAllocation, BulkOutcome, BulkView, EquityBand, ExecutionQuality,
+    ExecutionQualityView, ListenerListView, ListenerView, MonteCarloPercentile, MonteCarloView,
+    ReplayFill, ReplayLimits, ReplayOutcome, ReplayView, ReplayedAlert, SecretRotationView,
+    StateCompactView, StateImportView, StrategyPerformance, StrategyReportView,
 };
-pub use output::{BotListView, BotView};
-pub use output::{ListenerListView, ListenerView};
+pub use output::{BotStatsView, ExchangeRequestStats, FillLatencyStats};
+pub use output::{LoginView, UserListView, UserView};
 
+pub use provider::Providers;
 pub use registry::BotRegistry;
+pub use tenant::TenantScope;
 
 use super::model::{Bot, Listener};
 pub use crate::app_state::AppState;
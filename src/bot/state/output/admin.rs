@@ -0,0 +1,264 @@
+use crate::app_config::MaintenanceWindow;
+use crate::bot::reconcile::ReconciliationReport;
+use crate::bot::recovery::RecoveryReport;
+use crate::utils::alloc::AllocStats;
+use chrono::{DateTime, Utc};
+use prettytable::{format, Cell, Row, Table};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+
+/// Server facts for operators, served by `GET /admin/info`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdminInfoView {
+    pub version: String,
+    pub pid: u32,
+    pub started_at: DateTime<Utc>,
+    pub state_file: PathBuf,
+    pub bots: usize,
+    pub last_successful_save: Option<DateTime<Utc>>,
+    /// Present if this run started after an unclean shutdown
+    pub recovery: Option<RecoveryReport>,
+    /// Result of startup reconciliation, once it has finished
+    pub reconciliation: Option<ReconciliationReport>,
+    /// Alerts refused for being too old since the server started
+    #[serde(default)]
+    pub stale_alerts: u64,
+    /// Maintenance windows in progress or to come, soonest first
+    #[serde(default)]
+    pub maintenance: Vec<MaintenanceWindow>,
+}
+
+impl fmt::Display for AdminInfoView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Version: {}", self.version)?;
+        writeln!(f, "PID: {}", self.pid)?;
+        writeln!(f, "Started At: {}", self.started_at.to_rfc3339())?;
+        writeln!(f, "State File: {}", self.state_file.display())?;
+        writeln!(f, "Bots: {}", self.bots)?;
+        writeln!(f, "Stale Alerts Refused: {}", self.stale_alerts)?;
+        write!(
+            f,
+            "Last Save: {}",
+            self.last_successful_save
+                .map_or_else(|| "never".to_string(), |time| time.to_rfc3339())
+        )?;
+        match &self.recovery {
+            Some(report) => write!(f, "\n{}", report),
+            None => write!(f, "\nLast Shutdown: clean"),
+        }?;
+        match &self.reconciliation {
+            Some(report) => write!(f, "\n{}", report),
+            None => write!(f, "\nReconciliation: pending"),
+        }?;
+        if self.maintenance.is_empty() {
+            return write!(f, "\nMaintenance: none scheduled");
+        }
+        write!(f, "\nMaintenance:")?;
+        for window in &self.maintenance {
+            write!(
+                f,
+                "\n  {}: {} to {}",
+                window.exchange,
+                window.starts_at.to_rfc3339(),
+                window.ends_at.to_rfc3339()
+            )?;
+            if let Some(reason) = &window.reason {
+                write!(f, " ({})", reason)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// API versions of a server, served by `GET /api/versions`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ApiVersionsView {
+    /// Versions served, each under `/api/<version>`
+    pub versions: Vec<String>,
+    /// Version of the unversioned, deprecated paths
+    pub current: String,
+}
+
+/// Readiness of a server to take traffic, served by `GET /readyz`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ReadinessView {
+    /// Every check passed
+    pub ready: bool,
+    /// The state can be locked
+    pub state_loaded: bool,
+    /// The state file can still be written
+    pub storage_writable: bool,
+    /// Exchanges of the bots, by whether their circuit is closed; only when asked for
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub exchanges: BTreeMap<String, bool>,
+}
+
+impl fmt::Display for ReadinessView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let check = |passed: bool| if passed { "ok" } else { "failed" };
+        write!(
+            f,
+            "{}
+State loaded: {}
+Storage writable: {}",
+            if self.ready { "ready" } else { "not ready" },
+            check(self.state_loaded),
+            check(self.storage_writable)
+        )?;
+        for (exchange, reachable) in &self.exchanges {
+            let reachable = if *reachable {
+                "reachable"
+            } else {
+                "unreachable"
+            };
+            write!(f, "\nExchange {}: {}", exchange, reachable)?;
+        }
+        Ok(())
+    }
+}
+
+/// Build of a server, served by `GET /version`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VersionView {
+    pub version: String,
+    /// Commit the server was built from, if it was built from a git checkout
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub built_at: Option<DateTime<Utc>>,
+    /// Current API version
+    pub api_version: String,
+}
+
+impl fmt::Display for VersionView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "xtrade {}", self.version)?;
+        if let Some(git_hash) = &self.git_hash {
+            write!(f, " ({})", git_hash)?;
+        }
+        if let Some(built_at) = self.built_at {
+            write!(f, ", built {}", built_at.to_rfc3339())?;
+        }
+        write!(f, ", API {}", self.api_version)
+    }
+}
+
+/// A cached price, served by `GET /admin/prices`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceView {
+    pub symbol: String,
+    pub price: f64,
+    pub observed_at: DateTime<Utc>,
+    pub age_secs: i64,
+    /// Too old to be used without a refresh
+    pub stale: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceListView(pub Vec<PriceView>);
+
+impl fmt::Display for PriceListView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+
+        table.add_row(Row::new(vec![
+            Cell::new("Symbol"),
+            Cell::new("Price"),
+            Cell::new("Observed At"),
+            Cell::new("Age (s)"),
+            Cell::new("Stale"),
+        ]));
+        for price in &self.0 {
+            table.add_row(Row::new(vec![
+                Cell::new(&price.symbol),
+                Cell::new(&price.price.to_string()),
+                Cell::new(&price.observed_at.to_rfc3339()),
+                Cell::new(&price.age_secs.to_string()),
+                Cell::new(if price.stale { "yes" } else { "no" }),
+            ]));
+        }
+        write!(f, "{}", table)
+    }
+}
+
+/// Number of records of each kind held in memory.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EntityCounts {
+    pub bots: usize,
+    pub listeners: usize,
+    pub events: usize,
+    pub journal_entries: usize,
+    /// Alerts in the alert log
+    #[serde(default)]
+    pub alerts: usize,
+    /// Webhook alerts remembered to recognise repeats
+    #[serde(default)]
+    pub recent_alerts: usize,
+    pub cached_prices: usize,
+}
+
+/// Sizes in bytes of the state, by section as compact JSON, and of what is kept beside it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StateSizes {
+    pub bots_bytes: u64,
+    pub events_bytes: u64,
+    pub journal_bytes: u64,
+    /// The state file as stored (possibly pretty-printed or compressed)
+    pub file_bytes: Option<u64>,
+    pub trade_history_shards: usize,
+    pub trade_history_bytes: u64,
+}
+
+/// Memory diagnostics, served by `GET /admin/stats/memory`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryStatsView {
+    pub counts: EntityCounts,
+    pub state: StateSizes,
+    /// Heap usage; only counted by builds with the `alloc-stats` feature
+    pub allocator: Option<AllocStats>,
+}
+
+impl fmt::Display for MemoryStatsView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+
+        let file_bytes = self
+            .state
+            .file_bytes
+            .map_or_else(|| "-".to_string(), |bytes| bytes.to_string());
+        let trade_history = format!(
+            "{} ({} shards)",
+            self.state.trade_history_bytes, self.state.trade_history_shards
+        );
+        let mut rows = vec![
+            ("Bots", self.counts.bots.to_string()),
+            ("Listeners", self.counts.listeners.to_string()),
+            ("Events", self.counts.events.to_string()),
+            ("Journal Entries", self.counts.journal_entries.to_string()),
+            ("Alerts", self.counts.alerts.to_string()),
+            ("Recent Alerts", self.counts.recent_alerts.to_string()),
+            ("Cached Prices", self.counts.cached_prices.to_string()),
+            ("Bots (bytes)", self.state.bots_bytes.to_string()),
+            ("Events (bytes)", self.state.events_bytes.to_string()),
+            ("Journal (bytes)", self.state.journal_bytes.to_string()),
+            ("State File (bytes)", file_bytes),
+            ("Trade History (bytes)", trade_history),
+        ];
+        match &self.allocator {
+            Some(alloc) => rows.extend([
+                ("Heap Allocated (bytes)", alloc.allocated_bytes.to_string()),
+                ("Heap Peak (bytes)", alloc.peak_bytes.to_string()),
+                ("Allocations", alloc.allocations.to_string()),
+            ]),
+            None => rows.push(("Heap", "not counted by this build".to_string())),
+        }
+        for (label, value) in rows {
+            table.add_row(Row::new(vec![Cell::new(label), Cell::new(&value)]));
+        }
+        write!(f, "{}", table)
+    }
+}
@@ -0,0 +1,63 @@
+use crate::exchange::{Balance, Position};
+use prettytable::{format, Cell, Row, Table};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Balances of a bot's exchange account, served by `GET /bots/{bot_id}/balances`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BalanceListView(pub Vec<Balance>);
+
+impl fmt::Display for BalanceListView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+
+        table.add_row(Row::new(vec![
+            Cell::new("Asset"),
+            Cell::new("Free"),
+            Cell::new("Locked"),
+        ]));
+        for balance in &self.0 {
+            table.add_row(Row::new(vec![
+                Cell::new(balance.asset.as_str()),
+                Cell::new(&balance.free.to_string()),
+                Cell::new(&balance.locked.to_string()),
+            ]));
+        }
+        write!(f, "{}", table)
+    }
+}
+
+/// Open positions of a bot's exchange account, served by `GET /bots/{bot_id}/positions`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PositionListView(pub Vec<Position>);
+
+impl fmt::Display for PositionListView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "No open positions");
+        }
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+
+        table.add_row(Row::new(vec![
+            Cell::new("Symbol"),
+            Cell::new("Side"),
+            Cell::new("Quantity"),
+            Cell::new("Entry Price"),
+            Cell::new("Unrealized PnL"),
+        ]));
+        let or_na = |value: Option<Decimal>| value.map_or("N/A".to_string(), |v| v.to_string());
+        for position in &self.0 {
+            table.add_row(Row::new(vec![
+                Cell::new(&position.symbol.to_string()),
+                Cell::new(&position.side.to_string()),
+                Cell::new(&position.quantity.to_string()),
+                Cell::new(&or_na(position.entry_price)),
+                Cell::new(&or_na(position.unrealized_pnl)),
+            ]));
+        }
+        write!(f, "{}", table)
+    }
+}
@@ -0,0 +1,93 @@
+use crate::bot::dispatch::{ExecutionResult, ExecutionStatus};
+use crate::bot::state::alerts::AlertRecord;
+use crate::errors::ErrorCode;
+use prettytable::{format, Cell, Row, Table};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Alerts a bot received, served by `GET /bots/{bot_id}/alerts`, newest first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertListView {
+    pub bot_id: String,
+    pub alerts: Vec<AlertRecord>,
+}
+
+impl fmt::Display for AlertListView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.alerts.is_empty() {
+            return write!(f, "No alerts for bot '{}'", self.bot_id);
+        }
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+
+        table.add_row(Row::new(vec![
+            Cell::new("Received"),
+            Cell::new("Alert ID"),
+            Cell::new("Listener"),
+            Cell::new("Status"),
+            Cell::new("Message"),
+        ]));
+        for alert in &self.alerts {
+            let mut message = match (&alert.error, &alert.result) {
+                (Some(error), _) => error.clone(),
+                (None, Some(result)) => result.message.clone(),
+                (None, None) => String::new(),
+            };
+            if let Some(original) = &alert.replay_of {
+                message = format!("replay of {}: {}", original, message);
+            }
+            table.add_row(Row::new(vec![
+                Cell::new(&alert.received_at.format("%Y-%m-%d %H:%M:%S").to_string()),
+                Cell::new(&alert.id),
+                Cell::new(&alert.listener_id),
+                Cell::new(&alert.status()),
+                Cell::new(&message),
+            ]));
+        }
+        write!(f, "{}", table)
+    }
+}
+
+/// What a listener would make of an alert, answered by
+/// `POST /bots/{bot_id}/listeners/{listener_id}/test-fire`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertSimulationView {
+    pub bot_id: String,
+    pub listener_id: String,
+    pub exchange: String,
+    /// The order the alert asks for, mapped, rounded and cut down to the bot's budget
+    pub order: String,
+    /// What the alert would become; `executed` if the order would be sent
+    pub status: ExecutionStatus,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<ErrorCode>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// What became of the alert, if it was executed after all
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<ExecutionResult>,
+}
+
+impl fmt::Display for AlertSimulationView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(result) = &self.result {
+            return write!(f, "{}", result);
+        }
+        let outcome = match self.status {
+            ExecutionStatus::Executed => "would be sent",
+            ExecutionStatus::Failed => "would fail",
+            ExecutionStatus::Ignored => "would be ignored",
+            ExecutionStatus::Buffered => "would wait for its exchange",
+        };
+        write!(
+            f,
+            "Alert for bot '{}' via listener '{}' {}: {}",
+            self.bot_id, self.listener_id, outcome, self.order
+        )?;
+        if !self.exchange.is_empty() {
+            write!(f, " on {}", self.exchange)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
@@ -1,5 +1,21 @@
+pub mod account;
+pub mod alerts;
 pub mod list;
+pub mod orders;
+pub mod pnl;
+pub mod stats;
+pub mod symbols;
+pub mod timeline;
+pub mod trades;
 pub mod view;
 
+pub use account::{BalanceListView, PositionListView};
+pub use alerts::{AlertListView, AlertSimulationView};
 pub use list::BotListView;
+pub use orders::{CancelledOrderView, OpenOrderListView};
+pub use pnl::{PnlView, SymbolPnl};
+pub use stats::{BotStatsView, ExchangeRequestStats, FillLatencyStats};
+pub use symbols::SymbolMapView;
+pub use timeline::TimelineView;
+pub use trades::{FillImportView, TradeListView};
 pub use view::BotView;
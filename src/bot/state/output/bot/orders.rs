@@ -0,0 +1,56 @@
+use crate::exchange::OpenOrder;
+use prettytable::{format, Cell, Row, Table};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Open orders of a bot on its exchange, served by `GET /bots/{bot_id}/orders`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenOrderListView(pub Vec<OpenOrder>);
+
+impl fmt::Display for OpenOrderListView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "No open orders");
+        }
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+
+        table.add_row(Row::new(vec![
+            Cell::new("Order ID"),
+            Cell::new("Client Order ID"),
+            Cell::new("Symbol"),
+            Cell::new("Side"),
+            Cell::new("Price"),
+            Cell::new("Quantity"),
+        ]));
+        for order in &self.0 {
+            table.add_row(Row::new(vec![
+                Cell::new(&order.order_id),
+                Cell::new(&order.client_order_id),
+                Cell::new(&order.symbol),
+                Cell::new(&order.side),
+                Cell::new(&order.price.to_string()),
+                Cell::new(&order.quantity.to_string()),
+            ]));
+        }
+        write!(f, "{}", table)
+    }
+}
+
+/// An order cancelled by `DELETE /bots/{bot_id}/orders/{order_id}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CancelledOrderView {
+    pub bot_id: String,
+    pub order_id: String,
+    pub symbol: String,
+}
+
+impl fmt::Display for CancelledOrderView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Cancelled order {} of bot '{}' on {}",
+            self.order_id, self.bot_id, self.symbol
+        )
+    }
+}
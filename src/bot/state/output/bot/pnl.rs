@@ -0,0 +1,82 @@
+use prettytable::{format, Cell, Row, Table};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// PnL of a bot in one symbol.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SymbolPnl {
+    pub symbol: String,
+    /// Open quantity: positive when long, negative when short
+    pub quantity: Decimal,
+    /// Average entry price of the open quantity
+    pub entry_price: Decimal,
+    /// Cached price the open quantity is marked to
+    pub mark_price: Option<Decimal>,
+    /// Profit of the closed quantity, before fees
+    pub realized_pnl: Decimal,
+    /// Profit of the open quantity at `mark_price`; unknown without a price
+    pub unrealized_pnl: Option<Decimal>,
+    pub fees: Decimal,
+    pub trades: u64,
+}
+
+/// PnL of a bot's recorded trades, served by `GET /bots/{bot_id}/pnl`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PnlView {
+    pub bot_id: String,
+    pub symbols: Vec<SymbolPnl>,
+    pub realized_pnl: Decimal,
+    /// Of the symbols with a mark price
+    pub unrealized_pnl: Decimal,
+    pub fees: Decimal,
+    /// Realized plus unrealized PnL, after fees
+    pub net_pnl: Decimal,
+}
+
+impl fmt::Display for PnlView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.symbols.is_empty() {
+            return write!(f, "Bot '{}' has no filled trades", self.bot_id);
+        }
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+
+        table.add_row(Row::new(vec![
+            Cell::new("Symbol"),
+            Cell::new("Quantity"),
+            Cell::new("Entry Price"),
+            Cell::new("Mark Price"),
+            Cell::new("Realized"),
+            Cell::new("Unrealized"),
+            Cell::new("Fees"),
+            Cell::new("Trades"),
+        ]));
+        let or_dash =
+            |value: Option<Decimal>| value.map_or_else(|| "-".to_string(), |v| v.to_string());
+        for symbol in &self.symbols {
+            table.add_row(Row::new(vec![
+                Cell::new(&symbol.symbol),
+                Cell::new(&symbol.quantity.to_string()),
+                Cell::new(&symbol.entry_price.to_string()),
+                Cell::new(&or_dash(symbol.mark_price)),
+                Cell::new(&symbol.realized_pnl.to_string()),
+                Cell::new(&or_dash(symbol.unrealized_pnl)),
+                Cell::new(&symbol.fees.to_string()),
+                Cell::new(&symbol.trades.to_string()),
+            ]));
+        }
+        table.add_row(Row::new(vec![
+            Cell::new("Total"),
+            Cell::new(""),
+            Cell::new(""),
+            Cell::new(""),
+            Cell::new(&self.realized_pnl.to_string()),
+            Cell::new(&self.unrealized_pnl.to_string()),
+            Cell::new(&self.fees.to_string()),
+            Cell::new(""),
+        ]));
+        write!(f, "{}", table)?;
+        write!(f, "Net PnL: {}", self.net_pnl)
+    }
+}
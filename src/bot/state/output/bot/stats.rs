@@ -0,0 +1,112 @@
+use chrono::{DateTime, Utc};
+use prettytable::{format, Cell, Row, Table};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// How a bot's orders to one exchange went since the server started.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExchangeRequestStats {
+    pub exchange: String,
+    pub requests: u64,
+    pub errors: u64,
+    /// Share of the requests that failed, from 0 to 1
+    pub error_rate: f64,
+    pub errors_by_code: BTreeMap<String, u64>,
+    /// Latencies in milliseconds; percentiles are bounds of the histogram buckets they fall in
+    pub mean_ms: Option<f64>,
+    pub p50_ms: Option<f64>,
+    pub p95_ms: Option<f64>,
+    pub p99_ms: Option<f64>,
+    pub max_ms: Option<f64>,
+    pub last_error_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
+
+/// How long a bot's executed orders took from the receipt of their alert to the exchange's
+/// acknowledgment, since the server started.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FillLatencyStats {
+    pub fills: u64,
+    /// Latencies in milliseconds; percentiles are bounds of the histogram buckets they fall in
+    pub mean_ms: Option<f64>,
+    pub p50_ms: Option<f64>,
+    pub p95_ms: Option<f64>,
+    pub p99_ms: Option<f64>,
+    pub max_ms: f64,
+}
+
+/// Latency and errors of a bot's exchanges, served by `GET /bots/{bot_id}/stats`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BotStatsView {
+    pub bot_id: String,
+    pub exchanges: Vec<ExchangeRequestStats>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alert_to_fill: Option<FillLatencyStats>,
+}
+
+impl fmt::Display for BotStatsView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.exchanges.is_empty() {
+            return write!(
+                f,
+                "Bot '{}' sent no orders to an exchange since the server started",
+                self.bot_id
+            );
+        }
+        let ms =
+            |value: Option<f64>| value.map_or_else(|| "-".to_string(), |v| format!("{:.0}ms", v));
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+
+        table.add_row(Row::new(vec![
+            Cell::new("Exchange"),
+            Cell::new("Requests"),
+            Cell::new("Errors"),
+            Cell::new("Error Rate"),
+            Cell::new("Mean"),
+            Cell::new("p50"),
+            Cell::new("p95"),
+            Cell::new("p99"),
+            Cell::new("Max"),
+        ]));
+        for stats in &self.exchanges {
+            table.add_row(Row::new(vec![
+                Cell::new(&stats.exchange),
+                Cell::new(&stats.requests.to_string()),
+                Cell::new(&stats.errors.to_string()),
+                Cell::new(&format!("{:.1}%", stats.error_rate * 100.0)),
+                Cell::new(&ms(stats.mean_ms)),
+                Cell::new(&ms(stats.p50_ms)),
+                Cell::new(&ms(stats.p95_ms)),
+                Cell::new(&ms(stats.p99_ms)),
+                Cell::new(&ms(stats.max_ms)),
+            ]));
+        }
+        write!(f, "{}", table)?;
+        for stats in &self.exchanges {
+            if let (Some(at), Some(error)) = (stats.last_error_at, &stats.last_error) {
+                write!(
+                    f,
+                    "\nLast {} error at {}: {}",
+                    stats.exchange,
+                    at.format("%Y-%m-%d %H:%M:%S"),
+                    error
+                )?;
+            }
+        }
+        if let Some(fills) = &self.alert_to_fill {
+            write!(
+                f,
+                "\nAlert to fill over {} fills: mean {}, p50 {}, p95 {}, p99 {}, max {}",
+                fills.fills,
+                ms(fills.mean_ms),
+                ms(fills.p50_ms),
+                ms(fills.p95_ms),
+                ms(fills.p99_ms),
+                ms(Some(fills.max_ms))
+            )?;
+        }
+        Ok(())
+    }
+}
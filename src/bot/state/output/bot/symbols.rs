@@ -0,0 +1,33 @@
+use crate::exchange::SymbolMap;
+use prettytable::{format, Cell, Row, Table};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Symbol mappings of a bot, served by `GET /bots/{bot_id}/symbols`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SymbolMapView {
+    pub bot_id: String,
+    pub symbols: SymbolMap,
+}
+
+impl fmt::Display for SymbolMapView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.symbols.is_empty() {
+            return write!(f, "Bot '{}' maps no symbols", self.bot_id);
+        }
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+
+        table.add_row(Row::new(vec![
+            Cell::new("Symbol"),
+            Cell::new("Exchange Symbol"),
+        ]));
+        for (symbol, exchange_symbol) in self.symbols.iter() {
+            table.add_row(Row::new(vec![
+                Cell::new(symbol),
+                Cell::new(exchange_symbol),
+            ]));
+        }
+        write!(f, "{}", table)
+    }
+}
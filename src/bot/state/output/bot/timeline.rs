@@ -0,0 +1,47 @@
+use crate::bot::state::events::Event;
+use prettytable::{format, Cell, Row, Table};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A page of a bot's event timeline, newest first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineView {
+    pub events: Vec<Event>,
+    /// Pass as `before` to fetch the next (older) page
+    pub next_cursor: Option<u64>,
+}
+
+impl fmt::Display for TimelineView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+
+        table.add_row(Row::new(vec![
+            Cell::new("Seq"),
+            Cell::new("Time"),
+            Cell::new("Kind"),
+            Cell::new("Summary"),
+        ]));
+        table.add_row(Row::new(vec![
+            Cell::new("---"),
+            Cell::new("----"),
+            Cell::new("----"),
+            Cell::new("-------"),
+        ]));
+
+        for event in &self.events {
+            table.add_row(Row::new(vec![
+                Cell::new(&event.seq.to_string()),
+                Cell::new(&event.timestamp.to_rfc3339()),
+                Cell::new(&event.kind.to_string()),
+                Cell::new(&event.summary),
+            ]));
+        }
+
+        write!(f, "{}", table)?;
+        if let Some(cursor) = self.next_cursor {
+            write!(f, "\nMore events: --before {}", cursor)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,77 @@
+use crate::bot::state::history::TradeRecord;
+use prettytable::{format, Cell, Row, Table};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A page of a bot's trade history, served by `GET /bots/{bot_id}/trades`, newest first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TradeListView(pub Vec<TradeRecord>);
+
+impl fmt::Display for TradeListView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "No trades");
+        }
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+
+        table.add_row(Row::new(vec![
+            Cell::new("Finished"),
+            Cell::new("Order ID"),
+            Cell::new("Listener"),
+            Cell::new("Action"),
+            Cell::new("Symbol"),
+            Cell::new("Size"),
+            Cell::new("Status"),
+            Cell::new("Filled"),
+            Cell::new("Price"),
+        ]));
+        let or_dash = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+        for trade in &self.0 {
+            let result = &trade.result;
+            let report = result.report.as_ref();
+            table.add_row(Row::new(vec![
+                Cell::new(&result.finished_at.format("%Y-%m-%d %H:%M:%S").to_string()),
+                Cell::new(&or_dash(result.order_id.clone())),
+                Cell::new(&result.listener_id),
+                Cell::new(&result.action),
+                Cell::new(&result.symbol),
+                Cell::new(&result.order_size),
+                Cell::new(&result.status.to_string()),
+                Cell::new(&or_dash(
+                    report
+                        .and_then(|r| r.filled_quantity)
+                        .map(|q| q.to_string()),
+                )),
+                Cell::new(&or_dash(
+                    report.and_then(|r| r.average_price).map(|p| p.to_string()),
+                )),
+            ]));
+        }
+        write!(f, "{}", table)
+    }
+}
+
+/// Outcome of `POST /bots/{bot_id}/fills`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FillImportView {
+    pub bot_id: String,
+    /// Fills in the file
+    pub fills: usize,
+    /// Fills added to the history; the rest were recorded already
+    pub imported: usize,
+}
+
+impl fmt::Display for FillImportView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Imported {} of {} fill(s) into the trade history of bot '{}'",
+            self.imported, self.fills, self.bot_id
+        )?;
+        if self.imported < self.fills {
+            write!(f, " ({} already recorded)", self.fills - self.imported)?;
+        }
+        Ok(())
+    }
+}
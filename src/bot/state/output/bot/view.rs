@@ -1,4 +1,10 @@
-pub use crate::bot::model::{Bot, Listener};
+use crate::app_config::NotificationPrefs;
+use crate::bot::allocation::Budget;
+pub use crate::bot::model::{AccountRoute, Bot, Listener};
+use crate::bot::state::events::{self, FieldChange};
+use crate::exchange::paper::PaperFills;
+use crate::exchange::{KeyPermissions, SymbolMap};
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt;
@@ -20,19 +26,52 @@ pub struct BotView {
     #[allow(dead_code)]
     #[serde(skip_serializing)]
     pub webhook_secret: Option<String>,
-    pub trading_fee: Option<f64>,
+    pub trading_fee: Option<Decimal>,
     #[allow(dead_code)]
     #[serde(skip_serializing)]
     pub private_key: Option<String>,
     pub contract_address: Option<String>,
+    #[serde(default)]
+    pub dry_run: bool,
+    #[serde(default)]
+    pub observer: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paper_slippage_bps: Option<Decimal>,
+    #[serde(default, skip_serializing_if = "PaperFills::is_empty")]
+    pub paper_fills: PaperFills,
+    #[serde(default, skip_serializing_if = "SymbolMap::is_empty")]
+    pub symbol_map: SymbolMap,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exclusion_group: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget: Option<Budget>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub accounts: Vec<AccountView>,
+    #[serde(default, skip_serializing_if = "NotificationPrefs::is_empty")]
+    pub notifications: NotificationPrefs,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_permissions: Option<KeyPermissions>,
     pub listeners: HashMap<String, Listener>,
+    /// What the update answered with this view changed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changes: Option<Vec<FieldChange>>,
+}
+
+/// A sub-account of a bot, without its credentials.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AccountView {
+    pub name: String,
+    pub route: AccountRoute,
 }
 
 impl fmt::Display for BotView {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(changes) = &self.changes {
+            return events::write_changes(f, &format!("Bot '{}'", self.bot_id), changes);
+        }
         write!(
             f,
-            "Bot ID: {}\nName: {}\nExchange: {}\nREST Endpoint: {:?}\nRPC Endpoint: {:?}\nTrading Fee: {:?}\nContract Address: {:?}\nListeners: {}",
+            "Bot ID: {}\nName: {}\nExchange: {}\nREST Endpoint: {:?}\nRPC Endpoint: {:?}\nTrading Fee: {:?}\nContract Address: {:?}\nDry Run: {}\nObserver: {}\nSymbol Mappings: {}\nSub-accounts: {}\nListeners: {}",
             self.bot_id,
             self.name,
             self.exchange,
@@ -40,8 +79,28 @@ impl fmt::Display for BotView {
             self.rpc_endpoint,
             self.trading_fee,
             self.contract_address,
+            self.dry_run,
+            self.observer,
+            self.symbol_map.len(),
+            self.accounts.len(),
             self.listeners.len() // Display the number of listeners instead of their details
-        )
+        )?;
+        if !self.paper_fills.is_empty() {
+            write!(f, "\nPaper Fills: {}", self.paper_fills)?;
+        }
+        if let Some(group) = &self.exclusion_group {
+            write!(f, "\nExclusion Group: {}", group)?;
+        }
+        if let Some(budget) = &self.budget {
+            write!(f, "\nBudget: {}", budget)?;
+        }
+        if !self.notifications.is_empty() {
+            write!(f, "\nNotifications: {}", self.notifications)?;
+        }
+        if let Some(permissions) = &self.key_permissions {
+            write!(f, "\nKey Permissions: {}", permissions)?;
+        }
+        Ok(())
     }
 }
 
@@ -59,7 +118,25 @@ impl From<Bot> for BotView {
             trading_fee: args.trading_fee,
             private_key: args.private_key,
             contract_address: args.contract_address,
+            dry_run: args.dry_run,
+            observer: args.observer,
+            paper_slippage_bps: args.paper_slippage_bps,
+            paper_fills: args.paper_fills,
+            symbol_map: args.symbol_map,
+            exclusion_group: args.exclusion_group,
+            budget: args.budget,
+            accounts: args
+                .accounts
+                .into_iter()
+                .map(|account| AccountView {
+                    name: account.name,
+                    route: account.route,
+                })
+                .collect(),
+            notifications: args.notifications,
+            key_permissions: args.key_permissions,
             listeners: args.listeners,
+            changes: None,
         }
     }
 }
@@ -48,6 +48,8 @@ impl fmt::Display for ListenerListView {
             Cell::new("Bot ID"),
             Cell::new("Listener ID"),
             Cell::new("Service"),
+            Cell::new("Enabled"),
+            Cell::new("Last Fired"),
             Cell::new("Message Preview"),
         ]));
 
@@ -56,6 +58,8 @@ impl fmt::Display for ListenerListView {
             Cell::new("------"),
             Cell::new("-----------"),
             Cell::new("-------"),
+            Cell::new("-------"),
+            Cell::new("----------"),
             Cell::new("---------------"),
         ]));
 
@@ -72,8 +76,23 @@ impl fmt::Display for ListenerListView {
                 ),
                 Cell::new(
                     &listener
-                        .msg
-                        .clone()
+                        .enabled
+                        .map_or_else(|| "N/A".to_string(), |enabled| enabled.to_string()),
+                ),
+                Cell::new(
+                    &listener
+                        .last_fired
+                        .map_or_else(|| "Never".to_string(), |at| at.to_rfc3339()),
+                ),
+                // Lists only carry messages if asked to; their hash tells templates apart
+                Cell::new(
+                    &(listener.msg.clone())
+                        .or_else(|| {
+                            listener
+                                .msg_sha256
+                                .as_ref()
+                                .map(|hash| format!("#{}", hash))
+                        })
                         .unwrap_or_else(|| "N/A".to_string())
                         .chars()
                         .take(10)
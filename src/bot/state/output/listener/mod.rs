@@ -1,5 +1,7 @@
 pub mod list;
+pub mod rotate;
 pub mod view;
 
 pub use list::ListenerListView;
+pub use rotate::SecretRotationView;
 pub use view::ListenerView;
@@ -0,0 +1,51 @@
+use crate::bot::api::API_PREFIX;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A listener's new secret, shown once when it is rotated.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecretRotationView {
+    pub bot_id: String,
+    pub listener_id: String,
+    pub secret: String,
+    /// Webhook URL alerts should now use, secret included; only its path in offline mode
+    pub webhook_url: String,
+    /// When the old secret stops working; unset if it stopped at once
+    #[serde(default)]
+    pub previous_valid_until: Option<DateTime<Utc>>,
+}
+
+impl SecretRotationView {
+    /// Path of the webhook of `listener_id` on `bot_id`, with the credentials an alert needs.
+    pub fn webhook_path(
+        bot_id: &str,
+        listener_id: &str,
+        secret: &str,
+        tenant: Option<&str>,
+    ) -> String {
+        let mut path = format!(
+            "{}/webhook/{}/{}?secret={}",
+            API_PREFIX, bot_id, listener_id, secret
+        );
+        if let Some(tenant) = tenant {
+            path.push_str(&format!("&tenant={}", tenant));
+        }
+        path
+    }
+}
+
+impl fmt::Display for SecretRotationView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Listener ID: {}\nBot ID: {}\nSecret: {}\nWebhook URL: {}\nOld Secret Valid Until: {}",
+            self.listener_id,
+            self.bot_id,
+            self.secret,
+            self.webhook_url,
+            self.previous_valid_until
+                .map_or_else(|| "revoked".to_string(), |at| at.to_rfc3339()),
+        )
+    }
+}
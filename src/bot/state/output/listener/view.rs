@@ -1,8 +1,12 @@
 //use crate::models::Listener;
+use crate::app_config::NotificationPrefs;
+use crate::bot::filter::AlertFilter;
 pub use crate::bot::model::Listener;
+use crate::bot::state::events::{self, FieldChange};
+use crate::bot::state::templates::{self, Templates};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::fmt;
-use validator::Validate;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ListenerView {
@@ -11,19 +15,72 @@ pub struct ListenerView {
     pub service: Option<String>,
     #[serde(skip_serializing)]
     pub secret: Option<String>,
+    /// Message template; lists leave it out unless asked for it with `include=msg`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub msg: Option<String>,
+    /// Hex SHA-256 of the message template
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub msg_sha256: Option<String>,
+    #[serde(default)]
+    pub enabled: Option<bool>,
+    #[serde(default)]
+    pub verify_secret: Option<bool>,
+    #[serde(default)]
+    pub last_fired: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_alerts_per_minute: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cooldown_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "AlertFilter::is_empty")]
+    pub filter: AlertFilter,
+    #[serde(default, skip_serializing_if = "NotificationPrefs::is_empty")]
+    pub notifications: NotificationPrefs,
+    /// What the update answered with this view changed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changes: Option<Vec<FieldChange>>,
 }
 
 impl fmt::Display for ListenerView {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(changes) = &self.changes {
+            let what = format!("Listener '{}' of bot '{}'", self.listener_id, self.bot_id);
+            return events::write_changes(f, &what, changes);
+        }
         write!(
             f,
-            "Listener ID: {}\nService: {}\nBot ID: {}\nMessage: {}",
+            "Listener ID: {}\nService: {}\nBot ID: {}\nMessage: {}\nEnabled: {}\nVerify Secret: {}\nLast Fired: {}",
             self.listener_id,
             self.service.clone().unwrap_or_else(|| "N/A".to_string()),
             self.bot_id,
             self.msg.clone().unwrap_or_else(|| "N/A".to_string()),
-        )
+            self.enabled
+                .map_or_else(|| "N/A".to_string(), |enabled| enabled.to_string()),
+            self.verify_secret
+                .map_or_else(|| "N/A".to_string(), |verify| verify.to_string()),
+            self.last_fired
+                .map_or_else(|| "Never".to_string(), |at| at.to_rfc3339()),
+        )?;
+        if let Some(max) = self.max_alerts_per_minute {
+            write!(f, "\nMax Alerts per Minute: {}", max)?;
+        }
+        if let Some(cooldown) = self.cooldown_secs {
+            write!(f, "\nCooldown: {}s", cooldown)?;
+        }
+        if !self.filter.is_empty() {
+            write!(f, "\nFilter: {}", self.filter)?;
+        }
+        if !self.notifications.is_empty() {
+            write!(f, "\nNotifications: {}", self.notifications)?;
+        }
+        Ok(())
+    }
+}
+
+impl ListenerView {
+    /// The view with the message template of `listener`, as kept in `templates`.
+    pub fn with_msg(mut self, templates: &Templates, listener: &Listener) -> Self {
+        self.msg = templates.body(listener).map(str::to_string);
+        self
     }
 }
 
@@ -38,19 +95,16 @@ where
             listener_id: listener_id.as_ref().to_string(),
             service: Some(listener.service.clone()),
             secret: Some(listener.secret.clone()),
-            msg: Some(listener.msg.clone()),
+            msg: None,
+            msg_sha256: (listener.msg_sha256.clone()).or_else(|| templates::address(&listener.msg)),
+            enabled: Some(listener.enabled),
+            verify_secret: Some(listener.verify_secret),
+            last_fired: listener.last_fired,
+            max_alerts_per_minute: listener.max_alerts_per_minute,
+            cooldown_secs: listener.cooldown_secs,
+            filter: listener.filter.clone(),
+            notifications: listener.notifications.clone(),
+            changes: None,
         }
     }
 }
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ListenerListView(pub Vec<ListenerView>);
-
-#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
-pub struct ListenerInsertArgs {
-    pub listener_id: Option<String>,
-    #[validate(length(min = 1, message = "Service cannot be empty"))]
-    pub service: String,
-    pub secret: Option<String>,
-    pub msg: Option<String>,
-}
@@ -1,5 +1,24 @@
+pub mod admin;
 pub mod bot;
 pub mod listener;
+pub mod report;
+pub mod state;
+pub mod user;
 
-pub use bot::{BotListView, BotView};
-pub use listener::{ListenerListView, ListenerView};
+pub use admin::{
+    AdminInfoView, ApiVersionsView, EntityCounts, MemoryStatsView, PriceListView, PriceView,
+    ReadinessView, StateSizes, VersionView,
+};
+pub use bot::{
+    AlertListView, AlertSimulationView, BalanceListView, BotListView, BotStatsView, BotView,
+    CancelledOrderView, ExchangeRequestStats, FillImportView, FillLatencyStats, OpenOrderListView,
+    PnlView, PositionListView, SymbolMapView, SymbolPnl, TimelineView, TradeListView,
+};
+pub use listener::{ListenerListView, ListenerView, SecretRotationView};
+pub use report::{
+    AllocationView, BotAllocation, EquityBand, ExecutionQuality, ExecutionQualityView,
+    MonteCarloPercentile, MonteCarloView, ReplayFill, ReplayLimits, ReplayOutcome, ReplayView,
+    ReplayedAlert, StrategyPerformance, StrategyReportView,
+};
+pub use state::{BulkOutcome, BulkView, StateCompactView, StateImportView};
+pub use user::{LoginView, UserListView, UserView};
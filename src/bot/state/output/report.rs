@@ -0,0 +1,518 @@
+use crate::bot::allocation::Budget;
+use chrono::{DateTime, NaiveDate, Utc};
+use prettytable::{format, Cell, Row, Table};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// How far the fills of a bot's alerts through one listener were from the alert prices.
+///
+/// Slippage is positive when a fill was worse than the alert price: higher for a buy, lower
+/// for a sell.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ExecutionQuality {
+    pub bot_id: String,
+    pub listener_id: String,
+    /// Executed trades
+    pub trades: u64,
+    /// Trades with both an alert price and a fill price
+    pub measured: u64,
+    /// Slippage of the measured trades, weighted by their notional, in basis points
+    pub slippage_bps: Option<Decimal>,
+    /// Slippage of the worst measured trade, in basis points
+    pub worst_slippage_bps: Option<Decimal>,
+    /// What slippage cost, in the quote asset; negative if fills were better overall
+    pub slippage_cost: Decimal,
+}
+
+/// Served by `GET /reports/execution-quality`, per bot and listener.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionQualityView {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<DateTime<Utc>>,
+    pub listeners: Vec<ExecutionQuality>,
+}
+
+impl fmt::Display for ExecutionQualityView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.listeners.is_empty() {
+            return write!(f, "No executed trades");
+        }
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+
+        table.add_row(Row::new(vec![
+            Cell::new("Bot"),
+            Cell::new("Listener"),
+            Cell::new("Trades"),
+            Cell::new("Measured"),
+            Cell::new("Slippage (bps)"),
+            Cell::new("Worst (bps)"),
+            Cell::new("Cost"),
+        ]));
+        let or_dash =
+            |value: Option<Decimal>| value.map_or_else(|| "-".to_string(), |v| v.to_string());
+        for quality in &self.listeners {
+            table.add_row(Row::new(vec![
+                Cell::new(&quality.bot_id),
+                Cell::new(&quality.listener_id),
+                Cell::new(&quality.trades.to_string()),
+                Cell::new(&quality.measured.to_string()),
+                Cell::new(&or_dash(quality.slippage_bps)),
+                Cell::new(&or_dash(quality.worst_slippage_bps)),
+                Cell::new(&quality.slippage_cost.to_string()),
+            ]));
+        }
+        write!(f, "{}", table)?;
+        if let Some(since) = self.since {
+            write!(f, "Since {}", since.to_rfc3339())?;
+        }
+        Ok(())
+    }
+}
+
+/// How the trades of a bot through one listener, i.e. one strategy, performed.
+///
+/// A trade that reduces or closes a position is a round trip; its PnL is what it realized
+/// less its fee. Fees of opening trades count against net PnL and drawdown only.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct StrategyPerformance {
+    pub bot_id: String,
+    pub listener_id: String,
+    /// Executed trades with a reported fill
+    pub trades: u64,
+    pub round_trips: u64,
+    /// Share of round trips with a positive PnL, from 0 to 1
+    pub win_rate: Option<Decimal>,
+    /// Profit of the winning round trips over the loss of the losing ones
+    pub profit_factor: Option<Decimal>,
+    /// Realized PnL after every fee
+    pub net_pnl: Decimal,
+    /// Largest fall of the cumulative net PnL from a previous high
+    pub max_drawdown: Decimal,
+    /// Mean over standard deviation of the round trip PnLs; not annualized
+    pub sharpe: Option<Decimal>,
+}
+
+/// Served by `GET /reports/strategies`, per bot and listener.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrategyReportView {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<DateTime<Utc>>,
+    pub strategies: Vec<StrategyPerformance>,
+}
+
+impl fmt::Display for StrategyReportView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.strategies.is_empty() {
+            return write!(f, "No executed trades");
+        }
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+
+        table.add_row(Row::new(vec![
+            Cell::new("Bot"),
+            Cell::new("Listener"),
+            Cell::new("Trades"),
+            Cell::new("Round Trips"),
+            Cell::new("Win Rate"),
+            Cell::new("Profit Factor"),
+            Cell::new("Net PnL"),
+            Cell::new("Max Drawdown"),
+            Cell::new("Sharpe"),
+        ]));
+        let or_dash =
+            |value: Option<Decimal>| value.map_or_else(|| "-".to_string(), |v| v.to_string());
+        for strategy in &self.strategies {
+            let win_rate = strategy
+                .win_rate
+                .map(|rate| format!("{}%", (rate * Decimal::ONE_HUNDRED).round_dp(1)));
+            table.add_row(Row::new(vec![
+                Cell::new(&strategy.bot_id),
+                Cell::new(&strategy.listener_id),
+                Cell::new(&strategy.trades.to_string()),
+                Cell::new(&strategy.round_trips.to_string()),
+                Cell::new(win_rate.as_deref().unwrap_or("-")),
+                Cell::new(&or_dash(strategy.profit_factor)),
+                Cell::new(&strategy.net_pnl.to_string()),
+                Cell::new(&strategy.max_drawdown.to_string()),
+                Cell::new(&or_dash(strategy.sharpe)),
+            ]));
+        }
+        write!(f, "{}", table)?;
+        if let Some(since) = self.since {
+            write!(f, "Since {}", since.to_rfc3339())?;
+        }
+        Ok(())
+    }
+}
+
+/// Outcomes of the simulated trade sequences at one percentile of each outcome; at the 95th,
+/// 95% of the sequences had a smaller drawdown, and 95% a smaller final PnL.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct MonteCarloPercentile {
+    pub percentile: u8,
+    /// Largest fall of the cumulative PnL from a previous high
+    pub max_drawdown: Decimal,
+    /// Largest fall of the equity as a share of its previous high, from 0 to 1; needs a capital
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relative_drawdown: Option<Decimal>,
+    /// Cumulative PnL after the last trade
+    pub final_pnl: Decimal,
+}
+
+/// Cumulative PnL of the simulated sequences after `trade` trades.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct EquityBand {
+    pub trade: usize,
+    pub p5: Decimal,
+    pub p50: Decimal,
+    pub p95: Decimal,
+}
+
+/// Served by `GET /reports/montecarlo`: how a bot might fare if its round trips came in
+/// another order, or other ones like them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MonteCarloView {
+    pub bot_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<DateTime<Utc>>,
+    /// Historical round trips resampled
+    pub round_trips: usize,
+    pub simulations: usize,
+    /// Trades per simulated sequence
+    pub trades: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capital: Option<Decimal>,
+    /// Share of the capital whose loss counts as ruin
+    pub ruin: Decimal,
+    /// Share of the sequences that lost `ruin` of the capital at some point
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub risk_of_ruin: Option<Decimal>,
+    pub percentiles: Vec<MonteCarloPercentile>,
+    /// Spread of the cumulative PnL as the sequences go, for charts
+    #[serde(default)]
+    pub equity_bands: Vec<EquityBand>,
+}
+
+impl fmt::Display for MonteCarloView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.percentiles.is_empty() {
+            return write!(f, "No round trips to resample for bot '{}'", self.bot_id);
+        }
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+
+        table.add_row(Row::new(vec![
+            Cell::new("Percentile"),
+            Cell::new("Max Drawdown"),
+            Cell::new("Relative Drawdown"),
+            Cell::new("Final PnL"),
+        ]));
+        let percent = |share: Option<Decimal>| {
+            share.map_or_else(
+                || "-".to_string(),
+                |share| format!("{}%", (share * Decimal::ONE_HUNDRED).round_dp(1)),
+            )
+        };
+        for row in &self.percentiles {
+            table.add_row(Row::new(vec![
+                Cell::new(&format!("p{}", row.percentile)),
+                Cell::new(&row.max_drawdown.to_string()),
+                Cell::new(&percent(row.relative_drawdown)),
+                Cell::new(&row.final_pnl.to_string()),
+            ]));
+        }
+        write!(f, "{}", table)?;
+        writeln!(
+            f,
+            "{} sequences of {} trades resampled from {} round trips of bot '{}'",
+            self.simulations, self.trades, self.round_trips, self.bot_id
+        )?;
+        if let (Some(capital), Some(risk)) = (self.capital, self.risk_of_ruin) {
+            writeln!(
+                f,
+                "Risk of losing {} of {}: {}",
+                percent(Some(self.ruin)),
+                capital,
+                percent(Some(risk))
+            )?;
+        }
+        if let Some(since) = self.since {
+            write!(f, "Since {}", since.to_rfc3339())?;
+        }
+        Ok(())
+    }
+}
+
+/// How the open positions of a bot compare with its budget.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BotAllocation {
+    pub bot_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget: Option<Budget>,
+    /// The budget in the quote asset, if it can be worked out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget_amount: Option<Decimal>,
+    /// Value of the open positions, in the quote asset
+    pub exposure: Decimal,
+    /// How far the exposure is above (positive) or below the budget, in percent of it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drift_pct: Option<Decimal>,
+    /// How to rebalance the bot, once its drift passes the configured threshold
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+}
+
+/// Served by `GET /reports/allocation`, per bot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AllocationView {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capital: Option<Decimal>,
+    /// Exposure of every bot reported on
+    pub exposure: Decimal,
+    pub bots: Vec<BotAllocation>,
+}
+
+impl fmt::Display for AllocationView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.bots.is_empty() {
+            return write!(f, "No bots");
+        }
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+
+        table.add_row(Row::new(vec![
+            Cell::new("Bot"),
+            Cell::new("Budget"),
+            Cell::new("Amount"),
+            Cell::new("Exposure"),
+            Cell::new("Drift"),
+            Cell::new("Suggestion"),
+        ]));
+        let or_dash = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+        for bot in &self.bots {
+            table.add_row(Row::new(vec![
+                Cell::new(&bot.bot_id),
+                Cell::new(&or_dash(bot.budget.map(|budget| budget.to_string()))),
+                Cell::new(&or_dash(bot.budget_amount.map(|amount| amount.to_string()))),
+                Cell::new(&bot.exposure.to_string()),
+                Cell::new(&or_dash(bot.drift_pct.map(|pct| format!("{}%", pct)))),
+                Cell::new(bot.suggestion.as_deref().unwrap_or("")),
+            ]));
+        }
+        write!(f, "{}", table)?;
+        match self.capital {
+            Some(capital) => write!(f, "Exposure {} of capital {}", self.exposure, capital),
+            None => write!(f, "Exposure {}", self.exposure),
+        }
+    }
+}
+
+/// Risk limits and fill rules a replay ran with.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ReplayLimits {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget: Option<Budget>,
+    /// The budget in the quote asset; unset if there is none or it cannot be worked out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget_amount: Option<Decimal>,
+    /// Most trades in the day
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_trades: Option<u32>,
+    /// Slippage of market orders, in basis points
+    pub slippage_bps: Decimal,
+    /// Share of each order that fills, in percent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fill_pct: Option<Decimal>,
+    pub trading_fee: Decimal,
+}
+
+/// What a replayed alert's order did under some limits.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ReplayFill {
+    /// Quantity that filled; zero if the limits refused the order
+    pub filled: Decimal,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price: Option<Decimal>,
+    /// Why the limits cut the order down or refused it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl fmt::Display for ReplayFill {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.price, &self.note) {
+            (Some(price), _) if !self.filled.is_zero() => {
+                write!(f, "{} at {}", self.filled, price)?;
+                match &self.note {
+                    Some(note) => write!(f, " ({})", note),
+                    None => Ok(()),
+                }
+            }
+            (_, Some(note)) => write!(f, "{}", note),
+            _ => write!(f, "-"),
+        }
+    }
+}
+
+/// An alert of the replayed day.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ReplayedAlert {
+    pub alert_id: String,
+    pub received_at: DateTime<Utc>,
+    /// The order the alert asked for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<String>,
+    /// Price of the symbol when the alert fired, or that its order filled at
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price: Option<Decimal>,
+    /// Status of the alert as logged when it was received
+    pub logged: String,
+    /// Why the alert could not be replayed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// With the bot's current limits
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current: Option<ReplayFill>,
+    /// With the limits asked for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub counterfactual: Option<ReplayFill>,
+}
+
+/// What the replayed orders made under some limits, from flat positions.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ReplayOutcome {
+    pub limits: ReplayLimits,
+    pub trades: u64,
+    /// Orders the limits refused
+    pub refused: u64,
+    /// Orders the limits cut down
+    pub cut: u64,
+    /// Profit of the closed quantity, before fees
+    pub realized_pnl: Decimal,
+    pub fees: Decimal,
+    /// Realized profit after fees
+    pub net_pnl: Decimal,
+    /// Profit of the positions open at the end of the day, at the last price replayed
+    pub unrealized_pnl: Decimal,
+    /// Value of those positions
+    pub exposure: Decimal,
+}
+
+impl ReplayOutcome {
+    /// Labels and values of the outcome, as reported.
+    fn rows(&self) -> Vec<(&'static str, String)> {
+        let limits = &self.limits;
+        vec![
+            ("Budget", or_dash(limits.budget.map(|b| b.to_string()))),
+            (
+                "Max Trades",
+                or_dash(limits.max_trades.map(|n| n.to_string())),
+            ),
+            ("Slippage", format!("{} bps", limits.slippage_bps)),
+            (
+                "Fill",
+                format!("{}%", limits.fill_pct.unwrap_or(Decimal::ONE_HUNDRED)),
+            ),
+            ("Fee", limits.trading_fee.to_string()),
+            ("Trades", self.trades.to_string()),
+            ("Refused", self.refused.to_string()),
+            ("Cut Down", self.cut.to_string()),
+            ("Realized PnL", self.realized_pnl.round_dp(4).to_string()),
+            ("Fees", self.fees.round_dp(4).to_string()),
+            ("Net PnL", self.net_pnl.round_dp(4).to_string()),
+            (
+                "Unrealized PnL",
+                self.unrealized_pnl.round_dp(4).to_string(),
+            ),
+            ("Exposure", self.exposure.round_dp(4).to_string()),
+        ]
+    }
+}
+
+/// Served by `GET /reports/replay`: a bot's alerts of a day replayed by the paper engine with
+/// its current risk limits, and with the ones asked for.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayView {
+    pub bot_id: String,
+    pub date: NaiveDate,
+    /// Alerts of the day only the archive still kept
+    pub archived: usize,
+    pub alerts: Vec<ReplayedAlert>,
+    pub current: ReplayOutcome,
+    pub counterfactual: ReplayOutcome,
+}
+
+impl fmt::Display for ReplayView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.alerts.is_empty() {
+            return write!(
+                f,
+                "No alerts of bot '{}' to replay on {}",
+                self.bot_id, self.date
+            );
+        }
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+
+        table.add_row(Row::new(vec![
+            Cell::new("Received"),
+            Cell::new("Order"),
+            Cell::new("Price"),
+            Cell::new("Logged"),
+            Cell::new("Current Limits"),
+            Cell::new("What If"),
+        ]));
+        for alert in &self.alerts {
+            let fill = |fill: &Option<ReplayFill>| match (fill, &alert.error) {
+                (Some(fill), _) => fill.to_string(),
+                (None, Some(error)) => error.clone(),
+                (None, None) => "-".to_string(),
+            };
+            table.add_row(Row::new(vec![
+                Cell::new(&alert.received_at.format("%H:%M:%S").to_string()),
+                Cell::new(&or_dash(alert.order.clone())),
+                Cell::new(&or_dash(alert.price.map(|price| price.to_string()))),
+                Cell::new(&alert.logged),
+                Cell::new(&fill(&alert.current)),
+                Cell::new(&fill(&alert.counterfactual)),
+            ]));
+        }
+        writeln!(f, "{}", table)?;
+
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+        table.add_row(Row::new(vec![
+            Cell::new(""),
+            Cell::new("Current Limits"),
+            Cell::new("What If"),
+        ]));
+        let current = self.current.rows();
+        let counterfactual = self.counterfactual.rows();
+        for ((label, current), (_, counterfactual)) in current.iter().zip(&counterfactual) {
+            table.add_row(Row::new(vec![
+                Cell::new(label),
+                Cell::new(current),
+                Cell::new(counterfactual),
+            ]));
+        }
+        write!(f, "{}", table)?;
+        write!(
+            f,
+            "{} alerts of bot '{}' replayed on {}",
+            self.alerts.len(),
+            self.bot_id,
+            self.date
+        )?;
+        if self.archived > 0 {
+            write!(f, ", {} from the archive", self.archived)?;
+        }
+        Ok(())
+    }
+}
+
+/// `value`, or a dash if unset.
+fn or_dash(value: Option<String>) -> String {
+    value.unwrap_or_else(|| "-".to_string())
+}
@@ -0,0 +1,119 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::PathBuf;
+
+/// Bots an import changed, by ID.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct StateImportView {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    /// Bots the export did not hold, deleted by `replace`
+    pub removed: Vec<String>,
+}
+
+impl fmt::Display for StateImportView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Imported {} bot(s): {} added, {} updated, {} removed",
+            self.added.len() + self.updated.len(),
+            self.added.len(),
+            self.updated.len(),
+            self.removed.len()
+        )?;
+        for (label, ids) in [
+            ("Added", &self.added),
+            ("Updated", &self.updated),
+            ("Removed", &self.removed),
+        ] {
+            if !ids.is_empty() {
+                write!(f, "\n{}: {}", label, ids.join(", "))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An operation of a batch, once applied.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct BulkOutcome {
+    pub op: String,
+    pub bot_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub listener_id: Option<String>,
+}
+
+/// The operations of a batch, all applied, in order.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct BulkView {
+    pub applied: Vec<BulkOutcome>,
+}
+
+impl fmt::Display for BulkView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Applied {} operation(s)", self.applied.len())?;
+        for outcome in &self.applied {
+            match &outcome.listener_id {
+                Some(listener_id) => write!(
+                    f,
+                    "\n  {} '{}' of bot '{}'",
+                    outcome.op, listener_id, outcome.bot_id
+                )?,
+                None => write!(f, "\n  {} bot '{}'", outcome.op, outcome.bot_id)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What a compaction pruned.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct StateCompactView {
+    pub alerts: usize,
+    pub events: usize,
+    pub trades: usize,
+    /// Monthly trade shards deleted
+    pub shards: usize,
+    /// Lines of the audit log
+    #[serde(default)]
+    pub audit: usize,
+    /// Lines of the access log
+    #[serde(default)]
+    pub access_log: usize,
+    /// File the pruned records were appended to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive: Option<PathBuf>,
+}
+
+impl StateCompactView {
+    /// Records pruned, by kind of data.
+    pub fn counts(&self) -> [(&'static str, usize); 5] {
+        [
+            ("alerts", self.alerts),
+            ("events", self.events),
+            ("trades", self.trades),
+            ("audit", self.audit),
+            ("access_log", self.access_log),
+        ]
+    }
+
+    /// Records pruned in all.
+    pub fn pruned(&self) -> usize {
+        self.counts().iter().map(|(_, count)| count).sum()
+    }
+}
+
+impl fmt::Display for StateCompactView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Pruned {} alert(s), {} event(s), {} trade(s) in {} shard(s), {} audit log line(s) \
+             and {} access log line(s)",
+            self.alerts, self.events, self.trades, self.shards, self.audit, self.access_log
+        )?;
+        if let Some(archive) = &self.archive {
+            write!(f, "\nArchived to {}", archive.display())?;
+        }
+        Ok(())
+    }
+}
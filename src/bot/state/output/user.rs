@@ -0,0 +1,71 @@
+use crate::bot::auth::{Role, User};
+use chrono::{DateTime, Utc};
+use prettytable::{format, Cell, Row, Table};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A user, without their password hash.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserView {
+    pub username: String,
+    pub role: Role,
+    pub created_at: DateTime<Utc>,
+}
+
+impl UserView {
+    pub fn new(username: &str, user: &User) -> Self {
+        Self {
+            username: username.to_string(),
+            role: user.role,
+            created_at: user.created_at,
+        }
+    }
+}
+
+impl fmt::Display for UserView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "User '{}' ({})", self.username, self.role)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserListView(pub Vec<UserView>);
+
+impl fmt::Display for UserListView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "No users; the API takes tenancy tokens only");
+        }
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+        table.add_row(Row::new(vec![
+            Cell::new("Username"),
+            Cell::new("Role"),
+            Cell::new("Created"),
+        ]));
+        for user in &self.0 {
+            table.add_row(Row::new(vec![
+                Cell::new(&user.username),
+                Cell::new(&user.role.to_string()),
+                Cell::new(&user.created_at.format("%Y-%m-%d %H:%M").to_string()),
+            ]));
+        }
+        write!(f, "{}", table)
+    }
+}
+
+/// A token issued by `POST /auth/login`, to send as `Authorization: Bearer <token>`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoginView {
+    pub token: String,
+    pub token_type: String,
+    pub expires_at: DateTime<Utc>,
+    pub role: Role,
+}
+
+impl fmt::Display for LoginView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Signed in as {} until {}", self.role, self.expires_at)?;
+        write!(f, "{}", self.token)
+    }
+}
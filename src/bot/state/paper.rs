@@ -0,0 +1,230 @@
+// src/bot/state/paper.rs
+//! # Paper Positions
+//!
+//! Positions and PnL of `dry_run` bots, built from the fills the
+//! [paper backend](crate::exchange::paper) simulates and persisted with the state.
+use crate::exchange::{ExecutionReport, Position, Side, Symbol};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A simulated trade.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct PaperFill {
+    pub symbol: String,
+    /// Positive for buys, negative for sells
+    pub quantity: Decimal,
+    pub price: Decimal,
+    pub fee: Decimal,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl PaperFill {
+    /// The fill a paper backend reported for a trade on `symbol`, if it filled.
+    pub fn from_report(symbol: &str, buy: bool, report: &ExecutionReport) -> Option<Self> {
+        let quantity = report.filled_quantity?;
+        Some(Self {
+            symbol: symbol.to_string(),
+            quantity: if buy { quantity } else { -quantity },
+            price: report.average_price?,
+            fee: report.fees.unwrap_or_default(),
+            timestamp: report.acknowledged_at,
+        })
+    }
+}
+
+/// Simulated position of a bot in one symbol.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct PaperPosition {
+    /// Positive when long, negative when short
+    pub quantity: Decimal,
+    /// Average entry price of the open quantity
+    pub entry_price: Decimal,
+    /// Profit of the closed quantity, before fees
+    pub realized_pnl: Decimal,
+    pub fees: Decimal,
+    pub trades: u64,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl PaperPosition {
+    /// Realized profit after fees.
+    pub fn net_pnl(&self) -> Decimal {
+        self.realized_pnl - self.fees
+    }
+
+    /// Profit of the open quantity at `price`.
+    pub fn unrealized_pnl(&self, price: Decimal) -> Decimal {
+        (price - self.entry_price) * self.quantity
+    }
+
+    /// Add `fill` to the position at average cost.
+    pub(crate) fn apply(&mut self, fill: &PaperFill) {
+        let same_side = self.quantity.is_zero()
+            || self.quantity.is_sign_negative() == fill.quantity.is_sign_negative();
+        if same_side {
+            let open = self.quantity.abs() + fill.quantity.abs();
+            self.entry_price =
+                (self.entry_price * self.quantity.abs() + fill.price * fill.quantity.abs()) / open;
+        } else {
+            let closed = self.quantity.abs().min(fill.quantity.abs());
+            let closed = if self.quantity.is_sign_negative() {
+                -closed
+            } else {
+                closed
+            };
+            self.realized_pnl += (fill.price - self.entry_price) * closed;
+            if fill.quantity.abs() > self.quantity.abs() {
+                // Flipped to the other side at the fill price
+                self.entry_price = fill.price;
+            }
+        }
+        self.quantity += fill.quantity;
+        if self.quantity.is_zero() {
+            self.entry_price = Decimal::ZERO;
+        }
+        self.fees += fill.fee;
+        self.trades += 1;
+        self.updated_at = Some(fill.timestamp);
+    }
+}
+
+/// Paper positions of every `dry_run` bot, by bot and symbol.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(transparent)]
+pub struct PaperBook {
+    positions: BTreeMap<String, BTreeMap<String, PaperPosition>>,
+}
+
+impl PaperBook {
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Add a simulated fill of `bot_id` to its position and return the position.
+    pub fn apply(&mut self, bot_id: &str, fill: &PaperFill) -> &PaperPosition {
+        let position = self
+            .positions
+            .entry(bot_id.to_string())
+            .or_default()
+            .entry(fill.symbol.clone())
+            .or_default();
+        position.apply(fill);
+        position
+    }
+
+    /// Positions of `bot_id`, by symbol.
+    pub fn positions(&self, bot_id: &str) -> Option<&BTreeMap<String, PaperPosition>> {
+        self.positions.get(bot_id)
+    }
+
+    /// The positions of `bot_id` still open, the way an exchange lists them.
+    pub fn open_positions(&self, bot_id: &str) -> Vec<Position> {
+        let positions = self.positions(bot_id).into_iter().flatten();
+        positions
+            .filter(|(_, position)| !position.quantity.is_zero())
+            .filter_map(|(symbol, position)| {
+                Some(Position {
+                    symbol: Symbol::parse(symbol).ok()?,
+                    side: if position.quantity.is_sign_negative() {
+                        Side::Sell
+                    } else {
+                        Side::Buy
+                    },
+                    quantity: position.quantity.abs(),
+                    entry_price: Some(position.entry_price),
+                    unrealized_pnl: None,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::dec;
+
+    fn fill(quantity: Decimal, price: Decimal) -> PaperFill {
+        PaperFill {
+            symbol: "BTCUSDT".to_string(),
+            quantity,
+            price,
+            fee: dec!(1),
+            timestamp: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn fills_build_positions_and_realize_pnl() {
+        let mut book = PaperBook::default();
+        book.apply("alpha", &fill(dec!(1), dec!(100)));
+        let position = book.apply("alpha", &fill(dec!(1), dec!(110)));
+        assert_eq!(
+            (position.quantity, position.entry_price),
+            (dec!(2), dec!(105))
+        );
+
+        let position = book.apply("alpha", &fill(dec!(-1), dec!(115)));
+        assert_eq!(
+            (position.quantity, position.entry_price),
+            (dec!(1), dec!(105))
+        );
+        assert_eq!(position.realized_pnl, dec!(10));
+
+        // Flip short: close the remaining unit, open one at the fill price
+        let position = book.apply("alpha", &fill(dec!(-2), dec!(95)));
+        assert_eq!(
+            (position.quantity, position.entry_price),
+            (dec!(-1), dec!(95))
+        );
+        assert_eq!(position.realized_pnl, dec!(0));
+        assert_eq!(position.unrealized_pnl(dec!(90)), dec!(5));
+
+        let position = book.apply("alpha", &fill(dec!(1), dec!(90)));
+        assert_eq!(
+            (position.quantity, position.entry_price),
+            (dec!(0), dec!(0))
+        );
+        assert_eq!(position.realized_pnl, dec!(5));
+        assert_eq!(position.net_pnl(), dec!(0));
+        assert_eq!(position.trades, 5);
+
+        assert_eq!(book.positions("alpha").unwrap().len(), 1);
+        assert!(book.positions("beta").is_none());
+    }
+
+    #[test]
+    fn open_positions_are_listed_like_an_exchange_does() {
+        let mut book = PaperBook::default();
+        book.apply("alpha", &fill(dec!(-2), dec!(100)));
+        book.apply(
+            "alpha",
+            &PaperFill {
+                symbol: "ETHUSDT".to_string(),
+                ..fill(dec!(1), dec!(10))
+            },
+        );
+        book.apply(
+            "alpha",
+            &PaperFill {
+                symbol: "ETHUSDT".to_string(),
+                ..fill(dec!(-1), dec!(12))
+            },
+        );
+
+        let positions = book.open_positions("alpha");
+        assert_eq!(positions.len(), 1);
+        assert_eq!(positions[0].symbol.to_string(), "BTCUSDT");
+        assert_eq!(
+            (
+                positions[0].side,
+                positions[0].quantity,
+                positions[0].entry_price
+            ),
+            (Side::Sell, dec!(2), Some(dec!(100)))
+        );
+        assert!(book.open_positions("beta").is_empty());
+    }
+}
@@ -0,0 +1,165 @@
+// src/bot/state/provider.rs
+//! # ID and Clock Providers
+//!
+//! The registry never calls `Uuid::new_v4()` or `Utc::now()` directly; it asks the
+//! [`Providers`] attached to [`AppState`](crate::app_state::AppState) instead. Production
+//! code uses [`UuidGenerator`] and [`SystemClock`], while tests and the backtester can
+//! swap in [`SequentialIdGenerator`] and [`ManualClock`] to get reproducible IDs and
+//! timestamps.
+use chrono::{DateTime, Duration, Utc};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Source of unique identifiers for bots, listeners and other records.
+pub trait IdGenerator: Send + Sync {
+    fn next_id(&self) -> String;
+}
+
+/// Source of the current time.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// Random v4 UUIDs (default).
+#[derive(Debug, Default)]
+pub struct UuidGenerator;
+
+impl IdGenerator for UuidGenerator {
+    fn next_id(&self) -> String {
+        uuid::Uuid::new_v4().to_string()
+    }
+}
+
+/// Deterministic IDs of the form `<prefix>-1`, `<prefix>-2`, ...
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct SequentialIdGenerator {
+    prefix: String,
+    next: AtomicU64,
+}
+
+#[allow(dead_code)]
+impl SequentialIdGenerator {
+    pub fn new(prefix: &str) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            next: AtomicU64::new(1),
+        }
+    }
+}
+
+impl IdGenerator for SequentialIdGenerator {
+    fn next_id(&self) -> String {
+        format!(
+            "{}-{}",
+            self.prefix,
+            self.next.fetch_add(1, Ordering::SeqCst)
+        )
+    }
+}
+
+/// Wall-clock time (default).
+#[derive(Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock that only moves when told to.
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct ManualClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+#[allow(dead_code)]
+impl ManualClock {
+    pub fn new(start: DateTime<Utc>) -> Self {
+        Self {
+            now: Mutex::new(start),
+        }
+    }
+
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner()) = now;
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner()) += by;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// The set of providers used by the registry.
+///
+/// Providers are runtime wiring, not state: they are skipped by serde and always
+/// compare equal so they never affect `AppState` equality or persistence.
+#[derive(Clone)]
+pub struct Providers {
+    pub ids: Arc<dyn IdGenerator>,
+    pub clock: Arc<dyn Clock>,
+}
+
+#[allow(dead_code)]
+impl Providers {
+    pub fn new(ids: Arc<dyn IdGenerator>, clock: Arc<dyn Clock>) -> Self {
+        Self { ids, clock }
+    }
+
+    /// Sequential IDs and a manual clock starting at `start`.
+    pub fn deterministic(prefix: &str, start: DateTime<Utc>) -> Self {
+        Self::new(
+            Arc::new(SequentialIdGenerator::new(prefix)),
+            Arc::new(ManualClock::new(start)),
+        )
+    }
+}
+
+impl Default for Providers {
+    fn default() -> Self {
+        Self::new(Arc::new(UuidGenerator), Arc::new(SystemClock))
+    }
+}
+
+impl fmt::Debug for Providers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Providers")
+    }
+}
+
+impl PartialEq for Providers {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_sequential_ids() {
+        let ids = SequentialIdGenerator::new("bot");
+        assert_eq!(ids.next_id(), "bot-1");
+        assert_eq!(ids.next_id(), "bot-2");
+    }
+
+    #[test]
+    fn test_manual_clock() {
+        let start = Utc.with_ymd_and_hms(2025, 1, 10, 0, 0, 0).unwrap();
+        let clock = ManualClock::new(start);
+        assert_eq!(clock.now(), start);
+        clock.advance(Duration::seconds(90));
+        assert_eq!(clock.now(), start + Duration::seconds(90));
+    }
+}
@@ -0,0 +1,80 @@
+// src/bot/state/quota.rs
+//! # Quotas
+//!
+//! Limits from [`QuotaConfig`](crate::app_config::QuotaConfig) are enforced by
+//! [`TenantScope`](super::tenant::TenantScope) before a bot or listener is created, and by
+//! [`TradeCounter`] before a trade is executed. Exceeding a resource limit yields
+//! `403 Forbidden`; exceeding the daily trade limit yields `429 Too Many Requests`.
+use crate::errors::AppError;
+use chrono::NaiveDate;
+use std::collections::HashMap;
+
+pub const MAX_BOTS: &str = "max_bots";
+pub const MAX_LISTENERS_PER_BOT: &str = "max_listeners_per_bot";
+pub const MAX_TRADES_PER_DAY: &str = "max_trades_per_day";
+
+/// Fail with [`AppError::QuotaExceeded`] if one more unit would exceed `limit`.
+pub fn check(quota: &'static str, limit: Option<u64>, used: u64) -> Result<(), AppError> {
+    match limit {
+        Some(limit) if used >= limit => Err(AppError::QuotaExceeded { quota, limit, used }),
+        _ => Ok(()),
+    }
+}
+
+/// Trades executed today, per tenant (in memory, reset at UTC midnight).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TradeCounter {
+    day: Option<NaiveDate>,
+    counts: HashMap<String, u64>,
+}
+
+impl TradeCounter {
+    /// Count a trade for `tenant` on `today`, unless it would exceed `limit`.
+    pub fn record(
+        &mut self,
+        tenant: Option<&str>,
+        today: NaiveDate,
+        limit: Option<u32>,
+    ) -> Result<(), AppError> {
+        if self.day != Some(today) {
+            self.day = Some(today);
+            self.counts.clear();
+        }
+        let used = self
+            .counts
+            .entry(tenant.unwrap_or_default().to_string())
+            .or_default();
+        check(MAX_TRADES_PER_DAY, limit.map(u64::from), *used)?;
+        *used += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trade_counter_resets_daily() {
+        let monday = NaiveDate::from_ymd_opt(2025, 1, 6).unwrap();
+        let tuesday = monday.succ_opt().unwrap();
+        let mut counter = TradeCounter::default();
+
+        counter.record(Some("acme"), monday, Some(2)).unwrap();
+        counter.record(Some("acme"), monday, Some(2)).unwrap();
+        // Other tenants have their own allowance
+        counter.record(Some("globex"), monday, Some(2)).unwrap();
+
+        let err = counter.record(Some("acme"), monday, Some(2)).unwrap_err();
+        assert!(matches!(
+            err,
+            AppError::QuotaExceeded {
+                quota: MAX_TRADES_PER_DAY,
+                limit: 2,
+                used: 2
+            }
+        ));
+
+        counter.record(Some("acme"), tuesday, Some(2)).unwrap();
+    }
+}
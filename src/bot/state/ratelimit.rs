@@ -0,0 +1,114 @@
+// src/bot/state/ratelimit.rs
+//! # Listener Rate Limits
+//!
+//! A misfiring strategy can send a burst of alerts; a listener's limits keep them from all
+//! becoming orders. `max_alerts_per_minute` bounds the alerts a listener passes on to the
+//! exchange in any sliding minute, and `cooldown_secs` is the time after each trade, since
+//! the listener's `last_fired`, during which it passes on none. An alert over either limit is
+//! refused with `429 Too Many Requests` and a `Retry-After` header.
+//!
+//! The alerts of the last minute are tracked in memory only: a restart forgets them, but
+//! not the cooldown, which `last_fired` carries in the state file.
+use crate::bot::model::Listener;
+use crate::errors::AppError;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, VecDeque};
+
+/// When each listener passed on its alerts of the last minute, per bot key and listener.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RateLimiter {
+    passed: HashMap<(String, String), VecDeque<DateTime<Utc>>>,
+}
+
+impl RateLimiter {
+    /// Count an alert of the listener `listener_id` of the bot stored as `key` at `now`,
+    /// unless it would exceed the listener's limits.
+    pub fn check(
+        &mut self,
+        key: &str,
+        listener_id: &str,
+        listener: &Listener,
+        now: DateTime<Utc>,
+    ) -> Result<(), AppError> {
+        let limited = |reason: String, until: DateTime<Utc>| AppError::RateLimited {
+            listener_id: listener_id.to_string(),
+            reason,
+            // Round up, so that a retry after that long gets through
+            retry_after_secs: ((until - now).num_milliseconds().max(0) as u64).div_ceil(1000),
+        };
+        if let (Some(cooldown), Some(last_fired)) = (listener.cooldown_secs, listener.last_fired) {
+            let until = last_fired + Duration::seconds(cooldown as i64);
+            if now < until {
+                return Err(limited(
+                    format!("cooldown of {}s after a trade", cooldown),
+                    until,
+                ));
+            }
+        }
+
+        let minute = Duration::minutes(1);
+        let entry = (key.to_string(), listener_id.to_string());
+        let Some(max) = listener.max_alerts_per_minute else {
+            self.passed.remove(&entry);
+            return Ok(());
+        };
+        let passed = self.passed.entry(entry).or_default();
+        while passed.front().is_some_and(|&at| at + minute <= now) {
+            passed.pop_front();
+        }
+        if passed.len() >= max as usize {
+            let until = passed[passed.len() - max as usize] + minute;
+            return Err(limited(format!("at most {} per minute", max), until));
+        }
+        passed.push_back(now);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listeners_are_held_to_their_limits() {
+        let start = Utc::now();
+        let mut listener = Listener {
+            max_alerts_per_minute: Some(2),
+            ..Default::default()
+        };
+        let mut limiter = RateLimiter::default();
+        limiter.check("alpha", "tv", &listener, start).unwrap();
+        limiter
+            .check("alpha", "tv", &listener, start + Duration::seconds(10))
+            .unwrap();
+        // Other listeners have their own allowance
+        limiter.check("alpha", "manual", &listener, start).unwrap();
+
+        let err = limiter
+            .check("alpha", "tv", &listener, start + Duration::seconds(20))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            AppError::RateLimited {
+                retry_after_secs: 40,
+                ..
+            }
+        ));
+        limiter
+            .check("alpha", "tv", &listener, start + Duration::seconds(60))
+            .unwrap();
+
+        listener.cooldown_secs = Some(30);
+        listener.last_fired = Some(start + Duration::seconds(60));
+        let err = limiter
+            .check("alpha", "tv", &listener, start + Duration::seconds(75))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            AppError::RateLimited {
+                retry_after_secs: 15,
+                ..
+            }
+        ));
+    }
+}
@@ -1,9 +1,18 @@
+use crate::app_config::NotificationPrefs;
+use crate::bot::allocation;
+use crate::bot::filter::AlertFilter;
+use crate::bot::model::RetiredSecret;
+use crate::bot::state::events::{EventKind, FieldChange};
 use crate::bot::state::{
     AppState, Bot, BotDeleteArgs, BotGetArgs, BotInsertArgs, BotListArgs, BotListView,
-    BotUpdateArgs, BotView, Listener, ListenerDeleteArgs, ListenerGetArgs, ListenerInsertArgs,
-    ListenerListArgs, ListenerListView, ListenerUpdateArgs, ListenerView, ListenersDeleteArgs,
+    BotTimelineArgs, BotUpdateArgs, BotView, Listener, ListenerDeleteArgs, ListenerGetArgs,
+    ListenerInsertArgs, ListenerListArgs, ListenerListView, ListenerRotateSecretArgs,
+    ListenerUpdateArgs, ListenerView, ListenersDeleteArgs, SecretRotationView, SymbolMapArgs,
+    SymbolMapView, SymbolUnmapArgs, TimelineView, MAX_SECRET_GRACE_SECS,
 };
 use crate::errors::AppError;
+use crate::exchange::paper;
+use crate::utils::crypto::random_secret;
 use log::info;
 use std::path::PathBuf;
 
@@ -16,7 +25,10 @@ pub trait BotRegistry {
     fn get_bot(&self, args: BotGetArgs) -> Result<BotView, AppError>;
     fn update_bot(&mut self, args: BotUpdateArgs) -> Result<BotView, AppError>;
     fn delete_bot(&mut self, args: BotDeleteArgs) -> Result<BotView, AppError>;
+    fn bot_timeline(&self, args: BotTimelineArgs) -> Result<TimelineView, AppError>;
     fn validate_bot_id(&self, bot_id: &str) -> Result<(), AppError>;
+    fn map_symbol(&mut self, args: SymbolMapArgs) -> Result<SymbolMapView, AppError>;
+    fn unmap_symbol(&mut self, args: SymbolUnmapArgs) -> Result<SymbolMapView, AppError>;
 
     // Listener-related methods
     fn get_listener_mut(
@@ -27,15 +39,78 @@ pub trait BotRegistry {
     fn get_listener_ref(&self, bot_id: &str, listener_id: &str) -> Result<&Listener, AppError>;
     fn add_listener(&mut self, args: ListenerInsertArgs) -> Result<ListenerView, AppError>;
     fn list_listeners(&self, args: ListenerListArgs) -> Result<ListenerListView, AppError>;
+    fn list_all_listeners(&self, args: ListenerListArgs) -> Result<ListenerListView, AppError>;
     fn get_listener(&self, args: ListenerGetArgs) -> Result<ListenerView, AppError>;
     fn update_listener(&mut self, args: ListenerUpdateArgs) -> Result<ListenerView, AppError>;
     fn delete_listener(&mut self, args: ListenerDeleteArgs) -> Result<ListenerView, AppError>;
     fn delete_listeners(&mut self, args: ListenersDeleteArgs)
         -> Result<ListenerListView, AppError>;
+    fn rotate_listener_secret(
+        &mut self,
+        args: ListenerRotateSecretArgs,
+    ) -> Result<SecretRotationView, AppError>;
 
     // Utility methods for clearing data
     fn clear_bots(&mut self) -> Result<(), AppError>;
     fn clear_listeners(&mut self) -> Result<(), AppError>;
+
+    /// Warnings about what the last change left in place, for its response; they do not fail
+    /// the change.
+    fn take_warnings(&mut self) -> Vec<String>;
+}
+
+impl AppState {
+    /// Record a state change of `bot_id` in the event log.
+    pub(crate) fn record_change(&mut self, bot_id: &str, summary: String) {
+        let now = self.providers.clock.now();
+        self.events
+            .record(now, bot_id, EventKind::StateChange, summary, None);
+    }
+
+    /// Record an update of `bot_id` in the event log, with the fields it changed.
+    pub(crate) fn record_update(&mut self, bot_id: &str, summary: String, changes: &[FieldChange]) {
+        let now = self.providers.clock.now();
+        let details = serde_json::json!({ "changes": changes });
+        self.events
+            .record(now, bot_id, EventKind::StateChange, summary, Some(details));
+    }
+
+    /// Listeners matching `args` on every bot whose ID passes `include`, ordered by bot ID
+    /// and listener ID so pages are stable.
+    pub(crate) fn listeners_where(
+        &self,
+        args: &ListenerListArgs,
+        include: impl Fn(&str) -> bool,
+    ) -> Result<ListenerListView, AppError> {
+        let mut listeners: Vec<ListenerView> = self
+            .bots
+            .iter()
+            .filter(|(bot_id, _)| include(bot_id))
+            .flat_map(|(bot_id, bot)| {
+                bot.listeners
+                    .iter()
+                    .filter(|(id, listener)| args.matches(id, listener))
+                    .map(move |(id, listener)| {
+                        let view = ListenerView::from((bot_id, id.as_str(), listener));
+                        if args.includes_msg() {
+                            view.with_msg(&self.templates, listener)
+                        } else {
+                            view
+                        }
+                    })
+            })
+            .collect();
+
+        if listeners.is_empty() {
+            return Err(AppError::ListenerNotFound(
+                "No matching listeners found.".to_string(),
+            ));
+        }
+        listeners.sort_by(|a: &ListenerView, b: &ListenerView| {
+            (&a.bot_id, &a.listener_id).cmp(&(&b.bot_id, &b.listener_id))
+        });
+        Ok(ListenerListView(listeners))
+    }
 }
 
 /// These are the primary state management functions
@@ -87,6 +162,10 @@ impl BotRegistry for AppState {
             })
     }
 
+    fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.warnings)
+    }
+
     /// Utility: Validate a bot ID.
     fn validate_bot_id(&self, bot_id: &str) -> Result<(), AppError> {
         (!bot_id.trim().is_empty())
@@ -96,6 +175,9 @@ impl BotRegistry for AppState {
 
     /// Clear all bots and save the updated state.
     fn clear_bots(&mut self) -> Result<(), AppError> {
+        for bot_id in self.bots.keys().cloned().collect::<Vec<_>>() {
+            self.record_change(&bot_id, "Bot deleted (all bots cleared)".to_string());
+        }
         self.bots.clear();
         self.save::<PathBuf>(None)?;
         info!("Successfully cleared all bots.");
@@ -107,6 +189,9 @@ impl BotRegistry for AppState {
         for bot in self.bots.values_mut() {
             bot.listeners.clear();
         }
+        for bot_id in self.bots.keys().cloned().collect::<Vec<_>>() {
+            self.record_change(&bot_id, "All listeners cleared".to_string());
+        }
         self.save::<PathBuf>(None)?;
         info!("Successfully cleared all listeners.");
         Ok(())
@@ -114,12 +199,18 @@ impl BotRegistry for AppState {
 
     /// Add a bot to the application state.
     /// base add_bot function
-    fn add_bot(&mut self, args: BotInsertArgs) -> Result<BotView, AppError> {
+    fn add_bot(&mut self, mut args: BotInsertArgs) -> Result<BotView, AppError> {
+        args.bot_id = args.bot_id.or_else(|| Some(self.providers.ids.next_id()));
+        paper::check_fill_pct(args.paper_fill_pct).map_err(AppError::InvalidInput)?;
         let bot: Bot = args.into();
         if self.bots.contains_key(&bot.bot_id) {
             return Err(AppError::BotAlreadyExists(bot.bot_id.clone()));
         }
         self.bots.insert(bot.bot_id.clone(), bot.clone());
+        self.warnings = bot.warnings();
+        self.warnings
+            .extend(allocation::warnings(&bot, &self.allocation));
+        self.record_change(&bot.bot_id, format!("Bot created on {}", bot.exchange));
         self.save::<PathBuf>(None)?;
         Ok(bot.into())
     }
@@ -129,7 +220,7 @@ impl BotRegistry for AppState {
         let filtered_bots: Vec<BotView> = self
             .bots
             .values()
-            .filter(|bot| args.as_ref().map_or(true, |filters| filters.matches(bot)))
+            .filter(|bot| args.as_ref().is_none_or(|filters| filters.matches(bot)))
             .map(|bot| bot.clone().into())
             .collect();
         if filtered_bots.is_empty() {
@@ -145,18 +236,28 @@ impl BotRegistry for AppState {
 
     /// Update an existing bot.
     fn update_bot(&mut self, args: BotUpdateArgs) -> Result<BotView, AppError> {
-        let bot_clone = {
+        paper::check_fill_pct(args.paper_fill_pct).map_err(AppError::InvalidInput)?;
+        let (bot_clone, changes) = {
             // Retrieve the bot mutably and apply updates
             let bot = self.get_bot_mut(&args.bot_id)?;
+            let before = bot.clone();
             args.apply(bot);
-            bot.clone() // Clone the updated bot for the response
+            let changes = FieldChange::between(&before, &*bot);
+            (bot.clone(), changes) // Clone the updated bot for the response
         };
+        self.warnings = bot_clone.warnings();
+        self.warnings
+            .extend(allocation::warnings(&bot_clone, &self.allocation));
+
+        self.record_update(&args.bot_id, "Bot updated".to_string(), &changes);
 
         // Save the updated state to the persistent storage
         self.save::<PathBuf>(None)?;
 
-        // Return the updated bot as a `BotView`
-        Ok(bot_clone.into())
+        // Return the updated bot as a `BotView`, with what changed
+        let mut view = BotView::from(bot_clone);
+        view.changes = Some(changes);
+        Ok(view)
     }
 
     /// Delete a bot and return its view.
@@ -164,25 +265,115 @@ impl BotRegistry for AppState {
         let bot = self.bots.remove(&args.bot_id).ok_or_else(|| {
             AppError::BotNotFound(format!("Bot with ID '{}' not found.", &args.bot_id))
         })?;
+        self.record_change(&args.bot_id, "Bot deleted".to_string());
         self.save::<PathBuf>(None)?;
         Ok(bot.into())
     }
 
+    /// Trade `args.exchange_symbol` for alerts naming `args.symbol`.
+    fn map_symbol(&mut self, args: SymbolMapArgs) -> Result<SymbolMapView, AppError> {
+        if args.symbol.trim().is_empty() || args.exchange_symbol.trim().is_empty() {
+            return Err(AppError::ValidationError(
+                "symbol and exchange_symbol cannot be empty".to_string(),
+            ));
+        }
+        let bot = self.get_bot_mut(&args.bot_id)?;
+        bot.symbol_map.insert(&args.symbol, &args.exchange_symbol);
+        let symbols = bot.symbol_map.clone();
+        self.record_change(
+            &args.bot_id,
+            format!(
+                "Symbol '{}' mapped to '{}'",
+                args.symbol, args.exchange_symbol
+            ),
+        );
+        self.save::<PathBuf>(None)?;
+        Ok(SymbolMapView {
+            bot_id: args.bot_id,
+            symbols,
+        })
+    }
+
+    /// Trade the symbol alerts name again, or the configured mapping of it.
+    fn unmap_symbol(&mut self, args: SymbolUnmapArgs) -> Result<SymbolMapView, AppError> {
+        let bot = self.get_bot_mut(&args.bot_id)?;
+        if !bot.symbol_map.remove(&args.symbol) {
+            return Err(AppError::NotFound(format!(
+                "Bot '{}' does not map symbol '{}'.",
+                args.bot_id, args.symbol
+            )));
+        }
+        let symbols = bot.symbol_map.clone();
+        self.record_change(&args.bot_id, format!("Symbol '{}' unmapped", args.symbol));
+        self.save::<PathBuf>(None)?;
+        Ok(SymbolMapView {
+            bot_id: args.bot_id,
+            symbols,
+        })
+    }
+
+    /// A page of the bot's event timeline, newest first.
+    fn bot_timeline(&self, args: BotTimelineArgs) -> Result<TimelineView, AppError> {
+        self.validate_bot_id(&args.bot_id)?;
+        let (events, next_cursor) =
+            self.events
+                .page(&args.bot_id, args.before, args.since, args.limit());
+        if events.is_empty() && !self.bots.contains_key(&args.bot_id) {
+            return Err(AppError::BotNotFound(format!(
+                "Bot with ID '{}' not found.",
+                args.bot_id
+            )));
+        }
+        Ok(TimelineView {
+            events,
+            next_cursor,
+        })
+    }
+
     /// Add a listener to a bot.
     fn add_listener(&mut self, args: ListenerInsertArgs) -> Result<ListenerView, AppError> {
-        let bot = self.get_bot_mut(&args.bot_id)?;
         let listener_id = args
             .listener_id
-            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+            .unwrap_or_else(|| self.providers.ids.next_id());
+        let msg = args.msg.unwrap_or_default();
+        let msg_sha256 = self.templates.insert(&msg);
+        let bot = self.get_bot_mut(&args.bot_id)?;
+        if bot.listeners.contains_key(&listener_id) {
+            return Err(AppError::ListenerAlreadyExists(listener_id));
+        }
         let listener = Listener {
             service: args.service,
             secret: args.secret.unwrap_or_default(),
-            msg: args.msg.unwrap_or_default(),
+            msg: String::new(),
+            msg_sha256,
+            enabled: args.enabled.unwrap_or(true),
+            verify_secret: args.verify_secret.unwrap_or(true),
+            last_fired: None,
+            max_alerts_per_minute: args.max_alerts_per_minute,
+            cooldown_secs: args.cooldown_secs,
+            filter: AlertFilter {
+                symbols: args.symbols,
+                actions: args.actions,
+                min_price: args.min_price,
+                max_price: args.max_price,
+                trading_hours: args.trading_hours,
+            },
+            previous_secret: None,
+            notifications: NotificationPrefs {
+                on: args.notify_on,
+                channels: args.notify_channels,
+            },
         };
 
         bot.listeners.insert(listener_id.clone(), listener.clone());
+        self.warnings = listener.warnings();
+        self.record_change(
+            &args.bot_id,
+            format!("Listener '{}' added ({})", listener_id, listener.service),
+        );
         self.save::<PathBuf>(None)?;
-        Ok((&args.bot_id, listener_id.as_str(), &listener).into())
+        let view = ListenerView::from((&args.bot_id, listener_id.as_str(), &listener));
+        Ok(view.with_msg(&self.templates, &listener))
     }
 
     /// List listeners for a bot, optionally filtering by arguments.
@@ -194,7 +385,14 @@ impl BotRegistry for AppState {
             .listeners
             .iter()
             .filter(|(id, listener)| args.matches(id, listener))
-            .map(|(id, listener)| (&args.bot_id, id.as_str(), listener).into())
+            .map(|(id, listener)| {
+                let view = ListenerView::from((&args.bot_id, id.as_str(), listener));
+                if args.includes_msg() {
+                    view.with_msg(&self.templates, listener)
+                } else {
+                    view
+                }
+            })
             .collect();
 
         if filtered_listeners.is_empty() {
@@ -205,32 +403,93 @@ impl BotRegistry for AppState {
         Ok(ListenerListView(filtered_listeners))
     }
 
+    /// List listeners across all bots, optionally filtering by arguments.
+    fn list_all_listeners(&self, args: ListenerListArgs) -> Result<ListenerListView, AppError> {
+        self.listeners_where(&args, |_| true)
+    }
+
     /// Get a specific listener by bot ID and listener ID.
     fn get_listener(&self, args: ListenerGetArgs) -> Result<ListenerView, AppError> {
-        Ok((
-            args.bot_id.clone(),
-            args.listener_id.clone(),
-            self.get_listener_ref(&args.bot_id, &args.listener_id)?,
-        )
-            .into())
+        let listener = self.get_listener_ref(&args.bot_id, &args.listener_id)?;
+        let view = ListenerView::from((&args.bot_id, &args.listener_id, listener));
+        Ok(view.with_msg(&self.templates, listener))
     }
 
     /// Update a specific listener by bot ID and listener ID.
     fn update_listener(&mut self, args: ListenerUpdateArgs) -> Result<ListenerView, AppError> {
-        let updated_listener_view = {
+        if let Some(msg) = &args.msg {
+            self.templates.insert(msg);
+        }
+        let (updated_listener_view, warnings, changes) = {
             let listener = self.get_listener_mut(&args.bot_id, &args.listener_id)?;
+            let before = listener.clone();
             // Apply updates to the listener
             args.apply(listener);
             // Create an immutable reference to the updated listener
             let listener_ref = &*listener;
-            // Convert to ListenerView using the immutable reference
-            (&args.bot_id, args.listener_id.as_str(), listener_ref).into()
+            let changes = FieldChange::between(&before, listener_ref);
+            // Convert to ListenerView using the immutable reference, with what changed
+            let mut view: ListenerView =
+                (&args.bot_id, args.listener_id.as_str(), listener_ref).into();
+            view.changes = Some(changes.clone());
+            (view, listener_ref.warnings(), changes)
         };
+        self.warnings = warnings;
+        self.record_update(
+            &args.bot_id,
+            format!("Listener '{}' updated", args.listener_id),
+            &changes,
+        );
         // Save the updated state
         self.save::<PathBuf>(None)?;
         Ok(updated_listener_view)
     }
 
+    /// Give a listener a new random secret. The old one stays valid for `args.grace_secs`,
+    /// replacing any secret still valid from an earlier rotation.
+    fn rotate_listener_secret(
+        &mut self,
+        args: ListenerRotateSecretArgs,
+    ) -> Result<SecretRotationView, AppError> {
+        let grace_secs = args.grace_secs.unwrap_or(0);
+        if grace_secs > MAX_SECRET_GRACE_SECS {
+            return Err(AppError::ValidationError(format!(
+                "Grace period cannot exceed {} seconds (30 days).",
+                MAX_SECRET_GRACE_SECS
+            )));
+        }
+        let now = self.providers.clock.now();
+        let secret = random_secret();
+        let listener = self.get_listener_mut(&args.bot_id, &args.listener_id)?;
+        let old = std::mem::replace(&mut listener.secret, secret.clone());
+        listener.previous_secret = (grace_secs > 0 && !old.is_empty()).then(|| RetiredSecret {
+            secret: old,
+            valid_until: now + chrono::Duration::seconds(grace_secs as i64),
+        });
+        let previous_valid_until = listener
+            .previous_secret
+            .as_ref()
+            .map(|previous| previous.valid_until);
+
+        self.record_change(
+            &args.bot_id,
+            format!("Listener '{}' secret rotated", args.listener_id),
+        );
+        self.save::<PathBuf>(None)?;
+        Ok(SecretRotationView {
+            webhook_url: SecretRotationView::webhook_path(
+                &args.bot_id,
+                &args.listener_id,
+                &secret,
+                None,
+            ),
+            bot_id: args.bot_id,
+            listener_id: args.listener_id,
+            secret,
+            previous_valid_until,
+        })
+    }
+
     /// Delete a specific listener by bot ID and listener ID.
     fn delete_listener(&mut self, args: ListenerDeleteArgs) -> Result<ListenerView, AppError> {
         let bot = self.get_bot_mut(&args.bot_id)?;
@@ -240,6 +499,11 @@ impl BotRegistry for AppState {
                 args.listener_id, args.bot_id
             ))
         })?;
+        self.record_change(
+            &args.bot_id,
+            format!("Listener '{}' deleted", args.listener_id),
+        );
+        self.save::<PathBuf>(None)?;
         Ok((&args.bot_id, &args.listener_id, &listener).into())
     }
 
@@ -271,8 +535,571 @@ impl BotRegistry for AppState {
             ));
         }
 
+        self.record_change(
+            &args.bot_id,
+            format!("{} listener(s) deleted", deleted_listeners.len()),
+        );
         // Save the updated state
         self.save::<PathBuf>(None)?;
         Ok(ListenerListView(deleted_listeners))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app_config::AppConfig;
+    use crate::bot::state::Providers;
+    use proptest::prelude::*;
+    use tempfile::{tempdir, TempDir};
+
+    /// A single registry operation. Indices select among the bots currently in
+    /// the state (modulo its size) so sequences keep hitting existing entries.
+    #[derive(Clone, Debug)]
+    enum Op {
+        AddBot {
+            bot_id: Option<String>,
+            name: String,
+            exchange: String,
+        },
+        UpdateBot {
+            bot: usize,
+            name: Option<String>,
+            exchange: Option<String>,
+        },
+        DeleteBot {
+            bot: usize,
+        },
+        ListBots {
+            exchange: Option<String>,
+        },
+        AddListener {
+            bot: usize,
+            listener_id: Option<String>,
+            service: String,
+        },
+        UpdateListener {
+            bot: usize,
+            listener: usize,
+            service: Option<String>,
+        },
+        DeleteListener {
+            bot: usize,
+            listener: usize,
+        },
+        DeleteListeners {
+            bot: usize,
+            service: Option<String>,
+        },
+        ListListeners {
+            bot: usize,
+            service: Option<String>,
+        },
+    }
+
+    fn small_id() -> impl Strategy<Value = String> {
+        "[a-d]"
+    }
+
+    fn exchange() -> impl Strategy<Value = String> {
+        prop::sample::select(vec!["binance", "bybit", "dydx"]).prop_map(str::to_string)
+    }
+
+    fn service() -> impl Strategy<Value = String> {
+        prop::sample::select(vec!["tradingview", "telegram"]).prop_map(str::to_string)
+    }
+
+    fn op() -> impl Strategy<Value = Op> {
+        prop_oneof![
+            (prop::option::of(small_id()), "[a-z]{1,6}", exchange()).prop_map(
+                |(bot_id, name, exchange)| Op::AddBot {
+                    bot_id,
+                    name,
+                    exchange
+                }
+            ),
+            (
+                any::<usize>(),
+                prop::option::of("[a-z]{1,6}"),
+                prop::option::of(exchange())
+            )
+                .prop_map(|(bot, name, exchange)| Op::UpdateBot {
+                    bot,
+                    name,
+                    exchange
+                }),
+            any::<usize>().prop_map(|bot| Op::DeleteBot { bot }),
+            prop::option::of(exchange()).prop_map(|exchange| Op::ListBots { exchange }),
+            (any::<usize>(), prop::option::of(small_id()), service()).prop_map(
+                |(bot, listener_id, service)| Op::AddListener {
+                    bot,
+                    listener_id,
+                    service
+                }
+            ),
+            (any::<usize>(), any::<usize>(), prop::option::of(service())).prop_map(
+                |(bot, listener, service)| Op::UpdateListener {
+                    bot,
+                    listener,
+                    service
+                }
+            ),
+            (any::<usize>(), any::<usize>())
+                .prop_map(|(bot, listener)| Op::DeleteListener { bot, listener }),
+            (any::<usize>(), prop::option::of(service()))
+                .prop_map(|(bot, service)| Op::DeleteListeners { bot, service }),
+            (any::<usize>(), prop::option::of(service()))
+                .prop_map(|(bot, service)| Op::ListListeners { bot, service }),
+        ]
+    }
+
+    fn temp_state() -> (TempDir, AppConfig, AppState) {
+        let dir = tempdir().unwrap();
+        let mut config = AppConfig::default();
+        config.api_server.state_file = dir.path().join("state.json");
+        let state = AppState::load(config.clone()).unwrap();
+        (dir, config, state)
+    }
+
+    /// Pick an existing bot ID, or a never-used one when the state is empty.
+    fn pick_bot(state: &AppState, idx: usize) -> String {
+        let mut ids: Vec<&String> = state.bots.keys().collect();
+        ids.sort();
+        ids.get(idx % ids.len().max(1))
+            .map(|id| id.to_string())
+            .unwrap_or_else(|| "missing".to_string())
+    }
+
+    fn pick_listener(state: &AppState, bot_id: &str, idx: usize) -> String {
+        let mut ids: Vec<&String> = state
+            .bots
+            .get(bot_id)
+            .map(|bot| bot.listeners.keys().collect())
+            .unwrap_or_default();
+        ids.sort();
+        ids.get(idx % ids.len().max(1))
+            .map(|id| id.to_string())
+            .unwrap_or_else(|| "missing".to_string())
+    }
+
+    fn apply(state: &mut AppState, op: &Op) {
+        match op.clone() {
+            Op::AddBot {
+                bot_id,
+                name,
+                exchange,
+            } => {
+                let existed = bot_id
+                    .as_ref()
+                    .is_some_and(|id| state.bots.contains_key(id));
+                let before = state.bots.len();
+                let result = state.add_bot(BotInsertArgs::new(name, exchange).bot_id(bot_id));
+                if existed {
+                    assert!(matches!(result, Err(AppError::BotAlreadyExists(_))));
+                    assert_eq!(state.bots.len(), before);
+                } else {
+                    let view = result.unwrap();
+                    assert!(state.bots.contains_key(&view.bot_id));
+                    assert_eq!(state.bots.len(), before + 1);
+                }
+            }
+            Op::UpdateBot {
+                bot,
+                name,
+                exchange,
+            } => {
+                let bot_id = pick_bot(state, bot);
+                let mut args = BotUpdateArgs::new(&bot_id);
+                args.name = name;
+                args.exchange = exchange;
+                match state.update_bot(args) {
+                    Ok(view) => assert_eq!(view.bot_id, bot_id),
+                    Err(e) => assert!(matches!(e, AppError::BotNotFound(_))),
+                }
+            }
+            Op::DeleteBot { bot } => {
+                let bot_id = pick_bot(state, bot);
+                match state.delete_bot(BotDeleteArgs::new(&bot_id)) {
+                    Ok(view) => {
+                        assert_eq!(view.bot_id, bot_id);
+                        assert!(!state.bots.contains_key(&bot_id));
+                    }
+                    Err(e) => assert!(matches!(e, AppError::BotNotFound(_))),
+                }
+            }
+            Op::ListBots { exchange } => {
+                let args = BotListArgs {
+                    exchange: exchange.clone(),
+                    ..Default::default()
+                };
+                let expected = state
+                    .bots
+                    .values()
+                    .filter(|bot| exchange.as_ref().is_none_or(|e| &bot.exchange == e))
+                    .count();
+                match state.list_bots(Some(args)) {
+                    Ok(list) => {
+                        assert_eq!(list.0.len(), expected);
+                        assert!(list
+                            .0
+                            .iter()
+                            .all(|bot| exchange.as_ref().is_none_or(|e| &bot.exchange == e)));
+                    }
+                    Err(e) => {
+                        assert_eq!(expected, 0);
+                        assert!(matches!(e, AppError::NotFound(_)));
+                    }
+                }
+            }
+            Op::AddListener {
+                bot,
+                listener_id,
+                service,
+            } => {
+                let bot_id = pick_bot(state, bot);
+                let existed = state.bots.get(&bot_id).map(|bot| {
+                    listener_id
+                        .as_ref()
+                        .is_some_and(|id| bot.listeners.contains_key(id))
+                });
+                let result = state.add_listener(
+                    ListenerInsertArgs::new(&bot_id, &service).listener_id(listener_id),
+                );
+                match existed {
+                    None => assert!(matches!(result, Err(AppError::BotNotFound(_)))),
+                    Some(true) => {
+                        assert!(matches!(result, Err(AppError::ListenerAlreadyExists(_))))
+                    }
+                    Some(false) => {
+                        let view = result.unwrap();
+                        assert_eq!(view.bot_id, bot_id);
+                        assert!(state.bots[&bot_id]
+                            .listeners
+                            .contains_key(&view.listener_id));
+                    }
+                }
+            }
+            Op::UpdateListener {
+                bot,
+                listener,
+                service,
+            } => {
+                let bot_id = pick_bot(state, bot);
+                let listener_id = pick_listener(state, &bot_id, listener);
+                let mut args = ListenerUpdateArgs::new(&bot_id, &listener_id);
+                args.service = service.clone();
+                if let Ok(view) = state.update_listener(args) {
+                    assert_eq!(view.bot_id, bot_id);
+                    assert_eq!(view.listener_id, listener_id);
+                    if let Some(service) = service {
+                        assert_eq!(state.bots[&bot_id].listeners[&listener_id].service, service);
+                    }
+                }
+            }
+            Op::DeleteListener { bot, listener } => {
+                let bot_id = pick_bot(state, bot);
+                let listener_id = pick_listener(state, &bot_id, listener);
+                if state
+                    .delete_listener(ListenerDeleteArgs::new(&bot_id, &listener_id))
+                    .is_ok()
+                {
+                    assert!(!state.bots[&bot_id].listeners.contains_key(&listener_id));
+                }
+            }
+            Op::DeleteListeners { bot, service } => {
+                let bot_id = pick_bot(state, bot);
+                let mut args = ListenersDeleteArgs::new(&bot_id);
+                args.service = service.clone();
+                if let Ok(deleted) = state.delete_listeners(args) {
+                    assert!(!deleted.0.is_empty());
+                    let remaining = &state.bots[&bot_id].listeners;
+                    for view in &deleted {
+                        assert_eq!(view.bot_id, bot_id);
+                        assert!(!remaining.contains_key(&view.listener_id));
+                    }
+                    if let Some(service) = service {
+                        assert!(remaining.values().all(|l| l.service != service));
+                    } else {
+                        assert!(remaining.is_empty());
+                    }
+                }
+            }
+            Op::ListListeners { bot, service } => {
+                let bot_id = pick_bot(state, bot);
+                let mut args = ListenerListArgs::new(&bot_id);
+                args.service = service.clone();
+                if let Ok(list) = state.list_listeners(args) {
+                    for view in &list {
+                        assert_eq!(view.bot_id, bot_id);
+                        assert!(service.is_none() || view.service == service);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Invariants that must hold after every operation.
+    fn check_invariants(state: &AppState) {
+        for (key, bot) in &state.bots {
+            // Bots are keyed by their own ID, so IDs are unique by construction.
+            assert_eq!(key, &bot.bot_id);
+
+            // Every listener reported for a bot points back at that bot.
+            if let Ok(list) = state.list_listeners(ListenerListArgs::new(key)) {
+                assert_eq!(list.0.len(), bot.listeners.len());
+                assert!(list.0.iter().all(|view| &view.bot_id == key));
+            } else {
+                assert!(bot.listeners.is_empty());
+            }
+        }
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn registry_operations_preserve_invariants(ops in prop::collection::vec(op(), 1..40)) {
+            let (_dir, config, mut state) = temp_state();
+
+            for op in &ops {
+                apply(&mut state, op);
+                check_invariants(&state);
+            }
+
+            // Whatever was persisted must load back to the in-memory state; warnings are only
+            // kept for the response of a change.
+            state.take_warnings();
+            let reloaded = AppState::load(config).unwrap();
+            prop_assert_eq!(reloaded, state);
+        }
+    }
+
+    #[test]
+    fn delete_listener_is_persisted() {
+        let (_dir, config, mut state) = temp_state();
+        state
+            .add_bot(
+                BotInsertArgs::new("bot".to_string(), "binance".to_string())
+                    .bot_id(Some("a".to_string())),
+            )
+            .unwrap();
+        state
+            .add_listener(
+                ListenerInsertArgs::new("a", "tradingview").listener_id(Some("l".to_string())),
+            )
+            .unwrap();
+        state
+            .delete_listener(ListenerDeleteArgs::new("a", "l"))
+            .unwrap();
+
+        let reloaded = AppState::load(config).unwrap();
+        assert!(reloaded.bots["a"].listeners.is_empty());
+    }
+
+    #[test]
+    fn list_all_listeners_filters_across_bots() {
+        let (_dir, _config, mut state) = temp_state();
+        for bot_id in ["a", "b"] {
+            state
+                .add_bot(
+                    BotInsertArgs::new("bot".to_string(), "binance".to_string())
+                        .bot_id(Some(bot_id.to_string())),
+                )
+                .unwrap();
+            for service in ["tradingview", "other"] {
+                state
+                    .add_listener(
+                        ListenerInsertArgs::new(bot_id, service)
+                            .listener_id(Some(service.to_string())),
+                    )
+                    .unwrap();
+            }
+        }
+        let cutoff = chrono::Utc::now();
+        state
+            .get_listener_mut("a", "tradingview")
+            .unwrap()
+            .last_fired = Some(cutoff);
+
+        let mut args = ListenerListArgs::all_bots();
+        args.service = Some("tradingview".to_string());
+        args.last_fired_before = Some(cutoff);
+        let stale = state.list_all_listeners(args).unwrap();
+        let found: Vec<_> = stale
+            .0
+            .iter()
+            .map(|l| (l.bot_id.as_str(), l.listener_id.as_str()))
+            .collect();
+        assert_eq!(found, vec![("b", "tradingview")]);
+
+        let mut args = ListenerListArgs::all_bots();
+        args.enabled = Some(false);
+        assert!(state.list_all_listeners(args).is_err());
+    }
+
+    #[test]
+    fn lists_leave_message_templates_out_unless_asked() {
+        let (_dir, config, mut state) = temp_state();
+        state
+            .add_bot(
+                BotInsertArgs::new("bot".to_string(), "binance".to_string())
+                    .bot_id(Some("a".to_string())),
+            )
+            .unwrap();
+        let msg = "{\"action\":\"{{strategy.order.action}}\"}".repeat(50);
+        let added = state
+            .add_listener(
+                ListenerInsertArgs::new("a", "tradingview")
+                    .listener_id(Some("tv".to_string()))
+                    .msg(Some(msg.clone())),
+            )
+            .unwrap();
+        assert_eq!(added.msg.as_deref(), Some(msg.as_str()));
+
+        let listed = state.list_listeners(ListenerListArgs::new("a")).unwrap();
+        assert_eq!(listed.0[0].msg, None);
+        assert!(!serde_json::to_string(&listed).unwrap().contains("strategy"));
+        let mut args = ListenerListArgs::new("a");
+        args.include = vec!["msg".to_string()];
+        assert_eq!(
+            state.list_listeners(args).unwrap().0[0].msg,
+            Some(msg.clone())
+        );
+
+        // The state keeps the body once, apart from the listener
+        let reloaded = AppState::load(config).unwrap();
+        let listener = &reloaded.bots["a"].listeners["tv"];
+        assert!(listener.msg.is_empty() && listener.msg_sha256.is_some());
+        let view = reloaded
+            .get_listener(ListenerGetArgs::new("a", "tv"))
+            .unwrap();
+        assert_eq!(view.msg, Some(msg));
+    }
+
+    #[test]
+    fn state_changes_appear_on_the_timeline() {
+        let (_dir, config, mut state) = temp_state();
+        state
+            .add_bot(
+                BotInsertArgs::new("bot".to_string(), "binance".to_string())
+                    .bot_id(Some("a".to_string())),
+            )
+            .unwrap();
+        state
+            .add_listener(
+                ListenerInsertArgs::new("a", "tradingview").listener_id(Some("l".to_string())),
+            )
+            .unwrap();
+        state
+            .delete_listener(ListenerDeleteArgs::new("a", "l"))
+            .unwrap();
+
+        // The log is persisted with the state
+        let reloaded = AppState::load(config).unwrap();
+        let summaries: Vec<String> = reloaded
+            .bot_timeline(BotTimelineArgs::new("a"))
+            .unwrap()
+            .events
+            .into_iter()
+            .map(|event| event.summary)
+            .collect();
+        assert_eq!(
+            summaries,
+            [
+                "Listener 'l' deleted",
+                "Listener 'l' added (tradingview)",
+                "Bot created on binance"
+            ]
+        );
+        assert!(reloaded.bot_timeline(BotTimelineArgs::new("b")).is_err());
+    }
+
+    #[test]
+    fn updates_record_the_fields_they_changed() {
+        let (_dir, _config, mut state) = temp_state();
+        let args = BotInsertArgs::new("bot".to_string(), "binance".to_string())
+            .bot_id(Some("a".to_string()));
+        state.add_bot(args).unwrap();
+
+        let view = state
+            .update_bot(BotUpdateArgs {
+                name: Some("renamed".to_string()),
+                api_secret: Some("hunter2".to_string()),
+                ..BotUpdateArgs::new("a")
+            })
+            .unwrap();
+        let changes = view.changes.clone().unwrap();
+        let fields: Vec<_> = changes.iter().map(|c| c.field.as_str()).collect();
+        assert_eq!(fields, ["api_secret", "name"]);
+        assert_eq!(changes[0].after, "********");
+        assert_eq!(
+            (&changes[1].before, &changes[1].after),
+            (&"bot".into(), &"renamed".into())
+        );
+        assert!(view.to_string().starts_with("Bot 'a' updated:"));
+        assert!(!view.to_string().contains("hunter2"));
+
+        // The event of the update has them too
+        let timeline = state.bot_timeline(BotTimelineArgs::new("a")).unwrap();
+        let details = timeline.events[0].details.clone().unwrap();
+        assert_eq!(details["changes"], serde_json::to_value(&changes).unwrap());
+
+        // Fetched views show the bot rather than changes
+        assert!(state
+            .get_bot(BotGetArgs::new("a"))
+            .unwrap()
+            .changes
+            .is_none());
+        let unchanged = state.update_bot(BotUpdateArgs::new("a")).unwrap();
+        assert_eq!(unchanged.to_string(), "Bot 'a' unchanged");
+    }
+
+    #[test]
+    fn changes_report_what_they_left_in_place() {
+        let (_dir, _config, mut state) = temp_state();
+        let args = BotInsertArgs::new("bot".to_string(), "binance".to_string())
+            .bot_id(Some("a".to_string()));
+        state.add_bot(args).unwrap();
+        assert_eq!(
+            state.take_warnings(),
+            [
+                "trading_fee missing; PnL will exclude fees",
+                "no credentials; orders will fail until they are set"
+            ]
+        );
+        // Taken once, for the response of the change
+        assert!(state.take_warnings().is_empty());
+
+        state
+            .add_listener(ListenerInsertArgs::new("a", "tradingview").listener_id(Some("l".into())))
+            .unwrap();
+        assert_eq!(
+            state.take_warnings(),
+            ["no secret; anyone who knows the webhook URL can send alerts"]
+        );
+        state
+            .add_listener(
+                ListenerInsertArgs::new("a", "tradingview")
+                    .listener_id(Some("m".into()))
+                    .secret(Some("s3cret".to_string())),
+            )
+            .unwrap();
+        assert!(state.take_warnings().is_empty());
+    }
+
+    #[test]
+    fn deterministic_providers_assign_ids() {
+        let (_dir, _config, state) = temp_state();
+        let mut state = state.with_providers(Providers::deterministic("id", chrono::Utc::now()));
+
+        let bot = state
+            .add_bot(BotInsertArgs::new("bot".to_string(), "binance".to_string()))
+            .unwrap();
+        let listener = state
+            .add_listener(ListenerInsertArgs::new(&bot.bot_id, "tradingview"))
+            .unwrap();
+
+        assert_eq!(bot.bot_id, "id-1");
+        assert_eq!(listener.listener_id, "id-2");
+    }
+}
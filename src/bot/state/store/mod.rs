@@ -0,0 +1,105 @@
+// src/bot/state/store/mod.rs
+//! # State Stores
+//!
+//! With `[storage] backend` set to `redis` or `s3`, the state is kept in a [`StateStore`]
+//! instead of the state file, so servers without a persistent volume, or several of them,
+//! can share it. What a store keeps is exactly what the state file would hold. Saves only
+//! replace what the saving process last saw, and servers [watch](StateStore::watch) the
+//! store to reload what others saved.
+//!
+//! Only the state itself moves to a store: trade histories, logs and archives stay files
+//! next to `api_server.state_file`, and each server counts quotas and rate limits on its own.
+pub mod redis;
+pub mod s3;
+
+use self::redis::RedisStore;
+use self::s3::S3Store;
+use crate::app_config::{StorageBackend, StorageConfig};
+use crate::errors::AppError;
+use std::io;
+use std::path::Path;
+
+/// Where the state is kept in place of the state file.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StateStore {
+    Redis(RedisStore),
+    S3(Box<S3Store>),
+}
+
+impl StateStore {
+    /// The store `config` asks for, checked to answer; `None` if the state is kept in
+    /// `state_file`.
+    pub fn open(config: &StorageConfig, state_file: &Path) -> Result<Option<Self>, AppError> {
+        match config.backend {
+            StorageBackend::File => Ok(None),
+            StorageBackend::Redis => {
+                RedisStore::connect(config).map(|store| Some(Self::Redis(store)))
+            }
+            StorageBackend::S3 => {
+                S3Store::connect(config, state_file).map(|store| Some(Self::S3(Box::new(store))))
+            }
+        }
+    }
+
+    /// Where the state is kept, without credentials, for messages.
+    pub fn location(&self) -> &str {
+        match self {
+            Self::Redis(store) => store.location(),
+            Self::S3(store) => store.location(),
+        }
+    }
+
+    /// The state as last saved; `None` if nothing was saved yet.
+    pub fn read(&self) -> io::Result<Option<Vec<u8>>> {
+        match self {
+            Self::Redis(store) => store.read(),
+            Self::S3(store) => store.read(),
+        }
+    }
+
+    /// Replace the state with `content`, whose content hash is `hash`, unless someone else
+    /// saved it since: `unchanged` tells from what is there now. Returns whether it was
+    /// replaced.
+    pub fn write(
+        &self,
+        content: &[u8],
+        hash: u64,
+        unchanged: impl Fn(&[u8]) -> bool,
+    ) -> io::Result<bool> {
+        match self {
+            Self::Redis(store) => store.write(content, hash, unchanged),
+            Self::S3(store) => store.write(content, hash, unchanged),
+        }
+    }
+
+    /// Call `on_change` right away, then whenever another process may have saved the
+    /// state, until it returns `false` or watching fails.
+    pub fn watch(&self, on_change: impl FnMut() -> bool) -> io::Result<()> {
+        match self {
+            Self::Redis(store) => store.watch(on_change),
+            Self::S3(store) => store.watch(on_change),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_file_backend_needs_no_store() {
+        let state_file = Path::new("state.json");
+        assert!(StateStore::open(&StorageConfig::default(), state_file)
+            .unwrap()
+            .is_none());
+        let invalid = StorageConfig {
+            backend: StorageBackend::Redis,
+            redis_url: "http://localhost".to_string(),
+            ..StorageConfig::default()
+        };
+        assert!(matches!(
+            StateStore::open(&invalid, state_file),
+            Err(AppError::ConfigError(_))
+        ));
+    }
+}
@@ -0,0 +1,219 @@
+// src/bot/state/store/redis.rs
+//! # Redis State Store
+//!
+//! With `[storage] backend = "redis"`, the state is kept as one value under
+//! `<key_prefix>:state`. A save replaces the value only if it is still the one the saving
+//! process last saw, in a `WATCH`/`MULTI` transaction that also announces it on
+//! `<key_prefix>:changes`; servers [watching](RedisStore::watch) that channel reload the
+//! state as soon as another one saved it, and forget what they cached from the one before.
+//!
+//! Redis only shares the state: like the state file, it is read whole, and bots and
+//! listeners are looked up in the state each server holds in memory.
+use crate::app_config::StorageConfig;
+use crate::errors::AppError;
+use std::fmt;
+use std::io;
+
+/// The state in Redis; cheap to clone, clones share their connection.
+#[cfg(feature = "state-redis")]
+#[derive(Clone)]
+pub struct RedisStore {
+    client: redis::Client,
+    /// Connection for reads and saves, opened on first use and again after failures
+    connection: std::sync::Arc<std::sync::Mutex<Option<redis::Connection>>>,
+    key: String,
+    channel: String,
+    location: String,
+}
+
+/// The state in Redis, which this build cannot keep it in.
+#[cfg(not(feature = "state-redis"))]
+#[derive(Clone)]
+pub struct RedisStore {
+    never: std::convert::Infallible,
+}
+
+#[cfg(feature = "state-redis")]
+impl RedisStore {
+    /// How long connecting and each command may take.
+    const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+    /// The store at `config.redis_url`, checked to answer.
+    pub fn connect(config: &StorageConfig) -> Result<Self, AppError> {
+        let client = redis::Client::open(config.redis_url.as_str())
+            .map_err(|e| AppError::ConfigError(format!("Invalid storage.redis_url: {}", e)))?;
+        let info = client.get_connection_info();
+        let key = format!("{}:state", config.key_prefix);
+        let store = Self {
+            location: format!("redis://{}/{} ({})", info.addr, info.redis.db, key),
+            channel: format!("{}:changes", config.key_prefix),
+            key,
+            connection: Default::default(),
+            client,
+        };
+        store
+            .query(|con| redis::cmd("PING").exec(con))
+            .map_err(|e| AppError::ConnectionError(format!("{}: {}", store.location, e)))?;
+        Ok(store)
+    }
+
+    /// Where the state is kept, without credentials, for messages.
+    pub fn location(&self) -> &str {
+        &self.location
+    }
+
+    /// The state as last saved; `None` if nothing was saved yet.
+    pub fn read(&self) -> io::Result<Option<Vec<u8>>> {
+        self.query(|con| redis::cmd("GET").arg(&self.key).query(con))
+    }
+
+    /// Replace the state with `content`, whose content hash is `hash`, unless someone else
+    /// saved it since: `unchanged` tells from what is there now. Returns whether it was
+    /// replaced.
+    pub fn write(
+        &self,
+        content: &[u8],
+        hash: u64,
+        unchanged: impl Fn(&[u8]) -> bool,
+    ) -> io::Result<bool> {
+        self.query(|con| {
+            redis::transaction(con, &[&self.key], |con, pipe| {
+                let current: Option<Vec<u8>> = redis::cmd("GET").arg(&self.key).query(con)?;
+                if current.is_some_and(|current| !unchanged(&current)) {
+                    return Ok(Some(false));
+                }
+                // `None` when the key changed after `WATCH`, to look again
+                let replaced: Option<()> = pipe
+                    .set(&self.key, content)
+                    .ignore()
+                    .publish(&self.channel, hash.to_string())
+                    .ignore()
+                    .query(con)?;
+                Ok(replaced.map(|()| true))
+            })
+        })
+    }
+
+    /// Call `on_change` once subscribed to the changes channel, then whenever a server
+    /// saves the state, until it returns `false` or the subscription fails.
+    pub fn watch(&self, mut on_change: impl FnMut() -> bool) -> io::Result<()> {
+        let mut con = self
+            .client
+            .get_connection_with_timeout(Self::TIMEOUT)
+            .map_err(io::Error::other)?;
+        let mut pubsub = con.as_pubsub();
+        pubsub.subscribe(&self.channel).map_err(io::Error::other)?;
+        // Saves made while not subscribed went unannounced
+        while on_change() {
+            pubsub.get_message().map_err(io::Error::other)?;
+        }
+        Ok(())
+    }
+
+    /// Run `command` on the shared connection, dropping it if it fails.
+    fn query<T>(
+        &self,
+        command: impl FnOnce(&mut redis::Connection) -> redis::RedisResult<T>,
+    ) -> io::Result<T> {
+        let mut connection = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+        let con = match connection.as_mut() {
+            Some(con) => con,
+            None => {
+                let con = self
+                    .client
+                    .get_connection_with_timeout(Self::TIMEOUT)
+                    .map_err(io::Error::other)?;
+                con.set_read_timeout(Some(Self::TIMEOUT))
+                    .and_then(|()| con.set_write_timeout(Some(Self::TIMEOUT)))
+                    .map_err(io::Error::other)?;
+                connection.insert(con)
+            }
+        };
+        let result = command(con);
+        if result.is_err() {
+            *connection = None;
+        }
+        result.map_err(io::Error::other)
+    }
+}
+
+#[cfg(not(feature = "state-redis"))]
+impl RedisStore {
+    pub fn connect(_config: &StorageConfig) -> Result<Self, AppError> {
+        Err(AppError::ConfigError(
+            "storage.backend is \"redis\"; xtrade was built without the `state-redis` feature."
+                .to_string(),
+        ))
+    }
+
+    pub fn location(&self) -> &str {
+        match self.never {}
+    }
+
+    pub fn read(&self) -> io::Result<Option<Vec<u8>>> {
+        match self.never {}
+    }
+
+    pub fn write(
+        &self,
+        _content: &[u8],
+        _hash: u64,
+        _unchanged: impl Fn(&[u8]) -> bool,
+    ) -> io::Result<bool> {
+        match self.never {}
+    }
+
+    pub fn watch(&self, _on_change: impl FnMut() -> bool) -> io::Result<()> {
+        match self.never {}
+    }
+}
+
+impl fmt::Debug for RedisStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RedisStore").field(&self.location()).finish()
+    }
+}
+
+impl PartialEq for RedisStore {
+    fn eq(&self, other: &Self) -> bool {
+        self.location() == other.location()
+    }
+}
+
+#[cfg(all(test, feature = "state-redis"))]
+mod tests {
+    use super::*;
+    use crate::app_config::StorageBackend;
+
+    /// Needs a Redis server to share, e.g. `XTRADE_TEST_REDIS_URL=redis://127.0.0.1:6379`.
+    #[test]
+    #[ignore]
+    fn saves_of_others_are_refused_and_announced() {
+        let config = StorageConfig {
+            backend: StorageBackend::Redis,
+            redis_url: std::env::var("XTRADE_TEST_REDIS_URL").unwrap(),
+            key_prefix: format!("xtrade-test-{}", std::process::id()),
+            ..StorageConfig::default()
+        };
+        let store = RedisStore::connect(&config).unwrap();
+        assert_eq!(store.read().unwrap(), None);
+
+        let (changes, received) = std::sync::mpsc::channel();
+        let watcher = store.clone();
+        std::thread::spawn(move || watcher.watch(|| changes.send(()).is_ok()));
+        received.recv().unwrap();
+
+        assert!(store.write(b"{}", 1, |_| false).unwrap());
+        received.recv().unwrap();
+        assert!(!store
+            .write(b"{\"bots\":{}}", 2, |current| current != b"{}")
+            .unwrap());
+        assert!(store
+            .write(b"{\"bots\":{}}", 2, |current| current == b"{}")
+            .unwrap());
+        assert_eq!(store.read().unwrap().unwrap(), b"{\"bots\":{}}");
+        store
+            .query(|con| redis::cmd("DEL").arg(&store.key).exec(con))
+            .unwrap();
+    }
+}
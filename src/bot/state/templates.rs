@@ -0,0 +1,116 @@
+// src/bot/state/templates.rs
+//! # Message Templates
+//!
+//! The `msg` template of a listener, the alert message to paste into TradingView, can run to
+//! kilobytes. Listeners only keep the hex SHA-256 of theirs, as `msg_sha256`; the bodies are
+//! kept once each in the [`Templates`] of the state, and so by whichever `[storage]` backend
+//! keeps the state. Listeners sharing a template share its body.
+//!
+//! Lists of listeners and bots leave the bodies out, unless asked for them with
+//! `include=msg`; a listener's own view and exports carry its body inline. Listeners still
+//! holding their body inline, from older state files or from imports, have it moved to the
+//! templates when the state is loaded or saved, and bodies no listener refers to any more are
+//! dropped then.
+use crate::bot::model::{Bot, Listener};
+use crate::utils::crypto::sha256_hex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Message templates of listeners, by the hex SHA-256 of their body.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(transparent)]
+pub struct Templates(BTreeMap<String, String>);
+
+/// Hex SHA-256 a template is kept by; none for an empty template.
+pub fn address(body: &str) -> Option<String> {
+    (!body.is_empty()).then(|| sha256_hex(body.as_bytes()))
+}
+
+impl Templates {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Keep `body`, unless it is empty; returns its address.
+    pub fn insert(&mut self, body: &str) -> Option<String> {
+        let address = address(body)?;
+        self.0
+            .entry(address.clone())
+            .or_insert_with(|| body.to_string());
+        Some(address)
+    }
+
+    /// The template of `listener`, whether inline or kept here.
+    pub fn body<'a>(&'a self, listener: &'a Listener) -> Option<&'a str> {
+        if !listener.msg.is_empty() {
+            return Some(&listener.msg);
+        }
+        let address = listener.msg_sha256.as_ref()?;
+        self.0.get(address).map(String::as_str)
+    }
+
+    /// Put the template of each listener of `bot` inline, for exports.
+    pub fn inline(&self, bot: &mut Bot) {
+        for listener in bot.listeners.values_mut() {
+            if let Some(body) = self.body(listener) {
+                listener.msg = body.to_string();
+            }
+            listener.msg_sha256 = None;
+        }
+    }
+
+    /// Move the templates the listeners of `bots` hold inline here, and drop those no
+    /// listener refers to.
+    pub fn sync(&mut self, bots: &mut HashMap<String, Bot>) {
+        let mut used = HashSet::new();
+        for listener in bots.values_mut().flat_map(|bot| bot.listeners.values_mut()) {
+            if !listener.msg.is_empty() {
+                let body = std::mem::take(&mut listener.msg);
+                listener.msg_sha256 = self.insert(&body);
+            }
+            used.extend(listener.msg_sha256.clone());
+        }
+        self.0.retain(|address, _| used.contains(address));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn templates_are_kept_once_and_dropped_when_unused() {
+        let body = "{\"action\":\"{{strategy.order.action}}\"}";
+        let listener = |msg: &str| Listener {
+            msg: msg.to_string(),
+            ..Listener::default()
+        };
+        let mut bot = Bot::default();
+        bot.listeners.insert("tv".to_string(), listener(body));
+        bot.listeners.insert("copy".to_string(), listener(body));
+        bot.listeners.insert("bare".to_string(), listener(""));
+        let mut bots = HashMap::from([("alpha".to_string(), bot)]);
+
+        let mut templates = Templates::default();
+        templates.sync(&mut bots);
+        assert_eq!(templates.len(), 1);
+        let tv = &bots["alpha"].listeners["tv"];
+        assert!(tv.msg.is_empty());
+        assert_eq!(tv.msg_sha256, address(body));
+        assert_eq!(templates.body(tv), Some(body));
+        assert_eq!(bots["alpha"].listeners["bare"].msg_sha256, None);
+
+        let mut exported = bots["alpha"].clone();
+        templates.inline(&mut exported);
+        assert_eq!(exported.listeners["copy"].msg, body);
+        assert_eq!(exported.listeners["copy"].msg_sha256, None);
+
+        bots.get_mut("alpha").unwrap().listeners.clear();
+        templates.sync(&mut bots);
+        assert!(templates.is_empty());
+    }
+}
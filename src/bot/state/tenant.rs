@@ -0,0 +1,634 @@
+// src/bot/state/tenant.rs
+//! # Tenant Scoping
+//!
+//! In multi-tenant deployments every bot is stored under `<tenant>:<bot_id>`. Callers never
+//! see or send the prefix: [`TenantScope`] implements [`BotRegistry`] by namespacing the IDs
+//! in every input and stripping them from every output, and only ever reaches bots inside
+//! its own namespace. Listeners live inside their bot, so they are scoped along with it.
+//!
+//! A scope without a tenant addresses the whole state unchanged; it is used when tenancy is
+//! disabled and by offline mode.
+//!
+//! Scopes also enforce the tenant's [quotas](super::quota) when creating bots and listeners,
+//! [export](super::export) and import the tenant's bots, and apply batches of operations on
+//! them all or none.
+use crate::app_config::TenancyConfig;
+use crate::bot::allocation;
+use crate::bot::portfolio::Portfolio;
+use crate::bot::state::events::{Event, EventKind};
+use crate::bot::state::export::{self, StateExport};
+use crate::bot::state::quota::{self, MAX_BOTS, MAX_LISTENERS_PER_BOT};
+use crate::bot::state::{
+    AppState, Bot, BotDeleteArgs, BotGetArgs, BotInsertArgs, BotListArgs, BotListView, BotRegistry,
+    BotTimelineArgs, BotUpdateArgs, BotView, BulkOperation, BulkOutcome, BulkView, Listener,
+    ListenerDeleteArgs, ListenerGetArgs, ListenerInsertArgs, ListenerListArgs, ListenerListView,
+    ListenerRotateSecretArgs, ListenerUpdateArgs, ListenerView, ListenersDeleteArgs,
+    SecretRotationView, StateImportView, SymbolMapArgs, SymbolMapView, SymbolUnmapArgs,
+    TimelineView,
+};
+use crate::errors::AppError;
+use log::info;
+use std::path::PathBuf;
+
+/// A [`BotRegistry`] restricted to a single tenant's namespace.
+pub struct TenantScope<'a> {
+    state: &'a mut AppState,
+    tenant: Option<String>,
+}
+
+impl AppState {
+    /// Registry restricted to `tenant`; `None` addresses the whole state.
+    pub fn scoped(&mut self, tenant: Option<&str>) -> TenantScope<'_> {
+        TenantScope {
+            state: self,
+            tenant: tenant.map(str::to_string),
+        }
+    }
+}
+
+impl TenantScope<'_> {
+    /// Storage key for a caller-facing bot ID.
+    pub(crate) fn key(&self, bot_id: &str) -> String {
+        match &self.tenant {
+            Some(tenant) => format!("{}{}{}", tenant, TenancyConfig::SEPARATOR, bot_id),
+            None => bot_id.to_string(),
+        }
+    }
+
+    /// Caller-facing bot ID for a storage key.
+    fn strip(&self, key: &str) -> String {
+        match &self.tenant {
+            Some(tenant) => key
+                .strip_prefix(tenant.as_str())
+                .and_then(|rest| rest.strip_prefix(TenancyConfig::SEPARATOR))
+                .unwrap_or(key)
+                .to_string(),
+            None => key.to_string(),
+        }
+    }
+
+    /// Whether a storage key belongs to this scope.
+    fn owns(&self, key: &str) -> bool {
+        owned_by(self.tenant.as_deref(), key)
+    }
+
+    /// Caller-facing IDs of the bots in this scope, sorted.
+    pub(crate) fn bot_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self
+            .state
+            .bots
+            .keys()
+            .filter(|key| self.owns(key))
+            .map(|key| self.strip(key))
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    /// Portfolio of the bot `bot_id`, if it ever traded.
+    pub(crate) fn portfolio(&self, bot_id: &str) -> Option<&Portfolio> {
+        self.state.portfolios.get(&self.key(bot_id))
+    }
+
+    /// Count a trade against the tenant's daily trade quota.
+    pub fn record_trade(&mut self) -> Result<(), AppError> {
+        let limit = self
+            .state
+            .quotas
+            .limits_for(self.tenant.as_deref())
+            .max_trades_per_day;
+        let today = self.state.providers.clock.now().date_naive();
+        self.state
+            .trades
+            .record(self.tenant.as_deref(), today, limit)
+    }
+
+    /// Timeline events of the scope's bots newer than `after`, oldest first, at most `limit`
+    /// of them, with the bot IDs the tenant knows.
+    pub fn events_after(&self, after: u64, limit: usize) -> Vec<Event> {
+        (self.state.events.after(after))
+            .filter(|event| self.owns(&event.bot_id))
+            .take(limit)
+            .map(|event| Event {
+                bot_id: self.strip(&event.bot_id),
+                ..event.clone()
+            })
+            .collect()
+    }
+
+    /// State changes of the scope's bots newer than `after`, oldest first, at most `limit`
+    /// of them, and the sequence number the log was read up to.
+    pub fn changes_after(&self, after: u64, limit: usize) -> (Vec<Event>, u64) {
+        let mut read = after.min(self.state.events.last_seq());
+        let mut changes = Vec::new();
+        for event in self.state.events.after(read) {
+            if changes.len() == limit {
+                break;
+            }
+            read = event.seq;
+            if event.kind == EventKind::StateChange && self.owns(&event.bot_id) {
+                changes.push(Event {
+                    bot_id: self.strip(&event.bot_id),
+                    ..event.clone()
+                });
+            }
+        }
+        (changes, read)
+    }
+
+    /// The bots of this scope, without their secrets if `redact`.
+    pub fn export_bots(&self, redact: bool) -> StateExport {
+        let mut export = StateExport {
+            exported_at: Some(self.state.providers.clock.now()),
+            bots: (self.state.bots.iter())
+                .filter(|(key, _)| self.owns(key))
+                .map(|(key, bot)| {
+                    let bot_id = self.strip(key);
+                    let mut bot = Bot {
+                        bot_id: bot_id.clone(),
+                        ..bot.clone()
+                    };
+                    self.state.templates.inline(&mut bot);
+                    (bot_id, bot)
+                })
+                .collect(),
+        };
+        if redact {
+            export.redact();
+        }
+        export
+    }
+
+    /// Add the bots of `export` to this scope, replacing those with the same ID; with
+    /// `replace`, delete those it does not hold as well. Nothing changes if any bot fails
+    /// the quotas.
+    pub fn import_bots(
+        &mut self,
+        export: StateExport,
+        replace: bool,
+    ) -> Result<StateImportView, AppError> {
+        for bot_id in export.bots.keys() {
+            self.validate_bot_id(bot_id)?;
+        }
+        let limits = self.state.quotas.limits_for(self.tenant.as_deref());
+        let owned = self.bot_ids();
+        let kept = if replace {
+            0
+        } else {
+            (owned.iter())
+                .filter(|id| !export.bots.contains_key(*id))
+                .count()
+        };
+        let total = kept + export.bots.len();
+        if total > owned.len() {
+            quota::check(
+                MAX_BOTS,
+                limits.max_bots.map(|n| n as u64),
+                total as u64 - 1,
+            )?;
+        }
+        for bot in export.bots.values().filter(|bot| !bot.listeners.is_empty()) {
+            quota::check(
+                MAX_LISTENERS_PER_BOT,
+                limits.max_listeners_per_bot.map(|n| n as u64),
+                bot.listeners.len() as u64 - 1,
+            )?;
+        }
+
+        let mut view = StateImportView::default();
+        if replace {
+            for bot_id in owned.iter().filter(|id| !export.bots.contains_key(*id)) {
+                let key = self.key(bot_id);
+                self.state.bots.remove(&key);
+                self.state
+                    .record_change(&key, "Bot deleted by import".to_string());
+                view.removed.push(bot_id.clone());
+            }
+        }
+        let mut warnings = Vec::new();
+        for (bot_id, mut bot) in export.bots {
+            let key = self.key(&bot_id);
+            let existing = self.state.bots.get(&key);
+            let restored = export::restore_secrets(&mut bot, existing);
+            let summary = match existing {
+                Some(_) => {
+                    view.updated.push(bot_id.clone());
+                    "Bot replaced by import".to_string()
+                }
+                None => {
+                    view.added.push(bot_id.clone());
+                    format!("Bot imported on {}", bot.exchange)
+                }
+            };
+            bot.bot_id = key.clone();
+            let bot_warnings = (bot.warnings().into_iter())
+                .chain(allocation::warnings(&bot, &self.state.allocation))
+                .chain(restored)
+                .chain(bot.listeners.iter().flat_map(|(listener_id, listener)| {
+                    (listener.warnings().into_iter())
+                        .map(move |warning| format!("listener '{}': {}", listener_id, warning))
+                }));
+            warnings.extend(bot_warnings.map(|warning| format!("bot '{}': {}", bot_id, warning)));
+            self.state.bots.insert(key.clone(), bot);
+            self.state.record_change(&key, summary);
+        }
+        self.state.save::<PathBuf>(None)?;
+        self.state.warnings = warnings;
+        info!(
+            "Imported {} bot(s), deleted {}.",
+            view.added.len() + view.updated.len(),
+            view.removed.len()
+        );
+        Ok(view)
+    }
+
+    /// Apply `operations` in order, all or none: if one fails, the bots, their listeners and
+    /// the timeline are put back as they were, and the state is saved once if all succeed.
+    /// Warnings of each operation are kept, led by its index.
+    pub fn bulk(&mut self, operations: Vec<BulkOperation>) -> Result<BulkView, AppError> {
+        let bots = self.state.bots.clone();
+        let templates = self.state.templates.clone();
+        let events = self.state.events.clone();
+        let deferred = std::mem::replace(&mut self.state.persistence.deferred, true);
+
+        let mut view = BulkView::default();
+        let mut warnings = Vec::new();
+        let mut applied = Ok(());
+        for (index, operation) in operations.into_iter().enumerate() {
+            match self.apply(operation) {
+                Ok(outcome) => view.applied.push(outcome),
+                Err(e) => {
+                    applied = Err(AppError::BulkOperationFailed {
+                        index,
+                        source: Box::new(e),
+                    });
+                    break;
+                }
+            }
+            let taken = self.take_warnings().into_iter();
+            warnings.extend(taken.map(|warning| format!("operation {}: {}", index, warning)));
+        }
+        self.state.persistence.deferred = deferred;
+        if let Err(e) = applied {
+            self.state.bots = bots;
+            self.state.templates = templates;
+            self.state.events = events;
+            return Err(e);
+        }
+        if !deferred {
+            self.state.save_deferred()?;
+        }
+        self.state.warnings = warnings;
+        info!("Applied {} bulk operation(s).", view.applied.len());
+        Ok(view)
+    }
+
+    fn apply(&mut self, operation: BulkOperation) -> Result<BulkOutcome, AppError> {
+        let op = operation.name().to_string();
+        let (bot_id, listener_id) = match operation {
+            BulkOperation::Insert(args) => (self.add_bot(args)?.bot_id, None),
+            BulkOperation::Update(args) => (self.update_bot(args)?.bot_id, None),
+            BulkOperation::Delete(args) => (self.delete_bot(args)?.bot_id, None),
+            BulkOperation::InsertListener(args) => {
+                let view = self.add_listener(args)?;
+                (view.bot_id, Some(view.listener_id))
+            }
+            BulkOperation::UpdateListener(args) => {
+                let view = self.update_listener(args)?;
+                (view.bot_id, Some(view.listener_id))
+            }
+            BulkOperation::DeleteListener(args) => {
+                let view = self.delete_listener(args)?;
+                (view.bot_id, Some(view.listener_id))
+            }
+        };
+        Ok(BulkOutcome {
+            op,
+            bot_id,
+            listener_id,
+        })
+    }
+
+    fn bot_view(&self, mut view: BotView) -> BotView {
+        view.bot_id = self.strip(&view.bot_id);
+        view
+    }
+
+    fn listener_view(&self, mut view: ListenerView) -> ListenerView {
+        view.bot_id = self.strip(&view.bot_id);
+        view
+    }
+
+    fn listener_list_view(&self, list: ListenerListView) -> ListenerListView {
+        ListenerListView(
+            list.into_iter()
+                .map(|view| self.listener_view(view))
+                .collect(),
+        )
+    }
+}
+
+/// Whether `key` lies in `tenant`'s namespace; every key belongs to the unscoped state.
+fn owned_by(tenant: Option<&str>, key: &str) -> bool {
+    tenant.is_none_or(|tenant| {
+        key.strip_prefix(tenant)
+            .is_some_and(|rest| rest.starts_with(TenancyConfig::SEPARATOR))
+    })
+}
+
+impl BotRegistry for TenantScope<'_> {
+    fn get_bot_mut(&mut self, bot_id: &str) -> Result<&mut Bot, AppError> {
+        let key = self.key(bot_id);
+        self.state.get_bot_mut(&key)
+    }
+
+    fn get_bot_ref(&self, bot_id: &str) -> Result<&Bot, AppError> {
+        self.state.get_bot_ref(&self.key(bot_id))
+    }
+
+    fn add_bot(&mut self, mut args: BotInsertArgs) -> Result<BotView, AppError> {
+        let limits = self.state.quotas.limits_for(self.tenant.as_deref());
+        let used = self.state.bots.keys().filter(|key| self.owns(key)).count();
+        quota::check(MAX_BOTS, limits.max_bots.map(|n| n as u64), used as u64)?;
+
+        let bot_id = args
+            .bot_id
+            .take()
+            .unwrap_or_else(|| self.state.providers.ids.next_id());
+        args.bot_id = Some(self.key(&bot_id));
+        let view = self.state.add_bot(args)?;
+        Ok(self.bot_view(view))
+    }
+
+    fn list_bots(&self, args: Option<BotListArgs>) -> Result<BotListView, AppError> {
+        let mut args = args.unwrap_or_default();
+        args.bot_id = args.bot_id.map(|id| self.key(&id));
+
+        let bots: Vec<BotView> = self
+            .state
+            .bots
+            .iter()
+            .filter(|(key, bot)| self.owns(key) && args.matches(bot))
+            .map(|(_, bot)| self.bot_view(bot.clone().into()))
+            .collect();
+        if bots.is_empty() {
+            return Err(AppError::NotFound("No bots found.".to_string()));
+        }
+        Ok(BotListView(bots))
+    }
+
+    fn get_bot(&self, mut args: BotGetArgs) -> Result<BotView, AppError> {
+        args.bot_id = self.key(&args.bot_id);
+        Ok(self.bot_view(self.state.get_bot(args)?))
+    }
+
+    fn update_bot(&mut self, mut args: BotUpdateArgs) -> Result<BotView, AppError> {
+        args.bot_id = self.key(&args.bot_id);
+        let view = self.state.update_bot(args)?;
+        Ok(self.bot_view(view))
+    }
+
+    fn delete_bot(&mut self, mut args: BotDeleteArgs) -> Result<BotView, AppError> {
+        args.bot_id = self.key(&args.bot_id);
+        let view = self.state.delete_bot(args)?;
+        Ok(self.bot_view(view))
+    }
+
+    fn bot_timeline(&self, mut args: BotTimelineArgs) -> Result<TimelineView, AppError> {
+        args.bot_id = self.key(&args.bot_id);
+        let mut timeline = self.state.bot_timeline(args)?;
+        for event in &mut timeline.events {
+            event.bot_id = self.strip(&event.bot_id);
+        }
+        Ok(timeline)
+    }
+
+    fn validate_bot_id(&self, bot_id: &str) -> Result<(), AppError> {
+        self.state.validate_bot_id(bot_id)
+    }
+
+    fn map_symbol(&mut self, mut args: SymbolMapArgs) -> Result<SymbolMapView, AppError> {
+        args.bot_id = self.key(&args.bot_id);
+        let mut view = self.state.map_symbol(args)?;
+        view.bot_id = self.strip(&view.bot_id);
+        Ok(view)
+    }
+
+    fn unmap_symbol(&mut self, mut args: SymbolUnmapArgs) -> Result<SymbolMapView, AppError> {
+        args.bot_id = self.key(&args.bot_id);
+        let mut view = self.state.unmap_symbol(args)?;
+        view.bot_id = self.strip(&view.bot_id);
+        Ok(view)
+    }
+
+    fn get_listener_mut(
+        &mut self,
+        bot_id: &str,
+        listener_id: &str,
+    ) -> Result<&mut Listener, AppError> {
+        let key = self.key(bot_id);
+        self.state.get_listener_mut(&key, listener_id)
+    }
+
+    fn get_listener_ref(&self, bot_id: &str, listener_id: &str) -> Result<&Listener, AppError> {
+        self.state.get_listener_ref(&self.key(bot_id), listener_id)
+    }
+
+    fn add_listener(&mut self, mut args: ListenerInsertArgs) -> Result<ListenerView, AppError> {
+        args.bot_id = self.key(&args.bot_id);
+        let limits = self.state.quotas.limits_for(self.tenant.as_deref());
+        let used = self.state.get_bot_ref(&args.bot_id)?.listeners.len();
+        quota::check(
+            MAX_LISTENERS_PER_BOT,
+            limits.max_listeners_per_bot.map(|n| n as u64),
+            used as u64,
+        )?;
+        let view = self.state.add_listener(args)?;
+        Ok(self.listener_view(view))
+    }
+
+    fn list_listeners(&self, mut args: ListenerListArgs) -> Result<ListenerListView, AppError> {
+        self.validate_bot_id(&args.bot_id)?;
+        args.bot_id = self.key(&args.bot_id);
+        Ok(self.listener_list_view(self.state.list_listeners(args)?))
+    }
+
+    fn list_all_listeners(&self, args: ListenerListArgs) -> Result<ListenerListView, AppError> {
+        let list = self.state.listeners_where(&args, |key| self.owns(key))?;
+        Ok(self.listener_list_view(list))
+    }
+
+    fn get_listener(&self, mut args: ListenerGetArgs) -> Result<ListenerView, AppError> {
+        args.bot_id = self.key(&args.bot_id);
+        Ok(self.listener_view(self.state.get_listener(args)?))
+    }
+
+    fn update_listener(&mut self, mut args: ListenerUpdateArgs) -> Result<ListenerView, AppError> {
+        args.bot_id = self.key(&args.bot_id);
+        let view = self.state.update_listener(args)?;
+        Ok(self.listener_view(view))
+    }
+
+    fn delete_listener(&mut self, mut args: ListenerDeleteArgs) -> Result<ListenerView, AppError> {
+        args.bot_id = self.key(&args.bot_id);
+        let view = self.state.delete_listener(args)?;
+        Ok(self.listener_view(view))
+    }
+
+    fn delete_listeners(
+        &mut self,
+        mut args: ListenersDeleteArgs,
+    ) -> Result<ListenerListView, AppError> {
+        args.bot_id = self.key(&args.bot_id);
+        let list = self.state.delete_listeners(args)?;
+        Ok(self.listener_list_view(list))
+    }
+
+    fn take_warnings(&mut self) -> Vec<String> {
+        self.state.take_warnings()
+    }
+
+    fn rotate_listener_secret(
+        &mut self,
+        mut args: ListenerRotateSecretArgs,
+    ) -> Result<SecretRotationView, AppError> {
+        args.bot_id = self.key(&args.bot_id);
+        let mut view = self.state.rotate_listener_secret(args)?;
+        view.bot_id = self.strip(&view.bot_id);
+        view.webhook_url = SecretRotationView::webhook_path(
+            &view.bot_id,
+            &view.listener_id,
+            &view.secret,
+            self.tenant.as_deref(),
+        );
+        Ok(view)
+    }
+
+    fn clear_bots(&mut self) -> Result<(), AppError> {
+        if self.tenant.is_none() {
+            return self.state.clear_bots();
+        }
+        let owned: Vec<String> = self
+            .state
+            .bots
+            .keys()
+            .filter(|key| self.owns(key))
+            .cloned()
+            .collect();
+        for key in owned {
+            self.state.bots.remove(&key);
+            self.state
+                .record_change(&key, "Bot deleted (all bots cleared)".to_string());
+        }
+        self.state.save::<PathBuf>(None)?;
+        info!(
+            "Successfully cleared all bots of tenant '{}'.",
+            self.tenant.as_deref().unwrap_or_default()
+        );
+        Ok(())
+    }
+
+    fn clear_listeners(&mut self) -> Result<(), AppError> {
+        if self.tenant.is_none() {
+            return self.state.clear_listeners();
+        }
+        let mut cleared = Vec::new();
+        for (key, bot) in self.state.bots.iter_mut() {
+            if owned_by(self.tenant.as_deref(), key) {
+                bot.listeners.clear();
+                cleared.push(key.clone());
+            }
+        }
+        for key in cleared {
+            self.state
+                .record_change(&key, "All listeners cleared".to_string());
+        }
+        self.state.save::<PathBuf>(None)?;
+        info!(
+            "Successfully cleared all listeners of tenant '{}'.",
+            self.tenant.as_deref().unwrap_or_default()
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app_config::AppConfig;
+    use tempfile::tempdir;
+
+    #[test]
+    fn tenants_cannot_address_each_other() {
+        let dir = tempdir().unwrap();
+        let mut config = AppConfig::default();
+        config.api_server.state_file = dir.path().join("state.json");
+        let mut state = AppState::load(config).unwrap();
+
+        let bot = state
+            .scoped(Some("acme"))
+            .add_bot(
+                BotInsertArgs::new("bot".to_string(), "binance".to_string())
+                    .bot_id(Some("shared".to_string())),
+            )
+            .unwrap();
+        assert_eq!(bot.bot_id, "shared");
+        assert!(state.bots.contains_key("acme:shared"));
+
+        // The same caller-facing ID is free in another tenant's namespace
+        state
+            .scoped(Some("globex"))
+            .add_bot(
+                BotInsertArgs::new("bot".to_string(), "bybit".to_string())
+                    .bot_id(Some("shared".to_string())),
+            )
+            .unwrap();
+
+        let mut globex = state.scoped(Some("globex"));
+        assert_eq!(globex.list_bots(None).unwrap().0.len(), 1);
+        assert_eq!(
+            globex.get_bot(BotGetArgs::new("shared")).unwrap().exchange,
+            "bybit"
+        );
+        // Addressing another tenant's storage key stays inside our namespace
+        assert!(globex.get_bot(BotGetArgs::new("acme:shared")).is_err());
+        globex.clear_bots().unwrap();
+
+        let acme = state.scoped(Some("acme"));
+        assert_eq!(acme.list_bots(None).unwrap().0[0].bot_id, "shared");
+        assert_eq!(state.bots.len(), 1);
+    }
+
+    #[test]
+    fn quotas_are_enforced_per_tenant() {
+        let dir = tempdir().unwrap();
+        let mut config = AppConfig::default();
+        config.api_server.state_file = dir.path().join("state.json");
+        config.quotas.default.max_bots = Some(1);
+        config.quotas.default.max_listeners_per_bot = Some(1);
+        let mut state = AppState::load(config).unwrap();
+
+        let new_bot = || BotInsertArgs::new("bot".to_string(), "binance".to_string());
+        let bot_id = state
+            .scoped(Some("acme"))
+            .add_bot(new_bot())
+            .unwrap()
+            .bot_id;
+        let err = state.scoped(Some("acme")).add_bot(new_bot()).unwrap_err();
+        assert!(matches!(
+            err,
+            AppError::QuotaExceeded {
+                quota: MAX_BOTS,
+                limit: 1,
+                used: 1
+            }
+        ));
+        // Another tenant's bots do not count against acme's quota
+        state.scoped(Some("globex")).add_bot(new_bot()).unwrap();
+
+        let mut acme = state.scoped(Some("acme"));
+        let listener = || ListenerInsertArgs::new(&bot_id, "tradingview");
+        acme.add_listener(listener()).unwrap();
+        assert!(acme.add_listener(listener()).is_err());
+    }
+}
@@ -0,0 +1,402 @@
+// src/bot/statement.rs
+//! # Monthly Statements
+//!
+//! A statement sums up a bot's trading in one month as a self-contained HTML page: totals,
+//! the [performance](crate::bot::performance) of each listener, and every trade. Positions
+//! already open when the month began count from their first trade in it.
+//!
+//! `GET /bots/{bot_id}/statements/{month}` renders one on demand. With `statements.enabled`
+//! the server also writes each bot's statement once a month is over,
+//!
+//! ```text
+//! <state_file>.statements/<bot_id>/<YYYY-MM>.html
+//! ```
+//!
+//! optionally renders it as PDF with `statements.pdf_command`, and with `statements.notify`
+//! attaches it to a notification.
+use crate::app_config::{NotificationConfig, StatementConfig};
+use crate::app_state::AppState;
+use crate::bot::dispatch::ExecutionStatus;
+use crate::bot::notify::{self, Attachment, Notification};
+use crate::bot::performance::StrategyReport;
+use crate::bot::state::history::{self, TradeHistory, TradeRecord};
+use crate::bot::state::StrategyPerformance;
+use chrono::{DateTime, Datelike, Months, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How often the server checks for statements to write.
+const CHECK_INTERVAL: Duration = Duration::from_secs(3600);
+
+const STYLE: &str = "body{font-family:system-ui,sans-serif;margin:2em;color:#222}\
+table{border-collapse:collapse;margin-bottom:2em}\
+th,td{border-bottom:1px solid #ddd;padding:4px 10px;text-align:left}\
+td.n{text-align:right;font-variant-numeric:tabular-nums}\
+.failed{color:#b00}";
+
+/// A bot's trading in one month.
+#[derive(Debug)]
+pub struct Statement {
+    pub bot_id: String,
+    pub exchange: String,
+    /// `YYYY-MM`
+    pub month: String,
+    pub generated_at: DateTime<Utc>,
+    trades: Vec<TradeRecord>,
+    strategies: Vec<StrategyPerformance>,
+}
+
+impl Statement {
+    /// The statement for `month` of the bot stored as `key`, named `bot_id` for its reader.
+    pub fn load(
+        history: &TradeHistory,
+        key: &str,
+        bot_id: &str,
+        exchange: &str,
+        month: &str,
+        now: DateTime<Utc>,
+    ) -> io::Result<Self> {
+        let trades = history.load_month(key, month)?;
+        let mut report = StrategyReport::default();
+        for trade in &trades {
+            report.record(trade);
+        }
+        Ok(Self {
+            bot_id: bot_id.to_string(),
+            exchange: exchange.to_string(),
+            month: month.to_string(),
+            generated_at: now,
+            strategies: report.strategies(bot_id),
+            trades,
+        })
+    }
+
+    /// The statement as a standalone HTML page.
+    pub fn to_html(&self) -> String {
+        let title = format!(
+            "Statement of bot {} for {}",
+            self.bot_id,
+            month_name(&self.month)
+        );
+        let mut html = String::new();
+        let _ = write!(
+            html,
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>{title}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n\
+             <h1>{title}</h1>\n<p>Exchange {}. Generated {}.</p>\n",
+            escape(&self.exchange),
+            self.generated_at.format("%Y-%m-%d %H:%M UTC"),
+            title = escape(&title),
+        );
+
+        let executed: Vec<&TradeRecord> = self
+            .trades
+            .iter()
+            .filter(|trade| trade.result.status == ExecutionStatus::Executed)
+            .collect();
+        let failed = self
+            .trades
+            .iter()
+            .filter(|trade| trade.result.status == ExecutionStatus::Failed)
+            .count();
+        let reports = executed
+            .iter()
+            .filter_map(|trade| trade.result.report.as_ref());
+        let volume: Decimal = reports
+            .clone()
+            .filter_map(|report| Some(report.filled_quantity? * report.average_price?))
+            .sum();
+        let fees: Decimal = reports.filter_map(|report| report.fees).sum();
+        let net_pnl: Decimal = self.strategies.iter().map(|s| s.net_pnl).sum();
+        html.push_str("<h2>Summary</h2>\n<table>\n");
+        for (label, value) in [
+            ("Orders", self.trades.len().to_string()),
+            ("Executed", executed.len().to_string()),
+            ("Failed", failed.to_string()),
+            ("Volume", volume.round_dp(2).normalize().to_string()),
+            ("Fees", fees.round_dp(8).normalize().to_string()),
+            ("Net PnL", net_pnl.round_dp(2).normalize().to_string()),
+        ] {
+            let _ = writeln!(
+                html,
+                "<tr><th>{}</th><td class=\"n\">{}</td></tr>",
+                label, value
+            );
+        }
+        html.push_str("</table>\n");
+
+        if !self.strategies.is_empty() {
+            html.push_str(
+                "<h2>Strategies</h2>\n<table>\n<tr><th>Listener</th><th>Trades</th>\
+                 <th>Round Trips</th><th>Win Rate</th><th>Profit Factor</th><th>Net PnL</th>\
+                 <th>Max Drawdown</th><th>Sharpe</th></tr>\n",
+            );
+            let or_dash = |value: Option<Decimal>| value.map_or("-".to_string(), |v| v.to_string());
+            for strategy in &self.strategies {
+                let win_rate = strategy
+                    .win_rate
+                    .map(|rate| format!("{}%", (rate * Decimal::ONE_HUNDRED).round_dp(1)));
+                let _ = writeln!(
+                    html,
+                    "<tr><td>{}</td><td class=\"n\">{}</td><td class=\"n\">{}</td>\
+                     <td class=\"n\">{}</td><td class=\"n\">{}</td><td class=\"n\">{}</td>\
+                     <td class=\"n\">{}</td><td class=\"n\">{}</td></tr>",
+                    escape(&strategy.listener_id),
+                    strategy.trades,
+                    strategy.round_trips,
+                    win_rate.as_deref().unwrap_or("-"),
+                    or_dash(strategy.profit_factor),
+                    strategy.net_pnl,
+                    strategy.max_drawdown,
+                    or_dash(strategy.sharpe),
+                );
+            }
+            html.push_str("</table>\n");
+        }
+
+        html.push_str("<h2>Trades</h2>\n");
+        if self.trades.is_empty() {
+            html.push_str("<p>No trades this month.</p>\n");
+        } else {
+            html.push_str(
+                "<table>\n<tr><th>Finished</th><th>Listener</th><th>Action</th><th>Symbol</th>\
+                 <th>Quantity</th><th>Price</th><th>Fee</th><th>Status</th><th>Message</th></tr>\n",
+            );
+            for trade in &self.trades {
+                let result = &trade.result;
+                let report = result.report.as_ref();
+                let value =
+                    |field: Option<Decimal>| field.map_or("-".to_string(), |v| v.to_string());
+                let class = if result.status == ExecutionStatus::Failed {
+                    " class=\"failed\""
+                } else {
+                    ""
+                };
+                let _ = writeln!(
+                    html,
+                    "<tr{}><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td class=\"n\">{}</td>\
+                     <td class=\"n\">{}</td><td class=\"n\">{}</td><td>{}</td><td>{}</td></tr>",
+                    class,
+                    result.finished_at.format("%Y-%m-%d %H:%M:%S"),
+                    escape(&result.listener_id),
+                    escape(&result.action),
+                    escape(&result.symbol),
+                    report
+                        .and_then(|r| r.filled_quantity)
+                        .map_or_else(|| escape(&result.order_size), |q| q.to_string()),
+                    value(report.and_then(|r| r.average_price)),
+                    value(report.and_then(|r| r.fees)),
+                    result.status,
+                    escape(&result.message),
+                );
+            }
+            html.push_str("</table>\n");
+        }
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+}
+
+/// `month` (`YYYY-MM`) if it is one.
+pub fn parse_month(month: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d").ok()
+}
+
+/// `YYYY-MM` as e.g. `September 2026`.
+fn month_name(month: &str) -> String {
+    parse_month(month).map_or_else(
+        || month.to_string(),
+        |date| date.format("%B %Y").to_string(),
+    )
+}
+
+/// The month before the one `now` is in.
+fn previous_month(now: DateTime<Utc>) -> String {
+    let first = now.date_naive().with_day(1).unwrap_or(now.date_naive());
+    (first - Months::new(1)).format("%Y-%m").to_string()
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Where the statement of the bot stored as `key` for `month` is written.
+fn statement_path(state_file: &Path, key: &str, month: &str) -> PathBuf {
+    let mut dir = state_file.as_os_str().to_owned();
+    dir.push(".statements");
+    PathBuf::from(dir)
+        .join(history::dir_name(key))
+        .join(format!("{}.html", month))
+}
+
+/// Write the statements of every bot for the previous month as soon as it is over.
+pub async fn run(
+    app_state: Arc<Mutex<AppState>>,
+    config: StatementConfig,
+    notifications: NotificationConfig,
+) {
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(e) = write_due(&app_state, &config, &notifications).await {
+            log::error!("Failed to write monthly statements: {}", e);
+        }
+    }
+}
+
+/// Write the previous month's statement of each bot that traded then and has none yet.
+async fn write_due(
+    app_state: &Mutex<AppState>,
+    config: &StatementConfig,
+    notifications: &NotificationConfig,
+) -> io::Result<()> {
+    let (now, state_file, bots) = {
+        let state = app_state
+            .lock()
+            .map_err(|_| io::Error::other("Failed to acquire lock on AppState"))?;
+        let bots: Vec<(String, String)> = state
+            .bots
+            .iter()
+            .map(|(key, bot)| (key.clone(), bot.exchange.clone()))
+            .collect();
+        (state.providers.clock.now(), state.state_file.clone(), bots)
+    };
+    let month = previous_month(now);
+    let history = TradeHistory::for_state_file(&state_file);
+    for (key, exchange) in bots {
+        let path = statement_path(&state_file, &key, &month);
+        if path.exists() || !history.months(&key)?.contains(&month) {
+            continue;
+        }
+        let statement = Statement::load(&history, &key, &key, &exchange, &month, now)?;
+        let html = statement.to_html();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, &html)?;
+        log::info!("Wrote the {} statement of bot {} to {:?}", month, key, path);
+
+        if let Some(command) = &config.pdf_command {
+            render_pdf(command, &path).await;
+        }
+        if config.notify {
+            let notification = Notification::new(
+                &format!("xtrade statement of bot {} for {}", key, month_name(&month)),
+                &format!(
+                    "{} orders; see the attached statement.",
+                    statement.trades.len()
+                ),
+            )
+            .with_attachment(Attachment {
+                filename: format!("{}-{}.html", history::dir_name(&key), month),
+                content_type: "text/html".to_string(),
+                content: html,
+            });
+            notify::send(notifications, &notification).await;
+        }
+    }
+    Ok(())
+}
+
+/// Run `command` to render the statement at `html` as a PDF next to it. Failures are logged:
+/// the HTML statement is what counts.
+async fn render_pdf(command: &str, html: &Path) {
+    let pdf = html.with_extension("pdf");
+    let words: Vec<String> = command
+        .split_whitespace()
+        .map(|word| {
+            word.replace("{html}", &html.to_string_lossy())
+                .replace("{pdf}", &pdf.to_string_lossy())
+        })
+        .collect();
+    let Some((program, args)) = words.split_first() else {
+        return;
+    };
+    match tokio::process::Command::new(program)
+        .args(args)
+        .status()
+        .await
+    {
+        Ok(status) if status.success() => log::info!("Rendered {:?}", pdf),
+        Ok(status) => log::error!("PDF rendering of {:?} failed: {}", html, status),
+        Err(e) => log::error!("Failed to run {}: {}", program, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::dispatch::ExecutionResult;
+    use crate::exchange::ExecutionReport;
+    use chrono::TimeZone;
+    use rust_decimal::dec;
+
+    fn trade(status: ExecutionStatus, message: &str) -> TradeRecord {
+        TradeRecord {
+            result: ExecutionResult {
+                order_id: None,
+                bot_id: "alpha".to_string(),
+                listener_id: "tv".to_string(),
+                exchange: "binance".to_string(),
+                account: None,
+                action: "buy".to_string(),
+                symbol: "BTCUSDT".to_string(),
+                order_size: "0.5".to_string(),
+                status,
+                message: message.to_string(),
+                report: (status == ExecutionStatus::Executed).then(|| ExecutionReport {
+                    filled_quantity: Some(dec!(0.5)),
+                    average_price: Some(dec!(40000)),
+                    fees: Some(dec!(2)),
+                    ..Default::default()
+                }),
+                duplicate: false,
+                error_code: None,
+                warnings: Vec::new(),
+                latency_ms: None,
+                finished_at: Utc.with_ymd_and_hms(2026, 9, 14, 12, 0, 0).unwrap(),
+            },
+            order: None,
+        }
+    }
+
+    #[test]
+    fn statements_sum_up_a_month() {
+        let statement = Statement {
+            bot_id: "alpha".to_string(),
+            exchange: "binance".to_string(),
+            month: "2026-09".to_string(),
+            generated_at: Utc.with_ymd_and_hms(2026, 10, 1, 0, 0, 0).unwrap(),
+            trades: vec![
+                trade(ExecutionStatus::Executed, "order accepted by the exchange"),
+                trade(ExecutionStatus::Failed, "Insufficient <balance>"),
+            ],
+            strategies: Vec::new(),
+        };
+        let html = statement.to_html();
+        assert!(html.contains("<title>Statement of bot alpha for September 2026</title>"));
+        assert!(html.contains("<th>Volume</th><td class=\"n\">20000</td>"));
+        assert!(html.contains("<th>Failed</th><td class=\"n\">1</td>"));
+        assert!(html.contains("Insufficient &lt;balance&gt;"));
+
+        let now = Utc.with_ymd_and_hms(2026, 1, 3, 8, 0, 0).unwrap();
+        assert_eq!(previous_month(now), "2025-12");
+        assert!(parse_month("2026-13").is_none());
+    }
+}
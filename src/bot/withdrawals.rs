@@ -0,0 +1,224 @@
+// src/bot/withdrawals.rs
+//! # Withdrawal Monitor
+//!
+//! A stolen API key usually shows first as a withdrawal. With `withdrawals.enabled`, the
+//! server asks the exchange of every bot with credentials for the deposits and withdrawals of
+//! its account every `withdrawals.interval_secs`, and keeps them in the
+//! [ledger](crate::bot::state::ledger). A withdrawal not seen before, to an address not among
+//! `withdrawals.allowed_addresses`, is flagged on the bot's timeline and sent as an operator
+//! notification.
+//!
+//! The monitor only reads: it never cancels a withdrawal or disables a key. Venues that
+//! cannot list transfers are skipped.
+use crate::app_config::{NotificationConfig, WithdrawalMonitorConfig};
+use crate::app_state::AppState;
+use crate::bot::model::Bot;
+use crate::bot::notify::{self, Notification};
+use crate::bot::secrets::Secrets;
+use crate::bot::state::events::EventKind;
+use crate::exchange::{self, Transfer, TransferKind};
+use chrono::{DateTime, Duration, Utc};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Check the bots' withdrawals every `config.interval_secs`.
+pub async fn run(
+    app_state: Arc<Mutex<AppState>>,
+    config: WithdrawalMonitorConfig,
+    notifications: NotificationConfig,
+) {
+    let client = reqwest::Client::new();
+    let mut interval =
+        tokio::time::interval(std::time::Duration::from_secs(config.interval_secs.max(1)));
+    loop {
+        interval.tick().await;
+        check(&app_state, &config, &notifications, &client).await;
+    }
+}
+
+/// Fetch the transfers of every bot with credentials, record them and raise the alarm on
+/// unexpected withdrawals.
+async fn check(
+    app_state: &Mutex<AppState>,
+    config: &WithdrawalMonitorConfig,
+    notifications: &NotificationConfig,
+    client: &reqwest::Client,
+) {
+    let Ok((now, mut bots)) = app_state.lock().map(|state| {
+        let bots: Vec<Bot> = (state.bots.values())
+            .filter(|bot| bot.api_key.is_some() && !bot.dry_run)
+            .cloned()
+            .collect();
+        (state.providers.clock.now(), bots)
+    }) else {
+        log::error!("Failed to acquire lock on AppState; skipping the withdrawal check.");
+        return;
+    };
+    bots.sort_by(|a, b| a.bot_id.cmp(&b.bot_id));
+    let since = now - Duration::hours(config.lookback_hours as i64);
+
+    let mut results = Vec::with_capacity(bots.len());
+    for bot in &bots {
+        match fetch(bot, since, client).await {
+            Ok(Some(transfers)) => results.push((bot.bot_id.clone(), transfers)),
+            Ok(None) => {}
+            Err(e) => log::warn!(
+                "Failed to fetch the withdrawals of bot '{}': {}",
+                bot.bot_id,
+                e
+            ),
+        }
+    }
+    if results.is_empty() {
+        return;
+    }
+
+    let Ok(unexpected) = app_state
+        .lock()
+        .map(|mut state| apply(&mut state, config, results, since))
+    else {
+        log::error!("Failed to acquire lock on AppState; withdrawals are not recorded.");
+        return;
+    };
+    for (bot_id, withdrawal) in unexpected {
+        let notification = Notification::new(
+            "xtrade saw an unexpected withdrawal",
+            &format!("Bot '{}': {}", bot_id, describe(&withdrawal)),
+        );
+        notify::send(notifications, &notification).await;
+    }
+}
+
+/// Transfers of the account of `bot` since `since`; `None` if its venue cannot list them.
+async fn fetch(
+    bot: &Bot,
+    since: DateTime<Utc>,
+    client: &reqwest::Client,
+) -> Result<Option<Vec<Transfer>>, String> {
+    let Some(venue) = exchange::get_exchange(bot) else {
+        return Ok(None);
+    };
+    let bot = Secrets::default().resolve_bot(bot)?;
+    venue
+        .transfers(&bot, client, since)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Record the transfers fetched per bot in the ledger and flag unexpected withdrawals on the
+/// bots' timelines.
+///
+/// Returns the unexpected withdrawals, with the bot they were made from.
+fn apply(
+    state: &mut AppState,
+    config: &WithdrawalMonitorConfig,
+    results: Vec<(String, Vec<Transfer>)>,
+    since: DateTime<Utc>,
+) -> Vec<(String, Transfer)> {
+    let now = state.providers.clock.now();
+    let mut unexpected = Vec::new();
+    for (bot_id, transfers) in results {
+        for transfer in state.ledger.record(&bot_id, transfers, now, since) {
+            if transfer.kind != TransferKind::Withdrawal || is_allowed(config, &transfer) {
+                continue;
+            }
+            log::warn!("Bot '{}': {}", bot_id, describe(&transfer));
+            state.events.record(
+                now,
+                &bot_id,
+                EventKind::Error,
+                describe(&transfer),
+                serde_json::to_value(&transfer).ok(),
+            );
+            unexpected.push((bot_id.clone(), transfer));
+        }
+    }
+    if let Err(e) = state.save::<PathBuf>(None) {
+        log::error!("Failed to save the withdrawals seen: {}", e);
+    }
+    unexpected
+}
+
+/// Whether `withdrawal` went to an allowed address.
+fn is_allowed(config: &WithdrawalMonitorConfig, withdrawal: &Transfer) -> bool {
+    withdrawal.address.as_deref().is_some_and(|address| {
+        (config.allowed_addresses.iter()).any(|allowed| allowed.eq_ignore_ascii_case(address))
+    })
+}
+
+fn describe(withdrawal: &Transfer) -> String {
+    format!(
+        "Unexpected withdrawal of {} {} to {} ({}, {})",
+        withdrawal.amount,
+        withdrawal.asset,
+        withdrawal
+            .address
+            .as_deref()
+            .unwrap_or("an unknown address"),
+        withdrawal.status,
+        withdrawal.timestamp.to_rfc3339()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::dec;
+    use tempfile::tempdir;
+
+    fn transfer(id: &str, kind: TransferKind, address: &str) -> Transfer {
+        Transfer {
+            id: id.to_string(),
+            kind,
+            asset: "USDT".to_string(),
+            amount: dec!(250),
+            address: Some(address.to_string()),
+            status: "completed".to_string(),
+            timestamp: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn only_new_withdrawals_to_unknown_addresses_raise_the_alarm() {
+        let dir = tempdir().unwrap();
+        let mut state = AppState {
+            state_file: dir.path().join("state.json"),
+            ..Default::default()
+        };
+        let config = WithdrawalMonitorConfig {
+            allowed_addresses: vec!["0xCOLD".to_string()],
+            ..Default::default()
+        };
+        let since = Utc::now() - Duration::hours(24);
+        let transfers = vec![
+            transfer("1", TransferKind::Deposit, "0xexchange"),
+            transfer("2", TransferKind::Withdrawal, "0xcold"),
+            transfer("3", TransferKind::Withdrawal, "0xthief"),
+        ];
+
+        let unexpected = apply(
+            &mut state,
+            &config,
+            vec![("alpha".to_string(), transfers.clone())],
+            since,
+        );
+        let ids: Vec<_> = unexpected.iter().map(|(_, t)| t.id.as_str()).collect();
+        assert_eq!(ids, ["3"]);
+        let timeline = state.events.page("alpha", None, None, 10).0;
+        assert_eq!(timeline.len(), 1);
+        assert!(timeline[0].summary.contains("to 0xthief"));
+        assert_eq!(state.ledger.get("alpha").unwrap().transfers.len(), 3);
+
+        // Seen before, in the ledger saved with the state
+        let mut reloaded: AppState =
+            serde_json::from_slice(&std::fs::read(&state.state_file).unwrap()).unwrap();
+        reloaded.state_file = state.state_file.clone();
+        let unexpected = apply(
+            &mut reloaded,
+            &config,
+            vec![("alpha".to_string(), transfers)],
+            since,
+        );
+        assert!(unexpected.is_empty());
+    }
+}
@@ -0,0 +1,581 @@
+// src/bot/ws.rs
+//! # WebSocket Command Channel
+//!
+//! `GET /ws` upgrades to a persistent session over which the online CLI sends the same
+//! commands it would otherwise issue as individual REST requests. Each text frame carries a
+//! [`WsRequest`]; the server answers with one [`WsResponse`] per request, or a stream of them
+//! for [`WsCommand::Watch`], the last one marked `done`.
+//!
+//! Sessions that send [`WsCommand::Subscribe`] are also pushed a [`ServerEvent`] whenever
+//! something changed behind their back, e.g. the state was reloaded from a file edited
+//! outside the server; the Web UI refreshes on those.
+//!
+//! Sessions authenticate once, at the upgrade, exactly like REST requests. Impersonated
+//! sessions are read-only and every command they issue is audited.
+use crate::app_config::TenancyConfig;
+use crate::bot::api::{apply_pagination, create_api_response, ApiResponse, Tenant};
+use crate::bot::audit::{self, AuditEvent};
+use crate::bot::cli::Commands;
+use crate::bot::secrets;
+use crate::bot::state::events::Event;
+use crate::bot::state::{
+    AppState, BotDeleteArgs, BotGetArgs, BotInsertArgs, BotListArgs, BotListView, BotRegistry,
+    BotTimelineArgs, BotUpdateArgs, BotView, BotWatchArgs, ListenerDeleteArgs, ListenerGetArgs,
+    ListenerInsertArgs, ListenerListArgs, ListenerListView, ListenerUpdateArgs, ListenerView,
+    ListenersDeleteArgs, SymbolMapArgs, SymbolMapView, SymbolUnmapArgs, TenantScope, TimelineView,
+};
+use crate::errors::AppError;
+use actix_web::{get, web, HttpRequest, HttpResponse, ResponseError};
+use actix_ws::{Message, Session};
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt::{self, Display};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+/// Commands accepted over the WebSocket channel.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "command", content = "args", rename_all = "snake_case")]
+pub enum WsCommand {
+    AddBot(BotInsertArgs),
+    ListBots(BotListArgs),
+    GetBot(BotGetArgs),
+    BotTimeline(BotTimelineArgs),
+    UpdateBot(BotUpdateArgs),
+    DeleteBot(BotDeleteArgs),
+    AddListener(ListenerInsertArgs),
+    ListListeners(ListenerListArgs),
+    GetListener(ListenerGetArgs),
+    UpdateListener(ListenerUpdateArgs),
+    DeleteListener(ListenerDeleteArgs),
+    DeleteListeners(ListenersDeleteArgs),
+    MapSymbol(SymbolMapArgs),
+    UnmapSymbol(SymbolUnmapArgs),
+    /// Stream new timeline events of a bot until the session closes
+    Watch(BotWatchArgs),
+    /// Stream server events until the session closes
+    Subscribe,
+}
+
+impl WsCommand {
+    /// The channel equivalent of an online CLI command, if it has one.
+    pub fn from_cli(command: &Commands) -> Option<Self> {
+        Some(match command.clone() {
+            Commands::AddBot(args) => WsCommand::AddBot(args),
+            Commands::ListBots(args) => WsCommand::ListBots(args),
+            Commands::GetBot(args) => WsCommand::GetBot(args),
+            Commands::BotTimeline(args) => WsCommand::BotTimeline(args),
+            Commands::UpdateBot(args) => WsCommand::UpdateBot(args),
+            Commands::DeleteBot(args) => WsCommand::DeleteBot(args),
+            Commands::AddListener(args) => WsCommand::AddListener(args),
+            Commands::ListListeners(args) => WsCommand::ListListeners(args),
+            Commands::GetListener(args) => WsCommand::GetListener(args),
+            Commands::UpdateListener(args) => WsCommand::UpdateListener(args),
+            Commands::DeleteListener(args) => WsCommand::DeleteListener(args),
+            Commands::DeleteListeners(args) => WsCommand::DeleteListeners(args),
+            Commands::MapSymbol(args) => WsCommand::MapSymbol(args),
+            Commands::UnmapSymbol(args) => WsCommand::UnmapSymbol(args),
+            Commands::Watch(args) => WsCommand::Watch(args),
+            Commands::Offline { .. }
+            | Commands::Migrate { .. }
+            | Commands::Config { .. }
+            | Commands::Server(_)
+            | Commands::Healthcheck
+            | Commands::AdminInfo
+            | Commands::Balances(_)
+            | Commands::Positions(_)
+            | Commands::ListOrders(_)
+            | Commands::CancelOrder(_)
+            | Commands::ListTrades(_)
+            | Commands::ListAlerts(_)
+            | Commands::Replay(_)
+            | Commands::Report { .. }
+            | Commands::ReplayAlert(_)
+            | Commands::SimulateAlert(_)
+            | Commands::RotateSecret(_)
+            | Commands::Pnl(_)
+            | Commands::BotStats(_)
+            | Commands::ImportFills(_)
+            | Commands::ExecutionQuality(_)
+            | Commands::StrategyReport(_)
+            | Commands::AllocationReport(_)
+            | Commands::ListSymbols(_)
+            | Commands::Export(_)
+            | Commands::Import(_)
+            | Commands::Apply(_)
+            | Commands::State { .. } => return None,
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WsCommand::AddBot(_) => "add_bot",
+            WsCommand::ListBots(_) => "list_bots",
+            WsCommand::GetBot(_) => "get_bot",
+            WsCommand::BotTimeline(_) => "bot_timeline",
+            WsCommand::UpdateBot(_) => "update_bot",
+            WsCommand::DeleteBot(_) => "delete_bot",
+            WsCommand::AddListener(_) => "add_listener",
+            WsCommand::ListListeners(_) => "list_listeners",
+            WsCommand::GetListener(_) => "get_listener",
+            WsCommand::UpdateListener(_) => "update_listener",
+            WsCommand::DeleteListener(_) => "delete_listener",
+            WsCommand::DeleteListeners(_) => "delete_listeners",
+            WsCommand::MapSymbol(_) => "map_symbol",
+            WsCommand::UnmapSymbol(_) => "unmap_symbol",
+            WsCommand::Watch(_) => "watch",
+            WsCommand::Subscribe => "subscribe",
+        }
+    }
+
+    /// Whether the command changes state.
+    pub fn is_mutation(&self) -> bool {
+        matches!(
+            self,
+            WsCommand::AddBot(_)
+                | WsCommand::UpdateBot(_)
+                | WsCommand::DeleteBot(_)
+                | WsCommand::AddListener(_)
+                | WsCommand::UpdateListener(_)
+                | WsCommand::DeleteListener(_)
+                | WsCommand::DeleteListeners(_)
+                | WsCommand::MapSymbol(_)
+                | WsCommand::UnmapSymbol(_)
+        )
+    }
+
+    /// Render a response payload of this command for the terminal.
+    pub fn render(&self, data: Value) -> Result<String, AppError> {
+        fn show<T: DeserializeOwned + Display>(data: Value) -> Result<String, AppError> {
+            Ok(serde_json::from_value::<T>(data)?.to_string())
+        }
+
+        match self {
+            WsCommand::AddBot(_)
+            | WsCommand::GetBot(_)
+            | WsCommand::UpdateBot(_)
+            | WsCommand::DeleteBot(_) => show::<BotView>(data),
+            WsCommand::ListBots(_) => show::<BotListView>(data),
+            WsCommand::BotTimeline(_) => show::<TimelineView>(data),
+            WsCommand::AddListener(_)
+            | WsCommand::GetListener(_)
+            | WsCommand::UpdateListener(_)
+            | WsCommand::DeleteListener(_) => show::<ListenerView>(data),
+            WsCommand::ListListeners(_) | WsCommand::DeleteListeners(_) => {
+                show::<ListenerListView>(data)
+            }
+            WsCommand::MapSymbol(_) | WsCommand::UnmapSymbol(_) => show::<SymbolMapView>(data),
+            WsCommand::Watch(_) => show::<Event>(data),
+            WsCommand::Subscribe => show::<ServerEvent>(data),
+        }
+    }
+}
+
+/// Something that happened on the server, pushed to subscribed sessions.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ServerEvent {
+    /// The state was reloaded from its file after it was modified outside the server
+    StateReloaded { reloaded_at: DateTime<Utc> },
+}
+
+impl Display for ServerEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServerEvent::StateReloaded { reloaded_at } => {
+                write!(f, "{}  State reloaded from file", reloaded_at.to_rfc3339())
+            }
+        }
+    }
+}
+
+/// Fans [`ServerEvent`]s out to the subscribed sessions; app data of the API server.
+#[derive(Clone, Debug)]
+pub struct ServerEvents(broadcast::Sender<ServerEvent>);
+
+impl Default for ServerEvents {
+    fn default() -> Self {
+        Self(broadcast::channel(SERVER_EVENTS_BUFFER).0)
+    }
+}
+
+impl ServerEvents {
+    /// Push `event` to every session subscribed now; nobody listening is fine.
+    pub fn publish(&self, event: ServerEvent) {
+        let _ = self.0.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ServerEvent> {
+        self.0.subscribe()
+    }
+}
+
+/// Events a slow session may fall behind by before it misses some.
+const SERVER_EVENTS_BUFFER: usize = 64;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct WsRequest {
+    pub id: u64,
+    #[serde(flatten)]
+    pub command: WsCommand,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct WsResponse {
+    /// ID of the request this answers
+    pub id: u64,
+    /// HTTP-equivalent status code
+    pub status: u16,
+    /// No further responses follow for this request
+    pub done: bool,
+    pub response: ApiResponse<Value>,
+}
+
+impl WsResponse {
+    fn ok(id: u64, data: Value, done: bool) -> Self {
+        Self {
+            id,
+            status: 200,
+            done,
+            response: create_api_response(true, Some(data), None),
+        }
+    }
+
+    fn with_warnings(mut self, warnings: Vec<String>) -> Self {
+        self.response = self.response.with_warnings(warnings);
+        self
+    }
+
+    fn error(id: u64, error: &AppError) -> Self {
+        Self {
+            id,
+            status: error.status_code().as_u16(),
+            done: true,
+            response: ApiResponse {
+                error_code: Some(error.code()),
+                ..create_api_response(false, None, Some(error.to_string()))
+            },
+        }
+    }
+}
+
+/// Run a one-shot command against `registry`.
+fn execute<R: BotRegistry>(registry: &mut R, command: WsCommand) -> Result<Value, AppError> {
+    fn json<T: Serialize>(value: T) -> Result<Value, AppError> {
+        Ok(serde_json::to_value(value)?)
+    }
+    fn page<T: Clone>(items: &[T], page: Option<u32>, limit: Option<u32>) -> Vec<T> {
+        let page = page.unwrap_or(1).max(1) as usize;
+        let limit = limit.unwrap_or(10).max(1) as usize;
+        apply_pagination(items, page, limit)
+    }
+
+    match command {
+        WsCommand::AddBot(args) => json(registry.add_bot(args)?),
+        WsCommand::ListBots(args) => {
+            let (page_no, limit) = (args.page, args.limit);
+            let bots = registry.list_bots(Some(args))?;
+            json(BotListView(page(&bots.0, page_no, limit)))
+        }
+        WsCommand::GetBot(args) => json(registry.get_bot(args)?),
+        WsCommand::BotTimeline(args) => json(registry.bot_timeline(args)?),
+        WsCommand::UpdateBot(args) => json(registry.update_bot(args)?),
+        WsCommand::DeleteBot(args) => json(registry.delete_bot(args)?),
+        WsCommand::AddListener(args) => json(registry.add_listener(args)?),
+        WsCommand::ListListeners(args) => {
+            let (page_no, limit) = (args.page, args.limit);
+            let listeners = if args.all_bots {
+                registry.list_all_listeners(args)?
+            } else {
+                registry.list_listeners(args)?
+            };
+            json(ListenerListView(page(&listeners.0, page_no, limit)))
+        }
+        WsCommand::GetListener(args) => json(registry.get_listener(args)?),
+        WsCommand::UpdateListener(args) => json(registry.update_listener(args)?),
+        WsCommand::DeleteListener(args) => json(registry.delete_listener(args)?),
+        WsCommand::DeleteListeners(args) => json(registry.delete_listeners(args)?),
+        WsCommand::MapSymbol(args) => json(registry.map_symbol(args)?),
+        WsCommand::UnmapSymbol(args) => json(registry.unmap_symbol(args)?),
+        WsCommand::Watch(_) | WsCommand::Subscribe => Err(AppError::InvalidInput(
+            "watch and subscribe are streaming commands".to_string(),
+        )),
+    }
+}
+
+/// Everything a session needs to serve requests.
+#[derive(Clone)]
+struct SessionContext {
+    state: Arc<Mutex<AppState>>,
+    tenant: Tenant,
+    audit_log: Option<PathBuf>,
+}
+
+impl SessionContext {
+    /// Enforce read-only users and impersonation, auditing every impersonated command.
+    fn authorize(&self, command: &WsCommand) -> Result<(), AppError> {
+        if self.tenant.is_read_only() && command.is_mutation() {
+            return Err(AppError::Forbidden(format!(
+                "Read-only users may not {}.",
+                command.name()
+            )));
+        }
+        let (Some(admin), Some(tenant)) = (self.tenant.impersonated_by(), self.tenant.name())
+        else {
+            return Ok(());
+        };
+        let allowed = !command.is_mutation();
+        audit::record(
+            &AuditEvent::new(admin, tenant, "WS", command.name(), allowed),
+            self.audit_log.as_deref(),
+        )
+        .map_err(|e| AppError::InternalServerError(format!("Failed to write audit log: {}", e)))?;
+
+        if !allowed {
+            return Err(AppError::Forbidden(format!(
+                "Impersonated sessions are read-only; {} is not allowed.",
+                command.name()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Run `f` against the session's tenant-scoped registry.
+    fn with_registry<T>(
+        &self,
+        f: impl FnOnce(&mut TenantScope<'_>) -> Result<T, AppError>,
+    ) -> Result<T, AppError> {
+        let mut state = self.state.lock().map_err(|_| {
+            AppError::InternalServerError("Failed to acquire lock on AppState".to_string())
+        })?;
+        f(&mut state.scoped(self.tenant.name()))
+    }
+
+    /// The result of `command`, with the warnings of the change it made.
+    fn execute(&self, command: WsCommand) -> Result<(Value, Vec<String>), AppError> {
+        self.with_registry(|registry| {
+            let mut data = execute(registry, command)?;
+            if self.tenant.is_read_only() {
+                secrets::redact(&mut data);
+            }
+            Ok((data, registry.take_warnings()))
+        })
+    }
+
+    /// Sequence number of the bot's newest event (0 if it has none).
+    fn latest_seq(&self, bot_id: &str) -> Result<u64, AppError> {
+        let mut args = BotTimelineArgs::new(bot_id);
+        args.limit = Some(1);
+        let timeline = self.with_registry(|registry| registry.bot_timeline(args))?;
+        Ok(timeline.events.first().map_or(0, |event| event.seq))
+    }
+
+    /// Timeline events of the bot newer than `after`, oldest first.
+    fn events_after(&self, bot_id: &str, after: u64) -> Result<Vec<Event>, AppError> {
+        let mut events = Vec::new();
+        let mut args = BotTimelineArgs::new(bot_id);
+        args.limit = Some(100);
+        loop {
+            let timeline = self.with_registry(|registry| registry.bot_timeline(args.clone()))?;
+            let reached = timeline.events.iter().any(|event| event.seq <= after);
+            events.extend(
+                timeline
+                    .events
+                    .into_iter()
+                    .filter(|event| event.seq > after),
+            );
+            match timeline.next_cursor {
+                Some(cursor) if !reached => args.before = Some(cursor),
+                _ => break,
+            }
+        }
+        events.reverse();
+        Ok(events)
+    }
+}
+
+/// Stream new events of a bot until the session closes.
+async fn watch(context: SessionContext, mut session: Session, id: u64, args: BotWatchArgs) {
+    let mut last_seq = match context.latest_seq(&args.bot_id) {
+        Ok(seq) => seq,
+        Err(e) => {
+            let _ = send(&mut session, &WsResponse::error(id, &e)).await;
+            return;
+        }
+    };
+
+    loop {
+        tokio::time::sleep(args.interval()).await;
+        let events = match context.events_after(&args.bot_id, last_seq) {
+            Ok(events) => events,
+            Err(e) => {
+                let _ = send(&mut session, &WsResponse::error(id, &e)).await;
+                return;
+            }
+        };
+        for event in events {
+            last_seq = event.seq;
+            let Ok(data) = serde_json::to_value(&event) else {
+                continue;
+            };
+            if send(&mut session, &WsResponse::ok(id, data, false))
+                .await
+                .is_err()
+            {
+                return; // Session closed
+            }
+        }
+    }
+}
+
+/// Push server events to the session until it closes.
+async fn subscribe(mut session: Session, id: u64, mut events: broadcast::Receiver<ServerEvent>) {
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(missed)) => {
+                log::warn!("WebSocket session missed {} server event(s)", missed);
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => return,
+        };
+        let Ok(data) = serde_json::to_value(&event) else {
+            continue;
+        };
+        if send(&mut session, &WsResponse::ok(id, data, false))
+            .await
+            .is_err()
+        {
+            return; // Session closed
+        }
+    }
+}
+
+async fn send(session: &mut Session, response: &WsResponse) -> Result<(), actix_ws::Closed> {
+    match serde_json::to_string(response) {
+        Ok(text) => session.text(text).await,
+        Err(e) => {
+            log::error!("Failed to serialize WebSocket response: {}", e);
+            Ok(())
+        }
+    }
+}
+
+#[get("/ws")]
+pub async fn ws_session(
+    req: HttpRequest,
+    body: web::Payload,
+    data: web::Data<Arc<Mutex<AppState>>>,
+    tenant: Tenant,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (response, mut session, mut stream) = actix_ws::handle(&req, body)?;
+    let context = SessionContext {
+        state: data.get_ref().clone(),
+        tenant,
+        audit_log: req
+            .app_data::<web::Data<TenancyConfig>>()
+            .and_then(|config| config.audit_log.clone()),
+    };
+    let events = (req.app_data::<web::Data<ServerEvents>>())
+        .map(|events| events.get_ref().clone())
+        .unwrap_or_default();
+
+    actix_web::rt::spawn(async move {
+        while let Some(Ok(message)) = stream.recv().await {
+            match message {
+                Message::Text(text) => {
+                    let request = match serde_json::from_str::<WsRequest>(&text) {
+                        Ok(request) => request,
+                        Err(e) => {
+                            let error = AppError::InvalidInput(format!("Invalid request: {}", e));
+                            if send(&mut session, &WsResponse::error(0, &error))
+                                .await
+                                .is_err()
+                            {
+                                break;
+                            }
+                            continue;
+                        }
+                    };
+                    let id = request.id;
+                    if let Err(e) = context.authorize(&request.command) {
+                        if send(&mut session, &WsResponse::error(id, &e))
+                            .await
+                            .is_err()
+                        {
+                            break;
+                        }
+                        continue;
+                    }
+                    let response = match request.command {
+                        WsCommand::Watch(args) => {
+                            actix_web::rt::spawn(watch(context.clone(), session.clone(), id, args));
+                            continue;
+                        }
+                        WsCommand::Subscribe => {
+                            let events = events.subscribe();
+                            actix_web::rt::spawn(subscribe(session.clone(), id, events));
+                            continue;
+                        }
+                        command => match context.execute(command) {
+                            Ok((data, warnings)) => {
+                                WsResponse::ok(id, data, true).with_warnings(warnings)
+                            }
+                            Err(e) => WsResponse::error(id, &e),
+                        },
+                    };
+                    if send(&mut session, &response).await.is_err() {
+                        break;
+                    }
+                }
+                Message::Ping(bytes) if session.pong(&bytes).await.is_err() => break,
+                Message::Close(_) => break,
+                _ => {}
+            }
+        }
+        let _ = session.close(None).await;
+    });
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_round_trip_with_their_command() {
+        let text = r#"{"id":7,"command":"get_bot","args":{"bot_id":"alpha"}}"#;
+        let request: WsRequest = serde_json::from_str(text).unwrap();
+        assert_eq!(request.id, 7);
+        assert!(matches!(&request.command, WsCommand::GetBot(args) if args.bot_id == "alpha"));
+        assert!(!request.command.is_mutation());
+
+        let again: WsRequest =
+            serde_json::from_str(&serde_json::to_string(&request).unwrap()).unwrap();
+        assert_eq!(again.command.name(), "get_bot");
+    }
+
+    #[test]
+    fn server_events_reach_subscribers() {
+        let events = ServerEvents::default();
+        events.publish(ServerEvent::StateReloaded {
+            reloaded_at: Utc::now(),
+        }); // Nobody listening yet
+
+        let request: WsRequest = serde_json::from_str(r#"{"id":3,"command":"subscribe"}"#).unwrap();
+        assert!(matches!(request.command, WsCommand::Subscribe));
+        let mut subscriber = events.subscribe();
+        let reloaded_at = "2026-01-01T00:00:00Z".parse().unwrap();
+        events.publish(ServerEvent::StateReloaded { reloaded_at });
+        let event = subscriber.try_recv().unwrap();
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({"event": "state_reloaded", "reloaded_at": "2026-01-01T00:00:00Z"})
+        );
+        assert!(subscriber.try_recv().is_err());
+    }
+}
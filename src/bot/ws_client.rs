@@ -0,0 +1,93 @@
+// src/bot/ws_client.rs
+//! Client side of the [WebSocket command channel](super::ws), used by the online CLI when
+//! the server supports it.
+use crate::bot::ws::{WsCommand, WsRequest, WsResponse};
+use crate::errors::AppError;
+use futures_util::{SinkExt, StreamExt};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::header::{HeaderValue, AUTHORIZATION};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+pub struct WsClient {
+    stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    next_id: u64,
+}
+
+impl WsClient {
+    /// Open a session with the server at `base_url` (the URL of its REST API version).
+    ///
+    /// Fails if the server cannot be reached or does not offer the channel.
+    pub async fn connect(base_url: &str, token: Option<&str>) -> Result<Self, AppError> {
+        let mut url = url::Url::parse(base_url)
+            .map_err(|e| AppError::ConnectionError(format!("Invalid URL: {}", e)))?;
+        let scheme = if url.scheme() == "https" { "wss" } else { "ws" };
+        url.set_scheme(scheme)
+            .map_err(|_| AppError::ConnectionError(format!("Invalid URL: {}", base_url)))?;
+        url.set_path(&format!("{}/ws", url.path().trim_end_matches('/')));
+
+        let mut request = url
+            .as_str()
+            .into_client_request()
+            .map_err(|e| AppError::ConnectionError(e.to_string()))?;
+        if let Some(token) = token {
+            let value = HeaderValue::from_str(&format!("Bearer {}", token))
+                .map_err(|e| AppError::ConfigError(format!("Invalid token: {}", e)))?;
+            request.headers_mut().insert(AUTHORIZATION, value);
+        }
+
+        let (stream, _) = tokio_tungstenite::connect_async(request)
+            .await
+            .map_err(|e| AppError::ConnectionError(e.to_string()))?;
+        log::debug!("WebSocket session opened to {}", url);
+        Ok(Self { stream, next_id: 1 })
+    }
+
+    /// Send `command` and print every response to it until the server marks it done.
+    pub async fn run(&mut self, command: WsCommand) -> Result<(), AppError> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let request = serde_json::to_string(&WsRequest {
+            id,
+            command: command.clone(),
+        })?;
+        self.stream
+            .send(Message::text(request))
+            .await
+            .map_err(|e| AppError::ConnectionError(e.to_string()))?;
+
+        while let Some(message) = self.stream.next().await {
+            let text = match message.map_err(|e| AppError::ConnectionError(e.to_string()))? {
+                Message::Text(text) => text,
+                Message::Close(_) => break,
+                _ => continue,
+            };
+            let response: WsResponse = serde_json::from_str(&text)?;
+            if response.id != id {
+                continue;
+            }
+            if response.status >= 400 {
+                // Shaped like a REST error body, so the error code can be read back
+                let error = serde_json::json!({
+                    "error": response.response.error.unwrap_or_default(),
+                    "error_code": response.response.error_code,
+                });
+                return Err(AppError::HttpError(response.status, error.to_string()));
+            }
+            if let Some(data) = response.response.data {
+                println!("{}", command.render(data)?);
+            }
+            for warning in &response.response.warnings {
+                eprintln!("Warning: {}", warning);
+            }
+            if response.done {
+                let _ = self.stream.close(None).await;
+                return Ok(());
+            }
+        }
+        Err(AppError::ConnectionError(
+            "WebSocket session closed before the response was complete.".to_string(),
+        ))
+    }
+}
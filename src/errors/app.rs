@@ -1,4 +1,8 @@
-use actix_web::{http::StatusCode, HttpResponse, ResponseError};
+use crate::bot::state::quota::MAX_TRADES_PER_DAY;
+use crate::errors::ErrorCode;
+use actix_web::http::{header::RETRY_AFTER, StatusCode};
+use actix_web::{HttpResponse, ResponseError};
+use rust_decimal::Decimal;
 use serde::Serialize;
 use std::path::PathBuf;
 use thiserror::Error;
@@ -50,6 +54,60 @@ pub enum AppError {
     #[error("Bot ID required")]
     BotIdRequired, // HTTP 400
 
+    #[error("Quota `{quota}` exceeded: {used} of {limit} used.")]
+    QuotaExceeded {
+        quota: &'static str,
+        limit: u64,
+        used: u64,
+    }, // HTTP 403, or 429 for the daily trade quota
+
+    #[error("No price for `{symbol}` fresher than {max_staleness_secs}s.")]
+    StalePrice {
+        symbol: String,
+        /// Age of the cached price, if there is one
+        age_secs: Option<i64>,
+        max_staleness_secs: u64,
+    }, // HTTP 503
+
+    #[error("Listener `{listener_id}` is rate limited ({reason}); retry in {retry_after_secs}s.")]
+    RateLimited {
+        listener_id: String,
+        reason: String,
+        retry_after_secs: u64,
+    }, // HTTP 429
+
+    #[error(
+        "Bot `{holder}` of exclusion group `{group}` holds a position in `{symbol}`; no other \
+         bot of the group trades it until that is closed."
+    )]
+    PositionHeld {
+        group: String,
+        holder: String,
+        symbol: String,
+    }, // HTTP 409
+
+    #[error(
+        "Bot `{bot_id}` has {exposure} of its budget of {budget} in open positions; only \
+         orders reducing them are placed."
+    )]
+    BudgetExhausted {
+        bot_id: String,
+        budget: Decimal,
+        exposure: Decimal,
+    }, // HTTP 409
+
+    #[error("Alert is {age_secs}s old; alerts older than {max_age_secs}s are not executed.")]
+    StaleAlert { age_secs: i64, max_age_secs: u64 }, // HTTP 422
+
+    #[error("The API key is refused: {}.", .problems.join("; "))]
+    UnsafeApiKey { problems: Vec<String> }, // HTTP 422
+
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String), // HTTP 401
+
+    #[error("Forbidden: {0}")]
+    Forbidden(String), // HTTP 403
+
     #[error("Not found: {0}")]
     NotFound(String), // HTTP 404
 
@@ -62,6 +120,9 @@ pub enum AppError {
     #[error("Listener not found: {0}")]
     ListenerNotFound(String), // HTTP 404
 
+    #[error("A listener with ID `{0}` already exists.")]
+    ListenerAlreadyExists(String), // HTTP 409
+
     #[allow(dead_code)]
     #[error("Failed to save state: {0}")]
     SaveError(String), // HTTP 500
@@ -90,6 +151,38 @@ pub enum AppError {
         path: PathBuf,
     },
 
+    #[error(
+        "State file {0:?} was modified outside xtrade since it was last loaded; refusing to \
+         overwrite it. Reload the state (restart the server or enable \
+         api_server.watch_state_file) and retry."
+    )]
+    StateConflict(PathBuf), // HTTP 409
+
+    #[error(
+        "State file {path:?} is locked by {holder} (a server or another command); retry once \
+         it is done."
+    )]
+    StateLocked { path: PathBuf, holder: String }, // HTTP 409
+
+    #[error(
+        "A server (PID {pid}) is using state file {path:?}; make the change through it with \
+         `xtrade --url {url} ...`, or pass `offline --force` to edit the file anyway."
+    )]
+    ServerRunning {
+        path: PathBuf,
+        pid: u32,
+        url: String,
+    }, // HTTP 409
+
+    #[error(
+        "The state in {0} was saved by another server since it was last loaded; refusing to \
+         overwrite it. Retry once the state is reloaded."
+    )]
+    StoreConflict(String), // HTTP 409
+
+    #[error("Failed to access the state in {0}: {1}")]
+    StorageError(String, String), // HTTP 500
+
     #[allow(dead_code)]
     #[error("State already locked. Failed to acquire lock.")]
     LockError,
@@ -100,6 +193,14 @@ pub enum AppError {
 
     #[error("HTTP error {0}: {1}")]
     HttpError(u16, String),
+
+    #[error("Operation {index} of the batch failed, so none was applied: {source}")]
+    BulkOperationFailed {
+        /// Position of the operation in the batch, from 0
+        index: usize,
+        #[source]
+        source: Box<AppError>,
+    }, // HTTP status of the operation's error
 }
 
 /// A standardized error response structure for API responses
@@ -107,7 +208,9 @@ pub enum AppError {
 struct ErrorResponse {
     error: String,
     code: u16,
-    details: Option<String>, // Optional: Provide additional context if available
+    /// Stable machine-readable code of the error, from the [catalogue](ErrorCode)
+    error_code: ErrorCode,
+    details: Option<serde_json::Value>, // Optional: Provide additional context if available
 }
 
 impl ResponseError for AppError {
@@ -122,21 +225,41 @@ impl ResponseError for AppError {
             | AppError::NonUniqueResult(_)
             | AppError::ArgumentsRequired
             | AppError::BotIdRequired => StatusCode::BAD_REQUEST,
-            AppError::BotAlreadyExists(_) => StatusCode::CONFLICT,
+            AppError::StaleAlert { .. } | AppError::UnsafeApiKey { .. } => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::QuotaExceeded { quota, .. } if *quota == MAX_TRADES_PER_DAY => {
+                StatusCode::TOO_MANY_REQUESTS
+            }
+            AppError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            AppError::QuotaExceeded { .. } | AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::BotAlreadyExists(_)
+            | AppError::ListenerAlreadyExists(_)
+            | AppError::StateConflict(_)
+            | AppError::StateLocked { .. }
+            | AppError::ServerRunning { .. }
+            | AppError::StoreConflict(_)
+            | AppError::PositionHeld { .. }
+            | AppError::BudgetExhausted { .. } => StatusCode::CONFLICT,
             AppError::SaveError(_)
             | AppError::InternalServerError(_)
             | AppError::LockError
             | AppError::InvalidState(_)
             | AppError::FileReadError { .. }
-            | AppError::FileWriteError { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+            | AppError::FileWriteError { .. }
+            | AppError::StorageError(..) => StatusCode::INTERNAL_SERVER_ERROR,
             AppError::ConnectionError(_) | AppError::UnexpectedResponse(_) => {
                 StatusCode::BAD_GATEWAY
             }
             AppError::TimeoutError(_) => StatusCode::GATEWAY_TIMEOUT,
-            AppError::GeneralError(_) => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::GeneralError(_) | AppError::StalePrice { .. } => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
             AppError::HttpError(status, _) => {
                 StatusCode::from_u16(*status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
             }
+            AppError::BulkOperationFailed { source, .. } => source.status_code(),
             _ => StatusCode::INTERNAL_SERVER_ERROR, // Default fallback for unhandled errors
         }
     }
@@ -144,14 +267,144 @@ impl ResponseError for AppError {
     fn error_response(&self) -> HttpResponse {
         log::error!("Error occurred: {:?}", self);
 
-        HttpResponse::build(self.status_code()).json(ErrorResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        if let AppError::RateLimited {
+            retry_after_secs, ..
+        } = self
+        {
+            response.insert_header((RETRY_AFTER, retry_after_secs.to_string()));
+        }
+        response.json(ErrorResponse {
             error: self.to_string(),
             code: self.status_code().as_u16(),
-            details: None, // Optional: Add more details if applicable
+            error_code: self.code(),
+            details: self.details(),
         })
     }
 }
 
+impl AppError {
+    /// The stable code of the error. A server's error response carries its own code, which
+    /// is read back from the body of an [`AppError::HttpError`].
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            AppError::ConfigError(_) | AppError::EnvVarError(_) | AppError::NoFilePathProvided => {
+                ErrorCode::ConfigInvalid
+            }
+            AppError::Unknown(_) => ErrorCode::Unknown,
+            AppError::InternalServerError(_) | AppError::LockError | AppError::InvalidState(_) => {
+                ErrorCode::InternalError
+            }
+            AppError::InvalidInput(_)
+            | AppError::SerializationError(_)
+            | AppError::NonUniqueResult(_)
+            | AppError::ArgumentsRequired
+            | AppError::BotIdRequired
+            | AppError::JsonParseError(_) => ErrorCode::InvalidInput,
+            AppError::ValidationError(_) | AppError::UnsafeApiKey { .. } => {
+                ErrorCode::ValidationFailed
+            }
+            AppError::ConnectionError(_) => ErrorCode::ConnectionFailed,
+            AppError::TimeoutError(_) => ErrorCode::Timeout,
+            AppError::GeneralError(_) => ErrorCode::ServiceUnavailable,
+            AppError::UnexpectedResponse(_) => ErrorCode::UnexpectedResponse,
+            AppError::QuotaExceeded { .. } => ErrorCode::QuotaExceeded,
+            AppError::StalePrice { .. } => ErrorCode::StalePrice,
+            AppError::RateLimited { .. }
+            | AppError::PositionHeld { .. }
+            | AppError::BudgetExhausted { .. } => ErrorCode::RiskLimitExceeded,
+            AppError::StaleAlert { .. } => ErrorCode::StaleAlert,
+            AppError::Unauthorized(_) => ErrorCode::Unauthorized,
+            AppError::Forbidden(_) => ErrorCode::Forbidden,
+            AppError::NotFound(_) => ErrorCode::NotFound,
+            AppError::BotNotFound(_) => ErrorCode::BotNotFound,
+            AppError::BotAlreadyExists(_) => ErrorCode::BotAlreadyExists,
+            AppError::ListenerNotFound(_) => ErrorCode::ListenerNotFound,
+            AppError::ListenerAlreadyExists(_) => ErrorCode::ListenerAlreadyExists,
+            AppError::SaveError(_)
+            | AppError::FileReadError { .. }
+            | AppError::FileWriteError { .. }
+            | AppError::StorageError(..) => ErrorCode::StorageFailed,
+            AppError::StateConflict(_)
+            | AppError::StateLocked { .. }
+            | AppError::ServerRunning { .. }
+            | AppError::StoreConflict(_) => ErrorCode::StateConflict,
+            AppError::HttpError(status, body) => {
+                #[derive(serde::Deserialize)]
+                struct Coded {
+                    error_code: ErrorCode,
+                }
+                serde_json::from_str::<Coded>(body)
+                    .map(|coded| coded.error_code)
+                    .unwrap_or_else(|_| ErrorCode::from_status(*status))
+            }
+            AppError::BulkOperationFailed { source, .. } => source.code(),
+        }
+    }
+
+    /// Machine-readable context for the error response, where there is any.
+    fn details(&self) -> Option<serde_json::Value> {
+        match self {
+            AppError::QuotaExceeded { quota, limit, used } => Some(serde_json::json!({
+                "quota": quota,
+                "limit": limit,
+                "used": used,
+            })),
+            AppError::StalePrice {
+                symbol,
+                age_secs,
+                max_staleness_secs,
+            } => Some(serde_json::json!({
+                "symbol": symbol,
+                "age_secs": age_secs,
+                "max_staleness_secs": max_staleness_secs,
+            })),
+            AppError::RateLimited {
+                listener_id,
+                reason,
+                retry_after_secs,
+            } => Some(serde_json::json!({
+                "listener_id": listener_id,
+                "reason": reason,
+                "retry_after_secs": retry_after_secs,
+            })),
+            AppError::PositionHeld {
+                group,
+                holder,
+                symbol,
+            } => Some(serde_json::json!({
+                "group": group,
+                "holder": holder,
+                "symbol": symbol,
+            })),
+            AppError::BudgetExhausted {
+                bot_id,
+                budget,
+                exposure,
+            } => Some(serde_json::json!({
+                "bot_id": bot_id,
+                "budget": budget,
+                "exposure": exposure,
+            })),
+            AppError::StaleAlert {
+                age_secs,
+                max_age_secs,
+            } => Some(serde_json::json!({
+                "age_secs": age_secs,
+                "max_age_secs": max_age_secs,
+            })),
+            AppError::UnsafeApiKey { problems } => Some(serde_json::json!({
+                "problems": problems,
+            })),
+            AppError::BulkOperationFailed { index, source } => Some(serde_json::json!({
+                "index": index,
+                "details": source.details(),
+            })),
+            _ => None,
+        }
+    }
+}
+
 // /// Helper to map generic errors into `AppError::InternalServerError`
 // pub fn map_to_app_error<E: std::fmt::Display>(err: E) -> AppError {
 //     AppError::InternalServerError(err.to_string())
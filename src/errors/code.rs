@@ -0,0 +1,215 @@
+// src/errors/code.rs
+//! # Error Codes
+//!
+//! Every [`AppError`](super::AppError) carries a stable, machine-readable code next to its
+//! message. API error bodies return it as `error_code`, so the web UI can show its own wording
+//! for an error and scripts can branch on it without parsing messages. The CLI exits with the
+//! [status](ErrorCode::exit_code) of the code's family.
+//!
+//! Codes are part of the API: existing ones keep their name and meaning, new ones may be added.
+//! Clients reading a code they do not know get [`ErrorCode::Unknown`].
+//!
+//! | Exit status | Codes |
+//! |---|---|
+//! | 1 | `UNKNOWN`, `INTERNAL_ERROR`, `UNEXPECTED_RESPONSE` |
+//! | 2 | `INVALID_INPUT`, `VALIDATION_FAILED` |
+//! | 3 | `NOT_FOUND`, `BOT_NOT_FOUND`, `LISTENER_NOT_FOUND` |
+//! | 4 | `BOT_ALREADY_EXISTS`, `LISTENER_ALREADY_EXISTS`, `STATE_CONFLICT` |
+//! | 5 | `UNAUTHORIZED`, `FORBIDDEN` |
+//! | 6 | `QUOTA_EXCEEDED`, `RISK_LIMIT_EXCEEDED`, `STALE_PRICE`, `STALE_ALERT` |
+//! | 7 | `EXCHANGE_REJECTED`, `EXCHANGE_UNSUPPORTED` |
+//! | 8 | `CONNECTION_FAILED`, `TIMEOUT`, `SERVICE_UNAVAILABLE`, `EXCHANGE_UNAVAILABLE` |
+//! | 9 | `CONFIG_INVALID`, `STORAGE_FAILED` |
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    InternalError,
+    /// The server answered with something the client could not make sense of
+    UnexpectedResponse,
+    InvalidInput,
+    /// The input was well-formed but broke a rule, e.g. an empty ID
+    ValidationFailed,
+    NotFound,
+    BotNotFound,
+    ListenerNotFound,
+    BotAlreadyExists,
+    ListenerAlreadyExists,
+    /// The state file changed outside xtrade
+    StateConflict,
+    Unauthorized,
+    Forbidden,
+    /// A tenant quota is used up
+    QuotaExceeded,
+    /// A listener's limits on alerts, its rate or its cooldown, held the alert back
+    RiskLimitExceeded,
+    /// No recent enough price to check or size an order with
+    StalePrice,
+    /// The alert fired too long ago to act on
+    StaleAlert,
+    /// The exchange refused the order or request
+    ExchangeRejected,
+    /// The exchange could not be reached; the order may be buffered
+    ExchangeUnavailable,
+    /// No adapter exists for the bot's exchange
+    ExchangeUnsupported,
+    ConnectionFailed,
+    Timeout,
+    ServiceUnavailable,
+    /// The configuration, or a secret it refers to, is missing or invalid
+    ConfigInvalid,
+    /// The state or another file could not be read or written
+    StorageFailed,
+    /// An error without a more specific code, or a code this build does not know
+    #[serde(other)]
+    Unknown,
+}
+
+impl ErrorCode {
+    /// Every code, in the order of the catalogue.
+    #[allow(dead_code)]
+    pub const ALL: [ErrorCode; 25] = [
+        ErrorCode::Unknown,
+        ErrorCode::InternalError,
+        ErrorCode::UnexpectedResponse,
+        ErrorCode::InvalidInput,
+        ErrorCode::ValidationFailed,
+        ErrorCode::NotFound,
+        ErrorCode::BotNotFound,
+        ErrorCode::ListenerNotFound,
+        ErrorCode::BotAlreadyExists,
+        ErrorCode::ListenerAlreadyExists,
+        ErrorCode::StateConflict,
+        ErrorCode::Unauthorized,
+        ErrorCode::Forbidden,
+        ErrorCode::QuotaExceeded,
+        ErrorCode::RiskLimitExceeded,
+        ErrorCode::StalePrice,
+        ErrorCode::StaleAlert,
+        ErrorCode::ExchangeRejected,
+        ErrorCode::ExchangeUnavailable,
+        ErrorCode::ExchangeUnsupported,
+        ErrorCode::ConnectionFailed,
+        ErrorCode::Timeout,
+        ErrorCode::ServiceUnavailable,
+        ErrorCode::ConfigInvalid,
+        ErrorCode::StorageFailed,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::Unknown => "UNKNOWN",
+            ErrorCode::InternalError => "INTERNAL_ERROR",
+            ErrorCode::UnexpectedResponse => "UNEXPECTED_RESPONSE",
+            ErrorCode::InvalidInput => "INVALID_INPUT",
+            ErrorCode::ValidationFailed => "VALIDATION_FAILED",
+            ErrorCode::NotFound => "NOT_FOUND",
+            ErrorCode::BotNotFound => "BOT_NOT_FOUND",
+            ErrorCode::ListenerNotFound => "LISTENER_NOT_FOUND",
+            ErrorCode::BotAlreadyExists => "BOT_ALREADY_EXISTS",
+            ErrorCode::ListenerAlreadyExists => "LISTENER_ALREADY_EXISTS",
+            ErrorCode::StateConflict => "STATE_CONFLICT",
+            ErrorCode::Unauthorized => "UNAUTHORIZED",
+            ErrorCode::Forbidden => "FORBIDDEN",
+            ErrorCode::QuotaExceeded => "QUOTA_EXCEEDED",
+            ErrorCode::RiskLimitExceeded => "RISK_LIMIT_EXCEEDED",
+            ErrorCode::StalePrice => "STALE_PRICE",
+            ErrorCode::StaleAlert => "STALE_ALERT",
+            ErrorCode::ExchangeRejected => "EXCHANGE_REJECTED",
+            ErrorCode::ExchangeUnavailable => "EXCHANGE_UNAVAILABLE",
+            ErrorCode::ExchangeUnsupported => "EXCHANGE_UNSUPPORTED",
+            ErrorCode::ConnectionFailed => "CONNECTION_FAILED",
+            ErrorCode::Timeout => "TIMEOUT",
+            ErrorCode::ServiceUnavailable => "SERVICE_UNAVAILABLE",
+            ErrorCode::ConfigInvalid => "CONFIG_INVALID",
+            ErrorCode::StorageFailed => "STORAGE_FAILED",
+        }
+    }
+
+    /// Exit status of the CLI for an error with this code; see the table above.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ErrorCode::Unknown | ErrorCode::InternalError | ErrorCode::UnexpectedResponse => 1,
+            ErrorCode::InvalidInput | ErrorCode::ValidationFailed => 2,
+            ErrorCode::NotFound | ErrorCode::BotNotFound | ErrorCode::ListenerNotFound => 3,
+            ErrorCode::BotAlreadyExists
+            | ErrorCode::ListenerAlreadyExists
+            | ErrorCode::StateConflict => 4,
+            ErrorCode::Unauthorized | ErrorCode::Forbidden => 5,
+            ErrorCode::QuotaExceeded
+            | ErrorCode::RiskLimitExceeded
+            | ErrorCode::StalePrice
+            | ErrorCode::StaleAlert => 6,
+            ErrorCode::ExchangeRejected | ErrorCode::ExchangeUnsupported => 7,
+            ErrorCode::ConnectionFailed
+            | ErrorCode::Timeout
+            | ErrorCode::ServiceUnavailable
+            | ErrorCode::ExchangeUnavailable => 8,
+            ErrorCode::ConfigInvalid | ErrorCode::StorageFailed => 9,
+        }
+    }
+
+    /// Best guess at the code of an error response that names none, e.g. from an older
+    /// server, by its HTTP status.
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            400 | 422 => ErrorCode::InvalidInput,
+            401 => ErrorCode::Unauthorized,
+            403 => ErrorCode::Forbidden,
+            404 => ErrorCode::NotFound,
+            502 => ErrorCode::ConnectionFailed,
+            503 => ErrorCode::ServiceUnavailable,
+            504 => ErrorCode::Timeout,
+            500..=599 => ErrorCode::InternalError,
+            _ => ErrorCode::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::AppError;
+    use actix_web::ResponseError;
+
+    #[test]
+    fn codes_serialize_as_their_catalogue_names() {
+        for code in ErrorCode::ALL {
+            let json = serde_json::to_string(&code).unwrap();
+            assert_eq!(json, format!("\"{}\"", code.as_str()));
+            assert_eq!(serde_json::from_str::<ErrorCode>(&json).unwrap(), code);
+        }
+        assert_eq!(
+            serde_json::from_str::<ErrorCode>("\"ADDED_LATER\"").unwrap(),
+            ErrorCode::Unknown
+        );
+        assert_eq!(ErrorCode::ExchangeRejected.exit_code(), 7);
+    }
+
+    #[actix_web::test]
+    async fn server_errors_keep_their_code_on_the_client() {
+        let error = AppError::BotNotFound("alpha".to_string());
+        assert_eq!(error.code(), ErrorCode::BotNotFound);
+        let body = actix_web::body::to_bytes(error.error_response().into_body())
+            .await
+            .unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains(r#""error_code":"BOT_NOT_FOUND""#), "{}", body);
+        assert_eq!(
+            AppError::HttpError(404, body).code(),
+            ErrorCode::BotNotFound
+        );
+        assert_eq!(
+            AppError::HttpError(404, "Not Found".to_string()).code(),
+            ErrorCode::NotFound
+        );
+    }
+}
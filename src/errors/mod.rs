@@ -1,12 +1,21 @@
 // src/errors/mod.rs
 //pub mod api;
 pub mod app;
+pub mod code;
 //pub mod server;
 
 pub use app::AppError;
+pub use code::ErrorCode;
 
 //pub use api::ApiError;
 //pub use server::ServerError;
+/// The code of the [`AppError`] inside `err`, as the CLI wraps them.
+pub fn code_of(err: &std::io::Error) -> Option<ErrorCode> {
+    err.get_ref()
+        .and_then(|inner| inner.downcast_ref::<AppError>())
+        .map(AppError::code)
+}
+
 pub fn map_to_io_error<E: std::fmt::Display>(err: E) -> std::io::Error {
-    std::io::Error::new(std::io::ErrorKind::Other, format!("{}", err))
+    std::io::Error::other(format!("{}", err))
 }
@@ -1,44 +1,922 @@
 // src/exchange/binance.rs
+//! Binance spot API.
+//!
+//! Orders go to `POST /api/v3/order`, and one-cancels-the-other pairs to
+//! `POST /api/v3/orderList/oco`, signed with the bot's `api_key`/`api_secret`. Besides market
+//! and limit orders the adapter places stop-market (`STOP_LOSS`) and stop-limit
+//! (`STOP_LOSS_LIMIT`) orders, their take-profit counterparts, trailing stops (`STOP_LOSS`
+//! with a `trailingDelta`) and OCO pairs of a take-profit limit order and a stop, as
+//! [requested](OrderRequest). Orders are cancelled by their exchange order ID. Bots can point
+//! `rest_endpoint` at the testnet (`https://testnet.binance.vision`).
+//!
+//! Deposits and withdrawals come from the wallet endpoints `GET /sapi/v1/capital/deposit/hisrec`
+//! and `GET /sapi/v1/capital/withdraw/history`, which need a key allowed to read the wallet.
+//! What a key is allowed to do comes from `GET /sapi/v1/account/apiRestrictions`.
 
-use super::Exchange;
-use crate::bot::Bot;
+use super::{
+    Asset, Balance, Exchange, ExecutionReport, KeyPermissions, OpenOrder, OrderRequest, Position,
+    Symbol, TimeInForce, Transfer, TransferKind,
+};
+use crate::bot::model::Bot;
+use crate::utils::crypto::hmac_sha256_hex;
 use async_trait::async_trait;
-// use log::info;
-use reqwest::Client;
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use reqwest::{Client, Method};
+use rust_decimal::Decimal;
 use std::error::Error;
+use url::form_urlencoded;
 
-pub struct BinanceExchange {
-    // Add Binance-specific configurations here if needed
+pub const DEFAULT_ENDPOINT: &str = "https://api.binance.com";
+
+/// How long (ms) a signed request stays valid.
+const RECV_WINDOW: u64 = 5000;
+
+/// Query parameters of a request, before the timestamp and signature are added.
+type Params = Vec<(&'static str, String)>;
+
+/// Binance error code of a cancellation of an order it does not know (any more).
+const UNKNOWN_ORDER: i64 = -2011;
+
+/// Endpoint and parameters placing `order` for `quantity` of `symbol`.
+fn order_params(
+    order: &OrderRequest,
+    symbol: &str,
+    quantity: Decimal,
+) -> Result<(&'static str, Params), String> {
+    let side = if order.side().is_buy() { "BUY" } else { "SELL" };
+    let mut params = vec![
+        ("symbol", symbol.to_string()),
+        ("side", side.to_string()),
+        ("quantity", quantity.to_string()),
+    ];
+    // Stop and take-profit orders are limit orders once triggered if given a limit price
+    let mut triggered =
+        |kind: &str, trigger: Decimal, limit: &Option<Decimal>, tif: &TimeInForce| {
+            params.push(("stopPrice", trigger.to_string()));
+            match limit {
+                Some(limit) => params.extend([
+                    ("type", format!("{}_LIMIT", kind)),
+                    ("price", limit.to_string()),
+                    ("timeInForce", tif.to_string()),
+                ]),
+                None => params.push(("type", kind.to_string())),
+            }
+        };
+    let path = match order {
+        OrderRequest::Market { .. } => {
+            params.push(("type", "MARKET".to_string()));
+            "/api/v3/order"
+        }
+        OrderRequest::Limit {
+            price,
+            time_in_force,
+            ..
+        } => {
+            params.extend([
+                ("type", "LIMIT".to_string()),
+                ("price", price.to_string()),
+                ("timeInForce", time_in_force.to_string()),
+            ]);
+            "/api/v3/order"
+        }
+        OrderRequest::StopLoss {
+            stop_price,
+            limit_price,
+            time_in_force,
+            ..
+        } => {
+            triggered("STOP_LOSS", *stop_price, limit_price, time_in_force);
+            "/api/v3/order"
+        }
+        OrderRequest::TakeProfit {
+            trigger_price,
+            limit_price,
+            time_in_force,
+            ..
+        } => {
+            triggered("TAKE_PROFIT", *trigger_price, limit_price, time_in_force);
+            "/api/v3/order"
+        }
+        OrderRequest::TrailingStop {
+            trailing_delta_bps,
+            activation_price,
+            ..
+        } => {
+            params.extend([
+                ("type", "STOP_LOSS".to_string()),
+                ("trailingDelta", trailing_delta_bps.to_string()),
+            ]);
+            if let Some(activation) = activation_price {
+                params.push(("stopPrice", activation.to_string()));
+            }
+            "/api/v3/order"
+        }
+        OrderRequest::Oco {
+            side: order_side,
+            take_profit,
+            stop_price,
+            stop_limit_price,
+        } => {
+            // Selling takes profit above the market and stops out below it, buying the
+            // other way round
+            let profit_above = !order_side.is_buy();
+            if profit_above != (take_profit > stop_price) {
+                return Err(format!(
+                    "an OCO {} needs its take-profit {} the stop price",
+                    order_side,
+                    if profit_above { "above" } else { "below" }
+                ));
+            }
+            let [profit_type, profit_price, _, _] = leg(profit_above);
+            let [stop_type, stop_limit, stop_trigger, stop_tif] = leg(!profit_above);
+            params.extend([
+                (profit_type, "LIMIT_MAKER".to_string()),
+                (profit_price, take_profit.to_string()),
+                (stop_trigger, stop_price.to_string()),
+            ]);
+            match stop_limit_price {
+                Some(limit) => params.extend([
+                    (stop_type, "STOP_LOSS_LIMIT".to_string()),
+                    (stop_limit, limit.to_string()),
+                    (stop_tif, "GTC".to_string()),
+                ]),
+                None => params.push((stop_type, "STOP_LOSS".to_string())),
+            }
+            "/api/v3/orderList/oco"
+        }
+    };
+    params.push(("newOrderRespType", "FULL".to_string()));
+    Ok((path, params))
+}
+
+/// Names of the type, price, stop price and time-in-force parameters of the OCO leg above
+/// the market, or below it.
+fn leg(above: bool) -> [&'static str; 4] {
+    if above {
+        [
+            "aboveType",
+            "abovePrice",
+            "aboveStopPrice",
+            "aboveTimeInForce",
+        ]
+    } else {
+        [
+            "belowType",
+            "belowPrice",
+            "belowStopPrice",
+            "belowTimeInForce",
+        ]
+    }
 }
 
+#[derive(Default)]
+pub struct BinanceExchange {}
+
 impl BinanceExchange {
     pub fn new() -> Self {
-        BinanceExchange {
-            // Initialize configurations if necessary
+        BinanceExchange {}
+    }
+}
+
+/// Binance spot symbol of a symbol in any form, e.g. `BTCUSDT` for `BTC/USDT`.
+fn spot_symbol(symbol: &str) -> Result<String, String> {
+    let symbol = Symbol::parse(symbol)?;
+    if symbol.is_perpetual() {
+        return Err(format!(
+            "Binance spot does not trade the perpetual {}",
+            symbol
+        ));
+    }
+    Ok(symbol.pair(""))
+}
+
+/// Hex HMAC-SHA256 of the query string, Binance's `signature` parameter.
+fn sign(api_secret: &str, query: &str) -> String {
+    hmac_sha256_hex(api_secret.as_bytes(), query.as_bytes())
+}
+
+/// Binance's response to an order it refused.
+#[derive(Debug)]
+struct Rejection {
+    code: i64,
+    message: String,
+}
+
+impl std::fmt::Display for Rejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Binance rejected the request ({}): {}",
+            self.code, self.message
+        )
+    }
+}
+
+impl Error for Rejection {}
+
+/// Send a signed request with `params` to `path` and return the response body.
+async fn signed(
+    method: Method,
+    path: &str,
+    params: Params,
+    bot: &Bot,
+    client: &Client,
+) -> Result<serde_json::Value, Box<dyn Error + Send + Sync>> {
+    let (Some(api_key), Some(api_secret)) = (&bot.api_key, &bot.api_secret) else {
+        return Err("Binance bots need an api_key and api_secret".into());
+    };
+    let query = form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(params)
+        .append_pair("timestamp", &Utc::now().timestamp_millis().to_string())
+        .append_pair("recvWindow", &RECV_WINDOW.to_string())
+        .finish();
+    let endpoint = bot.rest_endpoint.as_deref().unwrap_or(DEFAULT_ENDPOINT);
+    let url = format!(
+        "{}{}?{}&signature={}",
+        endpoint.trim_end_matches('/'),
+        path,
+        query,
+        sign(api_secret, &query)
+    );
+
+    let response = client
+        .request(method, url)
+        .header("X-MBX-APIKEY", api_key)
+        .send()
+        .await?;
+    let status = response.status();
+    let body: serde_json::Value = response.json().await.unwrap_or_default();
+    if !status.is_success() {
+        return Err(match (body["code"].as_i64(), body["msg"].as_str()) {
+            (Some(code), Some(message)) => Box::new(Rejection {
+                code,
+                message: message.to_string(),
+            }),
+            _ => format!("Binance responded with HTTP {}", status).into(),
+        });
+    }
+    Ok(body)
+}
+
+/// Name of the status of a deposit or withdrawal, as Binance numbers them.
+fn transfer_status(kind: TransferKind, status: i64) -> String {
+    let name = match (kind, status) {
+        (TransferKind::Deposit, 0) => "pending",
+        (TransferKind::Deposit, 1) => "success",
+        (TransferKind::Deposit, 2) => "rejected",
+        (TransferKind::Deposit, 6) => "credited",
+        (TransferKind::Withdrawal, 0) => "email_sent",
+        (TransferKind::Withdrawal, 1) => "cancelled",
+        (TransferKind::Withdrawal, 2) => "awaiting_approval",
+        (TransferKind::Withdrawal, 3) => "rejected",
+        (TransferKind::Withdrawal, 4) => "processing",
+        (TransferKind::Withdrawal, 5) => "failure",
+        (TransferKind::Withdrawal, 6) => "completed",
+        _ => return status.to_string(),
+    };
+    name.to_string()
+}
+
+/// A deposit or withdrawal from Binance's history of them.
+fn transfer(kind: TransferKind, record: &serde_json::Value) -> Option<Transfer> {
+    let timestamp = match kind {
+        TransferKind::Deposit => Utc.timestamp_millis_opt(record["insertTime"].as_i64()?),
+        // e.g. "2024-05-01 12:00:00", in UTC
+        TransferKind::Withdrawal => {
+            let applied = record["applyTime"].as_str()?;
+            let applied = NaiveDateTime::parse_from_str(applied, "%Y-%m-%d %H:%M:%S").ok()?;
+            Utc.from_local_datetime(&applied)
         }
     }
+    .single()?;
+    let id = match &record["id"] {
+        serde_json::Value::String(id) => id.clone(),
+        id => id.as_u64()?.to_string(),
+    };
+    Some(Transfer {
+        id,
+        kind,
+        asset: record["coin"].as_str()?.to_string(),
+        amount: record["amount"].as_str()?.parse().ok()?,
+        address: record["address"].as_str().map(str::to_string),
+        status: transfer_status(kind, record["status"].as_i64()?),
+        timestamp,
+    })
+}
+
+/// Report of an order from Binance's `FULL` response, or of an OCO pair from its order list.
+fn report(
+    response: &serde_json::Value,
+    symbol: &str,
+    submitted_at: DateTime<Utc>,
+) -> ExecutionReport {
+    let number = |value: &serde_json::Value| value.as_str().and_then(|v| v.parse::<Decimal>().ok());
+    let acknowledged_at = response["transactTime"]
+        .as_i64()
+        .or_else(|| response["transactionTime"].as_i64())
+        .and_then(DateTime::from_timestamp_millis)
+        .unwrap_or_else(Utc::now);
+    let mut report = ExecutionReport {
+        acknowledged_at,
+        ..ExecutionReport::acknowledged(submitted_at)
+    };
+    if response
+        .get("orderListId")
+        .is_some_and(|id| id.as_i64() != Some(-1))
+    {
+        report.order_id = response["orderListId"].as_i64().map(|id| id.to_string());
+        return report;
+    }
+    report.order_id = response["orderId"].as_i64().map(|id| id.to_string());
+
+    let filled = number(&response["executedQty"])
+        .filter(|quantity| quantity.is_sign_positive() && !quantity.is_zero());
+    let Some(filled) = filled else {
+        return report;
+    };
+    report.filled_quantity = Some(filled);
+    report.average_price = number(&response["cummulativeQuoteQty"]).map(|quote| quote / filled);
+    // Commissions are paid in the quote asset, the base asset or BNB; the latter is not counted
+    let mut fees = None;
+    for fill in response["fills"].as_array().into_iter().flatten() {
+        let (Some(commission), Some(asset)) = (
+            number(&fill["commission"]),
+            fill["commissionAsset"].as_str(),
+        ) else {
+            continue;
+        };
+        let fee = if symbol.ends_with(asset) {
+            commission
+        } else if symbol.starts_with(asset) {
+            commission * number(&fill["price"]).unwrap_or_default()
+        } else {
+            continue;
+        };
+        *fees.get_or_insert(Decimal::ZERO) += fee;
+    }
+    report.fees = fees;
+    report
 }
 
 #[async_trait]
 impl Exchange for BinanceExchange {
     async fn execute_trade(
         &self,
-        action: &str,
+        order: &OrderRequest,
         symbol_or_contract: &str, // For Binance, this is the trading pair symbol
-        price: f64,
-        slippage: f64,
-        _bot: &Bot, // Prefixed with an underscore to silence the warning
-        _client: &Client,
-    ) -> Result<(), Box<dyn Error>> {
+        quantity: Decimal,
+        _slippage: f64,
+        bot: &Bot,
+        client: &Client,
+    ) -> Result<ExecutionReport, Box<dyn Error>> {
+        let symbol = spot_symbol(symbol_or_contract)?;
+        let (path, params) = order_params(order, &symbol, quantity)?;
         log::info!(
-            "Executing Binance trade: Action: {}, Symbol: {}, Price: {}, Slippage: {}",
-            action,
-            symbol_or_contract,
-            price,
-            slippage
+            "Executing Binance trade: {} {} ({})",
+            quantity,
+            symbol,
+            order
+        );
+        let submitted_at = Utc::now();
+        let response = signed(Method::POST, path, params, bot, client)
+            .await
+            .map_err(|e| e as Box<dyn Error>)?;
+        let report = report(&response, &symbol, submitted_at);
+        log::info!(
+            "Binance accepted order {}",
+            report.order_id.as_deref().unwrap_or("?")
+        );
+        Ok(report)
+    }
+
+    /// Cancel the order with exchange order ID `order_id`, or every order of the OCO pair
+    /// with order list ID `order_id`.
+    async fn cancel_order(
+        &self,
+        symbol: &str,
+        order_id: &str,
+        bot: &Bot,
+        client: &Client,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let id: u64 = order_id
+            .parse()
+            .map_err(|_| format!("'{}' is not a Binance order ID", order_id))?;
+        let symbol = spot_symbol(symbol)?;
+        let params = vec![("symbol", symbol.clone()), ("orderId", id.to_string())];
+        let result = match signed(Method::DELETE, "/api/v3/order", params, bot, client).await {
+            Err(e)
+                if e.downcast_ref::<Rejection>()
+                    .is_some_and(|r| r.code == UNKNOWN_ORDER) =>
+            {
+                // Not a single order; maybe an order list
+                let params = vec![("symbol", symbol.clone()), ("orderListId", id.to_string())];
+                signed(Method::DELETE, "/api/v3/orderList", params, bot, client).await
+            }
+            result => result,
+        };
+        result?;
+        log::info!("Binance cancelled order {} on {}", id, symbol);
+        Ok(true)
+    }
+
+    /// Open spot orders on every symbol; the quantity is what is left to fill.
+    async fn open_orders(
+        &self,
+        bot: &Bot,
+        client: &Client,
+    ) -> Result<Option<Vec<OpenOrder>>, Box<dyn Error + Send + Sync>> {
+        let orders = signed(Method::GET, "/api/v3/openOrders", Vec::new(), bot, client).await?;
+        let amount = |value: &serde_json::Value| {
+            value
+                .as_str()
+                .and_then(|amount| amount.parse::<Decimal>().ok())
+                .unwrap_or_default()
+        };
+        let orders = orders
+            .as_array()
+            .ok_or("Binance sent open orders that are not a list")?
+            .iter()
+            .filter_map(|order| {
+                Some(OpenOrder {
+                    order_id: order["orderId"].as_u64()?.to_string(),
+                    client_order_id: order["clientOrderId"].as_str()?.to_string(),
+                    symbol: order["symbol"].as_str()?.to_string(),
+                    side: order["side"].as_str()?.to_lowercase(),
+                    price: amount(&order["price"]),
+                    quantity: amount(&order["origQty"]) - amount(&order["executedQty"]),
+                })
+            })
+            .collect();
+        Ok(Some(orders))
+    }
+
+    /// Non-zero balances of the spot account.
+    async fn get_balances(
+        &self,
+        bot: &Bot,
+        client: &Client,
+    ) -> Result<Option<Vec<Balance>>, Box<dyn Error + Send + Sync>> {
+        let params = vec![("omitZeroBalances", "true".to_string())];
+        let account = signed(Method::GET, "/api/v3/account", params, bot, client).await?;
+        let amount = |value: &serde_json::Value| {
+            value
+                .as_str()
+                .and_then(|amount| amount.parse::<Decimal>().ok())
+                .unwrap_or_default()
+        };
+        let balances = account["balances"]
+            .as_array()
+            .ok_or("Binance sent an account without balances")?
+            .iter()
+            .filter_map(|balance| {
+                Some(Balance {
+                    asset: Asset::new(balance["asset"].as_str()?).ok()?,
+                    free: amount(&balance["free"]),
+                    locked: amount(&balance["locked"]),
+                })
+            })
+            .filter(|balance| !balance.free.is_zero() || !balance.locked.is_zero())
+            .collect();
+        Ok(Some(balances))
+    }
+
+    /// Permissions of the key; trading means spot and margin trading.
+    async fn key_permissions(
+        &self,
+        bot: &Bot,
+        client: &Client,
+    ) -> Result<Option<KeyPermissions>, Box<dyn Error + Send + Sync>> {
+        let path = "/sapi/v1/account/apiRestrictions";
+        let restrictions = signed(Method::GET, path, Vec::new(), bot, client).await?;
+        let flag = |name: &str| restrictions[name].as_bool();
+        let (Some(can_read), Some(can_trade), Some(can_withdraw)) = (
+            flag("enableReading"),
+            flag("enableSpotAndMarginTrading"),
+            flag("enableWithdrawals"),
+        ) else {
+            return Err("Binance sent API key restrictions without permissions".into());
+        };
+        Ok(Some(KeyPermissions {
+            can_read,
+            can_trade,
+            can_withdraw,
+            ip_restricted: flag("ipRestrict"),
+            checked_at: Utc::now(),
+        }))
+    }
+
+    /// Deposits and withdrawals of the wallet, oldest first.
+    async fn transfers(
+        &self,
+        bot: &Bot,
+        client: &Client,
+        since: DateTime<Utc>,
+    ) -> Result<Option<Vec<Transfer>>, Box<dyn Error + Send + Sync>> {
+        let mut transfers = Vec::new();
+        for (kind, path) in [
+            (TransferKind::Deposit, "/sapi/v1/capital/deposit/hisrec"),
+            (
+                TransferKind::Withdrawal,
+                "/sapi/v1/capital/withdraw/history",
+            ),
+        ] {
+            let params = vec![("startTime", since.timestamp_millis().to_string())];
+            let records = signed(Method::GET, path, params, bot, client).await?;
+            let records = records
+                .as_array()
+                .ok_or_else(|| format!("Binance sent a {} history that is not a list", kind))?;
+            transfers.extend(records.iter().filter_map(|record| transfer(kind, record)));
+        }
+        transfers.sort_by_key(|transfer| transfer.timestamp);
+        Ok(Some(transfers))
+    }
+
+    /// Spot accounts hold balances only; they never have open positions.
+    async fn get_positions(
+        &self,
+        _bot: &Bot,
+        _client: &Client,
+    ) -> Result<Option<Vec<Position>>, Box<dyn Error + Send + Sync>> {
+        Ok(Some(Vec::new()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::secrets::Secrets;
+    use crate::exchange::Side;
+    use mockito::Matcher;
+    use rust_decimal::dec;
+
+    fn bot(endpoint: String) -> Bot {
+        Bot {
+            exchange: "binance".to_string(),
+            api_key: Some("key".to_string()),
+            api_secret: Some("secret".to_string()),
+            rest_endpoint: Some(endpoint),
+            ..Default::default()
+        }
+    }
+
+    fn query(pairs: &[(&str, &str)]) -> Matcher {
+        Matcher::AllOf(
+            pairs
+                .iter()
+                .map(|(key, value)| Matcher::UrlEncoded(key.to_string(), value.to_string()))
+                .chain([Matcher::Regex("signature=[0-9a-f]{64}".to_string())])
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn orders_map_to_binance_parameters() {
+        assert_eq!(
+            sign("secret", "symbol=BTCUSDT&side=BUY"),
+            "83ef3517b61b829b8755e0f6dcff8b6b1c29f47ae72076ecd2aee6237ffbc10f"
         );
+        let stop = OrderRequest::StopLoss {
+            side: Side::Sell,
+            stop_price: dec!(95),
+            limit_price: Some(dec!(94.5)),
+            time_in_force: TimeInForce::Gtc,
+        };
+        let (path, params) = order_params(&stop, "BTCUSDT", dec!(0.5)).unwrap();
+        assert_eq!(path, "/api/v3/order");
+        assert!(params.contains(&("type", "STOP_LOSS_LIMIT".to_string())));
+        assert!(params.contains(&("stopPrice", "95".to_string())));
+        assert!(params.contains(&("price", "94.5".to_string())));
+
+        let take_profit = OrderRequest::TakeProfit {
+            side: Side::Sell,
+            trigger_price: dec!(110),
+            limit_price: None,
+            time_in_force: TimeInForce::Gtc,
+        };
+        let (_, params) = order_params(&take_profit, "BTCUSDT", dec!(0.5)).unwrap();
+        assert!(params.contains(&("type", "TAKE_PROFIT".to_string())));
+        assert!(params.contains(&("stopPrice", "110".to_string())));
+        assert!(!params.iter().any(|(key, _)| *key == "price"));
+
+        let trailing = OrderRequest::TrailingStop {
+            side: Side::Sell,
+            trailing_delta_bps: 200,
+            activation_price: None,
+        };
+        let (_, params) = order_params(&trailing, "BTCUSDT", dec!(0.5)).unwrap();
+        assert!(params.contains(&("type", "STOP_LOSS".to_string())));
+        assert!(params.contains(&("trailingDelta", "200".to_string())));
+
+        let fok = OrderRequest::Limit {
+            side: Side::Buy,
+            price: dec!(90),
+            time_in_force: TimeInForce::Fok,
+        };
+        let (_, params) = order_params(&fok, "BTCUSDT", dec!(0.5)).unwrap();
+        assert!(params.contains(&("timeInForce", "FOK".to_string())));
+
+        // A sell takes profit above the market, a buy below it
+        let oco = |side| OrderRequest::Oco {
+            side,
+            take_profit: dec!(110),
+            stop_price: dec!(95),
+            stop_limit_price: None,
+        };
+        let (path, params) = order_params(&oco(Side::Sell), "BTCUSDT", dec!(0.5)).unwrap();
+        assert_eq!(path, "/api/v3/orderList/oco");
+        assert!(params.contains(&("aboveType", "LIMIT_MAKER".to_string())));
+        assert!(params.contains(&("abovePrice", "110".to_string())));
+        assert!(params.contains(&("belowType", "STOP_LOSS".to_string())));
+        assert!(params.contains(&("belowStopPrice", "95".to_string())));
+        assert!(order_params(&oco(Side::Buy), "BTCUSDT", dec!(0.5)).is_err());
+    }
+
+    #[tokio::test]
+    async fn orders_are_placed_and_cancelled() {
+        let mut server = mockito::Server::new_async().await;
+        let bot = bot(server.url());
+        let client = Client::new();
+        let exchange = BinanceExchange::new();
+
+        let market = server
+            .mock("POST", "/api/v3/order")
+            .match_header("X-MBX-APIKEY", "key")
+            .match_query(query(&[
+                ("symbol", "BTCUSDT"),
+                ("side", "BUY"),
+                ("type", "MARKET"),
+                ("quantity", "0.5"),
+            ]))
+            .with_body(
+                r#"{"symbol":"BTCUSDT","orderId":28,"orderListId":-1,"transactTime":1700000000000,
+                    "executedQty":"0.5","cummulativeQuoteQty":"50.5","status":"FILLED",
+                    "fills":[{"price":"100","qty":"0.3","commission":"0.03","commissionAsset":"USDT"},
+                             {"price":"102.5","qty":"0.2","commission":"0.0002","commissionAsset":"BTC"},
+                             {"price":"102.5","qty":"0","commission":"0.1","commissionAsset":"BNB"}]}"#,
+            )
+            .create_async()
+            .await;
+        let report = exchange
+            .execute_trade(
+                &OrderRequest::Market { side: Side::Buy },
+                "btcusdt",
+                dec!(0.5),
+                0.0,
+                &bot,
+                &client,
+            )
+            .await
+            .unwrap();
+        market.assert_async().await;
+        assert_eq!(report.order_id.as_deref(), Some("28"));
+        assert_eq!(report.filled_quantity, Some(dec!(0.5)));
+        assert_eq!(report.average_price, Some(dec!(101)));
+        assert_eq!(report.fees, Some(dec!(0.0505)));
+        assert_eq!(report.acknowledged_at.timestamp(), 1_700_000_000);
+
+        let oco = server
+            .mock("POST", "/api/v3/orderList/oco")
+            .match_query(query(&[
+                ("side", "SELL"),
+                ("aboveType", "LIMIT_MAKER"),
+                ("belowType", "STOP_LOSS_LIMIT"),
+                ("belowPrice", "94"),
+                ("belowTimeInForce", "GTC"),
+            ]))
+            .with_body(
+                r#"{"orderListId":7,"contingencyType":"OCO","transactionTime":1700000000000}"#,
+            )
+            .create_async()
+            .await;
+        let order = OrderRequest::Oco {
+            side: Side::Sell,
+            take_profit: dec!(110),
+            stop_price: dec!(95),
+            stop_limit_price: Some(dec!(94)),
+        };
+        let report = exchange
+            .execute_trade(&order, "BTCUSDT", dec!(0.5), 0.0, &bot, &client)
+            .await
+            .unwrap();
+        oco.assert_async().await;
+        assert_eq!(report.order_id.as_deref(), Some("7"));
+        assert_eq!(report.filled_quantity, None);
+
+        // Cancelling the ID of an order list falls back to cancelling the list
+        server
+            .mock("DELETE", "/api/v3/order")
+            .match_query(query(&[("symbol", "BTCUSDT"), ("orderId", "7")]))
+            .with_status(400)
+            .with_body(r#"{"code":-2011,"msg":"Unknown order sent."}"#)
+            .create_async()
+            .await;
+        let list = server
+            .mock("DELETE", "/api/v3/orderList")
+            .match_query(query(&[("orderListId", "7")]))
+            .with_body(r#"{"orderListId":7,"listStatusType":"ALL_DONE"}"#)
+            .create_async()
+            .await;
+        assert!(exchange
+            .cancel_order("BTCUSDT", "7", &bot, &client)
+            .await
+            .unwrap());
+        list.assert_async().await;
+
+        server
+            .mock("POST", "/api/v3/order")
+            .match_query(query(&[("type", "LIMIT"), ("price", "90")]))
+            .with_status(400)
+            .with_body(r#"{"code":-2010,"msg":"Account has insufficient balance."}"#)
+            .create_async()
+            .await;
+        let err = exchange
+            .execute_trade(
+                &OrderRequest::Limit {
+                    side: Side::Buy,
+                    price: dec!(90),
+                    time_in_force: TimeInForce::Gtc,
+                },
+                "BTCUSDT",
+                dec!(0.5),
+                0.0,
+                &bot,
+                &client,
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("insufficient balance"));
+    }
+
+    #[tokio::test]
+    async fn balances_are_listed() {
+        let mut server = mockito::Server::new_async().await;
+        let bot = bot(server.url());
+        let client = Client::new();
+        server
+            .mock("GET", "/api/v3/account")
+            .match_header("X-MBX-APIKEY", "key")
+            .match_query(query(&[("omitZeroBalances", "true")]))
+            .with_body(
+                r#"{"canTrade":true,"balances":[{"asset":"BTC","free":"0.5","locked":"0.1"},
+                    {"asset":"USDT","free":"1000","locked":"0"},
+                    {"asset":"BNB","free":"0","locked":"0"}]}"#,
+            )
+            .create_async()
+            .await;
+
+        let exchange = BinanceExchange::new();
+        let balances = exchange.get_balances(&bot, &client).await.unwrap().unwrap();
+        assert_eq!(
+            balances,
+            [
+                Balance {
+                    asset: Asset::new("BTC").unwrap(),
+                    free: dec!(0.5),
+                    locked: dec!(0.1)
+                },
+                Balance {
+                    asset: Asset::new("USDT").unwrap(),
+                    free: dec!(1000),
+                    locked: dec!(0)
+                },
+            ]
+        );
+        let positions = exchange.get_positions(&bot, &client).await.unwrap();
+        assert_eq!(positions, Some(Vec::new()));
+    }
+
+    #[tokio::test]
+    async fn key_permissions_are_read() {
+        let mut server = mockito::Server::new_async().await;
+        let bot = bot(server.url());
+        server
+            .mock("GET", "/sapi/v1/account/apiRestrictions")
+            .match_header("X-MBX-APIKEY", "key")
+            .match_query(query(&[]))
+            .with_body(
+                r#"{"ipRestrict":false,"createTime":1698645219000,"enableReading":true,
+                    "enableWithdrawals":true,"enableInternalTransfer":false,
+                    "enableSpotAndMarginTrading":false,"enableFutures":false}"#,
+            )
+            .create_async()
+            .await;
+
+        let permissions = BinanceExchange::new()
+            .key_permissions(&bot, &Client::new())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(permissions.can_read && permissions.can_withdraw && !permissions.can_trade);
+        assert_eq!(permissions.ip_restricted, Some(false));
+    }
+
+    #[tokio::test]
+    async fn deposits_and_withdrawals_are_listed() {
+        let mut server = mockito::Server::new_async().await;
+        let bot = bot(server.url());
+        let since = Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap();
+        let start = since.timestamp_millis().to_string();
+        server
+            .mock("GET", "/sapi/v1/capital/deposit/hisrec")
+            .match_query(query(&[("startTime", &start)]))
+            .with_body(
+                r#"[{"id":"769800519366885376","amount":"0.001","coin":"BNB","status":1,
+                    "address":"bnb136ns6lfw4zs5hg4n85vdthaad7hq5m4gtkgf23","insertTime":1714557600000}]"#,
+            )
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/sapi/v1/capital/withdraw/history")
+            .match_query(query(&[("startTime", &start)]))
+            .with_body(
+                r#"[{"id":"b6ae22b3aa844210a7041aee7589627c","amount":"8.91","coin":"USDT",
+                    "status":6,"address":"0x94df8b352de7f46f64b01d3666bf6e936e44ce60",
+                    "applyTime":"2024-05-01 08:00:00"}]"#,
+            )
+            .create_async()
+            .await;
+
+        let transfers = BinanceExchange::new()
+            .transfers(&bot, &Client::new(), since)
+            .await
+            .unwrap()
+            .unwrap();
+        let summary: Vec<_> = transfers
+            .iter()
+            .map(|t| (t.kind, t.asset.as_str(), t.amount, t.status.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (TransferKind::Withdrawal, "USDT", dec!(8.91), "completed"),
+                (TransferKind::Deposit, "BNB", dec!(0.001), "success"),
+            ]
+        );
+        assert_eq!(
+            transfers[0].timestamp,
+            Utc.with_ymd_and_hms(2024, 5, 1, 8, 0, 0).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn open_orders_are_listed_and_cancelled() {
+        let mut server = mockito::Server::new_async().await;
+        let bot = bot(server.url());
+        let client = Client::new();
+        server
+            .mock("GET", "/api/v3/openOrders")
+            .match_header("X-MBX-APIKEY", "key")
+            .match_query(query(&[]))
+            .with_body(
+                r#"[{"symbol":"BTCUSDT","orderId":42,"clientOrderId":"ord-1","price":"60000.00",
+                    "origQty":"0.50","executedQty":"0.20","side":"BUY","type":"LIMIT"}]"#,
+            )
+            .create_async()
+            .await;
+        let cancelled = server
+            .mock("DELETE", "/api/v3/order")
+            .match_query(query(&[("symbol", "BTCUSDT"), ("orderId", "42")]))
+            .with_body(r#"{"symbol":"BTCUSDT","orderId":42,"status":"CANCELED"}"#)
+            .create_async()
+            .await;
+
+        let exchange = BinanceExchange::new();
+        let open = exchange.open_orders(&bot, &client).await.unwrap().unwrap();
+        assert_eq!(
+            open,
+            [OpenOrder {
+                order_id: "42".to_string(),
+                client_order_id: "ord-1".to_string(),
+                symbol: "BTCUSDT".to_string(),
+                side: "buy".to_string(),
+                price: dec!(60000),
+                quantity: dec!(0.3),
+            }]
+        );
+        assert!(exchange
+            .cancel_order("btcusdt", "42", &bot, &client)
+            .await
+            .unwrap());
+        cancelled.assert_async().await;
+    }
+
+    /// Reads the account on the spot testnet with the keys in `BINANCE_TESTNET_API_KEY` and
+    /// `BINANCE_TESTNET_API_SECRET`, or `secrets.test.toml`:
+    /// `cargo test -- --ignored testnet`
+    #[tokio::test]
+    #[ignore]
+    async fn testnet_account_is_readable() {
+        let bot = Bot {
+            api_key: Some("env:BINANCE_TESTNET_API_KEY".to_string()),
+            api_secret: Some("env:BINANCE_TESTNET_API_SECRET".to_string()),
+            ..bot("https://testnet.binance.vision".to_string())
+        };
+        let bot = Secrets::for_tests().resolve_bot(&bot).unwrap();
+        let client = Client::new();
+        let exchange = BinanceExchange::new();
 
-        // Placeholder logic. Add Binance-specific trade execution logic here.
-        Ok(())
+        let balances = exchange.get_balances(&bot, &client).await.unwrap();
+        assert!(balances.is_some_and(|balances| !balances.is_empty()));
+        assert!(exchange.open_orders(&bot, &client).await.unwrap().is_some());
     }
 }
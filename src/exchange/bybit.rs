@@ -0,0 +1,238 @@
+// src/exchange/bybit.rs
+//! Bybit V5 unified trading API.
+//!
+//! Orders go to `POST /v5/order/create`, signed with the bot's `api_key`/`api_secret`.
+//! Perpetual symbols (`BTCUSDT.P`, `BTC-USDT-PERP`) trade linear perpetuals, anything else
+//! trades spot. Only market and limit orders are placed. Bots can point `rest_endpoint` at the
+//! testnet (`https://api-testnet.bybit.com`).
+
+use super::{Exchange, ExecutionReport, OrderRequest, Symbol};
+use crate::bot::model::Bot;
+use crate::utils::crypto::hmac_sha256_hex;
+use async_trait::async_trait;
+use chrono::Utc;
+use reqwest::Client;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use serde_json::json;
+use std::error::Error;
+
+pub const DEFAULT_ENDPOINT: &str = "https://api.bybit.com";
+
+/// How long (ms) a signed request stays valid.
+const RECV_WINDOW: u64 = 5000;
+
+#[derive(Default)]
+pub struct BybitExchange {}
+
+impl BybitExchange {
+    pub fn new() -> Self {
+        BybitExchange {}
+    }
+}
+
+/// Bybit product category and exchange symbol of a symbol: perpetuals trade as linear
+/// contracts.
+pub(super) fn category(symbol: &Symbol) -> (&'static str, String) {
+    let category = if symbol.is_perpetual() {
+        "linear"
+    } else {
+        "spot"
+    };
+    (category, symbol.pair(""))
+}
+
+/// `X-BAPI-SIGN` of a POST request: hex HMAC-SHA256 of timestamp, key, window and body.
+fn sign(api_secret: &str, timestamp: i64, api_key: &str, body: &str) -> String {
+    let payload = format!("{}{}{}{}", timestamp, api_key, RECV_WINDOW, body);
+    hmac_sha256_hex(api_secret.as_bytes(), payload.as_bytes())
+}
+
+/// Envelope of every V5 response.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Response {
+    ret_code: i64,
+    ret_msg: String,
+    #[serde(default)]
+    result: serde_json::Value,
+}
+
+#[async_trait]
+impl Exchange for BybitExchange {
+    async fn execute_trade(
+        &self,
+        order: &OrderRequest,
+        symbol_or_contract: &str,
+        quantity: Decimal,
+        _slippage: f64,
+        bot: &Bot,
+        client: &Client,
+    ) -> Result<ExecutionReport, Box<dyn Error>> {
+        let (Some(api_key), Some(api_secret)) = (&bot.api_key, &bot.api_secret) else {
+            return Err("Bybit bots need an api_key and api_secret".into());
+        };
+        let limit = order.market_or_limit("Bybit")?;
+        let side = if order.side().is_buy() { "Buy" } else { "Sell" };
+        let (category, symbol) = category(&Symbol::parse(symbol_or_contract)?);
+
+        let mut request = json!({
+            "category": category,
+            "symbol": symbol,
+            "side": side,
+            "qty": quantity.to_string(),
+        });
+        match limit {
+            Some((price, time_in_force)) => {
+                request["orderType"] = json!("Limit");
+                request["price"] = json!(price.to_string());
+                request["timeInForce"] = json!(time_in_force.to_string());
+            }
+            None => {
+                request["orderType"] = json!("Market");
+                if category == "spot" {
+                    // Spot market buys are sized in the quote coin unless told otherwise
+                    request["marketUnit"] = json!("baseCoin");
+                }
+            }
+        }
+        let body = request.to_string();
+
+        let submitted_at = Utc::now();
+        let timestamp = submitted_at.timestamp_millis();
+        let endpoint = bot.rest_endpoint.as_deref().unwrap_or(DEFAULT_ENDPOINT);
+        log::info!(
+            "Executing Bybit trade: {} {} {} ({})",
+            category,
+            quantity,
+            symbol,
+            order
+        );
+
+        let response = client
+            .post(format!(
+                "{}/v5/order/create",
+                endpoint.trim_end_matches('/')
+            ))
+            .header("Content-Type", "application/json")
+            .header("X-BAPI-API-KEY", api_key)
+            .header("X-BAPI-TIMESTAMP", timestamp.to_string())
+            .header("X-BAPI-RECV-WINDOW", RECV_WINDOW.to_string())
+            .header("X-BAPI-SIGN", sign(api_secret, timestamp, api_key, &body))
+            .body(body)
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(format!("Bybit responded with HTTP {}", status).into());
+        }
+        let response: Response = response.json().await?;
+        if response.ret_code != 0 {
+            return Err(format!(
+                "Bybit rejected the order ({}): {}",
+                response.ret_code, response.ret_msg
+            )
+            .into());
+        }
+        // Bybit only acknowledges the order here; fills are reported by the execution APIs
+        let order_id = response.result["orderId"].as_str().map(str::to_string);
+        log::info!(
+            "Bybit accepted order {}",
+            order_id.as_deref().unwrap_or("?")
+        );
+        Ok(ExecutionReport {
+            order_id,
+            ..ExecutionReport::acknowledged(submitted_at)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::{Side, TimeInForce};
+    use mockito::Matcher;
+    use rust_decimal::dec;
+
+    #[test]
+    fn requests_are_signed_and_categorised() {
+        assert_eq!(
+            sign("secret", 1_700_000_000_000, "key", r#"{"category":"spot"}"#),
+            "c446015edd3b8668e682c2c079fc88baed42c83b97af66d87d1d70f9e097118f"
+        );
+        let category = |symbol| category(&Symbol::parse(symbol).unwrap());
+        assert_eq!(category("BTCUSDT.P"), ("linear", "BTCUSDT".to_string()));
+        assert_eq!(category("BTC/USDT"), ("spot", "BTCUSDT".to_string()));
+    }
+
+    #[tokio::test]
+    async fn orders_are_posted_and_rejections_reported() {
+        let mut server = mockito::Server::new_async().await;
+        let bot = Bot {
+            exchange: "bybit".to_string(),
+            api_key: Some("key".to_string()),
+            api_secret: Some("secret".to_string()),
+            rest_endpoint: Some(server.url()),
+            ..Default::default()
+        };
+        let client = Client::new();
+        let exchange = BybitExchange::new();
+
+        let accepted = server
+            .mock("POST", "/v5/order/create")
+            .match_header("X-BAPI-API-KEY", "key")
+            .match_header("X-BAPI-SIGN", Matcher::Regex("^[0-9a-f]{64}$".to_string()))
+            .match_body(Matcher::PartialJson(json!({
+                "category": "linear",
+                "symbol": "BTCUSDT",
+                "side": "Buy",
+                "orderType": "Limit",
+                "qty": "0.5",
+                "price": "100",
+            })))
+            .with_body(r#"{"retCode":0,"retMsg":"OK","result":{"orderId":"1"}}"#)
+            .create_async()
+            .await;
+        let report = exchange
+            .execute_trade(
+                &OrderRequest::Limit {
+                    side: Side::Buy,
+                    price: dec!(100),
+                    time_in_force: TimeInForce::Gtc,
+                },
+                "BTCUSDT.P",
+                dec!(0.5),
+                0.0,
+                &bot,
+                &client,
+            )
+            .await
+            .unwrap();
+        accepted.assert_async().await;
+        assert_eq!(report.order_id.as_deref(), Some("1"));
+        assert_eq!(report.filled_quantity, None);
+
+        server
+            .mock("POST", "/v5/order/create")
+            .match_body(Matcher::PartialJson(json!({
+                "category": "spot",
+                "orderType": "Market",
+                "marketUnit": "baseCoin",
+            })))
+            .with_body(r#"{"retCode":170131,"retMsg":"Insufficient balance.","result":{}}"#)
+            .create_async()
+            .await;
+        let err = exchange
+            .execute_trade(
+                &OrderRequest::Market { side: Side::Sell },
+                "BTCUSDT",
+                dec!(0.5),
+                0.0,
+                &bot,
+                &client,
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Insufficient balance"));
+    }
+}
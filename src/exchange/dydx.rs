@@ -0,0 +1,849 @@
+// src/exchange/dydx.rs
+//! dYdX v4 perpetuals.
+//!
+//! dYdX v4 is a Cosmos chain: orders are `MsgPlaceOrder` transactions signed with the bot's
+//! `private_key` — a hex secp256k1 key or the wallet's mnemonic — and broadcast to a
+//! validator's REST API (`rpc_endpoint`), while markets, block heights and open orders come
+//! from the indexer (`rest_endpoint`, `https://indexer.dydx.trade` by default). Orders are
+//! placed from subaccount 0 of the wallet.
+//!
+//! Market orders are short-term IOC orders priced `slippage` (5% by default) through the
+//! oracle price that expire after a few blocks; limit orders are long-term orders resting for
+//! up to four weeks, and the only ones [`Exchange::cancel_order`] can cancel. Order IDs are
+//! the client IDs xtrade picks. Placing and cancelling orders costs no gas.
+
+use super::OpenOrder;
+use super::{Exchange, ExecutionReport, OrderRequest, Symbol};
+use crate::bot::model::Bot;
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use bech32::{Bech32, Hrp};
+use chrono::Utc;
+use k256::ecdsa::signature::hazmat::PrehashSigner;
+use k256::ecdsa::{Signature, SigningKey};
+use k256::elliptic_curve::PrimeField;
+use k256::Scalar;
+use reqwest::Client;
+use ring::{hmac, pbkdf2};
+use ripemd::Ripemd160;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::{dec, Decimal, RoundingStrategy};
+use serde::Deserialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::num::NonZeroU32;
+
+pub const DEFAULT_ENDPOINT: &str = "https://indexer.dydx.trade";
+pub const DEFAULT_RPC_ENDPOINT: &str = "https://dydx-rest.publicnode.com";
+
+/// Bech32 prefix of dYdX addresses.
+const ADDRESS_PREFIX: &str = "dydx";
+
+/// BIP-44 path of the first Cosmos account, as dYdX wallets derive it.
+const DERIVATION_PATH: [u32; 5] = [44 | HARDENED, 118 | HARDENED, HARDENED, 0, 0];
+const HARDENED: u32 = 0x8000_0000;
+
+/// Subaccount orders are placed from.
+const SUBACCOUNT: u32 = 0;
+
+/// Price tolerance of market orders unless the caller asks for another one.
+const DEFAULT_SLIPPAGE: Decimal = dec!(0.05);
+
+/// Blocks a short-term order stays valid for; the chain allows at most 20.
+const SHORT_TERM_BLOCKS: u32 = 10;
+
+/// How long a limit order rests, in seconds.
+const LONG_TERM_SECS: i64 = 28 * 24 * 3600;
+
+/// Order flags of short-term and long-term orders.
+const SHORT_TERM: u32 = 0;
+const LONG_TERM: u32 = 64;
+
+/// Quote amounts are in millionths of USDC.
+const QUOTE_ATOMIC_RESOLUTION: i32 = -6;
+
+/// Gas limit of order transactions; order messages pay no fees.
+const GAS_LIMIT: u64 = 1_000_000;
+
+type DydxResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+
+#[derive(Default)]
+pub struct DydxExchange {}
+
+impl DydxExchange {
+    pub fn new() -> Self {
+        DydxExchange {}
+    }
+}
+
+/// dYdX market of a strategy symbol: `BTCUSDT.P`, `BTC-PERP`, `BTCUSD` and `BTC` all trade
+/// `BTC-USD`.
+fn ticker(symbol: &str) -> String {
+    match Symbol::parse(symbol) {
+        Ok(symbol) => format!("{}-USD", symbol.base()),
+        Err(_) => symbol.to_string(),
+    }
+}
+
+/// Signing key of a private key: 32 hex-encoded bytes, or a BIP-39 mnemonic.
+fn signing_key(private_key: &str) -> DydxResult<SigningKey> {
+    let private_key = private_key.trim();
+    if private_key.contains(' ') {
+        return derive_key(private_key);
+    }
+    let hex = private_key.trim_start_matches("0x");
+    if hex.len() != 64 || !hex.is_ascii() {
+        return Err("private_key must be 32 hex-encoded bytes or a mnemonic".into());
+    }
+    let bytes = (0..64)
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+        .collect::<Result<Vec<u8>, _>>()?;
+    Ok(SigningKey::from_slice(&bytes)?)
+}
+
+/// Key of the first account of `mnemonic`, derived as BIP-32 prescribes.
+fn derive_key(mnemonic: &str) -> DydxResult<SigningKey> {
+    let mnemonic = mnemonic.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut seed = [0u8; 64];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA512,
+        NonZeroU32::new(2048).expect("non-zero"),
+        b"mnemonic",
+        mnemonic.as_bytes(),
+        &mut seed,
+    );
+
+    let master = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA512, b"Bitcoin seed"), &seed);
+    let (mut key, mut chain_code) = split(master.as_ref());
+    for index in DERIVATION_PATH {
+        let mut data = if index & HARDENED != 0 {
+            [&[0u8][..], &key].concat()
+        } else {
+            SigningKey::from_slice(&key)?
+                .verifying_key()
+                .to_encoded_point(true)
+                .as_bytes()
+                .to_vec()
+        };
+        data.extend_from_slice(&index.to_be_bytes());
+        let derived = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA512, &chain_code), &data);
+        let (tweak, next_chain_code) = split(derived.as_ref());
+        let scalar = |bytes: [u8; 32]| {
+            Option::<Scalar>::from(Scalar::from_repr(bytes.into()))
+                .ok_or("mnemonic derives an invalid key")
+        };
+        key = (scalar(tweak)? + scalar(key)?).to_bytes().into();
+        chain_code = next_chain_code;
+    }
+    Ok(SigningKey::from_slice(&key)?)
+}
+
+fn split(bytes: &[u8]) -> ([u8; 32], [u8; 32]) {
+    let (left, right) = bytes.split_at(32);
+    (
+        left.try_into().expect("32 bytes"),
+        right.try_into().expect("32 bytes"),
+    )
+}
+
+/// Bech32 address with `prefix` of `key`: the RIPEMD-160 of the SHA-256 of its compressed
+/// public key.
+fn address(key: &SigningKey, prefix: &str) -> DydxResult<String> {
+    let public_key = key.verifying_key().to_encoded_point(true);
+    let hash = Ripemd160::digest(Sha256::digest(public_key.as_bytes()));
+    Ok(bech32::encode::<Bech32>(Hrp::parse(prefix)?, &hash)?)
+}
+
+/// Protobuf encoding of a message, built field by field. Scalars equal to their default
+/// are left out, as proto3 does.
+#[derive(Clone, Debug, Default)]
+struct Proto(Vec<u8>);
+
+impl Proto {
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.0.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.0.push(value as u8);
+    }
+
+    fn key(&mut self, field: u32, wire_type: u8) {
+        self.varint(((field as u64) << 3) | wire_type as u64);
+    }
+
+    fn uint(mut self, field: u32, value: u64) -> Self {
+        if value != 0 {
+            self.key(field, 0);
+            self.varint(value);
+        }
+        self
+    }
+
+    fn fixed32(mut self, field: u32, value: u32) -> Self {
+        if value != 0 {
+            self.key(field, 5);
+            self.0.extend_from_slice(&value.to_le_bytes());
+        }
+        self
+    }
+
+    fn bytes(mut self, field: u32, bytes: &[u8]) -> Self {
+        if !bytes.is_empty() {
+            self.key(field, 2);
+            self.varint(bytes.len() as u64);
+            self.0.extend_from_slice(bytes);
+        }
+        self
+    }
+
+    fn string(self, field: u32, value: &str) -> Self {
+        self.bytes(field, value.as_bytes())
+    }
+
+    /// An embedded message, which is sent even when empty.
+    fn message(mut self, field: u32, message: Proto) -> Self {
+        self.key(field, 2);
+        self.varint(message.0.len() as u64);
+        self.0.extend(message.0);
+        self
+    }
+
+    /// A `google.protobuf.Any` holding `message`.
+    fn any(type_url: &str, message: Proto) -> Proto {
+        Proto::default().string(1, type_url).bytes(2, &message.0)
+    }
+}
+
+/// ID of an order, the same when placing and cancelling it.
+#[derive(Clone, Debug, PartialEq)]
+struct OrderId {
+    owner: String,
+    client_id: u32,
+    order_flags: u32,
+    clob_pair_id: u32,
+}
+
+impl OrderId {
+    fn encode(&self) -> Proto {
+        let subaccount = Proto::default()
+            .string(1, &self.owner)
+            .uint(2, SUBACCOUNT as u64);
+        Proto::default()
+            .message(1, subaccount)
+            .fixed32(2, self.client_id)
+            .uint(3, self.order_flags as u64)
+            .uint(4, self.clob_pair_id as u64)
+    }
+}
+
+/// Until when an order is valid.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum GoodTil {
+    Block(u32),
+    /// Unix time, in seconds
+    Time(u32),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Order {
+    id: OrderId,
+    is_buy: bool,
+    quantums: u64,
+    subticks: u64,
+    good_til: GoodTil,
+    immediate_or_cancel: bool,
+}
+
+impl Order {
+    /// `dydxprotocol.clob.MsgPlaceOrder`.
+    fn place_message(&self) -> Proto {
+        let order = Proto::default()
+            .message(1, self.id.encode())
+            .uint(2, if self.is_buy { 1 } else { 2 })
+            .uint(3, self.quantums)
+            .uint(4, self.subticks);
+        let order = match self.good_til {
+            GoodTil::Block(block) => order.uint(5, block as u64),
+            GoodTil::Time(time) => order.fixed32(6, time),
+        };
+        let order = order.uint(7, if self.immediate_or_cancel { 1 } else { 0 });
+        Proto::any(
+            "/dydxprotocol.clob.MsgPlaceOrder",
+            Proto::default().message(1, order),
+        )
+    }
+}
+
+/// `dydxprotocol.clob.MsgCancelOrder`.
+fn cancel_message(id: &OrderId, good_til: GoodTil) -> Proto {
+    let message = Proto::default().message(1, id.encode());
+    let message = match good_til {
+        GoodTil::Block(block) => message.uint(2, block as u64),
+        GoodTil::Time(time) => message.fixed32(3, time),
+    };
+    Proto::any("/dydxprotocol.clob.MsgCancelOrder", message)
+}
+
+/// Account of the wallet on the chain.
+#[derive(Clone, Debug, PartialEq)]
+struct Account {
+    chain_id: String,
+    number: u64,
+    sequence: u64,
+}
+
+/// A signed `TxRaw` carrying `message`, signed in `SIGN_MODE_DIRECT`.
+fn signed_transaction(message: Proto, account: &Account, key: &SigningKey) -> DydxResult<Vec<u8>> {
+    let body = Proto::default().message(1, message);
+    let public_key = Proto::any(
+        "/cosmos.crypto.secp256k1.PubKey",
+        Proto::default().bytes(1, key.verifying_key().to_encoded_point(true).as_bytes()),
+    );
+    let mode = Proto::default().message(1, Proto::default().uint(1, 1));
+    let signer = Proto::default()
+        .message(1, public_key)
+        .message(2, mode)
+        .uint(3, account.sequence);
+    let fee = Proto::default().uint(2, GAS_LIMIT);
+    let auth_info = Proto::default().message(1, signer).message(2, fee);
+
+    let sign_doc = Proto::default()
+        .bytes(1, &body.0)
+        .bytes(2, &auth_info.0)
+        .string(3, &account.chain_id)
+        .uint(4, account.number);
+    let signature: Signature = key.sign_prehash(&Sha256::digest(&sign_doc.0))?;
+    Ok(Proto::default()
+        .bytes(1, &body.0)
+        .bytes(2, &auth_info.0)
+        .bytes(3, &signature.to_bytes())
+        .0)
+}
+
+/// How a market sizes and prices orders.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct Market {
+    clob_pair_id: String,
+    atomic_resolution: i32,
+    quantum_conversion_exponent: i32,
+    step_base_quantums: u64,
+    subticks_per_tick: u64,
+    oracle_price: Option<String>,
+}
+
+impl Market {
+    /// `size` in base quantums, rounded down to the step size.
+    fn quantums(&self, size: Decimal) -> DydxResult<u64> {
+        let raw = (size.checked_mul(power_of_ten(-self.atomic_resolution)))
+            .and_then(|raw| round(raw).to_u64())
+            .ok_or_else(|| format!("{} is out of the market's range", size))?;
+        let step = self.step_base_quantums.max(1);
+        let quantums = raw / step * step;
+        if quantums == 0 {
+            return Err(format!("{} is below the market's step size", size).into());
+        }
+        Ok(quantums)
+    }
+
+    /// `price` in subticks, rounded to the tick size.
+    fn subticks(&self, price: Decimal) -> DydxResult<u64> {
+        let exponent =
+            self.atomic_resolution - self.quantum_conversion_exponent - QUOTE_ATOMIC_RESOLUTION;
+        let tick = self.subticks_per_tick.max(1);
+        let ticks = (price.checked_mul(power_of_ten(exponent)))
+            .and_then(|raw| round(raw / Decimal::from(tick)).to_u64())
+            .ok_or_else(|| format!("{} is out of the market's price range", price))?;
+        Ok(ticks.max(1) * tick)
+    }
+}
+
+/// `10^exponent`.
+fn power_of_ten(exponent: i32) -> Decimal {
+    match u32::try_from(exponent) {
+        Ok(exponent) => Decimal::from(10u64.pow(exponent)),
+        Err(_) => Decimal::new(1, exponent.unsigned_abs()),
+    }
+}
+
+/// `value` rounded to a whole number, halves away from zero.
+fn round(value: Decimal) -> Decimal {
+    value.round_dp_with_strategy(0, RoundingStrategy::MidpointAwayFromZero)
+}
+
+async fn get<T: for<'de> Deserialize<'de>>(
+    url: String,
+    query: &[(&str, &str)],
+    client: &Client,
+) -> DydxResult<T> {
+    let response = client.get(url).query(query).send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("dYdX responded with HTTP {}", status).into());
+    }
+    Ok(response.json().await?)
+}
+
+fn endpoints(bot: &Bot) -> (&str, &str) {
+    (
+        bot.rest_endpoint
+            .as_deref()
+            .unwrap_or(DEFAULT_ENDPOINT)
+            .trim_end_matches('/'),
+        bot.rpc_endpoint
+            .as_deref()
+            .unwrap_or(DEFAULT_RPC_ENDPOINT)
+            .trim_end_matches('/'),
+    )
+}
+
+async fn market(indexer: &str, ticker: &str, client: &Client) -> DydxResult<Market> {
+    #[derive(Deserialize)]
+    struct Markets {
+        markets: std::collections::HashMap<String, Market>,
+    }
+    let mut markets: Markets = get(
+        format!("{}/v4/perpetualMarkets", indexer),
+        &[("ticker", ticker)],
+        client,
+    )
+    .await?;
+    markets
+        .markets
+        .remove(ticker)
+        .ok_or_else(|| format!("dYdX does not list '{}'", ticker).into())
+}
+
+async fn height(indexer: &str, client: &Client) -> DydxResult<u32> {
+    let height: serde_json::Value = get(format!("{}/v4/height", indexer), &[], client).await?;
+    height["height"]
+        .as_str()
+        .and_then(|height| height.parse().ok())
+        .ok_or_else(|| "dYdX returned no block height".into())
+}
+
+async fn account(validator: &str, address: &str, client: &Client) -> DydxResult<Account> {
+    let node: serde_json::Value = get(
+        format!("{}/cosmos/base/tendermint/v1beta1/node_info", validator),
+        &[],
+        client,
+    )
+    .await?;
+    let account: serde_json::Value = get(
+        format!("{}/cosmos/auth/v1beta1/accounts/{}", validator, address),
+        &[],
+        client,
+    )
+    .await?;
+    let number = |field: &str| -> u64 {
+        account["account"][field]
+            .as_str()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(0)
+    };
+    Ok(Account {
+        chain_id: node["default_node_info"]["network"]
+            .as_str()
+            .ok_or("validator did not report its chain ID")?
+            .to_string(),
+        number: number("account_number"),
+        sequence: number("sequence"),
+    })
+}
+
+/// Sign `message` with the bot's key and broadcast it, returning the transaction hash.
+async fn broadcast(
+    message: Proto,
+    key: &SigningKey,
+    address: &str,
+    bot: &Bot,
+    client: &Client,
+) -> DydxResult<String> {
+    let (_, validator) = endpoints(bot);
+    let account = account(validator, address, client).await?;
+    let transaction = signed_transaction(message, &account, key)?;
+    let response = client
+        .post(format!("{}/cosmos/tx/v1beta1/txs", validator))
+        .json(&json!({
+            "tx_bytes": BASE64.encode(transaction),
+            "mode": "BROADCAST_MODE_SYNC",
+        }))
+        .send()
+        .await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("dYdX validator responded with HTTP {}", status).into());
+    }
+    let body: serde_json::Value = response.json().await?;
+    let result = &body["tx_response"];
+    if result["code"].as_u64().unwrap_or(0) != 0 {
+        return Err(format!(
+            "dYdX rejected the transaction: {}",
+            result["raw_log"].as_str().unwrap_or("no reason given")
+        )
+        .into());
+    }
+    Ok(result["txhash"].as_str().unwrap_or_default().to_string())
+}
+
+fn wallet(bot: &Bot) -> DydxResult<(SigningKey, String)> {
+    let key = signing_key(
+        bot.private_key
+            .as_deref()
+            .ok_or("dYdX bots need a private_key")?,
+    )?;
+    let address = address(&key, ADDRESS_PREFIX)?;
+    Ok((key, address))
+}
+
+/// Build, sign and broadcast an order. Returns its client ID and the transaction hash.
+async fn place_order(
+    is_buy: bool,
+    ticker: &str,
+    quantity: Decimal,
+    price: Decimal,
+    slippage: f64,
+    bot: &Bot,
+    client: &Client,
+) -> DydxResult<(u32, String)> {
+    let (key, address) = wallet(bot)?;
+    let (indexer, _) = endpoints(bot);
+    let market = market(indexer, ticker, client).await?;
+    let (subticks, good_til, order_flags, immediate_or_cancel) = if price > Decimal::ZERO {
+        let until = Utc::now().timestamp() + LONG_TERM_SECS;
+        (
+            market.subticks(price)?,
+            GoodTil::Time(until as u32),
+            LONG_TERM,
+            false,
+        )
+    } else {
+        let oracle: Decimal = market
+            .oracle_price
+            .as_deref()
+            .and_then(|price| price.parse().ok())
+            .ok_or_else(|| format!("dYdX has no oracle price for {}", ticker))?;
+        let slippage = match Decimal::try_from(slippage) {
+            Ok(slippage) if slippage > Decimal::ZERO => slippage,
+            _ => DEFAULT_SLIPPAGE,
+        };
+        let limit = if is_buy {
+            oracle * (Decimal::ONE + slippage)
+        } else {
+            oracle * (Decimal::ONE - slippage)
+        };
+        let block = height(indexer, client).await? + SHORT_TERM_BLOCKS;
+        (
+            market.subticks(limit)?,
+            GoodTil::Block(block),
+            SHORT_TERM,
+            true,
+        )
+    };
+
+    let order = Order {
+        id: OrderId {
+            owner: address.clone(),
+            client_id: rand::random(),
+            order_flags,
+            clob_pair_id: market.clob_pair_id.parse()?,
+        },
+        is_buy,
+        quantums: market.quantums(quantity)?,
+        subticks,
+        good_til,
+        immediate_or_cancel,
+    };
+    let hash = broadcast(order.place_message(), &key, &address, bot, client).await?;
+    Ok((order.id.client_id, hash))
+}
+
+#[async_trait]
+impl Exchange for DydxExchange {
+    async fn execute_trade(
+        &self,
+        order: &OrderRequest,
+        symbol_or_contract: &str,
+        quantity: Decimal,
+        slippage: f64,
+        bot: &Bot,
+        client: &Client,
+    ) -> Result<ExecutionReport, Box<dyn Error>> {
+        let price = order.price("dYdX")?;
+        let is_buy = order.side().is_buy();
+        let ticker = ticker(symbol_or_contract);
+        let submitted_at = Utc::now();
+        log::info!(
+            "Executing dYdX trade: {} {} {} @ {}",
+            order.side(),
+            quantity,
+            ticker,
+            if price > Decimal::ZERO {
+                price.to_string()
+            } else {
+                "market".to_string()
+            }
+        );
+        let (client_id, hash) =
+            place_order(is_buy, &ticker, quantity, price, slippage, bot, client)
+                .await
+                .map_err(|e| e as Box<dyn Error>)?;
+        log::info!("dYdX accepted order {} in transaction {}", client_id, hash);
+        // Orders are matched after the transaction lands; their fills are not known yet
+        Ok(ExecutionReport {
+            order_id: Some(client_id.to_string()),
+            tx_hashes: vec![hash],
+            ..ExecutionReport::acknowledged(submitted_at)
+        })
+    }
+
+    /// Cancel the limit (long-term) order with client ID `order_id`.
+    async fn cancel_order(
+        &self,
+        symbol: &str,
+        order_id: &str,
+        bot: &Bot,
+        client: &Client,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let client_id: u32 = order_id
+            .parse()
+            .map_err(|_| format!("'{}' is not a dYdX client order ID", order_id))?;
+        let ticker = ticker(symbol);
+        let (key, address) = wallet(bot)?;
+        let (indexer, _) = endpoints(bot);
+        let market = market(indexer, &ticker, client).await?;
+        let id = OrderId {
+            owner: address.clone(),
+            client_id,
+            order_flags: LONG_TERM,
+            clob_pair_id: market.clob_pair_id.parse()?,
+        };
+        let until = GoodTil::Time((Utc::now().timestamp() + LONG_TERM_SECS) as u32);
+        let hash = broadcast(cancel_message(&id, until), &key, &address, bot, client).await?;
+        log::info!("dYdX cancelled order {} in transaction {}", client_id, hash);
+        Ok(true)
+    }
+
+    async fn open_orders(
+        &self,
+        bot: &Bot,
+        client: &Client,
+    ) -> Result<Option<Vec<OpenOrder>>, Box<dyn Error + Send + Sync>> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct IndexerOrder {
+            client_id: String,
+            ticker: String,
+            side: String,
+            price: String,
+            size: String,
+        }
+        let (_, address) = wallet(bot)?;
+        let (indexer, _) = endpoints(bot);
+        let subaccount = SUBACCOUNT.to_string();
+        let orders: Vec<IndexerOrder> = get(
+            format!("{}/v4/orders", indexer),
+            &[
+                ("address", address.as_str()),
+                ("subaccountNumber", subaccount.as_str()),
+                ("status", "OPEN"),
+            ],
+            client,
+        )
+        .await?;
+        Ok(Some(
+            orders
+                .into_iter()
+                .map(|order| OpenOrder {
+                    order_id: order.client_id.clone(),
+                    client_order_id: order.client_id,
+                    symbol: order.ticker,
+                    side: order.side.to_lowercase(),
+                    price: order.price.parse().unwrap_or_default(),
+                    quantity: order.size.parse().unwrap_or_default(),
+                })
+                .collect(),
+        ))
+    }
+
+    async fn last_price(
+        &self,
+        symbol: &str,
+        client: &Client,
+    ) -> Result<Option<f64>, Box<dyn Error + Send + Sync>> {
+        let market = market(DEFAULT_ENDPOINT, &ticker(symbol), client).await?;
+        Ok(market.oracle_price.and_then(|price| price.parse().ok()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::Side;
+    use mockito::Matcher;
+    use rust_decimal::dec;
+
+    const MNEMONIC: &str = "abandon abandon abandon abandon abandon abandon abandon abandon \
+                            abandon abandon abandon about";
+
+    fn btc_market() -> Market {
+        Market {
+            clob_pair_id: "0".to_string(),
+            atomic_resolution: -10,
+            quantum_conversion_exponent: -9,
+            step_base_quantums: 1_000_000,
+            subticks_per_tick: 100_000,
+            oracle_price: Some("50000".to_string()),
+        }
+    }
+
+    #[test]
+    fn keys_addresses_and_symbols() {
+        // Same key as any Cosmos wallet derives from the mnemonic
+        let key = signing_key(MNEMONIC).unwrap();
+        assert_eq!(
+            address(&key, "cosmos").unwrap(),
+            "cosmos19rl4cm2hmr8afy4kldpxz3fka4jguq0auqdal4"
+        );
+        assert!(address(&key, ADDRESS_PREFIX).unwrap().starts_with("dydx1"));
+        assert!(signing_key("0x1234").is_err());
+
+        assert_eq!(ticker("BTCUSDT.P"), "BTC-USD");
+        assert_eq!(ticker("ETH-PERP"), "ETH-USD");
+        assert_eq!(ticker("SOL-USD"), "SOL-USD");
+        assert_eq!(ticker("btc/usdc"), "BTC-USD");
+
+        let market = btc_market();
+        assert_eq!(market.quantums(dec!(0.01)).unwrap(), 100_000_000);
+        assert_eq!(market.quantums(dec!(0.012345)).unwrap(), 123_000_000);
+        assert!(market.quantums(dec!(0.00001)).is_err());
+        assert!(market.quantums(dec!(-1)).is_err());
+        assert_eq!(market.subticks(dec!(50_000)).unwrap(), 5_000_000_000);
+        assert_eq!(market.subticks(dec!(50_000.123)).unwrap(), 5_000_000_000);
+        // Exact in decimal, where 0.29 * 10^10 is not in binary
+        assert_eq!(market.quantums(dec!(0.29)).unwrap(), 2_900_000_000);
+    }
+
+    #[test]
+    fn orders_are_protobuf_encoded() {
+        let id = OrderId {
+            owner: "dydx1".to_string(),
+            client_id: 7,
+            order_flags: LONG_TERM,
+            clob_pair_id: 1,
+        };
+        let order = Order {
+            id: id.clone(),
+            is_buy: false,
+            quantums: 300,
+            subticks: 2,
+            good_til: GoodTil::Time(1),
+            immediate_or_cancel: false,
+        };
+        let message = Proto::default().message(1, order.place_message());
+        let expected_order = [
+            &[0x0a, 0x12][..],                         // order_id
+            &[0x0a, 0x07, 0x0a, 0x05][..],             // subaccount_id.owner
+            b"dydx1",                                  // (subaccount 0 is left out)
+            &[0x15, 7, 0, 0, 0][..],                   // client_id
+            &[0x18, 64, 0x20, 1][..],                  // order_flags, clob_pair_id
+            &[0x10, 2, 0x18, 0xac, 0x02, 0x20, 2][..], // side, quantums, subticks
+            &[0x35, 1, 0, 0, 0][..],                   // good_til_block_time
+        ]
+        .concat();
+        assert!(message
+            .0
+            .windows(expected_order.len())
+            .any(|window| window == expected_order));
+        assert_eq!(
+            cancel_message(&id, GoodTil::Block(5)).0,
+            Proto::any(
+                "/dydxprotocol.clob.MsgCancelOrder",
+                Proto::default().message(1, id.encode()).uint(2, 5)
+            )
+            .0
+        );
+    }
+
+    #[tokio::test]
+    async fn market_orders_are_signed_and_broadcast() {
+        let mut server = mockito::Server::new_async().await;
+        let bot = Bot {
+            exchange: "dydx".to_string(),
+            private_key: Some(MNEMONIC.to_string()),
+            rest_endpoint: Some(server.url()),
+            rpc_endpoint: Some(server.url()),
+            ..Default::default()
+        };
+        let client = Client::new();
+        server
+            .mock("GET", "/v4/perpetualMarkets")
+            .match_query(Matcher::UrlEncoded("ticker".into(), "BTC-USD".into()))
+            .with_body(
+                json!({"markets": {"BTC-USD": {
+                    "clobPairId": "0",
+                    "atomicResolution": -10,
+                    "quantumConversionExponent": -9,
+                    "stepBaseQuantums": 1000000,
+                    "subticksPerTick": 100000,
+                    "oraclePrice": "50000",
+                }}})
+                .to_string(),
+            )
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/v4/height")
+            .with_body(r#"{"height":"1000","time":"2025-01-01T00:00:00.000Z"}"#)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/cosmos/base/tendermint/v1beta1/node_info")
+            .with_body(r#"{"default_node_info":{"network":"dydx-testnet-4"}}"#)
+            .create_async()
+            .await;
+        server
+            .mock(
+                "GET",
+                Matcher::Regex("^/cosmos/auth/v1beta1/accounts/dydx1".to_string()),
+            )
+            .with_body(r#"{"account":{"account_number":"12","sequence":"3"}}"#)
+            .create_async()
+            .await;
+        let broadcast = server
+            .mock("POST", "/cosmos/tx/v1beta1/txs")
+            .match_body(Matcher::PartialJson(json!({"mode": "BROADCAST_MODE_SYNC"})))
+            .with_body(r#"{"tx_response":{"code":0,"txhash":"ABC"}}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        DydxExchange::new()
+            .execute_trade(
+                &OrderRequest::Market { side: Side::Buy },
+                "BTCUSDT.P",
+                dec!(0.01),
+                0.0,
+                &bot,
+                &client,
+            )
+            .await
+            .unwrap();
+        broadcast.assert_async().await;
+
+        server
+            .mock("POST", "/cosmos/tx/v1beta1/txs")
+            .with_body(r#"{"tx_response":{"code":5,"raw_log":"insufficient collateral"}}"#)
+            .create_async()
+            .await;
+        let err = DydxExchange::new()
+            .cancel_order("BTC-USD", "42", &bot, &client)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("insufficient collateral"));
+    }
+}
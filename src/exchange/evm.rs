@@ -0,0 +1,206 @@
+// src/exchange/evm.rs
+//! Ethereum primitives shared by the EVM-based adapters: keys and addresses, hex, Keccak
+//! hashing, ABI words and RLP. Only Uniswap sends transactions, so the ABI and RLP parts
+//! are built with it alone.
+use crate::utils::crypto::{decode_hex, encode_hex};
+use k256::ecdsa::SigningKey;
+use sha3::{Digest, Keccak256};
+use std::error::Error;
+
+#[cfg(feature = "exchange-uniswap")]
+pub type Address = [u8; 20];
+
+pub fn keccak(data: &[u8]) -> [u8; 32] {
+    Keccak256::digest(data).into()
+}
+
+/// `0x`-prefixed lowercase hex.
+pub fn to_hex(bytes: &[u8]) -> String {
+    format!("0x{}", encode_hex(bytes))
+}
+
+/// Bytes of `0x`-prefixed (or bare) hex.
+pub fn from_hex(hex: &str) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    let hex = hex.trim().trim_start_matches("0x");
+    decode_hex(hex).ok_or_else(|| format!("'{}' is not hex-encoded bytes", hex).into())
+}
+
+#[cfg(feature = "exchange-uniswap")]
+pub fn parse_address(address: &str) -> Result<Address, Box<dyn Error + Send + Sync>> {
+    from_hex(address)?
+        .try_into()
+        .map_err(|_| format!("'{}' is not an address", address).into())
+}
+
+/// Signing key of a hex-encoded 32-byte private key.
+pub fn signing_key(private_key: &str) -> Result<SigningKey, Box<dyn Error + Send + Sync>> {
+    let bytes = from_hex(private_key).map_err(|_| "private_key must be 32 hex-encoded bytes")?;
+    if bytes.len() != 32 {
+        return Err("private_key must be 32 hex-encoded bytes".into());
+    }
+    Ok(SigningKey::from_slice(&bytes)?)
+}
+
+#[cfg(feature = "exchange-uniswap")]
+/// Address of `key`: the last 20 bytes of the hash of its uncompressed public key.
+pub fn address(key: &SigningKey) -> Address {
+    let point = key.verifying_key().to_encoded_point(false);
+    let hash = keccak(&point.as_bytes()[1..]);
+    hash[12..].try_into().expect("20 bytes")
+}
+
+#[cfg(feature = "exchange-uniswap")]
+/// Parse a JSON-RPC quantity such as `0x1a`.
+pub fn parse_quantity(quantity: &str) -> Result<u128, Box<dyn Error + Send + Sync>> {
+    let digits = quantity.trim_start_matches("0x");
+    if digits.is_empty() {
+        return Ok(0);
+    }
+    Ok(u128::from_str_radix(digits, 16)?)
+}
+
+#[cfg(feature = "exchange-uniswap")]
+/// The first four bytes of the hash of a function signature.
+pub fn selector(signature: &str) -> [u8; 4] {
+    keccak(signature.as_bytes())[..4]
+        .try_into()
+        .expect("4 bytes")
+}
+
+#[cfg(feature = "exchange-uniswap")]
+/// An ABI `uint` word.
+pub fn uint_word(value: u128) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[16..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+#[cfg(feature = "exchange-uniswap")]
+/// An ABI `address` word.
+pub fn address_word(address: &Address) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[12..].copy_from_slice(address);
+    word
+}
+
+#[cfg(feature = "exchange-uniswap")]
+/// Call data of a function taking static arguments only.
+pub fn call_data(signature: &str, words: &[[u8; 32]]) -> Vec<u8> {
+    let mut data = selector(signature).to_vec();
+    for word in words {
+        data.extend_from_slice(word);
+    }
+    data
+}
+
+#[cfg(feature = "exchange-uniswap")]
+/// The `index`th word of ABI-encoded return data as an integer.
+pub fn decode_uint(data: &[u8], index: usize) -> Result<u128, Box<dyn Error + Send + Sync>> {
+    let word = data
+        .get(index * 32..(index + 1) * 32)
+        .ok_or("return data is too short")?;
+    if word[..16].iter().any(|byte| *byte != 0) {
+        return Err("returned integer does not fit in 128 bits".into());
+    }
+    Ok(u128::from_be_bytes(
+        word[16..].try_into().expect("16 bytes"),
+    ))
+}
+
+#[cfg(feature = "exchange-uniswap")]
+/// A value of Ethereum's recursive length prefix encoding.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Rlp {
+    Bytes(Vec<u8>),
+    List(Vec<Rlp>),
+}
+
+#[cfg(feature = "exchange-uniswap")]
+impl Rlp {
+    /// An integer: big-endian without leading zeros, so zero is the empty string.
+    pub fn uint(value: u128) -> Self {
+        Rlp::scalar(&value.to_be_bytes())
+    }
+
+    /// A big-endian integer of any width, such as a signature's `r` and `s`.
+    pub fn scalar(bytes: &[u8]) -> Self {
+        let start = bytes
+            .iter()
+            .position(|byte| *byte != 0)
+            .unwrap_or(bytes.len());
+        Rlp::Bytes(bytes[start..].to_vec())
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            Rlp::Bytes(bytes) if bytes.len() == 1 && bytes[0] < 0x80 => bytes.clone(),
+            Rlp::Bytes(bytes) => [length_prefix(0x80, bytes.len()), bytes.clone()].concat(),
+            Rlp::List(items) => {
+                let payload: Vec<u8> = items.iter().flat_map(Rlp::encode).collect();
+                [length_prefix(0xc0, payload.len()), payload].concat()
+            }
+        }
+    }
+}
+
+#[cfg(feature = "exchange-uniswap")]
+fn length_prefix(offset: u8, len: usize) -> Vec<u8> {
+    if len < 56 {
+        return vec![offset + len as u8];
+    }
+    let bytes = len.to_be_bytes();
+    let start = bytes.iter().position(|byte| *byte != 0).unwrap_or(0);
+    let len_bytes = &bytes[start..];
+    [
+        vec![offset + 55 + len_bytes.len() as u8],
+        len_bytes.to_vec(),
+    ]
+    .concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_and_hex_are_checked() {
+        assert!(
+            signing_key("0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318")
+                .is_ok()
+        );
+        assert!(signing_key("0x1234").is_err());
+        assert_eq!(to_hex(&[0x00, 0xab]), "0x00ab");
+        assert!(from_hex("0xabc").is_err());
+        assert_eq!(from_hex(" 0x00fF ").unwrap(), [0x00, 0xff]);
+    }
+
+    #[cfg(feature = "exchange-uniswap")]
+    #[test]
+    fn addresses_abi_words_and_rlp_match_the_references() {
+        let key = signing_key("0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318")
+            .unwrap();
+        assert_eq!(
+            to_hex(&address(&key)),
+            "0x2c7536e3605d9c16a7a3d7b1898e529396a65c23"
+        );
+
+        // Examples from the RLP specification
+        assert_eq!(Rlp::Bytes(b"dog".to_vec()).encode(), b"\x83dog");
+        assert_eq!(
+            Rlp::List(vec![
+                Rlp::Bytes(b"cat".to_vec()),
+                Rlp::Bytes(b"dog".to_vec())
+            ])
+            .encode(),
+            b"\xc8\x83cat\x83dog"
+        );
+        assert_eq!(Rlp::uint(0).encode(), [0x80]);
+        assert_eq!(Rlp::uint(15).encode(), [0x0f]);
+        assert_eq!(Rlp::uint(1024).encode(), [0x82, 0x04, 0x00]);
+        assert_eq!(Rlp::Bytes(vec![b'a'; 56]).encode()[..2], [0xb8, 56]);
+
+        assert_eq!(to_hex(&selector("transfer(address,uint256)")), "0xa9059cbb");
+        assert_eq!(parse_quantity("0x2105").unwrap(), 8453);
+        assert_eq!(decode_uint(&uint_word(42), 0).unwrap(), 42);
+    }
+}
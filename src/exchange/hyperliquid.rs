@@ -0,0 +1,537 @@
+// src/exchange/hyperliquid.rs
+//! Hyperliquid perpetuals.
+//!
+//! Hyperliquid has no API keys: every action sent to `POST /exchange` is signed with the
+//! bot's `private_key` (an Ethereum key, or an API wallet approved for the account). The
+//! action is msgpack-encoded and hashed together with a nonce into a "phantom agent", which
+//! is signed as EIP-712 typed data — exactly like the official Python SDK does.
+//!
+//! Hyperliquid has no true market orders. A market order is an IOC limit order priced
+//! `slippage` (5% by default) through the current mid price, so it fills immediately or not
+//! at all, but never further away than that. Bots can point `rest_endpoint` at the testnet
+//! (`https://api.hyperliquid-testnet.xyz`).
+
+use super::evm::{keccak, signing_key, to_hex};
+use super::{Exchange, ExecutionReport, OrderRequest, Symbol, TimeInForce};
+use crate::bot::model::Bot;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use k256::ecdsa::SigningKey;
+use reqwest::Client;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::{dec, Decimal, RoundingStrategy};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::error::Error;
+
+pub const DEFAULT_ENDPOINT: &str = "https://api.hyperliquid.xyz";
+
+/// Price tolerance of market orders unless the caller asks for another one.
+const DEFAULT_SLIPPAGE: Decimal = dec!(0.05);
+
+#[derive(Default)]
+pub struct HyperliquidExchange {}
+
+impl HyperliquidExchange {
+    pub fn new() -> Self {
+        HyperliquidExchange {}
+    }
+}
+
+/// Hyperliquid coin traded for a strategy symbol: `BTCUSDT.P`, `BTC-PERP` and `BTC` all
+/// trade `BTC`.
+fn coin(symbol: &str) -> String {
+    Symbol::parse(symbol).map_or_else(|_| symbol.to_string(), |symbol| symbol.base().to_string())
+}
+
+/// Prices have at most five significant figures and `6 - sz_decimals` decimals.
+fn round_price(price: Decimal, sz_decimals: u32) -> Decimal {
+    let significant = price.round_sf(5).unwrap_or(price);
+    round_to(significant, 6u32.saturating_sub(sz_decimals))
+}
+
+fn round_to(value: Decimal, decimals: u32) -> Decimal {
+    value.round_dp_with_strategy(decimals, RoundingStrategy::MidpointAwayFromZero)
+}
+
+/// Number as the API expects it: no exponent, no trailing zeros.
+fn to_wire(value: Decimal) -> String {
+    value.normalize().to_string()
+}
+
+/// Field names and order are part of the signed payload; do not rename or reorder.
+#[derive(Clone, Debug, Serialize, PartialEq)]
+struct OrderWire {
+    /// Asset index in the perpetuals universe
+    a: u32,
+    /// Buy?
+    b: bool,
+    p: String,
+    s: String,
+    /// Reduce only?
+    r: bool,
+    t: OrderType,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+enum OrderType {
+    #[serde(rename = "limit")]
+    Limit { tif: &'static str },
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+struct CancelWire {
+    a: u32,
+    /// Exchange order ID
+    o: u64,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum Action {
+    Order {
+        orders: Vec<OrderWire>,
+        grouping: &'static str,
+    },
+    Cancel {
+        cancels: Vec<CancelWire>,
+    },
+}
+
+/// Hash of `action` the phantom agent carries as its connection ID.
+fn action_hash(action: &Action, nonce: u64) -> Result<[u8; 32], Box<dyn Error>> {
+    let mut data = rmp_serde::to_vec_named(action)?;
+    data.extend_from_slice(&nonce.to_be_bytes());
+    data.push(0); // no vault address
+    Ok(keccak(&data))
+}
+
+/// EIP-712 digest of the phantom agent `Agent(string source, bytes32 connectionId)` in the
+/// `Exchange` domain; `source` is `a` on mainnet and `b` on the testnet.
+fn agent_digest(connection_id: &[u8; 32], mainnet: bool) -> [u8; 32] {
+    let domain_type = keccak(
+        b"EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)",
+    );
+    let mut chain_id = [0u8; 32];
+    chain_id[24..].copy_from_slice(&1337u64.to_be_bytes());
+    let domain = keccak(
+        &[
+            &domain_type[..],
+            &keccak(b"Exchange"),
+            &keccak(b"1"),
+            &chain_id,
+            &[0u8; 32], // verifyingContract: the zero address
+        ]
+        .concat(),
+    );
+
+    let agent_type = keccak(b"Agent(string source,bytes32 connectionId)");
+    let source = if mainnet { "a" } else { "b" };
+    let agent = keccak(&[&agent_type[..], &keccak(source.as_bytes()), connection_id].concat());
+
+    keccak(&[&[0x19, 0x01][..], &domain, &agent].concat())
+}
+
+/// `{r, s, v}` signature of `action` as sent to `/exchange`.
+fn sign(
+    key: &SigningKey,
+    action: &Action,
+    nonce: u64,
+    mainnet: bool,
+) -> Result<serde_json::Value, Box<dyn Error>> {
+    let digest = agent_digest(&action_hash(action, nonce)?, mainnet);
+    let (signature, recovery_id) = key.sign_prehash_recoverable(&digest)?;
+    let bytes = signature.to_bytes();
+    Ok(json!({
+        "r": to_hex(&bytes[..32]),
+        "s": to_hex(&bytes[32..]),
+        "v": 27 + recovery_id.to_byte(),
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Asset {
+    name: String,
+    sz_decimals: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct Meta {
+    universe: Vec<Asset>,
+}
+
+fn endpoint(bot: &Bot) -> &str {
+    bot.rest_endpoint
+        .as_deref()
+        .unwrap_or(DEFAULT_ENDPOINT)
+        .trim_end_matches('/')
+}
+
+async fn info<T: for<'de> Deserialize<'de>>(
+    endpoint: &str,
+    request: serde_json::Value,
+    client: &Client,
+) -> Result<T, Box<dyn Error + Send + Sync>> {
+    let response = client
+        .post(format!("{}/info", endpoint))
+        .json(&request)
+        .send()
+        .await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("Hyperliquid responded with HTTP {}", status).into());
+    }
+    Ok(response.json().await?)
+}
+
+/// Index and size decimals of `coin` in the perpetuals universe.
+async fn asset(
+    endpoint: &str,
+    coin: &str,
+    client: &Client,
+) -> Result<(u32, u32), Box<dyn Error + Send + Sync>> {
+    let meta: Meta = info(endpoint, json!({"type": "meta"}), client).await?;
+    meta.universe
+        .iter()
+        .position(|asset| asset.name == coin)
+        .map(|index| (index as u32, meta.universe[index].sz_decimals))
+        .ok_or_else(|| format!("Hyperliquid does not list '{}'", coin).into())
+}
+
+async fn mid_price(
+    endpoint: &str,
+    coin: &str,
+    client: &Client,
+) -> Result<Option<Decimal>, Box<dyn Error + Send + Sync>> {
+    let mids: HashMap<String, String> = info(endpoint, json!({"type": "allMids"}), client).await?;
+    Ok(mids.get(coin).and_then(|mid| mid.parse().ok()))
+}
+
+/// Sign `action` with the bot's key and send it, returning the per-order statuses.
+async fn post_action(
+    action: &Action,
+    bot: &Bot,
+    client: &Client,
+) -> Result<Vec<serde_json::Value>, Box<dyn Error>> {
+    let key = signing_key(
+        bot.private_key
+            .as_deref()
+            .ok_or("Hyperliquid bots need a private_key")?,
+    )
+    .map_err(|e| e as Box<dyn Error>)?;
+    let endpoint = endpoint(bot);
+    let nonce = Utc::now().timestamp_millis() as u64;
+    let signature = sign(&key, action, nonce, endpoint == DEFAULT_ENDPOINT)?;
+
+    let response = client
+        .post(format!("{}/exchange", endpoint))
+        .json(&json!({
+            "action": action,
+            "nonce": nonce,
+            "signature": signature,
+            "vaultAddress": null,
+        }))
+        .send()
+        .await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("Hyperliquid responded with HTTP {}", status).into());
+    }
+    let body: serde_json::Value = response.json().await?;
+    if body["status"] != "ok" {
+        return Err(format!("Hyperliquid rejected the request: {}", body["response"]).into());
+    }
+    let statuses = body["response"]["data"]["statuses"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    for status in &statuses {
+        if let Some(error) = status["error"].as_str() {
+            return Err(format!("Hyperliquid rejected the order: {}", error).into());
+        }
+    }
+    Ok(statuses)
+}
+
+/// Report of an order from its status, `{"filled": {"oid", "totalSz", "avgPx"}}` or
+/// `{"resting": {"oid"}}`.
+fn report(status: &serde_json::Value, submitted_at: DateTime<Utc>) -> ExecutionReport {
+    let (order, filled) = match status.get("filled") {
+        Some(filled) => (filled, true),
+        None => (&status["resting"], false),
+    };
+    let number = |field: &str| order[field].as_str().and_then(|value| value.parse().ok());
+    ExecutionReport {
+        order_id: order["oid"].as_u64().map(|oid| oid.to_string()),
+        filled_quantity: if filled { number("totalSz") } else { None },
+        average_price: if filled { number("avgPx") } else { None },
+        ..ExecutionReport::acknowledged(submitted_at)
+    }
+}
+
+#[async_trait]
+impl Exchange for HyperliquidExchange {
+    async fn execute_trade(
+        &self,
+        order: &OrderRequest,
+        symbol_or_contract: &str,
+        quantity: Decimal,
+        slippage: f64,
+        bot: &Bot,
+        client: &Client,
+    ) -> Result<ExecutionReport, Box<dyn Error>> {
+        let limit = order.market_or_limit("Hyperliquid")?;
+        let is_buy = order.side().is_buy();
+        let coin = &coin(symbol_or_contract);
+        let endpoint = endpoint(bot);
+        let submitted_at = Utc::now();
+        let (index, sz_decimals) = asset(endpoint, coin, client)
+            .await
+            .map_err(|e| e as Box<dyn Error>)?;
+
+        let size = round_to(quantity, sz_decimals);
+        if size <= Decimal::ZERO {
+            return Err(format!(
+                "{} {} rounds to nothing at {} decimals",
+                quantity, coin, sz_decimals
+            )
+            .into());
+        }
+        let (limit, tif) = if let Some((price, time_in_force)) = limit {
+            let tif = match time_in_force {
+                TimeInForce::Gtc => "Gtc",
+                TimeInForce::Ioc => "Ioc",
+                TimeInForce::Fok => return Err("Hyperliquid does not place FOK orders".into()),
+            };
+            (price, tif)
+        } else {
+            let mid = mid_price(endpoint, coin, client)
+                .await
+                .map_err(|e| e as Box<dyn Error>)?
+                .ok_or_else(|| format!("Hyperliquid has no mid price for '{}'", coin))?;
+            let slippage = Decimal::try_from(slippage)
+                .ok()
+                .filter(|slippage| *slippage > Decimal::ZERO)
+                .unwrap_or(DEFAULT_SLIPPAGE);
+            let limit = if is_buy {
+                mid * (Decimal::ONE + slippage)
+            } else {
+                mid * (Decimal::ONE - slippage)
+            };
+            (limit, "Ioc")
+        };
+
+        let action = Action::Order {
+            orders: vec![OrderWire {
+                a: index,
+                b: is_buy,
+                p: to_wire(round_price(limit, sz_decimals)),
+                s: to_wire(size),
+                r: false,
+                t: OrderType::Limit { tif },
+            }],
+            grouping: "na",
+        };
+        log::info!(
+            "Executing Hyperliquid trade: {} {} {} limit {} ({})",
+            order.side(),
+            to_wire(size),
+            coin,
+            to_wire(round_price(limit, sz_decimals)),
+            tif
+        );
+        let statuses = post_action(&action, bot, client).await?;
+        log::info!("Hyperliquid accepted the order: {:?}", statuses);
+        Ok(statuses
+            .first()
+            .map(|status| report(status, submitted_at))
+            .unwrap_or_else(|| ExecutionReport::acknowledged(submitted_at)))
+    }
+
+    /// Cancel the resting order with exchange order ID `order_id`.
+    async fn cancel_order(
+        &self,
+        symbol: &str,
+        order_id: &str,
+        bot: &Bot,
+        client: &Client,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let oid: u64 = order_id
+            .parse()
+            .map_err(|_| format!("'{}' is not a Hyperliquid order ID", order_id))?;
+        let coin = &coin(symbol);
+        let (index, _) = asset(endpoint(bot), coin, client).await?;
+        let action = Action::Cancel {
+            cancels: vec![CancelWire { a: index, o: oid }],
+        };
+        post_action(&action, bot, client)
+            .await
+            .map_err(|e| e.to_string())?;
+        log::info!("Hyperliquid cancelled order {} on {}", oid, coin);
+        Ok(true)
+    }
+
+    async fn last_price(
+        &self,
+        symbol: &str,
+        client: &Client,
+    ) -> Result<Option<f64>, Box<dyn Error + Send + Sync>> {
+        Ok(mid_price(DEFAULT_ENDPOINT, &coin(symbol), client)
+            .await?
+            .and_then(|mid| mid.to_f64()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::evm::from_hex;
+    use crate::exchange::Side;
+    use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+    use mockito::Matcher;
+
+    const PRIVATE_KEY: &str = "0x0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+
+    #[test]
+    fn symbols_and_numbers_are_normalised() {
+        assert_eq!(coin("BTCUSDT.P"), "BTC");
+        assert_eq!(coin("ETH-PERP"), "ETH");
+        assert_eq!(coin("SOL"), "SOL");
+        assert_eq!(coin("USDC"), "USDC");
+
+        assert_eq!(round_price(dec!(65_432.123), 5), dec!(65_432));
+        assert_eq!(round_price(dec!(1.234_567), 0), dec!(1.2346));
+        assert_eq!(round_price(dec!(0.000_123_456), 0), dec!(0.000_123));
+        assert_eq!(to_wire(dec!(65_432.000)), "65432");
+        assert_eq!(to_wire(dec!(0.50)), "0.5");
+        assert_eq!(to_wire(-Decimal::ZERO), "0");
+    }
+
+    #[test]
+    fn actions_are_signed_by_the_bot_key() {
+        // keccak256 of the EIP712Domain type, as published by the standard
+        assert_eq!(
+            to_hex(&keccak(
+                b"EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)"
+            )),
+            "0x8b73c3c69bb8fe3d512ecc4cf759cc79239f7b179b0ffacaa9a75d522b39400f"
+        );
+
+        let action = Action::Cancel {
+            cancels: vec![CancelWire { a: 0, o: 42 }],
+        };
+        // {"type": "cancel", "cancels": [{"a": 0, "o": 42}]}, field order preserved
+        assert_eq!(
+            rmp_serde::to_vec_named(&action).unwrap(),
+            b"\x82\xa4type\xa6cancel\xa7cancels\x91\x82\xa1a\x00\xa1o\x2a"
+        );
+
+        let key = signing_key(PRIVATE_KEY).unwrap();
+        let signature = sign(&key, &action, 1_700_000_000_000, true).unwrap();
+        let unhex = |value: &serde_json::Value| from_hex(value.as_str().unwrap()).unwrap();
+        let bytes = [unhex(&signature["r"]), unhex(&signature["s"])].concat();
+        let recovery_id =
+            RecoveryId::from_byte(signature["v"].as_u64().unwrap() as u8 - 27).unwrap();
+
+        let digest = agent_digest(&action_hash(&action, 1_700_000_000_000).unwrap(), true);
+        let recovered = VerifyingKey::recover_from_prehash(
+            &digest,
+            &Signature::from_slice(&bytes).unwrap(),
+            recovery_id,
+        )
+        .unwrap();
+        assert_eq!(&recovered, key.verifying_key());
+        assert_ne!(
+            digest,
+            agent_digest(&action_hash(&action, 1_700_000_000_000).unwrap(), false)
+        );
+    }
+
+    #[tokio::test]
+    async fn market_orders_are_bounded_by_slippage() {
+        let mut server = mockito::Server::new_async().await;
+        let bot = Bot {
+            exchange: "hyperliquid".to_string(),
+            private_key: Some(PRIVATE_KEY.to_string()),
+            rest_endpoint: Some(server.url()),
+            ..Default::default()
+        };
+        server
+            .mock("POST", "/info")
+            .match_body(Matcher::PartialJson(json!({"type": "meta"})))
+            .with_body(
+                r#"{"universe":[{"name":"BTC","szDecimals":5},{"name":"ETH","szDecimals":4}]}"#,
+            )
+            .create_async()
+            .await;
+        server
+            .mock("POST", "/info")
+            .match_body(Matcher::PartialJson(json!({"type": "allMids"})))
+            .with_body(r#"{"BTC":"60000.0","ETH":"3000.0"}"#)
+            .create_async()
+            .await;
+        let order = server
+            .mock("POST", "/exchange")
+            .match_body(Matcher::PartialJson(json!({
+                "action": {
+                    "type": "order",
+                    "orders": [{"a": 1, "b": true, "p": "3030", "s": "0.5", "r": false,
+                                "t": {"limit": {"tif": "Ioc"}}}],
+                    "grouping": "na",
+                },
+                "vaultAddress": null,
+            })))
+            .with_body(
+                r#"{"status":"ok","response":{"type":"order","data":{"statuses":[{"filled":{"totalSz":"0.5","avgPx":"3001.5","oid":7}}]}}}"#,
+            )
+            .create_async()
+            .await;
+
+        let client = Client::new();
+        let filled = HyperliquidExchange::new()
+            .execute_trade(
+                &OrderRequest::Market { side: Side::Buy },
+                "ETHUSDT.P",
+                dec!(0.5),
+                0.01,
+                &bot,
+                &client,
+            )
+            .await
+            .unwrap();
+        order.assert_async().await;
+        assert_eq!(filled.order_id.as_deref(), Some("7"));
+        assert_eq!(filled.filled_quantity, Some(dec!(0.5)));
+        assert_eq!(filled.average_price, Some(dec!(3001.5)));
+
+        let resting = report(&json!({"resting": {"oid": 8}}), Utc::now());
+        assert_eq!(resting.order_id.as_deref(), Some("8"));
+        assert_eq!(resting.filled_quantity, None);
+
+        server
+            .mock("POST", "/exchange")
+            .with_body(
+                r#"{"status":"ok","response":{"type":"order","data":{"statuses":[{"error":"Insufficient margin"}]}}}"#,
+            )
+            .create_async()
+            .await;
+        let err = HyperliquidExchange::new()
+            .execute_trade(
+                &OrderRequest::Limit {
+                    side: Side::Sell,
+                    price: dec!(59_000),
+                    time_in_force: TimeInForce::Gtc,
+                },
+                "BTC",
+                dec!(0.1),
+                0.0,
+                &bot,
+                &client,
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Insufficient margin"));
+    }
+}
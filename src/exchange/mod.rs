@@ -1,44 +1,348 @@
 // src/exchange/mod.rs
 
+#[cfg(feature = "exchange-binance")]
 pub mod binance;
-// pub mod bybit;
-// pub mod dydx;
-// pub mod hyperliquid;
-// pub mod raydium_solana;
-// pub mod uniswap_base;
+#[cfg(feature = "exchange-bybit")]
+pub mod bybit;
+#[cfg(feature = "exchange-dydx")]
+pub mod dydx;
+#[cfg(any(feature = "exchange-hyperliquid", feature = "exchange-uniswap"))]
+mod evm;
+#[cfg(feature = "exchange-hyperliquid")]
+pub mod hyperliquid;
+pub mod order;
+pub mod paper;
+pub mod plugin;
+pub mod prices;
+#[cfg(feature = "exchange-raydium")]
+pub mod raydium_solana;
+#[cfg(any(feature = "exchange-binance", feature = "exchange-bybit"))]
+pub mod streams;
+pub mod symbol;
+#[cfg(feature = "exchange-uniswap")]
+pub mod uniswap_base;
 
-use crate::bot::Bot;
+use crate::bot::model::Bot;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use reqwest::Client;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
 use std::error::Error;
+use std::fmt;
+
+pub use order::{OrderRequest, Side, TimeInForce};
+pub use symbol::{Asset, Symbol, SymbolMap};
+
+/// What the exchange did with an order, as far as it said.
+///
+/// Orders that rest on the book report no fill; DEX swaps fill their fixed side completely
+/// once their transactions confirm.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct ExecutionReport {
+    /// ID the exchange gave the order
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order_id: Option<String>,
+    /// Quantity filled, in units of the base asset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filled_quantity: Option<Decimal>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub average_price: Option<Decimal>,
+    /// Fees charged, in units of the quote asset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fees: Option<Decimal>,
+    /// Hashes (signatures on Solana) of the transactions carrying the order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tx_hashes: Vec<String>,
+    pub submitted_at: DateTime<Utc>,
+    /// When the exchange accepted the order
+    pub acknowledged_at: DateTime<Utc>,
+}
+
+impl ExecutionReport {
+    /// An empty report of an order submitted at `submitted_at` and accepted just now.
+    pub fn acknowledged(submitted_at: DateTime<Utc>) -> Self {
+        Self {
+            submitted_at,
+            acknowledged_at: Utc::now(),
+            ..Default::default()
+        }
+    }
+}
+
+impl fmt::Display for ExecutionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(order_id) = &self.order_id {
+            parts.push(format!("order {}", order_id));
+        }
+        match (self.filled_quantity, self.average_price) {
+            (Some(quantity), Some(price)) => {
+                parts.push(format!("filled {} at {}", quantity, price))
+            }
+            (Some(quantity), None) => parts.push(format!("filled {}", quantity)),
+            _ => parts.push("not filled yet".to_string()),
+        }
+        if let Some(fees) = self.fees {
+            parts.push(format!("fees {}", fees));
+        }
+        if !self.tx_hashes.is_empty() {
+            parts.push(format!("tx {}", self.tx_hashes.join(", ")));
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// An order the exchange still has open.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct OpenOrder {
+    /// ID the exchange cancels the order by
+    #[serde(default)]
+    pub order_id: String,
+    /// ID xtrade gave the order when submitting it
+    pub client_order_id: String,
+    pub symbol: String,
+    pub side: String,
+    pub price: Decimal,
+    pub quantity: Decimal,
+}
+
+/// An executed (part of an) order.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Fill {
+    pub client_order_id: String,
+    pub symbol: String,
+    pub price: Decimal,
+    pub quantity: Decimal,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// An asset held in an account.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Balance {
+    pub asset: Asset,
+    /// Available to trade
+    pub free: Decimal,
+    /// Held by open orders
+    pub locked: Decimal,
+}
+
+/// An open derivatives position.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Position {
+    pub symbol: Symbol,
+    pub side: Side,
+    /// In units of the base asset
+    pub quantity: Decimal,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entry_price: Option<Decimal>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unrealized_pnl: Option<Decimal>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferKind {
+    Deposit,
+    Withdrawal,
+}
+
+impl fmt::Display for TransferKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransferKind::Deposit => write!(f, "deposit"),
+            TransferKind::Withdrawal => write!(f, "withdrawal"),
+        }
+    }
+}
+
+/// Funds moved into or out of an account.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Transfer {
+    /// ID the exchange gave the transfer
+    pub id: String,
+    pub kind: TransferKind,
+    pub asset: String,
+    pub amount: Decimal,
+    /// Address the funds went to or came from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    /// Status as the exchange names it
+    pub status: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// What an API key is allowed to do, as the exchange reported it.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct KeyPermissions {
+    pub can_read: bool,
+    pub can_trade: bool,
+    pub can_withdraw: bool,
+    /// Whether the key only works from listed IP addresses
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip_restricted: Option<bool>,
+    pub checked_at: DateTime<Utc>,
+}
+
+impl fmt::Display for KeyPermissions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let allowed = |allowed: bool| if allowed { "yes" } else { "no" };
+        write!(
+            f,
+            "read {}, trade {}, withdraw {}",
+            allowed(self.can_read),
+            allowed(self.can_trade),
+            allowed(self.can_withdraw)
+        )?;
+        if let Some(restricted) = self.ip_restricted {
+            write!(f, ", IP restricted {}", allowed(restricted))?;
+        }
+        write!(f, " (checked {})", self.checked_at.to_rfc3339())
+    }
+}
 
 /// Defines a trait that each exchange must implement.
 #[async_trait]
 pub trait Exchange {
-    /// Executes a trade based on the provided parameters.
+    /// Places `order` for `quantity` of `symbol_or_contract`.
     async fn execute_trade(
         &self,
-        action: &str,
+        order: &OrderRequest,
         symbol_or_contract: &str, // Symbol for CEX or Contract Address for DEX
-        price: f64,               // Relevant for limit orders; ignored for market orders on DEXes
+        quantity: Decimal,        // In units of the base asset
         slippage: f64,            // Relevant for DEXes
         bot: &Bot,
         client: &Client,
-    ) -> Result<(), Box<dyn Error>>;
+    ) -> Result<ExecutionReport, Box<dyn Error>>;
+
+    /// Orders of `bot` still open on the exchange, or `None` if the venue cannot list them.
+    async fn open_orders(
+        &self,
+        _bot: &Bot,
+        _client: &Client,
+    ) -> Result<Option<Vec<OpenOrder>>, Box<dyn Error + Send + Sync>> {
+        Ok(None)
+    }
+
+    /// Cancel the open order `order_id` of `bot` on `symbol`. Returns `false` if the venue
+    /// cannot cancel orders.
+    async fn cancel_order(
+        &self,
+        _symbol: &str,
+        _order_id: &str,
+        _bot: &Bot,
+        _client: &Client,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        Ok(false)
+    }
+
+    /// Current price of `symbol`, or `None` if the venue cannot quote it.
+    async fn last_price(
+        &self,
+        _symbol: &str,
+        _client: &Client,
+    ) -> Result<Option<f64>, Box<dyn Error + Send + Sync>> {
+        Ok(None)
+    }
+
+    /// Balances of the account of `bot`, or `None` if the venue cannot list them.
+    async fn get_balances(
+        &self,
+        _bot: &Bot,
+        _client: &Client,
+    ) -> Result<Option<Vec<Balance>>, Box<dyn Error + Send + Sync>> {
+        Ok(None)
+    }
+
+    /// Open positions of `bot`, or `None` if the venue cannot list them.
+    async fn get_positions(
+        &self,
+        _bot: &Bot,
+        _client: &Client,
+    ) -> Result<Option<Vec<Position>>, Box<dyn Error + Send + Sync>> {
+        Ok(None)
+    }
+
+    /// Fills of `bot` since `since`, or `None` if the venue cannot list them.
+    async fn recent_fills(
+        &self,
+        _bot: &Bot,
+        _client: &Client,
+        _since: DateTime<Utc>,
+    ) -> Result<Option<Vec<Fill>>, Box<dyn Error + Send + Sync>> {
+        Ok(None)
+    }
+
+    /// What the API key of `bot` is allowed to do, or `None` if the venue cannot tell.
+    async fn key_permissions(
+        &self,
+        _bot: &Bot,
+        _client: &Client,
+    ) -> Result<Option<KeyPermissions>, Box<dyn Error + Send + Sync>> {
+        Ok(None)
+    }
+
+    /// Deposits and withdrawals of the account of `bot` since `since`, or `None` if the
+    /// venue cannot list them.
+    async fn transfers(
+        &self,
+        _bot: &Bot,
+        _client: &Client,
+        _since: DateTime<Utc>,
+    ) -> Result<Option<Vec<Transfer>>, Box<dyn Error + Send + Sync>> {
+        Ok(None)
+    }
+}
+
+/// Whether `error` means the exchange could not be reached at all, because the connection
+/// failed or timed out, rather than that it refused the request.
+pub fn is_unreachable(error: &(dyn Error + 'static)) -> bool {
+    let mut error = Some(error);
+    while let Some(current) = error {
+        if let Some(e) = current.downcast_ref::<reqwest::Error>() {
+            return e.is_connect() || e.is_timeout();
+        }
+        if let Some(e) = current.downcast_ref::<plugin::PluginError>() {
+            return matches!(e, plugin::PluginError::Unreachable(_));
+        }
+        error = current.source();
+    }
+    false
+}
+
+/// Factory function to create the Exchange implementation trading for `bot`: the
+/// [paper backend](paper) for `dry_run` bots, otherwise the adapter of its exchange.
+pub fn get_exchange(bot: &Bot) -> Option<Box<dyn Exchange + Send + Sync>> {
+    get_account(bot, Vec::new())
+}
+
+/// The Exchange implementation of `bot` like [`get_exchange`], listing `paper` as the
+/// positions of a `dry_run` bot.
+pub fn get_account(bot: &Bot, paper: Vec<Position>) -> Option<Box<dyn Exchange + Send + Sync>> {
+    let venue = venue(&bot.exchange);
+    if bot.dry_run {
+        return Some(Box::new(
+            paper::PaperExchange::new(venue).with_positions(paper),
+        ));
+    }
+    venue
 }
 
-/// Factory function to create an instance of the appropriate Exchange implementation.
-pub fn get_exchange(
-    exchange_name: &str,
-    _private_key: Option<&str>,
-) -> Option<Box<dyn Exchange + Send + Sync>> {
+/// Adapter of the exchange `exchange_name`.
+fn venue(exchange_name: &str) -> Option<Box<dyn Exchange + Send + Sync>> {
     match exchange_name.to_lowercase().as_str() {
+        #[cfg(feature = "exchange-binance")]
         "binance" => Some(Box::new(binance::BinanceExchange::new())),
-        // "bybit" => Some(Box::new(bybit::BybitExchange::new())),
-        // "dydx" => Some(Box::new(dydx::DydxExchange::new())),
-        // "hyperliquid" => Some(Box::new(hyperliquid::HyperliquidExchange::new())),
-        // "uniswap" => Some(Box::new(uniswap_base::UniswapExchange::new(...))),
-        // "raydium" => Some(Box::new(raydium_solana::RaydiumExchange::new(...))),
-        _ => None,
+        #[cfg(feature = "exchange-bybit")]
+        "bybit" => Some(Box::new(bybit::BybitExchange::new())),
+        #[cfg(feature = "exchange-dydx")]
+        "dydx" => Some(Box::new(dydx::DydxExchange::new())),
+        #[cfg(feature = "exchange-hyperliquid")]
+        "hyperliquid" => Some(Box::new(hyperliquid::HyperliquidExchange::new())),
+        #[cfg(feature = "exchange-uniswap")]
+        "uniswap" => Some(Box::new(uniswap_base::UniswapExchange::new())),
+        #[cfg(feature = "exchange-raydium")]
+        "raydium" => Some(Box::new(raydium_solana::RaydiumExchange::new())),
+        _ => plugin::adapter(exchange_name),
     }
 }
@@ -0,0 +1,334 @@
+// src/exchange/order.rs
+//! # Order Requests
+//!
+//! What [`Exchange::execute_trade`](super::Exchange::execute_trade) is asked to place. Venues
+//! that only know market and limit orders refuse the other kinds with an error naming them,
+//! rather than placing something else.
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl Side {
+    /// The side of an alert's `action`, `buy` or `sell` in any case.
+    pub fn parse(action: &str) -> Option<Self> {
+        match action.to_lowercase().as_str() {
+            "buy" => Some(Side::Buy),
+            "sell" => Some(Side::Sell),
+            _ => None,
+        }
+    }
+
+    pub fn is_buy(self) -> bool {
+        self == Side::Buy
+    }
+}
+
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", if self.is_buy() { "buy" } else { "sell" })
+    }
+}
+
+/// How long a limit order rests on the book.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum TimeInForce {
+    /// Good till cancelled
+    #[default]
+    Gtc,
+    /// Immediate or cancel: fill what can be filled now, cancel the rest
+    Ioc,
+    /// Fill or kill: fill everything now or nothing
+    Fok,
+}
+
+impl TimeInForce {
+    /// `GTC`, `IOC` or `FOK`, in any case.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_uppercase().as_str() {
+            "GTC" => Some(TimeInForce::Gtc),
+            "IOC" => Some(TimeInForce::Ioc),
+            "FOK" => Some(TimeInForce::Fok),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TimeInForce {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tif = match self {
+            TimeInForce::Gtc => "GTC",
+            TimeInForce::Ioc => "IOC",
+            TimeInForce::Fok => "FOK",
+        };
+        write!(f, "{}", tif)
+    }
+}
+
+/// An order to place; its symbol and quantity are given alongside.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OrderRequest {
+    Market {
+        side: Side,
+    },
+    Limit {
+        side: Side,
+        price: Decimal,
+        #[serde(default)]
+        time_in_force: TimeInForce,
+    },
+    /// Sell below (or buy above) the market once the price reaches `stop_price`: at market,
+    /// or as a limit order at `limit_price` if set
+    StopLoss {
+        side: Side,
+        stop_price: Decimal,
+        limit_price: Option<Decimal>,
+        #[serde(default)]
+        time_in_force: TimeInForce,
+    },
+    /// Sell above (or buy below) the market once the price reaches `trigger_price`: at
+    /// market, or as a limit order at `limit_price` if set
+    TakeProfit {
+        side: Side,
+        trigger_price: Decimal,
+        limit_price: Option<Decimal>,
+        #[serde(default)]
+        time_in_force: TimeInForce,
+    },
+    /// A stop following the price at a distance of `trailing_delta_bps`, from the moment the
+    /// price reaches `activation_price` if set
+    TrailingStop {
+        side: Side,
+        trailing_delta_bps: u32,
+        activation_price: Option<Decimal>,
+    },
+    /// A take-profit limit order and a stop-loss (a stop-limit order if `stop_limit_price`
+    /// is set); whichever executes first cancels the other
+    Oco {
+        side: Side,
+        take_profit: Decimal,
+        stop_price: Decimal,
+        stop_limit_price: Option<Decimal>,
+    },
+}
+
+impl OrderRequest {
+    pub fn side(&self) -> Side {
+        match self {
+            OrderRequest::Market { side }
+            | OrderRequest::Limit { side, .. }
+            | OrderRequest::StopLoss { side, .. }
+            | OrderRequest::TakeProfit { side, .. }
+            | OrderRequest::TrailingStop { side, .. }
+            | OrderRequest::Oco { side, .. } => *side,
+        }
+    }
+
+    /// Kind of the order, as in error messages.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            OrderRequest::Market { .. } => "market",
+            OrderRequest::Limit { .. } => "limit",
+            OrderRequest::StopLoss { .. } => "stop-loss",
+            OrderRequest::TakeProfit { .. } => "take-profit",
+            OrderRequest::TrailingStop { .. } => "trailing-stop",
+            OrderRequest::Oco { .. } => "OCO",
+        }
+    }
+
+    /// Limit price and time in force of a limit order, or `None` for a market order, for
+    /// venues placing nothing else.
+    pub fn market_or_limit(&self, venue: &str) -> Result<Option<(Decimal, TimeInForce)>, String> {
+        match self {
+            OrderRequest::Market { .. } => Ok(None),
+            OrderRequest::Limit {
+                price,
+                time_in_force,
+                ..
+            } => Ok(Some((*price, *time_in_force))),
+            other => Err(format!("{} does not place {} orders", venue, other.kind())),
+        }
+    }
+
+    /// The same order with every price passed through `round`.
+    pub fn map_prices(self, round: impl Fn(Decimal) -> Decimal) -> Self {
+        let round_limit = |price: Option<Decimal>| price.map(&round);
+        match self {
+            OrderRequest::Market { side } => OrderRequest::Market { side },
+            OrderRequest::Limit {
+                side,
+                price,
+                time_in_force,
+            } => OrderRequest::Limit {
+                side,
+                price: round(price),
+                time_in_force,
+            },
+            OrderRequest::StopLoss {
+                side,
+                stop_price,
+                limit_price,
+                time_in_force,
+            } => OrderRequest::StopLoss {
+                side,
+                stop_price: round(stop_price),
+                limit_price: round_limit(limit_price),
+                time_in_force,
+            },
+            OrderRequest::TakeProfit {
+                side,
+                trigger_price,
+                limit_price,
+                time_in_force,
+            } => OrderRequest::TakeProfit {
+                side,
+                trigger_price: round(trigger_price),
+                limit_price: round_limit(limit_price),
+                time_in_force,
+            },
+            OrderRequest::TrailingStop {
+                side,
+                trailing_delta_bps,
+                activation_price,
+            } => OrderRequest::TrailingStop {
+                side,
+                trailing_delta_bps,
+                activation_price: round_limit(activation_price),
+            },
+            OrderRequest::Oco {
+                side,
+                take_profit,
+                stop_price,
+                stop_limit_price,
+            } => OrderRequest::Oco {
+                side,
+                take_profit: round(take_profit),
+                stop_price: round(stop_price),
+                stop_limit_price: round_limit(stop_limit_price),
+            },
+        }
+    }
+
+    /// Every price the order sets.
+    pub fn prices(&self) -> Vec<Decimal> {
+        match self {
+            OrderRequest::Market { .. } => vec![],
+            OrderRequest::Limit { price, .. } => vec![*price],
+            OrderRequest::StopLoss {
+                stop_price,
+                limit_price,
+                ..
+            } => [Some(*stop_price), *limit_price]
+                .into_iter()
+                .flatten()
+                .collect(),
+            OrderRequest::TakeProfit {
+                trigger_price,
+                limit_price,
+                ..
+            } => [Some(*trigger_price), *limit_price]
+                .into_iter()
+                .flatten()
+                .collect(),
+            OrderRequest::TrailingStop {
+                activation_price, ..
+            } => activation_price.iter().copied().collect(),
+            OrderRequest::Oco {
+                take_profit,
+                stop_price,
+                stop_limit_price,
+                ..
+            } => [Some(*take_profit), Some(*stop_price), *stop_limit_price]
+                .into_iter()
+                .flatten()
+                .collect(),
+        }
+    }
+
+    /// Limit price of a good-till-cancelled limit order, or zero for a market order, for venues
+    /// placing nothing else.
+    #[cfg(any(
+        feature = "exchange-dydx",
+        feature = "exchange-uniswap",
+        feature = "exchange-raydium"
+    ))]
+    pub fn price(&self, venue: &str) -> Result<Decimal, String> {
+        match self.market_or_limit(venue)? {
+            None => Ok(Decimal::ZERO),
+            Some((price, TimeInForce::Gtc)) => Ok(price),
+            Some((_, tif)) => Err(format!("{} does not place {} limit orders", venue, tif)),
+        }
+    }
+}
+
+impl fmt::Display for OrderRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let limit = |price: &Option<Decimal>| {
+            price.map_or_else(String::new, |price| format!(", limit {}", price))
+        };
+        match self {
+            OrderRequest::Market { side } => write!(f, "{} at market", side),
+            OrderRequest::Limit {
+                side,
+                price,
+                time_in_force,
+            } => write!(f, "{} limit {} {}", side, price, time_in_force),
+            OrderRequest::StopLoss {
+                side,
+                stop_price,
+                limit_price,
+                ..
+            } => write!(
+                f,
+                "{} stop-loss at {}{}",
+                side,
+                stop_price,
+                limit(limit_price)
+            ),
+            OrderRequest::TakeProfit {
+                side,
+                trigger_price,
+                limit_price,
+                ..
+            } => write!(
+                f,
+                "{} take-profit at {}{}",
+                side,
+                trigger_price,
+                limit(limit_price)
+            ),
+            OrderRequest::TrailingStop {
+                side,
+                trailing_delta_bps,
+                activation_price,
+            } => {
+                write!(f, "{} trailing stop {} bps", side, trailing_delta_bps)?;
+                match activation_price {
+                    Some(price) => write!(f, " from {}", price),
+                    None => Ok(()),
+                }
+            }
+            OrderRequest::Oco {
+                side,
+                take_profit,
+                stop_price,
+                stop_limit_price,
+            } => write!(
+                f,
+                "{} OCO take-profit {} / stop {}{}",
+                side,
+                take_profit,
+                stop_price,
+                limit(stop_limit_price)
+            ),
+        }
+    }
+}
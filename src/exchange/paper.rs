@@ -0,0 +1,469 @@
+// src/exchange/paper.rs
+//! # Paper Trading
+//!
+//! Backend of bots with `dry_run` set: trades are never sent anywhere. Market orders fill at
+//! the price the bot's real exchange quotes, moved against the trader by the bot's
+//! `paper_slippage_bps` (5 bps if unset), and limit orders fill at their limit. Fees are the
+//! notional times `trading_fee`. The execution reports of the simulated fills end up in the
+//! bot's [paper positions](crate::bot::state::paper).
+//!
+//! By default whole orders fill the moment they are placed. A bot's [`PaperFills`] make the
+//! simulation closer to what its exchange would do: orders take `latency_ms` to get there,
+//! market orders fill at the next price the exchange quotes rather than the current one
+//! with the `next_tick` model, and only `fill_pct` percent of each order fills.
+//!
+//! [`slipped`] and [`SimulatedFill`] price fills the same way for prices that are not
+//! quoted live, such as those of [replayed alerts](crate::bot::replay).
+use crate::bot::model::Bot;
+use crate::exchange::{Exchange, ExecutionReport, OrderRequest, Position};
+use async_trait::async_trait;
+use chrono::Utc;
+use reqwest::Client;
+use rust_decimal::{dec, Decimal};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Slippage of simulated market orders when the bot does not set one.
+pub const DEFAULT_SLIPPAGE_BPS: Decimal = dec!(5);
+/// Longest a `next_tick` order waits for the price to move; it fills at the last one then.
+pub const NEXT_TICK_TIMEOUT: Duration = Duration::from_secs(10);
+/// How often a `next_tick` order asks for the price while it waits.
+const NEXT_TICK_POLL: Duration = Duration::from_millis(500);
+
+/// What price simulated market orders fill at.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FillModel {
+    /// The price quoted when the order reaches the exchange
+    #[default]
+    Instant,
+    /// The first price quoted after that one, as if the order waited for the next trade
+    NextTick,
+}
+
+impl FillModel {
+    pub fn is_instant(&self) -> bool {
+        *self == FillModel::Instant
+    }
+}
+
+impl FromStr for FillModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace('-', "_").as_str() {
+            "instant" => Ok(FillModel::Instant),
+            "next_tick" => Ok(FillModel::NextTick),
+            _ => Err(format!(
+                "fill model must be instant or next_tick, not '{}'",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for FillModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FillModel::Instant => write!(f, "instant"),
+            FillModel::NextTick => write!(f, "next_tick"),
+        }
+    }
+}
+
+/// How the simulated orders of a bot fill; by default whole orders fill at once.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct PaperFills {
+    #[serde(default, skip_serializing_if = "FillModel::is_instant")]
+    pub model: FillModel,
+    /// Share of each order that fills, in percent; the rest never does
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fill_pct: Option<Decimal>,
+    /// Milliseconds orders take to reach the exchange
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+}
+
+impl PaperFills {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The part of an order of `quantity` that fills.
+    pub fn filled(&self, quantity: Decimal) -> Decimal {
+        match self.fill_pct {
+            Some(pct) => (quantity * pct / dec!(100)).normalize(),
+            None => quantity,
+        }
+    }
+}
+
+/// The part of a simulated order that fills, at what price, and its fee.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SimulatedFill {
+    pub quantity: Decimal,
+    pub price: Decimal,
+    pub fee: Decimal,
+}
+
+impl SimulatedFill {
+    /// What an order of `bot` for `quantity` fills at `price`: its `fill_pct`, charged its
+    /// `trading_fee` on the notional.
+    pub fn at(price: Decimal, quantity: Decimal, bot: &Bot) -> Self {
+        let quantity = bot.paper_fills.filled(quantity);
+        Self {
+            quantity,
+            price,
+            fee: price * quantity * bot.trading_fee.unwrap_or_default(),
+        }
+    }
+}
+
+/// Price a market order of `bot` fills at when the market is at `quote`: moved against the
+/// trader by the bot's slippage.
+pub fn slipped(quote: Decimal, buy: bool, bot: &Bot) -> Decimal {
+    let slippage = bot.paper_slippage_bps.unwrap_or(DEFAULT_SLIPPAGE_BPS) / dec!(10_000);
+    if buy {
+        quote * (Decimal::ONE + slippage)
+    } else {
+        quote * (Decimal::ONE - slippage)
+    }
+}
+
+/// Refuse a `fill_pct` that is not a share of an order.
+pub fn check_fill_pct(fill_pct: Option<Decimal>) -> Result<(), String> {
+    match fill_pct {
+        Some(pct) if pct <= Decimal::ZERO || pct > dec!(100) => Err(format!(
+            "paper_fill_pct must be above 0 and at most 100, not {}",
+            pct
+        )),
+        _ => Ok(()),
+    }
+}
+
+impl fmt::Display for PaperFills {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.model)?;
+        if let Some(pct) = self.fill_pct {
+            write!(f, ", {}% of each order", pct)?;
+        }
+        if let Some(latency) = self.latency_ms {
+            write!(f, ", {} ms latency", latency)?;
+        }
+        Ok(())
+    }
+}
+
+/// Simulates the trades of a bot, quoting prices from its real exchange.
+pub struct PaperExchange {
+    quotes: Option<Box<dyn Exchange + Send + Sync>>,
+    /// Open paper positions of the bot, from the state
+    positions: Vec<Position>,
+}
+
+impl PaperExchange {
+    /// A paper backend taking its prices from `quotes`, if given.
+    pub fn new(quotes: Option<Box<dyn Exchange + Send + Sync>>) -> Self {
+        Self {
+            quotes,
+            positions: Vec::new(),
+        }
+    }
+
+    /// The backend listing `positions` as the bot's open ones.
+    pub fn with_positions(mut self, positions: Vec<Position>) -> Self {
+        self.positions = positions;
+        self
+    }
+
+    /// Price the real exchange quotes for `symbol`.
+    async fn quote(
+        &self,
+        symbol: &str,
+        bot: &Bot,
+        client: &Client,
+    ) -> Result<Decimal, Box<dyn Error>> {
+        let quote = match &self.quotes {
+            Some(quotes) => quotes
+                .last_price(symbol, client)
+                .await
+                .map_err(|e| e as Box<dyn Error>)?,
+            None => None,
+        };
+        let quote = quote.ok_or_else(|| {
+            format!(
+                "{} cannot quote {}; paper trades need a price",
+                bot.exchange, symbol
+            )
+        })?;
+        Ok(Decimal::try_from(quote)
+            .map_err(|_| format!("{} quoted {} at {}", bot.exchange, symbol, quote))?)
+    }
+
+    /// The first price quoted for `symbol` that differs from `current`, or `current` if the
+    /// price did not move within [`NEXT_TICK_TIMEOUT`].
+    async fn next_tick(
+        &self,
+        symbol: &str,
+        current: Decimal,
+        bot: &Bot,
+        client: &Client,
+    ) -> Result<Decimal, Box<dyn Error>> {
+        let deadline = Instant::now() + NEXT_TICK_TIMEOUT;
+        while Instant::now() < deadline {
+            tokio::time::sleep(NEXT_TICK_POLL).await;
+            let quote = self.quote(symbol, bot, client).await?;
+            if quote != current {
+                return Ok(quote);
+            }
+        }
+        log::debug!(
+            "{} did not move within {:?}; paper order of bot '{}' fills at {}",
+            symbol,
+            NEXT_TICK_TIMEOUT,
+            bot.bot_id,
+            current
+        );
+        Ok(current)
+    }
+
+    /// Price a market order of `bot` on `symbol` would fill at.
+    async fn market_price(
+        &self,
+        symbol: &str,
+        buy: bool,
+        bot: &Bot,
+        client: &Client,
+    ) -> Result<Decimal, Box<dyn Error>> {
+        let mut quote = self.quote(symbol, bot, client).await?;
+        if bot.paper_fills.model == FillModel::NextTick {
+            quote = self.next_tick(symbol, quote, bot, client).await?;
+        }
+        Ok(slipped(quote, buy, bot))
+    }
+}
+
+#[async_trait]
+impl Exchange for PaperExchange {
+    async fn execute_trade(
+        &self,
+        order: &OrderRequest,
+        symbol_or_contract: &str,
+        quantity: Decimal,
+        _slippage: f64,
+        bot: &Bot,
+        client: &Client,
+    ) -> Result<ExecutionReport, Box<dyn Error>> {
+        let submitted_at = Utc::now();
+        let buy = order.side().is_buy();
+        let limit = order.market_or_limit("Paper trading")?;
+        if let Some(latency) = bot.paper_fills.latency_ms.filter(|ms| *ms > 0) {
+            tokio::time::sleep(Duration::from_millis(latency)).await;
+        }
+        let price = match limit {
+            Some((price, _)) => price,
+            None => {
+                self.market_price(symbol_or_contract, buy, bot, client)
+                    .await?
+            }
+        };
+        let fill = SimulatedFill::at(price, quantity, bot);
+        log::info!(
+            "Paper trade for bot '{}': {} {} of {} {} at {} (fee {})",
+            bot.bot_id,
+            order.side(),
+            fill.quantity,
+            quantity,
+            symbol_or_contract,
+            fill.price,
+            fill.fee
+        );
+        Ok(ExecutionReport {
+            filled_quantity: Some(fill.quantity),
+            average_price: Some(fill.price),
+            fees: Some(fill.fee),
+            ..ExecutionReport::acknowledged(submitted_at)
+        })
+    }
+
+    async fn last_price(
+        &self,
+        symbol: &str,
+        client: &Client,
+    ) -> Result<Option<f64>, Box<dyn Error + Send + Sync>> {
+        match &self.quotes {
+            Some(quotes) => quotes.last_price(symbol, client).await,
+            None => Ok(None),
+        }
+    }
+
+    async fn get_positions(
+        &self,
+        _bot: &Bot,
+        _client: &Client,
+    ) -> Result<Option<Vec<Position>>, Box<dyn Error + Send + Sync>> {
+        Ok(Some(self.positions.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::{Side, TimeInForce};
+
+    struct Quote(f64);
+
+    #[async_trait]
+    impl Exchange for Quote {
+        async fn execute_trade(
+            &self,
+            _order: &OrderRequest,
+            _symbol_or_contract: &str,
+            _quantity: Decimal,
+            _slippage: f64,
+            _bot: &Bot,
+            _client: &Client,
+        ) -> Result<ExecutionReport, Box<dyn Error>> {
+            Err("real trades must not be placed".into())
+        }
+
+        async fn last_price(
+            &self,
+            _symbol: &str,
+            _client: &Client,
+        ) -> Result<Option<f64>, Box<dyn Error + Send + Sync>> {
+            Ok(Some(self.0))
+        }
+    }
+
+    #[tokio::test]
+    async fn fills_are_simulated_with_slippage_and_fees() {
+        let bot = Bot {
+            exchange: "bybit".to_string(),
+            trading_fee: Some(dec!(0.001)),
+            paper_slippage_bps: Some(dec!(10)),
+            dry_run: true,
+            ..Default::default()
+        };
+        let client = Client::new();
+        let paper = PaperExchange::new(Some(Box::new(Quote(100.0))));
+
+        let limit = OrderRequest::Limit {
+            side: Side::Sell,
+            price: dec!(105),
+            time_in_force: TimeInForce::Gtc,
+        };
+        // Decimal arithmetic makes the simulated prices and fees exact
+        for (order, quantity, price, fee) in [
+            (
+                OrderRequest::Market { side: Side::Buy },
+                dec!(2),
+                dec!(100.1),
+                dec!(0.2002),
+            ),
+            (
+                OrderRequest::Market { side: Side::Sell },
+                dec!(1),
+                dec!(99.9),
+                dec!(0.0999),
+            ),
+            (limit, dec!(1), dec!(105), dec!(0.105)),
+        ] {
+            let report = paper
+                .execute_trade(&order, "BTCUSDT", quantity, 0.0, &bot, &client)
+                .await
+                .unwrap();
+            assert_eq!(report.filled_quantity, Some(quantity));
+            assert_eq!(report.average_price, Some(price));
+            assert_eq!(report.fees, Some(fee));
+            assert!(report.order_id.is_none() && report.tx_hashes.is_empty());
+        }
+
+        let unquoted = PaperExchange::new(None);
+        let err = unquoted
+            .execute_trade(
+                &OrderRequest::Market { side: Side::Buy },
+                "BTCUSDT",
+                dec!(1),
+                0.0,
+                &bot,
+                &client,
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("cannot quote"));
+    }
+
+    /// Quotes the prices in turn, then the last one for good.
+    struct Ticks(std::sync::Mutex<Vec<f64>>);
+
+    #[async_trait]
+    impl Exchange for Ticks {
+        async fn execute_trade(
+            &self,
+            _order: &OrderRequest,
+            _symbol_or_contract: &str,
+            _quantity: Decimal,
+            _slippage: f64,
+            _bot: &Bot,
+            _client: &Client,
+        ) -> Result<ExecutionReport, Box<dyn Error>> {
+            Err("real trades must not be placed".into())
+        }
+
+        async fn last_price(
+            &self,
+            _symbol: &str,
+            _client: &Client,
+        ) -> Result<Option<f64>, Box<dyn Error + Send + Sync>> {
+            let mut ticks = self.0.lock().unwrap();
+            match ticks.len() {
+                1 => Ok(ticks.first().copied()),
+                _ => Ok(Some(ticks.remove(0))),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn fills_can_wait_for_the_next_tick_and_fill_in_part() {
+        let bot = Bot {
+            exchange: "bybit".to_string(),
+            trading_fee: Some(dec!(0.001)),
+            paper_slippage_bps: Some(Decimal::ZERO),
+            paper_fills: PaperFills {
+                model: FillModel::NextTick,
+                fill_pct: Some(dec!(25)),
+                latency_ms: Some(50),
+            },
+            dry_run: true,
+            ..Default::default()
+        };
+        let ticks = Ticks(std::sync::Mutex::new(vec![100.0, 100.0, 101.0]));
+        let paper = PaperExchange::new(Some(Box::new(ticks)));
+        let report = paper
+            .execute_trade(
+                &OrderRequest::Market { side: Side::Buy },
+                "BTCUSDT",
+                dec!(2),
+                0.0,
+                &bot,
+                &Client::new(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(report.average_price, Some(dec!(101)));
+        assert_eq!(report.filled_quantity, Some(dec!(0.5)));
+        assert_eq!(report.fees, Some(dec!(0.0505)));
+        let latency = report.acknowledged_at - report.submitted_at;
+        assert!(latency >= chrono::Duration::milliseconds(50));
+
+        assert_eq!("next-tick".parse(), Ok(FillModel::NextTick));
+        assert!(check_fill_pct(Some(dec!(100))).is_ok());
+        assert!(check_fill_pct(Some(Decimal::ZERO)).is_err());
+        assert!(check_fill_pct(Some(dec!(150))).is_err());
+    }
+}
@@ -0,0 +1,435 @@
+// src/exchange/plugin.rs
+//! Exchange adapters shipped by third parties as programs of their own, declared under
+//! `[plugins.exchanges.<name>]` and used by bots of that exchange like the built-in ones,
+//! which win over a plugin of the same name.
+//!
+//! Each call of the [`Exchange`] trait runs the program, writes one JSON-RPC 2.0 request
+//! to its stdin and reads the response from its stdout:
+//!
+//! ```text
+//! → {"jsonrpc":"2.0","id":1,"method":"last_price","params":{"symbol":"BTCUSDT"}}
+//! ← {"jsonrpc":"2.0","id":1,"result":64250.5}
+//! ```
+//!
+//! Methods are named like the trait's and take its arguments by name; the bot is passed as
+//! its `account`, only its credentials and endpoints. Decimals are strings and times RFC
+//! 3339. A `null` result, or the error `-32601` (method not found), is what the built-in
+//! adapters answer for what their venue cannot do; the error [`UNREACHABLE`] means the
+//! exchange could not be reached, which buffers alerts like an outage.
+//!
+//! The program starts with no environment but `PATH` and the configured `env`, and is
+//! killed once the call takes longer than `timeout_secs`; nothing it writes to stderr is
+//! kept but the last line of a failed call. `execute_trade` answers with the fields of an
+//! [`ExecutionReport`] but its times, which the server keeps itself.
+use super::{
+    Balance, Exchange, ExecutionReport, Fill, KeyPermissions, OpenOrder, OrderRequest, Position,
+    Transfer,
+};
+use crate::app_config::{ExchangePluginConfig, PluginConfig};
+use crate::bot::model::Bot;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use rust_decimal::Decimal;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::process::Stdio;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// JSON-RPC error of a method the adapter does not implement.
+pub const METHOD_NOT_FOUND: i64 = -32601;
+/// JSON-RPC error of an adapter that could not reach its exchange.
+pub const UNREACHABLE: i64 = -32000;
+
+/// Adapters by lowercase exchange name.
+static PLUGINS: OnceLock<HashMap<String, PluginExchange>> = OnceLock::new();
+
+/// Trade through the adapters of `config` from now on; only the first call counts.
+pub fn init(config: &PluginConfig) {
+    let plugins = (config.exchanges.iter())
+        .filter(|(name, _)| {
+            let shadowed = super::venue(name).is_some();
+            if shadowed {
+                log::warn!(
+                    "Ignoring the exchange plugin '{}': the exchange is built in.",
+                    name
+                );
+            }
+            !shadowed
+        })
+        .map(|(name, config)| {
+            log::info!(
+                "Exchange '{}' trades through {}.",
+                name,
+                config.command.display()
+            );
+            (
+                name.to_lowercase(),
+                PluginExchange::new(name, config.clone()),
+            )
+        })
+        .collect();
+    let _ = PLUGINS.set(plugins);
+}
+
+/// Adapter of the plugin exchange `exchange_name`.
+pub(super) fn adapter(exchange_name: &str) -> Option<Box<dyn Exchange + Send + Sync>> {
+    let plugin = PLUGINS.get()?.get(&exchange_name.to_lowercase())?;
+    Some(Box::new(plugin.clone()))
+}
+
+/// Why a call of an adapter failed.
+#[derive(Debug)]
+pub enum PluginError {
+    /// The exchange could not be reached, or the adapter did not answer in time
+    Unreachable(String),
+    /// The adapter failed, or refused the call
+    Failed(String),
+}
+
+impl fmt::Display for PluginError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PluginError::Unreachable(message) | PluginError::Failed(message) => {
+                write!(f, "{}", message)
+            }
+        }
+    }
+}
+
+impl Error for PluginError {}
+
+/// What of a bot an adapter gets to trade for it.
+#[derive(Serialize)]
+struct Account<'a> {
+    bot_id: &'a str,
+    exchange: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    api_key: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    api_secret: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    private_key: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rest_endpoint: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rpc_endpoint: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    contract_address: Option<&'a str>,
+}
+
+impl<'a> From<&'a Bot> for Account<'a> {
+    fn from(bot: &'a Bot) -> Self {
+        Self {
+            bot_id: &bot.bot_id,
+            exchange: &bot.exchange,
+            api_key: bot.api_key.as_deref(),
+            api_secret: bot.api_secret.as_deref(),
+            private_key: bot.private_key.as_deref(),
+            rest_endpoint: bot.rest_endpoint.as_deref(),
+            rpc_endpoint: bot.rpc_endpoint.as_deref(),
+            contract_address: bot.contract_address.as_deref(),
+        }
+    }
+}
+
+/// What an adapter reports of a trade; the server times it.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct TradeReport {
+    order_id: Option<String>,
+    filled_quantity: Option<Decimal>,
+    average_price: Option<Decimal>,
+    fees: Option<Decimal>,
+    tx_hashes: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    #[serde(default)]
+    result: Value,
+    #[serde(default)]
+    error: Option<RpcError>,
+}
+
+#[derive(Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+/// An exchange traded through a plugin program.
+#[derive(Clone)]
+pub struct PluginExchange {
+    name: String,
+    config: Arc<ExchangePluginConfig>,
+}
+
+impl PluginExchange {
+    pub fn new(name: &str, config: ExchangePluginConfig) -> Self {
+        Self {
+            name: name.to_string(),
+            config: Arc::new(config),
+        }
+    }
+
+    /// Result of `method`, or `None` if the adapter cannot do it.
+    async fn call<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<Option<T>, PluginError> {
+        let failed = |message: String| {
+            PluginError::Failed(format!("Exchange plugin '{}': {}", self.name, message))
+        };
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let mut child = Command::new(&self.config.command)
+            .args(&self.config.args)
+            .env_clear()
+            .envs(std::env::var_os("PATH").map(|path| ("PATH", path)))
+            .envs(&self.config.env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| {
+                failed(format!(
+                    "cannot run {}: {}",
+                    self.config.command.display(),
+                    e
+                ))
+            })?;
+
+        let timeout = Duration::from_secs(self.config.timeout_secs.max(1));
+        let exchange = async {
+            if let Some(mut stdin) = child.stdin.take() {
+                // An adapter may answer without reading all of the request
+                let _ = stdin.write_all(format!("{}\n", request).as_bytes()).await;
+            }
+            child.wait_with_output().await
+        };
+        let output = match tokio::time::timeout(timeout, exchange).await {
+            Ok(output) => output.map_err(|e| failed(e.to_string()))?,
+            // Dropping the call has killed the program
+            Err(_) => {
+                return Err(PluginError::Unreachable(format!(
+                    "Exchange plugin '{}' did not answer {} within {}s",
+                    self.name,
+                    method,
+                    timeout.as_secs()
+                )))
+            }
+        };
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let Some(line) = stdout.lines().rev().find(|line| !line.trim().is_empty()) else {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let reason = (stderr.lines().rev())
+                .find(|line| !line.trim().is_empty())
+                .unwrap_or("no response");
+            return Err(failed(format!(
+                "{} ({}): {}",
+                method, output.status, reason
+            )));
+        };
+        let response: Response = serde_json::from_str(line)
+            .map_err(|e| failed(format!("invalid response to {}: {}", method, e)))?;
+        match response.error {
+            Some(error) if error.code == METHOD_NOT_FOUND => Ok(None),
+            Some(error) if error.code == UNREACHABLE => Err(PluginError::Unreachable(format!(
+                "Exchange plugin '{}': {}",
+                self.name, error.message
+            ))),
+            Some(error) => Err(failed(error.message)),
+            None if response.result.is_null() => Ok(None),
+            None => serde_json::from_value(response.result)
+                .map(Some)
+                .map_err(|e| failed(format!("invalid result of {}: {}", method, e))),
+        }
+    }
+}
+
+#[async_trait]
+impl Exchange for PluginExchange {
+    async fn execute_trade(
+        &self,
+        order: &OrderRequest,
+        symbol_or_contract: &str,
+        quantity: Decimal,
+        slippage: f64,
+        bot: &Bot,
+        _client: &Client,
+    ) -> Result<ExecutionReport, Box<dyn Error>> {
+        let params = json!({
+            "account": Account::from(bot),
+            "order": order,
+            "symbol": symbol_or_contract,
+            "quantity": quantity,
+            "slippage": slippage,
+        });
+        let submitted_at = Utc::now();
+        let report: TradeReport = (self.call("execute_trade", params).await?)
+            .ok_or_else(|| format!("Exchange plugin '{}' cannot trade", self.name))?;
+        Ok(ExecutionReport {
+            order_id: report.order_id,
+            filled_quantity: report.filled_quantity,
+            average_price: report.average_price,
+            fees: report.fees,
+            tx_hashes: report.tx_hashes,
+            ..ExecutionReport::acknowledged(submitted_at)
+        })
+    }
+
+    async fn open_orders(
+        &self,
+        bot: &Bot,
+        _client: &Client,
+    ) -> Result<Option<Vec<OpenOrder>>, Box<dyn Error + Send + Sync>> {
+        let params = json!({ "account": Account::from(bot) });
+        Ok(self.call("open_orders", params).await?)
+    }
+
+    async fn cancel_order(
+        &self,
+        symbol: &str,
+        order_id: &str,
+        bot: &Bot,
+        _client: &Client,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let params =
+            json!({ "account": Account::from(bot), "symbol": symbol, "order_id": order_id });
+        Ok(self.call("cancel_order", params).await?.unwrap_or(false))
+    }
+
+    async fn last_price(
+        &self,
+        symbol: &str,
+        _client: &Client,
+    ) -> Result<Option<f64>, Box<dyn Error + Send + Sync>> {
+        Ok(self.call("last_price", json!({ "symbol": symbol })).await?)
+    }
+
+    async fn get_balances(
+        &self,
+        bot: &Bot,
+        _client: &Client,
+    ) -> Result<Option<Vec<Balance>>, Box<dyn Error + Send + Sync>> {
+        let params = json!({ "account": Account::from(bot) });
+        Ok(self.call("get_balances", params).await?)
+    }
+
+    async fn get_positions(
+        &self,
+        bot: &Bot,
+        _client: &Client,
+    ) -> Result<Option<Vec<Position>>, Box<dyn Error + Send + Sync>> {
+        let params = json!({ "account": Account::from(bot) });
+        Ok(self.call("get_positions", params).await?)
+    }
+
+    async fn recent_fills(
+        &self,
+        bot: &Bot,
+        _client: &Client,
+        since: DateTime<Utc>,
+    ) -> Result<Option<Vec<Fill>>, Box<dyn Error + Send + Sync>> {
+        let params = json!({ "account": Account::from(bot), "since": since });
+        Ok(self.call("recent_fills", params).await?)
+    }
+
+    async fn key_permissions(
+        &self,
+        bot: &Bot,
+        _client: &Client,
+    ) -> Result<Option<KeyPermissions>, Box<dyn Error + Send + Sync>> {
+        let params = json!({ "account": Account::from(bot) });
+        Ok(self.call("key_permissions", params).await?)
+    }
+
+    async fn transfers(
+        &self,
+        bot: &Bot,
+        _client: &Client,
+        since: DateTime<Utc>,
+    ) -> Result<Option<Vec<Transfer>>, Box<dyn Error + Send + Sync>> {
+        let params = json!({ "account": Account::from(bot), "since": since });
+        Ok(self.call("transfers", params).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::is_unreachable;
+
+    /// An adapter answering each method as the shell `script` does with the request on its
+    /// stdin.
+    fn adapter(script: &str) -> PluginExchange {
+        let config = ExchangePluginConfig {
+            command: "sh".into(),
+            args: vec!["-c".to_string(), script.to_string()],
+            env: HashMap::from([("QUOTE".to_string(), "101.5".to_string())]),
+            timeout_secs: 1,
+        };
+        PluginExchange::new("test", config)
+    }
+
+    #[tokio::test]
+    async fn adapters_answer_over_stdio_within_their_timeout() {
+        let plugin = adapter(
+            r#"read request
+            case "$request" in
+              *'"method":"last_price"'*'"symbol":"BTCUSDT"'*)
+                echo "{\"jsonrpc\":\"2.0\",\"id\":1,\"result\":$QUOTE}" ;;
+              *'"method":"execute_trade"'*'"api_key":"key"'*)
+                echo '{"jsonrpc":"2.0","id":1,"result":{"order_id":"7","filled_quantity":"0.5"}}' ;;
+              *'"method":"get_balances"'*)
+                sleep 5 ;;
+              *'"method":"get_positions"'*)
+                echo '{"jsonrpc":"2.0","id":1,"error":{"code":-32000,"message":"down"}}' ;;
+              *'"method":"cancel_order"'*)
+                echo 'oops' >&2; exit 3 ;;
+              *)
+                echo '{"jsonrpc":"2.0","id":1,"error":{"code":-32601,"message":"no"}}' ;;
+            esac"#,
+        );
+        let client = Client::new();
+        let bot = Bot {
+            bot_id: "alpha".to_string(),
+            exchange: "test".to_string(),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        };
+
+        let price = plugin.last_price("BTCUSDT", &client).await.unwrap();
+        assert_eq!(price, Some(101.5));
+        let order = OrderRequest::Market {
+            side: crate::exchange::Side::Buy,
+        };
+        let report = (plugin.execute_trade(&order, "BTCUSDT", Decimal::ONE, 0.0, &bot, &client))
+            .await
+            .unwrap();
+        assert_eq!(report.order_id.as_deref(), Some("7"));
+        assert_eq!(report.filled_quantity, Some("0.5".parse().unwrap()));
+        // What the adapter does not implement, the venue cannot do
+        assert_eq!(plugin.open_orders(&bot, &client).await.unwrap(), None);
+
+        let slow = plugin.get_balances(&bot, &client).await.unwrap_err();
+        assert!(is_unreachable(slow.as_ref()), "{}", slow);
+        let down = plugin.get_positions(&bot, &client).await.unwrap_err();
+        assert!(is_unreachable(down.as_ref()));
+        let failed = (plugin.cancel_order("BTCUSDT", "7", &bot, &client))
+            .await
+            .unwrap_err();
+        assert!(!is_unreachable(failed.as_ref()));
+        assert!(failed.to_string().ends_with("oops"), "{}", failed);
+    }
+}
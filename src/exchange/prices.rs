@@ -0,0 +1,225 @@
+// src/exchange/prices.rs
+//! # Price Cache
+//!
+//! Risk checks and order sizing need a recent price for a symbol, but should not hit an
+//! exchange for every alert. The cache keeps the last price seen for each symbol — from
+//! market data, fills or explicit refreshes — and is sticky: an observation older than the
+//! cached one never replaces it. Prices older than `prices.max_staleness_secs` are refreshed
+//! from the exchange or rejected, depending on `prices.on_stale`. Symbols are keyed in their
+//! [canonical form](Symbol::normalize), so `BTC/USDT` and `BTCUSDT` share a price.
+use super::{Exchange, Symbol};
+use crate::app_config::{PriceCacheConfig, StalePricePolicy};
+use crate::errors::AppError;
+use chrono::{DateTime, Duration, Utc};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Quote {
+    pub price: f64,
+    pub observed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default)]
+pub struct PriceCache {
+    quotes: RwLock<HashMap<String, Quote>>,
+    config: PriceCacheConfig,
+}
+
+impl PriceCache {
+    pub fn new(config: PriceCacheConfig) -> Self {
+        Self {
+            quotes: RwLock::default(),
+            config,
+        }
+    }
+
+    /// Remember `price` for `symbol` unless a newer price is already cached.
+    ///
+    /// Returns `false` if the observation was ignored, including non-positive prices.
+    pub fn record(&self, symbol: &str, price: f64, observed_at: DateTime<Utc>) -> bool {
+        if !price.is_finite() || price <= 0.0 {
+            log::warn!("Ignoring invalid price {} for {}", price, symbol);
+            return false;
+        }
+        let Ok(mut quotes) = self.quotes.write() else {
+            return false;
+        };
+        let symbol = Symbol::normalize(symbol);
+        match quotes.get(&symbol) {
+            Some(cached) if cached.observed_at > observed_at => false,
+            _ => {
+                quotes.insert(symbol, Quote { price, observed_at });
+                true
+            }
+        }
+    }
+
+    /// The cached price of `symbol`, however old.
+    pub fn quote(&self, symbol: &str) -> Option<Quote> {
+        self.quotes
+            .read()
+            .ok()?
+            .get(&Symbol::normalize(symbol))
+            .copied()
+    }
+
+    /// Every cached price, ordered by symbol.
+    pub fn quotes(&self) -> Vec<(String, Quote)> {
+        let mut quotes: Vec<(String, Quote)> = self
+            .quotes
+            .read()
+            .map(|quotes| quotes.iter().map(|(s, q)| (s.clone(), *q)).collect())
+            .unwrap_or_default();
+        quotes.sort_by(|a, b| a.0.cmp(&b.0));
+        quotes
+    }
+
+    /// Forget every cached price, e.g. once another process changed the bots they were
+    /// recorded for.
+    pub fn clear(&self) {
+        if let Ok(mut quotes) = self.quotes.write() {
+            quotes.clear();
+        }
+    }
+
+    pub fn is_stale(&self, quote: &Quote, now: DateTime<Utc>) -> bool {
+        now - quote.observed_at > Duration::seconds(self.config.max_staleness_secs as i64)
+    }
+
+    /// The cached price of `symbol` if it is fresh enough.
+    pub fn fresh(&self, symbol: &str, now: DateTime<Utc>) -> Result<Quote, AppError> {
+        match self.quote(symbol) {
+            Some(quote) if !self.is_stale(&quote, now) => Ok(quote),
+            quote => Err(AppError::StalePrice {
+                symbol: symbol.to_string(),
+                age_secs: quote.map(|quote| (now - quote.observed_at).num_seconds()),
+                max_staleness_secs: self.config.max_staleness_secs,
+            }),
+        }
+    }
+
+    /// A fresh price of `symbol`, asking `source` for one when the cached price is stale and
+    /// the policy allows it.
+    pub async fn price(
+        &self,
+        symbol: &str,
+        now: DateTime<Utc>,
+        source: &(dyn Exchange + Send + Sync),
+        client: &Client,
+    ) -> Result<Quote, AppError> {
+        let stale = match self.fresh(symbol, now) {
+            Ok(quote) => return Ok(quote),
+            Err(e) if self.config.on_stale == StalePricePolicy::Reject => return Err(e),
+            Err(e) => e,
+        };
+        match source.last_price(symbol, client).await {
+            Ok(Some(price)) if self.record(symbol, price, now) => Ok(Quote {
+                price,
+                observed_at: now,
+            }),
+            Ok(_) => Err(stale),
+            Err(e) => {
+                log::warn!("Failed to refresh the price of {}: {}", symbol, e);
+                Err(stale)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::model::Bot;
+    use crate::exchange::{ExecutionReport, OrderRequest};
+    use async_trait::async_trait;
+    use rust_decimal::Decimal;
+    use std::error::Error;
+
+    struct FixedPrice(Option<f64>);
+
+    #[async_trait]
+    impl Exchange for FixedPrice {
+        async fn execute_trade(
+            &self,
+            _order: &OrderRequest,
+            _symbol_or_contract: &str,
+            _quantity: Decimal,
+            _slippage: f64,
+            _bot: &Bot,
+            _client: &Client,
+        ) -> Result<ExecutionReport, Box<dyn Error>> {
+            Ok(ExecutionReport::acknowledged(Utc::now()))
+        }
+
+        async fn last_price(
+            &self,
+            _symbol: &str,
+            _client: &Client,
+        ) -> Result<Option<f64>, Box<dyn Error + Send + Sync>> {
+            Ok(self.0)
+        }
+    }
+
+    fn cache(on_stale: StalePricePolicy) -> PriceCache {
+        PriceCache::new(PriceCacheConfig {
+            max_staleness_secs: 30,
+            on_stale,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn older_observations_never_replace_newer_ones() {
+        let cache = cache(StalePricePolicy::Reject);
+        let now = Utc::now();
+
+        assert!(cache.record("BTCUSDT", 100.0, now));
+        assert!(!cache.record("BTCUSDT", 90.0, now - Duration::seconds(5)));
+        assert!(!cache.record("BTCUSDT", -1.0, now + Duration::seconds(5)));
+        assert_eq!(cache.quote("BTCUSDT").unwrap().price, 100.0);
+        assert_eq!(cache.quote("btc/usdt").unwrap().price, 100.0);
+        assert!(!cache.record("BTC-USDT", 95.0, now - Duration::seconds(1)));
+
+        assert!(cache.fresh("BTCUSDT", now + Duration::seconds(30)).is_ok());
+        assert!(matches!(
+            cache.fresh("BTCUSDT", now + Duration::seconds(31)),
+            Err(AppError::StalePrice {
+                age_secs: Some(31),
+                ..
+            })
+        ));
+        assert!(matches!(
+            cache.fresh("ETHUSDT", now),
+            Err(AppError::StalePrice { age_secs: None, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn stale_prices_are_refreshed_or_rejected() {
+        let client = Client::new();
+        let now = Utc::now();
+        let later = now + Duration::seconds(60);
+
+        let refreshing = cache(StalePricePolicy::Refresh);
+        refreshing.record("BTCUSDT", 100.0, now);
+        let quote = refreshing
+            .price("BTCUSDT", later, &FixedPrice(Some(110.0)), &client)
+            .await
+            .unwrap();
+        assert_eq!(quote.price, 110.0);
+        assert!(refreshing
+            .price("ETHUSDT", later, &FixedPrice(None), &client)
+            .await
+            .is_err());
+
+        let rejecting = cache(StalePricePolicy::Reject);
+        rejecting.record("BTCUSDT", 100.0, now);
+        assert!(rejecting
+            .price("BTCUSDT", later, &FixedPrice(Some(110.0)), &client)
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,551 @@
+// src/exchange/raydium_solana.rs
+//! Raydium on Solana.
+//!
+//! A Raydium bot swaps one SPL token against the quote mint in its `contract_address` (USDC,
+//! say); alerts name the traded token by its mint address. Routes through Raydium's pools
+//! and their swap instructions come from the Raydium trade API (`rest_endpoint`, by default
+//! `https://transaction-v1.raydium.io`), which returns unsigned transactions paying from the
+//! bot's wallet. They are checked to be paid by that wallet, signed locally with the bot's
+//! `private_key` and sent through its `rpc_endpoint` (Solana mainnet's public RPC by
+//! default); the key never leaves xtrade.
+//!
+//! A buy spends at most the quoted input plus `slippage` (1% by default) and a sell accepts
+//! no less than the quoted output minus it; with a positive price, swaps quoted beyond it are
+//! refused. The signatures of the sent transactions are logged as they go out.
+
+use super::{Exchange, ExecutionReport, OrderRequest};
+use crate::bot::model::Bot;
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use reqwest::Client;
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde_json::json;
+use std::error::Error;
+use std::time::Duration;
+
+pub const DEFAULT_ENDPOINT: &str = "https://transaction-v1.raydium.io";
+pub const DEFAULT_RPC_ENDPOINT: &str = "https://api.mainnet-beta.solana.com";
+
+/// Mint of wrapped SOL; swaps from or to it wrap and unwrap native SOL.
+const NATIVE_MINT: &str = "So11111111111111111111111111111111111111112";
+
+/// Price tolerance of swaps unless the caller asks for another one.
+const DEFAULT_SLIPPAGE: f64 = 0.01;
+
+/// Priority fee offered per compute unit.
+const COMPUTE_UNIT_PRICE_MICRO_LAMPORTS: &str = "100000";
+
+/// How often, and how many times, to ask whether the transactions were confirmed.
+const CONFIRMATION_POLL_INTERVAL: Duration = Duration::from_secs(2);
+const CONFIRMATION_POLLS: u32 = 30;
+
+type SwapResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+
+#[derive(Default)]
+pub struct RaydiumExchange {}
+
+impl RaydiumExchange {
+    pub fn new() -> Self {
+        RaydiumExchange {}
+    }
+}
+
+/// Key pair of a Solana private key: a base58 64-byte key pair as wallets export it, the
+/// JSON byte array of a `solana-keygen` file, or a bare 32-byte seed.
+fn keypair(private_key: &str) -> SwapResult<Ed25519KeyPair> {
+    let private_key = private_key.trim();
+    let bytes = if private_key.starts_with('[') {
+        serde_json::from_str::<Vec<u8>>(private_key)?
+    } else {
+        bs58::decode(private_key).into_vec()?
+    };
+    let keypair = match bytes.len() {
+        64 => Ed25519KeyPair::from_seed_and_public_key(&bytes[..32], &bytes[32..]),
+        32 => Ed25519KeyPair::from_seed_unchecked(&bytes),
+        _ => return Err("private_key must be a 64-byte Solana key pair".into()),
+    };
+    keypair.map_err(|_| "private_key is not a valid Solana key pair".into())
+}
+
+/// Solana's compact-u16 length prefix: the value and how many bytes it took.
+fn compact_u16(bytes: &[u8]) -> SwapResult<(usize, usize)> {
+    let mut value = 0;
+    for (i, byte) in bytes.iter().take(3).enumerate() {
+        value |= ((byte & 0x7f) as usize) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok((value, i + 1));
+        }
+    }
+    Err("malformed compact-u16".into())
+}
+
+/// Sign a serialized transaction (legacy or versioned) as its fee payer. Returns the signed
+/// transaction and its signature, which is also its ID.
+fn sign_transaction(transaction: &[u8], keypair: &Ed25519KeyPair) -> SwapResult<(Vec<u8>, String)> {
+    let (signatures, prefix) = compact_u16(transaction)?;
+    let message_start = prefix + 64 * signatures;
+    let message = transaction
+        .get(message_start..)
+        .ok_or("transaction is truncated")?;
+    // Versioned messages start with 0x80 | version before the header
+    let header = if message.first().is_some_and(|byte| byte & 0x80 != 0) {
+        1
+    } else {
+        0
+    };
+    let keys = message
+        .get(header + 3..)
+        .ok_or("transaction is truncated")?;
+    let (_, keys_prefix) = compact_u16(keys)?;
+    let fee_payer = keys
+        .get(keys_prefix..keys_prefix + 32)
+        .ok_or("transaction is truncated")?;
+    if signatures == 0 || fee_payer != keypair.public_key().as_ref() {
+        return Err("Raydium built a transaction that is not paid by the bot's wallet".into());
+    }
+
+    let signature = keypair.sign(message);
+    let mut signed = transaction.to_vec();
+    signed[prefix..prefix + 64].copy_from_slice(signature.as_ref());
+    Ok((signed, bs58::encode(signature.as_ref()).into_string()))
+}
+
+/// A token amount as the APIs return it, either a string or a number.
+fn amount(value: &serde_json::Value) -> SwapResult<u128> {
+    match value {
+        serde_json::Value::String(amount) => Ok(amount.parse()?),
+        serde_json::Value::Number(amount) => amount
+            .as_u64()
+            .map(u128::from)
+            .ok_or_else(|| format!("{} is not a token amount", amount).into()),
+        _ => Err(format!("{} is not a token amount", value).into()),
+    }
+}
+
+/// `amount` of a token with `decimals` in its smallest unit.
+fn to_units(amount: f64, decimals: u32) -> SwapResult<u128> {
+    let units = (amount * 10f64.powi(decimals as i32)).round();
+    if !units.is_finite() || units < 1.0 || units >= u64::MAX as f64 {
+        return Err(format!("{} is not a tradable amount", amount).into());
+    }
+    Ok(units as u128)
+}
+
+async fn rpc(
+    endpoint: &str,
+    method: &str,
+    params: serde_json::Value,
+    client: &Client,
+) -> SwapResult<serde_json::Value> {
+    let response = client
+        .post(endpoint)
+        .json(&json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params}))
+        .send()
+        .await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("Solana RPC responded with HTTP {} to {}", status, method).into());
+    }
+    let mut body: serde_json::Value = response.json().await?;
+    if !body["error"].is_null() {
+        return Err(format!(
+            "{} failed: {}",
+            method,
+            body["error"]["message"].as_str().unwrap_or("unknown error")
+        )
+        .into());
+    }
+    Ok(body["result"].take())
+}
+
+/// Body of a successful trade API response.
+async fn trade_api(request: reqwest::RequestBuilder) -> SwapResult<serde_json::Value> {
+    let response = request.send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("Raydium responded with HTTP {}", status).into());
+    }
+    let body: serde_json::Value = response.json().await?;
+    if body["success"] != true {
+        return Err(format!(
+            "Raydium refused the swap: {}",
+            body["msg"].as_str().unwrap_or("no reason given")
+        )
+        .into());
+    }
+    Ok(body)
+}
+
+/// Quote and build the swap with the trade API, then sign, send and confirm its
+/// transactions. Returns their signatures.
+async fn swap(
+    is_buy: bool,
+    mint: &str,
+    quantity: f64,
+    price: f64,
+    slippage: f64,
+    bot: &Bot,
+    client: &Client,
+) -> SwapResult<Vec<String>> {
+    let keypair = keypair(
+        bot.private_key
+            .as_deref()
+            .ok_or("Raydium bots need a private_key")?,
+    )?;
+    let quote_mint = bot
+        .contract_address
+        .as_deref()
+        .ok_or("Raydium bots need the quote mint in contract_address")?;
+    let endpoint = bot
+        .rest_endpoint
+        .as_deref()
+        .unwrap_or(DEFAULT_ENDPOINT)
+        .trim_end_matches('/');
+    let rpc_endpoint = bot.rpc_endpoint.as_deref().unwrap_or(DEFAULT_RPC_ENDPOINT);
+    let wallet = bs58::encode(keypair.public_key().as_ref()).into_string();
+    let slippage = if slippage > 0.0 {
+        slippage
+    } else {
+        DEFAULT_SLIPPAGE
+    };
+
+    let decimals = |mint: String| async move {
+        let supply = rpc(rpc_endpoint, "getTokenSupply", json!([mint]), client).await?;
+        supply["value"]["decimals"]
+            .as_u64()
+            .map(|decimals| decimals as u32)
+            .ok_or_else(|| Box::<dyn Error + Send + Sync>::from(format!("{} is not a mint", mint)))
+    };
+    let amount_units = to_units(quantity, decimals(mint.to_string()).await?)?;
+    let limit = if price > 0.0 {
+        Some(to_units(
+            price * quantity,
+            decimals(quote_mint.to_string()).await?,
+        )?)
+    } else {
+        None
+    };
+
+    // A buy fixes the tokens received, a sell the tokens given away
+    let (input_mint, output_mint, swap_type) = if is_buy {
+        (quote_mint, mint, "swap-base-out")
+    } else {
+        (mint, quote_mint, "swap-base-in")
+    };
+    let slippage_bps = ((slippage * 10_000.0).round() as u64).to_string();
+    let compute = trade_api(
+        client
+            .get(format!("{}/compute/{}", endpoint, swap_type))
+            .query(&[
+                ("inputMint", input_mint),
+                ("outputMint", output_mint),
+                ("amount", amount_units.to_string().as_str()),
+                ("slippageBps", slippage_bps.as_str()),
+                ("txVersion", "V0"),
+            ]),
+    )
+    .await?;
+    let response = &compute["data"];
+    let quoted = if is_buy {
+        amount(&response["inputAmount"])?
+    } else {
+        amount(&response["outputAmount"])?
+    };
+    if let Some(limit) = limit {
+        if (is_buy && quoted > limit) || (!is_buy && quoted < limit) {
+            return Err(format!(
+                "Raydium quotes {} units of the quote mint, beyond the limit of {}",
+                quoted, limit
+            )
+            .into());
+        }
+    }
+
+    let transactions = trade_api(
+        client
+            .post(format!("{}/transaction/{}", endpoint, swap_type))
+            .json(&json!({
+                "computeUnitPriceMicroLamports": COMPUTE_UNIT_PRICE_MICRO_LAMPORTS,
+                "swapResponse": compute,
+                "txVersion": "V0",
+                "wallet": wallet,
+                "wrapSol": input_mint == NATIVE_MINT,
+                "unwrapSol": output_mint == NATIVE_MINT,
+            })),
+    )
+    .await?;
+    let transactions = transactions["data"]
+        .as_array()
+        .filter(|transactions| !transactions.is_empty())
+        .ok_or("Raydium returned no transactions")?;
+
+    let mut signatures = Vec::new();
+    for transaction in transactions {
+        let unsigned = BASE64.decode(
+            transaction["transaction"]
+                .as_str()
+                .ok_or("Raydium returned a transaction without bytes")?,
+        )?;
+        let (signed, signature) = sign_transaction(&unsigned, &keypair)?;
+        rpc(
+            rpc_endpoint,
+            "sendTransaction",
+            json!([BASE64.encode(signed), {"encoding": "base64", "preflightCommitment": "confirmed"}]),
+            client,
+        )
+        .await?;
+        log::info!("Sent Raydium transaction {}", signature);
+        signatures.push(signature);
+    }
+
+    for poll in 0..CONFIRMATION_POLLS {
+        if poll > 0 {
+            tokio::time::sleep(CONFIRMATION_POLL_INTERVAL).await;
+        }
+        let statuses = rpc(
+            rpc_endpoint,
+            "getSignatureStatuses",
+            json!([signatures]),
+            client,
+        )
+        .await?;
+        let statuses = statuses["value"].as_array().cloned().unwrap_or_default();
+        let mut confirmed = 0;
+        for (signature, status) in signatures.iter().zip(&statuses) {
+            if !status["err"].is_null() {
+                return Err(format!("transaction {} failed: {}", signature, status["err"]).into());
+            }
+            if matches!(
+                status["confirmationStatus"].as_str(),
+                Some("confirmed" | "finalized")
+            ) {
+                confirmed += 1;
+            }
+        }
+        if confirmed == signatures.len() {
+            return Ok(signatures);
+        }
+    }
+    Err(format!(
+        "transactions {} were not confirmed in time",
+        signatures.join(", ")
+    )
+    .into())
+}
+
+#[async_trait]
+impl Exchange for RaydiumExchange {
+    async fn execute_trade(
+        &self,
+        order: &OrderRequest,
+        symbol_or_contract: &str,
+        quantity: Decimal,
+        slippage: f64,
+        bot: &Bot,
+        client: &Client,
+    ) -> Result<ExecutionReport, Box<dyn Error>> {
+        let price = order.price("Raydium")?;
+        let is_buy = order.side().is_buy();
+        let submitted_at = chrono::Utc::now();
+        log::info!(
+            "Executing Raydium swap: {} {} {} @ {}",
+            order.side(),
+            quantity,
+            symbol_or_contract,
+            if price > Decimal::ZERO {
+                price.to_string()
+            } else {
+                "market".to_string()
+            }
+        );
+        let signatures = swap(
+            is_buy,
+            symbol_or_contract,
+            quantity.to_f64().ok_or("quantity is out of range")?,
+            price.to_f64().ok_or("price is out of range")?,
+            slippage,
+            bot,
+            client,
+        )
+        .await
+        .map_err(|e| e as Box<dyn Error>)?;
+        log::info!("Raydium swap confirmed: {}", signatures.join(", "));
+        // The traded token's side of the swap is exact; the quote token's is bounded
+        Ok(ExecutionReport {
+            filled_quantity: Some(quantity),
+            tx_hashes: signatures,
+            ..ExecutionReport::acknowledged(submitted_at)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::{Side, TimeInForce};
+    use mockito::Matcher;
+    use ring::signature::{UnparsedPublicKey, ED25519};
+    use rust_decimal::dec;
+
+    const SEED: [u8; 32] = [7; 32];
+    const MINT: &str = "4k3Dyjzvzp8eMZWUXbBCjEvwSkkk59S5iCNLY3QrkX6R";
+    const USDC: &str = "EPjFWdd5AufqSSqeM2qJb9kEdwQmYBuRAWGyuzMUvS9M";
+
+    /// An unsigned transfer-less transaction paid by `payer`.
+    fn unsigned(payer: &[u8], versioned: bool) -> Vec<u8> {
+        let mut message = if versioned { vec![0x80] } else { Vec::new() };
+        message.extend_from_slice(&[1, 0, 1, 2]); // header, two account keys
+        message.extend_from_slice(payer);
+        message.extend_from_slice(&[9; 32]);
+        message.extend_from_slice(&[3; 32]); // recent blockhash
+        message.push(0); // no instructions
+        [vec![1], vec![0; 64], message].concat()
+    }
+
+    #[test]
+    fn keys_and_transactions_are_signed() {
+        let seed_only = keypair(&bs58::encode(SEED).into_string()).unwrap();
+        let public = seed_only.public_key().as_ref().to_vec();
+        let pair = [&SEED[..], &public].concat();
+        assert_eq!(
+            keypair(&bs58::encode(&pair).into_string())
+                .unwrap()
+                .public_key()
+                .as_ref(),
+            public
+        );
+        assert_eq!(
+            keypair(&serde_json::to_string(&pair).unwrap())
+                .unwrap()
+                .public_key()
+                .as_ref(),
+            public
+        );
+        let mut wrong = pair.clone();
+        wrong[40] ^= 1;
+        assert!(keypair(&bs58::encode(&wrong).into_string()).is_err());
+
+        assert_eq!(compact_u16(&[0x05]).unwrap(), (5, 1));
+        assert_eq!(compact_u16(&[0x80, 0x01]).unwrap(), (128, 2));
+
+        for versioned in [false, true] {
+            let transaction = unsigned(&public, versioned);
+            let (signed, id) = sign_transaction(&transaction, &seed_only).unwrap();
+            let signature = &signed[1..65];
+            assert_eq!(bs58::encode(signature).into_string(), id);
+            UnparsedPublicKey::new(&ED25519, &public)
+                .verify(&signed[65..], signature)
+                .unwrap();
+        }
+        assert!(sign_transaction(&unsigned(&[1; 32], false), &seed_only).is_err());
+    }
+
+    #[tokio::test]
+    async fn sells_are_built_signed_and_confirmed() {
+        let mut server = mockito::Server::new_async().await;
+        let public = keypair(&bs58::encode(SEED).into_string())
+            .unwrap()
+            .public_key()
+            .as_ref()
+            .to_vec();
+        let bot = Bot {
+            exchange: "raydium".to_string(),
+            private_key: Some(bs58::encode(SEED).into_string()),
+            contract_address: Some(USDC.to_string()),
+            rest_endpoint: Some(server.url()),
+            rpc_endpoint: Some(format!("{}/rpc", server.url())),
+            ..Default::default()
+        };
+        let client = Client::new();
+        let mut sent = None;
+        for (method, result) in [
+            ("getTokenSupply", json!({"value": {"decimals": 6}})),
+            ("sendTransaction", json!("ignored")),
+            (
+                "getSignatureStatuses",
+                json!({"value": [{"err": null, "confirmationStatus": "confirmed"}]}),
+            ),
+        ] {
+            let mock = server
+                .mock("POST", "/rpc")
+                .match_body(Matcher::PartialJson(json!({"method": method})))
+                .with_body(json!({"jsonrpc": "2.0", "id": 1, "result": result}).to_string())
+                .create_async()
+                .await;
+            if method == "sendTransaction" {
+                sent = Some(mock);
+            }
+        }
+        let sent = sent.unwrap();
+
+        let compute = json!({
+            "id": "1",
+            "success": true,
+            "version": "V1",
+            "data": {"inputAmount": "2500000", "outputAmount": "400000000"},
+        });
+        server
+            .mock("GET", "/compute/swap-base-in")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("inputMint".into(), MINT.into()),
+                Matcher::UrlEncoded("outputMint".into(), USDC.into()),
+                Matcher::UrlEncoded("amount".into(), "2500000".into()),
+                Matcher::UrlEncoded("slippageBps".into(), "100".into()),
+            ]))
+            .with_body(compute.to_string())
+            .create_async()
+            .await;
+        let built = server
+            .mock("POST", "/transaction/swap-base-in")
+            .match_body(Matcher::PartialJson(json!({
+                "wallet": bs58::encode(&public).into_string(),
+                "swapResponse": compute,
+                "wrapSol": false,
+            })))
+            .with_body(
+                json!({
+                    "success": true,
+                    "data": [{"transaction": BASE64.encode(unsigned(&public, true))}],
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        RaydiumExchange::new()
+            .execute_trade(
+                &OrderRequest::Market { side: Side::Sell },
+                MINT,
+                dec!(2.5),
+                0.0,
+                &bot,
+                &client,
+            )
+            .await
+            .unwrap();
+        built.assert_async().await;
+        sent.assert_async().await;
+
+        // 2.5 at 200 would need 500 USDC, more than the 400 quoted
+        let err = RaydiumExchange::new()
+            .execute_trade(
+                &OrderRequest::Limit {
+                    side: Side::Sell,
+                    price: dec!(200),
+                    time_in_force: TimeInForce::Gtc,
+                },
+                MINT,
+                dec!(2.5),
+                0.0,
+                &bot,
+                &client,
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("beyond the limit"));
+        sent.assert_async().await;
+    }
+}
@@ -0,0 +1,373 @@
+// src/exchange/streams.rs
+//! # Price Streams
+//!
+//! Keeps the [price cache](super::prices) current from exchange WebSocket ticker streams, so
+//! risk checks and exits read a price that is a moment old instead of asking the REST API
+//! once the cached one went stale. Every configured stream is a connection of its own,
+//! reconnected `prices.stream_reconnect_secs` after it fails or closes. A connection without
+//! a price update for `prices.stream_max_gap_secs` counts as stalled and is replaced. Gaps
+//! between two updates of a symbol are logged; meanwhile its cached price goes stale and
+//! falls back to `prices.on_stale` as usual.
+use super::prices::PriceCache;
+use super::Symbol;
+use crate::app_config::{PriceCacheConfig, PriceStreamConfig};
+use chrono::{DateTime, Utc};
+use futures_util::{SinkExt, StreamExt};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+use tokio_tungstenite::tungstenite::Message;
+
+#[cfg(feature = "exchange-binance")]
+const BINANCE_ENDPOINT: &str = "wss://stream.binance.com:9443";
+#[cfg(feature = "exchange-bybit")]
+const BYBIT_ENDPOINT: &str = "wss://stream.bybit.com";
+
+/// How often Bybit wants to hear from a client to keep its connection open.
+const HEARTBEAT: Duration = Duration::from_secs(20);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Venue {
+    #[cfg(feature = "exchange-binance")]
+    Binance,
+    #[cfg(feature = "exchange-bybit")]
+    Bybit,
+}
+
+impl Venue {
+    /// Symbol, last price and time of a ticker update; `None` for any other message.
+    fn parse(self, text: &str) -> Option<(String, f64, DateTime<Utc>)> {
+        let message: serde_json::Value = serde_json::from_str(text).ok()?;
+        let data = &message["data"];
+        let (symbol, price, time) = match self {
+            #[cfg(feature = "exchange-binance")]
+            Venue::Binance => (&data["s"], &data["c"], &data["E"]),
+            // Linear deltas leave out the last price when it did not change
+            #[cfg(feature = "exchange-bybit")]
+            Venue::Bybit => (&data["symbol"], &data["lastPrice"], &message["ts"]),
+        };
+        Some((
+            symbol.as_str()?.to_string(),
+            price.as_str()?.parse().ok()?,
+            DateTime::from_timestamp_millis(time.as_i64()?)?,
+        ))
+    }
+
+    /// Message to send every [`HEARTBEAT`], for venues that close quiet connections.
+    fn ping(self) -> Option<String> {
+        match self {
+            #[cfg(feature = "exchange-binance")]
+            Venue::Binance => None,
+            #[cfg(feature = "exchange-bybit")]
+            Venue::Bybit => Some(serde_json::json!({ "op": "ping" }).to_string()),
+        }
+    }
+}
+
+/// One connection and the symbols it carries.
+#[derive(Clone, Debug, PartialEq)]
+struct Subscription {
+    venue: Venue,
+    url: String,
+    /// Names the cache knows each exchange symbol by
+    symbols: BTreeMap<String, String>,
+}
+
+impl Subscription {
+    /// Message subscribing to the symbols once connected, for venues that need one.
+    fn request(&self) -> Option<String> {
+        match self.venue {
+            #[cfg(feature = "exchange-binance")]
+            Venue::Binance => None,
+            #[cfg(feature = "exchange-bybit")]
+            Venue::Bybit => {
+                let topics: Vec<String> = self
+                    .symbols
+                    .keys()
+                    .map(|symbol| format!("tickers.{}", symbol))
+                    .collect();
+                Some(serde_json::json!({ "op": "subscribe", "args": topics }).to_string())
+            }
+        }
+    }
+}
+
+/// Connections serving `stream`.
+fn subscriptions(stream: &PriceStreamConfig) -> Result<Vec<Subscription>, String> {
+    if stream.symbols.is_empty() {
+        return Err(format!(
+            "the {} price stream has no symbols",
+            stream.exchange
+        ));
+    }
+    let endpoint = |default: &str| {
+        stream
+            .endpoint
+            .as_deref()
+            .unwrap_or(default)
+            .trim_end_matches('/')
+            .to_string()
+    };
+    match stream.exchange.to_lowercase().as_str() {
+        #[cfg(feature = "exchange-binance")]
+        "binance" => {
+            let symbols = stream
+                .symbols
+                .iter()
+                .map(|symbol| Ok((Symbol::parse(symbol)?.pair(""), symbol.clone())))
+                .collect::<Result<BTreeMap<_, _>, String>>()?;
+            let streams: Vec<String> = symbols
+                .keys()
+                .map(|symbol| format!("{}@ticker", symbol.to_lowercase()))
+                .collect();
+            Ok(vec![Subscription {
+                venue: Venue::Binance,
+                url: format!(
+                    "{}/stream?streams={}",
+                    endpoint(BINANCE_ENDPOINT),
+                    streams.join("/")
+                ),
+                symbols,
+            }])
+        }
+        #[cfg(feature = "exchange-bybit")]
+        "bybit" => {
+            // Spot and perpetuals are published on separate connections
+            let mut categories: BTreeMap<&str, BTreeMap<String, String>> = BTreeMap::new();
+            for symbol in &stream.symbols {
+                let (category, exchange_symbol) = super::bybit::category(&Symbol::parse(symbol)?);
+                categories
+                    .entry(category)
+                    .or_default()
+                    .insert(exchange_symbol, symbol.clone());
+            }
+            let endpoint = endpoint(BYBIT_ENDPOINT);
+            Ok(categories
+                .into_iter()
+                .map(|(category, symbols)| Subscription {
+                    venue: Venue::Bybit,
+                    url: format!("{}/v5/public/{}", endpoint, category),
+                    symbols,
+                })
+                .collect())
+        }
+        other => Err(format!("no price stream for exchange '{}'", other)),
+    }
+}
+
+/// Start the streams of `config` feeding `prices`. Returns the number of connections, or
+/// an error without starting any if a stream cannot be served.
+pub fn spawn(config: &PriceCacheConfig, prices: Arc<PriceCache>) -> Result<usize, String> {
+    let mut all = Vec::new();
+    for stream in &config.streams {
+        all.extend(subscriptions(stream)?);
+    }
+    let reconnect = Duration::from_secs(config.stream_reconnect_secs);
+    let max_gap = Duration::from_secs(config.stream_max_gap_secs.max(1));
+    let count = all.len();
+    for subscription in all {
+        tokio::spawn(run(subscription, prices.clone(), reconnect, max_gap));
+    }
+    Ok(count)
+}
+
+/// Keep `subscription` connected for good.
+async fn run(
+    subscription: Subscription,
+    prices: Arc<PriceCache>,
+    reconnect: Duration,
+    max_gap: Duration,
+) {
+    let mut last_updates = HashMap::new();
+    loop {
+        match session(&subscription, &prices, max_gap, &mut last_updates).await {
+            Ok(()) => log::warn!("Price stream {} closed", subscription.url),
+            Err(e) => log::warn!("Price stream {} failed: {}", subscription.url, e),
+        }
+        tokio::time::sleep(reconnect).await;
+    }
+}
+
+/// Record prices from one connection of `subscription` until it closes, fails or stalls.
+///
+/// `last_updates` holds the time of the last update of each symbol across connections.
+async fn session(
+    subscription: &Subscription,
+    prices: &PriceCache,
+    max_gap: Duration,
+    last_updates: &mut HashMap<String, DateTime<Utc>>,
+) -> Result<(), String> {
+    let (mut socket, _) = tokio_tungstenite::connect_async(subscription.url.as_str())
+        .await
+        .map_err(|e| e.to_string())?;
+    if let Some(request) = subscription.request() {
+        socket
+            .send(Message::text(request))
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    log::info!("Price stream {} connected", subscription.url);
+
+    let mut heartbeat = tokio::time::interval_at(Instant::now() + HEARTBEAT, HEARTBEAT);
+    let mut deadline = Instant::now() + max_gap;
+    loop {
+        let message = tokio::select! {
+            message = socket.next() => message,
+            _ = heartbeat.tick() => {
+                if let Some(ping) = subscription.venue.ping() {
+                    socket.send(Message::text(ping)).await.map_err(|e| e.to_string())?;
+                }
+                continue;
+            }
+            _ = tokio::time::sleep_until(deadline) => {
+                return Err(format!("no price update for {}s", max_gap.as_secs()));
+            }
+        };
+        // Pings are answered by the WebSocket library itself
+        let text = match message {
+            None => return Ok(()),
+            Some(Err(e)) => return Err(e.to_string()),
+            Some(Ok(Message::Text(text))) => text,
+            Some(Ok(Message::Close(_))) => return Ok(()),
+            Some(Ok(_)) => continue,
+        };
+        let Some((symbol, price, observed_at)) = subscription.venue.parse(&text) else {
+            continue;
+        };
+        let Some(name) = subscription.symbols.get(&symbol) else {
+            continue;
+        };
+        deadline = Instant::now() + max_gap;
+        if let Some(previous) = last_updates.insert(name.clone(), observed_at) {
+            let gap = observed_at - previous;
+            if gap.to_std().is_ok_and(|gap| gap > max_gap) {
+                log::warn!(
+                    "Gap in the price stream of {}: no update for {}s",
+                    name,
+                    gap.num_seconds()
+                );
+            }
+        }
+        prices.record(name, price, observed_at);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream(exchange: &str, symbols: &[&str], endpoint: Option<String>) -> PriceStreamConfig {
+        PriceStreamConfig {
+            exchange: exchange.to_string(),
+            symbols: symbols.iter().map(|symbol| symbol.to_string()).collect(),
+            endpoint,
+        }
+    }
+
+    #[cfg(all(feature = "exchange-binance", feature = "exchange-bybit"))]
+    #[test]
+    fn ticker_updates_are_parsed() {
+        let binance = r#"{"stream":"btcusdt@ticker","data":{"e":"24hrTicker","E":1700000000000,
+            "s":"BTCUSDT","c":"101.5","o":"99"}}"#;
+        let (symbol, price, observed_at) = Venue::Binance.parse(binance).unwrap();
+        assert_eq!((symbol.as_str(), price), ("BTCUSDT", 101.5));
+        assert_eq!(observed_at.timestamp(), 1_700_000_000);
+
+        let bybit = r#"{"topic":"tickers.BTCUSDT","ts":1700000000000,"type":"snapshot",
+            "cs":1,"data":{"symbol":"BTCUSDT","lastPrice":"99.5"}}"#;
+        assert_eq!(
+            Venue::Bybit.parse(bybit).map(|(_, price, _)| price),
+            Some(99.5)
+        );
+        let delta = r#"{"topic":"tickers.BTCUSDT","ts":1700000000000,"type":"delta",
+            "data":{"symbol":"BTCUSDT","fundingRate":"0.0001"}}"#;
+        assert_eq!(Venue::Bybit.parse(delta), None);
+        assert_eq!(Venue::Bybit.parse(r#"{"op":"pong","success":true}"#), None);
+    }
+
+    #[test]
+    fn streams_map_to_connections() {
+        assert!(subscriptions(&stream("binance", &[], None)).is_err());
+        assert!(subscriptions(&stream("uniswap", &["WETHUSDC"], None)).is_err());
+
+        #[cfg(feature = "exchange-binance")]
+        {
+            let binance = subscriptions(&stream("Binance", &["ethusdt", "BTCUSDT"], None)).unwrap();
+            assert_eq!(
+                binance[0].url,
+                "wss://stream.binance.com:9443/stream?streams=btcusdt@ticker/ethusdt@ticker"
+            );
+            assert_eq!(binance[0].symbols["ETHUSDT"], "ethusdt");
+            assert_eq!(binance[0].request(), None);
+        }
+        #[cfg(feature = "exchange-bybit")]
+        {
+            let bybit = subscriptions(&stream("bybit", &["BTCUSDT.P", "BTCUSDT"], None)).unwrap();
+            let urls: Vec<&str> = bybit.iter().map(|s| s.url.as_str()).collect();
+            assert_eq!(
+                urls,
+                [
+                    "wss://stream.bybit.com/v5/public/linear",
+                    "wss://stream.bybit.com/v5/public/spot"
+                ]
+            );
+            assert_eq!(bybit[0].symbols["BTCUSDT"], "BTCUSDT.P");
+            assert_eq!(
+                bybit[0].request().unwrap(),
+                r#"{"args":["tickers.BTCUSDT"],"op":"subscribe"}"#
+            );
+        }
+    }
+
+    #[cfg(feature = "exchange-binance")]
+    #[tokio::test]
+    async fn streams_reconnect_and_feed_the_cache() {
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("ws://{}", listener.local_addr().unwrap());
+        let update = |price: &str, time: i64| {
+            format!(
+                r#"{{"stream":"btcusdt@ticker","data":{{"E":{},"s":"BTCUSDT","c":"{}"}}}}"#,
+                time, price
+            )
+        };
+        // The first connection drops after one update, the second stays open
+        let (first, second) = (
+            update("100", 1_700_000_000_000),
+            update("101", 1_700_000_060_000),
+        );
+        tokio::spawn(async move {
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut socket = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            socket.send(Message::text(first)).await.unwrap();
+            socket.close(None).await.unwrap();
+
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut socket = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            socket.send(Message::text(second)).await.unwrap();
+            while socket.next().await.is_some() {}
+        });
+
+        let prices = Arc::new(PriceCache::new(PriceCacheConfig::default()));
+        let config = PriceCacheConfig {
+            streams: vec![stream("binance", &["BTCUSDT"], Some(endpoint))],
+            stream_reconnect_secs: 0,
+            ..Default::default()
+        };
+        assert_eq!(spawn(&config, prices.clone()), Ok(1));
+
+        let quote = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                match prices.quote("BTCUSDT") {
+                    Some(quote) if quote.price == 101.0 => return quote,
+                    _ => tokio::time::sleep(Duration::from_millis(10)).await,
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(quote.observed_at.timestamp(), 1_700_000_060);
+    }
+}
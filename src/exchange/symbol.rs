@@ -0,0 +1,304 @@
+// src/exchange/symbol.rs
+//! # Symbols and Assets
+//!
+//! Alert sources name markets in different ways: `BTCUSDT`, `BTC/USDT`, `BTC-USDT` and
+//! `BTC_USDT` are the same spot market, and a `.P` or `-PERP` suffix makes it the perpetual
+//! contract. [`Symbol`] parses all of them to one value; its display form, `BTCUSDT` or
+//! `BTCUSDT.P`, is how xtrade keys prices, positions and precision rules, and each adapter
+//! renders the symbol the way its exchange expects.
+//!
+//! Contract addresses and token mints are not symbols; [`Symbol::normalize`] passes them
+//! through unchanged.
+//!
+//! Some strategies name markets in ways no parsing recovers, such as TradingView's
+//! `BINANCE:BTCUSDTPERP`. A [`SymbolMap`] translates them to the symbol the bot trades.
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Quote assets recognised at the end of a symbol without a separator, longest first.
+const QUOTE_ASSETS: [&str; 8] = ["FDUSD", "USDT", "USDC", "BUSD", "USD", "EUR", "BTC", "ETH"];
+
+/// Suffixes marking a perpetual contract.
+const PERPETUAL_SUFFIXES: [&str; 2] = [".P", "-PERP"];
+
+/// Separators between the base and quote asset.
+const SEPARATORS: [char; 3] = ['/', '-', '_'];
+
+/// Longest asset code accepted; longer strings are addresses, not symbols.
+const MAX_ASSET_LEN: usize = 15;
+
+/// An asset code such as `BTC` or `1000PEPE`, in upper case.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Asset(String);
+
+impl Asset {
+    /// `code` in upper case, if it is 1 to 15 ASCII letters and digits.
+    pub fn new(code: &str) -> Result<Self, String> {
+        let code = code.trim();
+        if code.is_empty()
+            || code.len() > MAX_ASSET_LEN
+            || !code.chars().all(|c| c.is_ascii_alphanumeric())
+        {
+            return Err(format!("'{}' is not an asset code", code));
+        }
+        Ok(Self(code.to_ascii_uppercase()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Asset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for Asset {
+    type Err = String;
+
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        Self::new(code)
+    }
+}
+
+impl TryFrom<String> for Asset {
+    type Error = String;
+
+    fn try_from(code: String) -> Result<Self, Self::Error> {
+        Self::new(&code)
+    }
+}
+
+impl From<Asset> for String {
+    fn from(asset: Asset) -> Self {
+        asset.0
+    }
+}
+
+/// A market: its base asset, the quote asset if the symbol names one, and whether it is the
+/// perpetual contract.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Symbol {
+    base: Asset,
+    quote: Option<Asset>,
+    perpetual: bool,
+}
+
+impl Symbol {
+    /// Parse `symbol` in any of the forms alert sources use, in any case.
+    pub fn parse(symbol: &str) -> Result<Self, String> {
+        let invalid = || format!("'{}' is not a symbol", symbol);
+        let upper = symbol.trim().to_ascii_uppercase();
+        let (pair, perpetual) = PERPETUAL_SUFFIXES
+            .iter()
+            .find_map(|suffix| upper.strip_suffix(suffix))
+            .map_or((upper.as_str(), false), |pair| (pair, true));
+
+        let (base, quote) = match pair.split_once(SEPARATORS) {
+            Some((base, quote)) => (base, Some(quote)),
+            None => QUOTE_ASSETS
+                .iter()
+                .find_map(|quote| {
+                    pair.strip_suffix(quote)
+                        .filter(|base| !base.is_empty())
+                        .map(|base| (base, Some(*quote)))
+                })
+                .unwrap_or((pair, None)),
+        };
+        Ok(Self {
+            base: Asset::new(base).map_err(|_| invalid())?,
+            quote: quote.map(Asset::new).transpose().map_err(|_| invalid())?,
+            perpetual,
+        })
+    }
+
+    /// The canonical form of `symbol`, or `symbol` itself if it is not one (e.g. a contract
+    /// address).
+    pub fn normalize(symbol: &str) -> String {
+        Self::parse(symbol).map_or_else(|_| symbol.to_string(), |symbol| symbol.to_string())
+    }
+
+    pub fn base(&self) -> &Asset {
+        &self.base
+    }
+
+    pub fn quote(&self) -> Option<&Asset> {
+        self.quote.as_ref()
+    }
+
+    pub fn is_perpetual(&self) -> bool {
+        self.perpetual
+    }
+
+    /// Base and quote asset separated by `separator`, without the perpetual suffix: `BTCUSDT`
+    /// for `""`, `BTC-USDT` for `"-"`. Just the base asset if there is no quote.
+    pub fn pair(&self, separator: &str) -> String {
+        match &self.quote {
+            Some(quote) => format!("{}{}{}", self.base, separator, quote),
+            None => self.base.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.pair(""))?;
+        if self.perpetual {
+            write!(f, "{}", PERPETUAL_SUFFIXES[0])?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Symbol {
+    type Err = String;
+
+    fn from_str(symbol: &str) -> Result<Self, Self::Err> {
+        Self::parse(symbol)
+    }
+}
+
+impl TryFrom<String> for Symbol {
+    type Error = String;
+
+    fn try_from(symbol: String) -> Result<Self, Self::Error> {
+        Self::parse(&symbol)
+    }
+}
+
+impl From<Symbol> for String {
+    fn from(symbol: Symbol) -> Self {
+        symbol.to_string()
+    }
+}
+
+/// Exchange symbols to trade for the symbols strategies send. Both sides are compared in
+/// their [normal form](Symbol::normalize), ignoring case.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SymbolMap(BTreeMap<String, String>);
+
+impl SymbolMap {
+    fn key(symbol: &str) -> String {
+        Symbol::normalize(symbol.trim()).to_ascii_uppercase()
+    }
+
+    /// The exchange symbol mapped to `symbol`, normalised.
+    pub fn get(&self, symbol: &str) -> Option<String> {
+        let key = Self::key(symbol);
+        self.0
+            .iter()
+            .find(|(from, _)| Self::key(from) == key)
+            .map(|(_, to)| Symbol::normalize(to.trim()))
+    }
+
+    /// Map `symbol` to `exchange_symbol`, replacing any mapping of it.
+    pub fn insert(&mut self, symbol: &str, exchange_symbol: &str) {
+        self.remove(symbol);
+        self.0.insert(
+            symbol.trim().to_string(),
+            exchange_symbol.trim().to_string(),
+        );
+    }
+
+    /// Drop the mapping of `symbol`; false if there was none.
+    pub fn remove(&mut self, symbol: &str) -> bool {
+        let key = Self::key(symbol);
+        let before = self.0.len();
+        self.0.retain(|from, _| Self::key(from) != key);
+        self.0.len() < before
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Strategy and exchange symbol of each mapping, sorted by strategy symbol.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(from, to)| (from.as_str(), to.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn symbol_forms_are_normalised() {
+        for form in [
+            "BTCUSDT",
+            "btcusdt",
+            "BTC/USDT",
+            "BTC-USDT",
+            "btc_usdt",
+            " BTCUSDT ",
+        ] {
+            let symbol = Symbol::parse(form).unwrap();
+            assert_eq!(symbol.to_string(), "BTCUSDT", "{}", form);
+            assert_eq!(symbol.base().as_str(), "BTC");
+            assert_eq!(symbol.quote().map(Asset::as_str), Some("USDT"));
+            assert!(!symbol.is_perpetual());
+        }
+        for form in ["BTCUSDT.P", "BTC-USDT-PERP", "btc/usdt.p"] {
+            let symbol = Symbol::parse(form).unwrap();
+            assert_eq!(symbol.to_string(), "BTCUSDT.P", "{}", form);
+            assert_eq!(symbol.pair("-"), "BTC-USDT");
+            assert!(symbol.is_perpetual());
+        }
+
+        let coin = Symbol::parse("BTC-PERP").unwrap();
+        assert_eq!((coin.base().as_str(), coin.quote()), ("BTC", None));
+        assert_eq!(Symbol::parse("ETHBTC").unwrap().pair("/"), "ETH/BTC");
+        assert_eq!(Symbol::parse("USDCUSDT").unwrap().pair("/"), "USDC/USDT");
+        assert_eq!(Symbol::parse("USDC").unwrap().pair("/"), "USDC");
+        assert_eq!(
+            Symbol::parse("1000PEPEUSDT").unwrap().base().as_str(),
+            "1000PEPE"
+        );
+    }
+
+    #[test]
+    fn addresses_are_not_symbols() {
+        let weth = "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2";
+        assert!(Symbol::parse(weth).is_err());
+        assert_eq!(Symbol::normalize(weth), weth);
+        assert!(Symbol::parse("BTC/").is_err());
+        assert!(Symbol::parse("").is_err());
+        assert_eq!(Symbol::normalize("btc/usdt"), "BTCUSDT");
+    }
+
+    #[test]
+    fn symbols_serialize_as_strings() {
+        let symbol: Symbol = serde_json::from_str(r#""eth-usdc.p""#).unwrap();
+        assert_eq!(serde_json::to_string(&symbol).unwrap(), r#""ETHUSDC.P""#);
+        assert!(serde_json::from_str::<Asset>(r#""not an asset""#).is_err());
+    }
+
+    #[test]
+    fn mapped_symbols_ignore_form_and_case() {
+        let mut map = SymbolMap::default();
+        map.insert("BINANCE:BTCUSDTPERP", "btc-usdt");
+        map.insert("ETH/USDT", "ETHUSDT.P");
+
+        assert_eq!(map.get("binance:btcusdtperp").as_deref(), Some("BTCUSDT"));
+        assert_eq!(map.get("ethusdt").as_deref(), Some("ETHUSDT.P"));
+        assert_eq!(map.get("ETHUSDT.P"), None);
+
+        map.insert("ethusdt", "ETHUSDC");
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get("ETH-USDT").as_deref(), Some("ETHUSDC"));
+        assert!(map.remove("Binance:BTCUSDTPERP"));
+        assert!(!map.remove("BINANCE:BTCUSDTPERP"));
+        assert_eq!(map.iter().collect::<Vec<_>>(), [("ethusdt", "ETHUSDC")]);
+    }
+}
@@ -0,0 +1,606 @@
+// src/exchange/uniswap_base.rs
+//! Uniswap V3 on Base.
+//!
+//! A Uniswap bot swaps one token against the quote token in its `contract_address` (USDC,
+//! say) through the 0.3% pool; alerts name the traded token by its contract address. Swaps
+//! are EIP-1559 transactions signed locally with the bot's `private_key` and broadcast
+//! through its `rpc_endpoint` (Base mainnet's public RPC by default).
+//!
+//! Every swap is quoted first. A buy spends at most the quote plus `slippage` (1% by default)
+//! and a sell accepts no less than the quote minus it; a positive price replaces the quote
+//! as the limit. The router is approved to spend the token given away the first time it is
+//! needed.
+
+use super::evm::{
+    address, address_word, call_data, decode_uint, from_hex, keccak, parse_address, parse_quantity,
+    signing_key, to_hex, uint_word, Address, Rlp,
+};
+use super::{Exchange, ExecutionReport, OrderRequest};
+use crate::bot::model::Bot;
+use async_trait::async_trait;
+use k256::ecdsa::SigningKey;
+use reqwest::Client;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde_json::json;
+use std::error::Error;
+use std::time::Duration;
+
+pub const DEFAULT_RPC_ENDPOINT: &str = "https://mainnet.base.org";
+
+/// SwapRouter02 on Base.
+const SWAP_ROUTER: &str = "0x2626664c2603336E57B271c5C0b26F421741e481";
+
+/// QuoterV2 on Base.
+const QUOTER: &str = "0x3d4e44Eb1374240CE5F1B871ab261CD16335B76a";
+
+/// Fee tier (in hundredths of a basis point) of the pools swapped through.
+const FEE_TIER: u128 = 3000;
+
+/// Price tolerance of swaps unless the caller asks for another one.
+const DEFAULT_SLIPPAGE: f64 = 0.01;
+
+/// How often, and how many times, to ask whether a transaction was mined.
+const RECEIPT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+const RECEIPT_POLLS: u32 = 60;
+
+type RpcResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+
+#[derive(Default)]
+pub struct UniswapExchange {}
+
+impl UniswapExchange {
+    pub fn new() -> Self {
+        UniswapExchange {}
+    }
+}
+
+/// `amount` of a token with `decimals` in its smallest unit.
+fn to_units(amount: f64, decimals: u32) -> RpcResult<u128> {
+    let units = (amount * 10f64.powi(decimals as i32)).round();
+    if !units.is_finite() || units < 1.0 || units >= u128::MAX as f64 {
+        return Err(format!("{} is not a tradable amount", amount).into());
+    }
+    Ok(units as u128)
+}
+
+/// `quote` moved `slippage` against the trader: up for what a buy spends, down for what a
+/// sell receives.
+fn with_slippage(quote: u128, slippage: f64, is_buy: bool) -> u128 {
+    let bps = (slippage * 10_000.0).round().clamp(0.0, 10_000.0) as u128;
+    if is_buy {
+        quote.saturating_mul(10_000 + bps).div_ceil(10_000)
+    } else {
+        quote.saturating_mul(10_000 - bps) / 10_000
+    }
+}
+
+/// An unsigned EIP-1559 transaction sending no ether.
+#[derive(Clone, Debug, PartialEq)]
+struct Transaction {
+    chain_id: u128,
+    nonce: u128,
+    max_priority_fee_per_gas: u128,
+    max_fee_per_gas: u128,
+    gas: u128,
+    to: Address,
+    data: Vec<u8>,
+}
+
+impl Transaction {
+    fn fields(&self) -> Vec<Rlp> {
+        vec![
+            Rlp::uint(self.chain_id),
+            Rlp::uint(self.nonce),
+            Rlp::uint(self.max_priority_fee_per_gas),
+            Rlp::uint(self.max_fee_per_gas),
+            Rlp::uint(self.gas),
+            Rlp::Bytes(self.to.to_vec()),
+            Rlp::uint(0), // value
+            Rlp::Bytes(self.data.clone()),
+            Rlp::List(Vec::new()), // access list
+        ]
+    }
+
+    fn signing_hash(&self) -> [u8; 32] {
+        keccak(&[&[0x02][..], &Rlp::List(self.fields()).encode()].concat())
+    }
+
+    /// The signed transaction as `eth_sendRawTransaction` takes it.
+    fn sign(&self, key: &SigningKey) -> RpcResult<Vec<u8>> {
+        let (signature, recovery_id) = key.sign_prehash_recoverable(&self.signing_hash())?;
+        let bytes = signature.to_bytes();
+        let mut fields = self.fields();
+        fields.push(Rlp::uint(recovery_id.is_y_odd() as u128));
+        fields.push(Rlp::scalar(&bytes[..32]));
+        fields.push(Rlp::scalar(&bytes[32..]));
+        Ok([&[0x02][..], &Rlp::List(fields).encode()].concat())
+    }
+}
+
+/// JSON-RPC client of an Ethereum node.
+struct Rpc<'a> {
+    endpoint: &'a str,
+    client: &'a Client,
+}
+
+impl Rpc<'_> {
+    async fn call(&self, method: &str, params: serde_json::Value) -> RpcResult<serde_json::Value> {
+        let response = self
+            .client
+            .post(self.endpoint)
+            .json(&json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params}))
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(format!("RPC node responded with HTTP {} to {}", status, method).into());
+        }
+        let mut body: serde_json::Value = response.json().await?;
+        if !body["error"].is_null() {
+            return Err(format!(
+                "{} failed: {}",
+                method,
+                body["error"]["message"].as_str().unwrap_or("unknown error")
+            )
+            .into());
+        }
+        Ok(body["result"].take())
+    }
+
+    async fn quantity(&self, method: &str, params: serde_json::Value) -> RpcResult<u128> {
+        let result = self.call(method, params).await?;
+        parse_quantity(
+            result
+                .as_str()
+                .ok_or_else(|| format!("{} returned {}", method, result))?,
+        )
+    }
+
+    /// Return data of calling `to` with `data` at the latest block.
+    async fn eth_call(&self, to: &Address, data: &[u8]) -> RpcResult<Vec<u8>> {
+        let result = self
+            .call(
+                "eth_call",
+                json!([{"to": to_hex(to), "data": to_hex(data)}, "latest"]),
+            )
+            .await?;
+        from_hex(result.as_str().unwrap_or_default())
+    }
+
+    async fn decimals(&self, token: &Address) -> RpcResult<u32> {
+        let data = self.eth_call(token, &call_data("decimals()", &[])).await?;
+        Ok(decode_uint(&data, 0)? as u32)
+    }
+
+    /// How much of `token` `spender` may still move for `owner`.
+    async fn allowance(
+        &self,
+        token: &Address,
+        owner: &Address,
+        spender: &Address,
+    ) -> RpcResult<u128> {
+        let data = self
+            .eth_call(
+                token,
+                &call_data(
+                    "allowance(address,address)",
+                    &[address_word(owner), address_word(spender)],
+                ),
+            )
+            .await?;
+        match decode_uint(&data, 0) {
+            Ok(allowance) => Ok(allowance),
+            // An "unlimited" approval does not fit in 128 bits
+            Err(_) if data.len() >= 32 => Ok(u128::MAX),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Sign and broadcast a call of `to` with `data`, then wait until it is mined. Returns
+    /// the transaction hash.
+    async fn transact(&self, key: &SigningKey, to: &Address, data: Vec<u8>) -> RpcResult<String> {
+        let from = to_hex(&address(key));
+        let chain_id = self.quantity("eth_chainId", json!([])).await?;
+        let nonce = self
+            .quantity("eth_getTransactionCount", json!([from, "pending"]))
+            .await?;
+        let estimate = self
+            .quantity(
+                "eth_estimateGas",
+                json!([{"from": from, "to": to_hex(to), "data": to_hex(&data)}]),
+            )
+            .await?;
+        let priority_fee = self.quantity("eth_maxPriorityFeePerGas", json!([])).await?;
+        let block = self
+            .call("eth_getBlockByNumber", json!(["latest", false]))
+            .await?;
+        let base_fee = parse_quantity(
+            block["baseFeePerGas"]
+                .as_str()
+                .ok_or("latest block has no base fee")?,
+        )?;
+
+        let transaction = Transaction {
+            chain_id,
+            nonce,
+            max_priority_fee_per_gas: priority_fee,
+            // Stays valid through a few blocks of rising base fees
+            max_fee_per_gas: 2 * base_fee + priority_fee,
+            gas: estimate * 6 / 5,
+            to: *to,
+            data,
+        };
+        let raw = transaction.sign(key)?;
+        let hash = self
+            .call("eth_sendRawTransaction", json!([to_hex(&raw)]))
+            .await?;
+        let hash = hash
+            .as_str()
+            .ok_or("eth_sendRawTransaction returned no hash")?
+            .to_string();
+
+        for poll in 0..RECEIPT_POLLS {
+            if poll > 0 {
+                tokio::time::sleep(RECEIPT_POLL_INTERVAL).await;
+            }
+            let receipt = self
+                .call("eth_getTransactionReceipt", json!([hash]))
+                .await?;
+            if receipt.is_null() {
+                continue;
+            }
+            if receipt["status"] != "0x1" {
+                return Err(format!("transaction {} reverted", hash).into());
+            }
+            return Ok(hash);
+        }
+        Err(format!("transaction {} was not mined in time", hash).into())
+    }
+}
+
+/// Quote the swap with QuoterV2, approve the router if needed and swap. Returns the hash of
+/// the swap transaction.
+async fn swap(
+    is_buy: bool,
+    token: &str,
+    quantity: f64,
+    price: f64,
+    slippage: f64,
+    bot: &Bot,
+    client: &Client,
+) -> RpcResult<String> {
+    let key = signing_key(
+        bot.private_key
+            .as_deref()
+            .ok_or("Uniswap bots need a private_key")?,
+    )?;
+    let quote_token = parse_address(
+        bot.contract_address
+            .as_deref()
+            .ok_or("Uniswap bots need the quote token in contract_address")?,
+    )?;
+    let token = parse_address(token)?;
+    let router = parse_address(SWAP_ROUTER)?;
+    let quoter = parse_address(QUOTER)?;
+    let rpc = Rpc {
+        endpoint: bot.rpc_endpoint.as_deref().unwrap_or(DEFAULT_RPC_ENDPOINT),
+        client,
+    };
+    let owner = address(&key);
+    let slippage = if slippage > 0.0 {
+        slippage
+    } else {
+        DEFAULT_SLIPPAGE
+    };
+    let amount = to_units(quantity, rpc.decimals(&token).await?)?;
+    let quote_decimals = rpc.decimals(&quote_token).await?;
+
+    let (token_in, token_out) = if is_buy {
+        (quote_token, token)
+    } else {
+        (token, quote_token)
+    };
+    let quote_signature = if is_buy {
+        "quoteExactOutputSingle((address,address,uint256,uint24,uint160))"
+    } else {
+        "quoteExactInputSingle((address,address,uint256,uint24,uint160))"
+    };
+    let quoted = decode_uint(
+        &rpc.eth_call(
+            &quoter,
+            &call_data(
+                quote_signature,
+                &[
+                    address_word(&token_in),
+                    address_word(&token_out),
+                    uint_word(amount),
+                    uint_word(FEE_TIER),
+                    uint_word(0), // no price limit
+                ],
+            ),
+        )
+        .await?,
+        0,
+    )?;
+    let limit = if price > 0.0 {
+        to_units(price * quantity, quote_decimals)?
+    } else {
+        with_slippage(quoted, slippage, is_buy)
+    };
+    if (is_buy && quoted > limit) || (!is_buy && quoted < limit) {
+        return Err(format!(
+            "Uniswap quotes {} units of the quote token, beyond the limit of {}",
+            quoted, limit
+        )
+        .into());
+    }
+
+    let spent = if is_buy { limit } else { amount };
+    if rpc.allowance(&token_in, &owner, &router).await? < spent {
+        let approve = call_data(
+            "approve(address,uint256)",
+            &[address_word(&router), uint_word(u128::MAX)],
+        );
+        let hash = rpc.transact(&key, &token_in, approve).await?;
+        log::info!(
+            "Approved the Uniswap router to spend {}: {}",
+            to_hex(&token_in),
+            hash
+        );
+    }
+
+    let data = if is_buy {
+        call_data(
+            "exactOutputSingle((address,address,uint24,address,uint256,uint256,uint160))",
+            &[
+                address_word(&token_in),
+                address_word(&token_out),
+                uint_word(FEE_TIER),
+                address_word(&owner),
+                uint_word(amount),
+                uint_word(limit),
+                uint_word(0),
+            ],
+        )
+    } else {
+        call_data(
+            "exactInputSingle((address,address,uint24,address,uint256,uint256,uint160))",
+            &[
+                address_word(&token_in),
+                address_word(&token_out),
+                uint_word(FEE_TIER),
+                address_word(&owner),
+                uint_word(amount),
+                uint_word(limit),
+                uint_word(0),
+            ],
+        )
+    };
+    rpc.transact(&key, &router, data).await
+}
+
+#[async_trait]
+impl Exchange for UniswapExchange {
+    async fn execute_trade(
+        &self,
+        order: &OrderRequest,
+        symbol_or_contract: &str,
+        quantity: Decimal,
+        slippage: f64,
+        bot: &Bot,
+        client: &Client,
+    ) -> Result<ExecutionReport, Box<dyn Error>> {
+        let price = order.price("Uniswap")?;
+        let is_buy = order.side().is_buy();
+        let submitted_at = chrono::Utc::now();
+        log::info!(
+            "Executing Uniswap swap: {} {} {} @ {}",
+            order.side(),
+            quantity,
+            symbol_or_contract,
+            if price > Decimal::ZERO {
+                price.to_string()
+            } else {
+                "market".to_string()
+            }
+        );
+        let hash = swap(
+            is_buy,
+            symbol_or_contract,
+            quantity.to_f64().ok_or("quantity is out of range")?,
+            price.to_f64().ok_or("price is out of range")?,
+            slippage,
+            bot,
+            client,
+        )
+        .await
+        .map_err(|e| e as Box<dyn Error>)?;
+        log::info!("Uniswap swap mined: {}", hash);
+        // The traded token's side of the swap is exact; the quote token's is bounded
+        Ok(ExecutionReport {
+            filled_quantity: Some(quantity),
+            tx_hashes: vec![hash],
+            ..ExecutionReport::acknowledged(submitted_at)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::{Side, TimeInForce};
+    use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+    use mockito::Matcher;
+    use rust_decimal::dec;
+
+    const KEY: &str = "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+    const WETH: &str = "0x4200000000000000000000000000000000000006";
+    const USDC: &str = "0x833589fcd6edb6e08f4c7c32d4f71b54bda02913";
+
+    #[test]
+    fn amounts_and_transactions_are_encoded() {
+        assert_eq!(to_units(1.5, 18).unwrap(), 1_500_000_000_000_000_000);
+        assert_eq!(to_units(2.5, 6).unwrap(), 2_500_000);
+        assert!(to_units(0.0000001, 6).is_err());
+        assert_eq!(with_slippage(3_000_000_000, 0.01, false), 2_970_000_000);
+        assert_eq!(with_slippage(3_000_000_000, 0.01, true), 3_030_000_000);
+
+        let key = signing_key(KEY).unwrap();
+        let transaction = Transaction {
+            chain_id: 8453,
+            nonce: 7,
+            max_priority_fee_per_gas: 1_000_000,
+            max_fee_per_gas: 5_000_000,
+            gas: 120_000,
+            to: parse_address(SWAP_ROUTER).unwrap(),
+            data: call_data("decimals()", &[]),
+        };
+        let raw = transaction.sign(&key).unwrap();
+        assert_eq!(raw[0], 0x02);
+
+        // The signature trails the payload: y parity, then 0xa0-prefixed r and s
+        let s = &raw[raw.len() - 32..];
+        let r = &raw[raw.len() - 65..raw.len() - 33];
+        let parity = raw[raw.len() - 67];
+        let signature = Signature::from_scalars(
+            <[u8; 32]>::try_from(r).unwrap(),
+            <[u8; 32]>::try_from(s).unwrap(),
+        )
+        .unwrap();
+        let recovered = VerifyingKey::recover_from_prehash(
+            &transaction.signing_hash(),
+            &signature,
+            RecoveryId::from_byte(if parity == 0x80 { 0 } else { parity }).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(&recovered, key.verifying_key());
+    }
+
+    async fn rpc_mock(
+        server: &mut mockito::ServerGuard,
+        method: &str,
+        body: &str,
+        result: serde_json::Value,
+    ) -> mockito::Mock {
+        server
+            .mock("POST", "/")
+            .match_body(Matcher::AllOf(vec![
+                Matcher::PartialJson(json!({"method": method})),
+                Matcher::Regex(body.to_string()),
+            ]))
+            .with_body(json!({"jsonrpc": "2.0", "id": 1, "result": result}).to_string())
+            .create_async()
+            .await
+    }
+
+    fn hex_word(value: u128) -> String {
+        to_hex(&uint_word(value))
+    }
+
+    #[tokio::test]
+    async fn sells_are_quoted_approved_and_swapped() {
+        let mut server = mockito::Server::new_async().await;
+        let bot = Bot {
+            exchange: "uniswap".to_string(),
+            private_key: Some(KEY.to_string()),
+            contract_address: Some(USDC.to_string()),
+            rpc_endpoint: Some(server.url()),
+            ..Default::default()
+        };
+        let client = Client::new();
+        let selector = |signature: &str| to_hex(&call_data(signature, &[]));
+
+        let decimals = selector("decimals()");
+        for (token, decimals_of) in [(WETH, 18), (USDC, 6)] {
+            rpc_mock(
+                &mut server,
+                "eth_call",
+                &format!(r#""data":"{}".*"to":"{}""#, decimals, token),
+                json!(hex_word(decimals_of)),
+            )
+            .await;
+        }
+        rpc_mock(
+            &mut server,
+            "eth_call",
+            &selector("quoteExactInputSingle((address,address,uint256,uint24,uint160))"),
+            json!(hex_word(3_000_000_000)),
+        )
+        .await;
+        rpc_mock(
+            &mut server,
+            "eth_call",
+            &selector("allowance(address,address)"),
+            json!(hex_word(0)),
+        )
+        .await;
+        for (method, result) in [
+            ("eth_chainId", json!("0x2105")),
+            ("eth_getTransactionCount", json!("0x7")),
+            ("eth_estimateGas", json!("0x1d4c0")),
+            ("eth_maxPriorityFeePerGas", json!("0xf4240")),
+            (
+                "eth_getBlockByNumber",
+                json!({"baseFeePerGas": "0x3b9aca00"}),
+            ),
+            ("eth_getTransactionReceipt", json!({"status": "0x1"})),
+        ] {
+            rpc_mock(&mut server, method, "", result).await;
+        }
+        let approve = rpc_mock(
+            &mut server,
+            "eth_sendRawTransaction",
+            &selector("approve(address,uint256)")[2..],
+            json!("0xaa"),
+        )
+        .await;
+        // The minimum output is the quote less 1%
+        let swap = rpc_mock(
+            &mut server,
+            "eth_sendRawTransaction",
+            &format!(
+                "{}.*{}",
+                &selector(
+                    "exactInputSingle((address,address,uint24,address,uint256,uint256,uint160))"
+                )[2..],
+                &hex_word(2_970_000_000)[2..]
+            ),
+            json!("0xbb"),
+        )
+        .await;
+
+        UniswapExchange::new()
+            .execute_trade(
+                &OrderRequest::Market { side: Side::Sell },
+                WETH,
+                dec!(1.5),
+                0.0,
+                &bot,
+                &client,
+            )
+            .await
+            .unwrap();
+        approve.assert_async().await;
+        swap.assert_async().await;
+
+        // A limit price the pool cannot meet is refused before anything is sent
+        let err = UniswapExchange::new()
+            .execute_trade(
+                &OrderRequest::Limit {
+                    side: Side::Sell,
+                    price: dec!(2100),
+                    time_in_force: TimeInForce::Gtc,
+                },
+                WETH,
+                dec!(1.5),
+                0.0,
+                &bot,
+                &client,
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("beyond the limit"));
+        approve.assert_async().await;
+    }
+}
@@ -1,4 +1,5 @@
 // src/lib.rs
+pub mod alert;
 // pub mod bot;
 // pub mod errors;
 // pub mod state;
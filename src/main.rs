@@ -1,8 +1,10 @@
 // src/main.rs
+mod alert;
 mod app_config;
 mod app_state;
 mod bot;
 mod errors;
+mod exchange;
 mod utils;
 
 use crate::app_config::AppConfig;
@@ -14,12 +16,16 @@ use dotenv::dotenv;
 use std::path::Path;
 use std::sync::{Arc, Mutex};
 
+#[cfg(feature = "alloc-stats")]
+#[global_allocator]
+static ALLOCATOR: utils::alloc::CountingAllocator = utils::alloc::CountingAllocator;
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     //fn main() -> std::io::Result<()> {
     dotenv().ok();
     // Initialize the logger, mapping fern::InitError into std::io::Error
-    setup_logger().map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+    setup_logger().map_err(std::io::Error::other)?;
 
     // Load AppConfig
     let app_config = AppConfig::load::<&Path>(None).map_err(map_to_io_error)?;
@@ -34,5 +40,16 @@ async fn main() -> std::io::Result<()> {
     // println!("hello2");
 
     let cli = bot::cli::Cli::parse();
-    cli.run(app_config, app_state.clone()).await
+    utils::progress::init(cli.output);
+    exchange::plugin::init(&app_config.plugins);
+    if let Err(e) = cli.run(app_config, app_state.clone()).await {
+        // Failed commands exit with the status of their error code; other errors with 1
+        let code = errors::code_of(&e);
+        match code {
+            Some(code) => eprintln!("Error ({}): {}", code, e),
+            None => eprintln!("Error: {}", e),
+        }
+        std::process::exit(code.map_or(1, |code| code.exit_code()));
+    }
+    Ok(())
 }
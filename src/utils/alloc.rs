@@ -0,0 +1,93 @@
+// src/utils/alloc.rs
+//! Heap usage of the process.
+//!
+//! Builds with the `alloc-stats` feature route every allocation through
+//! [`CountingAllocator`], a thin wrapper around the system allocator that keeps a few atomic
+//! counters. Without the feature nothing is counted and [`stats`] returns `None`.
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct AllocStats {
+    /// Bytes currently allocated
+    pub allocated_bytes: u64,
+    /// Most bytes allocated at once since startup
+    pub peak_bytes: u64,
+    /// Allocations made since startup
+    pub allocations: u64,
+}
+
+#[cfg(feature = "alloc-stats")]
+mod counting {
+    use super::AllocStats;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static ALLOCATED: AtomicU64 = AtomicU64::new(0);
+    static PEAK: AtomicU64 = AtomicU64::new(0);
+    static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+    /// The system allocator, counted.
+    pub struct CountingAllocator;
+
+    fn grow(bytes: usize) {
+        let now = ALLOCATED.fetch_add(bytes as u64, Ordering::Relaxed) + bytes as u64;
+        PEAK.fetch_max(now, Ordering::Relaxed);
+    }
+
+    fn shrink(bytes: usize) {
+        ALLOCATED.fetch_sub(bytes as u64, Ordering::Relaxed);
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let ptr = System.alloc(layout);
+            if !ptr.is_null() {
+                grow(layout.size());
+                ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            }
+            ptr
+        }
+
+        unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+            let ptr = System.alloc_zeroed(layout);
+            if !ptr.is_null() {
+                grow(layout.size());
+                ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            }
+            ptr
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout);
+            shrink(layout.size());
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            let new_ptr = System.realloc(ptr, layout, new_size);
+            if !new_ptr.is_null() {
+                shrink(layout.size());
+                grow(new_size);
+            }
+            new_ptr
+        }
+    }
+
+    pub fn stats() -> AllocStats {
+        AllocStats {
+            allocated_bytes: ALLOCATED.load(Ordering::Relaxed),
+            peak_bytes: PEAK.load(Ordering::Relaxed),
+            allocations: ALLOCATIONS.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(feature = "alloc-stats")]
+pub use counting::CountingAllocator;
+
+/// Current heap usage, if this build counts it.
+pub fn stats() -> Option<AllocStats> {
+    #[cfg(feature = "alloc-stats")]
+    return Some(counting::stats());
+    #[cfg(not(feature = "alloc-stats"))]
+    None
+}
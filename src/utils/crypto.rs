@@ -0,0 +1,122 @@
+// src/utils/crypto.rs
+use rand::RngCore;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::{digest, hmac};
+
+/// Compares two byte strings in time independent of where they differ,
+/// for checking tokens and secrets without leaking a timing side channel.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// HMAC-SHA256 of `message` under `key`.
+pub fn hmac_sha256(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, key);
+    hmac::sign(&key, message).as_ref().to_vec()
+}
+
+/// Lower-case hex HMAC-SHA256 of `message` under `key`.
+pub fn hmac_sha256_hex(key: &[u8], message: &[u8]) -> String {
+    encode_hex(&hmac_sha256(key, message))
+}
+
+/// Lower-case hex SHA-256 of `message`.
+pub fn sha256_hex(message: &[u8]) -> String {
+    encode_hex(digest::digest(&digest::SHA256, message).as_ref())
+}
+
+/// A new random secret: 32 bytes from the OS's generator, as lower-case hex.
+pub fn random_secret() -> String {
+    let mut bytes = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    encode_hex(&bytes)
+}
+
+/// Lower-case hex of `bytes`.
+pub fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Bytes of lower-case or upper-case hex `text`, if it is hex.
+pub fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) || !text.is_ascii() {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// An AES-256-GCM key for encrypting short values, each under a random nonce.
+pub struct AeadKey(LessSafeKey);
+
+impl AeadKey {
+    pub fn new(key: &[u8; 32]) -> Self {
+        let key = UnboundKey::new(&AES_256_GCM, key).expect("AES-256 keys are 32 bytes");
+        Self(LessSafeKey::new(key))
+    }
+
+    /// `plaintext` encrypted and authenticated, after the nonce it was encrypted under.
+    pub fn seal(&self, plaintext: &[u8]) -> Vec<u8> {
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::rngs::OsRng.fill_bytes(&mut nonce);
+        let mut sealed = plaintext.to_vec();
+        self.0
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::empty(),
+                &mut sealed,
+            )
+            .expect("values fit in a single AES-GCM message");
+        [nonce.as_slice(), &sealed].concat()
+    }
+
+    /// The plaintext of what [`seal`](Self::seal) returned; `None` if it was not sealed with
+    /// this key or was tampered with.
+    pub fn open(&self, sealed: &[u8]) -> Option<Vec<u8>> {
+        if sealed.len() < NONCE_LEN {
+            return None;
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce).ok()?;
+        let mut buffer = ciphertext.to_vec();
+        let plaintext = self
+            .0
+            .open_in_place(nonce, Aad::empty(), &mut buffer)
+            .ok()?;
+        Some(plaintext.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret!"));
+        assert!(constant_time_eq(b"", b""));
+    }
+
+    #[test]
+    fn test_hmac_sha256_hex() {
+        // RFC 4231, test case 2
+        assert_eq!(
+            hmac_sha256_hex(b"Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_hex_round_trip() {
+        assert_eq!(encode_hex(&[0x00, 0x0f, 0xab]), "000fab");
+        assert_eq!(decode_hex("000FaB").unwrap(), [0x00, 0x0f, 0xab]);
+        assert!(decode_hex("abc").is_none());
+    }
+}
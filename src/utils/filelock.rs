@@ -0,0 +1,132 @@
+// src/utils/filelock.rs
+//! # Advisory File Locks
+//!
+//! Processes sharing a file take turns on it through an advisory lock on a file next to it,
+//! `state.json.lock` for `state.json`. The lock file is locked rather than the file itself
+//! because saves rename a new file over it. Locks are released when dropped, or when their
+//! process exits; the lock file stays, holding the PID of the last process to lock it for
+//! writing so others can tell who they are [waiting](FileLock::wait_exclusive) for.
+//!
+//! The locks are advisory: they keep xtrade processes from reading a file another one is
+//! replacing, not editors or other programs.
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How often [`FileLock::wait_exclusive`] tries again.
+const RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A lock on `path`, held until dropped.
+#[derive(Debug)]
+pub struct FileLock {
+    file: File,
+}
+
+impl FileLock {
+    /// The lock for writing `path`; `None` if another process holds any lock on it.
+    pub fn exclusive(path: &Path) -> io::Result<Option<Self>> {
+        let Some(mut lock) = Self::acquire(path, File::try_lock)? else {
+            return Ok(None);
+        };
+        lock.file.set_len(0)?;
+        write!(lock.file, "{}", std::process::id())?;
+        Ok(Some(lock))
+    }
+
+    /// The lock for writing `path`, waiting up to `timeout` for other processes to release
+    /// theirs; `None` if they did not.
+    pub fn wait_exclusive(path: &Path, timeout: Duration) -> io::Result<Option<Self>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(lock) = Self::exclusive(path)? {
+                return Ok(Some(lock));
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(None);
+            }
+            std::thread::sleep(RETRY_INTERVAL.min(deadline - now));
+        }
+    }
+
+    /// A lock for reading `path`, shared with other readers; `None` if another process is
+    /// writing it.
+    pub fn shared(path: &Path) -> io::Result<Option<Self>> {
+        Self::acquire(path, File::try_lock_shared)
+    }
+
+    fn acquire(
+        path: &Path,
+        try_lock: fn(&File) -> Result<(), TryLockError>,
+    ) -> io::Result<Option<Self>> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(lock_path(path))?;
+        match try_lock(&file) {
+            Ok(()) => Ok(Some(Self { file })),
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Error(e)) => Err(e),
+        }
+    }
+}
+
+/// The process that last locked `path` for writing, as its lock file records it.
+pub fn holder(path: &Path) -> Option<u32> {
+    fs::read_to_string(lock_path(path))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// The lock file of `path`.
+pub fn lock_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".lock");
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn writers_exclude_everyone_and_readers_only_writers() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("state.json");
+
+        let reader = FileLock::shared(&path).unwrap().unwrap();
+        assert!(lock_path(&path).exists());
+        assert!(FileLock::shared(&path).unwrap().is_some());
+        assert!(FileLock::exclusive(&path).unwrap().is_none());
+        drop(reader);
+
+        let writer = FileLock::exclusive(&path).unwrap().unwrap();
+        assert!(FileLock::shared(&path).unwrap().is_none());
+        drop(writer);
+        assert!(FileLock::exclusive(&path).unwrap().is_some());
+    }
+
+    #[test]
+    fn writers_wait_for_locks_to_be_released_and_know_who_holds_them() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("state.json");
+
+        let writer = FileLock::exclusive(&path).unwrap().unwrap();
+        assert_eq!(holder(&path), Some(std::process::id()));
+        let timeout = Duration::from_millis(100);
+        assert!(FileLock::wait_exclusive(&path, timeout).unwrap().is_none());
+
+        let release = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            drop(writer);
+        });
+        let waited = FileLock::wait_exclusive(&path, Duration::from_secs(5)).unwrap();
+        assert!(waited.is_some());
+        release.join().unwrap();
+    }
+}
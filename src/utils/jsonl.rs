@@ -0,0 +1,117 @@
+// src/utils/jsonl.rs
+//! # JSON Lines Logs
+//!
+//! Logs such as the audit trail and the access log are files of one JSON object per line,
+//! appended to as things happen and pruned by [compaction](crate::bot::compaction). Appends
+//! and prunes of this process take turns, so a line appended while a log is rewritten is
+//! never lost; other processes appending to the same file are not held off.
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, ErrorKind, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+static WRITING: Mutex<()> = Mutex::new(());
+
+/// Append `lines` to the file at `path`, creating it if needed.
+pub fn append<I, S>(path: &Path, lines: I) -> io::Result<()>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let _writing = WRITING.lock().unwrap_or_else(|e| e.into_inner());
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let mut writer = BufWriter::new(file);
+    for line in lines {
+        writer.write_all(line.as_ref().as_bytes())?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()
+}
+
+/// Remove the lines of the file at `path` whose `timestamp` field is before `before`, once
+/// `keep` took them, e.g. to an archive. Lines without a timestamp are kept. Returns how
+/// many lines were removed.
+pub fn prune(
+    path: &Path,
+    before: DateTime<Utc>,
+    keep: impl FnOnce(&[Value]) -> io::Result<()>,
+) -> io::Result<usize> {
+    let _writing = WRITING.lock().unwrap_or_else(|e| e.into_inner());
+    let file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+    let (mut kept, mut pruned) = (Vec::new(), Vec::new());
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        let entry = serde_json::from_str::<Value>(&line).ok();
+        let timestamp = (entry.as_ref()).and_then(|entry| {
+            entry
+                .get("timestamp")?
+                .as_str()?
+                .parse::<DateTime<Utc>>()
+                .ok()
+        });
+        match (entry, timestamp) {
+            (Some(entry), Some(timestamp)) if timestamp < before => pruned.push(entry),
+            _ => kept.push(line),
+        }
+    }
+    if pruned.is_empty() {
+        return Ok(0);
+    }
+    keep(&pruned)?;
+
+    let parent = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+    let mut temp = tempfile::NamedTempFile::new_in(parent.unwrap_or(Path::new(".")))?;
+    for line in &kept {
+        writeln!(temp, "{}", line)?;
+    }
+    temp.as_file().sync_all()?;
+    temp.persist(path).map_err(|e| e.error)?;
+    Ok(pruned.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn lines_older_than_the_cutoff_are_pruned() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        append(
+            &path,
+            [
+                r#"{"timestamp":"2024-01-01T00:00:00Z","path":"/old"}"#,
+                "not json",
+                r#"{"timestamp":"2026-01-01T00:00:00Z","path":"/new"}"#,
+            ],
+        )
+        .unwrap();
+
+        let cutoff = "2025-01-01T00:00:00Z".parse().unwrap();
+        let mut kept = Vec::new();
+        let pruned = prune(&path, cutoff, |entries| {
+            kept.extend_from_slice(entries);
+            Ok(())
+        });
+        assert_eq!(pruned.unwrap(), 1);
+        assert_eq!(kept[0]["path"], "/old");
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content.lines().count(), 2);
+
+        // Lines stay if they could not be kept
+        let cutoff = "2027-01-01T00:00:00Z".parse().unwrap();
+        assert!(prune(&path, cutoff, |_| Err(io::Error::other("disk full"))).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+        assert_eq!(
+            prune(&dir.path().join("missing.jsonl"), cutoff, |_| Ok(())).unwrap(),
+            0
+        );
+    }
+}
@@ -80,6 +80,7 @@ fn determine_log_level() -> LevelFilter {
 ///
 /// # Examples
 /// ```rust
+/// # use xtrade::utils::logging::setup_logger;
 /// if let Err(e) = setup_logger() {
 ///     eprintln!("Failed to initialize logger: {}", e);
 ///     std::process::exit(1);
@@ -1,3 +1,8 @@
-// src/utils/mod.rs
+pub mod alloc;
+pub mod crypto;
+pub mod filelock;
+pub mod jsonl;
 pub mod logging;
+pub mod png;
+pub mod progress;
 pub mod validators;
@@ -0,0 +1,115 @@
+// src/utils/png.rs
+//! Just enough of PNG to draw simple charts: an RGB canvas, lines and rectangles on it, and
+//! its encoding as an 8-bit truecolor image.
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::io::{self, Write};
+
+pub type Rgb = [u8; 3];
+
+pub struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<Rgb>,
+}
+
+impl Canvas {
+    pub fn new(width: usize, height: usize, background: Rgb) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![background; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Colour the pixel at `(x, y)`, counted from the top left; outside the canvas, nothing.
+    pub fn set(&mut self, x: i64, y: i64, color: Rgb) {
+        if (0..self.width as i64).contains(&x) && (0..self.height as i64).contains(&y) {
+            self.pixels[y as usize * self.width + x as usize] = color;
+        }
+    }
+
+    /// A one pixel wide line from `from` to `to`.
+    pub fn line(&mut self, from: (i64, i64), to: (i64, i64), color: Rgb) {
+        let (mut x, mut y) = from;
+        let (dx, dy) = ((to.0 - x).abs(), -(to.1 - y).abs());
+        let (sx, sy) = ((to.0 - x).signum(), (to.1 - y).signum());
+        let mut error = dx + dy;
+        loop {
+            self.set(x, y, color);
+            if (x, y) == to {
+                return;
+            }
+            let doubled = 2 * error;
+            if doubled >= dy {
+                error += dy;
+                x += sx;
+            }
+            if doubled <= dx {
+                error += dx;
+                y += sy;
+            }
+        }
+    }
+
+    /// The PNG image of the canvas.
+    pub fn encode(&self) -> io::Result<Vec<u8>> {
+        let mut raw = Vec::with_capacity((self.width * 3 + 1) * self.height);
+        for row in self.pixels.chunks(self.width) {
+            // Filter type None
+            raw.push(0);
+            raw.extend(row.iter().flatten());
+        }
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&raw)?;
+
+        let mut header = Vec::with_capacity(13);
+        header.extend((self.width as u32).to_be_bytes());
+        header.extend((self.height as u32).to_be_bytes());
+        // 8 bits per channel, truecolor, deflate, adaptive filtering, not interlaced
+        header.extend([8, 2, 0, 0, 0]);
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        chunk(&mut png, b"IHDR", &header);
+        chunk(&mut png, b"IDAT", &encoder.finish()?);
+        chunk(&mut png, b"IEND", &[]);
+        Ok(png)
+    }
+}
+
+fn chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    png.extend(kind);
+    png.extend(data);
+    let mut crc = crc32fast::Hasher::new();
+    crc.update(kind);
+    crc.update(data);
+    png.extend(crc.finalize().to_be_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canvas_encodes_as_png() {
+        let mut canvas = Canvas::new(4, 3, [255, 255, 255]);
+        canvas.line((0, 0), (3, 2), [0, 0, 0]);
+        canvas.set(9, 9, [0, 0, 0]);
+        assert_eq!(canvas.pixels[0], [0, 0, 0]);
+        assert_eq!(canvas.pixels[2 * 4 + 3], [0, 0, 0]);
+        assert_eq!(canvas.pixels[4 * 3 - 4], [255, 255, 255]);
+
+        let png = canvas.encode().unwrap();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR\0\0\0\x04\0\0\0\x03"));
+        assert!(png.ends_with(b"IEND\xae\x42\x60\x82"));
+    }
+}
@@ -0,0 +1,318 @@
+// src/utils/progress.rs
+//! # Progress
+//!
+//! Long operations, such as manifests, compactions and requests the server takes a while to
+//! answer, report how far they got through a [`Progress`]. How it is shown follows
+//! `--output`: with `text`, as a bar redrawn on stderr if stderr is a terminal; with `json`,
+//! as one event per line on stderr, for scripts:
+//!
+//! ```text
+//! {"event":"started","operation":"apply","done":0,"total":3,"elapsed_ms":0}
+//! {"event":"progress","operation":"apply","done":1,"total":3,"elapsed_ms":12}
+//! {"event":"finished","operation":"apply","done":3,"total":3,"elapsed_ms":40}
+//! ```
+//!
+//! Operations of unknown length leave `total` null and show a spinner instead of a bar; those
+//! that fail end with a `failed` event. Bars and progress events are redrawn at most every
+//! [`REDRAW_INTERVAL`].
+use serde::Serialize;
+use std::fmt;
+use std::future::Future;
+use std::io::{self, IsTerminal, Write};
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+/// Least time between two redraws of a bar, or two progress events.
+pub const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+const BAR_WIDTH: usize = 30;
+const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+
+static OUTPUT: OnceLock<OutputFormat> = OnceLock::new();
+
+/// How the CLI reports on long operations, as `--output` asks.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(format!("Unknown output '{}'; expected text or json", s)),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputFormat::Text => write!(f, "text"),
+            OutputFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// Report progress as `output` asks from now on; only the first call counts.
+pub fn init(output: OutputFormat) {
+    let _ = OUTPUT.set(output);
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum EventKind {
+    Started,
+    Progress,
+    Finished,
+    Failed,
+}
+
+#[derive(Serialize)]
+struct Event<'a> {
+    event: EventKind,
+    operation: &'a str,
+    done: u64,
+    total: Option<u64>,
+    elapsed_ms: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Style {
+    Hidden,
+    Bar,
+    Json,
+}
+
+/// Reports on one operation until it is [finished](Progress::finish); dropped unfinished,
+/// the operation failed.
+pub struct Progress {
+    operation: String,
+    total: Option<u64>,
+    done: u64,
+    started: Instant,
+    drawn: Option<Instant>,
+    finished: bool,
+    style: Style,
+    out: Box<dyn Write + Send>,
+}
+
+impl Progress {
+    /// Start reporting on `operation`, of `total` steps if known.
+    pub fn start(operation: &str, total: Option<u64>) -> Self {
+        let style = match OUTPUT.get().copied().unwrap_or_default() {
+            OutputFormat::Json => Style::Json,
+            OutputFormat::Text if io::stderr().is_terminal() => Style::Bar,
+            OutputFormat::Text => Style::Hidden,
+        };
+        Self::to(operation, total, style, Box::new(io::stderr()))
+    }
+
+    /// Reports nothing; for operations the server runs on behalf of a client.
+    pub fn hidden() -> Self {
+        Self::to("", None, Style::Hidden, Box::new(io::sink()))
+    }
+
+    fn to(operation: &str, total: Option<u64>, style: Style, out: Box<dyn Write + Send>) -> Self {
+        let mut progress = Self {
+            operation: operation.to_string(),
+            total,
+            done: 0,
+            started: Instant::now(),
+            drawn: None,
+            finished: false,
+            style,
+            out,
+        };
+        progress.emit(EventKind::Started);
+        progress
+    }
+
+    /// Once known, the number of steps of the operation.
+    pub fn set_total(&mut self, total: u64) {
+        self.total = Some(total);
+        self.tick();
+    }
+
+    /// Count `steps` more steps as done.
+    pub fn advance(&mut self, steps: u64) {
+        self.done += steps;
+        if self.total == Some(self.done) {
+            self.emit(EventKind::Progress);
+        } else {
+            self.tick();
+        }
+    }
+
+    /// Redraw, if it is time to; spinners turn even if no step is done.
+    pub fn tick(&mut self) {
+        if self.drawn.is_some_and(|at| at.elapsed() < REDRAW_INTERVAL) {
+            return;
+        }
+        self.emit(EventKind::Progress);
+    }
+
+    /// The operation succeeded; clears the bar.
+    pub fn finish(mut self) {
+        self.emit(EventKind::Finished);
+        self.finished = true;
+    }
+
+    fn emit(&mut self, kind: EventKind) {
+        self.drawn = Some(Instant::now());
+        let elapsed = self.started.elapsed();
+        // Reporting is never worth failing the operation over
+        let _ = match (self.style, kind) {
+            (Style::Hidden, _) => return,
+            (Style::Json, _) => {
+                let event = Event {
+                    event: kind,
+                    operation: &self.operation,
+                    done: self.done,
+                    total: self.total,
+                    elapsed_ms: elapsed.as_millis() as u64,
+                };
+                serde_json::to_writer(&mut self.out, &event)
+                    .map_err(io::Error::from)
+                    .and_then(|_| self.out.write_all(b"\n"))
+            }
+            (Style::Bar, EventKind::Finished | EventKind::Failed) => write!(self.out, "\r\x1b[2K"),
+            (Style::Bar, _) => {
+                let bar = self.bar(elapsed);
+                write!(self.out, "\r\x1b[2K{}", bar)
+            }
+        };
+        let _ = self.out.flush();
+    }
+
+    fn bar(&self, elapsed: Duration) -> String {
+        match self.total {
+            Some(total) if total > 0 => {
+                let done = self.done.min(total);
+                let filled = (done * BAR_WIDTH as u64 / total) as usize;
+                format!(
+                    "{} [{}{}] {}/{} ({}%)",
+                    self.operation,
+                    "#".repeat(filled),
+                    "-".repeat(BAR_WIDTH - filled),
+                    done,
+                    total,
+                    done * 100 / total
+                )
+            }
+            _ => {
+                let turn = (elapsed.as_millis() / REDRAW_INTERVAL.as_millis()) as usize;
+                format!(
+                    "{} {} {}s",
+                    self.operation,
+                    SPINNER[turn % SPINNER.len()],
+                    elapsed.as_secs()
+                )
+            }
+        }
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.emit(EventKind::Failed);
+        }
+    }
+}
+
+/// Wait for `future`, an `operation` of unknown length, reporting on it meanwhile.
+pub async fn track<F: Future>(operation: &str, future: F) -> F::Output {
+    let mut progress = Progress::start(operation, None);
+    let mut future = std::pin::pin!(future);
+    let mut ticks = tokio::time::interval(REDRAW_INTERVAL);
+    let output = loop {
+        tokio::select! {
+            output = &mut future => break output,
+            _ = ticks.tick() => progress.tick(),
+        }
+    };
+    progress.finish();
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Shared {
+        fn text(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    #[test]
+    fn json_events_start_and_end_every_operation() {
+        let out = Shared::default();
+        let mut progress = Progress::to("apply", Some(3), Style::Json, Box::new(out.clone()));
+        for _ in 0..3 {
+            progress.advance(1);
+        }
+        progress.finish();
+        let events: Vec<serde_json::Value> = (out.text().lines())
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let first = &events[0];
+        assert_eq!(
+            (first["event"].as_str(), first["total"].as_u64()),
+            (Some("started"), Some(3))
+        );
+        // The last step is always reported, however soon it comes
+        let done = &events[events.len() - 2];
+        assert_eq!(
+            (done["event"].as_str(), done["done"].as_u64()),
+            (Some("progress"), Some(3))
+        );
+        assert_eq!(events.last().unwrap()["event"], "finished");
+
+        let out = Shared::default();
+        drop(Progress::to(
+            "compact",
+            None,
+            Style::Json,
+            Box::new(out.clone()),
+        ));
+        let last = out.text().lines().last().map(str::to_string).unwrap();
+        assert!(last.contains(r#""event":"failed""#) && last.contains(r#""total":null"#));
+    }
+
+    #[test]
+    fn bars_show_the_steps_done_and_are_cleared_at_the_end() {
+        let out = Shared::default();
+        let mut progress = Progress::to("apply", Some(4), Style::Bar, Box::new(out.clone()));
+        progress.advance(4);
+        assert!(out
+            .text()
+            .ends_with(&format!("apply [{}] 4/4 (100%)", "#".repeat(BAR_WIDTH))));
+        progress.finish();
+        assert!(out.text().ends_with("\r\x1b[2K"));
+
+        let spinner = Progress::to("compact", None, Style::Bar, Box::new(io::sink()));
+        assert_eq!(spinner.bar(Duration::from_millis(250)), "compact - 0s");
+        spinner.finish();
+    }
+}
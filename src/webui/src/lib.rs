@@ -1,5 +1,12 @@
 // src/webui/src/lib.rs
 
+mod maintenance;
+mod strategies;
+mod timeline;
+
+use maintenance::MaintenanceTable;
+use strategies::StrategyTable;
+use timeline::BotTimelines;
 use yew::prelude::*;
 
 #[function_component(App)]
@@ -7,6 +14,12 @@ pub fn app() -> Html {
     html! {
         <div>
             <h1>{ "Welcome to xTrade Web UI" }</h1>
+            <h2>{ "Strategies" }</h2>
+            <StrategyTable />
+            <h2>{ "Bots" }</h2>
+            <BotTimelines />
+            <h2>{ "Maintenance" }</h2>
+            <MaintenanceTable />
         </div>
     }
 }
@@ -0,0 +1,89 @@
+// src/webui/src/maintenance.rs
+
+use gloo_net::http::Request;
+use serde::Deserialize;
+use yew::prelude::*;
+
+/// A maintenance window of an exchange, as listed by `GET /admin/info`. Its bots ignore their
+/// alerts while it lasts.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct MaintenanceWindow {
+    pub exchange: String,
+    pub starts_at: String,
+    pub ends_at: String,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AdminInfo {
+    #[serde(default)]
+    maintenance: Vec<MaintenanceWindow>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiResponse {
+    data: Option<AdminInfo>,
+    error: Option<String>,
+}
+
+async fn fetch_maintenance() -> Result<Vec<MaintenanceWindow>, String> {
+    let response: ApiResponse = Request::get("/admin/info")
+        .send()
+        .await
+        .map_err(|e| e.to_string())?
+        .json()
+        .await
+        .map_err(|e| e.to_string())?;
+    match response.data {
+        Some(info) => Ok(info.maintenance),
+        None => Err(response.error.unwrap_or_else(|| "no info".to_string())),
+    }
+}
+
+/// Table of the maintenance windows in progress or to come, soonest first.
+#[function_component(MaintenanceTable)]
+pub fn maintenance_table() -> Html {
+    let windows = use_state(|| None::<Result<Vec<MaintenanceWindow>, String>>);
+    {
+        let windows = windows.clone();
+        use_effect_with((), move |_| {
+            wasm_bindgen_futures::spawn_local(async move {
+                windows.set(Some(fetch_maintenance().await));
+            });
+        });
+    }
+
+    let windows = match &*windows {
+        None => return html! { <p>{ "Loading maintenance windows…" }</p> },
+        Some(Err(e)) => {
+            return html! { <p>{ format!("Could not load maintenance windows: {}", e) }</p> }
+        }
+        Some(Ok(windows)) if windows.is_empty() => {
+            return html! { <p>{ "No maintenance planned" }</p> }
+        }
+        Some(Ok(windows)) => windows,
+    };
+    html! {
+        <table>
+            <thead>
+                <tr>
+                    <th>{ "Exchange" }</th>
+                    <th>{ "Starts" }</th>
+                    <th>{ "Ends" }</th>
+                    <th>{ "Reason" }</th>
+                </tr>
+            </thead>
+            <tbody>
+                { for windows.iter().map(|window| html! {
+                    <tr>
+                        <td>{ &window.exchange }</td>
+                        <td>{ &window.starts_at }</td>
+                        <td>{ &window.ends_at }</td>
+                        <td>{ window.reason.clone().unwrap_or_else(|| "-".to_string()) }</td>
+                    </tr>
+                }) }
+            </tbody>
+        </table>
+    }
+}
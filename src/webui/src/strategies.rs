@@ -0,0 +1,111 @@
+// src/webui/src/strategies.rs
+
+use gloo_net::http::Request;
+use serde::Deserialize;
+use yew::prelude::*;
+
+/// Performance of one listener's trades, as served by `GET /reports/strategies`. Decimals
+/// arrive as strings.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct StrategyPerformance {
+    pub bot_id: String,
+    pub listener_id: String,
+    pub trades: u64,
+    pub round_trips: u64,
+    pub win_rate: Option<String>,
+    pub profit_factor: Option<String>,
+    pub net_pnl: String,
+    pub max_drawdown: String,
+    pub sharpe: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StrategyReport {
+    strategies: Vec<StrategyPerformance>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiResponse {
+    data: Option<StrategyReport>,
+    error: Option<String>,
+}
+
+async fn fetch_strategies() -> Result<Vec<StrategyPerformance>, String> {
+    let response: ApiResponse = Request::get("/reports/strategies")
+        .send()
+        .await
+        .map_err(|e| e.to_string())?
+        .json()
+        .await
+        .map_err(|e| e.to_string())?;
+    match response.data {
+        Some(report) => Ok(report.strategies),
+        None => Err(response.error.unwrap_or_else(|| "no report".to_string())),
+    }
+}
+
+/// `rate`, a share from 0 to 1, as a percentage.
+fn percent(rate: &Option<String>) -> String {
+    rate.as_deref()
+        .and_then(|rate| rate.parse::<f64>().ok())
+        .map_or_else(|| "-".to_string(), |rate| format!("{:.1}%", rate * 100.0))
+}
+
+fn or_dash(value: &Option<String>) -> String {
+    value.clone().unwrap_or_else(|| "-".to_string())
+}
+
+/// Table comparing the strategies of every bot.
+#[function_component(StrategyTable)]
+pub fn strategy_table() -> Html {
+    let report = use_state(|| None::<Result<Vec<StrategyPerformance>, String>>);
+    {
+        let report = report.clone();
+        use_effect_with((), move |_| {
+            wasm_bindgen_futures::spawn_local(async move {
+                report.set(Some(fetch_strategies().await));
+            });
+        });
+    }
+
+    let strategies = match &*report {
+        None => return html! { <p>{ "Loading strategies…" }</p> },
+        Some(Err(e)) => return html! { <p>{ format!("Could not load strategies: {}", e) }</p> },
+        Some(Ok(strategies)) if strategies.is_empty() => {
+            return html! { <p>{ "No executed trades" }</p> }
+        }
+        Some(Ok(strategies)) => strategies,
+    };
+    html! {
+        <table>
+            <thead>
+                <tr>
+                    <th>{ "Bot" }</th>
+                    <th>{ "Listener" }</th>
+                    <th>{ "Trades" }</th>
+                    <th>{ "Round Trips" }</th>
+                    <th>{ "Win Rate" }</th>
+                    <th>{ "Profit Factor" }</th>
+                    <th>{ "Net PnL" }</th>
+                    <th>{ "Max Drawdown" }</th>
+                    <th>{ "Sharpe" }</th>
+                </tr>
+            </thead>
+            <tbody>
+                { for strategies.iter().map(|strategy| html! {
+                    <tr>
+                        <td>{ &strategy.bot_id }</td>
+                        <td>{ &strategy.listener_id }</td>
+                        <td>{ strategy.trades }</td>
+                        <td>{ strategy.round_trips }</td>
+                        <td>{ percent(&strategy.win_rate) }</td>
+                        <td>{ or_dash(&strategy.profit_factor) }</td>
+                        <td>{ &strategy.net_pnl }</td>
+                        <td>{ &strategy.max_drawdown }</td>
+                        <td>{ or_dash(&strategy.sharpe) }</td>
+                    </tr>
+                }) }
+            </tbody>
+        </table>
+    }
+}
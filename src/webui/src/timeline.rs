@@ -0,0 +1,209 @@
+// src/webui/src/timeline.rs
+
+use gloo_net::http::Request;
+use serde::Deserialize;
+use yew::prelude::*;
+
+/// A bot, as listed by `GET /bots`.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct BotSummary {
+    pub bot_id: String,
+    pub name: String,
+    pub exchange: String,
+    #[serde(default)]
+    pub dry_run: bool,
+    /// The bot only reads its account and places no orders.
+    #[serde(default)]
+    pub observer: bool,
+}
+
+impl BotSummary {
+    /// How the bot trades: not at all, on paper, or live.
+    fn mode(&self) -> &'static str {
+        if self.observer {
+            "Observer"
+        } else if self.dry_run {
+            "Paper"
+        } else {
+            "Live"
+        }
+    }
+}
+
+/// One entry of a bot's timeline, as served by `GET /bots/{bot_id}/timeline`.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct TimelineEvent {
+    pub seq: u64,
+    pub timestamp: String,
+    pub kind: String,
+    pub summary: String,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+struct Timeline {
+    events: Vec<TimelineEvent>,
+    next_cursor: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiResponse<T> {
+    data: Option<T>,
+    error: Option<String>,
+}
+
+async fn fetch<T: for<'de> Deserialize<'de>>(url: &str) -> Result<T, String> {
+    let response: ApiResponse<T> = Request::get(url)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?
+        .json()
+        .await
+        .map_err(|e| e.to_string())?;
+    match response.data {
+        Some(data) => Ok(data),
+        None => Err(response.error.unwrap_or_else(|| "no data".to_string())),
+    }
+}
+
+/// The page of the timeline of `bot_id` older than `before`, or the newest one.
+async fn fetch_timeline(bot_id: &str, before: Option<u64>) -> Result<Timeline, String> {
+    let mut url = format!("/bots/{}/timeline?limit=50", bot_id);
+    if let Some(before) = before {
+        url.push_str(&format!("&before={}", before));
+    }
+    fetch(&url).await
+}
+
+/// Bots to pick one from, and the timeline of the one picked.
+#[function_component(BotTimelines)]
+pub fn bot_timelines() -> Html {
+    let bots = use_state(|| None::<Result<Vec<BotSummary>, String>>);
+    let selected = use_state(|| None::<String>);
+    {
+        let bots = bots.clone();
+        use_effect_with((), move |_| {
+            wasm_bindgen_futures::spawn_local(async move {
+                bots.set(Some(fetch("/bots?limit=100").await));
+            });
+        });
+    }
+
+    let bots = match &*bots {
+        None => return html! { <p>{ "Loading bots…" }</p> },
+        Some(Err(e)) => return html! { <p>{ format!("Could not load bots: {}", e) }</p> },
+        Some(Ok(bots)) if bots.is_empty() => return html! { <p>{ "No bots" }</p> },
+        Some(Ok(bots)) => bots,
+    };
+    html! {
+        <div>
+            <table>
+                <thead>
+                    <tr>
+                        <th>{ "Bot" }</th>
+                        <th>{ "Name" }</th>
+                        <th>{ "Exchange" }</th>
+                        <th>{ "Mode" }</th>
+                        <th></th>
+                    </tr>
+                </thead>
+                <tbody>
+                    { for bots.iter().map(|bot| {
+                        let bot_id = bot.bot_id.clone();
+                        let selected = selected.clone();
+                        let onclick = Callback::from(move |_| selected.set(Some(bot_id.clone())));
+                        html! {
+                            <tr>
+                                <td>{ &bot.bot_id }</td>
+                                <td>{ &bot.name }</td>
+                                <td>{ &bot.exchange }</td>
+                                <td>{ bot.mode() }</td>
+                                <td><button {onclick}>{ "Timeline" }</button></td>
+                            </tr>
+                        }
+                    }) }
+                </tbody>
+            </table>
+            { for selected.as_ref().map(|bot_id| html! {
+                <BotTimeline bot_id={bot_id.clone()} />
+            }) }
+        </div>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct BotTimelineProps {
+    pub bot_id: String,
+}
+
+/// What happened to a bot, newest first, a page at a time.
+#[function_component(BotTimeline)]
+pub fn bot_timeline(props: &BotTimelineProps) -> Html {
+    let events = use_state(Vec::<TimelineEvent>::new);
+    let cursor = use_state(|| None::<u64>);
+    let error = use_state(|| None::<String>);
+    let load = {
+        let (events, cursor, error) = (events.clone(), cursor.clone(), error.clone());
+        let bot_id = props.bot_id.clone();
+        Callback::from(move |before: Option<u64>| {
+            let (events, cursor, error) = (events.clone(), cursor.clone(), error.clone());
+            let bot_id = bot_id.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                match fetch_timeline(&bot_id, before).await {
+                    Ok(page) => {
+                        let mut loaded = if before.is_some() {
+                            (*events).clone()
+                        } else {
+                            Vec::new()
+                        };
+                        loaded.extend(page.events);
+                        events.set(loaded);
+                        cursor.set(page.next_cursor);
+                        error.set(None);
+                    }
+                    Err(e) => error.set(Some(e)),
+                }
+            });
+        })
+    };
+    {
+        let load = load.clone();
+        use_effect_with(props.bot_id.clone(), move |_| load.emit(None));
+    }
+
+    let older = (*cursor).map(|before| {
+        let load = load.clone();
+        let onclick = Callback::from(move |_| load.emit(Some(before)));
+        html! { <button {onclick}>{ "Older events" }</button> }
+    });
+    html! {
+        <div>
+            <h3>{ format!("Timeline of {}", props.bot_id) }</h3>
+            { for (*error).as_ref().map(|e| html! {
+                <p>{ format!("Could not load the timeline: {}", e) }</p>
+            }) }
+            if events.is_empty() && error.is_none() {
+                <p>{ "Nothing happened to this bot yet" }</p>
+            } else {
+                <table>
+                    <thead>
+                        <tr>
+                            <th>{ "Time" }</th>
+                            <th>{ "Kind" }</th>
+                            <th>{ "Summary" }</th>
+                        </tr>
+                    </thead>
+                    <tbody>
+                        { for events.iter().map(|event| html! {
+                            <tr key={event.seq}>
+                                <td>{ &event.timestamp }</td>
+                                <td>{ event.kind.replace('_', " ") }</td>
+                                <td>{ &event.summary }</td>
+                            </tr>
+                        }) }
+                    </tbody>
+                </table>
+            }
+            { for older }
+        </div>
+    }
+}